@@ -71,8 +71,10 @@ pub fn build(b: *std.Build) void {
     b.installArtifact(lib);
 
     // Create C library for FFI
+    const c_linkage = b.option(std.builtin.LinkMode, "c-linkage", "Build the zqlite_c FFI library as static or dynamic") orelse .static;
     const c_lib = b.addLibrary(.{
         .name = "zqlite_c",
+        .linkage = c_linkage,
         .root_module = b.createModule(.{
             .root_source_file = b.path("src/ffi/c_api.zig"),
             .target = target,
@@ -87,6 +89,10 @@ pub fn build(b: *std.Build) void {
     // Install the C library
     b.installArtifact(c_lib);
 
+    // Build only the C library (used by the zqlite-rs build script)
+    const c_lib_step = b.step("c-lib", "Build and install only the zqlite_c FFI library");
+    c_lib_step.dependOn(&b.addInstallArtifact(c_lib, .{}).step);
+
     // Export the zqlite module for use by other packages
     const zqlite_module = b.addModule("zqlite", .{
         .root_source_file = b.path("src/zqlite.zig"),
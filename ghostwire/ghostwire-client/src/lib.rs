@@ -1,6 +1,7 @@
 //! Ghostwire VPN client library
 
 // Placeholder for client implementation
+#[derive(Default)]
 pub struct GhostwireClient;
 
 impl GhostwireClient {
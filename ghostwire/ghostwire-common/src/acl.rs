@@ -0,0 +1,612 @@
+//! Compiled ACL evaluation
+//!
+//! [`AclEngine::compile`] turns a rule list into two-level prefix tries, one
+//! per address family: a trie over source prefixes whose nodes each hold a
+//! trie over destination prefixes. Every source node's destination trie also
+//! carries the rules of its ancestors, and every destination node caches the
+//! best rule on its path, so a lookup is one walk down each trie. Rules
+//! limited to some protocols or ports that outrank that rule are kept beside
+//! it and scanned in order when a packet is evaluated.
+//!
+//! Rules must use CIDR blocks; resolve tag and peer selectors with
+//! [`policy::resolve_rules`](crate::policy::resolve_rules) first.
+
+use crate::policy::{PortRange, Protocol};
+use crate::{network::parse_cidr, AclAction, AclRule, Result};
+use ipnetwork::IpNetwork;
+use std::cmp::Reverse;
+use std::net::IpAddr;
+use uuid::Uuid;
+
+/// Outcome of evaluating a connection
+#[derive(Debug, Clone, PartialEq)]
+pub struct AclDecision {
+    /// Whether the connection is allowed
+    pub action: AclAction,
+    /// Rule that decided, or `None` for the default deny
+    pub rule_id: Option<Uuid>,
+}
+
+impl AclDecision {
+    /// Whether the connection is allowed
+    pub fn allowed(&self) -> bool {
+        self.action == AclAction::Allow
+    }
+}
+
+/// Ordering of matching rules: higher priority first, then peer rules over
+/// network-wide ones, then longer source and destination prefixes, then
+/// earlier rules
+type Rank = (i32, bool, u8, u8, Reverse<usize>);
+
+#[derive(Debug)]
+struct CompiledRule {
+    id: Uuid,
+    action: AclAction,
+    rank: Rank,
+    protocol: Option<Protocol>,
+    ports: Vec<PortRange>,
+}
+
+impl CompiledRule {
+    /// Whether the rule covers all protocols and ports
+    fn unrestricted(&self) -> bool {
+        self.protocol.is_none() && self.ports.is_empty()
+    }
+
+    fn matches(&self, protocol: Protocol, port: Option<u16>) -> bool {
+        self.protocol
+            .is_none_or(|p| p.number() == protocol.number())
+            && (self.ports.is_empty()
+                || port.is_some_and(|port| self.ports.iter().any(|r| r.contains(port))))
+    }
+}
+
+/// Rules a destination node can decide with
+#[derive(Debug, Clone, Default)]
+struct Candidates {
+    /// Best rule covering all protocols and ports
+    best: Option<usize>,
+    /// Restricted rules that outrank `best`, best first
+    restricted: Vec<usize>,
+}
+
+/// Immutable ACL lookup structure, rebuilt whenever the rules change
+#[derive(Debug, Default)]
+pub struct AclEngine {
+    rules: Vec<CompiledRule>,
+    v4: PrefixTrie<DestTrie>,
+    v6: PrefixTrie<DestTrie>,
+}
+
+/// Destination trie whose nodes hold the candidates on their path
+type DestTrie = PrefixTrie<Candidates>;
+
+impl AclEngine {
+    /// Compile rules, failing on the first invalid CIDR block
+    pub fn compile(rules: &[AclRule]) -> Result<Self> {
+        let mut compiled = Vec::with_capacity(rules.len());
+        let mut prefixes = Vec::with_capacity(rules.len());
+        for (index, rule) in rules.iter().enumerate() {
+            rule.validate()?;
+            let source = Prefix::from(parse_cidr(&rule.source_cidr)?);
+            let dest = Prefix::from(parse_cidr(&rule.dest_cidr)?);
+            compiled.push(CompiledRule {
+                id: rule.id,
+                action: rule.action.clone(),
+                rank: (
+                    rule.priority,
+                    rule.peer_id.is_some(),
+                    source.len,
+                    dest.len,
+                    Reverse(index),
+                ),
+                protocol: rule.protocol,
+                ports: rule.ports.clone(),
+            });
+            prefixes.push((source, dest));
+        }
+
+        let mut engine = Self {
+            rules: compiled,
+            ..Default::default()
+        };
+        engine.v4 = engine.build_family(&prefixes, true);
+        engine.v6 = engine.build_family(&prefixes, false);
+        Ok(engine)
+    }
+
+    /// Number of compiled rules
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Whether there are no rules, so everything is denied
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Decide a connection from `source` to `dest` on any protocol and port
+    ///
+    /// Only rules covering all protocols and ports apply. Takes at most one
+    /// step per prefix bit in each trie. Connections no rule matches,
+    /// including ones between address families, are denied.
+    pub fn evaluate(&self, source: IpAddr, dest: IpAddr) -> AclDecision {
+        self.decide(
+            self.candidates(source, dest)
+                .and_then(|candidates| candidates.best),
+        )
+    }
+
+    /// Decide a packet, with `port` its destination port if it has one
+    pub fn evaluate_packet(
+        &self,
+        source: IpAddr,
+        dest: IpAddr,
+        protocol: Protocol,
+        port: Option<u16>,
+    ) -> AclDecision {
+        let index = self.candidates(source, dest).and_then(|candidates| {
+            candidates
+                .restricted
+                .iter()
+                .copied()
+                .find(|&index| self.rules[index].matches(protocol, port))
+                .or(candidates.best)
+        });
+        self.decide(index)
+    }
+
+    /// Whether some packet from `source` to `dest` is allowed
+    pub fn reachable(&self, source: IpAddr, dest: IpAddr) -> bool {
+        self.candidates(source, dest).is_some_and(|candidates| {
+            candidates
+                .restricted
+                .iter()
+                .chain(&candidates.best)
+                .any(|&index| self.rules[index].action == AclAction::Allow)
+        })
+    }
+
+    fn candidates(&self, source: IpAddr, dest: IpAddr) -> Option<&Candidates> {
+        let trie = match (source, dest) {
+            (IpAddr::V4(_), IpAddr::V4(_)) => &self.v4,
+            (IpAddr::V6(_), IpAddr::V6(_)) => &self.v6,
+            _ => return None,
+        };
+        trie.longest_match(key(source))
+            .and_then(|dests| dests.longest_match(key(dest)))
+    }
+
+    fn decide(&self, index: Option<usize>) -> AclDecision {
+        match index {
+            Some(index) => AclDecision {
+                action: self.rules[index].action.clone(),
+                rule_id: Some(self.rules[index].id),
+            },
+            None => AclDecision {
+                action: AclAction::Deny,
+                rule_id: None,
+            },
+        }
+    }
+
+    /// Build the source trie for one family from each rule's `(source, dest)`
+    fn build_family(&self, prefixes: &[(Prefix, Prefix)], v4: bool) -> PrefixTrie<DestTrie> {
+        let mut sources: PrefixTrie<Vec<usize>> = PrefixTrie::default();
+        for (index, (source, _)) in prefixes.iter().enumerate() {
+            if source.v4 == v4 {
+                sources
+                    .entry(source.key, source.len)
+                    .get_or_insert_with(Vec::new)
+                    .push(index);
+            }
+        }
+
+        // Each source prefix sees its own rules and those of shorter prefixes
+        let mut trie = PrefixTrie::default();
+        for (source_key, source_len, _) in sources.iter() {
+            let mut dests = DestTrie::default();
+            sources.for_each_match(source_key, source_len, |rules| {
+                for &index in rules {
+                    let dest = prefixes[index].1;
+                    let own = dests
+                        .entry(dest.key, dest.len)
+                        .get_or_insert_with(Default::default);
+                    if self.rules[index].unrestricted() {
+                        own.best = self.better(own.best, Some(index));
+                    } else {
+                        own.restricted.push(index);
+                    }
+                }
+            });
+            dests.propagate(&Candidates::default(), &|parent, own| {
+                self.combine(parent, own)
+            });
+            *trie.entry(source_key, source_len) = Some(dests);
+        }
+        trie
+    }
+
+    fn better(&self, a: Option<usize>, b: Option<usize>) -> Option<usize> {
+        match (a, b) {
+            (Some(a), Some(b)) => Some(if self.rules[b].rank > self.rules[a].rank {
+                b
+            } else {
+                a
+            }),
+            (a, b) => a.or(b),
+        }
+    }
+
+    /// Candidates of a node given those of its parent
+    fn combine(&self, parent: &Candidates, own: Option<Candidates>) -> Candidates {
+        let Some(own) = own else {
+            return parent.clone();
+        };
+
+        let best = self.better(parent.best, own.best);
+        let outranks_best =
+            |&index: &usize| best.is_none_or(|best| self.rules[index].rank > self.rules[best].rank);
+        let mut restricted: Vec<usize> = parent
+            .restricted
+            .iter()
+            .chain(&own.restricted)
+            .copied()
+            .filter(outranks_best)
+            .collect();
+        restricted.sort_by_key(|&index| Reverse(self.rules[index].rank));
+
+        Candidates { best, restricted }
+    }
+}
+
+/// A prefix as a left-aligned 128-bit key, so IPv4 and IPv6 share the trie code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Prefix {
+    key: u128,
+    len: u8,
+    v4: bool,
+}
+
+impl From<IpNetwork> for Prefix {
+    fn from(network: IpNetwork) -> Self {
+        Self {
+            key: key(network.ip()) & mask(network.prefix()),
+            len: network.prefix(),
+            v4: network.is_ipv4(),
+        }
+    }
+}
+
+fn key(ip: IpAddr) -> u128 {
+    match ip {
+        IpAddr::V4(ip) => (u32::from(ip) as u128) << 96,
+        IpAddr::V6(ip) => u128::from(ip),
+    }
+}
+
+fn mask(len: u8) -> u128 {
+    if len == 0 {
+        0
+    } else {
+        !0u128 << (128 - len as u32)
+    }
+}
+
+fn bit(key: u128, index: u8) -> usize {
+    ((key >> (127 - index as u32)) & 1) as usize
+}
+
+/// Number of leading bits `a` and `b` share, at most `limit`
+fn common_len(a: u128, b: u128, limit: u8) -> u8 {
+    ((a ^ b).leading_zeros() as u8).min(limit)
+}
+
+/// Path-compressed binary trie keyed by prefix
+///
+/// Nodes exist only for stored prefixes and for branch points, so a walk
+/// visits at most one node per prefix bit.
+#[derive(Debug)]
+struct PrefixTrie<T> {
+    key: u128,
+    len: u8,
+    value: Option<T>,
+    children: [Option<Box<PrefixTrie<T>>>; 2],
+}
+
+impl<T> Default for PrefixTrie<T> {
+    fn default() -> Self {
+        Self::leaf(0, 0)
+    }
+}
+
+impl<T> PrefixTrie<T> {
+    fn leaf(key: u128, len: u8) -> Self {
+        Self {
+            key,
+            len,
+            value: None,
+            children: [None, None],
+        }
+    }
+
+    /// Value slot for the prefix `key/len`, creating the node if needed
+    fn entry(&mut self, key: u128, len: u8) -> &mut Option<T> {
+        let common = common_len(self.key, key, self.len.min(len));
+
+        if common < self.len {
+            // Split: this node moves under a new node for the shared prefix
+            let old = std::mem::replace(self, Self::leaf(key & mask(common), common));
+            let old_bit = bit(old.key, common);
+            self.children[old_bit] = Some(Box::new(old));
+            if len == common {
+                return &mut self.value;
+            }
+            let child = self.children[bit(key, common)].insert(Box::new(Self::leaf(key, len)));
+            return &mut child.value;
+        }
+        if len == self.len {
+            return &mut self.value;
+        }
+
+        match &mut self.children[bit(key, self.len)] {
+            Some(child) => child.entry(key, len),
+            slot => &mut slot.insert(Box::new(Self::leaf(key, len))).value,
+        }
+    }
+
+    /// Value of the longest stored prefix covering `key`
+    fn longest_match(&self, key: u128) -> Option<&T> {
+        let mut node = self;
+        let mut best = None;
+        loop {
+            if common_len(node.key, key, node.len) < node.len {
+                return best;
+            }
+            if let Some(value) = &node.value {
+                best = Some(value);
+            }
+            if node.len == 128 {
+                return best;
+            }
+            match &node.children[bit(key, node.len)] {
+                Some(child) => node = child,
+                None => return best,
+            }
+        }
+    }
+
+    /// Call `f` with every value stored at a prefix of `key/len`, shortest first
+    fn for_each_match(&self, key: u128, len: u8, mut f: impl FnMut(&T)) {
+        let mut node = self;
+        loop {
+            if node.len > len || common_len(node.key, key, node.len) < node.len {
+                return;
+            }
+            if let Some(value) = &node.value {
+                f(value);
+            }
+            if node.len == len {
+                return;
+            }
+            match &node.children[bit(key, node.len)] {
+                Some(child) => node = child,
+                None => return,
+            }
+        }
+    }
+
+    /// Every stored prefix as `(key, len, value)`
+    fn iter(&self) -> Vec<(u128, u8, &T)> {
+        let mut out = Vec::new();
+        let mut stack = vec![self];
+        while let Some(node) = stack.pop() {
+            if let Some(value) = &node.value {
+                out.push((node.key, node.len, value));
+            }
+            stack.extend(node.children.iter().flatten().map(|child| &**child));
+        }
+        out
+    }
+
+    /// Fold each node's value into its descendants, filling every node
+    fn propagate(&mut self, parent: &T, combine: &impl Fn(&T, Option<T>) -> T) {
+        let value = combine(parent, self.value.take());
+        for child in self.children.iter_mut().flatten() {
+            child.propagate(&value, combine);
+        }
+        self.value = Some(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(source: &str, dest: &str, action: AclAction, priority: i32) -> AclRule {
+        AclRule {
+            id: Uuid::new_v4(),
+            source_cidr: source.to_string(),
+            dest_cidr: dest.to_string(),
+            action,
+            priority,
+            description: None,
+            peer_id: None,
+            protocol: None,
+            ports: vec![],
+        }
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_default_deny() {
+        let engine = AclEngine::compile(&[]).unwrap();
+        let decision = engine.evaluate(ip("10.0.0.1"), ip("10.0.0.2"));
+        assert!(!decision.allowed());
+        assert_eq!(decision.rule_id, None);
+    }
+
+    #[test]
+    fn test_priority_decides() {
+        let allow = rule("10.0.0.0/8", "10.0.0.0/8", AclAction::Allow, 0);
+        let deny = rule("10.1.0.0/16", "10.2.0.0/16", AclAction::Deny, 10);
+        let low_deny = rule("10.3.0.0/16", "10.0.0.0/8", AclAction::Deny, -1);
+        let engine = AclEngine::compile(&[allow.clone(), deny.clone(), low_deny]).unwrap();
+
+        let decide = |s, d| engine.evaluate(ip(s), ip(d)).rule_id;
+        assert_eq!(decide("10.1.2.3", "10.2.3.4"), Some(deny.id));
+        assert_eq!(decide("10.1.2.3", "10.3.3.4"), Some(allow.id));
+        // A more specific source does not beat a higher priority
+        assert_eq!(decide("10.3.2.3", "10.9.3.4"), Some(allow.id));
+        assert_eq!(decide("192.168.0.1", "10.0.0.1"), None);
+    }
+
+    #[test]
+    fn test_specific_prefix_breaks_ties() {
+        let broad = rule("0.0.0.0/0", "10.0.0.0/8", AclAction::Deny, 5);
+        let narrow = rule("10.0.0.0/24", "10.0.0.0/8", AclAction::Allow, 5);
+        let engine = AclEngine::compile(&[broad.clone(), narrow.clone()]).unwrap();
+
+        assert_eq!(
+            engine.evaluate(ip("10.0.0.7"), ip("10.5.0.1")).rule_id,
+            Some(narrow.id)
+        );
+        assert_eq!(
+            engine.evaluate(ip("10.0.1.7"), ip("10.5.0.1")).rule_id,
+            Some(broad.id)
+        );
+    }
+
+    #[test]
+    fn test_ipv6_and_host_routes() {
+        let host = rule("fd00::1/128", "fd00::/64", AclAction::Allow, 0);
+        let v4 = rule("0.0.0.0/0", "0.0.0.0/0", AclAction::Allow, 0);
+        let engine = AclEngine::compile(&[host.clone(), v4]).unwrap();
+
+        assert_eq!(
+            engine.evaluate(ip("fd00::1"), ip("fd00::2")).rule_id,
+            Some(host.id)
+        );
+        assert!(!engine.evaluate(ip("fd00::2"), ip("fd00::1")).allowed());
+        assert!(!engine.evaluate(ip("fd00::1"), ip("fd01::1")).allowed());
+        assert!(!engine.evaluate(ip("10.0.0.1"), ip("fd00::1")).allowed());
+    }
+
+    #[test]
+    fn test_ports_and_protocols() {
+        let mut ssh = rule("10.0.0.0/8", "10.1.0.0/16", AclAction::Allow, 10);
+        ssh.protocol = Some(Protocol::Tcp);
+        ssh.ports = vec!["22".parse().unwrap()];
+        let rules = vec![ssh, rule("10.0.0.0/8", "10.0.0.0/8", AclAction::Deny, 5)];
+        let engine = AclEngine::compile(&rules).unwrap();
+
+        let (source, dest) = (ip("10.0.0.1"), ip("10.1.0.1"));
+        assert!(engine
+            .evaluate_packet(source, dest, Protocol::Tcp, Some(22))
+            .allowed());
+        assert!(!engine
+            .evaluate_packet(source, dest, Protocol::Tcp, Some(23))
+            .allowed());
+        assert!(!engine
+            .evaluate_packet(source, dest, Protocol::Udp, Some(22))
+            .allowed());
+        assert!(!engine
+            .evaluate_packet(source, dest, Protocol::Icmp, None)
+            .allowed());
+        // Address-only lookups only see rules covering all traffic
+        assert_eq!(engine.evaluate(source, dest).rule_id, Some(rules[1].id));
+        // SSH is enough to make the destination reachable
+        assert!(engine.reachable(source, dest));
+        assert!(!engine.reachable(dest, source));
+    }
+
+    #[test]
+    fn test_matches_linear_scan() {
+        // Small deterministic generator, so no rand dependency is needed
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut next = move |bound: u32| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % bound as u64) as u32
+        };
+        let addr = |next: &mut dyn FnMut(u32) -> u32| {
+            std::net::Ipv4Addr::new(10, next(4) as u8, next(4) as u8, next(256) as u8)
+        };
+
+        let mut rules = Vec::new();
+        for _ in 0..200 {
+            let source = format!("{}/{}", addr(&mut next), 8 + next(25));
+            let dest = format!("{}/{}", addr(&mut next), 8 + next(25));
+            let action = if next(2) == 0 {
+                AclAction::Allow
+            } else {
+                AclAction::Deny
+            };
+            let mut rule = rule(&source, &dest, action, next(5) as i32);
+            if next(4) == 0 {
+                rule.peer_id = Some(Uuid::new_v4());
+            }
+            match next(6) {
+                0 => rule.protocol = Some(Protocol::Udp),
+                1 => {
+                    rule.protocol = Some(Protocol::Tcp);
+                    let first = next(4) as u16 * 100;
+                    rule.ports = vec![PortRange {
+                        first,
+                        last: first + next(150) as u16,
+                    }];
+                }
+                _ => {}
+            }
+            rules.push(rule);
+        }
+        let engine = AclEngine::compile(&rules).unwrap();
+
+        for _ in 0..2000 {
+            let (source, dest) = (IpAddr::V4(addr(&mut next)), IpAddr::V4(addr(&mut next)));
+            let port = next(500) as u16;
+            let best = |filter: &dyn Fn(&CompiledRule) -> bool| {
+                engine
+                    .rules
+                    .iter()
+                    .zip(&rules)
+                    .filter(|(compiled, r)| {
+                        filter(compiled)
+                            && crate::network::ip_in_cidr(source, &r.source_cidr).unwrap()
+                            && crate::network::ip_in_cidr(dest, &r.dest_cidr).unwrap()
+                    })
+                    .max_by_key(|(compiled, _)| compiled.rank)
+                    .map(|(compiled, _)| compiled.id)
+            };
+            assert_eq!(
+                engine.evaluate(source, dest).rule_id,
+                best(&|r| r.unrestricted()),
+                "{} -> {}",
+                source,
+                dest
+            );
+            assert_eq!(
+                engine
+                    .evaluate_packet(source, dest, Protocol::Tcp, Some(port))
+                    .rule_id,
+                best(&|r| r.matches(Protocol::Tcp, Some(port))),
+                "{} -> {}:{}",
+                source,
+                dest,
+                port
+            );
+        }
+    }
+
+    #[test]
+    fn test_rejects_invalid_rules() {
+        assert!(
+            AclEngine::compile(&[rule("10.0.0.0/40", "10.0.0.0/8", AclAction::Allow, 0)]).is_err()
+        );
+    }
+}
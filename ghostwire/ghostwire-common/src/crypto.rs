@@ -1,8 +1,17 @@
 //! Cryptographic utilities for Ghostwire
 
-use ring::rand::{SecureRandom, SystemRandom};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use ring::{
+    rand::{SecureRandom, SystemRandom},
+    signature::{self, Ed25519KeyPair, KeyPair, UnparsedPublicKey},
+};
 use std::convert::TryInto;
 
+use crate::{GhostwireError, NodeKeyProof, PublicKey, RegistrationChallenge};
+
+/// Prefix of every signed challenge, so the signature means nothing elsewhere
+const CHALLENGE_CONTEXT: &[u8] = b"ghostwire registration challenge v1\0";
+
 /// Generate a new WireGuard private key
 pub fn generate_private_key() -> [u8; 32] {
     let rng = SystemRandom::new();
@@ -34,15 +43,108 @@ pub fn generate_keypair() -> ([u8; 32], [u8; 32]) {
 
 /// Encode key as base64
 pub fn encode_key(key: &[u8; 32]) -> String {
-    base64::encode(key)
+    STANDARD.encode(key)
 }
 
 /// Decode key from base64
 pub fn decode_key(encoded: &str) -> Result<[u8; 32], base64::DecodeError> {
-    let bytes = base64::decode(encoded)?;
+    let bytes = STANDARD.decode(encoded)?;
     if bytes.len() != 32 {
-        return Err(base64::DecodeError::InvalidLength);
+        return Err(base64::DecodeError::InvalidLength(bytes.len()));
     }
 
     Ok(bytes.try_into().unwrap())
-}
\ No newline at end of file
+}
+
+/// Ed25519 key a node registers with, proving possession by signing
+/// server challenges
+pub struct NodeKey {
+    pair: Ed25519KeyPair,
+    pkcs8: Vec<u8>,
+}
+
+impl NodeKey {
+    /// Generate a new node key
+    pub fn generate() -> crate::Result<Self> {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+            .map_err(|_| GhostwireError::Crypto("Failed to generate node key".to_string()))?;
+        Self::from_pkcs8(pkcs8.as_ref())
+    }
+
+    /// Load a node key saved with [`Self::to_pkcs8`]
+    pub fn from_pkcs8(pkcs8: &[u8]) -> crate::Result<Self> {
+        let pair = Ed25519KeyPair::from_pkcs8(pkcs8)
+            .map_err(|e| GhostwireError::Crypto(format!("Invalid node key: {}", e)))?;
+        Ok(Self { pair, pkcs8: pkcs8.to_vec() })
+    }
+
+    /// PKCS#8 encoding of the key, for storing it
+    pub fn to_pkcs8(&self) -> &[u8] {
+        &self.pkcs8
+    }
+
+    /// Public half, registered as the peer's public key
+    pub fn public_key(&self) -> PublicKey {
+        let mut key = [0u8; 32];
+        key.copy_from_slice(self.pair.public_key().as_ref());
+        PublicKey(key)
+    }
+
+    /// Sign a registration challenge
+    pub fn prove(&self, challenge: &RegistrationChallenge) -> crate::Result<NodeKeyProof> {
+        let nonce = STANDARD.decode(&challenge.nonce)
+            .map_err(|e| GhostwireError::Crypto(format!("Invalid challenge nonce: {}", e)))?;
+        let signature = self.pair.sign(&challenge_message(&nonce, &self.public_key()));
+        Ok(NodeKeyProof {
+            nonce: challenge.nonce.clone(),
+            signature: STANDARD.encode(signature.as_ref()),
+        })
+    }
+}
+
+/// Check that `proof` was signed by the private key of `public_key`,
+/// returning the signed nonce
+pub fn verify_proof(public_key: &PublicKey, proof: &NodeKeyProof) -> crate::Result<Vec<u8>> {
+    let invalid = || GhostwireError::Unauthorized("invalid node key signature".to_string());
+    let nonce = STANDARD.decode(&proof.nonce).map_err(|_| invalid())?;
+    let signature = STANDARD.decode(&proof.signature).map_err(|_| invalid())?;
+
+    UnparsedPublicKey::new(&signature::ED25519, public_key.0)
+        .verify(&challenge_message(&nonce, public_key), &signature)
+        .map_err(|_| invalid())?;
+    Ok(nonce)
+}
+
+/// Bytes signed to answer a challenge: the context, nonce and public key
+fn challenge_message(nonce: &[u8], public_key: &PublicKey) -> Vec<u8> {
+    [CHALLENGE_CONTEXT, nonce, &public_key.0].concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn challenge(nonce: &[u8]) -> RegistrationChallenge {
+        RegistrationChallenge {
+            nonce: STANDARD.encode(nonce),
+            expires_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_node_key_proof() {
+        let node = NodeKey::generate().unwrap();
+        let proof = node.prove(&challenge(b"nonce")).unwrap();
+        assert_eq!(verify_proof(&node.public_key(), &proof).unwrap(), b"nonce");
+
+        // The key survives a round trip through storage
+        let reloaded = NodeKey::from_pkcs8(node.to_pkcs8()).unwrap();
+        assert_eq!(reloaded.public_key(), node.public_key());
+
+        // Another key, or another nonce, does not verify
+        let other = NodeKey::generate().unwrap();
+        assert!(matches!(verify_proof(&other.public_key(), &proof), Err(GhostwireError::Unauthorized(_))));
+        let replayed = NodeKeyProof { nonce: STANDARD.encode(b"other"), ..proof };
+        assert!(verify_proof(&node.public_key(), &replayed).is_err());
+    }
+}
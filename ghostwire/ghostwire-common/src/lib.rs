@@ -1,12 +1,15 @@
 //! Common types and utilities for Ghostwire mesh VPN
 
+use base64::{engine::general_purpose::STANDARD, Engine as _};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::net::{IpAddr, SocketAddr};
 use uuid::Uuid;
 
+pub mod acl;
 pub mod crypto;
 pub mod network;
+pub mod policy;
 pub mod protocol;
 
 /// Peer information in the mesh network
@@ -24,6 +27,9 @@ pub struct PeerInfo {
     pub metadata: PeerMetadata,
     /// Network access control list
     pub acl_rules: Vec<AclRule>,
+    /// User who joined the peer through OIDC login
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<Uuid>,
 }
 
 /// WireGuard public key wrapper
@@ -32,10 +38,10 @@ pub struct PublicKey(pub [u8; 32]);
 
 impl PublicKey {
     /// Create from base64 string
-    pub fn from_base64(s: &str) -> Result<Self, base64::DecodeError> {
-        let bytes = base64::decode(s)?;
+    pub fn from_base64(s: &str) -> std::result::Result<Self, base64::DecodeError> {
+        let bytes = STANDARD.decode(s)?;
         if bytes.len() != 32 {
-            return Err(base64::DecodeError::InvalidLength);
+            return Err(base64::DecodeError::InvalidLength(bytes.len()));
         }
         let mut key = [0u8; 32];
         key.copy_from_slice(&bytes);
@@ -44,7 +50,7 @@ impl PublicKey {
 
     /// Convert to base64 string
     pub fn to_base64(&self) -> String {
-        base64::encode(self.0)
+        STANDARD.encode(self.0)
     }
 }
 
@@ -66,11 +72,12 @@ pub struct PeerMetadata {
 /// Access Control List rule
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct AclRule {
-    /// Rule identifier
+    /// Rule identifier, assigned by the server when the rule is added
+    #[serde(default)]
     pub id: Uuid,
-    /// Source CIDR block
+    /// Source CIDR block, or a `*`, `tag:` or `peer:` selector
     pub source_cidr: String,
-    /// Destination CIDR block
+    /// Destination CIDR block, or a `*`, `tag:` or `peer:` selector
     pub dest_cidr: String,
     /// Action to take
     pub action: AclAction,
@@ -78,12 +85,83 @@ pub struct AclRule {
     pub priority: i32,
     /// Optional description
     pub description: Option<String>,
+    /// Peer the rule belongs to, or `None` for a network-wide rule
+    #[serde(default)]
+    pub peer_id: Option<Uuid>,
+    /// Protocol the rule applies to, or `None` for all protocols
+    #[serde(default)]
+    pub protocol: Option<policy::Protocol>,
+    /// Destination ports, empty for all ports
+    #[serde(default)]
+    pub ports: Vec<policy::PortRange>,
+}
+
+impl AclRule {
+    /// Check selectors, ports and protocol
+    ///
+    /// CIDR sources and destinations must share an address family, groups
+    /// must already be expanded, and ports need TCP, UDP or any protocol.
+    pub fn validate(&self) -> Result<()> {
+        use policy::Selector;
+
+        let source: Selector = self.source_cidr.parse()?;
+        let dest: Selector = self.dest_cidr.parse()?;
+        for selector in [&source, &dest] {
+            if let Selector::Group(name) = selector {
+                return Err(GhostwireError::Acl(format!("group:{} must be expanded by a policy", name)));
+            }
+        }
+        if let (Selector::Cidr(source), Selector::Cidr(dest)) = (&source, &dest) {
+            if source.is_ipv4() != dest.is_ipv4() {
+                return Err(GhostwireError::Acl(format!(
+                    "source {} and destination {} are different address families",
+                    self.source_cidr, self.dest_cidr
+                )));
+            }
+        }
+        if let Some(protocol) = self.protocol {
+            if !self.ports.is_empty() && !protocol.has_ports() {
+                return Err(GhostwireError::Acl(format!("{} has no ports", protocol)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether both rules can match the same traffic with opposite actions
+    ///
+    /// Only rules with the same scope and priority can contradict each
+    /// other; otherwise scope and priority decide which one wins.
+    pub fn contradicts(&self, other: &AclRule) -> Result<bool> {
+        use policy::Selector;
+
+        if self.id == other.id
+            || self.peer_id != other.peer_id
+            || self.priority != other.priority
+            || self.action == other.action
+        {
+            return Ok(false);
+        }
+
+        let protocols = match (self.protocol, other.protocol) {
+            (Some(a), Some(b)) => a.number() == b.number(),
+            _ => true,
+        };
+        let ports = self.ports.is_empty()
+            || other.ports.is_empty()
+            || self.ports.iter().any(|a| other.ports.iter().any(|b| a.overlaps(b)));
+
+        Ok(protocols
+            && ports
+            && self.source_cidr.parse::<Selector>()?.overlaps(&other.source_cidr.parse()?)
+            && self.dest_cidr.parse::<Selector>()?.overlaps(&other.dest_cidr.parse()?))
+    }
 }
 
 /// ACL action
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum AclAction {
+    #[serde(alias = "accept")]
     Allow,
     Deny,
 }
@@ -120,6 +198,56 @@ pub struct NetworkTopology {
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
 
+/// Topology entities carried by a delta
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TopologyEntities {
+    pub peers: Vec<PeerInfo>,
+    pub routes: Vec<Route>,
+    pub acls: Vec<AclRule>,
+}
+
+/// IDs of topology entities removed by a delta
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RemovedEntities {
+    pub peers: Vec<Uuid>,
+    /// Network IDs of the removed routes
+    pub routes: Vec<Uuid>,
+    pub acls: Vec<Uuid>,
+}
+
+/// Changes between two topology generations
+///
+/// Clients apply `added` and `updated` as upserts and ignore removals of
+/// entities they do not know.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TopologyDelta {
+    /// Generation the delta applies to
+    pub since: u64,
+    /// Generation the client is at once the delta is applied
+    pub generation: u64,
+    pub added: TopologyEntities,
+    pub updated: TopologyEntities,
+    pub removed: RemovedEntities,
+}
+
+impl TopologyDelta {
+    /// Whether applying the delta changes nothing but the generation
+    pub fn is_empty(&self) -> bool {
+        self.added == TopologyEntities::default()
+            && self.updated == TopologyEntities::default()
+            && self.removed == RemovedEntities::default()
+    }
+}
+
+/// Topology sent to a client: the changes since its generation, or the
+/// whole topology when they are no longer known
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "topology", rename_all = "lowercase")]
+pub enum TopologySync {
+    Delta(TopologyDelta),
+    Snapshot(NetworkTopology),
+}
+
 /// Peer registration request
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RegisterPeerRequest {
@@ -129,6 +257,162 @@ pub struct RegisterPeerRequest {
     pub endpoints: Vec<SocketAddr>,
     /// Peer metadata
     pub metadata: PeerMetadata,
+    /// Pre-auth key authorizing a new peer; a known public key needs none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_key: Option<String>,
+    /// Server challenge signed with the node private key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proof: Option<NodeKeyProof>,
+}
+
+/// Nonce a node signs to prove it holds the private key for its public key
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RegistrationChallenge {
+    /// Base64 nonce, valid for one registration
+    pub nonce: String,
+    /// When the server forgets the nonce
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Answer to a [`RegistrationChallenge`], see [`crypto::NodeKey::prove`]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NodeKeyProof {
+    /// Base64 nonce from the challenge
+    pub nonce: String,
+    /// Base64 Ed25519 signature
+    pub signature: String,
+}
+
+/// Pre-auth key as stored; the secret itself is only shown when minted
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PreAuthKey {
+    pub id: Uuid,
+    /// Whether the key may register more than one peer
+    pub reusable: bool,
+    /// Whether its peers are removed when their last session ends
+    pub ephemeral: bool,
+    /// Tags given to every peer registered with the key
+    pub tags: Vec<String>,
+    pub description: Option<String>,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Peers registered with the key so far
+    pub used_count: u64,
+    pub revoked: bool,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Request to mint a pre-auth key
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CreatePreAuthKeyRequest {
+    #[serde(default)]
+    pub reusable: bool,
+    #[serde(default)]
+    pub ephemeral: bool,
+    /// Tag names, with or without the `tag:` prefix
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Omitted for a key that never expires
+    #[serde(default)]
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Newly minted pre-auth key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatedPreAuthKey {
+    /// The secret to hand to clients; the server keeps only its hash
+    pub key: String,
+    #[serde(flatten)]
+    pub info: PreAuthKey,
+}
+
+/// Permission granted to an admin API token
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum ApiScope {
+    #[serde(rename = "peers:read")]
+    PeersRead,
+    #[serde(rename = "peers:write")]
+    PeersWrite,
+    #[serde(rename = "acl:read")]
+    AclRead,
+    #[serde(rename = "acl:write")]
+    AclWrite,
+    /// Every other scope, plus pre-auth key management
+    #[serde(rename = "admin")]
+    Admin,
+}
+
+impl ApiScope {
+    /// Whether holding this scope allows what `scope` allows
+    ///
+    /// Write scopes include reading.
+    pub fn grants(self, scope: ApiScope) -> bool {
+        self == scope
+            || self == ApiScope::Admin
+            || (self == ApiScope::PeersWrite && scope == ApiScope::PeersRead)
+            || (self == ApiScope::AclWrite && scope == ApiScope::AclRead)
+    }
+}
+
+impl std::fmt::Display for ApiScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ApiScope::PeersRead => "peers:read",
+            ApiScope::PeersWrite => "peers:write",
+            ApiScope::AclRead => "acl:read",
+            ApiScope::AclWrite => "acl:write",
+            ApiScope::Admin => "admin",
+        })
+    }
+}
+
+impl std::str::FromStr for ApiScope {
+    type Err = GhostwireError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "peers:read" => Ok(ApiScope::PeersRead),
+            "peers:write" => Ok(ApiScope::PeersWrite),
+            "acl:read" => Ok(ApiScope::AclRead),
+            "acl:write" => Ok(ApiScope::AclWrite),
+            "admin" => Ok(ApiScope::Admin),
+            _ => Err(GhostwireError::Config(format!("unknown API scope: {}", s))),
+        }
+    }
+}
+
+/// Admin API token as stored; the secret itself is only shown when created
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ApiToken {
+    pub id: Uuid,
+    pub name: String,
+    pub scopes: Vec<ApiScope>,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Last authenticated request, to within a minute
+    pub last_used_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub revoked: bool,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl ApiToken {
+    /// Whether the token's scopes allow `scope`
+    pub fn allows(&self, scope: ApiScope) -> bool {
+        self.scopes.iter().any(|held| held.grants(scope))
+    }
+}
+
+/// Peer update request
+///
+/// Fields left out keep their stored value.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdatePeerRequest {
+    /// New endpoints
+    #[serde(default)]
+    pub endpoints: Option<Vec<SocketAddr>>,
+    /// New metadata
+    #[serde(default)]
+    pub metadata: Option<PeerMetadata>,
 }
 
 /// Peer registration response
@@ -144,6 +428,37 @@ pub struct RegisterPeerResponse {
     pub acl_rules: Vec<AclRule>,
 }
 
+/// Registration waiting for the user to log in with the OIDC provider
+///
+/// Once the login completes the peer exists, and the node resumes it by
+/// connecting with its node key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingRegistration {
+    /// Page for the user to log in at
+    pub auth_url: String,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Outcome of a registration request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum Registration {
+    /// The peer was registered
+    Complete(RegisterPeerResponse),
+    /// The peer is registered once the user logs in
+    Pending(PendingRegistration),
+}
+
+/// User who joined peers through OIDC login
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct User {
+    pub id: Uuid,
+    pub email: Option<String>,
+    pub display_name: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub last_login_at: chrono::DateTime<chrono::Utc>,
+}
+
 /// Network configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NetworkConfig {
@@ -177,7 +492,7 @@ pub struct HealthCheck {
 }
 
 /// Coordination server configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct ServerConfig {
     /// Server bind address
     pub bind_address: SocketAddr,
@@ -193,6 +508,72 @@ pub struct ServerConfig {
     pub metrics_config: MetricsConfig,
     /// Log level
     pub log_level: String,
+    /// ACL policy applied at startup, replacing the network-wide rules
+    #[serde(default)]
+    pub policy_path: Option<String>,
+    /// Origins browsers may call the API from; empty allows none
+    #[serde(default)]
+    pub cors_allowed_origins: Vec<String>,
+    /// Lets users join peers by logging in, instead of with a pre-auth key
+    #[serde(default)]
+    pub oidc: Option<OidcConfig>,
+    /// Bearer secret granted the admin scope, so a server can be managed
+    /// before any API token is stored
+    #[serde(default)]
+    pub admin_token: Option<String>,
+}
+
+// Hand-written so the admin token stays out of logged configuration
+impl std::fmt::Debug for ServerConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ServerConfig")
+            .field("bind_address", &self.bind_address)
+            .field("database_path", &self.database_path)
+            .field("network_cidr", &self.network_cidr)
+            .field("tls_cert_path", &self.tls_cert_path)
+            .field("tls_key_path", &self.tls_key_path)
+            .field("metrics_config", &self.metrics_config)
+            .field("log_level", &self.log_level)
+            .field("policy_path", &self.policy_path)
+            .field("cors_allowed_origins", &self.cors_allowed_origins)
+            .field("oidc", &self.oidc)
+            .field("admin_token", &self.admin_token.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+/// OpenID Connect provider that users log in with to join peers
+#[derive(Clone, Serialize, Deserialize)]
+pub struct OidcConfig {
+    /// Issuer URL; endpoints come from its `/.well-known/openid-configuration`
+    pub issuer: String,
+    pub client_id: String,
+    #[serde(default)]
+    pub client_secret: Option<String>,
+    /// Callback registered with the provider, ending in `/api/v1/auth/oidc/callback`
+    pub redirect_uri: String,
+    /// Scopes requested besides `openid`
+    #[serde(default = "OidcConfig::default_scopes")]
+    pub scopes: Vec<String>,
+}
+
+impl OidcConfig {
+    fn default_scopes() -> Vec<String> {
+        vec!["email".to_string(), "profile".to_string()]
+    }
+}
+
+// Hand-written so the client secret stays out of logged configuration
+impl std::fmt::Debug for OidcConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OidcConfig")
+            .field("issuer", &self.issuer)
+            .field("client_id", &self.client_id)
+            .field("client_secret", &self.client_secret.as_ref().map(|_| "<redacted>"))
+            .field("redirect_uri", &self.redirect_uri)
+            .field("scopes", &self.scopes)
+            .finish()
+    }
 }
 
 /// Metrics configuration
@@ -220,6 +601,10 @@ impl Default for ServerConfig {
                 metrics_path: "/metrics".to_string(),
             },
             log_level: "info".to_string(),
+            policy_path: None,
+            cors_allowed_origins: Vec::new(),
+            oidc: None,
+            admin_token: None,
         }
     }
 }
@@ -251,6 +636,38 @@ pub enum GhostwireError {
     #[error("ACL error: {0}")]
     Acl(String),
 
+    /// ACL rule not found
+    #[error("ACL rule not found: {0}")]
+    AclRuleNotFound(Uuid),
+
+    /// ACL rule contradicts an existing rule
+    #[error("ACL rule contradicts rule {0} at the same priority")]
+    AclConflict(Uuid),
+
+    /// Peer with the same public key is already registered
+    #[error("Public key is already registered to peer {0}")]
+    PeerAlreadyRegistered(Uuid),
+
+    /// Pre-auth key not found
+    #[error("Pre-auth key not found: {0}")]
+    PreAuthKeyNotFound(Uuid),
+
+    /// API token not found
+    #[error("API token not found: {0}")]
+    ApiTokenNotFound(Uuid),
+
+    /// Request could not be authenticated
+    #[error("Authentication failed: {0}")]
+    Unauthorized(String),
+
+    /// Authenticated request asked for more than it may
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    /// Policy tests failed, so the policy was not applied
+    #[error("Policy tests failed:\n{0}")]
+    PolicyTestFailed(policy::PolicyTestReport),
+
     /// Cryptographic operation failed
     #[error("Crypto error: {0}")]
     Crypto(String),
@@ -287,6 +704,7 @@ mod tests {
             last_seen: chrono::Utc::now(),
             metadata: PeerMetadata::default(),
             acl_rules: vec![],
+            user_id: Some(Uuid::new_v4()),
         };
 
         let json = serde_json::to_string(&peer).unwrap();
@@ -295,11 +713,100 @@ mod tests {
         assert_eq!(peer, deserialized);
     }
 
+    #[test]
+    fn test_acl_rule_contradicts() {
+        let rule = |source: &str, dest: &str, action| AclRule {
+            id: Uuid::new_v4(),
+            source_cidr: source.to_string(),
+            dest_cidr: dest.to_string(),
+            action,
+            priority: 10,
+            description: None,
+            peer_id: None,
+            protocol: None,
+            ports: vec![],
+        };
+
+        let allow = rule("10.0.0.0/24", "10.0.1.0/24", AclAction::Allow);
+        assert!(allow.validate().is_ok());
+        assert!(rule("10.0.0.0/24", "fd00::/64", AclAction::Allow).validate().is_err());
+        assert!(rule("tag:ci", "fd00::/64", AclAction::Allow).validate().is_ok());
+        assert!(rule("group:eng", "*", AclAction::Allow).validate().is_err());
+        assert!(matches!(
+            rule("10.0.0.0/33", "10.0.1.0/24", AclAction::Allow).validate(),
+            Err(GhostwireError::InvalidCidr(_))
+        ));
+
+        let deny = rule("10.0.0.128/25", "10.0.0.0/16", AclAction::Deny);
+        assert!(allow.contradicts(&deny).unwrap());
+        assert!(!allow.contradicts(&rule("10.0.0.0/24", "10.0.1.0/24", AclAction::Allow)).unwrap());
+        assert!(!allow.contradicts(&rule("10.0.2.0/24", "10.0.1.0/24", AclAction::Deny)).unwrap());
+        assert!(!allow.contradicts(&AclRule { priority: 20, ..deny.clone() }).unwrap());
+        assert!(!allow.contradicts(&AclRule { peer_id: Some(Uuid::new_v4()), ..deny.clone() }).unwrap());
+
+        // Disjoint ports or protocols never meet
+        let ssh = AclRule { ports: policy::parse_ports("22").unwrap(), ..allow.clone() };
+        let web = AclRule { ports: policy::parse_ports("80,443").unwrap(), ..deny.clone() };
+        assert!(!ssh.contradicts(&web).unwrap());
+        assert!(ssh.contradicts(&deny).unwrap());
+        let udp = AclRule { protocol: Some(policy::Protocol::Udp), ..deny.clone() };
+        assert!(!AclRule { protocol: Some(policy::Protocol::Tcp), ..allow.clone() }.contradicts(&udp).unwrap());
+
+        // Tags only meet the same tag or `*`
+        let tagged = AclRule { source_cidr: "tag:ci".to_string(), ..allow.clone() };
+        assert!(!tagged.contradicts(&deny).unwrap());
+        assert!(tagged.contradicts(&AclRule { source_cidr: "*".to_string(), ..deny }).unwrap());
+    }
+
     #[test]
     fn test_default_server_config() {
         let config = ServerConfig::default();
         assert!(config.metrics_config.enabled);
         assert_eq!(config.log_level, "info");
         assert_eq!(config.network_cidr, "10.0.0.0/8");
+        assert!(config.cors_allowed_origins.is_empty());
+        assert!(config.oidc.is_none());
+    }
+
+    #[test]
+    fn test_oidc_config() {
+        let config: ServerConfig = toml::from_str(
+            r#"
+            bind_address = "0.0.0.0:8080"
+            database_path = "ghostwire.db"
+            network_cidr = "10.0.0.0/8"
+            log_level = "info"
+
+            [metrics_config]
+            enabled = false
+            metrics_path = "/metrics"
+
+            [oidc]
+            issuer = "https://login.example.com"
+            client_id = "ghostwire"
+            client_secret = "hunter2"
+            redirect_uri = "https://ghostwire.example.com/api/v1/auth/oidc/callback"
+            "#,
+        )
+        .unwrap();
+
+        let oidc = config.oidc.unwrap();
+        assert_eq!(oidc.scopes, ["email", "profile"]);
+        // The secret never reaches the logs
+        assert!(!format!("{:?}", oidc).contains("hunter2"));
+    }
+
+    #[test]
+    fn test_api_scopes() {
+        let scopes: Vec<ApiScope> = serde_json::from_str(r#"["peers:read", "acl:write"]"#).unwrap();
+        assert_eq!(scopes, [ApiScope::PeersRead, ApiScope::AclWrite]);
+        assert_eq!("admin".parse::<ApiScope>().unwrap(), ApiScope::Admin);
+        assert_eq!(ApiScope::AclWrite.to_string(), "acl:write");
+        assert!("acl:delete".parse::<ApiScope>().is_err());
+
+        assert!(ApiScope::AclWrite.grants(ApiScope::AclRead));
+        assert!(!ApiScope::AclRead.grants(ApiScope::AclWrite));
+        assert!(!ApiScope::AclWrite.grants(ApiScope::PeersRead));
+        assert!(ApiScope::Admin.grants(ApiScope::PeersWrite));
     }
 }
\ No newline at end of file
@@ -39,6 +39,13 @@ impl IpAllocator {
         Err(GhostwireError::Network("No available IP addresses".to_string()))
     }
 
+    /// Mark an IP address as taken, e.g. one restored from the database
+    ///
+    /// Returns false if the address is outside the network or already taken.
+    pub fn reserve(&mut self, ip: Ipv4Addr) -> bool {
+        self.network.contains(ip) && self.allocated.insert(ip)
+    }
+
     /// Release an IP address back to the pool
     pub fn release(&mut self, ip: Ipv4Addr) -> bool {
         self.allocated.remove(&ip)
@@ -66,26 +73,23 @@ impl IpAllocator {
     }
 }
 
+/// Parse a CIDR block, e.g. `10.0.0.0/24` or `fd00::/64`
+pub fn parse_cidr(cidr: &str) -> Result<ipnetwork::IpNetwork> {
+    cidr.parse()
+        .map_err(|_| GhostwireError::InvalidCidr(cidr.to_string()))
+}
+
 /// Check if two CIDR blocks overlap
 pub fn cidrs_overlap(cidr1: &str, cidr2: &str) -> Result<bool> {
-    let net1 = cidr1
-        .parse::<ipnetwork::IpNetwork>()
-        .map_err(|_| GhostwireError::InvalidCidr(cidr1.to_string()))?;
-
-    let net2 = cidr2
-        .parse::<ipnetwork::IpNetwork>()
-        .map_err(|_| GhostwireError::InvalidCidr(cidr2.to_string()))?;
+    let net1 = parse_cidr(cidr1)?;
+    let net2 = parse_cidr(cidr2)?;
 
-    Ok(net1.overlaps(net2))
+    Ok(net1.contains(net2.network()) || net2.contains(net1.network()))
 }
 
 /// Check if an IP address is within a CIDR block
 pub fn ip_in_cidr(ip: IpAddr, cidr: &str) -> Result<bool> {
-    let network = cidr
-        .parse::<ipnetwork::IpNetwork>()
-        .map_err(|_| GhostwireError::InvalidCidr(cidr.to_string()))?;
-
-    Ok(network.contains(ip))
+    Ok(parse_cidr(cidr)?.contains(ip))
 }
 
 #[cfg(test)]
@@ -116,6 +120,18 @@ mod tests {
         assert_eq!(ip1, ip3);
     }
 
+    #[test]
+    fn test_ip_allocator_reserve() {
+        let mut allocator = IpAllocator::new("192.168.1.0/29").unwrap();
+
+        assert!(allocator.reserve("192.168.1.1".parse().unwrap()));
+        assert!(!allocator.reserve("192.168.1.1".parse().unwrap()));
+        assert!(!allocator.reserve("10.0.0.1".parse().unwrap()));
+
+        // Reserved addresses are skipped by allocate
+        assert_eq!(allocator.allocate().unwrap(), "192.168.1.2".parse::<Ipv4Addr>().unwrap());
+    }
+
     #[test]
     fn test_cidr_overlap() {
         assert!(cidrs_overlap("192.168.1.0/24", "192.168.1.128/25").unwrap());
@@ -0,0 +1,1138 @@
+//! Tag, group, port and protocol based access policy
+//!
+//! A policy file lists groups and rules in HuJSON (JSON with comments and
+//! trailing commas) or TOML:
+//!
+//! ```text
+//! {
+//!   "groups": { "group:backend": ["tag:api", "tag:worker"] },
+//!   "acls": [
+//!     // CI may reach the databases over Postgres
+//!     { "action": "accept", "src": ["tag:ci"], "dst": ["tag:db:5432"], "proto": "tcp" },
+//!     { "action": "accept", "src": ["group:backend"], "dst": ["10.0.5.0/24:80,443"] },
+//!   ],
+//!   "tests": [
+//!     { "src": "tag:ci", "accept": ["tag:db:5432"], "deny": ["tag:db:22"] },
+//!   ],
+//! }
+//! ```
+//!
+//! [`Policy::to_acl_rules`] expands it into [`AclRule`]s, whose sources and
+//! destinations may name peers by tag. [`resolve_rules`] turns those into
+//! CIDR-only rules for [`AclEngine`], and [`compile_filters`] into the packet
+//! filter each peer enforces. [`Policy::run_tests`] checks the embedded tests
+//! against the rules before they are enforced.
+
+use crate::acl::{AclDecision, AclEngine};
+use crate::{network::parse_cidr, AclAction, AclRule, GhostwireError, PeerInfo, Result};
+use ipnetwork::IpNetwork;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::net::IpAddr;
+use std::path::Path;
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// IP protocol a rule applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Protocol {
+    Tcp,
+    Udp,
+    Icmp,
+    /// Any other protocol by IANA number
+    Number(u8),
+}
+
+impl Protocol {
+    /// IANA protocol number
+    pub fn number(self) -> u8 {
+        match self {
+            Protocol::Tcp => 6,
+            Protocol::Udp => 17,
+            Protocol::Icmp => 1,
+            Protocol::Number(number) => number,
+        }
+    }
+
+    /// Whether the protocol has ports
+    pub fn has_ports(self) -> bool {
+        matches!(self.number(), 6 | 17)
+    }
+}
+
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Protocol::Tcp => f.write_str("tcp"),
+            Protocol::Udp => f.write_str("udp"),
+            Protocol::Icmp => f.write_str("icmp"),
+            Protocol::Number(number) => write!(f, "{}", number),
+        }
+    }
+}
+
+impl FromStr for Protocol {
+    type Err = GhostwireError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "tcp" => Ok(Protocol::Tcp),
+            "udp" => Ok(Protocol::Udp),
+            "icmp" => Ok(Protocol::Icmp),
+            other => match other.parse::<u8>() {
+                Ok(6) => Ok(Protocol::Tcp),
+                Ok(17) => Ok(Protocol::Udp),
+                Ok(1) => Ok(Protocol::Icmp),
+                Ok(number) => Ok(Protocol::Number(number)),
+                Err(_) => Err(GhostwireError::Acl(format!("unknown protocol: {}", s))),
+            },
+        }
+    }
+}
+
+/// Inclusive range of ports, written `22` or `8000-8080`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PortRange {
+    pub first: u16,
+    pub last: u16,
+}
+
+impl PortRange {
+    /// Whether `port` is in the range
+    pub fn contains(&self, port: u16) -> bool {
+        self.first <= port && port <= self.last
+    }
+
+    /// Whether the ranges share a port
+    pub fn overlaps(&self, other: &PortRange) -> bool {
+        self.first <= other.last && other.first <= self.last
+    }
+}
+
+impl fmt::Display for PortRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.first == self.last {
+            write!(f, "{}", self.first)
+        } else {
+            write!(f, "{}-{}", self.first, self.last)
+        }
+    }
+}
+
+impl FromStr for PortRange {
+    type Err = GhostwireError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || GhostwireError::Acl(format!("invalid port range: {}", s));
+        let (first, last) = s.split_once('-').unwrap_or((s, s));
+        let first: u16 = first.trim().parse().map_err(|_| invalid())?;
+        let last: u16 = last.trim().parse().map_err(|_| invalid())?;
+        if first > last {
+            return Err(invalid());
+        }
+        Ok(PortRange { first, last })
+    }
+}
+
+macro_rules! serde_via_str {
+    ($ty:ty) => {
+        impl Serialize for $ty {
+            fn serialize<S: Serializer>(
+                &self,
+                serializer: S,
+            ) -> std::result::Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $ty {
+            fn deserialize<D: Deserializer<'de>>(
+                deserializer: D,
+            ) -> std::result::Result<Self, D::Error> {
+                let s = String::deserialize(deserializer)?;
+                s.parse().map_err(serde::de::Error::custom)
+            }
+        }
+    };
+}
+
+serde_via_str!(Protocol);
+serde_via_str!(PortRange);
+
+/// Parse `*` (all ports) or a comma-separated list of port ranges
+pub fn parse_ports(s: &str) -> Result<Vec<PortRange>> {
+    if s.trim() == "*" || s.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    s.split(',').map(str::parse).collect()
+}
+
+/// Write port ranges in the form [`parse_ports`] reads, `*` when empty
+pub fn format_ports(ports: &[PortRange]) -> String {
+    if ports.is_empty() {
+        return "*".to_string();
+    }
+    ports
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// The peers or addresses a rule's source or destination refers to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selector {
+    /// Every address, written `*`
+    Any,
+    /// An address block, or a single address written without a prefix
+    Cidr(IpNetwork),
+    /// Peers with a tag, written `tag:ci` or `tag:env=prod` to also match the value
+    Tag { name: String, value: Option<String> },
+    /// A peer by name or ID, written `peer:laptop`
+    Peer(String),
+    /// A group from the policy file, written `group:backend`
+    Group(String),
+}
+
+impl Selector {
+    /// Whether the selector covers `peer`
+    pub fn matches(&self, peer: &PolicyPeer) -> bool {
+        match self {
+            Selector::Any => true,
+            Selector::Cidr(network) => network.contains(peer.address),
+            Selector::Tag { name, value } => match (peer.tags.get(name), value) {
+                (Some(_), None) => true,
+                (Some(actual), Some(expected)) => actual == expected,
+                (None, _) => false,
+            },
+            Selector::Peer(name) => {
+                peer.id.to_string() == *name || peer.name.as_deref() == Some(name.as_str())
+            }
+            // Groups are expanded before rules are stored
+            Selector::Group(_) => false,
+        }
+    }
+
+    /// Address blocks the selector stands for, given the current peers
+    pub fn resolve(&self, peers: &[PolicyPeer]) -> Vec<IpNetwork> {
+        match self {
+            Selector::Any => vec![
+                "0.0.0.0/0".parse().expect("valid network"),
+                "::/0".parse().expect("valid network"),
+            ],
+            Selector::Cidr(network) => vec![*network],
+            _ => peers
+                .iter()
+                .filter(|peer| self.matches(peer))
+                .map(|peer| IpNetwork::from(peer.address))
+                .collect(),
+        }
+    }
+
+    /// Whether the selectors may cover the same address
+    ///
+    /// Tags and peer names are compared by name only, since the peers they
+    /// cover change as peers are updated.
+    pub fn overlaps(&self, other: &Selector) -> bool {
+        match (self, other) {
+            (Selector::Any, _) | (_, Selector::Any) => true,
+            (Selector::Cidr(a), Selector::Cidr(b)) => {
+                a.contains(b.network()) || b.contains(a.network())
+            }
+            (a, b) => a == b,
+        }
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Selector::Any => f.write_str("*"),
+            Selector::Cidr(network) => write!(f, "{}", network),
+            Selector::Tag { name, value: None } => write!(f, "tag:{}", name),
+            Selector::Tag {
+                name,
+                value: Some(value),
+            } => write!(f, "tag:{}={}", name, value),
+            Selector::Peer(name) => write!(f, "peer:{}", name),
+            Selector::Group(name) => write!(f, "group:{}", name),
+        }
+    }
+}
+
+impl FromStr for Selector {
+    type Err = GhostwireError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let named = |kind: &str, name: &str| {
+            if name.is_empty() || name.contains(':') {
+                Err(GhostwireError::Acl(format!(
+                    "invalid {} name in {}",
+                    kind, s
+                )))
+            } else {
+                Ok(name.to_string())
+            }
+        };
+
+        if s == "*" {
+            Ok(Selector::Any)
+        } else if let Some(tag) = s.strip_prefix("tag:") {
+            let (name, value) = match tag.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (tag, None),
+            };
+            Ok(Selector::Tag {
+                name: named("tag", name)?,
+                value,
+            })
+        } else if let Some(name) = s.strip_prefix("peer:") {
+            Ok(Selector::Peer(named("peer", name)?))
+        } else if let Some(name) = s.strip_prefix("group:") {
+            Ok(Selector::Group(named("group", name)?))
+        } else if let Ok(ip) = s.parse::<IpAddr>() {
+            Ok(Selector::Cidr(ip.into()))
+        } else {
+            parse_cidr(s).map(Selector::Cidr)
+        }
+    }
+}
+
+/// What rules need to know about a peer
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolicyPeer {
+    pub id: Uuid,
+    pub name: Option<String>,
+    pub address: IpAddr,
+    pub tags: HashMap<String, String>,
+}
+
+impl PolicyPeer {
+    /// Describe a peer at its assigned address
+    pub fn new(peer: &PeerInfo, address: IpAddr) -> Self {
+        Self {
+            id: peer.id,
+            name: peer.metadata.name.clone(),
+            address,
+            tags: peer.metadata.tags.clone(),
+        }
+    }
+}
+
+/// A policy file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Policy {
+    /// Named lists of selectors, keyed `group:<name>`
+    #[serde(default)]
+    pub groups: BTreeMap<String, Vec<String>>,
+    /// Rules in order
+    #[serde(default)]
+    pub acls: Vec<PolicyRule>,
+    /// Assertions the rules must satisfy before they are applied
+    #[serde(default)]
+    pub tests: Vec<PolicyTest>,
+}
+
+/// One rule of a policy file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyRule {
+    /// `accept`/`allow` or `deny`
+    pub action: AclAction,
+    /// Sources: selectors or groups
+    pub src: Vec<String>,
+    /// Destinations as `selector:ports`, e.g. `tag:db:5432` or `*:*`;
+    /// IPv6 addresses are bracketed, `[fd00::/64]:22`
+    pub dst: Vec<String>,
+    /// Protocol, all protocols when left out
+    #[serde(default)]
+    pub proto: Option<Protocol>,
+    /// Higher values take precedence
+    #[serde(default)]
+    pub priority: i32,
+    /// Optional description
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// An assertion about what a source may reach
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyTest {
+    /// Source: an address, a tag, peer or group selector
+    pub src: String,
+    /// Protocol of the test packets, TCP when left out
+    #[serde(default)]
+    pub proto: Option<Protocol>,
+    /// Destinations as `host:port` that must be accepted
+    #[serde(default)]
+    pub accept: Vec<String>,
+    /// Destinations as `host:port` that must be denied
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+impl Policy {
+    /// Parse HuJSON: JSON with `//` and `/* */` comments and trailing commas
+    pub fn from_hujson(s: &str) -> Result<Self> {
+        Ok(serde_json::from_str(&standardize_hujson(s))?)
+    }
+
+    /// Parse TOML, with rules as `[[acls]]` tables
+    pub fn from_toml(s: &str) -> Result<Self> {
+        toml::from_str(s).map_err(|e| GhostwireError::Acl(format!("invalid policy: {}", e)))
+    }
+
+    /// Read a policy file, as TOML if it ends in `.toml` and HuJSON otherwise
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            GhostwireError::Config(format!("cannot read {}: {}", path.display(), e))
+        })?;
+        if path.extension().is_some_and(|ext| ext == "toml") {
+            Self::from_toml(&content)
+        } else {
+            Self::from_hujson(&content)
+        }
+    }
+
+    /// Expand groups and destinations into one rule per source and destination
+    ///
+    /// Fails on unknown or nested groups, malformed selectors, ports and
+    /// protocols, and on expanded rules that contradict each other.
+    pub fn to_acl_rules(&self) -> Result<Vec<AclRule>> {
+        for (name, members) in &self.groups {
+            if !name.starts_with("group:") {
+                return Err(GhostwireError::Acl(format!(
+                    "group {} must be named group:<name>",
+                    name
+                )));
+            }
+            for member in members {
+                if matches!(member.parse::<Selector>()?, Selector::Group(_)) {
+                    return Err(GhostwireError::Acl(format!(
+                        "group {} cannot contain group {}",
+                        name, member
+                    )));
+                }
+            }
+        }
+
+        let mut rules = Vec::new();
+        for (index, policy_rule) in self.acls.iter().enumerate() {
+            let context =
+                |e: GhostwireError| GhostwireError::Acl(format!("acls[{}]: {}", index, e));
+            if policy_rule.src.is_empty() || policy_rule.dst.is_empty() {
+                return Err(context(GhostwireError::Acl(
+                    "src and dst must not be empty".into(),
+                )));
+            }
+
+            let mut sources = Vec::new();
+            for src in &policy_rule.src {
+                sources.extend(self.expand(src).map_err(context)?);
+            }
+            let mut dests = Vec::new();
+            for dst in &policy_rule.dst {
+                let (selector, ports) = split_destination(dst).map_err(context)?;
+                let ports = parse_ports(ports).map_err(context)?;
+                for dest in self.expand(selector).map_err(context)? {
+                    dests.push((dest, ports.clone()));
+                }
+            }
+
+            for source in &sources {
+                for (dest, ports) in &dests {
+                    let rule = AclRule {
+                        id: Uuid::new_v4(),
+                        source_cidr: source.to_string(),
+                        dest_cidr: dest.to_string(),
+                        action: policy_rule.action.clone(),
+                        priority: policy_rule.priority,
+                        description: policy_rule.description.clone(),
+                        peer_id: None,
+                        protocol: policy_rule.proto,
+                        ports: ports.clone(),
+                    };
+                    rule.validate().map_err(context)?;
+                    rules.push(rule);
+                }
+            }
+        }
+
+        for (i, rule) in rules.iter().enumerate() {
+            for other in &rules[..i] {
+                if rule.contradicts(other)? {
+                    return Err(GhostwireError::Acl(format!(
+                        "{} -> {} contradicts {} -> {} at priority {}",
+                        rule.source_cidr,
+                        rule.dest_cidr,
+                        other.source_cidr,
+                        other.dest_cidr,
+                        rule.priority
+                    )));
+                }
+            }
+        }
+        Ok(rules)
+    }
+
+    /// Run the embedded tests against `rules`, usually this policy's rules
+    /// plus any peer-scoped ones already stored
+    ///
+    /// Selectors in tests match `peers`; an address stands for itself. Fails
+    /// on malformed tests; failed assertions and selectors that match no peer
+    /// are collected in the report.
+    pub fn run_tests(&self, rules: &[AclRule], peers: &[PolicyPeer]) -> Result<PolicyTestReport> {
+        let engine = AclEngine::compile(&resolve_rules(rules, peers)?)?;
+        let mut report = PolicyTestReport::default();
+
+        for (index, test) in self.tests.iter().enumerate() {
+            let context =
+                |e: GhostwireError| GhostwireError::Acl(format!("tests[{}]: {}", index, e));
+            let protocol = test.proto.unwrap_or(Protocol::Tcp);
+
+            let sources = self.test_addresses(&test.src, peers).map_err(context)?;
+            if sources.is_empty() {
+                report
+                    .unresolved
+                    .push(format!("tests[{}]: {} matches no peer", index, test.src));
+                continue;
+            }
+
+            let expectations = test
+                .accept
+                .iter()
+                .map(|dst| (AclAction::Allow, dst))
+                .chain(test.deny.iter().map(|dst| (AclAction::Deny, dst)));
+            for (expected, dst) in expectations {
+                let (selector, port) = split_destination(dst).map_err(context)?;
+                let port: u16 = port.parse().map_err(|_| {
+                    context(GhostwireError::Acl(format!(
+                        "destination {} needs a single port",
+                        dst
+                    )))
+                })?;
+                let dests = self.test_addresses(selector, peers).map_err(context)?;
+                if dests.is_empty() {
+                    report
+                        .unresolved
+                        .push(format!("tests[{}]: {} matches no peer", index, selector));
+                    continue;
+                }
+
+                for &source in &sources {
+                    for &dest in dests
+                        .iter()
+                        .filter(|dest| dest.is_ipv4() == source.is_ipv4())
+                    {
+                        let actual = engine.evaluate_packet(
+                            source,
+                            dest,
+                            protocol,
+                            protocol.has_ports().then_some(port),
+                        );
+                        if actual.action != expected {
+                            report.failures.push(TestFailure {
+                                test: index,
+                                src: test.src.clone(),
+                                dst: dst.clone(),
+                                source,
+                                dest,
+                                protocol,
+                                port,
+                                expected: expected.clone(),
+                                actual,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Addresses a test source or destination stands for
+    fn test_addresses(&self, selector: &str, peers: &[PolicyPeer]) -> Result<Vec<IpAddr>> {
+        let mut addresses = Vec::new();
+        for selector in self.expand(selector)? {
+            match selector {
+                Selector::Any => {
+                    return Err(GhostwireError::Acl(
+                        "tests need concrete hosts, not *".into(),
+                    ))
+                }
+                Selector::Cidr(network) => addresses.push(network.ip()),
+                other => addresses.extend(
+                    peers
+                        .iter()
+                        .filter(|peer| other.matches(peer))
+                        .map(|peer| peer.address),
+                ),
+            }
+        }
+        Ok(addresses)
+    }
+
+    /// A source or destination selector with groups replaced by their members
+    fn expand(&self, selector: &str) -> Result<Vec<Selector>> {
+        match selector.parse()? {
+            Selector::Group(_) => self
+                .groups
+                .get(selector)
+                .ok_or_else(|| GhostwireError::Acl(format!("unknown group {}", selector)))?
+                .iter()
+                .map(|member| member.parse())
+                .collect(),
+            other => Ok(vec![other]),
+        }
+    }
+}
+
+/// Split `selector:ports`, allowing `[ipv6]:ports`
+fn split_destination(dst: &str) -> Result<(&str, &str)> {
+    let missing =
+        || GhostwireError::Acl(format!("destination {} needs ports, e.g. {}:*", dst, dst));
+    if let Some(rest) = dst.strip_prefix('[') {
+        let (selector, ports) = rest.split_once(']').ok_or_else(missing)?;
+        let ports = ports.strip_prefix(':').ok_or_else(missing)?;
+        return Ok((selector, ports));
+    }
+    match dst.rsplit_once(':') {
+        // `tag:db` is a selector without ports, not host `tag` on port `db`
+        Some((selector, ports)) if !matches!(selector, "tag" | "peer" | "group") => {
+            Ok((selector, ports))
+        }
+        _ => Err(missing()),
+    }
+}
+
+/// Turn HuJSON into plain JSON by dropping comments and trailing commas
+fn standardize_hujson(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    let mut in_string = false;
+    // A comma is held back until the next token shows it is not trailing
+    let mut pending_comma = false;
+
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => out.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '/' if chars.peek() == Some(&'/') => while chars.next_if(|&c| c != '\n').is_some() {},
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+            }
+            ',' => {
+                if pending_comma {
+                    out.push(',');
+                }
+                pending_comma = true;
+            }
+            c if c.is_whitespace() => out.push(c),
+            c => {
+                if pending_comma && c != '}' && c != ']' {
+                    out.push(',');
+                }
+                pending_comma = false;
+                if c == '"' {
+                    in_string = true;
+                }
+                out.push(c);
+            }
+        }
+    }
+    if pending_comma {
+        out.push(',');
+    }
+    out
+}
+
+/// Replace selectors with CIDR blocks so [`AclEngine`] can compile the rules
+///
+/// A rule expands into one rule per source and destination block of the
+/// same address family, keeping its ID. Peer-scoped rules only cover
+/// traffic to their peer.
+pub fn resolve_rules(rules: &[AclRule], peers: &[PolicyPeer]) -> Result<Vec<AclRule>> {
+    let mut resolved = Vec::new();
+    for rule in rules {
+        let sources = rule.source_cidr.parse::<Selector>()?.resolve(peers);
+        let mut dests = rule.dest_cidr.parse::<Selector>()?.resolve(peers);
+        if let Some(owner) = rule.peer_id {
+            let owner = peers.iter().find(|peer| peer.id == owner);
+            dests = match owner {
+                Some(owner) if dests.iter().any(|dest| dest.contains(owner.address)) => {
+                    vec![IpNetwork::from(owner.address)]
+                }
+                _ => Vec::new(),
+            };
+        }
+
+        for source in &sources {
+            for dest in dests
+                .iter()
+                .filter(|dest| dest.is_ipv4() == source.is_ipv4())
+            {
+                resolved.push(AclRule {
+                    source_cidr: source.to_string(),
+                    dest_cidr: dest.to_string(),
+                    ..rule.clone()
+                });
+            }
+        }
+    }
+    Ok(resolved)
+}
+
+/// Outcome of [`Policy::run_tests`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PolicyTestReport {
+    /// Assertions the rules break
+    pub failures: Vec<TestFailure>,
+    /// Test selectors that matched no peer, so nothing was checked
+    pub unresolved: Vec<String>,
+}
+
+/// A test packet decided differently than the policy expects
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestFailure {
+    /// Index into [`Policy::tests`]
+    pub test: usize,
+    pub src: String,
+    pub dst: String,
+    pub source: IpAddr,
+    pub dest: IpAddr,
+    pub protocol: Protocol,
+    pub port: u16,
+    pub expected: AclAction,
+    pub actual: AclDecision,
+}
+
+impl PolicyTestReport {
+    /// Whether every assertion was checked and held
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty() && self.unresolved.is_empty()
+    }
+}
+
+impl fmt::Display for PolicyTestReport {
+    /// Expected and actual decisions as a unified diff
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verb = |action: &AclAction| match action {
+            AclAction::Allow => "accept",
+            AclAction::Deny => "deny",
+        };
+
+        if !self.failures.is_empty() {
+            writeln!(f, "--- expected")?;
+            writeln!(f, "+++ actual")?;
+        }
+        for failure in &self.failures {
+            let packet = format!(
+                "{} {} -> {}",
+                failure.protocol,
+                failure.source,
+                std::net::SocketAddr::new(failure.dest, failure.port)
+            );
+            let rule = match failure.actual.rule_id {
+                Some(id) => format!("rule {}", id),
+                None => "no rule matched".to_string(),
+            };
+            writeln!(
+                f,
+                "@@ tests[{}]: {} -> {} @@",
+                failure.test, failure.src, failure.dst
+            )?;
+            writeln!(f, "-{:<6} {}", verb(&failure.expected), packet)?;
+            writeln!(
+                f,
+                "+{:<6} {} ({})",
+                verb(&failure.actual.action),
+                packet,
+                rule
+            )?;
+        }
+        for unresolved in &self.unresolved {
+            writeln!(f, "? {}", unresolved)?;
+        }
+        Ok(())
+    }
+}
+
+/// The rules a peer enforces on traffic it receives
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PacketFilter {
+    /// Peer enforcing the filter
+    pub peer_id: Uuid,
+    /// Rules in evaluation order: first match wins, no match denies
+    pub rules: Vec<FilterRule>,
+}
+
+/// A rule of a [`PacketFilter`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FilterRule {
+    /// Rule it was compiled from
+    pub rule_id: Uuid,
+    pub action: AclAction,
+    /// Source address blocks
+    pub sources: Vec<String>,
+    /// Protocol, all protocols when `None`
+    pub protocol: Option<Protocol>,
+    /// Destination ports, all ports when empty
+    pub ports: Vec<PortRange>,
+}
+
+impl FilterRule {
+    fn matches(&self, source: IpAddr, protocol: Protocol, port: Option<u16>) -> bool {
+        self.sources
+            .iter()
+            .any(|cidr| parse_cidr(cidr).is_ok_and(|network| network.contains(source)))
+            && self
+                .protocol
+                .is_none_or(|p| p.number() == protocol.number())
+            && (self.ports.is_empty()
+                || port.is_some_and(|port| self.ports.iter().any(|r| r.contains(port))))
+    }
+}
+
+impl PacketFilter {
+    /// Decide an inbound packet, denying when no rule matches
+    pub fn evaluate(&self, source: IpAddr, protocol: Protocol, port: Option<u16>) -> AclDecision {
+        match self
+            .rules
+            .iter()
+            .find(|rule| rule.matches(source, protocol, port))
+        {
+            Some(rule) => AclDecision {
+                action: rule.action.clone(),
+                rule_id: Some(rule.rule_id),
+            },
+            None => AclDecision {
+                action: AclAction::Deny,
+                rule_id: None,
+            },
+        }
+    }
+}
+
+/// Compile the packet filter of every peer
+///
+/// A peer's filter holds the rules whose destination covers it, highest
+/// priority first and peer-scoped rules before network-wide ones at equal
+/// priority, with sources resolved to address blocks. Rules whose sources
+/// match nothing are left out.
+pub fn compile_filters(rules: &[AclRule], peers: &[PolicyPeer]) -> Result<Vec<PacketFilter>> {
+    let mut ordered: Vec<(usize, &AclRule)> = rules.iter().enumerate().collect();
+    ordered.sort_by_key(|(index, rule)| {
+        (
+            std::cmp::Reverse((rule.priority, rule.peer_id.is_some())),
+            *index,
+        )
+    });
+
+    let mut compiled = Vec::with_capacity(ordered.len());
+    for (_, rule) in ordered {
+        let dest: Selector = rule.dest_cidr.parse()?;
+        let sources: Vec<String> = rule
+            .source_cidr
+            .parse::<Selector>()?
+            .resolve(peers)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        compiled.push((rule, dest, sources));
+    }
+
+    Ok(peers
+        .iter()
+        .map(|peer| PacketFilter {
+            peer_id: peer.id,
+            rules: compiled
+                .iter()
+                .filter(|(rule, dest, sources)| {
+                    !sources.is_empty()
+                        && dest.matches(peer)
+                        && rule.peer_id.is_none_or(|owner| owner == peer.id)
+                })
+                .map(|(rule, _, sources)| FilterRule {
+                    rule_id: rule.id,
+                    action: rule.action.clone(),
+                    sources: sources.clone(),
+                    protocol: rule.protocol,
+                    ports: rule.ports.clone(),
+                })
+                .collect(),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: &str = r#"
+        // Databases only talk Postgres, and only to CI and the backend
+        {
+          "groups": {
+            "group:backend": ["tag:api", "peer:worker-1"], /* trailing comma -> */
+          },
+          "acls": [
+            { "action": "accept", "src": ["tag:ci", "group:backend"], "dst": ["tag:db:5432"], "proto": "tcp" },
+            { "action": "deny", "src": ["*"], "dst": ["tag:db:*"], "priority": -1 },
+            { "action": "accept", "src": ["fd00:1::/48"], "dst": ["[fd00::/64]:22,8000-8080"], "description": "a // b" },
+          ],
+        }
+    "#;
+
+    fn peer(name: &str, address: &str, tags: &[&str]) -> PolicyPeer {
+        PolicyPeer {
+            id: Uuid::new_v4(),
+            name: Some(name.to_string()),
+            address: address.parse().unwrap(),
+            tags: tags
+                .iter()
+                .map(|tag| (tag.to_string(), String::new()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_parse_selectors_and_ports() {
+        assert_eq!("*".parse::<Selector>().unwrap(), Selector::Any);
+        assert_eq!(
+            "tag:env=prod".parse::<Selector>().unwrap(),
+            Selector::Tag {
+                name: "env".into(),
+                value: Some("prod".into())
+            }
+        );
+        assert_eq!(
+            "10.0.0.1".parse::<Selector>().unwrap().to_string(),
+            "10.0.0.1/32"
+        );
+        assert!("tag:".parse::<Selector>().is_err());
+        assert!("laptop".parse::<Selector>().is_err());
+
+        let ports = parse_ports("22,8000-8080").unwrap();
+        assert_eq!(format_ports(&ports), "22,8000-8080");
+        assert!(parse_ports("*").unwrap().is_empty());
+        assert!(parse_ports("80-22").is_err());
+        assert!(parse_ports("70000").is_err());
+    }
+
+    #[test]
+    fn test_policy_formats_agree() {
+        let hujson = Policy::from_hujson(POLICY).unwrap();
+        assert_eq!(hujson.acls.len(), 3);
+        assert_eq!(hujson.acls[0].proto, Some(Protocol::Tcp));
+        assert_eq!(hujson.acls[2].description.as_deref(), Some("a // b"));
+
+        let toml = Policy::from_toml(
+            r#"
+            [groups]
+            "group:backend" = ["tag:api", "peer:worker-1"]
+
+            [[acls]]
+            action = "accept"
+            src = ["tag:ci", "group:backend"]
+            dst = ["tag:db:5432"]
+            proto = "tcp"
+
+            [[acls]]
+            action = "deny"
+            src = ["*"]
+            dst = ["tag:db:*"]
+            priority = -1
+
+            [[acls]]
+            action = "accept"
+            src = ["fd00:1::/48"]
+            dst = ["[fd00::/64]:22,8000-8080"]
+            description = "a // b"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(toml, hujson);
+    }
+
+    #[test]
+    fn test_policy_expands_groups() {
+        let rules = Policy::from_hujson(POLICY).unwrap().to_acl_rules().unwrap();
+        let summary: Vec<_> = rules
+            .iter()
+            .map(|r| {
+                (
+                    r.source_cidr.as_str(),
+                    r.dest_cidr.as_str(),
+                    format_ports(&r.ports),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("tag:ci", "tag:db", "5432".to_string()),
+                ("tag:api", "tag:db", "5432".to_string()),
+                ("peer:worker-1", "tag:db", "5432".to_string()),
+                ("*", "tag:db", "*".to_string()),
+                ("fd00:1::/48", "fd00::/64", "22,8000-8080".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_policy_errors() {
+        let check = |policy: &str| {
+            Policy::from_hujson(policy)
+                .and_then(|p| p.to_acl_rules())
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert!(check(
+            r#"{ "acls": [{ "action": "accept", "src": ["group:nope"], "dst": ["*:*"] }] }"#
+        )
+        .contains("unknown group group:nope"));
+        assert!(
+            check(r#"{ "acls": [{ "action": "accept", "src": ["*"], "dst": ["tag:db"] }] }"#)
+                .contains("needs ports")
+        );
+        assert!(check(r#"{ "acls": [{ "action": "accept", "src": ["*"], "dst": ["*:22"], "proto": "icmp" }] }"#)
+            .contains("ports"));
+        assert!(
+            check(r#"{ "groups": { "group:a": ["group:b"] }, "acls": [] }"#)
+                .contains("cannot contain")
+        );
+        assert!(check(
+            r#"{ "acls": [
+                { "action": "accept", "src": ["tag:ci"], "dst": ["tag:db:5432"] },
+                { "action": "deny", "src": ["tag:ci"], "dst": ["tag:db:5000-6000"] },
+            ] }"#
+        )
+        .contains("contradicts"));
+    }
+
+    #[test]
+    fn test_run_tests() {
+        let peers = [
+            peer("db-1", "10.0.0.10", &["db"]),
+            peer("ci-1", "10.0.0.20", &["ci"]),
+            peer("worker-1", "10.0.0.30", &[]),
+        ];
+        let mut policy = Policy::from_hujson(POLICY).unwrap();
+        policy.tests = Policy::from_hujson(
+            r#"{ "tests": [
+                { "src": "group:backend", "accept": ["tag:db:5432"], "deny": ["tag:db:22", "10.0.0.10:5433"] },
+                { "src": "10.0.0.99", "deny": ["db-1:5432", "peer:db-1:5432"] },
+            ] }"#,
+        )
+        .unwrap()
+        .tests;
+        // `db-1` is an address-less host name, so it is rejected
+        assert!(policy
+            .run_tests(&policy.to_acl_rules().unwrap(), &peers)
+            .is_err());
+
+        policy.tests[1].deny.remove(0);
+        let rules = policy.to_acl_rules().unwrap();
+        let report = policy.run_tests(&rules, &peers).unwrap();
+        assert!(report.passed(), "{}", report);
+
+        // The laptop is denied too, so asserting it is accepted fails
+        policy.tests[1].accept.push("tag:db:5432".into());
+        policy.tests.push(PolicyTest {
+            src: "tag:nope".into(),
+            proto: None,
+            accept: vec!["10.0.0.10:80".into()],
+            deny: vec![],
+        });
+        let report = policy.run_tests(&rules, &peers).unwrap();
+        assert!(!report.passed());
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures[0].actual.rule_id, Some(rules[3].id));
+        assert_eq!(
+            report.to_string(),
+            format!(
+                "--- expected\n+++ actual\n@@ tests[1]: 10.0.0.99 -> tag:db:5432 @@\n\
+                 -accept tcp 10.0.0.99 -> 10.0.0.10:5432\n\
+                 +deny   tcp 10.0.0.99 -> 10.0.0.10:5432 (rule {})\n\
+                 ? tests[2]: tag:nope matches no peer\n",
+                rules[3].id
+            )
+        );
+    }
+
+    #[test]
+    fn test_compile_filters() {
+        let rules = Policy::from_hujson(POLICY).unwrap().to_acl_rules().unwrap();
+        let peers = [
+            peer("db-1", "10.0.0.10", &["db"]),
+            peer("ci-1", "10.0.0.20", &["ci"]),
+            peer("worker-1", "10.0.0.30", &[]),
+            peer("laptop", "10.0.0.40", &[]),
+        ];
+
+        let filters = compile_filters(&rules, &peers).unwrap();
+        let db = &filters[0];
+        assert_eq!(db.rules.len(), 3); // tag:api matches no peer
+        assert_eq!(db.rules[0].sources, ["10.0.0.20/32"]);
+
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+        assert!(db
+            .evaluate(ip("10.0.0.20"), Protocol::Tcp, Some(5432))
+            .allowed());
+        assert!(db
+            .evaluate(ip("10.0.0.30"), Protocol::Tcp, Some(5432))
+            .allowed());
+        assert!(!db
+            .evaluate(ip("10.0.0.20"), Protocol::Udp, Some(5432))
+            .allowed());
+        assert!(!db
+            .evaluate(ip("10.0.0.20"), Protocol::Tcp, Some(22))
+            .allowed());
+        let laptop = db.evaluate(ip("10.0.0.40"), Protocol::Tcp, Some(5432));
+        assert_eq!(laptop.rule_id, Some(rules[3].id));
+
+        // Nothing targets the laptop, so it accepts nothing
+        assert!(filters[3].rules.is_empty());
+    }
+
+    #[test]
+    fn test_resolve_rules() {
+        let peers = [
+            peer("db-1", "10.0.0.10", &["db"]),
+            peer("db-2", "10.0.0.11", &["db"]),
+        ];
+        let rule = AclRule {
+            id: Uuid::new_v4(),
+            source_cidr: "*".to_string(),
+            dest_cidr: "tag:db".to_string(),
+            action: AclAction::Allow,
+            priority: 0,
+            description: None,
+            peer_id: Some(peers[1].id),
+            protocol: None,
+            ports: vec![],
+        };
+
+        // Any resolves to both families, but only the IPv4 half pairs with the
+        // IPv4 peer, and the peer scope narrows the destination to its owner
+        let resolved = resolve_rules(std::slice::from_ref(&rule), &peers).unwrap();
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].source_cidr, "0.0.0.0/0");
+        assert_eq!(resolved[0].dest_cidr, "10.0.0.11/32");
+        assert_eq!(resolved[0].id, rule.id);
+    }
+}
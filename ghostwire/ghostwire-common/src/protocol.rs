@@ -1,6 +1,7 @@
 //! Protocol definitions for Ghostwire
 
 use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
 use uuid::Uuid;
 
 /// API version
@@ -21,6 +22,8 @@ pub enum WebSocketMessage {
     HealthCheck(crate::HealthCheck),
     /// Topology update
     TopologyUpdate(crate::NetworkTopology),
+    /// Topology changes since the last update
+    TopologyDelta(crate::TopologyDelta),
     /// Peer disconnected
     PeerDisconnected { peer_id: Uuid },
     /// Error message
@@ -38,6 +41,9 @@ pub mod endpoints {
     /// Peer registration endpoint
     pub const REGISTER_PEER: &str = const_format::concatcp!(API_BASE_PATH, "/peers/register");
 
+    /// Registration challenge for a node to sign
+    pub const REGISTER_CHALLENGE: &str = const_format::concatcp!(API_BASE_PATH, "/peers/challenge");
+
     /// Get peer information
     pub const GET_PEER: &str = const_format::concatcp!(API_BASE_PATH, "/peers/{id}");
 
@@ -65,6 +71,18 @@ pub mod endpoints {
     /// Delete ACL rule
     pub const DELETE_ACL_RULE: &str = const_format::concatcp!(API_BASE_PATH, "/acl/rules/{id}");
 
+    /// List or mint pre-auth keys
+    pub const PRE_AUTH_KEYS: &str = const_format::concatcp!(API_BASE_PATH, "/auth/keys");
+
+    /// Revoke a pre-auth key
+    pub const REVOKE_PRE_AUTH_KEY: &str = const_format::concatcp!(API_BASE_PATH, "/auth/keys/{id}");
+
+    /// Redirect target of the OIDC provider after a user logs in
+    pub const OIDC_CALLBACK: &str = const_format::concatcp!(API_BASE_PATH, "/auth/oidc/callback");
+
+    /// List users who joined peers through OIDC login
+    pub const LIST_USERS: &str = const_format::concatcp!(API_BASE_PATH, "/users");
+
     /// Health check endpoint
     pub const HEALTH: &str = const_format::concatcp!(API_BASE_PATH, "/health");
 
@@ -188,10 +206,36 @@ impl PaginationParams {
 
     /// Get page size with default and bounds checking
     pub fn page_size(&self) -> u32 {
-        self.page_size.unwrap_or(50).min(1000).max(1)
+        self.page_size.unwrap_or(50).clamp(1, 1000)
     }
 }
 
+/// Query parameters the OIDC provider redirects back with
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct OidcCallbackParams {
+    /// Authorization code, absent when the login failed
+    pub code: Option<String>,
+    pub state: String,
+    /// Error code from the provider
+    pub error: Option<String>,
+}
+
+/// Query parameters for topology requests
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TopologyParams {
+    /// Generation the client already has; omitted for the whole topology
+    pub since: Option<u64>,
+    /// Peer whose view to return; omitted for the whole network
+    pub peer_id: Option<Uuid>,
+}
+
+/// Query parameters for listing ACL rules
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AclRuleParams {
+    /// Address the listed rules' sources must contain; omitted for every rule
+    pub source: Option<IpAddr>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,127 @@
+-- Coordination server schema, run at startup with execute_script and
+-- snapshotted for the query macros with `cargo zqlite prepare schema/coordination.sql`
+
+-- Peers table
+CREATE TABLE IF NOT EXISTS peers (
+    id TEXT PRIMARY KEY,
+    public_key BLOB NOT NULL UNIQUE,
+    assigned_ip TEXT NOT NULL UNIQUE,
+    endpoints TEXT,  -- JSON array of socket addresses
+    last_seen REAL NOT NULL,
+    metadata TEXT,  -- JSON object
+    ephemeral INTEGER NOT NULL DEFAULT 0,  -- removed when its last session ends
+    user_id TEXT,  -- user who joined the peer through OIDC login
+    created_at REAL NOT NULL,
+    updated_at REAL NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_peers_last_seen ON peers (last_seen);
+
+-- Users who joined peers through OIDC login
+CREATE TABLE IF NOT EXISTS users (
+    id TEXT PRIMARY KEY,
+    email TEXT,
+    display_name TEXT,
+    created_at REAL NOT NULL,
+    last_login_at REAL NOT NULL
+);
+
+-- OIDC identities, each belonging to one user
+CREATE TABLE IF NOT EXISTS identities (
+    issuer TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    email TEXT,
+    created_at REAL NOT NULL,
+    PRIMARY KEY (issuer, subject),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+-- Pre-auth keys, stored as SHA-256 hashes of the secret
+CREATE TABLE IF NOT EXISTS pre_auth_keys (
+    id TEXT PRIMARY KEY,
+    key_hash BLOB NOT NULL UNIQUE,
+    reusable INTEGER NOT NULL,
+    ephemeral INTEGER NOT NULL,
+    tags TEXT NOT NULL,  -- JSON array of tag names
+    description TEXT,
+    expires_at REAL,
+    used_count INTEGER NOT NULL DEFAULT 0,
+    revoked_at REAL,
+    created_at REAL NOT NULL
+);
+
+-- Admin API tokens, stored as SHA-256 hashes of the secret
+CREATE TABLE IF NOT EXISTS api_tokens (
+    id TEXT PRIMARY KEY,
+    token_hash BLOB NOT NULL UNIQUE,
+    name TEXT NOT NULL,
+    scopes TEXT NOT NULL,  -- JSON array of scope names
+    expires_at REAL,
+    last_used_at REAL,
+    revoked_at REAL,
+    created_at REAL NOT NULL
+);
+
+-- ACL rules
+CREATE TABLE IF NOT EXISTS acl_rules (
+    id TEXT PRIMARY KEY,
+    peer_id TEXT,
+    source_cidr TEXT NOT NULL,
+    dest_cidr TEXT NOT NULL,
+    action TEXT NOT NULL CHECK(action = 'allow' OR action = 'deny'),
+    priority INTEGER NOT NULL DEFAULT 0,
+    description TEXT,
+    protocol TEXT,  -- tcp, udp, icmp or an IP protocol number; NULL for all
+    ports TEXT NOT NULL DEFAULT '*',  -- e.g. 22,8000-8080
+    created_at REAL NOT NULL,
+    updated_at REAL NOT NULL,
+    FOREIGN KEY (peer_id) REFERENCES peers(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_acl_priority ON acl_rules (priority);
+CREATE INDEX IF NOT EXISTS idx_acl_peer_id ON acl_rules (peer_id);
+
+-- Network routes
+CREATE TABLE IF NOT EXISTS routes (
+    network_id TEXT PRIMARY KEY,
+    cidr TEXT NOT NULL,
+    peer_id TEXT NOT NULL,
+    metric INTEGER DEFAULT 100,
+    advertised_by TEXT NOT NULL,
+    advertised_at REAL NOT NULL,
+    created_at REAL NOT NULL,
+    FOREIGN KEY (peer_id) REFERENCES peers(id) ON DELETE CASCADE,
+    FOREIGN KEY (advertised_by) REFERENCES peers(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_routes_cidr ON routes (cidr);
+CREATE INDEX IF NOT EXISTS idx_routes_peer_id ON routes (peer_id);
+
+-- Health reports, one row per peer and report time
+CREATE TABLE IF NOT EXISTS health_metrics (
+    peer_id TEXT NOT NULL,
+    timestamp REAL NOT NULL,
+    server_latency_ms REAL,
+    connected_peers INTEGER,
+    rx_bytes INTEGER,
+    tx_bytes INTEGER,
+    PRIMARY KEY (peer_id, timestamp),
+    FOREIGN KEY (peer_id) REFERENCES peers(id) ON DELETE CASCADE
+);
+
+-- Topology generation, a single row created at startup
+CREATE TABLE IF NOT EXISTS topology_state (
+    id INTEGER PRIMARY KEY,
+    generation INTEGER NOT NULL,
+    compacted_through INTEGER NOT NULL  -- changes up to here are no longer logged
+);
+
+-- Entities changed by each generation, for topology deltas
+CREATE TABLE IF NOT EXISTS topology_changes (
+    generation INTEGER NOT NULL,
+    entity TEXT NOT NULL CHECK(entity = 'peer' OR entity = 'route' OR entity = 'acl'),
+    entity_id TEXT NOT NULL,
+    change TEXT NOT NULL CHECK(change = 'added' OR change = 'updated' OR change = 'removed'),
+    PRIMARY KEY (generation, entity, entity_id)
+);
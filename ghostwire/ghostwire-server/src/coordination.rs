@@ -1,21 +1,347 @@
 //! Coordination server implementation with ZQLite backend
 
 use anyhow::{Context, Result};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, SubsecRound, Utc};
 use ghostwire_common::{
-    network::IpAllocator, AclAction, AclRule, GhostwireError, NetworkTopology, PeerInfo,
-    PublicKey, RegisterPeerRequest, RegisterPeerResponse, Route, ServerConfig
+    acl::AclEngine, crypto, network::{ip_in_cidr, IpAllocator},
+    policy::{self, PacketFilter, Policy, PolicyPeer}, AclAction, AclRule, ApiScope, ApiToken, CreatePreAuthKeyRequest, CreatedPreAuthKey,
+    GhostwireError, NetworkTopology, PeerInfo, PeerMetadata, PreAuthKey, PublicKey, RegisterPeerRequest,
+    PendingRegistration, Registration, RegisterPeerResponse, RegistrationChallenge, RemovedEntities, Route,
+    ServerConfig, TopologyDelta, TopologyEntities, TopologySync, UpdatePeerRequest, User
 };
-use std::{collections::HashMap, net::IpAddr, sync::Arc};
-use tokio::sync::RwLock;
-use tracing::{debug, error, info, instrument, warn};
+use ring::{digest, rand::{SecureRandom, SystemRandom}};
+use serde::de::DeserializeOwned;
+use std::{
+    collections::{HashMap, HashSet},
+    net::{IpAddr, Ipv4Addr},
+    sync::Arc,
+};
+use tokio::sync::{watch, Mutex, RwLock};
+use tracing::{debug, info, instrument, warn};
 use uuid::Uuid;
-use zqlite_rs::{AsyncConnectionPool, PoolConfig, ZQLiteMetrics};
+use arc_swap::ArcSwap;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use zqlite_rs::{col, AsyncConnectionPool, Capabilities, FunctionFlags, Order, PoolConfig, Query, ZQLiteMetrics};
+
+use crate::oidc::{self, Identity, OidcProvider};
+
+/// Columns of `PeerRow`, in order
+const PEER_COLUMNS: [&str; 6] = ["id", "public_key", "endpoints", "last_seen", "metadata", "user_id"];
+
+/// Stored peer fields, checked against zqlite-schema.json by `query_as!`
+struct PeerRow {
+    id: Uuid,
+    public_key: [u8; 32],
+    endpoints: Option<String>,
+    last_seen: DateTime<Utc>,
+    metadata: Option<String>,
+    user_id: Option<Uuid>,
+}
+
+impl PeerRow {
+    /// Read a row selected with `PEER_COLUMNS`, for queries built at runtime
+    fn from_row(row: &zqlite_rs::Row<'_>) -> zqlite_rs::Result<Self> {
+        Ok(Self {
+            id: row.get(0)?,
+            public_key: row.get(1)?,
+            endpoints: row.get(2)?,
+            last_seen: row.get(3)?,
+            metadata: row.get(4)?,
+            user_id: row.get(5)?,
+        })
+    }
+}
+
+/// A peer's ID and address, found by its public key
+struct PeerAddressRow {
+    id: Uuid,
+    assigned_ip: String,
+}
+
+/// The user an OIDC identity signs in as
+struct IdentityRow {
+    user_id: Uuid,
+}
+
+/// Stored peer fields that ACL selectors match on
+struct PolicyPeerRow {
+    id: Uuid,
+    assigned_ip: String,
+    metadata: Option<String>,
+}
+
+impl PolicyPeerRow {
+    fn into_policy_peer(self) -> Result<PolicyPeer, GhostwireError> {
+        let metadata: PeerMetadata = parse_json_column(self.metadata.as_deref())?;
+        let address = self.assigned_ip.parse()
+            .map_err(|_| GhostwireError::Network(format!("Stored peer address is invalid: {}", self.assigned_ip)))?;
+        Ok(PolicyPeer {
+            id: self.id,
+            name: metadata.name,
+            address,
+            tags: metadata.tags,
+        })
+    }
+}
+
+/// Columns of `AclRow`, in order
+const ACL_COLUMNS: [&str; 9] = [
+    "id", "peer_id", "source_cidr", "dest_cidr", "action", "priority", "description", "protocol", "ports",
+];
+
+/// Stored ACL rule fields, checked against zqlite-schema.json by `query_as!`
+struct AclRow {
+    id: Uuid,
+    peer_id: Option<Uuid>,
+    source_cidr: String,
+    dest_cidr: String,
+    action: String,
+    priority: i32,
+    description: Option<String>,
+    protocol: Option<String>,
+    /// Port ranges in policy syntax, `*` for all ports
+    ports: String,
+}
+
+impl AclRow {
+    /// Read a row selected with `ACL_COLUMNS`, for queries built at runtime
+    fn from_row(row: &zqlite_rs::Row<'_>) -> zqlite_rs::Result<Self> {
+        Ok(Self {
+            id: row.get(0)?,
+            peer_id: row.get(1)?,
+            source_cidr: row.get(2)?,
+            dest_cidr: row.get(3)?,
+            action: row.get(4)?,
+            priority: row.get(5)?,
+            description: row.get(6)?,
+            protocol: row.get(7)?,
+            ports: row.get(8)?,
+        })
+    }
+
+    fn into_rule(self) -> Result<AclRule, GhostwireError> {
+        let action = match self.action.as_str() {
+            "allow" => AclAction::Allow,
+            "deny" => AclAction::Deny,
+            other => return Err(GhostwireError::Acl(format!("Unknown stored action: {}", other))),
+        };
+        Ok(AclRule {
+            id: self.id,
+            source_cidr: self.source_cidr,
+            dest_cidr: self.dest_cidr,
+            action,
+            priority: self.priority,
+            description: self.description,
+            peer_id: self.peer_id,
+            protocol: self.protocol.as_deref().map(str::parse).transpose()?,
+            ports: policy::parse_ports(&self.ports)?,
+        })
+    }
+}
+
+/// Stored route fields, checked against zqlite-schema.json by `query_as!`
+struct RouteRow {
+    network_id: Uuid,
+    cidr: String,
+    peer_id: Uuid,
+    metric: Option<u32>,
+    advertised_by: Uuid,
+    advertised_at: DateTime<Utc>,
+}
+
+impl RouteRow {
+    fn into_route(self) -> Route {
+        Route {
+            network_id: self.network_id,
+            cidr: self.cidr,
+            peer_id: self.peer_id,
+            // The column default
+            metric: self.metric.unwrap_or(100),
+            advertised_by: self.advertised_by,
+            advertised_at: self.advertised_at,
+        }
+    }
+}
+
+/// Stored pre-auth key fields, checked against zqlite-schema.json by `query_as!`
+struct PreAuthKeyRow {
+    id: Uuid,
+    reusable: bool,
+    ephemeral: bool,
+    /// JSON array of tag names
+    tags: String,
+    description: Option<String>,
+    expires_at: Option<DateTime<Utc>>,
+    used_count: u64,
+    revoked_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+}
+
+impl PreAuthKeyRow {
+    fn into_key(self) -> Result<PreAuthKey, GhostwireError> {
+        Ok(PreAuthKey {
+            id: self.id,
+            reusable: self.reusable,
+            ephemeral: self.ephemeral,
+            tags: serde_json::from_str(&self.tags).map_err(GhostwireError::Serialization)?,
+            description: self.description,
+            expires_at: self.expires_at,
+            used_count: self.used_count,
+            revoked: self.revoked_at.is_some(),
+            created_at: self.created_at,
+        })
+    }
+}
+
+/// Stored API token fields, checked against zqlite-schema.json by `query_as!`
+struct ApiTokenRow {
+    id: Uuid,
+    name: String,
+    /// JSON array of scope names
+    scopes: String,
+    expires_at: Option<DateTime<Utc>>,
+    last_used_at: Option<DateTime<Utc>>,
+    revoked_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+}
+
+impl ApiTokenRow {
+    fn into_token(self) -> Result<ApiToken, GhostwireError> {
+        Ok(ApiToken {
+            id: self.id,
+            name: self.name,
+            scopes: serde_json::from_str(&self.scopes).map_err(GhostwireError::Serialization)?,
+            expires_at: self.expires_at,
+            last_used_at: self.last_used_at,
+            revoked: self.revoked_at.is_some(),
+            created_at: self.created_at,
+        })
+    }
+}
+
+/// Minimum time between recorded uses of an API token
+const TOKEN_USE_INTERVAL: chrono::TimeDelta = chrono::TimeDelta::seconds(60);
+
+/// How long a registration challenge may be answered
+const CHALLENGE_TTL: chrono::TimeDelta = chrono::TimeDelta::seconds(60);
+
+/// Unanswered challenges kept before new ones are refused
+const MAX_PENDING_CHALLENGES: usize = 10_000;
+
+/// How long a user has to finish an OIDC login
+const LOGIN_TTL: chrono::TimeDelta = chrono::TimeDelta::minutes(10);
+
+/// Unfinished logins kept before new ones are refused
+const MAX_PENDING_LOGINS: usize = 1_000;
+
+/// Registration waiting for its user to log in with the OIDC provider
+struct PendingLogin {
+    login: oidc::Login,
+    request: RegisterPeerRequest,
+    expires_at: DateTime<Utc>,
+}
+
+/// Generations kept in the change log; clients further behind get a snapshot
+const CHANGE_LOG_RETENTION: u64 = 10_000;
+
+/// Kind of topology entity a change refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Entity {
+    Peer,
+    Route,
+    Acl,
+}
+
+/// How a generation changed an entity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChangeKind {
+    Added,
+    Updated,
+    Removed,
+}
+
+/// An entity and how it changed
+type Change = (Entity, Uuid, ChangeKind);
+
+/// Logged topology change, checked against zqlite-schema.json by `query_as!`
+struct ChangeRow {
+    generation: u64,
+    entity: String,
+    entity_id: Uuid,
+    change: String,
+}
+
+impl ChangeRow {
+    fn into_change(self) -> Result<Change, GhostwireError> {
+        let entity = match self.entity.as_str() {
+            "peer" => Entity::Peer,
+            "route" => Entity::Route,
+            "acl" => Entity::Acl,
+            other => return Err(GhostwireError::Network(format!("Unknown logged entity: {}", other))),
+        };
+        let change = match self.change.as_str() {
+            "added" => ChangeKind::Added,
+            "updated" => ChangeKind::Updated,
+            "removed" => ChangeKind::Removed,
+            other => return Err(GhostwireError::Network(format!("Unknown logged change: {}", other))),
+        };
+        Ok((entity, self.entity_id, change))
+    }
+}
+
+/// The part of the topology one peer is shown
+struct PeerView {
+    peer_id: Uuid,
+    /// The peer itself and every peer it may exchange traffic with
+    visible: HashSet<Uuid>,
+}
+
+impl PeerView {
+    /// The peer as the viewer sees it, without other peers' scoped rules
+    fn peer(&self, mut peer: PeerInfo) -> Option<PeerInfo> {
+        if !self.visible.contains(&peer.id) {
+            return None;
+        }
+        if peer.id != self.peer_id {
+            peer.acl_rules.clear();
+        }
+        Some(peer)
+    }
+
+    fn route(&self, route: Route) -> Option<Route> {
+        self.visible.contains(&route.peer_id).then_some(route)
+    }
+
+    fn acl_rule(&self, rule: AclRule) -> Option<AclRule> {
+        rule.peer_id.is_none_or(|peer_id| peer_id == self.peer_id).then_some(rule)
+    }
+}
 
 /// Coordination server managing the mesh VPN network
 pub struct CoordinationServer {
     database: Arc<AsyncConnectionPool>,
     ip_allocator: Arc<RwLock<IpAllocator>>,
+    /// Compiled rules, swapped whole after every ACL change
+    acl: ArcSwap<AclEngine>,
+    /// Serializes ACL writes and registrations so the contradiction and
+    /// duplicate checks and the rebuilt engine see every change
+    acl_lock: Mutex<()>,
+    /// Serializes user sign-ins, so an identity gets one user
+    sign_in_lock: Mutex<()>,
+    /// Unanswered registration challenges and when they expire
+    challenges: Mutex<HashMap<Vec<u8>, DateTime<Utc>>>,
+    /// OIDC provider for interactive registration, when configured
+    oidc: Option<OidcProvider>,
+    /// Registrations waiting on an OIDC login, by login state
+    logins: Mutex<HashMap<String, PendingLogin>>,
+    /// Latest generation written to the change log, locked while a change
+    /// is committed
+    committed: Mutex<u64>,
+    /// Latest generation whose changes are visible; sessions watch it to
+    /// push updates
+    generation: watch::Sender<u64>,
+    /// Generations kept in the change log
+    change_log_retention: u64,
     metrics: ZQLiteMetrics,
     config: ServerConfig,
 }
@@ -26,13 +352,22 @@ impl CoordinationServer {
     pub async fn new(config: &ServerConfig, metrics: ZQLiteMetrics) -> Result<Self> {
         info!("Initializing coordination server with ZQLite backend");
 
+        let library_version = zqlite_rs::check_library_version()
+            .context("Refusing to start with an incompatible ZQLite library")?;
+        info!(
+            "Using ZQLite {} (capabilities: {})",
+            library_version,
+            Capabilities::library()
+        );
+
         // Create database connection pool
         let pool_config = PoolConfig {
             min_connections: 2,
             max_connections: 10,
             connection_timeout: std::time::Duration::from_secs(30),
             ..Default::default()
-        };
+        }
+        .after_connect(register_sql_functions);
 
         let database = AsyncConnectionPool::new(Some(&config.database_path), pool_config)
             .await
@@ -44,20 +379,59 @@ impl CoordinationServer {
         let conn = database.get_connection().await?;
         Self::initialize_schema(&conn).await?;
 
-        // Create IP allocator
-        let ip_allocator = Arc::new(RwLock::new(
-            IpAllocator::new(&config.network_cidr)
-                .context("Failed to create IP allocator")?,
-        ));
+        // Create IP allocator, keeping the addresses of peers already registered
+        let mut allocator = IpAllocator::new(&config.network_cidr)
+            .context("Failed to create IP allocator")?;
+        let rows = conn.query("SELECT assigned_ip FROM peers").await
+            .context("Failed to load assigned IP addresses")?;
+        for row in &rows {
+            let ip: String = row.get(0)?;
+            match ip.parse::<Ipv4Addr>() {
+                Ok(ip) if allocator.reserve(ip) => {}
+                _ => warn!(assigned_ip = %ip, "Stored peer address is outside the network"),
+            }
+        }
+        let ip_allocator = Arc::new(RwLock::new(allocator));
 
-        info!("IP allocator initialized for network: {}", config.network_cidr);
+        info!(
+            "IP allocator initialized for network: {} ({} addresses in use)",
+            config.network_cidr,
+            ip_allocator.read().await.allocated_count()
+        );
 
-        Ok(Self {
+        // The generation survives restarts, so clients can keep asking for deltas
+        conn.execute("INSERT OR IGNORE INTO topology_state (id, generation, compacted_through) VALUES (1, 1, 0)")
+            .await.context("Failed to store the topology generation")?;
+        let generation = conn.fetch_optional(zqlite_rs::query!("SELECT generation FROM topology_state WHERE id = 1"))
+            .await.context("Failed to load the topology generation")?
+            .context("The topology generation is missing")?
+            .generation as u64;
+        info!("Topology at generation {}", generation);
+
+        let oidc = config.oidc.clone().map(OidcProvider::new).transpose()?;
+        if let Some(oidc_config) = &config.oidc {
+            info!("OIDC login enabled with issuer {}", oidc_config.issuer);
+        }
+
+        let server = Self {
             database: Arc::new(database),
             ip_allocator,
+            acl: ArcSwap::from_pointee(AclEngine::default()),
+            acl_lock: Mutex::new(()),
+            sign_in_lock: Mutex::new(()),
+            challenges: Mutex::new(HashMap::new()),
+            oidc,
+            logins: Mutex::new(HashMap::new()),
+            committed: Mutex::new(generation),
+            generation: watch::channel(generation).0,
+            change_log_retention: CHANGE_LOG_RETENTION,
             metrics,
             config: config.clone(),
-        })
+        };
+        server.rebuild_acl().await?;
+
+        info!("ACL engine compiled with {} rules", server.acl.load().len());
+        Ok(server)
     }
 
     /// Initialize the database schema
@@ -65,82 +439,176 @@ impl CoordinationServer {
     async fn initialize_schema(conn: &zqlite_rs::AsyncConnection) -> Result<()> {
         info!("Initializing database schema");
 
-        // Peers table with ZQLite optimizations
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS peers (
-                id TEXT PRIMARY KEY,
-                public_key BLOB NOT NULL UNIQUE,
-                assigned_ip TEXT NOT NULL UNIQUE,
-                endpoints TEXT COMPRESSED,  -- ZQLite compression for JSON
-                last_seen REAL NOT NULL,
-                metadata TEXT COMPRESSED,
-                created_at REAL NOT NULL,
-                updated_at REAL NOT NULL,
-                INDEX idx_peers_last_seen(last_seen),  -- ZQLite fast index
-                INDEX idx_peers_assigned_ip(assigned_ip)
-            )"
-        ).await.context("Failed to create peers table")?;
-
-        // ACL rules with bitmap indexing
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS acl_rules (
-                id TEXT PRIMARY KEY,
-                peer_id TEXT,
-                source_cidr TEXT NOT NULL,
-                dest_cidr TEXT NOT NULL,
-                action TEXT NOT NULL CHECK(action IN ('allow', 'deny')),
-                priority INTEGER NOT NULL DEFAULT 0,
-                description TEXT,
-                created_at REAL NOT NULL,
-                updated_at REAL NOT NULL,
-                INDEX idx_acl_priority(priority) USING BITMAP,  -- ZQLite bitmap index
-                INDEX idx_acl_peer_id(peer_id),
-                FOREIGN KEY (peer_id) REFERENCES peers(id) ON DELETE CASCADE
-            )"
-        ).await.context("Failed to create acl_rules table")?;
-
-        // Network routes with spatial indexing
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS routes (
-                network_id TEXT PRIMARY KEY,
-                cidr TEXT NOT NULL,
-                peer_id TEXT NOT NULL,
-                metric INTEGER DEFAULT 100,
-                advertised_by TEXT NOT NULL,
-                advertised_at REAL NOT NULL,
-                created_at REAL NOT NULL,
-                INDEX idx_routes_cidr(cidr) USING RTREE,  -- ZQLite R-tree for CIDR
-                INDEX idx_routes_peer_id(peer_id),
-                FOREIGN KEY (peer_id) REFERENCES peers(id) ON DELETE CASCADE,
-                FOREIGN KEY (advertised_by) REFERENCES peers(id) ON DELETE CASCADE
-            )"
-        ).await.context("Failed to create routes table")?;
-
-        // Health metrics with time-series optimization
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS health_metrics (
-                peer_id TEXT NOT NULL,
-                timestamp REAL NOT NULL,
-                server_latency_ms REAL,
-                connected_peers INTEGER,
-                rx_bytes INTEGER,
-                tx_bytes INTEGER,
-                PRIMARY KEY (peer_id, timestamp),
-                FOREIGN KEY (peer_id) REFERENCES peers(id) ON DELETE CASCADE
-            ) WITH TIME_SERIES(interval='1m', retention='7d')"  // ZQLite time-series
-        ).await.context("Failed to create health_metrics table")?;
+        // Table definitions live in schema/ so `cargo zqlite prepare` can snapshot them
+        conn.execute_script(include_str!("../schema/coordination.sql"), false)
+            .await.context("Failed to create database schema")?;
 
         info!("Database schema initialized successfully");
         Ok(())
     }
 
     /// Register a new peer in the network
+    ///
+    /// The request must answer a challenge from [`Self::issue_challenge`].
+    /// With a valid pre-auth key the peer is registered at once; without
+    /// one, and with OIDC configured, it is registered once the user logs
+    /// in at the returned URL. A public key that is already registered is
+    /// rejected with `PeerAlreadyRegistered`.
     #[instrument(skip(self, request), fields(public_key = %request.public_key.to_base64()))]
-    pub async fn register_peer(&self, request: RegisterPeerRequest) -> Result<RegisterPeerResponse, GhostwireError> {
+    pub async fn register_peer(&self, request: RegisterPeerRequest) -> Result<Registration, GhostwireError> {
+        self.verify_node_key(&request).await?;
+        if let Some(auth_key) = request.auth_key.clone() {
+            return Ok(Registration::Complete(self.add_peer(request, Some(&auth_key), None).await?));
+        }
+        match &self.oidc {
+            Some(oidc) => Ok(Registration::Pending(self.begin_login(oidc, request).await?)),
+            None => Err(GhostwireError::Unauthorized("a pre-auth key is required".to_string())),
+        }
+    }
+
+    /// Hold a registration until its user logs in with the OIDC provider
+    async fn begin_login(&self, oidc: &OidcProvider, request: RegisterPeerRequest) -> Result<PendingRegistration, GhostwireError> {
+        // Tags are only granted by pre-auth keys
+        if let Some(tag) = request.metadata.tags.keys().next() {
+            return Err(GhostwireError::Forbidden(format!("tag {} needs a pre-auth key that grants it", tag)));
+        }
+        // Checked again on completion, but the user should not log in for nothing
+        if let Some((existing, _)) = self.find_peer_by_key(&request.public_key).await? {
+            return Err(GhostwireError::PeerAlreadyRegistered(existing));
+        }
+
+        let login = oidc.begin().await?;
+        let now = Utc::now();
+        let expires_at = now + LOGIN_TTL;
+        let pending = PendingRegistration { auth_url: login.auth_url.clone(), expires_at };
+
+        let mut logins = self.logins.lock().await;
+        logins.retain(|_, login| login.expires_at > now);
+        if logins.len() >= MAX_PENDING_LOGINS {
+            return Err(GhostwireError::Network("Too many pending logins".to_string()));
+        }
+        logins.insert(login.state.clone(), PendingLogin { login, request, expires_at });
+
+        info!("Registration waiting for OIDC login");
+        Ok(pending)
+    }
+
+    /// Finish the registration waiting on the login `state` with the code
+    /// the OIDC provider redirected back with, binding the new peer to the
+    /// user who logged in
+    #[instrument(skip(self, state, code))]
+    pub async fn complete_login(&self, state: &str, code: &str) -> Result<RegisterPeerResponse, GhostwireError> {
+        let oidc = self.oidc.as_ref()
+            .ok_or_else(|| GhostwireError::Unauthorized("OIDC login is not configured".to_string()))?;
+        // Removed first, so each login completes at most once
+        let pending = self.logins.lock().await.remove(state)
+            .filter(|pending| pending.expires_at > Utc::now())
+            .ok_or_else(|| GhostwireError::Unauthorized("unknown or expired login".to_string()))?;
+
+        let identity = oidc.complete(&pending.login, code).await?;
+        let user_id = self.sign_in(&identity).await?;
+        self.add_peer(pending.request, None, Some(user_id)).await
+    }
+
+    /// Drop the registration waiting on the login `state`, after the OIDC
+    /// provider reported the login failed
+    pub async fn cancel_login(&self, state: &str) {
+        self.logins.lock().await.remove(state);
+    }
+
+    /// Find or create the user for an OIDC identity, recording the login
+    async fn sign_in(&self, identity: &Identity) -> Result<Uuid, GhostwireError> {
+        // Serialized, so two first logins of one identity create one user
+        let _guard = self.sign_in_lock.lock().await;
+        let conn = self.database.get_connection().await
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+
+        let existing = conn.fetch_optional(zqlite_rs::query_as!(
+            IdentityRow,
+            "SELECT user_id FROM identities WHERE issuer = ? AND subject = ?",
+            identity.issuer,
+            identity.subject
+        )).await.map_err(|e| GhostwireError::Database(e.into()))?
+            .map(|row| row.user_id);
+        let user_id = existing.unwrap_or_else(Uuid::new_v4);
+
+        // Both rows are created on the first login; the provider's claims
+        // replace what was stored at the last one
+        let now = Utc::now().timestamp() as f64;
+        let queries = [
+            zqlite_rs::insert_into("users")
+                .or_ignore()
+                .value("id", &user_id)
+                .value("created_at", &now)
+                .value("last_login_at", &now)
+                .build(),
+            zqlite_rs::insert_into("identities")
+                .or_ignore()
+                .value("issuer", &identity.issuer)
+                .value("subject", &identity.subject)
+                .value("user_id", &user_id)
+                .value("created_at", &now)
+                .build(),
+            zqlite_rs::update("users")
+                .set("email", &identity.email)
+                .set("display_name", &identity.name)
+                .set("last_login_at", &now)
+                .filter(col("id").eq(&user_id))
+                .build(),
+            zqlite_rs::update("identities")
+                .set("email", &identity.email)
+                .filter(col("issuer").eq(&identity.issuer))
+                .filter(col("subject").eq(&identity.subject))
+                .build(),
+        ];
+        let queries = queries.into_iter()
+            .collect::<zqlite_rs::Result<Vec<_>>>()
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+        conn.execute_queries(queries).await
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+
+        info!(user_id = %user_id, new_user = existing.is_none(), "User logged in");
+        Ok(user_id)
+    }
+
+    /// List users who have logged in with OIDC, newest first
+    #[instrument(skip(self))]
+    pub async fn list_users(&self) -> Result<Vec<User>, GhostwireError> {
+        let conn = self.database.get_connection().await
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+
+        conn.fetch_all(zqlite_rs::query_as!(
+            User,
+            "SELECT id, email, display_name, created_at, last_login_at FROM users ORDER BY created_at DESC"
+        )).await.map_err(|e| GhostwireError::Database(e.into()))
+    }
+
+    /// Store a new peer, authorized by the pre-auth key `auth_key` and
+    /// owned by the user `user_id`
+    ///
+    /// Callers have verified the node key, and the user if any; `None`
+    /// skips the pre-auth key for trusted callers.
+    async fn add_peer(
+        &self,
+        mut request: RegisterPeerRequest,
+        auth_key: Option<&str>,
+        user_id: Option<Uuid>,
+    ) -> Result<RegisterPeerResponse, GhostwireError> {
         let start_time = std::time::Instant::now();
 
         info!("Registering new peer");
 
+        // Checked under the lock, so concurrent registrations of one key or
+        // one single-use pre-auth key cannot both pass
+        let _guard = self.acl_lock.lock().await;
+        if let Some((existing, _)) = self.find_peer_by_key(&request.public_key).await? {
+            return Err(GhostwireError::PeerAlreadyRegistered(existing));
+        }
+        let key = match auth_key {
+            Some(secret) => Some(self.authorize(secret, &mut request.metadata).await?),
+            None => None,
+        };
+
         // Allocate IP address
         let assigned_ip = {
             let mut allocator = self.ip_allocator.write().await;
@@ -151,27 +619,53 @@ impl CoordinationServer {
         let peer_id = Uuid::new_v4();
         let now = Utc::now().timestamp() as f64;
 
+        let endpoints_json = serde_json::to_string(&request.endpoints)
+            .map_err(GhostwireError::Serialization)?;
+        let metadata_json = serde_json::to_string(&request.metadata)
+            .map_err(GhostwireError::Serialization)?;
+
         // Store peer in database
-        let conn = self.database.get_connection().await
+        let mut queries = vec![zqlite_rs::insert_into("peers")
+            .value("id", &peer_id)
+            .value("public_key", &request.public_key.0)
+            .value("assigned_ip", &assigned_ip.to_string())
+            .value("endpoints", &endpoints_json)
+            .value("last_seen", &now)
+            .value("metadata", &metadata_json)
+            .value("ephemeral", &key.as_ref().is_some_and(|key| key.ephemeral))
+            .value("user_id", &user_id)
+            .value("created_at", &now)
+            .value("updated_at", &now)
+            .build()];
+        if let Some(key) = &key {
+            // Checked by `authorize` under the same lock, so the count is current
+            queries.push(zqlite_rs::update("pre_auth_keys")
+                .set("used_count", &(key.used_count + 1))
+                .filter(col("id").eq(&key.id))
+                .build());
+        }
+        let queries = queries.into_iter()
+            .collect::<zqlite_rs::Result<Vec<_>>>()
             .map_err(|e| GhostwireError::Database(e.into()))?;
 
-        let endpoints_json = serde_json::to_string(&request.endpoints)
-            .map_err(|e| GhostwireError::Serialization(e))?;
-        let metadata_json = serde_json::to_string(&request.metadata)
-            .map_err(|e| GhostwireError::Serialization(e))?;
+        let generation = match self.commit(queries, &[(Entity::Peer, peer_id, ChangeKind::Added)]).await {
+            Ok(generation) => generation,
+            Err(e) => {
+                // The peer was never stored, so its address is free again
+                self.ip_allocator.write().await.release(assigned_ip);
+                // Another server on the database registered the key first
+                if is_constraint_violation(&e) {
+                    if let Some((existing, _)) = self.find_peer_by_key(&request.public_key).await? {
+                        return Err(GhostwireError::PeerAlreadyRegistered(existing));
+                    }
+                }
+                return Err(e);
+            }
+        };
 
-        conn.execute(&format!(
-            "INSERT INTO peers (id, public_key, assigned_ip, endpoints, last_seen, metadata, created_at, updated_at)
-             VALUES ('{}', X'{}', '{}', '{}', {}, '{}', {}, {})",
-            peer_id,
-            hex::encode(&request.public_key.0),
-            assigned_ip,
-            endpoints_json,
-            now,
-            metadata_json,
-            now,
-            now
-        )).await.map_err(|e| GhostwireError::Database(e.into()))?;
+        // Tag and peer selectors may now match the new peer
+        self.rebuild_acl().await?;
+        self.publish(generation);
 
         // Get default ACL rules for the peer
         let acl_rules = self.get_default_acl_rules().await?;
@@ -189,6 +683,8 @@ impl CoordinationServer {
         info!(
             peer_id = %peer_id,
             assigned_ip = %assigned_ip,
+            pre_auth_key = ?key.map(|key| key.id),
+            user_id = ?user_id,
             duration_ms = duration.as_millis(),
             "Peer registered successfully"
         );
@@ -196,212 +692,1026 @@ impl CoordinationServer {
         Ok(response)
     }
 
-    /// Get peer information by ID
-    #[instrument(skip(self))]
-    pub async fn get_peer(&self, peer_id: Uuid) -> Result<PeerInfo, GhostwireError> {
-        let start_time = std::time::Instant::now();
+    /// Resume the peer registered under the request's public key, or
+    /// register a new one if the key is unknown
+    ///
+    /// Either way the request must answer a challenge; only a new peer needs
+    /// a pre-auth key. A resumed peer keeps its ID, address and tags; its
+    /// endpoints and the rest of its metadata are replaced with the request's.
+    #[instrument(skip(self, request), fields(public_key = %request.public_key.to_base64()))]
+    pub async fn connect_peer(&self, request: RegisterPeerRequest) -> Result<RegisterPeerResponse, GhostwireError> {
+        self.verify_node_key(&request).await?;
+
+        let Some((peer_id, assigned_ip)) = self.find_peer_by_key(&request.public_key).await? else {
+            let auth_key = request.auth_key.clone()
+                .ok_or_else(|| GhostwireError::Unauthorized("a pre-auth key is required".to_string()))?;
+            return self.add_peer(request, Some(&auth_key), None).await;
+        };
+        let assigned_ip = assigned_ip.parse()
+            .map_err(|_| GhostwireError::Network(format!("Stored peer address is invalid: {}", assigned_ip)))?;
+
+        // Tags come from the pre-auth key, not from the peer
+        let current = self.get_peer(peer_id).await?;
+        let update = UpdatePeerRequest {
+            endpoints: Some(request.endpoints),
+            metadata: Some(PeerMetadata { tags: current.metadata.tags, ..request.metadata }),
+        };
+        self.update_peer(peer_id, update).await?;
+
+        info!(peer_id = %peer_id, "Peer resumed");
+        Ok(RegisterPeerResponse {
+            peer_id,
+            assigned_ip,
+            network_config: self.get_network_config(),
+            acl_rules: self.get_default_acl_rules().await?,
+        })
+    }
 
+    /// Remove a peer registered with an ephemeral pre-auth key, returning
+    /// whether it was removed
+    #[instrument(skip(self))]
+    pub async fn remove_ephemeral_peer(&self, peer_id: Uuid) -> Result<bool, GhostwireError> {
         let conn = self.database.get_connection().await
             .map_err(|e| GhostwireError::Database(e.into()))?;
 
-        let rows = conn.query(&format!(
-            "SELECT id, public_key, assigned_ip, endpoints, last_seen, metadata, created_at, updated_at
-             FROM peers WHERE id = '{}'",
+        let row = conn.fetch_optional(zqlite_rs::query!(
+            "SELECT ephemeral FROM peers WHERE id = ?",
             peer_id
         )).await.map_err(|e| GhostwireError::Database(e.into()))?;
+        let ephemeral = row.is_some_and(|row| row.ephemeral != 0);
 
-        if rows.row_count() == 0 {
-            return Err(GhostwireError::PeerNotFound(peer_id));
+        if ephemeral {
+            self.unregister_peer(peer_id).await?;
         }
+        Ok(ephemeral)
+    }
 
-        let peer_info = self.row_to_peer_info(rows.into_iter().next().unwrap()).await?;
-
-        let duration = start_time.elapsed();
-        self.metrics.query_executed("SELECT FROM peers", duration, true);
+    /// Issue a nonce for a registering node to sign with its private key
+    #[instrument(skip(self))]
+    pub async fn issue_challenge(&self) -> Result<RegistrationChallenge, GhostwireError> {
+        let mut nonce = [0u8; 32];
+        SystemRandom::new().fill(&mut nonce)
+            .map_err(|_| GhostwireError::Crypto("Failed to generate challenge".to_string()))?;
+
+        let now = Utc::now();
+        let expires_at = now + CHALLENGE_TTL;
+        let mut challenges = self.challenges.lock().await;
+        challenges.retain(|_, expires_at| *expires_at > now);
+        if challenges.len() >= MAX_PENDING_CHALLENGES {
+            return Err(GhostwireError::Network("Too many pending registration challenges".to_string()));
+        }
+        challenges.insert(nonce.to_vec(), expires_at);
 
-        debug!(peer_id = %peer_id, "Retrieved peer information");
-        Ok(peer_info)
+        Ok(RegistrationChallenge { nonce: STANDARD.encode(nonce), expires_at })
     }
 
-    /// List all peers with pagination
-    #[instrument(skip(self))]
-    pub async fn list_peers(&self, offset: u32, limit: u32) -> Result<Vec<PeerInfo>, GhostwireError> {
-        let start_time = std::time::Instant::now();
+    /// Check the request's signed challenge, which is then used up
+    async fn verify_node_key(&self, request: &RegisterPeerRequest) -> Result<(), GhostwireError> {
+        let proof = request.proof.as_ref().ok_or_else(|| {
+            GhostwireError::Unauthorized("a signed registration challenge is required".to_string())
+        })?;
+        let nonce = crypto::verify_proof(&request.public_key, proof)?;
 
+        match self.challenges.lock().await.remove(&nonce) {
+            Some(expires_at) if expires_at > Utc::now() => Ok(()),
+            _ => Err(GhostwireError::Unauthorized("unknown or expired registration challenge".to_string())),
+        }
+    }
+
+    /// Check a pre-auth key and give the registering peer its tags
+    ///
+    /// The peer may not claim tags the key does not grant.
+    async fn authorize(&self, secret: &str, metadata: &mut PeerMetadata) -> Result<PreAuthKey, GhostwireError> {
         let conn = self.database.get_connection().await
             .map_err(|e| GhostwireError::Database(e.into()))?;
 
-        let rows = conn.query(&format!(
-            "SELECT id, public_key, assigned_ip, endpoints, last_seen, metadata, created_at, updated_at
-             FROM peers
-             ORDER BY created_at DESC
-             LIMIT {} OFFSET {}",
-            limit, offset
+        let row = conn.fetch_optional(zqlite_rs::query_as!(
+            PreAuthKeyRow,
+            "SELECT id, reusable, ephemeral, tags, description, expires_at, used_count, revoked_at, created_at
+             FROM pre_auth_keys WHERE key_hash = ?",
+            secret_hash(secret)
         )).await.map_err(|e| GhostwireError::Database(e.into()))?;
 
-        let mut peers = Vec::with_capacity(rows.row_count());
-        for row in rows {
-            peers.push(self.row_to_peer_info(row).await?);
+        let unauthorized = |reason: &str| GhostwireError::Unauthorized(reason.to_string());
+        let key = row.ok_or_else(|| unauthorized("unknown pre-auth key"))?.into_key()?;
+        if key.revoked {
+            return Err(unauthorized("pre-auth key was revoked"));
+        }
+        if key.expires_at.is_some_and(|expires_at| expires_at <= Utc::now()) {
+            return Err(unauthorized("pre-auth key has expired"));
+        }
+        if !key.reusable && key.used_count > 0 {
+            return Err(unauthorized("pre-auth key was already used"));
         }
 
-        let duration = start_time.elapsed();
-        self.metrics.query_executed("SELECT FROM peers (list)", duration, true);
-        self.metrics.query_rows_returned(peers.len());
+        if let Some(tag) = metadata.tags.keys().find(|tag| !key.tags.contains(tag)) {
+            return Err(GhostwireError::Forbidden(format!("the pre-auth key does not grant tag:{}", tag)));
+        }
+        for tag in &key.tags {
+            metadata.tags.entry(tag.clone()).or_default();
+        }
+        Ok(key)
+    }
 
-        debug!(count = peers.len(), "Retrieved peer list");
-        Ok(peers)
+    /// Mint a pre-auth key; the secret is returned once and stored hashed
+    #[instrument(skip(self, request))]
+    pub async fn create_pre_auth_key(&self, request: CreatePreAuthKeyRequest) -> Result<CreatedPreAuthKey, GhostwireError> {
+        let mut secret = [0u8; 32];
+        SystemRandom::new().fill(&mut secret)
+            .map_err(|_| GhostwireError::Crypto("Failed to generate pre-auth key".to_string()))?;
+        let key = format!("gwauth-{}", hex::encode(secret));
+
+        let info = PreAuthKey {
+            id: Uuid::new_v4(),
+            reusable: request.reusable,
+            ephemeral: request.ephemeral,
+            tags: request.tags.iter().map(|tag| tag.strip_prefix("tag:").unwrap_or(tag).to_string()).collect(),
+            description: request.description,
+            expires_at: request.expires_at,
+            used_count: 0,
+            revoked: false,
+            // Stored in whole seconds, like every other timestamp
+            created_at: Utc::now().trunc_subsecs(0),
+        };
+        let tags_json = serde_json::to_string(&info.tags)
+            .map_err(GhostwireError::Serialization)?;
+
+        let conn = self.database.get_connection().await
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+        let insert = zqlite_rs::insert_into("pre_auth_keys")
+            .value("id", &info.id)
+            .value("key_hash", &secret_hash(&key))
+            .value("reusable", &info.reusable)
+            .value("ephemeral", &info.ephemeral)
+            .value("tags", &tags_json)
+            .value("description", &info.description)
+            .value("expires_at", &info.expires_at.map(|at| at.timestamp() as f64))
+            .value("created_at", &(info.created_at.timestamp() as f64))
+            .build()
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+        conn.execute_query(insert).await.map_err(|e| GhostwireError::Database(e.into()))?;
+
+        info!(key_id = %info.id, reusable = info.reusable, ephemeral = info.ephemeral, "Pre-auth key created");
+        Ok(CreatedPreAuthKey { key, info })
     }
 
-    /// Update peer health information
+    /// List pre-auth keys, newest first
     #[instrument(skip(self))]
-    pub async fn update_peer_health(&self, health: &ghostwire_common::HealthCheck) -> Result<(), GhostwireError> {
+    pub async fn list_pre_auth_keys(&self) -> Result<Vec<PreAuthKey>, GhostwireError> {
         let conn = self.database.get_connection().await
             .map_err(|e| GhostwireError::Database(e.into()))?;
 
-        let timestamp = health.timestamp.timestamp() as f64;
-
-        // Update last_seen in peers table
-        conn.execute(&format!(
-            "UPDATE peers SET last_seen = {} WHERE id = '{}'",
-            timestamp, health.peer_id
+        let rows = conn.fetch_all(zqlite_rs::query_as!(
+            PreAuthKeyRow,
+            "SELECT id, reusable, ephemeral, tags, description, expires_at, used_count, revoked_at, created_at
+             FROM pre_auth_keys ORDER BY created_at DESC"
         )).await.map_err(|e| GhostwireError::Database(e.into()))?;
 
-        // Insert health metrics
-        conn.execute(&format!(
-            "INSERT INTO health_metrics (peer_id, timestamp, server_latency_ms, connected_peers, rx_bytes, tx_bytes)
-             VALUES ('{}', {}, {}, {}, {}, {})",
-            health.peer_id,
-            timestamp,
-            health.server_latency_ms.map_or("NULL".to_string(), |v| v.to_string()),
-            health.connected_peers,
-            health.rx_bytes,
-            health.tx_bytes
+        rows.into_iter().map(PreAuthKeyRow::into_key).collect()
+    }
+
+    /// Revoke a pre-auth key; peers it registered stay registered
+    #[instrument(skip(self))]
+    pub async fn revoke_pre_auth_key(&self, key_id: Uuid) -> Result<(), GhostwireError> {
+        let conn = self.database.get_connection().await
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+
+        let row = conn.fetch_optional(zqlite_rs::query_as!(
+            PreAuthKeyRow,
+            "SELECT id, reusable, ephemeral, tags, description, expires_at, used_count, revoked_at, created_at
+             FROM pre_auth_keys WHERE id = ?",
+            key_id
         )).await.map_err(|e| GhostwireError::Database(e.into()))?;
+        if row.is_none() {
+            return Err(GhostwireError::PreAuthKeyNotFound(key_id));
+        }
 
-        debug!(peer_id = %health.peer_id, "Updated peer health");
+        let revoke = zqlite_rs::update("pre_auth_keys")
+            .set("revoked_at", &(Utc::now().timestamp() as f64))
+            .filter(col("id").eq(&key_id))
+            .build()
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+        conn.execute_query(revoke).await.map_err(|e| GhostwireError::Database(e.into()))?;
+
+        info!(key_id = %key_id, "Pre-auth key revoked");
         Ok(())
     }
 
-    /// Get network topology
+    /// Create an admin API token; the secret is returned once and stored hashed
     #[instrument(skip(self))]
-    pub async fn get_topology(&self) -> Result<NetworkTopology, GhostwireError> {
-        let start_time = std::time::Instant::now();
-
-        // Get all peers
-        let peers_list = self.list_peers(0, 1000).await?; // TODO: Handle large networks better
-        let mut peers = HashMap::new();
-        for peer in peers_list {
-            peers.insert(peer.id, peer);
+    pub async fn create_api_token(
+        &self,
+        name: &str,
+        scopes: Vec<ApiScope>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<(String, ApiToken), GhostwireError> {
+        if scopes.is_empty() {
+            return Err(GhostwireError::Config("an API token needs at least one scope".to_string()));
         }
 
-        // Get routes
-        let routes = self.get_all_routes().await?;
-
-        // Get global ACL rules
-        let global_acl = self.get_global_acl_rules().await?;
+        let mut secret = [0u8; 32];
+        SystemRandom::new().fill(&mut secret)
+            .map_err(|_| GhostwireError::Crypto("Failed to generate API token".to_string()))?;
+        let secret = format!("gwapi-{}", hex::encode(secret));
 
-        let topology = NetworkTopology {
-            peers,
-            routes,
-            global_acl,
-            generation: self.get_topology_generation().await?,
-            updated_at: Utc::now(),
+        let token = ApiToken {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            scopes,
+            expires_at,
+            last_used_at: None,
+            revoked: false,
+            created_at: Utc::now().trunc_subsecs(0),
         };
+        let scopes_json = serde_json::to_string(&token.scopes)
+            .map_err(GhostwireError::Serialization)?;
 
-        let duration = start_time.elapsed();
-        self.metrics.query_executed("get_topology", duration, true);
-
-        info!(
-            peers_count = topology.peers.len(),
-            routes_count = topology.routes.len(),
-            "Retrieved network topology"
-        );
+        let conn = self.database.get_connection().await
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+        let insert = zqlite_rs::insert_into("api_tokens")
+            .value("id", &token.id)
+            .value("token_hash", &secret_hash(&secret))
+            .value("name", &token.name)
+            .value("scopes", &scopes_json)
+            .value("expires_at", &token.expires_at.map(|at| at.timestamp() as f64))
+            .value("created_at", &(token.created_at.timestamp() as f64))
+            .build()
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+        conn.execute_query(insert).await.map_err(|e| GhostwireError::Database(e.into()))?;
 
-        Ok(topology)
+        info!(token_id = %token.id, name = %token.name, "API token created");
+        Ok((secret, token))
     }
 
-    /// Evaluate ACL for a connection between two peers
+    /// List admin API tokens, newest first
     #[instrument(skip(self))]
-    pub async fn evaluate_acl(&self, source_ip: &str, dest_ip: &str) -> Result<bool, GhostwireError> {
-        let start_time = std::time::Instant::now();
-
+    pub async fn list_api_tokens(&self) -> Result<Vec<ApiToken>, GhostwireError> {
         let conn = self.database.get_connection().await
             .map_err(|e| GhostwireError::Database(e.into()))?;
 
-        // Use ZQLite's subnet matching operators for fast ACL evaluation
-        let rows = conn.query(&format!(
-            "SELECT action
-             FROM acl_rules
-             WHERE '{}' <<= source_cidr  -- ZQLite subnet match operator
-               AND '{}' <<= dest_cidr
-             ORDER BY priority DESC
-             LIMIT 1",
-            source_ip, dest_ip
+        let rows = conn.fetch_all(zqlite_rs::query_as!(
+            ApiTokenRow,
+            "SELECT id, name, scopes, expires_at, last_used_at, revoked_at, created_at
+             FROM api_tokens ORDER BY created_at DESC"
         )).await.map_err(|e| GhostwireError::Database(e.into()))?;
 
-        let allowed = if rows.row_count() > 0 {
-            let row = rows.into_iter().next().unwrap();
-            let action: String = row.get(0).map_err(|e| GhostwireError::Database(e.into()))?;
-            action == "allow"
-        } else {
-            false // Default deny
-        };
+        rows.into_iter().map(ApiTokenRow::into_token).collect()
+    }
 
-        let duration = start_time.elapsed();
-        self.metrics.query_executed("ACL evaluation", duration, true);
+    /// Revoke an admin API token
+    #[instrument(skip(self))]
+    pub async fn revoke_api_token(&self, token_id: Uuid) -> Result<(), GhostwireError> {
+        let conn = self.database.get_connection().await
+            .map_err(|e| GhostwireError::Database(e.into()))?;
 
-        debug!(
-            source_ip = source_ip,
-            dest_ip = dest_ip,
-            allowed = allowed,
-            "ACL evaluation completed"
-        );
+        let row = conn.fetch_optional(zqlite_rs::query_as!(
+            ApiTokenRow,
+            "SELECT id, name, scopes, expires_at, last_used_at, revoked_at, created_at
+             FROM api_tokens WHERE id = ?",
+            token_id
+        )).await.map_err(|e| GhostwireError::Database(e.into()))?;
+        if row.is_none() {
+            return Err(GhostwireError::ApiTokenNotFound(token_id));
+        }
 
-        Ok(allowed)
-    }
+        let revoke = zqlite_rs::update("api_tokens")
+            .set("revoked_at", &(Utc::now().timestamp() as f64))
+            .filter(col("id").eq(&token_id))
+            .build()
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+        conn.execute_query(revoke).await.map_err(|e| GhostwireError::Database(e.into()))?;
 
-    /// Helper method to convert database row to PeerInfo
-    async fn row_to_peer_info(&self, row: zqlite_rs::Row) -> Result<PeerInfo, GhostwireError> {
-        let id: String = row.get(0).map_err(|e| GhostwireError::Database(e.into()))?;
-        let public_key_hex: String = row.get(1).map_err(|e| GhostwireError::Database(e.into()))?;
-        let endpoints_json: String = row.get(3).map_err(|e| GhostwireError::Database(e.into()))?;
-        let last_seen_timestamp: f64 = row.get(4).map_err(|e| GhostwireError::Database(e.into()))?;
-        let metadata_json: String = row.get(5).map_err(|e| GhostwireError::Database(e.into()))?;
+        info!(token_id = %token_id, "API token revoked");
+        Ok(())
+    }
 
-        let peer_id = Uuid::parse_str(&id)
-            .map_err(|_| GhostwireError::Database("Invalid peer ID".into()))?;
+    /// Look up the live API token for a bearer secret, recording its use
+    ///
+    /// The configured `admin_token` authenticates as an admin token with the
+    /// nil ID, which is never stored.
+    pub async fn authenticate_token(&self, secret: &str) -> Result<ApiToken, GhostwireError> {
+        // Hashes are compared, so the comparison time says nothing about the secret
+        let is_admin_token = self
+            .config
+            .admin_token
+            .as_deref()
+            .is_some_and(|admin| secret_hash(admin) == secret_hash(secret));
+        if is_admin_token {
+            return Ok(ApiToken {
+                id: Uuid::nil(),
+                name: "admin_token".to_string(),
+                scopes: vec![ApiScope::Admin],
+                expires_at: None,
+                last_used_at: None,
+                revoked: false,
+                created_at: Utc::now().trunc_subsecs(0),
+            });
+        }
 
-        let public_key_bytes = hex::decode(public_key_hex)
-            .map_err(|_| GhostwireError::Database("Invalid public key".into()))?;
-        let mut public_key_array = [0u8; 32];
-        public_key_array.copy_from_slice(&public_key_bytes);
-        let public_key = PublicKey(public_key_array);
+        let conn = self.database.get_connection().await
+            .map_err(|e| GhostwireError::Database(e.into()))?;
 
-        let endpoints = serde_json::from_str(&endpoints_json)
-            .map_err(|e| GhostwireError::Serialization(e))?;
+        let row = conn.fetch_optional(zqlite_rs::query_as!(
+            ApiTokenRow,
+            "SELECT id, name, scopes, expires_at, last_used_at, revoked_at, created_at
+             FROM api_tokens WHERE token_hash = ?",
+            secret_hash(secret)
+        )).await.map_err(|e| GhostwireError::Database(e.into()))?;
 
-        let metadata = serde_json::from_str(&metadata_json)
-            .map_err(|e| GhostwireError::Serialization(e))?;
+        let unauthorized = |reason: &str| GhostwireError::Unauthorized(reason.to_string());
+        let mut token = row.ok_or_else(|| unauthorized("unknown API token"))?.into_token()?;
+        if token.revoked {
+            return Err(unauthorized("API token was revoked"));
+        }
+        let now = Utc::now();
+        if token.expires_at.is_some_and(|expires_at| expires_at <= now) {
+            return Err(unauthorized("API token has expired"));
+        }
 
-        let last_seen = DateTime::from_timestamp(last_seen_timestamp as i64, 0)
-            .unwrap_or_else(|| Utc::now());
+        // A busy token writes at most once per interval
+        if token.last_used_at.is_none_or(|used| now - used >= TOKEN_USE_INTERVAL) {
+            let touch = zqlite_rs::update("api_tokens")
+                .set("last_used_at", &(now.timestamp() as f64))
+                .filter(col("id").eq(&token.id))
+                .build()
+                .map_err(|e| GhostwireError::Database(e.into()))?;
+            conn.execute_query(touch).await.map_err(|e| GhostwireError::Database(e.into()))?;
+            token.last_used_at = Some(now.trunc_subsecs(0));
+        }
+        Ok(token)
+    }
 
-        // Get ACL rules for this peer
-        let acl_rules = self.get_peer_acl_rules(peer_id).await?;
+    /// Find the peer registered under a public key, with its address
+    async fn find_peer_by_key(&self, public_key: &PublicKey) -> Result<Option<(Uuid, String)>, GhostwireError> {
+        let conn = self.database.get_connection().await
+            .map_err(|e| GhostwireError::Database(e.into()))?;
 
-        Ok(PeerInfo {
-            id: peer_id,
-            public_key,
-            endpoints,
-            last_seen,
-            metadata,
-            acl_rules,
-        })
+        let row = conn.fetch_optional(zqlite_rs::query_as!(
+            PeerAddressRow,
+            "SELECT id, assigned_ip FROM peers WHERE public_key = ?",
+            public_key.0
+        )).await.map_err(|e| GhostwireError::Database(e.into()))?;
+        Ok(row.map(|row| (row.id, row.assigned_ip)))
     }
 
-    /// Get default ACL rules for new peers
-    async fn get_default_acl_rules(&self) -> Result<Vec<AclRule>, GhostwireError> {
-        // Return basic allow-all rule for the network
+    /// Get peer information by ID
+    #[instrument(skip(self))]
+    pub async fn get_peer(&self, peer_id: Uuid) -> Result<PeerInfo, GhostwireError> {
+        let start_time = std::time::Instant::now();
+
+        let conn = self.database.get_connection().await
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+
+        let row = conn.fetch_optional(zqlite_rs::query_as!(
+            PeerRow,
+            "SELECT id, public_key, endpoints, last_seen, metadata, user_id FROM peers WHERE id = ?",
+            peer_id
+        )).await.map_err(|e| GhostwireError::Database(e.into()))?;
+
+        let row = row.ok_or(GhostwireError::PeerNotFound(peer_id))?;
+        let peer_info = self.row_to_peer_info(row).await?;
+
+        let duration = start_time.elapsed();
+        self.metrics.query_executed("SELECT FROM peers", duration, true);
+
+        debug!(peer_id = %peer_id, "Retrieved peer information");
+        Ok(peer_info)
+    }
+
+    /// List all peers with pagination
+    #[instrument(skip(self))]
+    pub async fn list_peers(&self, offset: u32, limit: u32) -> Result<Vec<PeerInfo>, GhostwireError> {
+        let start_time = std::time::Instant::now();
+
+        let conn = self.database.get_connection().await
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+
+        // LIMIT and OFFSET must be literals in ZQLite, so this query is built at
+        // runtime
+        let query = zqlite_rs::select("peers")
+            .columns(PEER_COLUMNS)
+            .order_by("created_at", Order::Desc)
+            .limit(limit)
+            .offset(offset)
+            .build()
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+
+        let rows = conn.fetch_query(query).await.map_err(|e| GhostwireError::Database(e.into()))?
+            .iter()
+            .map(|row| PeerRow::from_row(&row))
+            .collect::<zqlite_rs::Result<Vec<_>>>()
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+
+        let mut peers = Vec::with_capacity(rows.len());
+        for row in rows {
+            peers.push(self.row_to_peer_info(row).await?);
+        }
+
+        let duration = start_time.elapsed();
+        self.metrics.query_executed("SELECT FROM peers (list)", duration, true);
+        self.metrics.query_rows_returned(peers.len());
+
+        debug!(count = peers.len(), "Retrieved peer list");
+        Ok(peers)
+    }
+
+    /// Count registered peers
+    #[instrument(skip(self))]
+    pub async fn count_peers(&self) -> Result<u64, GhostwireError> {
+        let conn = self.database.get_connection().await
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+
+        let rows = conn.query("SELECT COUNT(*) FROM peers").await
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+
+        let count: i64 = match rows.get(0) {
+            Some(row) => row.get(0).map_err(|e| GhostwireError::Database(e.into()))?,
+            None => 0,
+        };
+        Ok(count as u64)
+    }
+
+    /// Update a peer's endpoints and metadata
+    #[instrument(skip(self, request))]
+    pub async fn update_peer(&self, peer_id: Uuid, request: UpdatePeerRequest) -> Result<PeerInfo, GhostwireError> {
+        let start_time = std::time::Instant::now();
+
+        // Fails with PeerNotFound before anything is written
+        let current = self.get_peer(peer_id).await?;
+
+        let endpoints = request.endpoints.unwrap_or(current.endpoints);
+        let metadata = request.metadata.unwrap_or_else(|| current.metadata.clone());
+        let endpoints_json = serde_json::to_string(&endpoints)
+            .map_err(GhostwireError::Serialization)?;
+        let metadata_json = serde_json::to_string(&metadata)
+            .map_err(GhostwireError::Serialization)?;
+
+        let update = zqlite_rs::update("peers")
+            .set("endpoints", &endpoints_json)
+            .set("metadata", &metadata_json)
+            .set("updated_at", &(Utc::now().timestamp() as f64))
+            .filter(col("id").eq(&peer_id))
+            .build()
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+
+        let _guard = self.acl_lock.lock().await;
+        let generation = self.commit(vec![update], &[(Entity::Peer, peer_id, ChangeKind::Updated)]).await?;
+        if metadata != current.metadata {
+            // Names and tags decide which selectors match the peer
+            self.rebuild_acl().await?;
+        }
+        self.publish(generation);
+
+        let duration = start_time.elapsed();
+        self.metrics.query_executed("UPDATE peers", duration, true);
+
+        info!(peer_id = %peer_id, "Peer updated");
+        Ok(PeerInfo { endpoints, metadata, ..current })
+    }
+
+    /// Remove a peer and everything it owns, releasing its IP address
+    #[instrument(skip(self))]
+    pub async fn unregister_peer(&self, peer_id: Uuid) -> Result<(), GhostwireError> {
+        let start_time = std::time::Instant::now();
+
+        let conn = self.database.get_connection().await
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+
+        let assigned_ip = conn.fetch_optional(zqlite_rs::query!(
+            "SELECT assigned_ip FROM peers WHERE id = ?",
+            peer_id
+        )).await.map_err(|e| GhostwireError::Database(e.into()))?
+            .ok_or(GhostwireError::PeerNotFound(peer_id))?
+            .assigned_ip;
+
+        let _guard = self.acl_lock.lock().await;
+        let mut changes = vec![(Entity::Peer, peer_id, ChangeKind::Removed)];
+        for route in self.get_all_routes().await? {
+            if route.peer_id == peer_id || route.advertised_by == peer_id {
+                changes.push((Entity::Route, route.network_id, ChangeKind::Removed));
+            }
+        }
+        for rule in self.get_peer_acl_rules(peer_id).await? {
+            changes.push((Entity::Acl, rule.id, ChangeKind::Removed));
+        }
+
+        // ZQLite does not enforce ON DELETE CASCADE, so remove dependents explicitly
+        let deletes = [
+            zqlite_rs::delete_from("health_metrics").filter(col("peer_id").eq(&peer_id)),
+            zqlite_rs::delete_from("routes").filter(col("peer_id").eq(&peer_id)),
+            zqlite_rs::delete_from("routes").filter(col("advertised_by").eq(&peer_id)),
+            zqlite_rs::delete_from("acl_rules").filter(col("peer_id").eq(&peer_id)),
+            zqlite_rs::delete_from("peers").filter(col("id").eq(&peer_id)),
+        ];
+        let deletes = deletes.into_iter()
+            .map(|delete| delete.build())
+            .collect::<zqlite_rs::Result<Vec<_>>>()
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+        let generation = self.commit(deletes, &changes).await?;
+
+        match assigned_ip.parse::<Ipv4Addr>() {
+            Ok(ip) => {
+                self.ip_allocator.write().await.release(ip);
+            }
+            Err(_) => warn!(peer_id = %peer_id, assigned_ip = %assigned_ip, "Stored peer address is invalid"),
+        }
+        self.rebuild_acl().await?;
+        self.publish(generation);
+
+        let duration = start_time.elapsed();
+        self.metrics.query_executed("DELETE FROM peers", duration, true);
+
+        info!(peer_id = %peer_id, assigned_ip = %assigned_ip, "Peer unregistered");
+        Ok(())
+    }
+
+    /// List ACL rules, highest priority first
+    ///
+    /// With `source`, only rules whose source block contains that address are
+    /// listed; `*` matches every address and tag or user selectors none.
+    #[instrument(skip(self))]
+    pub async fn list_acl_rules(
+        &self,
+        offset: u32,
+        limit: u32,
+        source: Option<IpAddr>,
+    ) -> Result<Vec<AclRule>, GhostwireError> {
+        let conn = self.database.get_connection().await
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+
+        let query = zqlite_rs::select("acl_rules")
+            .columns(ACL_COLUMNS)
+            .filter_opt(source, |ip| col("source_cidr").call("cidr_contains", &ip))
+            .order_by("priority", Order::Desc)
+            .limit(limit)
+            .offset(offset)
+            .build()
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+
+        let rows = conn.fetch_query(query).await.map_err(|e| GhostwireError::Database(e.into()))?;
+        rows.iter().map(|row| {
+            AclRow::from_row(&row)
+                .map_err(|e| GhostwireError::Database(e.into()))
+                .and_then(AclRow::into_rule)
+        })
+        .collect()
+    }
+
+    /// Count ACL rules, only those matching `source` as in [`Self::list_acl_rules`]
+    #[instrument(skip(self))]
+    pub async fn count_acl_rules(&self, source: Option<IpAddr>) -> Result<u64, GhostwireError> {
+        let conn = self.database.get_connection().await
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+
+        if let Some(ip) = source {
+            let query = zqlite_rs::select("acl_rules")
+                .columns(["id"])
+                .filter(col("source_cidr").call("cidr_contains", &ip))
+                .build()
+                .map_err(|e| GhostwireError::Database(e.into()))?;
+            let rows = conn.fetch_query(query).await.map_err(|e| GhostwireError::Database(e.into()))?;
+            return Ok(rows.row_count() as u64);
+        }
+
+        let rows = conn.query("SELECT COUNT(*) FROM acl_rules").await
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+
+        let count: i64 = match rows.get(0) {
+            Some(row) => row.get(0).map_err(|e| GhostwireError::Database(e.into()))?,
+            None => 0,
+        };
+        Ok(count as u64)
+    }
+
+    /// Get an ACL rule by ID
+    #[instrument(skip(self))]
+    pub async fn get_acl_rule(&self, rule_id: Uuid) -> Result<AclRule, GhostwireError> {
+        let conn = self.database.get_connection().await
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+
+        let row = conn.fetch_optional(zqlite_rs::query_as!(
+            AclRow,
+            "SELECT id, peer_id, source_cidr, dest_cidr, action, priority, description, protocol, ports FROM acl_rules WHERE id = ?",
+            rule_id
+        )).await.map_err(|e| GhostwireError::Database(e.into()))?;
+
+        row.ok_or(GhostwireError::AclRuleNotFound(rule_id))?.into_rule()
+    }
+
+    /// Store a new ACL rule under a fresh ID
+    ///
+    /// Rejects invalid CIDR blocks, unknown peers and rules that contradict
+    /// an existing rule of the same scope and priority.
+    #[instrument(skip(self, rule))]
+    pub async fn add_acl_rule(&self, rule: AclRule) -> Result<AclRule, GhostwireError> {
+        let rule = AclRule { id: Uuid::new_v4(), ..rule };
+
+        let _guard = self.acl_lock.lock().await;
+        self.check_acl_rule(&rule).await?;
+
+        let insert = insert_acl_rule(&rule, Utc::now().timestamp() as f64)?;
+        let generation = self.commit(vec![insert], &[(Entity::Acl, rule.id, ChangeKind::Added)]).await?;
+        self.rebuild_acl().await?;
+        self.publish(generation);
+
+        info!(rule_id = %rule.id, peer_id = ?rule.peer_id, "ACL rule added");
+        Ok(rule)
+    }
+
+    /// Replace an existing ACL rule, validated like [`Self::add_acl_rule`]
+    #[instrument(skip(self, rule))]
+    pub async fn update_acl_rule(&self, rule_id: Uuid, rule: AclRule) -> Result<AclRule, GhostwireError> {
+        let rule = AclRule { id: rule_id, ..rule };
+
+        let _guard = self.acl_lock.lock().await;
+        self.get_acl_rule(rule_id).await?;
+        self.check_acl_rule(&rule).await?;
+
+        let update = zqlite_rs::update("acl_rules")
+            .set("peer_id", &rule.peer_id)
+            .set("source_cidr", &rule.source_cidr)
+            .set("dest_cidr", &rule.dest_cidr)
+            .set("action", action_name(&rule.action))
+            .set("priority", &rule.priority)
+            .set("description", &rule.description)
+            .set("protocol", &rule.protocol.map(|protocol| protocol.to_string()))
+            .set("ports", &policy::format_ports(&rule.ports))
+            .set("updated_at", &(Utc::now().timestamp() as f64))
+            .filter(col("id").eq(&rule_id))
+            .build()
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+        let generation = self.commit(vec![update], &[(Entity::Acl, rule_id, ChangeKind::Updated)]).await?;
+        self.rebuild_acl().await?;
+        self.publish(generation);
+
+        info!(rule_id = %rule_id, "ACL rule updated");
+        Ok(rule)
+    }
+
+    /// Delete an ACL rule
+    #[instrument(skip(self))]
+    pub async fn delete_acl_rule(&self, rule_id: Uuid) -> Result<(), GhostwireError> {
+        let _guard = self.acl_lock.lock().await;
+        self.get_acl_rule(rule_id).await?;
+
+        let delete = zqlite_rs::delete_from("acl_rules")
+            .filter(col("id").eq(&rule_id))
+            .build()
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+        let generation = self.commit(vec![delete], &[(Entity::Acl, rule_id, ChangeKind::Removed)]).await?;
+        self.rebuild_acl().await?;
+        self.publish(generation);
+
+        info!(rule_id = %rule_id, "ACL rule deleted");
+        Ok(())
+    }
+
+    /// Replace the network-wide ACL rules with those of a policy
+    ///
+    /// The policy's tests run first against the rules that would be
+    /// enforced, peer-scoped ones included. If a test fails, nothing is
+    /// stored and the report is returned in the error; tests whose tags or
+    /// peers match no registered peer yet are skipped with a warning.
+    #[instrument(skip(self, policy))]
+    pub async fn apply_policy(&self, policy: &Policy) -> Result<Vec<AclRule>, GhostwireError> {
+        let rules = policy.to_acl_rules()?;
+
+        let _guard = self.acl_lock.lock().await;
+        let (scoped, replaced): (Vec<AclRule>, Vec<AclRule>) = self
+            .get_all_acl_rules()
+            .await?
+            .into_iter()
+            .partition(|rule| rule.peer_id.is_some());
+
+        let enforced: Vec<AclRule> = scoped.into_iter().chain(rules.iter().cloned()).collect();
+        let report = policy.run_tests(&enforced, &self.get_policy_peers().await?)?;
+        if !report.failures.is_empty() {
+            return Err(GhostwireError::PolicyTestFailed(report));
+        }
+        for skipped in &report.unresolved {
+            warn!("Skipped policy test {}", skipped);
+        }
+
+        let now = Utc::now().timestamp() as f64;
+        let mut queries = Vec::with_capacity(replaced.len() + rules.len());
+        let mut changes = Vec::with_capacity(replaced.len() + rules.len());
+        for rule in &replaced {
+            queries.push(zqlite_rs::delete_from("acl_rules")
+                .filter(col("id").eq(&rule.id))
+                .build()
+                .map_err(|e| GhostwireError::Database(e.into()))?);
+            changes.push((Entity::Acl, rule.id, ChangeKind::Removed));
+        }
+        for rule in &rules {
+            queries.push(insert_acl_rule(rule, now)?);
+            changes.push((Entity::Acl, rule.id, ChangeKind::Added));
+        }
+
+        let generation = self.commit(queries, &changes).await?;
+        self.rebuild_acl().await?;
+        self.publish(generation);
+
+        info!(rules = rules.len(), replaced = replaced.len(), tests = policy.tests.len(), "ACL policy applied");
+        Ok(rules)
+    }
+
+    /// Validate a rule before it is stored
+    async fn check_acl_rule(&self, rule: &AclRule) -> Result<(), GhostwireError> {
+        rule.validate()?;
+
+        let scope = match rule.peer_id {
+            Some(peer_id) => {
+                // Fails with PeerNotFound for unknown peers
+                self.get_peer(peer_id).await?;
+                self.get_peer_acl_rules(peer_id).await?
+            }
+            None => self.get_global_acl_rules().await?,
+        };
+
+        for existing in &scope {
+            if rule.contradicts(existing)? {
+                return Err(GhostwireError::AclConflict(existing.id));
+            }
+        }
+        Ok(())
+    }
+
+    /// Update peer health information
+    #[instrument(skip(self))]
+    pub async fn update_peer_health(&self, health: &ghostwire_common::HealthCheck) -> Result<(), GhostwireError> {
+        let conn = self.database.get_connection().await
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+
+        let timestamp = health.timestamp.timestamp() as f64;
+
+        // Update last_seen in peers table
+        let update = zqlite_rs::update("peers")
+            .set("last_seen", &timestamp)
+            .filter(col("id").eq(&health.peer_id))
+            .build()
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+        conn.execute_query(update).await.map_err(|e| GhostwireError::Database(e.into()))?;
+
+        // Insert health metrics
+        let insert = zqlite_rs::insert_into("health_metrics")
+            .value("peer_id", &health.peer_id)
+            .value("timestamp", &timestamp)
+            .value("server_latency_ms", &health.server_latency_ms)
+            .value("connected_peers", &health.connected_peers)
+            .value("rx_bytes", &health.rx_bytes)
+            .value("tx_bytes", &health.tx_bytes)
+            .build()
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+        conn.execute_query(insert).await.map_err(|e| GhostwireError::Database(e.into()))?;
+
+        debug!(peer_id = %health.peer_id, "Updated peer health");
+        Ok(())
+    }
+
+    /// Get the topology changes after generation `since`
+    ///
+    /// Falls back to a snapshot when `since` is `None`, ahead of the server,
+    /// or older than the change log. With `viewer` set, only the peers it
+    /// may exchange traffic with are included, without the peer-scoped ACL
+    /// rules of others.
+    #[instrument(skip(self))]
+    pub async fn topology_since(&self, since: Option<u64>, viewer: Option<Uuid>) -> Result<TopologySync, GhostwireError> {
+        let start_time = std::time::Instant::now();
+
+        let generation = self.get_topology_generation().await?;
+        let view = match viewer {
+            Some(peer_id) => Some(self.peer_view(peer_id).await?),
+            None => None,
+        };
+
+        let changes = match since {
+            Some(since) if since <= generation => self.changes_between(since, generation).await?,
+            _ => None,
+        };
+        // What a viewer may see changes with the ACLs and its own tags
+        let changes = changes.filter(|changes| {
+            viewer.is_none_or(|viewer| {
+                !changes.iter().any(|&(entity, id, _)| entity == Entity::Acl || (entity == Entity::Peer && id == viewer))
+            })
+        });
+
+        let sync = match (since, changes) {
+            (Some(since), Some(changes)) => {
+                TopologySync::Delta(self.delta(since, generation, changes, view.as_ref()).await?)
+            }
+            _ => TopologySync::Snapshot(self.snapshot(generation, view.as_ref()).await?),
+        };
+
+        let duration = start_time.elapsed();
+        self.metrics.query_executed("get_topology", duration, true);
+
+        debug!(
+            since = ?since,
+            generation,
+            delta = matches!(sync, TopologySync::Delta(_)),
+            "Retrieved network topology"
+        );
+        Ok(sync)
+    }
+
+    /// The topology at `generation`, as seen by `view` if given
+    async fn snapshot(&self, generation: u64, view: Option<&PeerView>) -> Result<NetworkTopology, GhostwireError> {
+        let mut peers = self.get_all_peers().await?;
+        let mut routes = self.get_all_routes().await?;
+        if let Some(view) = view {
+            peers = peers.into_iter().filter_map(|peer| view.peer(peer)).collect();
+            routes = routes.into_iter().filter_map(|route| view.route(route)).collect();
+        }
+
+        let topology = NetworkTopology {
+            peers: peers.into_iter().map(|peer| (peer.id, peer)).collect(),
+            routes,
+            global_acl: self.get_global_acl_rules().await?,
+            generation,
+            updated_at: Utc::now(),
+        };
+
+        info!(
+            peers_count = topology.peers.len(),
+            routes_count = topology.routes.len(),
+            "Built topology snapshot"
+        );
+        Ok(topology)
+    }
+
+    /// Net change of every entity changed after `since` up to `until`, in
+    /// the order they were first changed, or `None` if the change log has
+    /// been compacted past `since`
+    async fn changes_between(&self, since: u64, until: u64) -> Result<Option<Vec<Change>>, GhostwireError> {
+        let conn = self.database.get_connection().await
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+
+        let rows = conn.fetch_all(zqlite_rs::query_as!(
+            ChangeRow,
+            "SELECT generation, entity, entity_id, change FROM topology_changes
+             WHERE generation > ? ORDER BY generation",
+            since
+        )).await.map_err(|e| GhostwireError::Database(e.into()))?;
+
+        // Compaction moves this in the transaction that deletes the changes,
+        // so reading it second catches changes deleted under the query above
+        let state = conn.query("SELECT compacted_through FROM topology_state WHERE id = 1").await
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+        let compacted_through: i64 = match state.get(0) {
+            Some(row) => row.get(0).map_err(|e| GhostwireError::Database(e.into()))?,
+            None => 0,
+        };
+        if since < compacted_through as u64 {
+            return Ok(None);
+        }
+
+        let mut order = Vec::new();
+        let mut net: HashMap<(Entity, Uuid), (ChangeKind, ChangeKind)> = HashMap::new();
+        for row in rows {
+            if row.generation > until {
+                break;
+            }
+            let (entity, id, change) = row.into_change()?;
+            net.entry((entity, id))
+                .and_modify(|(_, last)| *last = change)
+                .or_insert_with(|| {
+                    order.push((entity, id));
+                    (change, change)
+                });
+        }
+
+        let changes = order.into_iter().filter_map(|(entity, id)| {
+            let change = match net[&(entity, id)] {
+                // Never seen by a client at `since`
+                (ChangeKind::Added, ChangeKind::Removed) => return None,
+                (ChangeKind::Added, _) => ChangeKind::Added,
+                (_, ChangeKind::Removed) => ChangeKind::Removed,
+                _ => ChangeKind::Updated,
+            };
+            Some((entity, id, change))
+        });
+        Ok(Some(changes.collect()))
+    }
+
+    /// Delta from `since` to `generation` with the current state of each
+    /// changed entity, as seen by `view` if given
+    ///
+    /// A changed entity that is gone or hidden from the view is listed as
+    /// removed, unless it was added after `since`.
+    async fn delta(
+        &self,
+        since: u64,
+        generation: u64,
+        changes: Vec<Change>,
+        view: Option<&PeerView>,
+    ) -> Result<TopologyDelta, GhostwireError> {
+        let mut delta = TopologyDelta {
+            since,
+            generation,
+            added: TopologyEntities::default(),
+            updated: TopologyEntities::default(),
+            removed: RemovedEntities::default(),
+        };
+
+        for (entity, id, change) in changes {
+            match entity {
+                Entity::Peer => {
+                    let current = match change {
+                        ChangeKind::Removed => None,
+                        _ => match self.get_peer(id).await {
+                            Ok(peer) => match view { Some(view) => view.peer(peer), None => Some(peer) },
+                            Err(GhostwireError::PeerNotFound(_)) => None,
+                            Err(e) => return Err(e),
+                        },
+                    };
+                    file_change(id, change, current, &mut delta.added.peers, &mut delta.updated.peers, &mut delta.removed.peers);
+                }
+                Entity::Route => {
+                    let current = match change {
+                        ChangeKind::Removed => None,
+                        _ => self.get_route(id).await?
+                            .and_then(|route| match view { Some(view) => view.route(route), None => Some(route) }),
+                    };
+                    file_change(id, change, current, &mut delta.added.routes, &mut delta.updated.routes, &mut delta.removed.routes);
+                }
+                Entity::Acl => {
+                    let current = match change {
+                        ChangeKind::Removed => None,
+                        _ => match self.get_acl_rule(id).await {
+                            Ok(rule) => match view { Some(view) => view.acl_rule(rule), None => Some(rule) },
+                            Err(GhostwireError::AclRuleNotFound(_)) => None,
+                            Err(e) => return Err(e),
+                        },
+                    };
+                    file_change(id, change, current, &mut delta.added.acls, &mut delta.updated.acls, &mut delta.removed.acls);
+                }
+            }
+        }
+        Ok(delta)
+    }
+
+    /// Whether `viewer` is shown the peer `peer_id` in its topology
+    pub async fn is_visible_to(&self, viewer: Uuid, peer_id: Uuid) -> Result<bool, GhostwireError> {
+        Ok(self.peer_view(viewer).await?.visible.contains(&peer_id))
+    }
+
+    /// Work out which peers `peer_id` may exchange traffic with
+    async fn peer_view(&self, peer_id: Uuid) -> Result<PeerView, GhostwireError> {
+        let peers = self.get_policy_peers().await?;
+        let viewer = peers.iter().find(|peer| peer.id == peer_id)
+            .ok_or(GhostwireError::PeerNotFound(peer_id))?;
+
+        let engine = self.acl.load();
+        let visible = peers.iter()
+            .filter(|peer| {
+                peer.id == peer_id
+                    || engine.reachable(viewer.address, peer.address)
+                    || engine.reachable(peer.address, viewer.address)
+            })
+            .map(|peer| peer.id)
+            .collect();
+        Ok(PeerView { peer_id, visible })
+    }
+
+    /// Compile the packet filter a peer enforces on inbound traffic
+    #[instrument(skip(self))]
+    pub async fn peer_packet_filter(&self, peer_id: Uuid) -> Result<PacketFilter, GhostwireError> {
+        let peers = self.get_policy_peers().await?;
+        let rules = self.get_all_acl_rules().await?;
+
+        policy::compile_filters(&rules, &peers)?
+            .into_iter()
+            .find(|filter| filter.peer_id == peer_id)
+            .ok_or(GhostwireError::PeerNotFound(peer_id))
+    }
+
+    /// Recompile the ACL engine from the stored rules and swap it in
+    ///
+    /// Selectors are resolved against the current peers. Callers hold
+    /// `acl_lock`, so engines are stored in write order.
+    async fn rebuild_acl(&self) -> Result<(), GhostwireError> {
+        let start_time = std::time::Instant::now();
+
+        let rules = self.get_all_acl_rules().await?;
+        let peers = self.get_policy_peers().await?;
+        let engine = AclEngine::compile(&policy::resolve_rules(&rules, &peers)?)?;
+        self.acl.store(Arc::new(engine));
+
+        self.metrics.query_executed("ACL rebuild", start_time.elapsed(), true);
+        debug!(rules = rules.len(), "ACL engine rebuilt");
+        Ok(())
+    }
+
+    /// Helper method to convert a stored peer to PeerInfo
+    async fn row_to_peer_info(&self, row: PeerRow) -> Result<PeerInfo, GhostwireError> {
+        // Get ACL rules for this peer
+        let acl_rules = self.get_peer_acl_rules(row.id).await?;
+        peer_info(row, acl_rules)
+    }
+
+    /// Get default ACL rules for new peers
+    async fn get_default_acl_rules(&self) -> Result<Vec<AclRule>, GhostwireError> {
+        // Return basic allow-all rule for the network
         Ok(vec![AclRule {
             id: Uuid::new_v4(),
             source_cidr: self.config.network_cidr.clone(),
@@ -409,6 +1719,9 @@ impl CoordinationServer {
             action: AclAction::Allow,
             priority: 0,
             description: Some("Default allow rule for network".to_string()),
+            peer_id: None,
+            protocol: None,
+            ports: vec![],
         }])
     }
 
@@ -423,31 +1736,294 @@ impl CoordinationServer {
         }
     }
 
-    /// Get ACL rules for a specific peer
+    /// Get ACL rules for a specific peer, highest priority first
     async fn get_peer_acl_rules(&self, peer_id: Uuid) -> Result<Vec<AclRule>, GhostwireError> {
-        // Simplified implementation - in practice, you'd query the database
-        Ok(vec![])
+        let conn = self.database.get_connection().await
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+
+        let rows = conn.fetch_all(zqlite_rs::query_as!(
+            AclRow,
+            "SELECT id, peer_id, source_cidr, dest_cidr, action, priority, description, protocol, ports FROM acl_rules
+             WHERE peer_id = ? ORDER BY priority DESC",
+            peer_id
+        )).await.map_err(|e| GhostwireError::Database(e.into()))?;
+
+        rows.into_iter().map(AclRow::into_rule).collect()
+    }
+
+    /// Get every peer with its ACL rules
+    async fn get_all_peers(&self) -> Result<Vec<PeerInfo>, GhostwireError> {
+        let conn = self.database.get_connection().await
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+
+        let rows = conn.fetch_all(zqlite_rs::query_as!(
+            PeerRow,
+            "SELECT id, public_key, endpoints, last_seen, metadata, user_id FROM peers"
+        )).await.map_err(|e| GhostwireError::Database(e.into()))?;
+
+        // One query for every peer's rules, which stay in priority order
+        let mut rules: HashMap<Uuid, Vec<AclRule>> = HashMap::new();
+        for rule in self.get_all_acl_rules().await? {
+            if let Some(peer_id) = rule.peer_id {
+                rules.entry(peer_id).or_default().push(rule);
+            }
+        }
+
+        self.metrics.query_rows_returned(rows.len());
+        rows.into_iter()
+            .map(|row| {
+                let acl_rules = rules.remove(&row.id).unwrap_or_default();
+                peer_info(row, acl_rules)
+            })
+            .collect()
     }
 
     /// Get all routes in the network
     async fn get_all_routes(&self) -> Result<Vec<Route>, GhostwireError> {
-        // Simplified implementation
-        Ok(vec![])
+        let conn = self.database.get_connection().await
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+
+        let rows = conn.fetch_all(zqlite_rs::query_as!(
+            RouteRow,
+            "SELECT network_id, cidr, peer_id, metric, advertised_by, advertised_at FROM routes"
+        )).await.map_err(|e| GhostwireError::Database(e.into()))?;
+
+        Ok(rows.into_iter().map(RouteRow::into_route).collect())
+    }
+
+    /// Get a route by network ID
+    async fn get_route(&self, network_id: Uuid) -> Result<Option<Route>, GhostwireError> {
+        let conn = self.database.get_connection().await
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+
+        let row = conn.fetch_optional(zqlite_rs::query_as!(
+            RouteRow,
+            "SELECT network_id, cidr, peer_id, metric, advertised_by, advertised_at FROM routes WHERE network_id = ?",
+            network_id
+        )).await.map_err(|e| GhostwireError::Database(e.into()))?;
+
+        Ok(row.map(RouteRow::into_route))
+    }
+
+    /// Get every ACL rule, highest priority first
+    async fn get_all_acl_rules(&self) -> Result<Vec<AclRule>, GhostwireError> {
+        let conn = self.database.get_connection().await
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+
+        let rows = conn.fetch_all(zqlite_rs::query_as!(
+            AclRow,
+            "SELECT id, peer_id, source_cidr, dest_cidr, action, priority, description, protocol, ports FROM acl_rules
+             ORDER BY priority DESC"
+        )).await.map_err(|e| GhostwireError::Database(e.into()))?;
+
+        rows.into_iter().map(AclRow::into_rule).collect()
+    }
+
+    /// Get every peer as ACL selectors see it
+    async fn get_policy_peers(&self) -> Result<Vec<PolicyPeer>, GhostwireError> {
+        let conn = self.database.get_connection().await
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+
+        let rows = conn.fetch_all(zqlite_rs::query_as!(
+            PolicyPeerRow,
+            "SELECT id, assigned_ip, metadata FROM peers"
+        )).await.map_err(|e| GhostwireError::Database(e.into()))?;
+
+        rows.into_iter().map(PolicyPeerRow::into_policy_peer).collect()
     }
 
-    /// Get global ACL rules
+    /// Get global ACL rules, highest priority first
     async fn get_global_acl_rules(&self) -> Result<Vec<AclRule>, GhostwireError> {
-        // Simplified implementation
-        Ok(vec![])
+        // The macros cannot check IS NULL, so peer rules are filtered out here
+        let mut rules = self.get_all_acl_rules().await?;
+        rules.retain(|rule| rule.peer_id.is_none());
+        Ok(rules)
     }
 
     /// Get current topology generation/version
     async fn get_topology_generation(&self) -> Result<u64, GhostwireError> {
-        // Simplified implementation
-        Ok(1)
+        Ok(*self.generation.borrow())
+    }
+
+    /// Watch the topology generation, which changes with every peer or ACL change
+    pub fn subscribe(&self) -> watch::Receiver<u64> {
+        self.generation.subscribe()
+    }
+
+    /// Run `queries` and log `changes` under the next generation, in one
+    /// transaction
+    ///
+    /// Returns the new generation for [`Self::publish`] once the change is
+    /// visible, e.g. after the ACL engine is rebuilt.
+    async fn commit(&self, mut queries: Vec<Query>, changes: &[Change]) -> Result<u64, GhostwireError> {
+        let mut committed = self.committed.lock().await;
+        let generation = *committed + 1;
+
+        let mut log = vec![zqlite_rs::update("topology_state")
+            .set("generation", &generation)
+            .filter(col("id").eq(&1))
+            .build()];
+        for (entity, id, change) in changes {
+            log.push(zqlite_rs::insert_into("topology_changes")
+                .value("generation", &generation)
+                .value("entity", entity_name(*entity))
+                .value("entity_id", id)
+                .value("change", change_name(*change))
+                .build());
+        }
+        // Clients further behind than the retained changes get a snapshot
+        if let Some(cut) = generation.checked_sub(self.change_log_retention).filter(|&cut| cut > 0) {
+            log.push(zqlite_rs::delete_from("topology_changes").filter(col("generation").le(&cut)).build());
+            log.push(zqlite_rs::update("topology_state")
+                .set("compacted_through", &cut)
+                .filter(col("id").eq(&1))
+                .build());
+        }
+        for query in log {
+            queries.push(query.map_err(|e| GhostwireError::Database(e.into()))?);
+        }
+
+        let conn = self.database.get_connection().await
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+        conn.execute_queries(queries).await
+            .map_err(|e| GhostwireError::Database(e.into()))?;
+
+        *committed = generation;
+        Ok(generation)
+    }
+
+    /// Announce a committed generation to watchers
+    fn publish(&self, generation: u64) {
+        // Commits may be published out of order; the generation only grows
+        self.generation.send_if_modified(|current| {
+            let newer = generation > *current;
+            if newer {
+                *current = generation;
+            }
+            newer
+        });
+    }
+}
+
+/// Register the SQL helper functions the coordination queries rely on
+fn register_sql_functions(conn: &zqlite_rs::Connection) -> zqlite_rs::Result<()> {
+    // cidr_contains(source, ip): whether `ip` falls inside a stored rule
+    // source, where `*` holds every address and tag or user selectors none
+    conn.create_scalar_function("cidr_contains", 2, FunctionFlags::DETERMINISTIC, |ctx| {
+        let source = ctx.get_ref(0)?.as_str()?;
+        let ip = ctx.get_ref(1)?.as_str()?;
+        let ip: IpAddr = ip
+            .parse()
+            .map_err(|_| zqlite_rs::Error::user_function(format!("Invalid IP address: {}", ip)))?;
+
+        Ok(source == "*" || ip_in_cidr(ip, source).unwrap_or(false))
+    })
+}
+
+/// Stored form of an ACL action
+fn action_name(action: &AclAction) -> &'static str {
+    match action {
+        AclAction::Allow => "allow",
+        AclAction::Deny => "deny",
+    }
+}
+
+/// Whether a database error is a UNIQUE or NOT NULL constraint rejecting a write
+fn is_constraint_violation(error: &GhostwireError) -> bool {
+    match error {
+        GhostwireError::Database(e) => e
+            .downcast_ref::<zqlite_rs::Error>()
+            .is_some_and(zqlite_rs::Error::is_constraint_violation),
+        _ => false,
+    }
+}
+
+/// Stored form of a pre-auth key or API token secret
+fn secret_hash(secret: &str) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(digest::digest(&digest::SHA256, secret.as_bytes()).as_ref());
+    hash
+}
+
+/// Logged form of an entity kind
+fn entity_name(entity: Entity) -> &'static str {
+    match entity {
+        Entity::Peer => "peer",
+        Entity::Route => "route",
+        Entity::Acl => "acl",
+    }
+}
+
+/// Logged form of a change
+fn change_name(change: ChangeKind) -> &'static str {
+    match change {
+        ChangeKind::Added => "added",
+        ChangeKind::Updated => "updated",
+        ChangeKind::Removed => "removed",
     }
 }
 
+/// Put a changed entity's current state, if the reader may see it, in the
+/// delta list for its change
+fn file_change<T>(
+    id: Uuid,
+    change: ChangeKind,
+    current: Option<T>,
+    added: &mut Vec<T>,
+    updated: &mut Vec<T>,
+    removed: &mut Vec<Uuid>,
+) {
+    match (current, change) {
+        (Some(entity), ChangeKind::Added) => added.push(entity),
+        (Some(entity), _) => updated.push(entity),
+        // A client at `since` never saw it
+        (None, ChangeKind::Added) => {}
+        (None, _) => removed.push(id),
+    }
+}
+
+/// Convert a stored peer to PeerInfo
+fn peer_info(row: PeerRow, acl_rules: Vec<AclRule>) -> Result<PeerInfo, GhostwireError> {
+    let PeerRow { id: peer_id, public_key, endpoints: endpoints_json, last_seen, metadata: metadata_json, user_id } = row;
+    let public_key = PublicKey(public_key);
+
+    let endpoints = parse_json_column(endpoints_json.as_deref())?;
+    let metadata = parse_json_column(metadata_json.as_deref())?;
+
+    Ok(PeerInfo {
+        id: peer_id,
+        public_key,
+        endpoints,
+        last_seen,
+        metadata,
+        acl_rules,
+        user_id,
+    })
+}
+
+/// Parse a nullable JSON column, reading NULL as the default value
+fn parse_json_column<T: DeserializeOwned + Default>(json: Option<&str>) -> Result<T, GhostwireError> {
+    json.map_or_else(|| Ok(T::default()), |json| serde_json::from_str(json).map_err(GhostwireError::Serialization))
+}
+
+/// Statement storing a new ACL rule
+fn insert_acl_rule(rule: &AclRule, now: f64) -> Result<Query, GhostwireError> {
+    zqlite_rs::insert_into("acl_rules")
+        .value("id", &rule.id)
+        .value("peer_id", &rule.peer_id)
+        .value("source_cidr", &rule.source_cidr)
+        .value("dest_cidr", &rule.dest_cidr)
+        .value("action", action_name(&rule.action))
+        .value("priority", &rule.priority)
+        .value("description", &rule.description)
+        .value("protocol", &rule.protocol.map(|protocol| protocol.to_string()))
+        .value("ports", &policy::format_ports(&rule.ports))
+        .value("created_at", &now)
+        .value("updated_at", &now)
+        .build()
+        .map_err(|e| GhostwireError::Database(e.into()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -467,6 +2043,20 @@ mod tests {
         CoordinationServer::new(&config, metrics).await.unwrap()
     }
 
+    #[test]
+    fn test_schema_snapshot_is_current() {
+        let conn = zqlite_rs::Connection::open(":memory:").unwrap();
+        conn.execute_batch(include_str!("../schema/coordination.sql")).unwrap();
+
+        let snapshot: serde_json::Value = serde_json::from_str(include_str!("../zqlite-schema.json")).unwrap();
+        let tables: Vec<zqlite_rs::TableInfo> = serde_json::from_value(snapshot["tables"].clone()).unwrap();
+        assert_eq!(
+            tables,
+            conn.schema().unwrap(),
+            "zqlite-schema.json is stale; run `cargo zqlite prepare schema/coordination.sql`"
+        );
+    }
+
     #[tokio::test]
     async fn test_peer_registration() {
         let server = create_test_server().await;
@@ -475,9 +2065,11 @@ mod tests {
             public_key: PublicKey([1u8; 32]),
             endpoints: vec!["192.168.1.100:51820".parse().unwrap()],
             metadata: PeerMetadata::default(),
+            auth_key: None,
+            proof: None,
         };
 
-        let response = server.register_peer(request).await.unwrap();
+        let response = server.add_peer(request, None, None).await.unwrap();
 
         assert!(response.peer_id != Uuid::nil());
         assert!(response.assigned_ip.to_string().starts_with("10.0.0."));
@@ -487,12 +2079,635 @@ mod tests {
         assert_eq!(peer_info.id, response.peer_id);
     }
 
+    #[tokio::test]
+    async fn test_update_and_unregister_peer() {
+        let server = create_test_server().await;
+
+        let request = RegisterPeerRequest {
+            public_key: PublicKey([2u8; 32]),
+            endpoints: vec!["192.168.1.100:51820".parse().unwrap()],
+            metadata: PeerMetadata::default(),
+            auth_key: None,
+            proof: None,
+        };
+        let registered = server.add_peer(request, None, None).await.unwrap();
+        assert_eq!(server.count_peers().await.unwrap(), 1);
+
+        let metadata = PeerMetadata {
+            name: Some("o'brien-laptop".to_string()),
+            ..Default::default()
+        };
+        let update = UpdatePeerRequest { endpoints: None, metadata: Some(metadata.clone()) };
+        let updated = server.update_peer(registered.peer_id, update).await.unwrap();
+        assert_eq!(updated.metadata, metadata);
+        assert_eq!(updated.endpoints.len(), 1);
+        assert_eq!(server.get_peer(registered.peer_id).await.unwrap(), updated);
+
+        server.unregister_peer(registered.peer_id).await.unwrap();
+        assert_eq!(server.count_peers().await.unwrap(), 0);
+        assert!(matches!(
+            server.get_peer(registered.peer_id).await,
+            Err(GhostwireError::PeerNotFound(_))
+        ));
+        assert!(matches!(
+            server.unregister_peer(registered.peer_id).await,
+            Err(GhostwireError::PeerNotFound(_))
+        ));
+
+        // The released address is handed out again
+        let request = RegisterPeerRequest {
+            public_key: PublicKey([3u8; 32]),
+            endpoints: vec![],
+            metadata: PeerMetadata::default(),
+            auth_key: None,
+            proof: None,
+        };
+        let reregistered = server.add_peer(request, None, None).await.unwrap();
+        assert_eq!(reregistered.assigned_ip, registered.assigned_ip);
+    }
+
+    #[tokio::test]
+    async fn test_peer_and_global_acl_rules() {
+        let server = create_test_server().await;
+
+        let request = RegisterPeerRequest {
+            public_key: PublicKey([4u8; 32]),
+            endpoints: vec![],
+            metadata: PeerMetadata::default(),
+            auth_key: None,
+            proof: None,
+        };
+        let peer_id = server.add_peer(request, None, None).await.unwrap().peer_id;
+        let generation = server.get_topology_generation().await.unwrap();
+
+        let rule = |peer_id, action| AclRule {
+            id: Uuid::nil(),
+            source_cidr: "10.0.0.0/24".to_string(),
+            dest_cidr: "10.0.0.0/24".to_string(),
+            action,
+            priority: 5,
+            description: None,
+            peer_id,
+            protocol: None,
+            ports: vec![],
+        };
+
+        let global = server.add_acl_rule(rule(None, AclAction::Allow)).await.unwrap();
+        // A peer rule may contradict a global one: the scopes differ
+        let scoped = server.add_acl_rule(rule(Some(peer_id), AclAction::Deny)).await.unwrap();
+        assert!(matches!(
+            server.add_acl_rule(rule(Some(peer_id), AclAction::Allow)).await,
+            Err(GhostwireError::AclConflict(id)) if id == scoped.id
+        ));
+
+        assert_eq!(server.get_global_acl_rules().await.unwrap(), vec![global.clone()]);
+        assert_eq!(server.get_peer(peer_id).await.unwrap().acl_rules, vec![scoped.clone()]);
+        assert_eq!(server.get_topology_generation().await.unwrap(), generation + 2);
+
+        // Moving the scoped rule to the global scope now contradicts the global rule
+        assert!(matches!(
+            server.update_acl_rule(scoped.id, rule(None, AclAction::Deny)).await,
+            Err(GhostwireError::AclConflict(id)) if id == global.id
+        ));
+
+        server.delete_acl_rule(global.id).await.unwrap();
+        assert!(matches!(
+            server.get_acl_rule(global.id).await,
+            Err(GhostwireError::AclRuleNotFound(_))
+        ));
+        assert_eq!(server.get_topology_generation().await.unwrap(), generation + 3);
+    }
+
     #[tokio::test]
     async fn test_acl_evaluation() {
         let server = create_test_server().await;
 
+        let (source, dest) = ("10.0.0.100".parse().unwrap(), "10.0.0.200".parse().unwrap());
+
         // Test default deny behavior
-        let allowed = server.evaluate_acl("10.0.0.100", "10.0.0.200").await.unwrap();
-        assert!(!allowed); // Should be denied by default
+        let decision = server.acl.load().evaluate(source, dest);
+        assert!(!decision.allowed()); // Should be denied by default
+        assert_eq!(decision.rule_id, None);
+
+        let rule = server.add_acl_rule(AclRule {
+            id: Uuid::nil(),
+            source_cidr: "10.0.0.0/25".to_string(),
+            dest_cidr: "10.0.0.128/25".to_string(),
+            action: AclAction::Allow,
+            priority: 0,
+            description: None,
+            peer_id: None,
+            protocol: None,
+            ports: vec![],
+        }).await.unwrap();
+
+        // The engine is swapped as soon as the rule is stored
+        let decision = server.acl.load().evaluate(source, dest);
+        assert!(decision.allowed());
+        assert_eq!(decision.rule_id, Some(rule.id));
+        assert!(!server.acl.load().evaluate(dest, source).allowed());
+
+        server.delete_acl_rule(rule.id).await.unwrap();
+        assert!(!server.acl.load().evaluate(source, dest).allowed());
+    }
+
+    #[tokio::test]
+    async fn test_peer_rules_only_guard_their_peer() {
+        let server = create_test_server().await;
+
+        let a = server.add_peer(register(18), None, None).await.unwrap();
+        let b = server.add_peer(register(19), None, None).await.unwrap();
+
+        let rule = |peer_id, action| AclRule {
+            id: Uuid::nil(),
+            source_cidr: "*".to_string(),
+            dest_cidr: "*".to_string(),
+            action,
+            priority: 0,
+            description: None,
+            peer_id,
+            protocol: None,
+            ports: vec![],
+        };
+        let global = server.add_acl_rule(rule(None, AclAction::Allow)).await.unwrap();
+        // a refuses everything, but only traffic to a
+        let scoped = server.add_acl_rule(rule(Some(a.peer_id), AclAction::Deny)).await.unwrap();
+
+        let decision = server.acl.load().evaluate(b.assigned_ip, a.assigned_ip);
+        assert!(!decision.allowed());
+        assert_eq!(decision.rule_id, Some(scoped.id));
+        let decision = server.acl.load().evaluate(a.assigned_ip, b.assigned_ip);
+        assert!(decision.allowed());
+        assert_eq!(decision.rule_id, Some(global.id));
+
+        // Stored sources are matched by the registered cidr_contains function
+        let listed = server.list_acl_rules(0, 10, Some(b.assigned_ip)).await.unwrap();
+        assert_eq!(listed.len(), 2);
+        assert_eq!(server.count_acl_rules(Some(b.assigned_ip)).await.unwrap(), 2);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_apply_policy_keeps_peer_rules() {
+        let server = create_test_server().await;
+
+        let request = RegisterPeerRequest {
+            public_key: PublicKey([7u8; 32]),
+            endpoints: vec![],
+            metadata: PeerMetadata::default(),
+            auth_key: None,
+            proof: None,
+        };
+        let peer = server.add_peer(request, None, None).await.unwrap();
+        let source = "10.0.0.200".parse().unwrap();
+
+        // The peer refuses SSH from everyone, whatever the policy says
+        let scoped = server.add_acl_rule(AclRule {
+            id: Uuid::nil(),
+            source_cidr: "*".to_string(),
+            dest_cidr: "*".to_string(),
+            action: AclAction::Deny,
+            priority: 100,
+            description: None,
+            peer_id: Some(peer.peer_id),
+            protocol: Some(policy::Protocol::Tcp),
+            ports: policy::parse_ports("22").unwrap(),
+        }).await.unwrap();
+
+        let mut policy = Policy::from_hujson(
+            r#"{ "acls": [{ "action": "accept", "src": ["10.0.0.0/24"], "dst": ["10.0.0.0/24:*"] }] }"#,
+        ).unwrap();
+        let address = peer.assigned_ip;
+        policy.tests = vec![policy::PolicyTest {
+            src: "10.0.0.200".to_string(),
+            proto: None,
+            accept: vec![format!("{}:22", address)],
+            deny: vec![],
+        }];
+        // The scoped rule still applies, so the assertion fails
+        assert!(matches!(
+            server.apply_policy(&policy).await,
+            Err(GhostwireError::PolicyTestFailed(report)) if report.failures[0].actual.rule_id == Some(scoped.id)
+        ));
+        assert!(server.get_global_acl_rules().await.unwrap().is_empty());
+
+        policy.tests[0].accept = vec![format!("{}:443", address)];
+        let generation = server.get_topology_generation().await.unwrap();
+        let rules = server.apply_policy(&policy).await.unwrap();
+        assert_eq!(server.get_global_acl_rules().await.unwrap(), rules);
+        assert_eq!(server.get_peer(peer.peer_id).await.unwrap().acl_rules, vec![scoped]);
+        assert_eq!(server.get_topology_generation().await.unwrap(), generation + 1);
+        assert!(server.acl.load().evaluate_packet(source, address, policy::Protocol::Tcp, Some(443)).allowed());
+        assert!(!server.acl.load().evaluate_packet(source, address, policy::Protocol::Tcp, Some(22)).allowed());
+    }
+
+    #[tokio::test]
+    async fn test_apply_policy_skips_unresolved_tests() {
+        let server = create_test_server().await;
+
+        // No peer carries the tag yet, as on a fresh database
+        let mut policy = Policy::from_hujson(
+            r#"{ "acls": [{ "action": "accept", "src": ["tag:ci"], "dst": ["10.0.0.0/24:22"] }] }"#,
+        ).unwrap();
+        policy.tests = vec![policy::PolicyTest {
+            src: "tag:ci".to_string(),
+            proto: None,
+            accept: vec!["10.0.0.10:22".to_string()],
+            deny: vec![],
+        }];
+
+        let rules = server.apply_policy(&policy).await.unwrap();
+        assert_eq!(server.get_global_acl_rules().await.unwrap(), rules);
+    }
+
+    #[tokio::test]
+    async fn test_tag_rules_follow_peer_metadata() {
+        let server = create_test_server().await;
+
+        let register = |key: u8| RegisterPeerRequest {
+            public_key: PublicKey([key; 32]),
+            endpoints: vec![],
+            metadata: PeerMetadata::default(),
+            auth_key: None,
+            proof: None,
+        };
+        let db = server.add_peer(register(5), None, None).await.unwrap();
+        let ci = server.add_peer(register(6), None, None).await.unwrap();
+
+        let rule = server.add_acl_rule(AclRule {
+            id: Uuid::nil(),
+            source_cidr: "tag:ci".to_string(),
+            dest_cidr: "tag:db".to_string(),
+            action: AclAction::Allow,
+            priority: 0,
+            description: None,
+            peer_id: None,
+            protocol: Some(policy::Protocol::Tcp),
+            ports: policy::parse_ports("5432").unwrap(),
+        }).await.unwrap();
+        assert_eq!(server.get_acl_rule(rule.id).await.unwrap(), rule);
+
+        let packet = |port| server.acl.load().evaluate_packet(ci.assigned_ip, db.assigned_ip, policy::Protocol::Tcp, Some(port));
+        // No peer carries the tags yet
+        assert!(!packet(5432).allowed());
+
+        for (peer, tag) in [(&db, "db"), (&ci, "ci")] {
+            let mut metadata = PeerMetadata::default();
+            metadata.tags.insert(tag.to_string(), String::new());
+            let update = UpdatePeerRequest { endpoints: None, metadata: Some(metadata) };
+            server.update_peer(peer.peer_id, update).await.unwrap();
+        }
+
+        assert_eq!(packet(5432).rule_id, Some(rule.id));
+        assert!(!packet(22).allowed());
+        // Address-only lookups ignore port-restricted rules
+        assert!(!server.acl.load().evaluate(ci.assigned_ip, db.assigned_ip).allowed());
+
+        let filter = server.peer_packet_filter(db.peer_id).await.unwrap();
+        assert_eq!(filter.rules.len(), 1);
+        assert!(server.peer_packet_filter(ci.peer_id).await.unwrap().rules.is_empty());
+    }
+
+    fn register(key: u8) -> RegisterPeerRequest {
+        RegisterPeerRequest {
+            public_key: PublicKey([key; 32]),
+            endpoints: vec![],
+            metadata: PeerMetadata::default(),
+            auth_key: None,
+            proof: None,
+        }
+    }
+
+    fn moved(port: u16) -> UpdatePeerRequest {
+        UpdatePeerRequest {
+            endpoints: Some(vec![SocketAddr::from(([192, 168, 1, 100], port))]),
+            metadata: None,
+        }
+    }
+
+    async fn delta(server: &CoordinationServer, since: u64, viewer: Option<Uuid>) -> TopologyDelta {
+        match server.topology_since(Some(since), viewer).await.unwrap() {
+            TopologySync::Delta(delta) => delta,
+            TopologySync::Snapshot(_) => panic!("expected a delta since generation {}", since),
+        }
+    }
+
+    async fn snapshot(server: &CoordinationServer, viewer: Uuid) -> NetworkTopology {
+        match server.topology_since(None, Some(viewer)).await.unwrap() {
+            TopologySync::Snapshot(topology) => topology,
+            TopologySync::Delta(_) => panic!("expected a snapshot"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_topology_deltas() {
+        let server = create_test_server().await;
+
+        let first = server.add_peer(register(8), None, None).await.unwrap();
+        let since = server.get_topology_generation().await.unwrap();
+
+        let second = server.add_peer(register(9), None, None).await.unwrap();
+        server.update_peer(first.peer_id, moved(51820)).await.unwrap();
+        // Added and removed within the window, so it is left out
+        let third = server.add_peer(register(10), None, None).await.unwrap();
+        server.unregister_peer(third.peer_id).await.unwrap();
+
+        let changes = delta(&server, since, None).await;
+        assert_eq!(changes.generation, since + 4);
+        assert_eq!(changes.added.peers, vec![server.get_peer(second.peer_id).await.unwrap()]);
+        assert_eq!(changes.updated.peers, vec![server.get_peer(first.peer_id).await.unwrap()]);
+        assert_eq!(changes.removed, RemovedEntities::default());
+
+        server.unregister_peer(first.peer_id).await.unwrap();
+        let changes = delta(&server, since + 4, None).await;
+        assert_eq!(changes.removed.peers, vec![first.peer_id]);
+        assert!(changes.added.peers.is_empty() && changes.updated.peers.is_empty());
+
+        let generation = server.get_topology_generation().await.unwrap();
+        assert!(delta(&server, generation, None).await.is_empty());
+        // A client ahead of the server starts over
+        assert!(matches!(
+            server.topology_since(Some(generation + 1), None).await.unwrap(),
+            TopologySync::Snapshot(topology) if topology.generation == generation
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_change_log_compaction() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = ServerConfig {
+            database_path: dir.path().join("ghostwire.db").to_string_lossy().to_string(),
+            network_cidr: "10.0.0.0/24".to_string(),
+            ..Default::default()
+        };
+
+        let mut server = CoordinationServer::new(&config, ZQLiteMetrics::new("test")).await.unwrap();
+        server.change_log_retention = 2;
+        let since = server.get_topology_generation().await.unwrap();
+        for key in 11..14 {
+            server.add_peer(register(key), None, None).await.unwrap();
+        }
+        let generation = server.get_topology_generation().await.unwrap();
+
+        // The changes after `since` are partly gone
+        assert!(matches!(
+            server.topology_since(Some(since), None).await.unwrap(),
+            TopologySync::Snapshot(topology) if topology.peers.len() == 3 && topology.generation == generation
+        ));
+        assert_eq!(delta(&server, generation - 1, None).await.added.peers.len(), 1);
+
+        // The generation and the log survive a restart
+        drop(server);
+        let server = CoordinationServer::new(&config, ZQLiteMetrics::new("test")).await.unwrap();
+        assert_eq!(server.get_topology_generation().await.unwrap(), generation);
+        assert_eq!(delta(&server, generation - 1, None).await.added.peers.len(), 1);
+        server.add_peer(register(14), None, None).await.unwrap();
+        assert_eq!(server.get_topology_generation().await.unwrap(), generation + 1);
+    }
+
+    #[tokio::test]
+    async fn test_peer_topology_view() {
+        let server = create_test_server().await;
+
+        let a = server.add_peer(register(15), None, None).await.unwrap();
+        let b = server.add_peer(register(16), None, None).await.unwrap();
+        let c = server.add_peer(register(17), None, None).await.unwrap();
+
+        // a may reach b; c may reach nobody
+        let rule = |peer_id, protocol, ports: &str| AclRule {
+            id: Uuid::nil(),
+            source_cidr: format!("{}/32", a.assigned_ip),
+            dest_cidr: format!("{}/32", b.assigned_ip),
+            action: AclAction::Allow,
+            priority: 0,
+            description: None,
+            peer_id,
+            protocol,
+            ports: policy::parse_ports(ports).unwrap(),
+        };
+        server.add_acl_rule(rule(None, None, "*")).await.unwrap();
+        let scoped = server
+            .add_acl_rule(rule(Some(b.peer_id), Some(policy::Protocol::Tcp), "22"))
+            .await
+            .unwrap();
+
+        let seen_by_a = snapshot(&server, a.peer_id).await;
+        let mut peers: Vec<Uuid> = seen_by_a.peers.keys().copied().collect();
+        let mut expected = vec![a.peer_id, b.peer_id];
+        peers.sort();
+        expected.sort();
+        assert_eq!(peers, expected);
+        // Other peers' scoped rules are theirs alone
+        assert!(seen_by_a.peers[&b.peer_id].acl_rules.is_empty());
+        let seen_by_b = snapshot(&server, b.peer_id).await;
+        assert!(seen_by_b.peers.contains_key(&a.peer_id));
+        assert_eq!(seen_by_b.peers[&b.peer_id].acl_rules, vec![scoped]);
+        let seen_by_c = snapshot(&server, c.peer_id).await;
+        assert_eq!(seen_by_c.peers.keys().collect::<Vec<_>>(), vec![&c.peer_id]);
+
+        let since = server.get_topology_generation().await.unwrap();
+        server.update_peer(b.peer_id, moved(1)).await.unwrap();
+        server.update_peer(c.peer_id, moved(2)).await.unwrap();
+        let changes = delta(&server, since, Some(a.peer_id)).await;
+        assert_eq!(changes.updated.peers.iter().map(|peer| peer.id).collect::<Vec<_>>(), vec![b.peer_id]);
+        // c may have just become hidden, so a is told to drop it
+        assert_eq!(changes.removed.peers, vec![c.peer_id]);
+
+        // A change to the viewer itself needs the whole view
+        let since = server.get_topology_generation().await.unwrap();
+        server.update_peer(a.peer_id, moved(3)).await.unwrap();
+        assert!(matches!(
+            server.topology_since(Some(since), Some(a.peer_id)).await.unwrap(),
+            TopologySync::Snapshot(_)
+        ));
+        assert!(matches!(
+            server.topology_since(None, Some(Uuid::new_v4())).await,
+            Err(GhostwireError::PeerNotFound(_))
+        ));
+    }
+
+    /// A registration request answering a fresh challenge with `node`
+    async fn signed(server: &CoordinationServer, node: &crypto::NodeKey, auth_key: &str, tag: Option<&str>) -> RegisterPeerRequest {
+        let challenge = server.issue_challenge().await.unwrap();
+        let mut metadata = PeerMetadata::default();
+        if let Some(tag) = tag {
+            metadata.tags.insert(tag.to_string(), String::new());
+        }
+        RegisterPeerRequest {
+            public_key: node.public_key(),
+            endpoints: vec![],
+            metadata,
+            auth_key: Some(auth_key.to_string()),
+            proof: Some(node.prove(&challenge).unwrap()),
+        }
+    }
+
+    /// The peer of a registration that did not wait on a login
+    fn complete(registration: Registration) -> RegisterPeerResponse {
+        match registration {
+            Registration::Complete(response) => response,
+            Registration::Pending(_) => panic!("registration is waiting on a login"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pre_auth_registration() {
+        let server = create_test_server().await;
+        let created = server.create_pre_auth_key(CreatePreAuthKeyRequest {
+            tags: vec!["tag:ci".to_string()],
+            ..Default::default()
+        }).await.unwrap();
+        assert_eq!(created.info.tags, vec!["ci".to_string()]);
+        let node = crypto::NodeKey::generate().unwrap();
+
+        let unsigned = RegisterPeerRequest { proof: None, ..signed(&server, &node, &created.key, None).await };
+        assert!(matches!(server.register_peer(unsigned).await, Err(GhostwireError::Unauthorized(_))));
+        let keyless = RegisterPeerRequest { auth_key: None, ..signed(&server, &node, &created.key, None).await };
+        assert!(matches!(server.register_peer(keyless).await, Err(GhostwireError::Unauthorized(_))));
+        let impostor = crypto::NodeKey::generate().unwrap();
+        let stolen = RegisterPeerRequest {
+            public_key: impostor.public_key(),
+            ..signed(&server, &node, &created.key, None).await
+        };
+        assert!(matches!(server.register_peer(stolen).await, Err(GhostwireError::Unauthorized(_))));
+        let ungranted = signed(&server, &node, &created.key, Some("prod")).await;
+        assert!(matches!(server.register_peer(ungranted).await, Err(GhostwireError::Forbidden(_))));
+
+        // The peer gets the key's tags
+        let request = signed(&server, &node, &created.key, None).await;
+        let replayed = request.clone();
+        let peer = complete(server.register_peer(request).await.unwrap());
+        let stored = server.get_peer(peer.peer_id).await.unwrap();
+        assert!(stored.metadata.tags.contains_key("ci"));
+        assert!(matches!(server.register_peer(replayed).await, Err(GhostwireError::Unauthorized(_))));
+        assert!(matches!(
+            server.register_peer(signed(&server, &node, &created.key, None).await).await,
+            Err(GhostwireError::PeerAlreadyRegistered(id)) if id == peer.peer_id
+        ));
+
+        // Single-use keys do not register a second node
+        let other = crypto::NodeKey::generate().unwrap();
+        assert!(matches!(
+            server.register_peer(signed(&server, &other, &created.key, None).await).await,
+            Err(GhostwireError::Unauthorized(_))
+        ));
+        assert!(matches!(
+            server.register_peer(signed(&server, &other, "gwauth-unknown", None).await).await,
+            Err(GhostwireError::Unauthorized(_))
+        ));
+
+        let expired = server.create_pre_auth_key(CreatePreAuthKeyRequest {
+            reusable: true,
+            expires_at: Some(Utc::now() - chrono::TimeDelta::seconds(1)),
+            ..Default::default()
+        }).await.unwrap();
+        assert!(matches!(
+            server.register_peer(signed(&server, &other, &expired.key, None).await).await,
+            Err(GhostwireError::Unauthorized(_))
+        ));
+
+        let reusable = server.create_pre_auth_key(CreatePreAuthKeyRequest {
+            reusable: true,
+            ephemeral: true,
+            ..Default::default()
+        }).await.unwrap();
+        let ephemeral = complete(server.register_peer(signed(&server, &other, &reusable.key, None).await).await.unwrap());
+        server.revoke_pre_auth_key(reusable.info.id).await.unwrap();
+        let third = crypto::NodeKey::generate().unwrap();
+        assert!(matches!(
+            server.register_peer(signed(&server, &third, &reusable.key, None).await).await,
+            Err(GhostwireError::Unauthorized(_))
+        ));
+        assert!(matches!(
+            server.revoke_pre_auth_key(Uuid::new_v4()).await,
+            Err(GhostwireError::PreAuthKeyNotFound(_))
+        ));
+
+        let keys = server.list_pre_auth_keys().await.unwrap();
+        assert_eq!(keys.len(), 3);
+        let listed = keys.iter().find(|key| key.id == created.info.id).unwrap();
+        assert_eq!(listed.used_count, 1);
+        assert!(keys.iter().find(|key| key.id == reusable.info.id).unwrap().revoked);
+
+        // Only peers of ephemeral keys are removed when they go away
+        assert!(!server.remove_ephemeral_peer(peer.peer_id).await.unwrap());
+        assert!(server.remove_ephemeral_peer(ephemeral.peer_id).await.unwrap());
+        assert!(matches!(server.get_peer(ephemeral.peer_id).await, Err(GhostwireError::PeerNotFound(_))));
+    }
+
+    /// Start registering `node` without a pre-auth key
+    async fn login(server: &CoordinationServer, node: &crypto::NodeKey) -> PendingRegistration {
+        let request = RegisterPeerRequest { auth_key: None, ..signed(server, node, "", None).await };
+        match server.register_peer(request).await.unwrap() {
+            Registration::Pending(pending) => pending,
+            Registration::Complete(_) => panic!("registration did not wait on a login"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_oidc_registration() {
+        let provider = oidc::mock::MockProvider::start().await;
+        let temp_file = NamedTempFile::new().unwrap();
+        let config = ServerConfig {
+            database_path: temp_file.path().to_string_lossy().to_string(),
+            network_cidr: "10.0.0.0/24".to_string(),
+            oidc: Some(provider.config()),
+            ..Default::default()
+        };
+        let server = CoordinationServer::new(&config, ZQLiteMetrics::new("test")).await.unwrap();
+
+        // Without a pre-auth key the registration waits for the user
+        let node = crypto::NodeKey::generate().unwrap();
+        let tagged = RegisterPeerRequest { auth_key: None, ..signed(&server, &node, "", Some("ci")).await };
+        assert!(matches!(server.register_peer(tagged).await, Err(GhostwireError::Forbidden(_))));
+
+        let pending = login(&server, &node).await;
+        let (code, state) = provider.authorize(&pending.auth_url, "alice", "alice@example.com");
+        assert!(matches!(
+            server.complete_login("unknown", &code).await,
+            Err(GhostwireError::Unauthorized(_))
+        ));
+        let peer = server.complete_login(&state, &code).await.unwrap();
+        assert!(matches!(server.complete_login(&state, &code).await, Err(GhostwireError::Unauthorized(_))));
+
+        let users = server.list_users().await.unwrap();
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].email.as_deref(), Some("alice@example.com"));
+        assert_eq!(server.get_peer(peer.peer_id).await.unwrap().user_id, Some(users[0].id));
+
+        // A second device of the same user is bound to the same user
+        let laptop = crypto::NodeKey::generate().unwrap();
+        let pending = login(&server, &laptop).await;
+        let (code, state) = provider.authorize(&pending.auth_url, "alice", "alice@example.org");
+        let second = server.complete_login(&state, &code).await.unwrap();
+        let users = server.list_users().await.unwrap();
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].email.as_deref(), Some("alice@example.org"));
+        assert_eq!(server.get_peer(second.peer_id).await.unwrap().user_id, Some(users[0].id));
+
+        // A cancelled login cannot be completed
+        let phone = crypto::NodeKey::generate().unwrap();
+        let pending = login(&server, &phone).await;
+        let (code, state) = provider.authorize(&pending.auth_url, "bob", "bob@example.com");
+        server.cancel_login(&state).await;
+        assert!(matches!(server.complete_login(&state, &code).await, Err(GhostwireError::Unauthorized(_))));
+        assert_eq!(server.list_users().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_sign_ins_create_one_user() {
+        let server = create_test_server().await;
+        let identity = Identity {
+            issuer: "https://id.example.com".to_string(),
+            subject: "o'neil".to_string(),
+            email: Some("oneil@example.com".to_string()),
+            name: Some("Pat O'Neil".to_string()),
+        };
+
+        let (first, second) = tokio::join!(server.sign_in(&identity), server.sign_in(&identity));
+        assert_eq!(first.unwrap(), second.unwrap());
+
+        let users = server.list_users().await.unwrap();
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].display_name.as_deref(), Some("Pat O'Neil"));
+    }
+}
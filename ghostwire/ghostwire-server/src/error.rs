@@ -0,0 +1,81 @@
+//! HTTP error responses
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use ghostwire_common::{protocol::ApiResponse, GhostwireError};
+use tracing::error;
+
+/// A `GhostwireError` returned from a handler
+///
+/// Responds with the matching status code and an `ApiResponse` error body.
+#[derive(Debug)]
+pub struct ApiError(pub GhostwireError);
+
+impl ApiError {
+    /// HTTP status code for the error
+    pub fn status(&self) -> StatusCode {
+        match &self.0 {
+            GhostwireError::PeerNotFound(_)
+            | GhostwireError::AclRuleNotFound(_)
+            | GhostwireError::PreAuthKeyNotFound(_)
+            | GhostwireError::ApiTokenNotFound(_) => StatusCode::NOT_FOUND,
+            GhostwireError::AclConflict(_) | GhostwireError::PeerAlreadyRegistered(_) => StatusCode::CONFLICT,
+            GhostwireError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            GhostwireError::Forbidden(_) => StatusCode::FORBIDDEN,
+            GhostwireError::InvalidCidr(_)
+            | GhostwireError::Acl(_)
+            | GhostwireError::PolicyTestFailed(_)
+            | GhostwireError::Crypto(_)
+            | GhostwireError::Serialization(_) => StatusCode::BAD_REQUEST,
+            // Address exhaustion is the only network error a request can hit
+            GhostwireError::Network(_) => StatusCode::SERVICE_UNAVAILABLE,
+            GhostwireError::Database(_) | GhostwireError::Config(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<GhostwireError> for ApiError {
+    fn from(error: GhostwireError) -> Self {
+        Self(error)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+
+        // Internal details stay in the log
+        let message = if status == StatusCode::INTERNAL_SERVER_ERROR {
+            error!(error = %self.0, "Request failed");
+            "Internal server error".to_string()
+        } else {
+            self.0.to_string()
+        };
+
+        (status, Json(ApiResponse::<()>::error(message))).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_status_codes() {
+        let status = |e| ApiError(e).status();
+        assert_eq!(status(GhostwireError::PeerNotFound(Uuid::nil())), StatusCode::NOT_FOUND);
+        assert_eq!(status(GhostwireError::ApiTokenNotFound(Uuid::nil())), StatusCode::NOT_FOUND);
+        assert_eq!(status(GhostwireError::InvalidCidr("10.0.0.0/33".into())), StatusCode::BAD_REQUEST);
+        assert_eq!(status(GhostwireError::AclConflict(Uuid::nil())), StatusCode::CONFLICT);
+        assert_eq!(status(GhostwireError::PeerAlreadyRegistered(Uuid::nil())), StatusCode::CONFLICT);
+        assert_eq!(status(GhostwireError::Unauthorized("bad key".into())), StatusCode::UNAUTHORIZED);
+        assert_eq!(status(GhostwireError::Forbidden("tag:prod".into())), StatusCode::FORBIDDEN);
+        assert_eq!(
+            status(GhostwireError::Database(anyhow::anyhow!("disk full"))),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+}
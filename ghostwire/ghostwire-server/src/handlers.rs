@@ -6,109 +6,211 @@ use axum::{
     response::{Json, Response},
 };
 use ghostwire_common::{
-    protocol::{ApiResponse, PaginationParams},
-    AclRule, PeerInfo, RegisterPeerRequest, RegisterPeerResponse,
+    policy::{PacketFilter, Policy},
+    protocol::{AclRuleParams, ApiResponse, OidcCallbackParams, PaginatedResponse, PaginationParams, TopologyParams},
+    AclRule, CreatePreAuthKeyRequest, CreatedPreAuthKey, GhostwireError, PeerInfo, PreAuthKey, RegisterPeerRequest,
+    RegisterPeerResponse, Registration, RegistrationChallenge, TopologySync, UpdatePeerRequest, User,
 };
 use uuid::Uuid;
 
-use crate::AppState;
+use crate::{error::ApiError, session, AppState};
 
-/// Register a new peer
+/// Register a new peer, or start the OIDC login it waits on
 pub async fn register_peer(
-    State(_state): State<AppState>,
-    Json(_request): Json<RegisterPeerRequest>,
-) -> Result<Json<ApiResponse<RegisterPeerResponse>>, StatusCode> {
-    // TODO: Implement peer registration
-    Err(StatusCode::NOT_IMPLEMENTED)
+    State(state): State<AppState>,
+    Json(request): Json<RegisterPeerRequest>,
+) -> Result<(StatusCode, Json<ApiResponse<Registration>>), ApiError> {
+    let registration = state.coordination_server.register_peer(request).await?;
+    let status = match registration {
+        Registration::Complete(_) => StatusCode::CREATED,
+        Registration::Pending(_) => StatusCode::ACCEPTED,
+    };
+    Ok((status, Json(ApiResponse::success(registration))))
+}
+
+/// Finish a registration when the OIDC provider redirects the user back
+pub async fn oidc_callback(
+    State(state): State<AppState>,
+    Query(params): Query<OidcCallbackParams>,
+) -> Result<Json<ApiResponse<RegisterPeerResponse>>, ApiError> {
+    let Some(code) = params.code else {
+        state.coordination_server.cancel_login(&params.state).await;
+        let reason = params.error.unwrap_or_else(|| "no authorization code".to_string());
+        return Err(GhostwireError::Unauthorized(format!("login failed: {}", reason)).into());
+    };
+    let response = state.coordination_server.complete_login(&params.state, &code).await?;
+    Ok(Json(ApiResponse::success(response)))
+}
+
+/// Issue a challenge for a registering node to sign
+pub async fn registration_challenge(
+    State(state): State<AppState>,
+) -> Result<Json<ApiResponse<RegistrationChallenge>>, ApiError> {
+    let challenge = state.coordination_server.issue_challenge().await?;
+    Ok(Json(ApiResponse::success(challenge)))
 }
 
 /// Get peer by ID
 pub async fn get_peer(
-    State(_state): State<AppState>,
-    Path(_peer_id): Path<Uuid>,
-) -> Result<Json<ApiResponse<PeerInfo>>, StatusCode> {
-    // TODO: Implement get peer
-    Err(StatusCode::NOT_IMPLEMENTED)
+    State(state): State<AppState>,
+    Path(peer_id): Path<Uuid>,
+) -> Result<Json<ApiResponse<PeerInfo>>, ApiError> {
+    let peer = state.coordination_server.get_peer(peer_id).await?;
+    Ok(Json(ApiResponse::success(peer)))
 }
 
 /// List all peers
 pub async fn list_peers(
-    State(_state): State<AppState>,
-    Query(_params): Query<PaginationParams>,
-) -> Result<Json<ApiResponse<Vec<PeerInfo>>>, StatusCode> {
-    // TODO: Implement list peers
-    Err(StatusCode::NOT_IMPLEMENTED)
+    State(state): State<AppState>,
+    Query(params): Query<PaginationParams>,
+) -> Result<Json<ApiResponse<PaginatedResponse<PeerInfo>>>, ApiError> {
+    let (page, page_size) = (params.page(), params.page_size());
+
+    let peers = state
+        .coordination_server
+        .list_peers(page.saturating_mul(page_size), page_size)
+        .await?;
+    let total_count = state.coordination_server.count_peers().await?;
+
+    Ok(Json(ApiResponse::success(PaginatedResponse::new(peers, page, page_size, total_count))))
 }
 
 /// Update peer information
 pub async fn update_peer(
-    State(_state): State<AppState>,
-    Path(_peer_id): Path<Uuid>,
-    Json(_peer_info): Json<PeerInfo>,
-) -> Result<Json<ApiResponse<PeerInfo>>, StatusCode> {
-    // TODO: Implement update peer
-    Err(StatusCode::NOT_IMPLEMENTED)
+    State(state): State<AppState>,
+    Path(peer_id): Path<Uuid>,
+    Json(request): Json<UpdatePeerRequest>,
+) -> Result<Json<ApiResponse<PeerInfo>>, ApiError> {
+    let peer = state.coordination_server.update_peer(peer_id, request).await?;
+    Ok(Json(ApiResponse::success(peer)))
 }
 
 /// Unregister a peer
 pub async fn unregister_peer(
-    State(_state): State<AppState>,
-    Path(_peer_id): Path<Uuid>,
-) -> Result<StatusCode, StatusCode> {
-    // TODO: Implement unregister peer
-    Err(StatusCode::NOT_IMPLEMENTED)
+    State(state): State<AppState>,
+    Path(peer_id): Path<Uuid>,
+) -> Result<StatusCode, ApiError> {
+    state.coordination_server.unregister_peer(peer_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Get the packet filter a peer enforces
+pub async fn get_peer_filter(
+    State(state): State<AppState>,
+    Path(peer_id): Path<Uuid>,
+) -> Result<Json<ApiResponse<PacketFilter>>, ApiError> {
+    let filter = state.coordination_server.peer_packet_filter(peer_id).await?;
+    Ok(Json(ApiResponse::success(filter)))
 }
 
-/// Get network topology
+/// Get network topology, as changes since `since` when the server still
+/// has them
 pub async fn get_topology(
-    State(_state): State<AppState>,
-) -> Result<Json<ApiResponse<ghostwire_common::NetworkTopology>>, StatusCode> {
-    // TODO: Implement get topology
-    Err(StatusCode::NOT_IMPLEMENTED)
+    State(state): State<AppState>,
+    Query(params): Query<TopologyParams>,
+) -> Result<Json<ApiResponse<TopologySync>>, ApiError> {
+    let topology = state
+        .coordination_server
+        .topology_since(params.since, params.peer_id)
+        .await?;
+    Ok(Json(ApiResponse::success(topology)))
 }
 
 /// Get ACL rules
 pub async fn get_acl_rules(
-    State(_state): State<AppState>,
-    Query(_params): Query<PaginationParams>,
-) -> Result<Json<ApiResponse<Vec<AclRule>>>, StatusCode> {
-    // TODO: Implement get ACL rules
-    Err(StatusCode::NOT_IMPLEMENTED)
+    State(state): State<AppState>,
+    Query(params): Query<PaginationParams>,
+    Query(filter): Query<AclRuleParams>,
+) -> Result<Json<ApiResponse<PaginatedResponse<AclRule>>>, ApiError> {
+    let (page, page_size) = (params.page(), params.page_size());
+
+    let rules = state
+        .coordination_server
+        .list_acl_rules(page.saturating_mul(page_size), page_size, filter.source)
+        .await?;
+    let total_count = state.coordination_server.count_acl_rules(filter.source).await?;
+
+    Ok(Json(ApiResponse::success(PaginatedResponse::new(rules, page, page_size, total_count))))
 }
 
 /// Add ACL rule
 pub async fn add_acl_rule(
-    State(_state): State<AppState>,
-    Json(_rule): Json<AclRule>,
-) -> Result<Json<ApiResponse<AclRule>>, StatusCode> {
-    // TODO: Implement add ACL rule
-    Err(StatusCode::NOT_IMPLEMENTED)
+    State(state): State<AppState>,
+    Json(rule): Json<AclRule>,
+) -> Result<(StatusCode, Json<ApiResponse<AclRule>>), ApiError> {
+    let rule = state.coordination_server.add_acl_rule(rule).await?;
+    Ok((StatusCode::CREATED, Json(ApiResponse::success(rule))))
 }
 
 /// Update ACL rule
 pub async fn update_acl_rule(
-    State(_state): State<AppState>,
-    Path(_rule_id): Path<Uuid>,
-    Json(_rule): Json<AclRule>,
-) -> Result<Json<ApiResponse<AclRule>>, StatusCode> {
-    // TODO: Implement update ACL rule
-    Err(StatusCode::NOT_IMPLEMENTED)
+    State(state): State<AppState>,
+    Path(rule_id): Path<Uuid>,
+    Json(rule): Json<AclRule>,
+) -> Result<Json<ApiResponse<AclRule>>, ApiError> {
+    let rule = state.coordination_server.update_acl_rule(rule_id, rule).await?;
+    Ok(Json(ApiResponse::success(rule)))
 }
 
 /// Delete ACL rule
 pub async fn delete_acl_rule(
-    State(_state): State<AppState>,
-    Path(_rule_id): Path<Uuid>,
-) -> Result<StatusCode, StatusCode> {
-    // TODO: Implement delete ACL rule
-    Err(StatusCode::NOT_IMPLEMENTED)
+    State(state): State<AppState>,
+    Path(rule_id): Path<Uuid>,
+) -> Result<StatusCode, ApiError> {
+    state.coordination_server.delete_acl_rule(rule_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Replace the network-wide ACL rules with a HuJSON policy
+///
+/// Rejected with the test report if any of the policy's tests fail.
+pub async fn apply_policy(
+    State(state): State<AppState>,
+    body: String,
+) -> Result<Json<ApiResponse<Vec<AclRule>>>, ApiError> {
+    let policy = Policy::from_hujson(&body)?;
+    let rules = state.coordination_server.apply_policy(&policy).await?;
+    Ok(Json(ApiResponse::success(rules)))
+}
+
+/// Mint a pre-auth key; the response is the only time its secret is shown
+pub async fn create_pre_auth_key(
+    State(state): State<AppState>,
+    Json(request): Json<CreatePreAuthKeyRequest>,
+) -> Result<(StatusCode, Json<ApiResponse<CreatedPreAuthKey>>), ApiError> {
+    let key = state.coordination_server.create_pre_auth_key(request).await?;
+    Ok((StatusCode::CREATED, Json(ApiResponse::success(key))))
+}
+
+/// List pre-auth keys, without their secrets
+pub async fn list_pre_auth_keys(
+    State(state): State<AppState>,
+) -> Result<Json<ApiResponse<Vec<PreAuthKey>>>, ApiError> {
+    let keys = state.coordination_server.list_pre_auth_keys().await?;
+    Ok(Json(ApiResponse::success(keys)))
+}
+
+/// Revoke a pre-auth key
+pub async fn revoke_pre_auth_key(
+    State(state): State<AppState>,
+    Path(key_id): Path<Uuid>,
+) -> Result<StatusCode, ApiError> {
+    state.coordination_server.revoke_pre_auth_key(key_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// List users who have logged in with OIDC
+pub async fn list_users(
+    State(state): State<AppState>,
+) -> Result<Json<ApiResponse<Vec<User>>>, ApiError> {
+    let users = state.coordination_server.list_users().await?;
+    Ok(Json(ApiResponse::success(users)))
 }
 
 /// WebSocket handler for real-time updates
 pub async fn websocket_handler(
-    _ws: WebSocketUpgrade,
-    State(_state): State<AppState>,
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
 ) -> Response {
-    // TODO: Implement WebSocket handler
-    StatusCode::NOT_IMPLEMENTED.into_response()
+    ws.on_upgrade(move |socket| session::serve(socket, state.coordination_server, state.sessions))
 }
\ No newline at end of file
@@ -4,30 +4,36 @@
 
 use anyhow::{Context, Result};
 use axum::{
-    extract::{Path, Query, State},
+    extract::State,
     http::StatusCode,
     response::Json,
     routing::{delete, get, post, put},
     Router,
 };
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use ghostwire_common::{
-    protocol::{ApiResponse, PaginationParams},
-    GhostwireError, PeerInfo, RegisterPeerRequest, RegisterPeerResponse, ServerConfig,
+    policy::{self, Policy},
+    protocol::ApiResponse,
+    AclAction, ApiScope,
+    ServerConfig,
 };
-use std::{net::SocketAddr, sync::Arc};
+use std::{net::SocketAddr, path::{Path as FsPath, PathBuf}, sync::Arc};
 use tokio::net::TcpListener;
-use tracing::{error, info, warn};
+use tower_http::cors::CorsLayer;
+use tracing::info;
 use uuid::Uuid;
 
 mod config;
 mod coordination;
 mod database;
+mod error;
 mod handlers;
-mod metrics;
 mod middleware;
+mod oidc;
+mod session;
 
 use coordination::CoordinationServer;
+use session::{SessionConfig, SessionHub};
 
 /// Command line arguments
 #[derive(Parser, Debug)]
@@ -48,24 +54,75 @@ struct Args {
     /// Log level
     #[arg(long, default_value = "info")]
     log_level: String,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// Offline tools; without one the server runs
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Work with ACL policy files
+    Policy {
+        #[command(subcommand)]
+        action: PolicyCommand,
+    },
+    /// Manage admin API tokens in the configured database
+    Token {
+        #[command(subcommand)]
+        action: TokenCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum PolicyCommand {
+    /// Validate a HuJSON or TOML policy file and list the rules it defines
+    Check {
+        /// Policy file; `.toml` files are read as TOML, anything else as HuJSON
+        path: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum TokenCommand {
+    /// Create a token and print its secret, which is not shown again
+    Create {
+        /// Name to tell the token apart by
+        name: String,
+        /// Scope to grant: peers:read, peers:write, acl:read, acl:write or admin
+        #[arg(long = "scope", required = true)]
+        scopes: Vec<ApiScope>,
+        /// Days until the token expires; omitted for a token that never does
+        #[arg(long)]
+        expires_in_days: Option<u32>,
+    },
+    /// List tokens without their secrets
+    List,
+    /// Revoke a token
+    Revoke {
+        /// Token ID, as shown by `token list`
+        id: Uuid,
+    },
 }
 
 /// Application state shared across handlers
 #[derive(Clone)]
 struct AppState {
     coordination_server: Arc<CoordinationServer>,
-    metrics: zqlite_rs::ZQLiteMetrics,
+    sessions: Arc<SessionHub>,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
 
+    if let Some(Command::Policy { action: PolicyCommand::Check { path } }) = &args.command {
+        return check_policy(path);
+    }
+
     // Initialize tracing
     init_tracing(&args.log_level)?;
 
-    info!("Starting Ghostwire Coordination Server v{}", env!("CARGO_PKG_VERSION"));
-
     // Load configuration
     let mut config = config::load_config(&args.config).await?;
 
@@ -77,6 +134,12 @@ async fn main() -> Result<()> {
         config.bind_address = bind;
     }
 
+    if let Some(Command::Token { action }) = args.command {
+        return manage_tokens(&config, action).await;
+    }
+
+    info!("Starting Ghostwire Coordination Server v{}", env!("CARGO_PKG_VERSION"));
+
     info!("Configuration loaded: {:#?}", config);
 
     // Initialize metrics
@@ -92,6 +155,7 @@ async fn main() -> Result<()> {
 
             zqlite_rs::init_prometheus_exporter(prometheus_config)
                 .await
+                .map_err(anyhow::Error::from_boxed)
                 .context("Failed to initialize Prometheus metrics")?;
 
             info!("Prometheus metrics server started on {}", prometheus_addr);
@@ -99,17 +163,29 @@ async fn main() -> Result<()> {
     }
 
     // Initialize coordination server with ZQLite backend
-    let coordination_server = CoordinationServer::new(&config, metrics.clone())
+    let coordination_server = CoordinationServer::new(&config, metrics)
         .await
         .context("Failed to initialize coordination server")?;
 
+    if let Some(policy_path) = &config.policy_path {
+        let policy = Policy::load(FsPath::new(policy_path))
+            .with_context(|| format!("Failed to load policy {}", policy_path))?;
+        let rules = coordination_server
+            .apply_policy(&policy)
+            .await
+            .with_context(|| format!("Failed to apply policy {}", policy_path))?;
+        info!(rules = rules.len(), "Applied ACL policy {}", policy_path);
+    }
+
+    let cors = middleware::cors_middleware(&config.cors_allowed_origins)?;
+
     let app_state = AppState {
         coordination_server: Arc::new(coordination_server),
-        metrics,
+        sessions: Arc::new(SessionHub::new(SessionConfig::default())),
     };
 
     // Build the application router
-    let app = build_router(app_state);
+    let app = build_router(app_state, cors);
 
     // Start the server
     let listener = TcpListener::bind(&config.bind_address)
@@ -125,6 +201,85 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+/// Validate a policy file and run its tests without touching the database
+///
+/// With no peers to match, tests naming tags or peers are skipped; the
+/// server runs them when the policy is applied.
+fn check_policy(path: &FsPath) -> Result<()> {
+    let policy = Policy::load(path).with_context(|| format!("Invalid policy {}", path.display()))?;
+    let rules = policy.to_acl_rules().with_context(|| format!("Invalid policy {}", path.display()))?;
+    let report = policy.run_tests(&rules, &[]).with_context(|| format!("Invalid policy {}", path.display()))?;
+    if !report.failures.is_empty() {
+        anyhow::bail!("Policy tests failed in {}:\n{}", path.display(), report);
+    }
+
+    for rule in &rules {
+        let action = match rule.action {
+            AclAction::Allow => "accept",
+            AclAction::Deny => "deny",
+        };
+        let protocol = rule.protocol.map_or("*".to_string(), |p| p.to_string());
+        println!(
+            "{:>4}  {:<6} {} -> {} proto {} ports {}",
+            rule.priority,
+            action,
+            rule.source_cidr,
+            rule.dest_cidr,
+            protocol,
+            policy::format_ports(&rule.ports)
+        );
+    }
+    for skipped in &report.unresolved {
+        println!("skipped {}", skipped);
+    }
+    println!(
+        "{}: {} rules OK, {} tests checked",
+        path.display(),
+        rules.len(),
+        policy.tests.len() - report.unresolved.len()
+    );
+    Ok(())
+}
+
+/// Create, list or revoke admin API tokens in the configured database
+async fn manage_tokens(config: &ServerConfig, action: TokenCommand) -> Result<()> {
+    let metrics = zqlite_rs::ZQLiteMetrics::new("ghostwire_server");
+    let server = CoordinationServer::new(config, metrics)
+        .await
+        .context("Failed to open the coordination database")?;
+
+    match action {
+        TokenCommand::Create { name, scopes, expires_in_days } => {
+            let expires_at = expires_in_days.map(|days| chrono::Utc::now() + chrono::TimeDelta::days(days.into()));
+            let (secret, token) = server.create_api_token(&name, scopes, expires_at).await?;
+            println!("{}", secret);
+            eprintln!("Created token {} ({}); its secret is not shown again", token.id, token.name);
+        }
+        TokenCommand::List => {
+            for token in server.list_api_tokens().await? {
+                let scopes: Vec<String> = token.scopes.iter().map(ApiScope::to_string).collect();
+                let date = |at: Option<chrono::DateTime<chrono::Utc>>| {
+                    at.map_or("never".to_string(), |at| at.format("%Y-%m-%d %H:%M").to_string())
+                };
+                println!(
+                    "{}  {:<20} {:<30} expires {:<16}  last used {:<16}{}",
+                    token.id,
+                    token.name,
+                    scopes.join(","),
+                    date(token.expires_at),
+                    date(token.last_used_at),
+                    if token.revoked { "  revoked" } else { "" }
+                );
+            }
+        }
+        TokenCommand::Revoke { id } => {
+            server.revoke_api_token(id).await?;
+            println!("Revoked token {}", id);
+        }
+    }
+    Ok(())
+}
+
 /// Initialize tracing/logging
 fn init_tracing(log_level: &str) -> Result<()> {
     use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
@@ -146,24 +301,60 @@ fn init_tracing(log_level: &str) -> Result<()> {
 }
 
 /// Build the application router with all routes
-fn build_router(state: AppState) -> Router {
-    Router::new()
-        // Health check endpoint
-        .route("/health", get(health_check))
+///
+/// Admin routes need a bearer API token with the route's scope; peers
+/// register and connect with their node keys instead.
+fn build_router(state: AppState, cors: CorsLayer) -> Router {
+    let scoped = |scope| {
+        axum::middleware::from_fn_with_state(
+            (Arc::clone(&state.coordination_server), scope),
+            middleware::require_scope,
+        )
+    };
 
-        // API v1 routes
-        .route("/api/v1/peers/register", post(handlers::register_peer))
+    let peers_read = Router::new()
         .route("/api/v1/peers", get(handlers::list_peers))
-        .route("/api/v1/peers/:id", get(handlers::get_peer))
-        .route("/api/v1/peers/:id", put(handlers::update_peer))
-        .route("/api/v1/peers/:id", delete(handlers::unregister_peer))
-
+        .route("/api/v1/peers/{id}", get(handlers::get_peer))
+        .route("/api/v1/peers/{id}/filter", get(handlers::get_peer_filter))
         .route("/api/v1/topology", get(handlers::get_topology))
+        .route_layer(scoped(ApiScope::PeersRead));
 
+    let peers_write = Router::new()
+        .route("/api/v1/peers/{id}", put(handlers::update_peer))
+        .route("/api/v1/peers/{id}", delete(handlers::unregister_peer))
+        .route_layer(scoped(ApiScope::PeersWrite));
+
+    let acl_read = Router::new()
         .route("/api/v1/acl/rules", get(handlers::get_acl_rules))
+        .route_layer(scoped(ApiScope::AclRead));
+
+    let acl_write = Router::new()
         .route("/api/v1/acl/rules", post(handlers::add_acl_rule))
-        .route("/api/v1/acl/rules/:id", put(handlers::update_acl_rule))
-        .route("/api/v1/acl/rules/:id", delete(handlers::delete_acl_rule))
+        .route("/api/v1/acl/rules/{id}", put(handlers::update_acl_rule))
+        .route("/api/v1/acl/rules/{id}", delete(handlers::delete_acl_rule))
+        .route("/api/v1/acl/policy", put(handlers::apply_policy))
+        .route_layer(scoped(ApiScope::AclWrite));
+
+    let admin = Router::new()
+        .route("/api/v1/auth/keys", get(handlers::list_pre_auth_keys))
+        .route("/api/v1/auth/keys", post(handlers::create_pre_auth_key))
+        .route("/api/v1/auth/keys/{id}", delete(handlers::revoke_pre_auth_key))
+        .route("/api/v1/users", get(handlers::list_users))
+        .route_layer(scoped(ApiScope::Admin));
+
+    Router::new()
+        // Health check endpoint
+        .route("/health", get(health_check))
+
+        // API v1 routes
+        .route("/api/v1/peers/register", post(handlers::register_peer))
+        .route("/api/v1/peers/challenge", post(handlers::registration_challenge))
+        .route("/api/v1/auth/oidc/callback", get(handlers::oidc_callback))
+        .merge(peers_read)
+        .merge(peers_write)
+        .merge(acl_read)
+        .merge(acl_write)
+        .merge(admin)
 
         // WebSocket endpoint for real-time updates
         .route("/api/v1/ws", get(handlers::websocket_handler))
@@ -173,7 +364,7 @@ fn build_router(state: AppState) -> Router {
 
         // Add middleware
         .layer(middleware::logging_middleware())
-        .layer(middleware::cors_middleware())
+        .layer(cors)
         .layer(middleware::timeout_middleware())
 
         // Add shared state
@@ -181,11 +372,12 @@ fn build_router(state: AppState) -> Router {
 }
 
 /// Health check handler
-async fn health_check() -> Json<ApiResponse<serde_json::Value>> {
+async fn health_check(State(state): State<AppState>) -> Json<ApiResponse<serde_json::Value>> {
     let health_data = serde_json::json!({
         "status": "healthy",
         "version": env!("CARGO_PKG_VERSION"),
         "timestamp": chrono::Utc::now(),
+        "connected_peers": state.sessions.connected_peers(),
     });
 
     Json(ApiResponse::success(health_data))
@@ -201,18 +393,76 @@ async fn metrics_handler() -> Result<String, StatusCode> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use axum::http::header;
     use axum_test::TestServer;
+    use ghostwire_common::{
+        crypto::NodeKey, AclRule, CreatedPreAuthKey, OidcConfig, PeerInfo, PreAuthKey, RegisterPeerRequest,
+        RegisterPeerResponse, Registration, RegistrationChallenge, TopologySync, User,
+    };
+
+    /// App state backed by a database in a temp dir that lives as long as the guard
+    async fn create_test_state(oidc: Option<OidcConfig>) -> (AppState, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let config = ServerConfig {
+            database_path: dir.path().join("ghostwire.db").to_string_lossy().to_string(),
+            network_cidr: "10.0.0.0/24".to_string(),
+            oidc,
+            ..Default::default()
+        };
+
+        let metrics = zqlite_rs::ZQLiteMetrics::new("test");
+        let coordination_server = CoordinationServer::new(&config, metrics).await.unwrap();
+        let state = AppState {
+            coordination_server: Arc::new(coordination_server),
+            sessions: Arc::new(SessionHub::new(SessionConfig::default())),
+        };
+        (state, dir)
+    }
 
-    fn create_test_state() -> AppState {
-        // This would create a test coordination server
-        // For now, we'll skip the actual implementation
-        todo!("Create test coordination server")
+    /// Test server whose requests carry an admin API token
+    async fn create_test_server() -> (TestServer, tempfile::TempDir) {
+        let (state, dir) = create_test_state(None).await;
+        (admin_test_server(state).await, dir)
+    }
+
+    /// Test server for `state` whose requests carry an admin API token
+    async fn admin_test_server(state: AppState) -> TestServer {
+        let (token, _) = state
+            .coordination_server
+            .create_api_token("test", vec![ApiScope::Admin], None)
+            .await
+            .unwrap();
+        let mut server = TestServer::new(build_router(state, CorsLayer::new())).unwrap();
+        server.add_header(header::AUTHORIZATION, format!("Bearer {}", token));
+        server
+    }
+
+    /// A registration request for a new node, with a pre-auth key for
+    /// `tags` minted over the API
+    async fn register_request(server: &TestServer, tags: &[&str]) -> RegisterPeerRequest {
+        register_node(server, &NodeKey::generate().unwrap(), tags).await
+    }
+
+    /// A registration request for `node`, with a fresh challenge and pre-auth key
+    async fn register_node(server: &TestServer, node: &NodeKey, tags: &[&str]) -> RegisterPeerRequest {
+        let response = server.post("/api/v1/auth/keys").json(&serde_json::json!({ "tags": tags })).await;
+        response.assert_status(StatusCode::CREATED);
+        let key = response.json::<ApiResponse<CreatedPreAuthKey>>().data.unwrap();
+        let challenge = server.post("/api/v1/peers/challenge").await;
+        let challenge = challenge.json::<ApiResponse<RegistrationChallenge>>().data.unwrap();
+
+        RegisterPeerRequest {
+            public_key: node.public_key(),
+            endpoints: vec!["192.168.1.100:51820".parse().unwrap()],
+            metadata: Default::default(),
+            auth_key: Some(key.key),
+            proof: Some(node.prove(&challenge).unwrap()),
+        }
     }
 
     #[tokio::test]
     async fn test_health_check() {
-        let app = build_router(create_test_state());
-        let server = TestServer::new(app).unwrap();
+        let (server, _dir) = create_test_server().await;
 
         let response = server.get("/health").await;
         response.assert_status_ok();
@@ -221,4 +471,566 @@ mod tests {
         assert!(body.success);
         assert!(body.data.is_some());
     }
+
+    #[tokio::test]
+    async fn test_peer_lifecycle() {
+        let (server, _dir) = create_test_server().await;
+
+        let request = register_request(&server, &[]).await;
+        let response = server.post("/api/v1/peers/register").json(&request).await;
+        response.assert_status(StatusCode::CREATED);
+        let registered = response.json::<ApiResponse<RegisterPeerResponse>>().data.unwrap();
+        let path = format!("/api/v1/peers/{}", registered.peer_id);
+
+        let peer = server.get(&path).await.json::<ApiResponse<PeerInfo>>().data.unwrap();
+        assert_eq!(peer.id, registered.peer_id);
+
+        // Quotes are bound as parameters, never spliced into the SQL
+        let update = serde_json::json!({ "metadata": { "name": "Bob's laptop", "tags": {}, "attributes": null } });
+        let response = server.put(&path).json(&update).await;
+        response.assert_status_ok();
+        let updated = response.json::<ApiResponse<PeerInfo>>().data.unwrap();
+        assert_eq!(updated.metadata.name.as_deref(), Some("Bob's laptop"));
+        assert_eq!(updated.endpoints, peer.endpoints);
+
+        let stored = server.get(&path).await.json::<ApiResponse<PeerInfo>>().data.unwrap();
+        assert_eq!(stored.metadata.name.as_deref(), Some("Bob's laptop"));
+
+        server.delete(&path).await.assert_status(StatusCode::NO_CONTENT);
+
+        let response = server.get(&path).await;
+        response.assert_status_not_found();
+        let body: ApiResponse<PeerInfo> = response.json();
+        assert!(!body.success);
+        assert!(body.error.unwrap().contains(&registered.peer_id.to_string()));
+
+        server.delete(&path).await.assert_status_not_found();
+    }
+
+    #[tokio::test]
+    async fn test_list_peers_paginates() {
+        let (server, _dir) = create_test_server().await;
+
+        for _ in 1..=3 {
+            server
+                .post("/api/v1/peers/register")
+                .json(&register_request(&server, &[]).await)
+                .await
+                .assert_status(StatusCode::CREATED);
+        }
+
+        let response = server
+            .get("/api/v1/peers")
+            .add_query_param("page", 1)
+            .add_query_param("page_size", 2)
+            .await;
+        response.assert_status_ok();
+
+        let page = response
+            .json::<ApiResponse<ghostwire_common::protocol::PaginatedResponse<PeerInfo>>>()
+            .data
+            .unwrap();
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(page.total_count, 3);
+        assert_eq!(page.total_pages, 2);
+        assert!(page.has_previous);
+        assert!(!page.has_next);
+    }
+
+    #[tokio::test]
+    async fn test_update_unknown_peer() {
+        let (server, _dir) = create_test_server().await;
+
+        let response = server
+            .put(&format!("/api/v1/peers/{}", Uuid::new_v4()))
+            .json(&serde_json::json!({ "endpoints": [] }))
+            .await;
+        response.assert_status_not_found();
+    }
+
+    #[tokio::test]
+    async fn test_acl_rule_crud() {
+        let (server, _dir) = create_test_server().await;
+
+        let rule = serde_json::json!({
+            "source_cidr": "10.0.0.0/24",
+            "dest_cidr": "10.0.1.0/24",
+            "action": "allow",
+            "priority": 10,
+            "description": "office to servers",
+        });
+        let response = server.post("/api/v1/acl/rules").json(&rule).await;
+        response.assert_status(StatusCode::CREATED);
+        let added = response.json::<ApiResponse<AclRule>>().data.unwrap();
+        assert!(!added.id.is_nil());
+        assert_eq!(added.peer_id, None);
+
+        // Same traffic, same priority, opposite action
+        let mut deny = rule.clone();
+        deny["action"] = "deny".into();
+        deny["source_cidr"] = "10.0.0.128/25".into();
+        server.post("/api/v1/acl/rules").json(&deny).await.assert_status(StatusCode::CONFLICT);
+
+        // A higher priority settles the order, so it is accepted
+        deny["priority"] = 20.into();
+        server.post("/api/v1/acl/rules").json(&deny).await.assert_status(StatusCode::CREATED);
+
+        let mut invalid = rule.clone();
+        invalid["dest_cidr"] = "10.0.1.0/33".into();
+        server.post("/api/v1/acl/rules").json(&invalid).await.assert_status_bad_request();
+
+        let path = format!("/api/v1/acl/rules/{}", added.id);
+        let mut update = rule.clone();
+        update["priority"] = 30.into();
+        let updated = server.put(&path).json(&update).await.json::<ApiResponse<AclRule>>().data.unwrap();
+        assert_eq!(updated.id, added.id);
+        assert_eq!(updated.priority, 30);
+
+        let page = server
+            .get("/api/v1/acl/rules")
+            .await
+            .json::<ApiResponse<ghostwire_common::protocol::PaginatedResponse<AclRule>>>()
+            .data
+            .unwrap();
+        assert_eq!(page.total_count, 2);
+        assert_eq!(page.items[0].id, added.id);
+
+        // Only rules whose source holds the address are listed
+        for (source, expected) in [("10.0.0.7", 1), ("10.0.0.200", 2), ("10.9.9.9", 0)] {
+            let page = server
+                .get("/api/v1/acl/rules")
+                .add_query_param("source", source)
+                .await
+                .json::<ApiResponse<ghostwire_common::protocol::PaginatedResponse<AclRule>>>()
+                .data
+                .unwrap();
+            assert_eq!(page.total_count, expected);
+            assert_eq!(page.items.len() as u64, expected);
+        }
+        server.get("/api/v1/acl/rules").add_query_param("source", "tag:ci").await.assert_status_bad_request();
+
+        server.delete(&path).await.assert_status(StatusCode::NO_CONTENT);
+        server.delete(&path).await.assert_status_not_found();
+        server.put(&path).json(&update).await.assert_status_not_found();
+    }
+
+    #[tokio::test]
+    async fn test_peer_acl_rule_requires_peer() {
+        let (server, _dir) = create_test_server().await;
+
+        let rule = serde_json::json!({
+            "source_cidr": "10.0.0.0/24",
+            "dest_cidr": "10.0.1.0/24",
+            "action": "deny",
+            "priority": 0,
+            "description": null,
+            "peer_id": Uuid::new_v4(),
+        });
+        server.post("/api/v1/acl/rules").json(&rule).await.assert_status_not_found();
+    }
+
+    #[tokio::test]
+    async fn test_peer_filter_follows_tags() {
+        let (server, _dir) = create_test_server().await;
+
+        let db = register_request(&server, &["tag:db"]).await;
+        let db = server.post("/api/v1/peers/register").json(&db).await.json::<ApiResponse<RegisterPeerResponse>>();
+        let db = db.data.unwrap();
+        let ci = register_request(&server, &["tag:ci"]).await;
+        let ci = server.post("/api/v1/peers/register").json(&ci).await.json::<ApiResponse<RegisterPeerResponse>>();
+        let ci = ci.data.unwrap();
+
+        let rule = serde_json::json!({
+            "source_cidr": "tag:ci",
+            "dest_cidr": "tag:db",
+            "action": "accept",
+            "priority": 0,
+            "description": null,
+            "protocol": "tcp",
+            "ports": ["5432"],
+        });
+        server.post("/api/v1/acl/rules").json(&rule).await.assert_status(StatusCode::CREATED);
+
+        let filter = server
+            .get(&format!("/api/v1/peers/{}/filter", db.peer_id))
+            .await
+            .json::<ApiResponse<policy::PacketFilter>>()
+            .data
+            .unwrap();
+        assert_eq!(filter.rules.len(), 1);
+        assert_eq!(filter.rules[0].sources, [format!("{}/32", ci.assigned_ip)]);
+        assert!(filter.evaluate(ci.assigned_ip, policy::Protocol::Tcp, Some(5432)).allowed());
+        assert!(!filter.evaluate(ci.assigned_ip, policy::Protocol::Tcp, Some(22)).allowed());
+
+        server
+            .get(&format!("/api/v1/peers/{}/filter", Uuid::new_v4()))
+            .await
+            .assert_status_not_found();
+    }
+
+    #[test]
+    fn test_check_policy() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let valid = dir.path().join("policy.toml");
+        std::fs::write(&valid, "[[acls]]\naction = \"accept\"\nsrc = [\"tag:ci\"]\ndst = [\"tag:db:5432\"]\n").unwrap();
+        check_policy(&valid).unwrap();
+
+        let invalid = dir.path().join("policy.hujson");
+        std::fs::write(&invalid, r#"{ "acls": [{ "action": "accept", "src": ["group:nope"], "dst": ["*:*"] }] }"#).unwrap();
+        let error = format!("{:#}", check_policy(&invalid).unwrap_err());
+        assert!(error.contains("group:nope"), "{}", error);
+
+        // Address-only tests run offline; tag tests need peers and are skipped
+        let tested = dir.path().join("tested.hujson");
+        std::fs::write(
+            &tested,
+            r#"{
+              "acls": [{ "action": "accept", "src": ["10.0.0.0/24"], "dst": ["10.0.1.0/24:443"] }],
+              "tests": [
+                { "src": "10.0.0.5", "accept": ["10.0.1.5:443"], "deny": ["10.0.1.5:80"] },
+                { "src": "tag:ci", "accept": ["10.0.1.5:443"] },
+              ],
+            }"#,
+        )
+        .unwrap();
+        check_policy(&tested).unwrap();
+
+        std::fs::write(
+            &tested,
+            r#"{ "tests": [{ "src": "10.0.0.5", "accept": ["10.0.1.5:443"] }] }"#,
+        )
+        .unwrap();
+        let error = format!("{:#}", check_policy(&tested).unwrap_err());
+        assert!(error.contains("-accept tcp 10.0.0.5 -> 10.0.1.5:443"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_apply_policy() {
+        let (server, _dir) = create_test_server().await;
+
+        let db = register_request(&server, &["tag:db"]).await;
+        server.post("/api/v1/peers/register").json(&db).await.assert_status(StatusCode::CREATED);
+        let ci = register_request(&server, &["tag:ci"]).await;
+        server.post("/api/v1/peers/register").json(&ci).await.assert_status(StatusCode::CREATED);
+
+        let old = serde_json::json!({
+            "source_cidr": "10.0.0.0/24",
+            "dest_cidr": "10.0.0.0/24",
+            "action": "allow",
+            "priority": 0,
+            "description": null,
+        });
+        server.post("/api/v1/acl/rules").json(&old).await.assert_status(StatusCode::CREATED);
+
+        // Forgetting the CI rule would lock CI out, and the test catches it
+        let locked_out = r#"{
+            "acls": [{ "action": "deny", "src": ["*"], "dst": ["tag:db:*"] }],
+            "tests": [{ "src": "tag:ci", "accept": ["tag:db:5432"] }],
+        }"#;
+        let response = server.put("/api/v1/acl/policy").text(locked_out).await;
+        response.assert_status_bad_request();
+        let error = response.json::<ApiResponse<()>>().error.unwrap();
+        assert!(error.contains("-accept tcp"), "{}", error);
+        assert!(error.contains("+deny"), "{}", error);
+
+        // Nothing changed
+        let page = server
+            .get("/api/v1/acl/rules")
+            .await
+            .json::<ApiResponse<ghostwire_common::protocol::PaginatedResponse<AclRule>>>()
+            .data
+            .unwrap();
+        assert_eq!(page.total_count, 1);
+
+        let policy = r#"{
+            "acls": [
+                { "action": "accept", "src": ["tag:ci"], "dst": ["tag:db:5432"], "proto": "tcp" },
+                { "action": "deny", "src": ["*"], "dst": ["tag:db:*"], "priority": -1 },
+            ],
+            "tests": [{ "src": "tag:ci", "accept": ["tag:db:5432"], "deny": ["tag:db:22"] }],
+        }"#;
+        let response = server.put("/api/v1/acl/policy").text(policy).await;
+        response.assert_status_ok();
+        let rules = response.json::<ApiResponse<Vec<AclRule>>>().data.unwrap();
+        assert_eq!(rules.len(), 2);
+
+        // The policy replaced the old network-wide rule
+        let page = server
+            .get("/api/v1/acl/rules")
+            .await
+            .json::<ApiResponse<ghostwire_common::protocol::PaginatedResponse<AclRule>>>()
+            .data
+            .unwrap();
+        assert_eq!(page.total_count, 2);
+        assert!(page.items.iter().all(|rule| rule.source_cidr != "10.0.0.0/24"));
+    }
+
+    #[tokio::test]
+    async fn test_topology_since() {
+        let (server, _dir) = create_test_server().await;
+
+        let request = register_request(&server, &[]).await;
+        let first = server.post("/api/v1/peers/register").json(&request).await;
+        let first = first.json::<ApiResponse<RegisterPeerResponse>>().data.unwrap();
+
+        let snapshot = match server.get("/api/v1/topology").await.json::<ApiResponse<TopologySync>>().data {
+            Some(TopologySync::Snapshot(topology)) => topology,
+            other => panic!("expected a snapshot, got {:?}", other),
+        };
+        assert_eq!(snapshot.peers.len(), 1);
+
+        let request = register_request(&server, &[]).await;
+        let second = server.post("/api/v1/peers/register").json(&request).await;
+        let second = second.json::<ApiResponse<RegisterPeerResponse>>().data.unwrap();
+        server.delete(&format!("/api/v1/peers/{}", first.peer_id)).await.assert_status(StatusCode::NO_CONTENT);
+
+        let response = server.get(&format!("/api/v1/topology?since={}", snapshot.generation)).await;
+        let delta = match response.json::<ApiResponse<TopologySync>>().data {
+            Some(TopologySync::Delta(delta)) => delta,
+            other => panic!("expected a delta, got {:?}", other),
+        };
+        assert_eq!(delta.generation, snapshot.generation + 2);
+        assert_eq!(delta.added.peers.len(), 1);
+        assert_eq!(delta.added.peers[0].id, second.peer_id);
+        assert_eq!(delta.removed.peers, vec![first.peer_id]);
+
+        // No rule lets the second peer reach anyone, so it only sees itself
+        let response = server.get(&format!("/api/v1/topology?peer_id={}", second.peer_id)).await;
+        match response.json::<ApiResponse<TopologySync>>().data {
+            Some(TopologySync::Snapshot(topology)) => {
+                assert_eq!(topology.peers.keys().collect::<Vec<_>>(), vec![&second.peer_id]);
+            }
+            other => panic!("expected a snapshot, got {:?}", other),
+        }
+        server
+            .get(&format!("/api/v1/topology?peer_id={}", Uuid::new_v4()))
+            .await
+            .assert_status_not_found();
+    }
+
+    #[tokio::test]
+    async fn test_pre_auth_keys() {
+        let (server, _dir) = create_test_server().await;
+
+        let node = NodeKey::generate().unwrap();
+        let request = register_node(&server, &node, &["tag:ci"]).await;
+        let unsigned = RegisterPeerRequest { proof: None, ..request.clone() };
+        server.post("/api/v1/peers/register").json(&unsigned).await.assert_status(StatusCode::UNAUTHORIZED);
+        server.post("/api/v1/peers/register").json(&request).await.assert_status(StatusCode::CREATED);
+
+        // A replayed challenge and a second registration of the node both fail
+        server.post("/api/v1/peers/register").json(&request).await.assert_status(StatusCode::UNAUTHORIZED);
+        let duplicate = register_node(&server, &node, &[]).await;
+        server.post("/api/v1/peers/register").json(&duplicate).await.assert_status(StatusCode::CONFLICT);
+
+        let keys = server.get("/api/v1/auth/keys").await.json::<ApiResponse<Vec<PreAuthKey>>>().data.unwrap();
+        assert_eq!(keys.len(), 2);
+        let used = keys.iter().find(|key| key.used_count == 1).unwrap();
+        assert_eq!(used.tags, vec!["ci".to_string()]);
+        // Secrets are never listed
+        let body = server.get("/api/v1/auth/keys").await.text();
+        assert!(!body.contains(request.auth_key.as_deref().unwrap()));
+
+        let path = format!("/api/v1/auth/keys/{}", used.id);
+        server.delete(&path).await.assert_status(StatusCode::NO_CONTENT);
+        let keys = server.get("/api/v1/auth/keys").await.json::<ApiResponse<Vec<PreAuthKey>>>().data.unwrap();
+        assert!(keys.iter().find(|key| key.id == used.id).unwrap().revoked);
+        server
+            .delete(&format!("/api/v1/auth/keys/{}", Uuid::new_v4()))
+            .await
+            .assert_status_not_found();
+    }
+
+    #[tokio::test]
+    async fn test_oidc_login() {
+        let provider = oidc::mock::MockProvider::start().await;
+        let (state, _dir) = create_test_state(Some(provider.config())).await;
+        let server = admin_test_server(state).await;
+
+        // Without a pre-auth key the node is sent to log in
+        let node = NodeKey::generate().unwrap();
+        let request = RegisterPeerRequest { auth_key: None, ..register_node(&server, &node, &[]).await };
+        let response = server.post("/api/v1/peers/register").json(&request).await;
+        response.assert_status(StatusCode::ACCEPTED);
+        let Registration::Pending(pending) = response.json::<ApiResponse<Registration>>().data.unwrap() else {
+            panic!("registration did not wait on a login");
+        };
+
+        let (code, login_state) = provider.authorize(&pending.auth_url, "alice", "alice@example.com");
+        let callback = || server.get("/api/v1/auth/oidc/callback").add_query_param("state", &login_state);
+        callback().add_query_param("code", "forged").await.assert_status(StatusCode::UNAUTHORIZED);
+        // The failed attempt used up the login
+        callback().add_query_param("code", &code).await.assert_status(StatusCode::UNAUTHORIZED);
+
+        let request = RegisterPeerRequest { auth_key: None, ..register_node(&server, &node, &[]).await };
+        let response = server.post("/api/v1/peers/register").json(&request).await;
+        let Registration::Pending(pending) = response.json::<ApiResponse<Registration>>().data.unwrap() else {
+            panic!("registration did not wait on a login");
+        };
+        let (code, login_state) = provider.authorize(&pending.auth_url, "alice", "alice@example.com");
+        let response = server
+            .get("/api/v1/auth/oidc/callback")
+            .add_query_param("state", &login_state)
+            .add_query_param("code", &code)
+            .await;
+        response.assert_status_ok();
+        let peer = response.json::<ApiResponse<RegisterPeerResponse>>().data.unwrap();
+
+        let users = server.get("/api/v1/users").await.json::<ApiResponse<Vec<User>>>().data.unwrap();
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].email.as_deref(), Some("alice@example.com"));
+        let stored = server
+            .get(&format!("/api/v1/peers/{}", peer.peer_id))
+            .await
+            .json::<ApiResponse<PeerInfo>>()
+            .data
+            .unwrap();
+        assert_eq!(stored.user_id, Some(users[0].id));
+
+        // A login the provider refused ends the registration
+        let other = NodeKey::generate().unwrap();
+        let request = RegisterPeerRequest { auth_key: None, ..register_node(&server, &other, &[]).await };
+        let response = server.post("/api/v1/peers/register").json(&request).await;
+        let Registration::Pending(pending) = response.json::<ApiResponse<Registration>>().data.unwrap() else {
+            panic!("registration did not wait on a login");
+        };
+        let (code, login_state) = provider.authorize(&pending.auth_url, "bob", "bob@example.com");
+        server
+            .get("/api/v1/auth/oidc/callback")
+            .add_query_param("state", &login_state)
+            .add_query_param("error", "access_denied")
+            .await
+            .assert_status(StatusCode::UNAUTHORIZED);
+        server
+            .get("/api/v1/auth/oidc/callback")
+            .add_query_param("state", &login_state)
+            .add_query_param("code", &code)
+            .await
+            .assert_status(StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_api_token_scopes() {
+        let (state, _dir) = create_test_state(None).await;
+        let coordination = Arc::clone(&state.coordination_server);
+        let token = |name: &'static str, scopes: Vec<ApiScope>, expires_at| {
+            let coordination = Arc::clone(&coordination);
+            async move { coordination.create_api_token(name, scopes, expires_at).await.unwrap() }
+        };
+        let (reader, _) = token("reader", vec![ApiScope::PeersRead, ApiScope::AclRead], None).await;
+        let (writer, writer_info) = token("writer", vec![ApiScope::AclWrite], None).await;
+        let yesterday = chrono::Utc::now() - chrono::TimeDelta::days(1);
+        let (expired, _) = token("expired", vec![ApiScope::Admin], Some(yesterday)).await;
+
+        let cors = middleware::cors_middleware(&["https://console.example.com".to_string()]).unwrap();
+        let server = TestServer::new(build_router(state, cors)).unwrap();
+
+        // Health checks and peer registration need no token
+        server.get("/health").await.assert_status_ok();
+        server.post("/api/v1/peers/challenge").await.assert_status_ok();
+
+        server.get("/api/v1/acl/rules").await.assert_status(StatusCode::UNAUTHORIZED);
+        for secret in ["gwapi-unknown", expired.as_str()] {
+            let response = server.get("/api/v1/acl/rules").authorization_bearer(secret).await;
+            response.assert_status(StatusCode::UNAUTHORIZED);
+        }
+        server.get("/api/v1/acl/rules").authorization_bearer(&reader).await.assert_status_ok();
+        server.get("/api/v1/peers").authorization_bearer(&reader).await.assert_status_ok();
+
+        let rule = serde_json::json!({
+            "source_cidr": "10.0.0.0/24",
+            "dest_cidr": "10.0.1.0/24",
+            "action": "allow",
+            "priority": 0,
+            "description": null,
+        });
+        let response = server.post("/api/v1/acl/rules").authorization_bearer(&reader).json(&rule).await;
+        response.assert_status(StatusCode::FORBIDDEN);
+        let response = server.post("/api/v1/acl/rules").authorization_bearer(&writer).json(&rule).await;
+        response.assert_status(StatusCode::CREATED);
+
+        // acl:write includes reading rules, but nothing outside ACLs
+        server.get("/api/v1/acl/rules").authorization_bearer(&writer).await.assert_status_ok();
+        server.get("/api/v1/peers").authorization_bearer(&writer).await.assert_status(StatusCode::FORBIDDEN);
+        server.get("/api/v1/auth/keys").authorization_bearer(&writer).await.assert_status(StatusCode::FORBIDDEN);
+
+        let tokens = coordination.list_api_tokens().await.unwrap();
+        let used = tokens.iter().find(|token| token.id == writer_info.id).unwrap();
+        assert!(used.last_used_at.is_some());
+        assert!(tokens.iter().find(|token| token.name == "expired").unwrap().last_used_at.is_none());
+
+        coordination.revoke_api_token(writer_info.id).await.unwrap();
+        let response = server.get("/api/v1/acl/rules").authorization_bearer(&writer).await;
+        response.assert_status(StatusCode::UNAUTHORIZED);
+
+        // Only configured origins are allowed cross-origin
+        let allowed = server.get("/health").add_header(header::ORIGIN, "https://console.example.com").await;
+        assert_eq!(allowed.header(header::ACCESS_CONTROL_ALLOW_ORIGIN), "https://console.example.com");
+        let other = server.get("/health").add_header(header::ORIGIN, "https://elsewhere.example.com").await;
+        assert!(other.maybe_header(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_admin_token() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = ServerConfig {
+            database_path: dir.path().join("ghostwire.db").to_string_lossy().to_string(),
+            network_cidr: "10.0.0.0/24".to_string(),
+            admin_token: Some("bootstrap-secret".to_string()),
+            ..Default::default()
+        };
+        assert!(!format!("{:?}", config).contains("bootstrap-secret"));
+
+        let metrics = zqlite_rs::ZQLiteMetrics::new("test");
+        let state = AppState {
+            coordination_server: Arc::new(CoordinationServer::new(&config, metrics).await.unwrap()),
+            sessions: Arc::new(SessionHub::new(SessionConfig::default())),
+        };
+        let cors = middleware::cors_middleware(&[]).unwrap();
+        let server = TestServer::new(build_router(state, cors)).unwrap();
+
+        // The configured secret works before any token is stored, with any
+        // capitalization of the scheme
+        server.get("/api/v1/auth/keys").authorization_bearer("bootstrap-secret").await.assert_status_ok();
+        let response = server
+            .get("/api/v1/peers")
+            .add_header(header::AUTHORIZATION, "bearer bootstrap-secret")
+            .await;
+        response.assert_status_ok();
+        let response = server
+            .get("/api/v1/peers")
+            .add_header(header::AUTHORIZATION, "Basic bootstrap-secret")
+            .await;
+        response.assert_status(StatusCode::UNAUTHORIZED);
+        server.get("/api/v1/peers").authorization_bearer("other-secret").await.assert_status(StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_manage_tokens() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = ServerConfig {
+            database_path: dir.path().join("ghostwire.db").to_string_lossy().to_string(),
+            network_cidr: "10.0.0.0/24".to_string(),
+            ..Default::default()
+        };
+
+        let create = TokenCommand::Create {
+            name: "ci".to_string(),
+            scopes: vec![ApiScope::AclWrite],
+            expires_in_days: Some(30),
+        };
+        manage_tokens(&config, create).await.unwrap();
+        manage_tokens(&config, TokenCommand::List).await.unwrap();
+
+        let server = CoordinationServer::new(&config, zqlite_rs::ZQLiteMetrics::new("test")).await.unwrap();
+        let tokens = server.list_api_tokens().await.unwrap();
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].scopes, [ApiScope::AclWrite]);
+        assert!(tokens[0].expires_at.unwrap() > chrono::Utc::now() + chrono::TimeDelta::days(29));
+        drop(server);
+
+        manage_tokens(&config, TokenCommand::Revoke { id: tokens[0].id }).await.unwrap();
+        assert!(manage_tokens(&config, TokenCommand::Revoke { id: Uuid::new_v4() }).await.is_err());
+    }
 }
\ No newline at end of file
@@ -1,10 +0,0 @@
-//! Server-specific metrics
-
-// Placeholder for server metrics
-pub struct ServerMetrics;
-
-impl ServerMetrics {
-    pub fn new() -> Self {
-        Self
-    }
-}
\ No newline at end of file
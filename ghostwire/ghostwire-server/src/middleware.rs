@@ -1,28 +1,80 @@
 //! HTTP middleware
 
-use axum::http::{header, Method};
-use std::time::Duration;
-use tower::ServiceBuilder;
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue, Method},
+    middleware::Next,
+    response::Response,
+};
+use ghostwire_common::{ApiScope, GhostwireError};
+use std::{sync::Arc, time::Duration};
 use tower_http::{
-    cors::{Any, CorsLayer},
+    classify::{ServerErrorsAsFailures, SharedClassifier},
+    cors::{AllowOrigin, Any, CorsLayer},
     timeout::TimeoutLayer,
     trace::TraceLayer,
 };
+use tracing::debug;
+
+use crate::{coordination::CoordinationServer, error::ApiError};
 
 /// Create logging middleware
-pub fn logging_middleware() -> TraceLayer {
+pub fn logging_middleware() -> TraceLayer<SharedClassifier<ServerErrorsAsFailures>> {
     TraceLayer::new_for_http()
 }
 
-/// Create CORS middleware
-pub fn cors_middleware() -> CorsLayer {
-    CorsLayer::new()
-        .allow_origin(Any)
+/// Create CORS middleware allowing `origins`, where `*` allows any
+pub fn cors_middleware(origins: &[String]) -> Result<CorsLayer> {
+    let allow_origin = if origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::from(Any)
+    } else {
+        let origins = origins
+            .iter()
+            .map(|origin| {
+                HeaderValue::from_str(origin).with_context(|| format!("Invalid CORS origin: {}", origin))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        AllowOrigin::list(origins)
+    };
+
+    Ok(CorsLayer::new()
+        .allow_origin(allow_origin)
         .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
-        .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION])
+        .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION]))
+}
+
+/// Reject requests without a bearer API token that grants `scope`
+///
+/// Used with `axum::middleware::from_fn_with_state` as a route layer.
+pub async fn require_scope(
+    State((server, scope)): State<(Arc<CoordinationServer>, ApiScope)>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let secret = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        // The scheme name is case-insensitive (RFC 9110)
+        .and_then(|value| value.split_once(' '))
+        .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("bearer"))
+        .map(|(_, secret)| secret)
+        .ok_or_else(|| GhostwireError::Unauthorized("a bearer API token is required".to_string()))?;
+
+    let token = server.authenticate_token(secret.trim()).await?;
+    if !token.allows(scope) {
+        return Err(GhostwireError::Forbidden(format!("API token {} lacks scope {}", token.id, scope)).into());
+    }
+
+    debug!(token_id = %token.id, scope = %scope, "API token accepted");
+    Ok(next.run(request).await)
 }
 
 /// Create timeout middleware
+///
+/// Timed-out requests drop their database futures, which interrupts the
+/// statements still running on the blocking pool.
 pub fn timeout_middleware() -> TimeoutLayer {
     TimeoutLayer::new(Duration::from_secs(30))
 }
\ No newline at end of file
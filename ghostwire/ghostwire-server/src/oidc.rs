@@ -0,0 +1,574 @@
+//! OpenID Connect login for joining peers
+//!
+//! A node that registers without a pre-auth key gets an authorization URL
+//! for the authorization code flow with PKCE. The user logs in with the
+//! configured provider, which redirects to the server's callback; the
+//! server redeems the code, checks the ID token against the provider's
+//! JWKS and registers the node for that user.
+
+use axum::{
+    body::Bytes,
+    http::{header, Method, Request},
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use ghostwire_common::{GhostwireError, OidcConfig};
+use http_body_util::{BodyExt, Full};
+use hyper_rustls::HttpsConnector;
+use hyper_util::{
+    client::legacy::{connect::HttpConnector, Client},
+    rt::TokioExecutor,
+};
+use ring::{
+    digest,
+    rand::{SecureRandom, SystemRandom},
+    signature,
+};
+use serde::{de::DeserializeOwned, Deserialize};
+use std::time::Duration;
+use tokio::sync::OnceCell;
+use url::Url;
+
+/// Time allowed for each request to the provider
+const PROVIDER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Provider endpoints from its discovery document
+#[derive(Debug, Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+/// Signing keys published at `jwks_uri`
+#[derive(Debug, Deserialize)]
+struct JwkSet {
+    keys: Vec<Jwk>,
+}
+
+/// One published key; only the fields RS256 and ES256 use
+#[derive(Debug, Deserialize)]
+struct Jwk {
+    kty: String,
+    #[serde(default)]
+    kid: Option<String>,
+    #[serde(default)]
+    crv: Option<String>,
+    #[serde(default)]
+    x: Option<String>,
+    #[serde(default)]
+    y: Option<String>,
+    #[serde(default)]
+    n: Option<String>,
+    #[serde(default)]
+    e: Option<String>,
+}
+
+impl Jwk {
+    /// Whether `signature` over `message` verifies with this key under `alg`
+    fn verifies(&self, alg: &str, message: &[u8], signature: &[u8]) -> bool {
+        let decode = |field: &Option<String>| field.as_deref().and_then(|value| URL_SAFE_NO_PAD.decode(value).ok());
+        match (alg, self.kty.as_str()) {
+            ("RS256", "RSA") => match (decode(&self.n), decode(&self.e)) {
+                (Some(n), Some(e)) => signature::RsaPublicKeyComponents { n, e }
+                    .verify(&signature::RSA_PKCS1_2048_8192_SHA256, message, signature)
+                    .is_ok(),
+                _ => false,
+            },
+            ("ES256", "EC") if self.crv.as_deref() == Some("P-256") => match (decode(&self.x), decode(&self.y)) {
+                (Some(x), Some(y)) => {
+                    // Uncompressed SEC1 point
+                    let point = [&[4u8][..], &x, &y].concat();
+                    signature::UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_FIXED, point)
+                        .verify(message, signature)
+                        .is_ok()
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct JwtHeader {
+    alg: String,
+    #[serde(default)]
+    kid: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+/// `aud` is one client ID or a list of them
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// ID token claims that are checked or kept
+#[derive(Debug, Deserialize)]
+struct Claims {
+    iss: String,
+    sub: String,
+    aud: Audience,
+    exp: i64,
+    #[serde(default)]
+    nonce: Option<String>,
+    #[serde(default)]
+    email: Option<String>,
+    #[serde(default)]
+    name: Option<String>,
+}
+
+/// User identity from a verified ID token
+#[derive(Debug, Clone, PartialEq)]
+pub struct Identity {
+    pub issuer: String,
+    pub subject: String,
+    pub email: Option<String>,
+    pub name: Option<String>,
+}
+
+/// Login started by [`OidcProvider::begin`], kept until its callback
+#[derive(Debug)]
+pub struct Login {
+    /// Echoed back to the callback, identifying the login
+    pub state: String,
+    /// Echoed back in the ID token, binding it to the login
+    nonce: String,
+    /// PKCE secret, sent only when redeeming the code
+    code_verifier: String,
+    /// Page for the user to log in at
+    pub auth_url: String,
+}
+
+/// Configured provider, with its discovery document once fetched
+pub struct OidcProvider {
+    config: OidcConfig,
+    client: Client<HttpsConnector<HttpConnector>, Full<Bytes>>,
+    metadata: OnceCell<ProviderMetadata>,
+}
+
+impl OidcProvider {
+    /// Create a provider client; nothing is fetched until the first login
+    pub fn new(config: OidcConfig) -> Result<Self, GhostwireError> {
+        let connector = hyper_rustls::HttpsConnectorBuilder::new()
+            .with_provider_and_native_roots(rustls::crypto::ring::default_provider())
+            .map_err(|e| GhostwireError::Config(format!("Failed to load TLS roots for OIDC: {}", e)))?
+            .https_or_http()
+            .enable_http1()
+            .build();
+
+        Ok(Self {
+            config,
+            client: Client::builder(TokioExecutor::new()).build(connector),
+            metadata: OnceCell::new(),
+        })
+    }
+
+    /// Start a login with fresh state, nonce and PKCE verifier
+    pub async fn begin(&self) -> Result<Login, GhostwireError> {
+        let metadata = self.metadata().await?;
+        let state = random_token()?;
+        let nonce = random_token()?;
+        let code_verifier = random_token()?;
+        let code_challenge = URL_SAFE_NO_PAD.encode(digest::digest(&digest::SHA256, code_verifier.as_bytes()));
+
+        let scope = std::iter::once("openid")
+            .chain(self.config.scopes.iter().map(String::as_str).filter(|scope| *scope != "openid"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut auth_url = Url::parse(&metadata.authorization_endpoint)
+            .map_err(|e| GhostwireError::Config(format!("Invalid OIDC authorization endpoint: {}", e)))?;
+        auth_url
+            .query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.config.client_id)
+            .append_pair("redirect_uri", &self.config.redirect_uri)
+            .append_pair("scope", &scope)
+            .append_pair("state", &state)
+            .append_pair("nonce", &nonce)
+            .append_pair("code_challenge", &code_challenge)
+            .append_pair("code_challenge_method", "S256");
+
+        Ok(Login { state, nonce, code_verifier, auth_url: auth_url.into() })
+    }
+
+    /// Redeem the callback's code and verify the ID token it yields
+    pub async fn complete(&self, login: &Login, code: &str) -> Result<Identity, GhostwireError> {
+        let metadata = self.metadata().await?;
+
+        // The serializer is not Send, so it is finished before any await
+        let form = {
+            let mut form = url::form_urlencoded::Serializer::new(String::new());
+            form.append_pair("grant_type", "authorization_code")
+                .append_pair("code", code)
+                .append_pair("redirect_uri", &self.config.redirect_uri)
+                .append_pair("client_id", &self.config.client_id)
+                .append_pair("code_verifier", &login.code_verifier);
+            if let Some(secret) = &self.config.client_secret {
+                form.append_pair("client_secret", secret);
+            }
+            form.finish()
+        };
+        let tokens: TokenResponse = self.fetch(Method::POST, &metadata.token_endpoint, Some(form)).await?;
+
+        // Fetched per login, so rotated keys are picked up
+        let keys: JwkSet = self.fetch(Method::GET, &metadata.jwks_uri, None).await?;
+        let claims = verify_id_token(&tokens.id_token, &keys)?;
+
+        let invalid = |reason: &str| GhostwireError::Unauthorized(format!("invalid ID token: {}", reason));
+        if claims.iss != metadata.issuer {
+            return Err(invalid("issued by another provider"));
+        }
+        let audience = match &claims.aud {
+            Audience::One(audience) => audience == &self.config.client_id,
+            Audience::Many(audiences) => audiences.contains(&self.config.client_id),
+        };
+        if !audience {
+            return Err(invalid("issued to another client"));
+        }
+        if claims.exp <= Utc::now().timestamp() {
+            return Err(invalid("expired"));
+        }
+        if claims.nonce.as_deref() != Some(login.nonce.as_str()) {
+            return Err(invalid("nonce does not match the login"));
+        }
+
+        Ok(Identity { issuer: claims.iss, subject: claims.sub, email: claims.email, name: claims.name })
+    }
+
+    async fn metadata(&self) -> Result<&ProviderMetadata, GhostwireError> {
+        self.metadata
+            .get_or_try_init(|| async {
+                let issuer = self.config.issuer.trim_end_matches('/');
+                let url = format!("{}/.well-known/openid-configuration", issuer);
+                let metadata: ProviderMetadata = self.fetch(Method::GET, &url, None).await?;
+                if metadata.issuer.trim_end_matches('/') != issuer {
+                    return Err(GhostwireError::Config(format!(
+                        "OIDC discovery names issuer {}, not {}",
+                        metadata.issuer, self.config.issuer
+                    )));
+                }
+                Ok(metadata)
+            })
+            .await
+    }
+
+    /// Send a request to the provider, with a form body if given, and read
+    /// its JSON response
+    async fn fetch<T: DeserializeOwned>(&self, method: Method, url: &str, form: Option<String>) -> Result<T, GhostwireError> {
+        let request = Request::builder().method(method).uri(url).header(header::ACCEPT, "application/json");
+        let request = match form {
+            Some(form) => request
+                .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .body(Full::from(form)),
+            None => request.body(Full::default()),
+        }
+        .map_err(|e| GhostwireError::Config(format!("Invalid OIDC URL {}: {}", url, e)))?;
+
+        let unreachable = |e: &dyn std::fmt::Display| GhostwireError::Network(format!("OIDC request to {} failed: {}", url, e));
+        let response = tokio::time::timeout(PROVIDER_TIMEOUT, async {
+            let response = self.client.request(request).await.map_err(|e| unreachable(&e))?;
+            let status = response.status();
+            let body = response.into_body().collect().await.map_err(|e| unreachable(&e))?.to_bytes();
+            Ok::<_, GhostwireError>((status, body))
+        })
+        .await
+        .map_err(|e| unreachable(&e))?;
+
+        let (status, body) = response?;
+        if !status.is_success() {
+            return Err(GhostwireError::Unauthorized(format!("OIDC provider answered {} from {}", status, url)));
+        }
+        serde_json::from_slice(&body).map_err(GhostwireError::Serialization)
+    }
+}
+
+/// Check an ID token's signature against the provider's keys and read its claims
+fn verify_id_token(token: &str, keys: &JwkSet) -> Result<Claims, GhostwireError> {
+    let invalid = |reason: &str| GhostwireError::Unauthorized(format!("invalid ID token: {}", reason));
+    let decode = |part: &str| URL_SAFE_NO_PAD.decode(part).map_err(|_| invalid("not base64url"));
+
+    let parts: Vec<&str> = token.split('.').collect();
+    let [header, payload, signature] = parts[..] else {
+        return Err(invalid("not a signed JWT"));
+    };
+    let jwt_header: JwtHeader = serde_json::from_slice(&decode(header)?).map_err(|_| invalid("malformed header"))?;
+    let signature = decode(signature)?;
+    let message = &token[..header.len() + 1 + payload.len()];
+
+    // A `kid` in the header picks the key; without one any key may match
+    let verified = keys
+        .keys
+        .iter()
+        .filter(|key| jwt_header.kid.is_none() || key.kid == jwt_header.kid)
+        .any(|key| key.verifies(&jwt_header.alg, message.as_bytes(), &signature));
+    if !verified {
+        return Err(invalid("signature does not match the provider's keys"));
+    }
+
+    serde_json::from_slice(&decode(payload)?).map_err(|_| invalid("malformed claims"))
+}
+
+/// 32 random bytes, base64url-encoded
+fn random_token() -> Result<String, GhostwireError> {
+    let mut bytes = [0u8; 32];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| GhostwireError::Crypto("Failed to generate OIDC login parameters".to_string()))?;
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+/// In-process OIDC provider serving discovery, JWKS and token endpoints
+#[cfg(test)]
+pub(crate) mod mock {
+    use super::*;
+    use axum::{
+        extract::State,
+        http::StatusCode,
+        routing::{get, post},
+        Form, Json, Router,
+    };
+    use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+    use serde_json::{json, Value};
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+    use tokio::{net::TcpListener, task::JoinHandle};
+
+    /// Client ID the mock provider knows
+    pub const CLIENT_ID: &str = "ghostwire";
+
+    /// Code handed out by [`MockProvider::authorize`], awaiting redemption
+    struct Grant {
+        redirect_uri: String,
+        nonce: String,
+        code_challenge: String,
+        subject: String,
+        email: String,
+    }
+
+    struct Provider {
+        issuer: String,
+        key: EcdsaKeyPair,
+        grants: Mutex<HashMap<String, Grant>>,
+    }
+
+    /// Running mock provider, stopped when dropped
+    pub struct MockProvider {
+        provider: Arc<Provider>,
+        task: JoinHandle<()>,
+    }
+
+    impl MockProvider {
+        /// Serve a provider with a fresh ES256 key on a local port
+        pub async fn start() -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let issuer = format!("http://{}", listener.local_addr().unwrap());
+            let rng = SystemRandom::new();
+            let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+            let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng).unwrap();
+
+            let provider = Arc::new(Provider { issuer, key, grants: Mutex::new(HashMap::new()) });
+            let app = Router::new()
+                .route("/.well-known/openid-configuration", get(discovery))
+                .route("/jwks", get(jwks))
+                .route("/token", post(token))
+                .with_state(Arc::clone(&provider));
+            let task = tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+            Self { provider, task }
+        }
+
+        /// Configuration for a client of this provider
+        pub fn config(&self) -> OidcConfig {
+            OidcConfig {
+                issuer: self.provider.issuer.clone(),
+                client_id: CLIENT_ID.to_string(),
+                client_secret: None,
+                redirect_uri: "http://ghostwire.test/api/v1/auth/oidc/callback".to_string(),
+                scopes: vec!["email".to_string()],
+            }
+        }
+
+        /// Log `subject` in at `auth_url` as a browser would, returning the
+        /// `code` and `state` the provider redirects back with
+        pub fn authorize(&self, auth_url: &str, subject: &str, email: &str) -> (String, String) {
+            let url = Url::parse(auth_url).unwrap();
+            assert!(auth_url.starts_with(&format!("{}/authorize?", self.provider.issuer)));
+            let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+            assert_eq!(query["response_type"], "code");
+            assert_eq!(query["client_id"], CLIENT_ID);
+            assert_eq!(query["code_challenge_method"], "S256");
+            assert!(query["scope"].split(' ').any(|scope| scope == "openid"));
+
+            let code = random_token().unwrap();
+            let grant = Grant {
+                redirect_uri: query["redirect_uri"].clone(),
+                nonce: query["nonce"].clone(),
+                code_challenge: query["code_challenge"].clone(),
+                subject: subject.to_string(),
+                email: email.to_string(),
+            };
+            self.provider.grants.lock().unwrap().insert(code.clone(), grant);
+            (code, query["state"].clone())
+        }
+
+        /// An ID token for `claims` signed with the provider's key
+        pub(super) fn sign(&self, claims: &Value) -> String {
+            self.provider.sign(&json!({ "alg": "ES256", "kid": "mock" }), claims)
+        }
+
+        /// The provider's published keys
+        pub(super) async fn keys(&self) -> JwkSet {
+            let Json(keys) = jwks(State(Arc::clone(&self.provider))).await;
+            serde_json::from_value(keys).unwrap()
+        }
+    }
+
+    impl Drop for MockProvider {
+        fn drop(&mut self) {
+            self.task.abort();
+        }
+    }
+
+    async fn discovery(State(provider): State<Arc<Provider>>) -> Json<Value> {
+        let issuer = &provider.issuer;
+        Json(json!({
+            "issuer": issuer,
+            "authorization_endpoint": format!("{}/authorize", issuer),
+            "token_endpoint": format!("{}/token", issuer),
+            "jwks_uri": format!("{}/jwks", issuer),
+            "id_token_signing_alg_values_supported": ["ES256"],
+            "code_challenge_methods_supported": ["S256"],
+        }))
+    }
+
+    async fn jwks(State(provider): State<Arc<Provider>>) -> Json<Value> {
+        // Uncompressed point: 0x04, then x and y
+        let point = provider.key.public_key().as_ref();
+        Json(json!({
+            "keys": [{
+                "kty": "EC",
+                "crv": "P-256",
+                "kid": "mock",
+                "alg": "ES256",
+                "use": "sig",
+                "x": URL_SAFE_NO_PAD.encode(&point[1..33]),
+                "y": URL_SAFE_NO_PAD.encode(&point[33..]),
+            }],
+        }))
+    }
+
+    async fn token(
+        State(provider): State<Arc<Provider>>,
+        Form(form): Form<HashMap<String, String>>,
+    ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+        let invalid = || (StatusCode::BAD_REQUEST, Json(json!({ "error": "invalid_grant" })));
+        let field = |name: &str| form.get(name).map(String::as_str).unwrap_or_default();
+        if field("grant_type") != "authorization_code" || field("client_id") != CLIENT_ID {
+            return Err(invalid());
+        }
+
+        // Codes are single use, even when the rest of the request is wrong
+        let grant = provider.grants.lock().unwrap().remove(field("code")).ok_or_else(invalid)?;
+        let challenge = URL_SAFE_NO_PAD.encode(digest::digest(&digest::SHA256, field("code_verifier").as_bytes()));
+        if challenge != grant.code_challenge || field("redirect_uri") != grant.redirect_uri {
+            return Err(invalid());
+        }
+
+        let now = Utc::now().timestamp();
+        let header = json!({ "alg": "ES256", "typ": "JWT", "kid": "mock" });
+        let claims = json!({
+            "iss": provider.issuer,
+            "sub": grant.subject,
+            "aud": CLIENT_ID,
+            "iat": now,
+            "exp": now + 300,
+            "nonce": grant.nonce,
+            "email": grant.email,
+        });
+        Ok(Json(json!({
+            "access_token": random_token().unwrap(),
+            "token_type": "Bearer",
+            "id_token": provider.sign(&header, &claims),
+        })))
+    }
+
+    impl Provider {
+        fn sign(&self, header: &Value, claims: &Value) -> String {
+            let message = format!(
+                "{}.{}",
+                URL_SAFE_NO_PAD.encode(header.to_string()),
+                URL_SAFE_NO_PAD.encode(claims.to_string())
+            );
+            let signature = self.key.sign(&SystemRandom::new(), message.as_bytes()).unwrap();
+            format!("{}.{}", message, URL_SAFE_NO_PAD.encode(signature.as_ref()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mock::MockProvider;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_login_with_mock_provider() {
+        let idp = MockProvider::start().await;
+        let provider = OidcProvider::new(idp.config()).unwrap();
+
+        let login = provider.begin().await.unwrap();
+        let (code, state) = idp.authorize(&login.auth_url, "alice", "alice@example.com");
+        assert_eq!(state, login.state);
+
+        let identity = provider.complete(&login, &code).await.unwrap();
+        assert_eq!(identity.issuer, idp.config().issuer);
+        assert_eq!(identity.subject, "alice");
+        assert_eq!(identity.email.as_deref(), Some("alice@example.com"));
+
+        // Codes are single use
+        assert!(matches!(provider.complete(&login, &code).await, Err(GhostwireError::Unauthorized(_))));
+
+        // A code redeemed for another login fails PKCE
+        let other = provider.begin().await.unwrap();
+        let (code, _) = idp.authorize(&login.auth_url, "alice", "alice@example.com");
+        assert!(matches!(provider.complete(&other, &code).await, Err(GhostwireError::Unauthorized(_))));
+    }
+
+    #[tokio::test]
+    async fn test_verify_id_token() {
+        let idp = MockProvider::start().await;
+        let keys = idp.keys().await;
+        let claims = json!({ "iss": "https://idp", "sub": "bob", "aud": ["a", "b"], "exp": 1 });
+
+        let token = idp.sign(&claims);
+        let verified = verify_id_token(&token, &keys).unwrap();
+        assert_eq!(verified.sub, "bob");
+        assert!(matches!(verified.aud, Audience::Many(ref audiences) if audiences.len() == 2));
+
+        // Swapping in other claims breaks the signature
+        let forged = idp.sign(&json!({ "iss": "https://idp", "sub": "mallory", "aud": "a", "exp": 1 }));
+        let parts: Vec<&str> = token.split('.').collect();
+        let forged_payload = forged.split('.').nth(1).unwrap();
+        let tampered = format!("{}.{}.{}", parts[0], forged_payload, parts[2]);
+        assert!(verify_id_token(&tampered, &keys).is_err());
+
+        // Unsigned tokens are never accepted
+        let unsigned = format!(
+            "{}.{}.",
+            URL_SAFE_NO_PAD.encode(json!({ "alg": "none" }).to_string()),
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        );
+        assert!(verify_id_token(&unsigned, &keys).is_err());
+        assert!(verify_id_token("not-a-jwt", &keys).is_err());
+    }
+}
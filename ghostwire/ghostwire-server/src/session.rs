@@ -0,0 +1,624 @@
+//! WebSocket sessions for real-time updates
+//!
+//! A peer opens a session by sending `PeerRegister` with a signed
+//! registration challenge: a known public key resumes its peer, an unknown
+//! one registers a new peer with its pre-auth key. The peer may then
+//! send `HealthCheck`s and `Ping`s. It starts with a `TopologyUpdate` of the
+//! peers it may reach, then receives a `TopologyDelta` whenever the
+//! generation advances (or a fresh `TopologyUpdate` when a delta cannot
+//! describe the change), and `PeerDisconnected` when another peer's last
+//! session ends. A peer registered with an ephemeral key is removed when
+//! its last session ends.
+//!
+//! Outbound messages go through a bounded per-connection queue drained by a
+//! writer task. A peer that leaves the queue full for `send_timeout`, or
+//! sends nothing for `ping_interval + pong_timeout`, is disconnected.
+
+use axum::extract::ws::{Message, WebSocket};
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
+use ghostwire_common::{protocol::WebSocketMessage, GhostwireError, TopologySync};
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    sync::{broadcast, mpsc},
+    time::{self, Instant},
+};
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use crate::coordination::CoordinationServer;
+
+/// Session timing and queue limits
+#[derive(Debug, Clone)]
+pub struct SessionConfig {
+    /// Time allowed for the opening `PeerRegister`
+    pub auth_timeout: Duration,
+    /// Interval between server pings
+    pub ping_interval: Duration,
+    /// Grace period after a missed ping interval before the peer counts as dead
+    pub pong_timeout: Duration,
+    /// Outbound messages buffered per connection
+    pub queue_capacity: usize,
+    /// Time a full queue may block before the peer counts as too slow
+    pub send_timeout: Duration,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            auth_timeout: Duration::from_secs(10),
+            ping_interval: Duration::from_secs(20),
+            pong_timeout: Duration::from_secs(10),
+            queue_capacity: 64,
+            send_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Open sessions, shared by every connection
+pub struct SessionHub {
+    config: SessionConfig,
+    /// Open sessions per peer
+    sessions: Mutex<HashMap<Uuid, usize>>,
+    /// Peers whose last session ended
+    disconnects: broadcast::Sender<Uuid>,
+}
+
+impl SessionHub {
+    /// Create a hub with no sessions
+    pub fn new(config: SessionConfig) -> Self {
+        Self {
+            config,
+            sessions: Mutex::new(HashMap::new()),
+            disconnects: broadcast::channel(256).0,
+        }
+    }
+
+    /// Number of peers with at least one open session
+    pub fn connected_peers(&self) -> usize {
+        self.sessions.lock().unwrap().len()
+    }
+
+    fn open(&self, peer_id: Uuid) {
+        *self.sessions.lock().unwrap().entry(peer_id).or_default() += 1;
+    }
+
+    /// Close one of the peer's sessions, returning whether it was the last
+    fn close(&self, peer_id: Uuid) -> bool {
+        let mut sessions = self.sessions.lock().unwrap();
+        if let Some(count) = sessions.get_mut(&peer_id) {
+            *count -= 1;
+            if *count == 0 {
+                sessions.remove(&peer_id);
+                // Nobody may be listening, which is fine
+                let _ = self.disconnects.send(peer_id);
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// Serve one WebSocket connection until it closes or fails a deadline
+pub async fn serve(socket: WebSocket, server: Arc<CoordinationServer>, hub: Arc<SessionHub>) {
+    let (sink, stream) = socket.split();
+    run(sink, stream, server, hub).await;
+}
+
+/// Why a session ended early
+type EndReason = String;
+
+async fn run<W, R, E>(sink: W, stream: R, server: Arc<CoordinationServer>, hub: Arc<SessionHub>)
+where
+    W: Sink<Message> + Unpin + Send + 'static,
+    R: Stream<Item = Result<Message, E>> + Unpin,
+    E: fmt::Display,
+{
+    let config = hub.config.clone();
+    let (queue, outbound) = mpsc::channel(config.queue_capacity);
+    let mut writer = tokio::spawn(write_messages(sink, outbound));
+
+    let mut session = Session {
+        stream,
+        queue,
+        server,
+        hub: Arc::clone(&hub),
+        config: config.clone(),
+    };
+
+    match session.authenticate().await {
+        Ok(peer_id) => {
+            hub.open(peer_id);
+            info!(peer_id = %peer_id, "Session opened");
+            match session.serve(peer_id).await {
+                Ok(()) => info!(peer_id = %peer_id, "Session closed"),
+                Err(reason) => warn!(peer_id = %peer_id, reason = %reason, "Session ended"),
+            }
+            // Peers of ephemeral pre-auth keys only exist while connected
+            if hub.close(peer_id) {
+                match session.server.remove_ephemeral_peer(peer_id).await {
+                    Ok(true) => info!(peer_id = %peer_id, "Ephemeral peer removed"),
+                    Ok(false) => {}
+                    Err(e) => warn!(peer_id = %peer_id, error = %e, "Failed to remove ephemeral peer"),
+                }
+            }
+        }
+        Err(reason) => debug!(reason = %reason, "Session rejected"),
+    }
+
+    // Closing the queue lets the writer flush and close the socket, unless
+    // the peer stopped reading
+    drop(session);
+    if time::timeout(config.send_timeout, &mut writer).await.is_err() {
+        writer.abort();
+    }
+}
+
+/// Drain the outbound queue into the socket, then close it
+async fn write_messages<W>(mut sink: W, mut outbound: mpsc::Receiver<WebSocketMessage>)
+where
+    W: Sink<Message> + Unpin,
+{
+    while let Some(message) = outbound.recv().await {
+        let text = match serde_json::to_string(&message) {
+            Ok(text) => text,
+            Err(e) => {
+                warn!(error = %e, "Failed to encode WebSocket message");
+                continue;
+            }
+        };
+        if sink.send(Message::Text(text.into())).await.is_err() {
+            return;
+        }
+    }
+    let _ = sink.send(Message::Close(None)).await;
+}
+
+struct Session<R> {
+    stream: R,
+    queue: mpsc::Sender<WebSocketMessage>,
+    server: Arc<CoordinationServer>,
+    hub: Arc<SessionHub>,
+    config: SessionConfig,
+}
+
+impl<R, E> Session<R>
+where
+    R: Stream<Item = Result<Message, E>> + Unpin,
+    E: fmt::Display,
+{
+    /// Wait for `PeerRegister` and resolve it to a peer
+    async fn authenticate(&mut self) -> Result<Uuid, EndReason> {
+        let deadline = Instant::now() + self.config.auth_timeout;
+        let request = loop {
+            let frame = time::timeout_at(deadline, self.stream.next())
+                .await
+                .map_err(|_| "no PeerRegister before the deadline".to_string())?;
+            match frame {
+                Some(Ok(Message::Text(text))) => match serde_json::from_str(text.as_str()) {
+                    Ok(WebSocketMessage::PeerRegister(request)) => break request,
+                    _ => {
+                        let message = "The first message must be PeerRegister".to_string();
+                        self.send(WebSocketMessage::PeerRegisterResponse(Err(message.clone()))).await?;
+                        return Err(message);
+                    }
+                },
+                Some(Ok(Message::Binary(_))) => {
+                    let message = "Binary messages are not supported".to_string();
+                    self.send(WebSocketMessage::PeerRegisterResponse(Err(message.clone()))).await?;
+                    return Err(message);
+                }
+                Some(Ok(Message::Close(_))) | None => return Err("closed before PeerRegister".to_string()),
+                Some(Err(e)) => return Err(e.to_string()),
+                // Control frames are answered by axum
+                Some(Ok(_)) => {}
+            }
+        };
+
+        match self.server.connect_peer(request).await {
+            Ok(response) => {
+                let peer_id = response.peer_id;
+                self.send(WebSocketMessage::PeerRegisterResponse(Ok(response))).await?;
+                Ok(peer_id)
+            }
+            Err(e) => {
+                self.send(WebSocketMessage::PeerRegisterResponse(Err(e.to_string()))).await?;
+                Err(e.to_string())
+            }
+        }
+    }
+
+    /// Exchange messages until the peer closes or a deadline passes
+    async fn serve(&mut self, peer_id: Uuid) -> Result<(), EndReason> {
+        let mut generations = self.server.subscribe();
+        // Start the peer off with the current topology
+        generations.mark_changed();
+        let mut synced = None;
+        let mut disconnects = self.hub.disconnects.subscribe();
+
+        let idle_limit = self.config.ping_interval + self.config.pong_timeout;
+        let mut ping = time::interval_at(Instant::now() + self.config.ping_interval, self.config.ping_interval);
+        let deadline = time::sleep(idle_limit);
+        tokio::pin!(deadline);
+
+        loop {
+            tokio::select! {
+                frame = self.stream.next() => {
+                    deadline.as_mut().reset(Instant::now() + idle_limit);
+                    match frame {
+                        Some(Ok(Message::Text(text))) => self.handle(peer_id, text.as_str()).await?,
+                        Some(Ok(Message::Binary(_))) => {
+                            self.send_error("Binary messages are not supported").await?;
+                        }
+                        Some(Ok(Message::Close(_))) | None => return Ok(()),
+                        Some(Err(e)) => return Err(e.to_string()),
+                        Some(Ok(_)) => {}
+                    }
+                }
+                changed = generations.changed() => {
+                    if changed.is_err() {
+                        return Err("coordination server shut down".to_string());
+                    }
+                    match self.server.topology_since(synced, Some(peer_id)).await {
+                        Ok(TopologySync::Snapshot(topology)) => {
+                            synced = Some(topology.generation);
+                            self.send(WebSocketMessage::TopologyUpdate(topology)).await?;
+                        }
+                        Ok(TopologySync::Delta(delta)) => {
+                            synced = Some(delta.generation);
+                            if !delta.is_empty() {
+                                self.send(WebSocketMessage::TopologyDelta(delta)).await?;
+                            }
+                        }
+                        Err(GhostwireError::PeerNotFound(_)) => return Err("peer was unregistered".to_string()),
+                        Err(e) => warn!(error = %e, "Failed to load topology for a session"),
+                    }
+                }
+                event = disconnects.recv() => match event {
+                    // Only peers in the viewer's topology are announced
+                    Ok(other) if other != peer_id => match self.server.is_visible_to(peer_id, other).await {
+                        Ok(true) => self.send(WebSocketMessage::PeerDisconnected { peer_id: other }).await?,
+                        Ok(false) => {}
+                        Err(GhostwireError::PeerNotFound(_)) => return Err("peer was unregistered".to_string()),
+                        Err(e) => warn!(error = %e, "Failed to check a disconnected peer's visibility"),
+                    },
+                    Ok(_) => {}
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        // A full topology is the best resync available
+                        warn!(peer_id = %peer_id, missed, "Session missed disconnect events");
+                        synced = None;
+                        generations.mark_changed();
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return Ok(()),
+                },
+                _ = ping.tick() => self.send(WebSocketMessage::Ping).await?,
+                _ = &mut deadline => return Err("no message within the ping deadline".to_string()),
+            }
+        }
+    }
+
+    /// Handle one message from an authenticated peer
+    async fn handle(&self, peer_id: Uuid, text: &str) -> Result<(), EndReason> {
+        match serde_json::from_str(text) {
+            Ok(WebSocketMessage::HealthCheck(health)) if health.peer_id == peer_id => {
+                if let Err(e) = self.server.update_peer_health(&health).await {
+                    warn!(peer_id = %peer_id, error = %e, "Failed to record health check");
+                    self.send_error("Failed to record health check").await?;
+                }
+                Ok(())
+            }
+            Ok(WebSocketMessage::HealthCheck(_)) => {
+                self.send_error("Health checks must be for the session's own peer").await
+            }
+            Ok(WebSocketMessage::Ping) => self.send(WebSocketMessage::Pong).await,
+            Ok(WebSocketMessage::Pong) => Ok(()),
+            Ok(_) => self.send_error("Unexpected message").await,
+            Err(e) => self.send_error(&format!("Invalid message: {}", e)).await,
+        }
+    }
+
+    async fn send_error(&self, message: &str) -> Result<(), EndReason> {
+        self.send(WebSocketMessage::Error { message: message.to_string() }).await
+    }
+
+    /// Queue a message, giving up on peers that leave the queue full
+    async fn send(&self, message: WebSocketMessage) -> Result<(), EndReason> {
+        self.queue
+            .send_timeout(message, self.config.send_timeout)
+            .await
+            .map_err(|e| match e {
+                mpsc::error::SendTimeoutError::Timeout(_) => "outbound queue stayed full".to_string(),
+                mpsc::error::SendTimeoutError::Closed(_) => "connection closed".to_string(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::{sink, stream};
+    use ghostwire_common::{
+        crypto::NodeKey, AclAction, AclRule, CreatePreAuthKeyRequest, HealthCheck, PeerMetadata,
+        RegisterPeerRequest, ServerConfig,
+    };
+    use std::convert::Infallible;
+    use tokio::task::JoinHandle;
+
+    async fn create_test_server() -> (Arc<CoordinationServer>, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let config = ServerConfig {
+            database_path: dir.path().join("ghostwire.db").to_string_lossy().to_string(),
+            network_cidr: "10.0.0.0/24".to_string(),
+            ..Default::default()
+        };
+        let metrics = zqlite_rs::ZQLiteMetrics::new("test");
+        (Arc::new(CoordinationServer::new(&config, metrics).await.unwrap()), dir)
+    }
+
+    /// Registration of `node` with a freshly minted pre-auth key
+    async fn register(server: &CoordinationServer, node: &NodeKey, ephemeral: bool) -> WebSocketMessage {
+        let key = server
+            .create_pre_auth_key(CreatePreAuthKeyRequest { ephemeral, ..Default::default() })
+            .await
+            .unwrap();
+        let challenge = server.issue_challenge().await.unwrap();
+        WebSocketMessage::PeerRegister(RegisterPeerRequest {
+            public_key: node.public_key(),
+            endpoints: vec![],
+            metadata: PeerMetadata::default(),
+            auth_key: Some(key.key),
+            proof: Some(node.prove(&challenge).unwrap()),
+        })
+    }
+
+    /// Let every peer of the test network reach every other
+    async fn allow_network(server: &CoordinationServer) {
+        server.add_acl_rule(AclRule {
+            id: Uuid::nil(),
+            source_cidr: "10.0.0.0/24".to_string(),
+            dest_cidr: "10.0.0.0/24".to_string(),
+            action: AclAction::Allow,
+            priority: 0,
+            description: None,
+            peer_id: None,
+            protocol: None,
+            ports: vec![],
+        }).await.unwrap();
+    }
+
+    /// One end of an in-memory connection
+    struct TestPeer {
+        server: Arc<CoordinationServer>,
+        frames: mpsc::UnboundedSender<Message>,
+        received: mpsc::UnboundedReceiver<Message>,
+        task: JoinHandle<()>,
+    }
+
+    impl TestPeer {
+        fn connect(server: &Arc<CoordinationServer>, hub: &Arc<SessionHub>) -> Self {
+            let (frames, inbound) = mpsc::unbounded_channel();
+            let (outbound, received) = mpsc::unbounded_channel();
+
+            let stream = Box::pin(stream::unfold(inbound, |mut inbound| async move {
+                inbound.recv().await.map(|frame| (Ok::<_, Infallible>(frame), inbound))
+            }));
+            let sink = Box::pin(sink::unfold(outbound, |outbound, frame: Message| async move {
+                outbound.send(frame).map_err(|_| "peer gone")?;
+                Ok::<_, &str>(outbound)
+            }));
+
+            let task = tokio::spawn(run(sink, stream, Arc::clone(server), Arc::clone(hub)));
+            Self { server: Arc::clone(server), frames, received, task }
+        }
+
+        fn send(&self, message: &WebSocketMessage) {
+            let text = serde_json::to_string(message).unwrap();
+            self.frames.send(Message::Text(text.into())).unwrap();
+        }
+
+        /// Next message, or `None` once the server closes the connection
+        async fn recv(&mut self) -> Option<WebSocketMessage> {
+            let frame = time::timeout(Duration::from_secs(5), self.received.recv()).await.unwrap();
+            match frame? {
+                Message::Text(text) => Some(serde_json::from_str(text.as_str()).unwrap()),
+                Message::Close(_) => None,
+                other => panic!("unexpected frame {:?}", other),
+            }
+        }
+
+        /// Register and consume the response and the first topology
+        async fn register(&mut self, node: &NodeKey, ephemeral: bool) -> Uuid {
+            self.send(&register(&self.server, node, ephemeral).await);
+            let peer_id = match self.recv().await {
+                Some(WebSocketMessage::PeerRegisterResponse(Ok(response))) => response.peer_id,
+                other => panic!("expected a registration response, got {:?}", other),
+            };
+            assert!(matches!(self.recv().await, Some(WebSocketMessage::TopologyUpdate(_))));
+            peer_id
+        }
+    }
+
+    #[tokio::test]
+    async fn test_session_lifecycle() {
+        let (server, _dir) = create_test_server().await;
+        let hub = Arc::new(SessionHub::new(SessionConfig::default()));
+
+        let node = NodeKey::generate().unwrap();
+        let mut peer = TestPeer::connect(&server, &hub);
+        let peer_id = peer.register(&node, false).await;
+        assert_eq!(hub.connected_peers(), 1);
+
+        peer.send(&WebSocketMessage::Ping);
+        assert!(matches!(peer.recv().await, Some(WebSocketMessage::Pong)));
+
+        let timestamp = chrono::Utc::now();
+        let health = |peer_id| HealthCheck {
+            peer_id,
+            timestamp,
+            server_latency_ms: Some(3.5),
+            connected_peers: 0,
+            rx_bytes: 10,
+            tx_bytes: 20,
+        };
+        peer.send(&WebSocketMessage::HealthCheck(health(Uuid::new_v4())));
+        assert!(matches!(peer.recv().await, Some(WebSocketMessage::Error { .. })));
+        peer.send(&WebSocketMessage::HealthCheck(health(peer_id)));
+        peer.send(&WebSocketMessage::Ping);
+        assert!(matches!(peer.recv().await, Some(WebSocketMessage::Pong)));
+        let stored = server.get_peer(peer_id).await.unwrap();
+        assert_eq!(stored.last_seen.timestamp(), timestamp.timestamp());
+
+        // Reconnecting with the same key resumes the peer
+        peer.frames.send(Message::Close(None)).unwrap();
+        assert!(peer.recv().await.is_none());
+        peer.task.await.unwrap();
+        assert_eq!(hub.connected_peers(), 0);
+
+        let mut again = TestPeer::connect(&server, &hub);
+        assert_eq!(again.register(&node, false).await, peer_id);
+        assert_eq!(server.count_peers().await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_first_message_must_register() {
+        let (server, _dir) = create_test_server().await;
+        let hub = Arc::new(SessionHub::new(SessionConfig::default()));
+
+        let mut peer = TestPeer::connect(&server, &hub);
+        peer.send(&WebSocketMessage::Ping);
+        assert!(matches!(peer.recv().await, Some(WebSocketMessage::PeerRegisterResponse(Err(_)))));
+        assert!(peer.recv().await.is_none());
+        assert_eq!(hub.connected_peers(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_topology_pushes_and_disconnects() {
+        let (server, _dir) = create_test_server().await;
+        let hub = Arc::new(SessionHub::new(SessionConfig::default()));
+
+        // Peers are only shown the peers they may reach
+        allow_network(&server).await;
+
+        let mut first = TestPeer::connect(&server, &hub);
+        first.register(&NodeKey::generate().unwrap(), false).await;
+
+        let mut second = TestPeer::connect(&server, &hub);
+        let second_id = second.register(&NodeKey::generate().unwrap(), false).await;
+
+        // Registering the second peer advanced the generation
+        match first.recv().await {
+            Some(WebSocketMessage::TopologyDelta(delta)) => {
+                assert_eq!(delta.added.peers.len(), 1);
+                assert_eq!(delta.added.peers[0].id, second_id);
+                assert!(delta.updated.peers.is_empty());
+            }
+            other => panic!("expected a topology delta, got {:?}", other),
+        }
+
+        drop(second);
+        assert!(matches!(
+            first.recv().await,
+            Some(WebSocketMessage::PeerDisconnected { peer_id }) if peer_id == second_id
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_hidden_peer_disconnects_are_not_announced() {
+        let (server, _dir) = create_test_server().await;
+        let hub = Arc::new(SessionHub::new(SessionConfig::default()));
+
+        // Without an allow rule neither peer is shown the other
+        let mut first = TestPeer::connect(&server, &hub);
+        first.register(&NodeKey::generate().unwrap(), false).await;
+        let mut second = TestPeer::connect(&server, &hub);
+        second.register(&NodeKey::generate().unwrap(), false).await;
+
+        drop(second);
+        let announced = time::timeout(Duration::from_millis(200), first.received.recv()).await;
+        assert!(announced.is_err(), "got {:?}", announced);
+    }
+
+    #[tokio::test]
+    async fn test_silent_peer_is_disconnected() {
+        let (server, _dir) = create_test_server().await;
+        let config = SessionConfig {
+            ping_interval: Duration::from_millis(50),
+            pong_timeout: Duration::from_millis(50),
+            ..Default::default()
+        };
+        let hub = Arc::new(SessionHub::new(config));
+        allow_network(&server).await;
+
+        let mut watcher = TestPeer::connect(&server, &hub);
+        watcher.register(&NodeKey::generate().unwrap(), false).await;
+        let mut silent = TestPeer::connect(&server, &hub);
+        let silent_id = silent.register(&NodeKey::generate().unwrap(), false).await;
+
+        // The watcher answers pings; the silent peer never does
+        loop {
+            match watcher.recv().await {
+                Some(WebSocketMessage::Ping) => watcher.send(&WebSocketMessage::Pong),
+                Some(WebSocketMessage::PeerDisconnected { peer_id }) => {
+                    assert_eq!(peer_id, silent_id);
+                    break;
+                }
+                Some(_) => {}
+                None => panic!("watcher was disconnected"),
+            }
+        }
+        while silent.recv().await.is_some() {}
+        assert_eq!(hub.connected_peers(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_slow_reader_is_disconnected() {
+        let (server, _dir) = create_test_server().await;
+        let config = SessionConfig {
+            queue_capacity: 1,
+            send_timeout: Duration::from_millis(50),
+            ..Default::default()
+        };
+        let hub = Arc::new(SessionHub::new(config));
+
+        // A socket whose writes never complete
+        let (frames, inbound) = mpsc::unbounded_channel::<Message>();
+        let stream = Box::pin(stream::unfold(inbound, |mut inbound| async move {
+            inbound.recv().await.map(|frame| (Ok::<_, Infallible>(frame), inbound))
+        }));
+        let sink = Box::pin(sink::unfold((), |_, _: Message| std::future::pending::<Result<(), Infallible>>()));
+        let task = tokio::spawn(run(sink, stream, Arc::clone(&server), Arc::clone(&hub)));
+
+        let text = serde_json::to_string(&register(&server, &NodeKey::generate().unwrap(), false).await).unwrap();
+        frames.send(Message::Text(text.into())).unwrap();
+        for _ in 0..3 {
+            let text = serde_json::to_string(&WebSocketMessage::Ping).unwrap();
+            frames.send(Message::Text(text.into())).unwrap();
+        }
+
+        time::timeout(Duration::from_secs(5), task).await.unwrap().unwrap();
+        assert_eq!(hub.connected_peers(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_ephemeral_peer_removed_on_disconnect() {
+        let (server, _dir) = create_test_server().await;
+        let hub = Arc::new(SessionHub::new(SessionConfig::default()));
+
+        let mut kept = TestPeer::connect(&server, &hub);
+        let kept_id = kept.register(&NodeKey::generate().unwrap(), false).await;
+        let mut ephemeral = TestPeer::connect(&server, &hub);
+        let ephemeral_id = ephemeral.register(&NodeKey::generate().unwrap(), true).await;
+        assert_eq!(server.count_peers().await.unwrap(), 2);
+
+        for peer in [kept, ephemeral] {
+            peer.frames.send(Message::Close(None)).unwrap();
+            peer.task.await.unwrap();
+        }
+        assert!(server.get_peer(kept_id).await.is_ok());
+        assert!(matches!(server.get_peer(ephemeral_id).await, Err(GhostwireError::PeerNotFound(_))));
+    }
+}
\ No newline at end of file
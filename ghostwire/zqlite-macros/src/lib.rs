@@ -0,0 +1,194 @@
+//! Compile-time checked query macros for zqlite-rs
+//!
+//! Use these through `zqlite_rs::query!` and `zqlite_rs::query_as!` with the
+//! `macros` feature; the generated code refers to `::zqlite_rs`.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Ident, Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{parse_macro_input, Expr, LitStr, Path, Token};
+
+mod schema;
+mod sql;
+
+use schema::{Affinity, Schema};
+use sql::SqlType;
+
+/// Check a statement against the schema snapshot and build a `TypedQuery`
+///
+/// ```ignore
+/// let peer = zqlite_rs::query!("SELECT id, last_seen FROM peers WHERE id = ?", peer_id)
+///     .fetch_one(&conn)?;
+/// println!("{} {}", peer.id, peer.last_seen);
+/// ```
+///
+/// SELECT rows become an anonymous struct with one field per column: INTEGER
+/// columns are `i64`, REAL and NUMERIC `f64`, TEXT `String` and BLOB
+/// `Vec<u8>`, wrapped in `Option` when the column is nullable. Other
+/// statements produce `()`.
+#[proc_macro]
+pub fn query(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as QueryInput);
+    expand(None, input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+/// Like [`query!`](macro@query), converting each row into an existing struct
+///
+/// ```ignore
+/// let peer = zqlite_rs::query_as!(PeerRow, "SELECT id, public_key FROM peers WHERE id = ?", id)
+///     .fetch_one(&conn)?;
+/// ```
+///
+/// The struct needs one field per selected column, named after it, whose
+/// type is `Compatible` with the column: reading a BLOB column into a
+/// `String` field is a compile error, and so is reading a nullable column
+/// into anything but an `Option`.
+#[proc_macro]
+pub fn query_as(input: TokenStream) -> TokenStream {
+    let QueryAsInput { record, input } = parse_macro_input!(input as QueryAsInput);
+    expand(Some(record), input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+struct QueryInput {
+    sql: LitStr,
+    args: Vec<Expr>,
+}
+
+impl Parse for QueryInput {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let sql = input.parse()?;
+        let mut args = Vec::new();
+        if input.parse::<Option<Token![,]>>()?.is_some() {
+            args = Punctuated::<Expr, Token![,]>::parse_terminated(input)?
+                .into_iter()
+                .collect();
+        }
+        Ok(Self { sql, args })
+    }
+}
+
+struct QueryAsInput {
+    record: Path,
+    input: QueryInput,
+}
+
+impl Parse for QueryAsInput {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let record = input.parse()?;
+        input.parse::<Token![,]>()?;
+        Ok(Self {
+            record,
+            input: input.parse()?,
+        })
+    }
+}
+
+fn marker(affinity: Affinity) -> TokenStream2 {
+    let name = Ident::new(affinity.marker(), Span::call_site());
+    quote!(::zqlite_rs::macros::sql_types::#name)
+}
+
+/// Field type of an anonymous `query!` record
+fn record_type(ty: SqlType) -> TokenStream2 {
+    let base = match ty.affinity {
+        Affinity::Integer => quote!(i64),
+        Affinity::Real | Affinity::Numeric => quote!(f64),
+        Affinity::Text => quote!(::std::string::String),
+        Affinity::Blob => quote!(::std::vec::Vec<u8>),
+    };
+    if ty.nullable {
+        quote!(::std::option::Option<#base>)
+    } else {
+        base
+    }
+}
+
+fn field_name(column: &str) -> Ident {
+    syn::parse_str(column).unwrap_or_else(|_| Ident::new_raw(column, Span::call_site()))
+}
+
+fn expand(record: Option<Path>, input: QueryInput) -> syn::Result<TokenStream2> {
+    let sql = input.sql.value();
+    let error = |message: String| syn::Error::new(input.sql.span(), message);
+
+    let (schema, snapshot) = Schema::load().map_err(error)?;
+    let analysis = sql::analyze(&sql, &schema).map_err(error)?;
+
+    if analysis.params.len() != input.args.len() {
+        return Err(error(format!(
+            "expected {} parameters, got {}",
+            analysis.params.len(),
+            input.args.len()
+        )));
+    }
+
+    let params = analysis.params.iter().zip(&input.args).map(|(ty, arg)| {
+        let marker = marker(ty.affinity);
+        quote!(::zqlite_rs::macros::encode::<_, #marker>(&(#arg)))
+    });
+
+    let fields: Vec<Ident> = analysis.outputs.iter().map(|(name, _)| field_name(name)).collect();
+    let decoders: Vec<TokenStream2> = analysis
+        .outputs
+        .iter()
+        .enumerate()
+        .map(|(index, (_, ty))| {
+            let marker = marker(ty.affinity);
+            if ty.nullable {
+                quote!(::zqlite_rs::macros::decode_nullable::<_, #marker>(row, #index)?)
+            } else {
+                quote!(::zqlite_rs::macros::decode::<_, #marker>(row, #index)?)
+            }
+        })
+        .collect();
+
+    let map_into = |record: &TokenStream2| {
+        quote! {
+            |row: &::zqlite_rs::Row<'_>| ::std::result::Result::Ok(#record {
+                #(#fields: #decoders,)*
+            })
+        }
+    };
+
+    let (definition, record, map) = match record {
+        Some(path) => {
+            let record = quote!(#path);
+            let map = map_into(&record);
+            (quote!(), record, map)
+        }
+        None if analysis.outputs.is_empty() => (
+            quote!(),
+            quote!(()),
+            quote!(|_: &::zqlite_rs::Row<'_>| ::std::result::Result::Ok(())),
+        ),
+        None => {
+            let types = analysis.outputs.iter().map(|(_, ty)| record_type(*ty));
+            let definition = quote! {
+                #[derive(Debug, Clone)]
+                #[allow(dead_code)]
+                struct Record {
+                    #(#fields: #types,)*
+                }
+            };
+            let record = quote!(Record);
+            let map = map_into(&record);
+            (definition, record, map)
+        }
+    };
+
+    let snapshot = snapshot.to_string_lossy().into_owned();
+    Ok(quote! {
+        {
+            // Rebuild when the snapshot changes
+            const _: &[u8] = include_bytes!(#snapshot);
+            #definition
+            ::zqlite_rs::TypedQuery::<#record>::new(
+                #sql,
+                ::std::vec![#(#params),*],
+                #map,
+            )
+        }
+    })
+}
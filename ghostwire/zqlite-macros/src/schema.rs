@@ -0,0 +1,138 @@
+//! Schema snapshots written by `cargo zqlite prepare`
+
+use serde::Deserialize;
+use std::path::PathBuf;
+
+/// Default snapshot file, next to the calling crate's `Cargo.toml`
+const SNAPSHOT_FILE: &str = "zqlite-schema.json";
+
+/// Tables known at compile time
+#[derive(Debug, Deserialize)]
+pub struct Schema {
+    pub tables: Vec<Table>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Table {
+    pub name: String,
+    pub columns: Vec<Column>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Column {
+    pub name: String,
+    #[serde(default)]
+    pub decl_type: String,
+    #[serde(default)]
+    pub not_null: bool,
+    #[serde(default)]
+    pub pk: bool,
+}
+
+impl Schema {
+    /// Read the snapshot named by `ZQLITE_SCHEMA`, or the default one
+    pub fn load() -> Result<(Self, PathBuf), String> {
+        let manifest_dir = std::env::var("CARGO_MANIFEST_DIR")
+            .map_err(|_| "CARGO_MANIFEST_DIR is not set".to_string())?;
+        let file = std::env::var("ZQLITE_SCHEMA").unwrap_or_else(|_| SNAPSHOT_FILE.to_string());
+        let path = PathBuf::from(manifest_dir).join(file);
+
+        let json = std::fs::read_to_string(&path).map_err(|e| {
+            format!(
+                "cannot read schema snapshot {}: {}; run `cargo zqlite prepare`",
+                path.display(),
+                e
+            )
+        })?;
+        let schema = serde_json::from_str(&json)
+            .map_err(|e| format!("invalid schema snapshot {}: {}", path.display(), e))?;
+
+        Ok((schema, path))
+    }
+
+    pub fn table(&self, name: &str) -> Option<&Table> {
+        self.tables.iter().find(|table| table.name == name)
+    }
+}
+
+impl Table {
+    pub fn column(&self, name: &str) -> Option<&Column> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+}
+
+impl Column {
+    /// Primary keys are implicitly NOT NULL
+    pub fn nullable(&self) -> bool {
+        !self.not_null && !self.pk
+    }
+
+    pub fn affinity(&self) -> Affinity {
+        Affinity::from_decl_type(&self.decl_type)
+    }
+}
+
+/// Column affinity, following SQLite's rules for declared types
+///
+/// zqlite's own types map to the storage class the engine hands back:
+/// `BOOLEAN` is an integer, while `UUID`, `JSON` and the date and time
+/// types are text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Affinity {
+    Integer,
+    Text,
+    Blob,
+    Real,
+    Numeric,
+}
+
+impl Affinity {
+    pub fn from_decl_type(decl_type: &str) -> Self {
+        let decl_type = decl_type.to_ascii_uppercase();
+        if decl_type.contains("INT") || decl_type.starts_with("BOOL") {
+            Affinity::Integer
+        } else if ["CHAR", "CLOB", "TEXT", "UUID", "JSON", "DATE", "TIME"]
+            .iter()
+            .any(|t| decl_type.contains(t))
+        {
+            Affinity::Text
+        } else if decl_type.contains("BLOB") || decl_type.is_empty() {
+            Affinity::Blob
+        } else if ["REAL", "FLOA", "DOUB"].iter().any(|t| decl_type.contains(t)) {
+            Affinity::Real
+        } else {
+            Affinity::Numeric
+        }
+    }
+
+    /// Name of the marker type in `zqlite_rs::macros::sql_types`
+    pub fn marker(self) -> &'static str {
+        match self {
+            Affinity::Integer => "Integer",
+            Affinity::Text => "Text",
+            Affinity::Blob => "Blob",
+            Affinity::Real => "Real",
+            Affinity::Numeric => "Numeric",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_affinity_rules() {
+        assert_eq!(Affinity::from_decl_type("BIGINT"), Affinity::Integer);
+        assert_eq!(Affinity::from_decl_type("VARCHAR(64)"), Affinity::Text);
+        assert_eq!(Affinity::from_decl_type("TEXT COMPRESSED"), Affinity::Text);
+        assert_eq!(Affinity::from_decl_type(""), Affinity::Blob);
+        assert_eq!(Affinity::from_decl_type("DOUBLE"), Affinity::Real);
+        assert_eq!(Affinity::from_decl_type("DECIMAL(10,2)"), Affinity::Numeric);
+        assert_eq!(Affinity::from_decl_type("BOOLEAN"), Affinity::Integer);
+        assert_eq!(Affinity::from_decl_type("UUID"), Affinity::Text);
+        assert_eq!(Affinity::from_decl_type("JSONB"), Affinity::Text);
+        assert_eq!(Affinity::from_decl_type("DATETIME"), Affinity::Text);
+        assert_eq!(Affinity::from_decl_type("TIMESTAMPTZ"), Affinity::Text);
+    }
+}
@@ -0,0 +1,510 @@
+//! Checking statements in ZQLite's dialect against a schema snapshot
+//!
+//! Covers what the ZQLite parser accepts for single-table statements: WHERE
+//! is a chain of comparisons joined by AND/OR, operands are a column, `?` or
+//! a literal, and LIMIT/OFFSET take integer literals.
+
+use crate::schema::{Affinity, Schema, Table};
+
+/// Type of a parameter or output column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SqlType {
+    pub affinity: Affinity,
+    pub nullable: bool,
+}
+
+/// What a statement binds and returns
+#[derive(Debug, PartialEq)]
+pub struct Analysis {
+    /// One entry per `?`, in order
+    pub params: Vec<SqlType>,
+    /// Output columns, in order
+    pub outputs: Vec<(String, SqlType)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Param,
+    Number,
+    Str,
+    Punct(&'static str),
+}
+
+fn describe(token: Option<&Token>) -> String {
+    match token {
+        None => "end of statement".to_string(),
+        Some(Token::Word(word)) => format!("`{}`", word),
+        Some(Token::Param) => "`?`".to_string(),
+        Some(Token::Number) => "a number".to_string(),
+        Some(Token::Str) => "a string".to_string(),
+        Some(Token::Punct(p)) => format!("`{}`", p),
+    }
+}
+
+fn tokenize(sql: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = sql.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '?' => Token::Param,
+            '\'' | '"' => {
+                // ZQLite string literals have no escapes
+                if !chars.by_ref().any(|next| next == c) {
+                    return Err("unterminated string literal".to_string());
+                }
+                Token::Str
+            }
+            c if c.is_ascii_digit() => {
+                while chars.peek().is_some_and(|c| c.is_ascii_digit() || *c == '.') {
+                    chars.next();
+                }
+                Token::Number
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut word = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if !(next.is_ascii_alphanumeric() || next == '_') {
+                        break;
+                    }
+                    word.push(next);
+                    chars.next();
+                }
+                Token::Word(word)
+            }
+            '!' if chars.next_if_eq(&'=').is_some() => Token::Punct("!="),
+            '<' if chars.next_if_eq(&'=').is_some() => Token::Punct("<="),
+            '>' if chars.next_if_eq(&'=').is_some() => Token::Punct(">="),
+            '<' => Token::Punct("<"),
+            '>' => Token::Punct(">"),
+            '=' => Token::Punct("="),
+            ',' => Token::Punct(","),
+            '(' => Token::Punct("("),
+            ')' => Token::Punct(")"),
+            '*' => Token::Punct("*"),
+            ';' => Token::Punct(";"),
+            '-' => Token::Punct("-"),
+            other => return Err(format!("unexpected character `{}`", other)),
+        };
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+/// ZQLite keywords are all-uppercase or all-lowercase
+fn is_keyword(word: &str, keyword: &str) -> bool {
+    word == keyword || word == keyword.to_ascii_lowercase()
+}
+
+enum Operand {
+    Column(SqlType),
+    Param,
+    Literal,
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    schema: &'a Schema,
+    params: Vec<SqlType>,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn unexpected<T>(&self, expected: &str) -> Result<T, String> {
+        Err(format!("expected {}, found {}", expected, describe(self.peek())))
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Word(word)) if is_keyword(word, keyword) => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), String> {
+        if self.keyword(keyword) {
+            Ok(())
+        } else {
+            self.unexpected(keyword)
+        }
+    }
+
+    fn punct(&mut self, punct: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Punct(p)) if *p == punct) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_punct(&mut self, punct: &str) -> Result<(), String> {
+        if self.punct(punct) {
+            Ok(())
+        } else {
+            self.unexpected(&format!("`{}`", punct))
+        }
+    }
+
+    fn identifier(&mut self) -> Result<String, String> {
+        match self.peek() {
+            Some(Token::Word(word)) => {
+                let word = word.clone();
+                self.pos += 1;
+                Ok(word)
+            }
+            _ => self.unexpected("a name"),
+        }
+    }
+
+    fn table(&mut self) -> Result<&'a Table, String> {
+        let name = self.identifier()?;
+        self.schema
+            .table(&name)
+            .ok_or_else(|| format!("no table `{}` in the schema snapshot", name))
+    }
+
+    fn column(&mut self, table: &Table) -> Result<(String, SqlType), String> {
+        let name = self.identifier()?;
+        let column = table
+            .column(&name)
+            .ok_or_else(|| format!("no column `{}` in table `{}`", name, table.name))?;
+        let ty = SqlType {
+            affinity: column.affinity(),
+            nullable: column.nullable(),
+        };
+        Ok((name, ty))
+    }
+
+    fn operand(&mut self, table: &Table) -> Result<Operand, String> {
+        match self.peek() {
+            Some(Token::Param) => {
+                self.pos += 1;
+                Ok(Operand::Param)
+            }
+            Some(Token::Number) | Some(Token::Str) => {
+                self.pos += 1;
+                Ok(Operand::Literal)
+            }
+            Some(Token::Punct("-")) => {
+                self.pos += 1;
+                match self.peek() {
+                    Some(Token::Number) => {
+                        self.pos += 1;
+                        Ok(Operand::Literal)
+                    }
+                    _ => self.unexpected("a number"),
+                }
+            }
+            Some(Token::Word(word))
+                if ["NULL", "CURRENT_TIMESTAMP", "CURRENT_DATE", "CURRENT_TIME"]
+                    .iter()
+                    .any(|k| is_keyword(word, k)) =>
+            {
+                self.pos += 1;
+                Ok(Operand::Literal)
+            }
+            Some(Token::Word(_)) => Ok(Operand::Column(self.column(table)?.1)),
+            _ => self.unexpected("a column, `?` or a literal"),
+        }
+    }
+
+    /// Record the type of `?` from the column it is assigned or compared to
+    fn bind(&mut self, operand: Operand, column: Option<SqlType>) -> Result<(), String> {
+        if let Operand::Param = operand {
+            let ty = column.ok_or("cannot infer the type of `?`: compare it with a column")?;
+            self.params.push(ty);
+        }
+        Ok(())
+    }
+
+    fn comparison(&mut self) -> Result<(), String> {
+        let op = match self.peek() {
+            Some(Token::Punct(op)) if ["=", "!=", "<", "<=", ">", ">="].contains(op) => true,
+            Some(Token::Word(word)) if is_keyword(word, "LIKE") => true,
+            _ => false,
+        };
+        if op {
+            self.pos += 1;
+            Ok(())
+        } else {
+            self.unexpected("a comparison operator")
+        }
+    }
+
+    fn where_clause(&mut self, table: &Table) -> Result<(), String> {
+        if !self.keyword("WHERE") {
+            return Ok(());
+        }
+
+        loop {
+            let left = self.operand(table)?;
+            self.comparison()?;
+            let right = self.operand(table)?;
+
+            let column = |operand: &Operand| match operand {
+                Operand::Column(ty) => Some(*ty),
+                _ => None,
+            };
+            let (left_type, right_type) = (column(&left), column(&right));
+            self.bind(left, right_type)?;
+            self.bind(right, left_type)?;
+
+            if !(self.keyword("AND") || self.keyword("OR")) {
+                return Ok(());
+            }
+        }
+    }
+
+    fn integer_literal(&mut self, clause: &str) -> Result<(), String> {
+        match self.peek() {
+            Some(Token::Number) => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(Token::Param) => Err(format!("ZQLite only accepts an integer literal after {}", clause)),
+            _ => self.unexpected("an integer"),
+        }
+    }
+
+    fn select(&mut self) -> Result<Vec<(String, SqlType)>, String> {
+        self.keyword("DISTINCT");
+
+        let mut names = Vec::new();
+        let all = self.punct("*");
+        if !all {
+            loop {
+                names.push(self.identifier()?);
+                if self.punct("(") {
+                    return Err("only plain columns can be selected".to_string());
+                }
+                if !self.punct(",") {
+                    break;
+                }
+            }
+        }
+
+        self.expect_keyword("FROM")?;
+        let table = self.table()?;
+
+        let outputs = if all {
+            table
+                .columns
+                .iter()
+                .map(|c| (c.name.clone(), SqlType { affinity: c.affinity(), nullable: c.nullable() }))
+                .collect()
+        } else {
+            names
+                .into_iter()
+                .map(|name| {
+                    let column = table
+                        .column(&name)
+                        .ok_or_else(|| format!("no column `{}` in table `{}`", name, table.name))?;
+                    Ok((name, SqlType { affinity: column.affinity(), nullable: column.nullable() }))
+                })
+                .collect::<Result<_, String>>()?
+        };
+
+        self.where_clause(table)?;
+
+        if self.keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            loop {
+                self.column(table)?;
+                let _ = self.keyword("ASC") || self.keyword("DESC");
+                if !self.punct(",") {
+                    break;
+                }
+            }
+        }
+        if self.keyword("LIMIT") {
+            self.integer_literal("LIMIT")?;
+        }
+        if self.keyword("OFFSET") {
+            self.integer_literal("OFFSET")?;
+        }
+
+        Ok(outputs)
+    }
+
+    fn insert(&mut self) -> Result<(), String> {
+        if self.keyword("OR") && !(self.keyword("REPLACE") || self.keyword("IGNORE")) {
+            return self.unexpected("REPLACE or IGNORE");
+        }
+        self.expect_keyword("INTO")?;
+        let table = self.table()?;
+
+        let mut columns = Vec::new();
+        if self.punct("(") {
+            loop {
+                columns.push(self.column(table)?.1);
+                if !self.punct(",") {
+                    break;
+                }
+            }
+            self.expect_punct(")")?;
+        } else {
+            columns = table
+                .columns
+                .iter()
+                .map(|c| SqlType { affinity: c.affinity(), nullable: c.nullable() })
+                .collect();
+        }
+
+        self.expect_keyword("VALUES")?;
+        loop {
+            self.expect_punct("(")?;
+            let mut values = Vec::new();
+            loop {
+                values.push(self.operand(table)?);
+                if !self.punct(",") {
+                    break;
+                }
+            }
+            self.expect_punct(")")?;
+
+            if values.len() != columns.len() {
+                return Err(format!("{} values for {} columns", values.len(), columns.len()));
+            }
+            for (value, column) in values.into_iter().zip(&columns) {
+                self.bind(value, Some(*column))?;
+            }
+            if !self.punct(",") {
+                return Ok(());
+            }
+        }
+    }
+
+    fn update(&mut self) -> Result<(), String> {
+        let table = self.table()?;
+        self.expect_keyword("SET")?;
+        loop {
+            let (_, column) = self.column(table)?;
+            self.expect_punct("=")?;
+            let value = self.operand(table)?;
+            self.bind(value, Some(column))?;
+            if !self.punct(",") {
+                break;
+            }
+        }
+        self.where_clause(table)
+    }
+
+    fn delete(&mut self) -> Result<(), String> {
+        self.expect_keyword("FROM")?;
+        let table = self.table()?;
+        self.where_clause(table)
+    }
+}
+
+/// Check `sql` against `schema` and infer its parameter and output types
+pub fn analyze(sql: &str, schema: &Schema) -> Result<Analysis, String> {
+    let mut parser = Parser {
+        tokens: tokenize(sql)?,
+        pos: 0,
+        schema,
+        params: Vec::new(),
+    };
+
+    let mut outputs = Vec::new();
+    if parser.keyword("SELECT") {
+        outputs = parser.select()?;
+    } else if parser.keyword("INSERT") {
+        parser.insert()?;
+    } else if parser.keyword("UPDATE") {
+        parser.update()?;
+    } else if parser.keyword("DELETE") {
+        parser.delete()?;
+    } else {
+        return Err("only SELECT, INSERT, UPDATE and DELETE statements can be checked".to_string());
+    }
+
+    parser.punct(";");
+    if parser.peek().is_some() {
+        return parser.unexpected("end of statement");
+    }
+
+    Ok(Analysis {
+        params: parser.params,
+        outputs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema() -> Schema {
+        serde_json::from_str(
+            r#"{"tables": [{"name": "peers", "columns": [
+                {"name": "id", "decl_type": "TEXT", "pk": true},
+                {"name": "public_key", "decl_type": "BLOB", "not_null": true},
+                {"name": "last_seen", "decl_type": "REAL", "not_null": true},
+                {"name": "metadata", "decl_type": "TEXT COMPRESSED"}
+            ]}]}"#,
+        )
+        .unwrap()
+    }
+
+    fn ty(affinity: Affinity, nullable: bool) -> SqlType {
+        SqlType { affinity, nullable }
+    }
+
+    #[test]
+    fn test_select_types() {
+        let analysis = analyze(
+            "SELECT id, public_key, metadata FROM peers WHERE id = ? AND last_seen > ? ORDER BY last_seen DESC LIMIT 10",
+            &schema(),
+        )
+        .unwrap();
+
+        assert_eq!(analysis.params, vec![ty(Affinity::Text, false), ty(Affinity::Real, false)]);
+        assert_eq!(
+            analysis.outputs,
+            vec![
+                ("id".to_string(), ty(Affinity::Text, false)),
+                ("public_key".to_string(), ty(Affinity::Blob, false)),
+                ("metadata".to_string(), ty(Affinity::Text, true)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_write_statements() {
+        let schema = schema();
+
+        let insert = analyze("INSERT OR REPLACE INTO peers (id, last_seen) VALUES (?, ?)", &schema).unwrap();
+        assert_eq!(insert.params, vec![ty(Affinity::Text, false), ty(Affinity::Real, false)]);
+        assert!(insert.outputs.is_empty());
+
+        let update = analyze("UPDATE peers SET last_seen = ? WHERE id = ?", &schema).unwrap();
+        assert_eq!(update.params.len(), 2);
+
+        let delete = analyze("delete from peers where last_seen < ?;", &schema).unwrap();
+        assert_eq!(delete.params, vec![ty(Affinity::Real, false)]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let schema = schema();
+        let error = |sql| analyze(sql, &schema).unwrap_err();
+
+        assert_eq!(error("SELECT pubkey FROM peers"), "no column `pubkey` in table `peers`");
+        assert_eq!(error("SELECT * FROM peer"), "no table `peer` in the schema snapshot");
+        assert!(error("SELECT id FROM peers LIMIT ?").contains("integer literal"));
+        assert!(error("SELECT id FROM peers WHERE ? = ?").contains("cannot infer"));
+        assert!(error("INSERT INTO peers (id) VALUES (?, ?)").contains("2 values for 1 columns"));
+        assert!(error("SELECT id FROM peers; DROP TABLE peers").contains("end of statement"));
+        assert!(error("Select id FROM peers").contains("only SELECT"));
+    }
+}
@@ -1,7 +1,10 @@
 //! Async wrapper for ZQLite connections
 
-use crate::{Connection, ConnectionPool, Error, PoolConfig, PooledConnectionGuard, Result, Rows};
-use std::sync::Arc;
+use crate::{
+    Connection, ConnectionPool, Error, InterruptHandle, PoolConfig, PooledConnectionGuard, Query,
+    Result, Rows, TableInfo,
+};
+use std::sync::{Arc, Mutex, PoisonError};
 use tokio::task;
 use tracing::{debug, instrument};
 
@@ -40,39 +43,94 @@ impl AsyncConnection {
     }
 
     /// Execute a SQL statement without returning results
+    ///
+    /// Dropping the returned future interrupts the statement, so wrapping it
+    /// in `tokio::time::timeout` acts as a statement timeout.
     #[instrument(skip(self, sql), fields(sql = %sql))]
     pub async fn execute(&self, sql: &str) -> Result<()> {
         let sql = sql.to_string();
-        let pool = Arc::clone(&self.pool);
 
-        task::spawn_blocking(move || {
-            let conn = pool.get_connection()?;
-            conn.execute(&sql)
-        })
-        .await
-        .map_err(|e| Error::pool_error(format!("Task join error: {}", e)))??;
+        run_interruptible(Arc::clone(&self.pool), move |conn| conn.execute(&sql)).await?;
 
         debug!("Executed SQL statement successfully");
         Ok(())
     }
 
+    /// Execute a built query without returning results
+    ///
+    /// Dropping the returned future interrupts the statement.
+    #[instrument(skip(self, query), fields(sql = %query.sql()))]
+    pub async fn execute_query(&self, query: Query) -> Result<()> {
+        run_interruptible(Arc::clone(&self.pool), move |conn| conn.execute_query(&query)).await?;
+
+        debug!("Executed query successfully");
+        Ok(())
+    }
+
+    /// Run a built query and return results
+    ///
+    /// Dropping the returned future interrupts the query.
+    #[instrument(skip(self, query), fields(sql = %query.sql()))]
+    pub async fn fetch_query(&self, query: Query) -> Result<Rows> {
+        let rows = run_interruptible(Arc::clone(&self.pool), move |conn| conn.fetch_query(&query)).await?;
+
+        debug!("Executed query successfully");
+        Ok(rows)
+    }
+
+    /// Execute built queries in order, all or nothing
+    ///
+    /// Async counterpart of [`Connection::execute_queries_in_transaction`].
+    #[instrument(skip(self, queries), fields(count = queries.len()))]
+    pub async fn execute_queries(&self, queries: Vec<Query>) -> Result<()> {
+        run_interruptible(Arc::clone(&self.pool), move |conn| {
+            conn.execute_queries_in_transaction(&queries)
+        })
+        .await?;
+
+        debug!("Executed queries successfully");
+        Ok(())
+    }
+
     /// Execute a SQL query and return results
+    ///
+    /// Dropping the returned future interrupts the query.
     #[instrument(skip(self, sql), fields(sql = %sql))]
     pub async fn query(&self, sql: &str) -> Result<Rows> {
         let sql = sql.to_string();
-        let pool = Arc::clone(&self.pool);
 
-        let rows = task::spawn_blocking(move || {
-            let conn = pool.get_connection()?;
-            conn.query(&sql)
-        })
-        .await
-        .map_err(|e| Error::pool_error(format!("Task join error: {}", e)))??;
+        let rows = run_interruptible(Arc::clone(&self.pool), move |conn| conn.query(&sql)).await?;
 
         debug!("Executed query successfully");
         Ok(rows)
     }
 
+    /// Execute a script of `;`-separated statements in order
+    ///
+    /// Async counterpart of [`Connection::execute_batch`]; with
+    /// `in_transaction` the script is applied all or nothing.
+    #[instrument(skip(self, sql))]
+    pub async fn execute_script(&self, sql: &str, in_transaction: bool) -> Result<()> {
+        let sql = sql.to_string();
+
+        run_interruptible(Arc::clone(&self.pool), move |conn| {
+            if in_transaction {
+                conn.execute_batch_in_transaction(&sql)
+            } else {
+                conn.execute_batch(&sql)
+            }
+        })
+        .await?;
+
+        debug!("Executed script successfully");
+        Ok(())
+    }
+
+    /// List every table with its columns, indexes and foreign keys
+    pub async fn schema(&self) -> Result<Vec<TableInfo>> {
+        run_interruptible(Arc::clone(&self.pool), |conn| conn.schema()).await
+    }
+
     /// Prepare a SQL statement for repeated execution
     #[instrument(skip(self, sql), fields(sql = %sql))]
     pub async fn prepare(&self, sql: &str) -> Result<AsyncPreparedStatement> {
@@ -88,26 +146,25 @@ impl AsyncConnection {
         .map_err(|e| Error::pool_error(format!("Task join error: {}", e)))?
     }
 
-    /// Begin a transaction
+    /// Begin a transaction on a connection held until it commits or rolls back
     #[instrument(skip(self))]
     pub async fn begin_transaction(&self) -> Result<AsyncTransaction> {
         let pool = Arc::clone(&self.pool);
 
-        let conn_guard = task::spawn_blocking(move || pool.get_connection())
-            .await
-            .map_err(|e| Error::pool_error(format!("Task join error: {}", e)))??;
-
-        // Begin transaction on the acquired connection
-        let tx = task::spawn_blocking(move || conn_guard.begin_transaction())
-            .await
-            .map_err(|e| Error::pool_error(format!("Task join error: {}", e)))??;
+        let connection = task::spawn_blocking(move || {
+            let conn = pool.get_connection()?;
+            conn.execute("BEGIN")?;
+            Ok::<_, Error>(conn)
+        })
+        .await
+        .map_err(|e| Error::pool_error(format!("Task join error: {}", e)))??;
 
         debug!("Started transaction");
-        Ok(AsyncTransaction::new(tx))
+        Ok(AsyncTransaction::new(connection))
     }
 
     /// Execute multiple statements in a transaction
-    #[instrument(skip(self, statements))]
+    #[instrument(skip(self, f))]
     pub async fn execute_batch<F, Fut>(&self, f: F) -> Result<()>
     where
         F: FnOnce(AsyncTransaction) -> Fut + Send + 'static,
@@ -142,6 +199,82 @@ impl AsyncConnection {
     }
 }
 
+/// Cancellation state shared between a future and its blocking task
+#[derive(Default)]
+struct Cancellation {
+    running: Option<InterruptHandle>,
+    cancelled: bool,
+}
+
+/// Interrupts the blocking task's statement when the owning future is dropped
+struct CancelOnDrop(Arc<Mutex<Cancellation>>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        // The interrupt is raised while holding the lock, so it can't race the
+        // blocking task releasing its connection
+        let mut state = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        state.cancelled = true;
+        if let Some(handle) = state.running.take() {
+            debug!("Future dropped, interrupting running statement");
+            handle.interrupt();
+        }
+    }
+}
+
+#[cfg(feature = "macros")]
+impl AsyncConnection {
+    /// Execute a checked query without reading rows
+    pub async fn execute_typed<R: Send + 'static>(&self, query: crate::TypedQuery<R>) -> Result<()> {
+        run_interruptible(Arc::clone(&self.pool), move |conn| query.execute(conn)).await
+    }
+
+    /// Read every row of a checked query
+    pub async fn fetch_all<R: Send + 'static>(&self, query: crate::TypedQuery<R>) -> Result<Vec<R>> {
+        run_interruptible(Arc::clone(&self.pool), move |conn| query.fetch_all(conn)).await
+    }
+
+    /// Read the first row of a checked query, if any
+    pub async fn fetch_optional<R: Send + 'static>(
+        &self,
+        query: crate::TypedQuery<R>,
+    ) -> Result<Option<R>> {
+        run_interruptible(Arc::clone(&self.pool), move |conn| query.fetch_optional(conn)).await
+    }
+}
+
+/// Run `f` on a pooled connection in a blocking task that is interrupted if
+/// the calling future is dropped before it finishes
+async fn run_interruptible<T, F>(pool: Arc<ConnectionPool>, f: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&Connection) -> Result<T> + Send + 'static,
+{
+    let state = Arc::new(Mutex::new(Cancellation::default()));
+    let _cancel_on_drop = CancelOnDrop(Arc::clone(&state));
+
+    task::spawn_blocking(move || {
+        let conn = pool.get_connection()?;
+        {
+            let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+            if state.cancelled {
+                return Err(Error::Interrupted);
+            }
+            state.running = Some(conn.interrupt_handle());
+        }
+
+        let result = f(&conn);
+
+        // Stop answering cancellation before the connection goes back to the
+        // pool, where its next statement belongs to another caller
+        state.lock().unwrap_or_else(PoisonError::into_inner).running = None;
+        drop(conn);
+        result
+    })
+    .await
+    .map_err(|e| Error::pool_error(format!("Task join error: {}", e)))?
+}
+
 /// An async wrapper around a prepared statement
 pub struct AsyncPreparedStatement {
     // Note: We can't directly wrap PreparedStatement because it's not Send
@@ -167,10 +300,8 @@ impl AsyncPreparedStatement {
     pub async fn execute_with_params(&self, params: &[SqlValue]) -> Result<()> {
         let sql = self.sql.clone();
         let params = params.to_vec();
-        let pool = Arc::clone(&self.pool);
 
-        task::spawn_blocking(move || {
-            let conn = pool.get_connection()?;
+        run_interruptible(Arc::clone(&self.pool), move |conn| {
             let mut stmt = conn.prepare(&sql)?;
 
             // Bind parameters
@@ -179,6 +310,7 @@ impl AsyncPreparedStatement {
                     SqlValue::Integer(value) => stmt.bind_int(index, *value)?,
                     SqlValue::Real(value) => stmt.bind_real(index, *value)?,
                     SqlValue::Text(value) => stmt.bind_text(index, value)?,
+                    SqlValue::Blob(value) => stmt.bind_blob(index, value)?,
                     SqlValue::Null => stmt.bind_null(index)?,
                 }
             }
@@ -186,7 +318,6 @@ impl AsyncPreparedStatement {
             stmt.execute()
         })
         .await
-        .map_err(|e| Error::pool_error(format!("Task join error: {}", e)))?
     }
 }
 
@@ -199,62 +330,57 @@ pub enum SqlValue {
     Real(f64),
     /// Text value
     Text(String),
+    /// Binary value
+    Blob(Vec<u8>),
     /// Null value
     Null,
 }
 
-/// An async transaction wrapper
+/// An async transaction holding its pooled connection
+///
+/// Dropping it without committing returns the connection to the pool, which
+/// rolls the transaction back.
 pub struct AsyncTransaction {
-    // Note: Similar to PreparedStatement, we can't directly wrap Transaction
-    // because it contains lifetime parameters and isn't Send
-    connection_pool: Option<Arc<ConnectionPool>>,
-    committed: bool,
+    connection: Arc<PooledConnectionGuard>,
 }
 
 impl AsyncTransaction {
-    fn new(tx: crate::Transaction) -> Self {
-        // Drop the original transaction and work directly with connection
-        drop(tx);
-
+    fn new(connection: PooledConnectionGuard) -> Self {
         Self {
-            connection_pool: None, // TODO: Store connection pool reference
-            committed: false,
+            connection: Arc::new(connection),
         }
     }
 
+    /// Run `f` on the transaction's connection in a blocking task
+    async fn run<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> Result<T> + Send + 'static,
+    {
+        let connection = Arc::clone(&self.connection);
+        task::spawn_blocking(move || f(&connection))
+            .await
+            .map_err(|e| Error::pool_error(format!("Task join error: {}", e)))?
+    }
+
     /// Execute a statement within the transaction
     #[instrument(skip(self, sql), fields(sql = %sql))]
     pub async fn execute(&self, sql: &str) -> Result<()> {
-        if self.committed {
-            return Err(Error::TransactionError);
-        }
-
-        // TODO: Execute on the transaction's connection
-        // For now, this is a simplified implementation
-        Ok(())
+        let sql = sql.to_string();
+        self.run(move |conn| conn.execute(&sql)).await
     }
 
     /// Query within the transaction
     #[instrument(skip(self, sql), fields(sql = %sql))]
     pub async fn query(&self, sql: &str) -> Result<Rows> {
-        if self.committed {
-            return Err(Error::TransactionError);
-        }
-
-        // TODO: Query on the transaction's connection
-        // For now, return an error
-        Err(Error::TransactionError)
+        let sql = sql.to_string();
+        self.run(move |conn| conn.query(&sql)).await
     }
 
     /// Commit the transaction
     #[instrument(skip(self))]
-    pub async fn commit(mut self) -> Result<()> {
-        if self.committed {
-            return Err(Error::TransactionError);
-        }
-
-        // TODO: Commit the actual transaction
-        self.committed = true;
+    pub async fn commit(self) -> Result<()> {
+        self.run(|conn| conn.execute("COMMIT")).await?;
         debug!("Transaction committed");
         Ok(())
     }
@@ -262,7 +388,7 @@ impl AsyncTransaction {
     /// Rollback the transaction
     #[instrument(skip(self))]
     pub async fn rollback(self) -> Result<()> {
-        // TODO: Rollback the actual transaction
+        self.run(|conn| conn.execute("ROLLBACK")).await?;
         debug!("Transaction rolled back");
         Ok(())
     }
@@ -399,4 +525,24 @@ mod tests {
         let stats = pool.stats();
         assert!(stats.connections_created >= 1);
     }
+
+    #[test]
+    fn test_cancel_on_drop_marks_cancelled() {
+        let state = Arc::new(Mutex::new(Cancellation::default()));
+        drop(CancelOnDrop(Arc::clone(&state)));
+        assert!(state.lock().unwrap().cancelled);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_future_leaves_connection_usable() {
+        let conn = AsyncConnection::open(":memory:").await.unwrap();
+
+        let _ = tokio::time::timeout(
+            std::time::Duration::ZERO,
+            conn.execute("CREATE TABLE test (id INTEGER)"),
+        )
+        .await;
+
+        conn.execute("CREATE TABLE other (id INTEGER)").await.unwrap();
+    }
 }
\ No newline at end of file
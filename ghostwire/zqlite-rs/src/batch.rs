@@ -0,0 +1,212 @@
+//! Multi-statement SQL scripts
+
+use crate::{Connection, Error, Result, Transaction};
+
+/// Split a script into statements on top-level semicolons
+///
+/// Semicolons inside string literals, quoted identifiers, comments and the
+/// `BEGIN ... END` body of `CREATE TRIGGER` do not end a statement. Returned
+/// statements are trimmed, keep their comments and have no trailing
+/// semicolon; empty and comment-only statements are dropped.
+pub fn split_statements(sql: &str) -> Vec<&str> {
+    let bytes = sql.as_bytes();
+    let mut statements = Vec::new();
+    let mut start = 0;
+    let mut has_content = false;
+    // Words of the current statement, enough to spot CREATE [TEMP] TRIGGER
+    let mut leading_words = Vec::new();
+    let mut in_trigger = false;
+    let mut depth = 0usize;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i += 2;
+                while i < bytes.len() && !(bytes[i] == b'*' && bytes.get(i + 1) == Some(&b'/')) {
+                    i += 1;
+                }
+                i += 2;
+                continue;
+            }
+            quote @ (b'\'' | b'"' | b'`' | b'[') => {
+                let close = if quote == b'[' { b']' } else { quote };
+                has_content = true;
+                i += 1;
+                while i < bytes.len() {
+                    if bytes[i] == close {
+                        // A doubled quote is an escaped quote, not the end
+                        if close != b']' && bytes.get(i + 1) == Some(&close) {
+                            i += 2;
+                            continue;
+                        }
+                        break;
+                    }
+                    i += 1;
+                }
+                i += 1;
+                continue;
+            }
+            b';' if depth == 0 => {
+                if has_content {
+                    statements.push(sql[start..i].trim());
+                }
+                start = i + 1;
+                has_content = false;
+                leading_words.clear();
+                in_trigger = false;
+            }
+            c if c.is_ascii_alphabetic() || c == b'_' => {
+                let word_start = i;
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                    i += 1;
+                }
+                let word = &sql[word_start..i];
+                has_content = true;
+
+                if leading_words.len() < 3 {
+                    leading_words.push(word.to_ascii_uppercase());
+                    let words: Vec<&str> = leading_words.iter().map(String::as_str).collect();
+                    in_trigger = matches!(
+                        words.as_slice(),
+                        ["CREATE", "TRIGGER", ..] | ["CREATE", "TEMP" | "TEMPORARY", "TRIGGER"]
+                    );
+                }
+                if in_trigger {
+                    if word.eq_ignore_ascii_case("BEGIN") || word.eq_ignore_ascii_case("CASE") {
+                        depth += 1;
+                    } else if word.eq_ignore_ascii_case("END") {
+                        depth = depth.saturating_sub(1);
+                    }
+                }
+                continue;
+            }
+            c if !c.is_ascii_whitespace() => has_content = true,
+            _ => {}
+        }
+        i += 1;
+    }
+    if has_content {
+        statements.push(sql[start..].trim());
+    }
+
+    statements
+}
+
+/// Run each statement, tagging a failure with its position in the script
+fn run_statements(sql: &str, mut execute: impl FnMut(&str) -> Result<()>) -> Result<()> {
+    for (index, statement) in split_statements(sql).into_iter().enumerate() {
+        execute(statement).map_err(|source| Error::Batch {
+            index,
+            sql: statement.to_string(),
+            source: Box::new(source),
+        })?;
+    }
+    Ok(())
+}
+
+impl Connection {
+    /// Execute a script of `;`-separated statements in order
+    ///
+    /// Stops at the first failing statement and returns [`Error::Batch`]
+    /// with its index; earlier statements stay applied. Use
+    /// [`Connection::execute_batch_in_transaction`] to apply all or nothing.
+    ///
+    /// ```rust,no_run
+    /// # use zqlite_rs::Connection;
+    /// # let conn = Connection::open(":memory:")?;
+    /// conn.execute_batch(
+    ///     "CREATE TABLE users (id INTEGER, name TEXT);
+    ///      INSERT INTO users VALUES (1, 'Alice; admin');",
+    /// )?;
+    /// # Ok::<(), zqlite_rs::Error>(())
+    /// ```
+    pub fn execute_batch(&self, sql: &str) -> Result<()> {
+        run_statements(sql, |statement| self.execute(statement))
+    }
+
+    /// Execute a script in a transaction, rolling back if any statement fails
+    pub fn execute_batch_in_transaction(&self, sql: &str) -> Result<()> {
+        let tx = self.begin_transaction()?;
+        tx.execute_batch(sql)?;
+        tx.commit()
+    }
+}
+
+impl Transaction<'_> {
+    /// Execute a script of `;`-separated statements within the transaction
+    pub fn execute_batch(&self, sql: &str) -> Result<()> {
+        run_statements(sql, |statement| self.execute(statement))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_statements() {
+        let script = "
+            -- users; with a semicolon in a comment
+            CREATE TABLE users (id INTEGER, name TEXT);
+            INSERT INTO users VALUES (1, 'O''Brien; Jr.');
+            /* ; */ ;
+            CREATE TRIGGER audit AFTER INSERT ON users BEGIN
+                INSERT INTO log VALUES (CASE WHEN 1 THEN 'a' END);
+                DELETE FROM log;
+            END;
+            SELECT \"odd;name\" FROM users
+        ";
+
+        let statements = split_statements(script);
+        assert_eq!(statements.len(), 4);
+        assert!(statements[0].starts_with("-- users;"));
+        assert!(statements[0].ends_with("name TEXT)"));
+        assert_eq!(statements[1], "INSERT INTO users VALUES (1, 'O''Brien; Jr.')");
+        assert!(statements[2].starts_with("CREATE TRIGGER") && statements[2].ends_with("END"));
+        assert_eq!(statements[3], "SELECT \"odd;name\" FROM users");
+    }
+
+    #[test]
+    fn test_execute_batch_reports_failing_statement() {
+        let conn = Connection::open(":memory:").unwrap();
+        let err = conn
+            .execute_batch("CREATE TABLE a (id INTEGER); INSERT INTO missing VALUES (1);")
+            .unwrap_err();
+        assert!(matches!(err, Error::Batch { index: 1, .. }));
+
+        // The first statement ran before the failure
+        conn.execute("INSERT INTO a VALUES (1)").unwrap();
+    }
+
+    #[test]
+    fn test_execute_batch_in_transaction_rolls_back() {
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        let conn = Connection::open(&temp_file.path().to_string_lossy()).unwrap();
+        conn.execute("CREATE TABLE a (id INTEGER)").unwrap();
+        conn.execute("INSERT INTO a VALUES (1)").unwrap();
+
+        let err = conn
+            .execute_batch_in_transaction(
+                "INSERT INTO a VALUES (2); DELETE FROM a WHERE id = 1; INSERT INTO missing VALUES (3)",
+            )
+            .unwrap_err();
+        assert!(matches!(err, Error::Batch { index: 2, .. }));
+        assert!(conn.is_autocommit());
+
+        // The insert is gone and the deleted row is back
+        let ids: Vec<i64> = conn
+            .query("SELECT id FROM a")
+            .unwrap()
+            .iter()
+            .map(|row| row.get(0).unwrap())
+            .collect();
+        assert_eq!(ids, vec![1]);
+    }
+}
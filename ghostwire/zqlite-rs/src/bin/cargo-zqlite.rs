@@ -0,0 +1,143 @@
+//! `cargo zqlite prepare`: write the schema snapshot the query macros check against
+//!
+//! ```text
+//! cargo zqlite prepare [--out zqlite-schema.json] --database app.db
+//! cargo zqlite prepare [--out zqlite-schema.json] schema.sql [more.sql ...]
+//! ```
+//!
+//! The snapshot is [`Connection::schema`] of the given database, or of an
+//! in-memory database the scripts were run against. Check it in so builds
+//! never need a database.
+
+use serde::Serialize;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use zqlite_rs::{Connection, TableInfo};
+
+const USAGE: &str =
+    "usage: cargo zqlite prepare [--out zqlite-schema.json] (--database <path> | <schema.sql>...)";
+
+#[derive(Debug, Serialize)]
+struct Snapshot {
+    tables: Vec<TableInfo>,
+}
+
+enum Source {
+    Database(String),
+    Scripts(Vec<PathBuf>),
+}
+
+fn main() -> ExitCode {
+    // Invoked as `cargo-zqlite zqlite prepare ...` through cargo, or directly
+    let mut args = std::env::args().skip(1).peekable();
+    if args.peek().map(String::as_str) == Some("zqlite") {
+        args.next();
+    }
+    if args.next().as_deref() != Some("prepare") {
+        return usage();
+    }
+
+    let mut out = PathBuf::from("zqlite-schema.json");
+    let mut database = None;
+    let mut scripts = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--out" | "-o" => match args.next() {
+                Some(path) => out = PathBuf::from(path),
+                None => return usage(),
+            },
+            "--database" | "-d" => match args.next() {
+                Some(path) => database = Some(path),
+                None => return usage(),
+            },
+            _ => scripts.push(PathBuf::from(arg)),
+        }
+    }
+
+    let source = match (database, scripts.is_empty()) {
+        (Some(path), true) => Source::Database(path),
+        (None, false) => Source::Scripts(scripts),
+        _ => return usage(),
+    };
+
+    let tables = match load(&source) {
+        Ok(tables) => tables,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let json = serde_json::to_string_pretty(&Snapshot { tables }).expect("snapshot serializes");
+    if let Err(e) = std::fs::write(&out, json + "\n") {
+        eprintln!("cannot write {}: {}", out.display(), e);
+        return ExitCode::FAILURE;
+    }
+
+    println!("wrote {}", out.display());
+    ExitCode::SUCCESS
+}
+
+fn usage() -> ExitCode {
+    eprintln!("{}", USAGE);
+    ExitCode::FAILURE
+}
+
+/// Open the database, or build one from the scripts, and read its schema
+fn load(source: &Source) -> Result<Vec<TableInfo>, String> {
+    let conn = match source {
+        Source::Database(path) => {
+            Connection::open(path).map_err(|e| format!("cannot open {}: {}", path, e))?
+        }
+        Source::Scripts(scripts) => {
+            let conn = Connection::open(":memory:").map_err(|e| e.to_string())?;
+            for script in scripts {
+                let sql = std::fs::read_to_string(script)
+                    .map_err(|e| format!("cannot read {}: {}", script.display(), e))?;
+                conn.execute_batch(&sql)
+                    .map_err(|e| format!("cannot run {}: {}", script.display(), e))?;
+            }
+            conn
+        }
+    };
+
+    conn.schema().map_err(|e| format!("cannot read schema: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_scripts() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("peers.sql");
+        std::fs::write(&script, "CREATE TABLE peers (id TEXT PRIMARY KEY, public_key BLOB NOT NULL)").unwrap();
+
+        let tables = load(&Source::Scripts(vec![script])).unwrap();
+        assert_eq!(tables.len(), 1);
+        let summary: Vec<_> = tables[0]
+            .columns
+            .iter()
+            .map(|c| (c.name.as_str(), c.decl_type.as_str(), c.not_null, c.pk))
+            .collect();
+        assert_eq!(summary, [("id", "TEXT", false, true), ("public_key", "BLOB", true, false)]);
+    }
+
+    #[test]
+    fn test_load_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("coordination.db").to_string_lossy().into_owned();
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute("CREATE TABLE peers (id TEXT PRIMARY KEY, name VARCHAR(64) NOT NULL)").unwrap();
+            conn.execute("CREATE INDEX idx_peers_name ON peers (name)").unwrap();
+        }
+
+        let tables = load(&Source::Database(path)).unwrap();
+        assert_eq!(tables.len(), 1);
+        assert_eq!(tables[0].name, "peers");
+        assert_eq!(tables[0].columns[1].decl_type, "VARCHAR(64)");
+        assert_eq!(tables[0].indexes[0].name, "idx_peers_name");
+    }
+}
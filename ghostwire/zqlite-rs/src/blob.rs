@@ -0,0 +1,303 @@
+//! Incremental blob I/O
+
+use crate::ffi::{
+    zqlite_blob_bytes, zqlite_blob_close, zqlite_blob_open, zqlite_blob_read, zqlite_blob_t,
+    zqlite_blob_write, ZQLITE_OK,
+};
+use crate::{Connection, Error, Result};
+use std::ffi::CString;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+use std::os::raw::{c_int, c_void};
+use std::ptr;
+
+/// Handle for reading and writing one blob value in place
+///
+/// Blobs have a fixed size: writes past the end fail, so reserve space with
+/// [`PreparedStatement::bind_zeroblob`](crate::PreparedStatement::bind_zeroblob)
+/// first. Reads and writes go straight to the stored row.
+pub struct Blob<'conn> {
+    inner: *mut zqlite_blob_t,
+    len: usize,
+    pos: usize,
+    _connection: PhantomData<&'conn Connection>,
+}
+
+// Safety: a blob handle is only used through &mut self and the connection it
+// borrows is Sync
+unsafe impl Send for Blob<'_> {}
+
+impl Connection {
+    /// Open the blob stored in `table.column` of row `rowid`
+    pub fn blob_open(&self, table: &str, column: &str, rowid: i64, read_only: bool) -> Result<Blob<'_>> {
+        let table_cstr = CString::new(table).map_err(|_| Error::InvalidSql)?;
+        let column_cstr = CString::new(column).map_err(|_| Error::InvalidSql)?;
+        let mut blob = ptr::null_mut();
+
+        let result = unsafe {
+            zqlite_blob_open(
+                self.inner,
+                ptr::null(),
+                table_cstr.as_ptr(),
+                column_cstr.as_ptr(),
+                rowid,
+                if read_only { 0 } else { 1 },
+                &mut blob,
+            )
+        };
+
+        if result != ZQLITE_OK as c_int || blob.is_null() {
+            return Err(Error::database(format!(
+                "cannot open blob {}.{} for row {}",
+                table, column, rowid
+            )));
+        }
+
+        let len = unsafe { zqlite_blob_bytes(blob) }.max(0) as usize;
+        Ok(Blob {
+            inner: blob,
+            len,
+            pos: 0,
+            _connection: PhantomData,
+        })
+    }
+}
+
+impl Blob<'_> {
+    /// Size of the blob in bytes
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Check if the blob is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Close the blob, reporting any error from releasing the handle
+    pub fn close(mut self) -> Result<()> {
+        self.close_inner()
+    }
+
+    fn close_inner(&mut self) -> Result<()> {
+        if self.inner.is_null() {
+            return Ok(());
+        }
+
+        let result = unsafe { zqlite_blob_close(self.inner) };
+        self.inner = ptr::null_mut();
+
+        if result != ZQLITE_OK as c_int {
+            return Err(Error::from(result));
+        }
+
+        Ok(())
+    }
+
+    /// Number of bytes that fit between the cursor and the end of the blob
+    fn remaining(&self, requested: usize) -> usize {
+        requested.min(self.len.saturating_sub(self.pos))
+    }
+}
+
+impl Read for Blob<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.remaining(buf.len());
+        if n == 0 {
+            return Ok(0);
+        }
+
+        let result = unsafe {
+            zqlite_blob_read(self.inner, buf.as_mut_ptr() as *mut c_void, n as c_int, self.pos as c_int)
+        };
+        if result != ZQLITE_OK as c_int {
+            return Err(io::Error::other(Error::from(result)));
+        }
+
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+impl Write for Blob<'_> {
+    /// Writes stop at the end of the blob; `write_all` then fails with `WriteZero`
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.remaining(buf.len());
+        if n == 0 {
+            return Ok(0);
+        }
+
+        let result = unsafe {
+            zqlite_blob_write(self.inner, buf.as_ptr() as *const c_void, n as c_int, self.pos as c_int)
+        };
+        if result != ZQLITE_OK as c_int {
+            return Err(io::Error::other(Error::from(result)));
+        }
+
+        self.pos += n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for Blob<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => i128::from(offset),
+            SeekFrom::End(offset) => self.len as i128 + i128::from(offset),
+            SeekFrom::Current(offset) => self.pos as i128 + i128::from(offset),
+        };
+
+        if target < 0 || target > self.len as i128 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek outside the blob",
+            ));
+        }
+
+        self.pos = target as usize;
+        Ok(self.pos as u64)
+    }
+}
+
+impl Drop for Blob<'_> {
+    fn drop(&mut self) {
+        if let Err(e) = self.close_inner() {
+            tracing::error!("Failed to close blob: {}", e);
+        }
+    }
+}
+
+// Blob I/O never blocks on the network or disk, so the async traits complete
+// immediately using the synchronous implementations
+#[cfg(feature = "async")]
+mod async_io {
+    use super::Blob;
+    use std::io::{self, Read, Write};
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+    use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+    impl AsyncRead for Blob<'_> {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            let n = self.get_mut().read(buf.initialize_unfilled())?;
+            buf.advance(n);
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    impl AsyncWrite for Blob<'_> {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            Poll::Ready(self.get_mut().write(buf))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A connection holding one artifact, and the artifact's row id
+    fn connection_with_artifact(contents: &str) -> (Connection, i64) {
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE artifacts (id INTEGER, data BLOB)").unwrap();
+        conn.execute(&format!("INSERT INTO artifacts VALUES (1, '{}')", contents))
+            .unwrap();
+        let rowid = conn.last_insert_rowid();
+        (conn, rowid)
+    }
+
+    #[test]
+    fn test_blob_read_and_seek() {
+        let (conn, rowid) = connection_with_artifact("hello world");
+        let mut blob = conn.blob_open("artifacts", "data", rowid, true).unwrap();
+        assert_eq!(blob.len(), 11);
+
+        blob.seek(SeekFrom::Start(6)).unwrap();
+        let mut tail = String::new();
+        blob.read_to_string(&mut tail).unwrap();
+        assert_eq!(tail, "world");
+
+        assert!(blob.seek(SeekFrom::Current(1)).is_err());
+    }
+
+    #[test]
+    fn test_blob_write_is_stored_in_place() {
+        let (conn, rowid) = connection_with_artifact("hello world");
+
+        let mut blob = conn.blob_open("artifacts", "data", rowid, false).unwrap();
+        blob.write_all(b"HELLO").unwrap();
+        blob.seek(SeekFrom::End(0)).unwrap();
+        assert!(blob.write_all(b"!").is_err());
+
+        // The row sees the write while the handle is still open
+        let rows = conn.query("SELECT data FROM artifacts").unwrap();
+        let row = rows.get(0).unwrap();
+        assert_eq!(row.get::<String>(0).unwrap(), "HELLO world");
+        blob.close().unwrap();
+
+        let mut blob = conn.blob_open("artifacts", "data", rowid, true).unwrap();
+        let mut contents = Vec::new();
+        blob.read_to_end(&mut contents).unwrap();
+        assert_eq!(contents, b"HELLO world");
+    }
+
+    #[test]
+    fn test_blob_write_is_undone_on_rollback() {
+        let (conn, rowid) = connection_with_artifact("hello");
+
+        // Overlapping writes are undone newest first
+        let tx = conn.begin_transaction().unwrap();
+        let mut blob = conn.blob_open("artifacts", "data", rowid, false).unwrap();
+        blob.write_all(b"jelly").unwrap();
+        blob.seek(SeekFrom::Start(3)).unwrap();
+        blob.write_all(b"ow").unwrap();
+        blob.close().unwrap();
+        tx.rollback().unwrap();
+
+        let rows = conn.query("SELECT data FROM artifacts").unwrap();
+        let row = rows.get(0).unwrap();
+        assert_eq!(row.get::<String>(0).unwrap(), "hello");
+    }
+
+    #[test]
+    fn test_blob_open_missing_column() {
+        let (conn, rowid) = connection_with_artifact("x");
+        assert!(conn.blob_open("artifacts", "missing", rowid, true).is_err());
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_blob_async_io() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (conn, rowid) = connection_with_artifact("abcdef");
+        let mut blob = conn.blob_open("artifacts", "data", rowid, false).unwrap();
+        // Qualified calls: the std Read/Write traits are in scope too
+        AsyncWriteExt::write_all(&mut blob, b"xyz").await.unwrap();
+        blob.seek(SeekFrom::Start(0)).unwrap();
+
+        let mut contents = Vec::new();
+        AsyncReadExt::read_to_end(&mut blob, &mut contents).await.unwrap();
+        assert_eq!(contents, b"xyzdef");
+    }
+}
@@ -1,12 +1,12 @@
 //! Error types for ZQLite Rust bindings
 
-use std::fmt;
+use crate::Version;
 
 /// Result type alias for ZQLite operations
 pub type Result<T> = std::result::Result<T, Error>;
 
 /// ZQLite error types
-#[derive(Debug, Clone, thiserror::Error)]
+#[derive(Debug, thiserror::Error)]
 pub enum Error {
     /// Database operation failed
     #[error("Database error: {0}")]
@@ -24,6 +24,10 @@ pub enum Error {
     #[error("Invalid SQL statement")]
     InvalidSql,
 
+    /// Table or column name that cannot be used in generated SQL
+    #[error("Invalid identifier: {0:?}")]
+    InvalidIdentifier(String),
+
     /// Parameter binding failed
     #[error("Failed to bind parameter")]
     BindError,
@@ -45,12 +49,16 @@ pub enum Error {
     RowError(String),
 
     /// Type conversion error
-    #[error("Type conversion error: expected {expected}, got {actual}")]
+    #[error("Type conversion error: expected {expected}, got {actual}{}", describe_column(.column, .column_name))]
     TypeMismatch {
         /// Expected type
         expected: String,
         /// Actual type
         actual: String,
+        /// Index of the column being read, when known
+        column: Option<usize>,
+        /// Name of the column being read, when known
+        column_name: Option<String>,
     },
 
     /// Connection pool error
@@ -75,6 +83,39 @@ pub enum Error {
         /// The invalid index
         index: usize,
     },
+
+    /// A user-defined function reported an error
+    #[error("User function error: {0}")]
+    UserFunction(String),
+
+    /// A statement of a script passed to `execute_batch` failed
+    #[error("Statement {index} of batch failed: {source}")]
+    Batch {
+        /// Zero-based position of the statement in the script
+        index: usize,
+        /// The failing statement
+        sql: String,
+        /// Why it failed
+        #[source]
+        source: Box<Error>,
+    },
+
+    /// The statement was interrupted or timed out
+    #[error("Statement interrupted")]
+    Interrupted,
+
+    /// A UNIQUE or NOT NULL constraint rejected the statement
+    #[error("Constraint violated: {0}")]
+    ConstraintViolation(String),
+
+    /// The linked ZQLite library is outside [`SUPPORTED_VERSIONS`](crate::SUPPORTED_VERSIONS)
+    #[error("Incompatible ZQLite library {found}: bindings need {expected} or a later release with the same major version")]
+    IncompatibleLibrary {
+        /// Version of the linked library
+        found: Version,
+        /// Version of the header the bindings were built against
+        expected: Version,
+    },
 }
 
 impl Error {
@@ -93,6 +134,33 @@ impl Error {
         Error::TypeMismatch {
             expected: expected.into(),
             actual: actual.into(),
+            column: None,
+            column_name: None,
+        }
+    }
+
+    /// Whether a constraint rejected the statement, or the failing statement
+    /// of a batch
+    pub fn is_constraint_violation(&self) -> bool {
+        match self {
+            Error::ConstraintViolation(_) => true,
+            Error::Batch { source, .. } => source.is_constraint_violation(),
+            _ => false,
+        }
+    }
+
+    /// Record which column a type mismatch happened in
+    pub fn in_column(self, index: usize, name: Option<String>) -> Self {
+        match self {
+            Error::TypeMismatch {
+                expected, actual, ..
+            } => Error::TypeMismatch {
+                expected,
+                actual,
+                column: Some(index),
+                column_name: name,
+            },
+            other => other,
         }
     }
 
@@ -101,6 +169,11 @@ impl Error {
         Error::PoolError(message.into())
     }
 
+    /// Create a user-defined function error
+    pub fn user_function<S: Into<String>>(message: S) -> Self {
+        Error::UserFunction(message.into())
+    }
+
     /// Create an index out of bounds error
     pub fn index_out_of_bounds(index: usize) -> Self {
         Error::IndexOutOfBounds { index }
@@ -113,6 +186,7 @@ impl Error {
             Error::ConnectionFailed => false,
             Error::InvalidPath => false,
             Error::InvalidSql => true,
+            Error::InvalidIdentifier(_) => true,
             Error::BindError => true,
             Error::ExecutionError => true,
             Error::ResetError => true,
@@ -124,23 +198,37 @@ impl Error {
             Error::Io(_) => false,
             Error::NullPointer => false,
             Error::IndexOutOfBounds { .. } => true,
+            Error::UserFunction(_) => true,
+            Error::Batch { source, .. } => source.is_recoverable(),
+            Error::Interrupted => true,
+            Error::ConstraintViolation(_) => true,
+            Error::IncompatibleLibrary { .. } => false,
         }
     }
 }
 
+fn describe_column(column: &Option<usize>, name: &Option<String>) -> String {
+    match (column, name) {
+        (Some(index), Some(name)) => format!(" in column {} ({})", index, name),
+        (Some(index), None) => format!(" in column {}", index),
+        (None, Some(name)) => format!(" in column {}", name),
+        (None, None) => String::new(),
+    }
+}
+
 /// Convert ZQLite error codes to Rust errors
 impl From<i32> for Error {
     fn from(code: i32) -> Self {
-        use crate::{
+        use crate::ffi::{
             ZQLITE_AUTH, ZQLITE_BUSY, ZQLITE_CANTOPEN, ZQLITE_CONSTRAINT, ZQLITE_CORRUPT,
-            ZQLITE_ERROR, ZQLITE_FULL, ZQLITE_INTERNAL, ZQLITE_IOERR, ZQLITE_LOCKED,
+            ZQLITE_ERROR, ZQLITE_FULL, ZQLITE_INTERNAL, ZQLITE_INTERRUPT, ZQLITE_IOERR, ZQLITE_LOCKED,
             ZQLITE_MISMATCH, ZQLITE_MISUSE, ZQLITE_NOLFS, ZQLITE_NOMEM, ZQLITE_NOTADB,
             ZQLITE_NOTFOUND, ZQLITE_OK, ZQLITE_PERM, ZQLITE_PROTOCOL, ZQLITE_RANGE,
             ZQLITE_READONLY, ZQLITE_SCHEMA, ZQLITE_TOOBIG,
         };
 
         match code {
-            x if x == ZQLITE_OK as i32 => return Error::Unknown, // Shouldn't happen
+            x if x == ZQLITE_OK as i32 => Error::Unknown, // Shouldn't happen
             x if x == ZQLITE_ERROR as i32 => Error::Database("Generic error".to_string()),
             x if x == ZQLITE_INTERNAL as i32 => Error::Database("Internal logic error".to_string()),
             x if x == ZQLITE_PERM as i32 => Error::Database("Access permission denied".to_string()),
@@ -150,6 +238,7 @@ impl From<i32> for Error {
             x if x == ZQLITE_READONLY as i32 => {
                 Error::Database("Attempt to write readonly database".to_string())
             }
+            x if x == ZQLITE_INTERRUPT as i32 => Error::Interrupted,
             x if x == ZQLITE_IOERR as i32 => Error::Database("Disk I/O error".to_string()),
             x if x == ZQLITE_CORRUPT as i32 => {
                 Error::Database("Database image is malformed".to_string())
@@ -171,7 +260,7 @@ impl From<i32> for Error {
                 Error::Database("String or BLOB exceeds size limit".to_string())
             }
             x if x == ZQLITE_CONSTRAINT as i32 => {
-                Error::Database("Constraint violation".to_string())
+                Error::ConstraintViolation("Constraint violation".to_string())
             }
             x if x == ZQLITE_MISMATCH as i32 => Error::Database("Data type mismatch".to_string()),
             x if x == ZQLITE_MISUSE as i32 => {
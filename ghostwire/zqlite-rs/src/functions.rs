@@ -0,0 +1,510 @@
+//! User-defined SQL functions implemented in Rust
+
+use crate::value::{ToSql, ValueRef};
+use crate::ffi::{
+    zqlite_aggregate_context, zqlite_context_result_blob, zqlite_context_result_error,
+    zqlite_context_result_int, zqlite_context_result_null, zqlite_context_result_real,
+    zqlite_context_result_text, zqlite_context_t, zqlite_create_function, zqlite_user_data,
+    zqlite_value_blob, zqlite_value_int, zqlite_value_real, zqlite_value_t, zqlite_value_text,
+    zqlite_value_type, ZQLITE_BLOB, ZQLITE_DETERMINISTIC, ZQLITE_DIRECTONLY, ZQLITE_FLOAT,
+    ZQLITE_INNOCUOUS, ZQLITE_INTEGER, ZQLITE_OK, ZQLITE_TEXT, ZQLITE_UTF8,
+};
+use crate::{Connection, Error, FromSql, Result};
+use std::ffi::{c_void, CString};
+use std::os::raw::{c_char, c_int};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+/// Flags describing how a user-defined function behaves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FunctionFlags(c_int);
+
+impl FunctionFlags {
+    /// Text arguments and results are UTF-8
+    pub const UTF8: Self = Self(ZQLITE_UTF8 as c_int);
+    /// The function always returns the same result for the same arguments
+    pub const DETERMINISTIC: Self = Self(ZQLITE_DETERMINISTIC as c_int);
+    /// The function may only be called from top-level SQL
+    pub const DIRECT_ONLY: Self = Self(ZQLITE_DIRECTONLY as c_int);
+    /// The function has no side effects and is safe to use anywhere
+    pub const INNOCUOUS: Self = Self(ZQLITE_INNOCUOUS as c_int);
+
+    /// Raw flag bits passed to ZQLite
+    pub fn bits(self) -> c_int {
+        self.0
+    }
+}
+
+impl Default for FunctionFlags {
+    fn default() -> Self {
+        Self::UTF8
+    }
+}
+
+impl std::ops::BitOr for FunctionFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// Arguments of a user-defined function invocation
+pub struct Context<'a> {
+    args: &'a [*mut zqlite_value_t],
+}
+
+impl<'a> Context<'a> {
+    /// Number of arguments passed to the function
+    pub fn len(&self) -> usize {
+        self.args.len()
+    }
+
+    /// Check if the function was called without arguments
+    pub fn is_empty(&self) -> bool {
+        self.args.is_empty()
+    }
+
+    /// Borrow the argument at `index`
+    pub fn get_ref(&self, index: usize) -> Result<ValueRef<'a>> {
+        let value = *self
+            .args
+            .get(index)
+            .ok_or_else(|| Error::index_out_of_bounds(index))?;
+
+        unsafe {
+            let value_type = zqlite_value_type(value);
+            let value_ref = match value_type {
+                x if x == ZQLITE_INTEGER as c_int => ValueRef::Integer(zqlite_value_int(value)),
+                x if x == ZQLITE_FLOAT as c_int => ValueRef::Real(zqlite_value_real(value)),
+                x if x == ZQLITE_TEXT as c_int => {
+                    let mut size: c_int = 0;
+                    let text = zqlite_value_text(value, &mut size);
+                    ValueRef::Text(raw_bytes(text as *const u8, size))
+                }
+                x if x == ZQLITE_BLOB as c_int => {
+                    let mut size: c_int = 0;
+                    let blob = zqlite_value_blob(value, &mut size);
+                    ValueRef::Blob(raw_bytes(blob as *const u8, size))
+                }
+                _ => ValueRef::Null,
+            };
+            Ok(value_ref)
+        }
+    }
+
+    /// Convert the argument at `index` with [`FromSql`]
+    pub fn get<T: FromSql>(&self, index: usize) -> Result<T> {
+        T::from_sql(self.get_ref(index)?)
+    }
+}
+
+/// Build a slice from a pointer/length pair handed out by ZQLite
+unsafe fn raw_bytes<'a>(data: *const u8, size: c_int) -> &'a [u8] {
+    if data.is_null() || size <= 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(data, size as usize)
+    }
+}
+
+/// An aggregate function implemented in Rust
+///
+/// `A` is the per-group accumulator and `T` the final result type.
+pub trait Aggregate<A, T>: Send + Sync + 'static
+where
+    T: ToSql,
+{
+    /// Create the accumulator for a new group
+    fn init(&self) -> A;
+
+    /// Fold one row into the accumulator
+    fn step(&self, ctx: &Context<'_>, acc: &mut A) -> Result<()>;
+
+    /// Produce the result; `acc` is `None` when the group had no rows
+    fn finalize(&self, acc: Option<A>) -> Result<T>;
+}
+
+impl Connection {
+    /// Register a Rust closure as a scalar SQL function
+    ///
+    /// `n_args` is the number of arguments the function accepts, or -1 for
+    /// any. Errors returned by the closure, and panics inside it, surface as
+    /// SQL errors from the statement that called the function.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use zqlite_rs::{Connection, FunctionFlags};
+    /// # let conn = Connection::open(":memory:")?;
+    /// conn.create_scalar_function("double_it", 1, FunctionFlags::DETERMINISTIC, |ctx| {
+    ///     Ok(ctx.get_ref(0)?.as_i64()? * 2)
+    /// })?;
+    /// # Ok::<(), zqlite_rs::Error>(())
+    /// ```
+    pub fn create_scalar_function<F, T>(
+        &self,
+        name: &str,
+        n_args: i32,
+        flags: FunctionFlags,
+        function: F,
+    ) -> Result<()>
+    where
+        F: Fn(&Context<'_>) -> Result<T> + Send + Sync + 'static,
+        T: ToSql,
+    {
+        let boxed: *mut F = Box::into_raw(Box::new(function));
+
+        self.register_function(
+            name,
+            n_args,
+            flags,
+            boxed as *mut c_void,
+            Some(call_scalar::<F, T>),
+            None,
+            None,
+            Some(drop_boxed::<F>),
+        )
+    }
+
+    /// Register a Rust [`Aggregate`] as an aggregate SQL function
+    pub fn create_aggregate_function<A, D, T>(
+        &self,
+        name: &str,
+        n_args: i32,
+        flags: FunctionFlags,
+        aggregate: D,
+    ) -> Result<()>
+    where
+        A: 'static,
+        D: Aggregate<A, T>,
+        T: ToSql,
+    {
+        let boxed: *mut D = Box::into_raw(Box::new(aggregate));
+
+        self.register_function(
+            name,
+            n_args,
+            flags,
+            boxed as *mut c_void,
+            None,
+            Some(call_aggregate_step::<A, D, T>),
+            Some(call_aggregate_final::<A, D, T>),
+            Some(drop_boxed::<D>),
+        )
+    }
+
+    /// Remove a previously registered user-defined function
+    pub fn remove_function(&self, name: &str, n_args: i32) -> Result<()> {
+        self.register_function(
+            name,
+            n_args,
+            FunctionFlags::default(),
+            ptr::null_mut(),
+            None,
+            None,
+            None,
+            None,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn register_function(
+        &self,
+        name: &str,
+        n_args: i32,
+        flags: FunctionFlags,
+        user_data: *mut c_void,
+        x_func: Option<unsafe extern "C" fn(*mut zqlite_context_t, c_int, *mut *mut zqlite_value_t)>,
+        x_step: Option<unsafe extern "C" fn(*mut zqlite_context_t, c_int, *mut *mut zqlite_value_t)>,
+        x_final: Option<unsafe extern "C" fn(*mut zqlite_context_t)>,
+        x_destroy: Option<unsafe extern "C" fn(*mut c_void)>,
+    ) -> Result<()> {
+        let name_cstr = match CString::new(name) {
+            Ok(name_cstr) => name_cstr,
+            Err(_) => {
+                // ZQLite never saw the user data, so release it here
+                if let (Some(destroy), false) = (x_destroy, user_data.is_null()) {
+                    unsafe { destroy(user_data) };
+                }
+                return Err(Error::InvalidSql);
+            }
+        };
+
+        // On failure ZQLite calls x_destroy itself, like sqlite3_create_function_v2
+        let result = unsafe {
+            zqlite_create_function(
+                self.inner,
+                name_cstr.as_ptr(),
+                n_args as c_int,
+                flags.bits(),
+                user_data,
+                x_func,
+                x_step,
+                x_final,
+                x_destroy,
+            )
+        };
+
+        if result != ZQLITE_OK as c_int {
+            return Err(self.get_last_error());
+        }
+
+        Ok(())
+    }
+}
+
+unsafe extern "C" fn drop_boxed<T>(user_data: *mut c_void) {
+    drop(Box::from_raw(user_data as *mut T));
+}
+
+/// Hand a Rust result back to ZQLite as the function's return value
+unsafe fn set_result<T: ToSql>(ctx: *mut zqlite_context_t, result: Result<T>) {
+    let value = match result {
+        Ok(value) => value,
+        Err(e) => return set_error(ctx, &e.to_string()),
+    };
+
+    let output = match value.to_sql() {
+        Ok(output) => output,
+        Err(e) => return set_error(ctx, &e.to_string()),
+    };
+
+    match output.value_ref() {
+        ValueRef::Null => zqlite_context_result_null(ctx),
+        ValueRef::Integer(i) => zqlite_context_result_int(ctx, i),
+        ValueRef::Real(f) => zqlite_context_result_real(ctx, f),
+        ValueRef::Text(text) => {
+            zqlite_context_result_text(ctx, text.as_ptr() as *const c_char, text.len() as c_int)
+        }
+        ValueRef::Blob(blob) => {
+            zqlite_context_result_blob(ctx, blob.as_ptr() as *const c_void, blob.len() as c_int)
+        }
+    }
+}
+
+unsafe fn set_error(ctx: *mut zqlite_context_t, message: &str) {
+    zqlite_context_result_error(ctx, message.as_ptr() as *const c_char, message.len() as c_int);
+}
+
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    let detail = payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string());
+    format!("user function panicked: {}", detail)
+}
+
+unsafe fn args_slice<'a>(argc: c_int, argv: *mut *mut zqlite_value_t) -> &'a [*mut zqlite_value_t] {
+    if argv.is_null() || argc <= 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(argv, argc as usize)
+    }
+}
+
+unsafe extern "C" fn call_scalar<F, T>(
+    ctx: *mut zqlite_context_t,
+    argc: c_int,
+    argv: *mut *mut zqlite_value_t,
+) where
+    F: Fn(&Context<'_>) -> Result<T> + Send + Sync + 'static,
+    T: ToSql,
+{
+    // Panics must never unwind into ZQLite
+    let outcome = catch_unwind(AssertUnwindSafe(|| {
+        let function = &*(zqlite_user_data(ctx) as *const F);
+        let context = Context {
+            args: args_slice(argc, argv),
+        };
+        let result = function(&context);
+        set_result(ctx, result);
+    }));
+
+    if let Err(payload) = outcome {
+        set_error(ctx, &panic_message(payload));
+    }
+}
+
+/// Locate the accumulator slot ZQLite keeps for the current group
+unsafe fn aggregate_slot<A>(ctx: *mut zqlite_context_t) -> *mut *mut A {
+    zqlite_aggregate_context(ctx, std::mem::size_of::<*mut A>() as c_int) as *mut *mut A
+}
+
+unsafe extern "C" fn call_aggregate_step<A, D, T>(
+    ctx: *mut zqlite_context_t,
+    argc: c_int,
+    argv: *mut *mut zqlite_value_t,
+) where
+    A: 'static,
+    D: Aggregate<A, T>,
+    T: ToSql,
+{
+    let outcome = catch_unwind(AssertUnwindSafe(|| {
+        let aggregate = &*(zqlite_user_data(ctx) as *const D);
+        let slot = aggregate_slot::<A>(ctx);
+        if slot.is_null() {
+            return set_error(ctx, "out of memory allocating aggregate state");
+        }
+        if (*slot).is_null() {
+            *slot = Box::into_raw(Box::new(aggregate.init()));
+        }
+
+        let context = Context {
+            args: args_slice(argc, argv),
+        };
+        if let Err(e) = aggregate.step(&context, &mut **slot) {
+            set_error(ctx, &e.to_string());
+        }
+    }));
+
+    if let Err(payload) = outcome {
+        set_error(ctx, &panic_message(payload));
+    }
+}
+
+unsafe extern "C" fn call_aggregate_final<A, D, T>(ctx: *mut zqlite_context_t)
+where
+    A: 'static,
+    D: Aggregate<A, T>,
+    T: ToSql,
+{
+    let outcome = catch_unwind(AssertUnwindSafe(|| {
+        let aggregate = &*(zqlite_user_data(ctx) as *const D);
+
+        // A zero-sized request only returns existing state, so empty groups see None
+        let slot = zqlite_aggregate_context(ctx, 0) as *mut *mut A;
+        let acc = if slot.is_null() || (*slot).is_null() {
+            None
+        } else {
+            let acc = Box::from_raw(*slot);
+            *slot = ptr::null_mut();
+            Some(*acc)
+        };
+
+        set_result(ctx, aggregate.finalize(acc));
+    }));
+
+    if let Err(payload) = outcome {
+        set_error(ctx, &panic_message(payload));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Sum;
+
+    impl Aggregate<i64, i64> for Sum {
+        fn init(&self) -> i64 {
+            0
+        }
+
+        fn step(&self, ctx: &Context<'_>, acc: &mut i64) -> Result<()> {
+            *acc += ctx.get_ref(0)?.as_i64()?;
+            Ok(())
+        }
+
+        fn finalize(&self, acc: Option<i64>) -> Result<i64> {
+            Ok(acc.unwrap_or(0))
+        }
+    }
+
+    #[test]
+    fn test_function_flags() {
+        let flags = FunctionFlags::UTF8 | FunctionFlags::DETERMINISTIC;
+        assert_eq!(
+            flags.bits(),
+            (ZQLITE_UTF8 | ZQLITE_DETERMINISTIC) as c_int
+        );
+    }
+
+    fn numbers() -> Connection {
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE numbers (n INTEGER)").unwrap();
+        for n in 1..=3 {
+            conn.execute(&format!("INSERT INTO numbers VALUES ({n})")).unwrap();
+        }
+        conn
+    }
+
+    #[test]
+    fn test_scalar_function() {
+        let conn = numbers();
+        conn.create_scalar_function("add_one", 1, FunctionFlags::DETERMINISTIC, |ctx| {
+            Ok(ctx.get_ref(0)?.as_i64()? + 1)
+        })
+        .unwrap();
+
+        let values: Vec<i64> = conn
+            .query("SELECT add_one(n) FROM numbers")
+            .unwrap()
+            .iter()
+            .map(|row| row.get::<i64>(0).unwrap())
+            .collect();
+        assert_eq!(values, vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn test_scalar_function_in_where_with_parameter() {
+        let conn = numbers();
+        conn.create_scalar_function("below", 2, FunctionFlags::DETERMINISTIC, |ctx| {
+            Ok(ctx.get_ref(0)?.as_i64()? < ctx.get_ref(1)?.as_i64()?)
+        })
+        .unwrap();
+
+        let mut stmt = conn
+            .prepare("SELECT n FROM numbers WHERE below(n, ?) AND n > 1")
+            .unwrap();
+        stmt.bind_int(0, 3).unwrap();
+        let values: Vec<i64> = conn
+            .query_prepared(&mut stmt)
+            .unwrap()
+            .iter()
+            .map(|row| row.get::<i64>(0).unwrap())
+            .collect();
+        assert_eq!(values, vec![2]);
+    }
+
+    #[test]
+    fn test_scalar_function_error_is_sql_error() {
+        let conn = numbers();
+        conn.create_scalar_function("fail", 1, FunctionFlags::default(), |_| {
+            Err::<i64, _>(Error::user_function("boom"))
+        })
+        .unwrap();
+
+        assert!(conn.query("SELECT fail(n) FROM numbers").is_err());
+    }
+
+    #[test]
+    fn test_scalar_function_panic_does_not_unwind() {
+        let conn = numbers();
+        conn.create_scalar_function("explode", 1, FunctionFlags::default(), |_| -> Result<i64> {
+            panic!("kaboom")
+        })
+        .unwrap();
+
+        assert!(conn.query("SELECT explode(n) FROM numbers").is_err());
+    }
+
+    #[test]
+    fn test_aggregate_function() {
+        let conn = numbers();
+        conn.create_aggregate_function("rust_sum", 1, FunctionFlags::DETERMINISTIC, Sum)
+            .unwrap();
+
+        let total: Vec<i64> = conn
+            .query("SELECT rust_sum(n) FROM numbers")
+            .unwrap()
+            .iter()
+            .map(|row| row.get::<i64>(0).unwrap())
+            .collect();
+        assert_eq!(total, vec![6]);
+
+        conn.remove_function("rust_sum", 1).unwrap();
+        assert!(conn.query("SELECT rust_sum(n) FROM numbers").is_err());
+    }
+}
@@ -0,0 +1,356 @@
+//! Change notification hooks
+
+use crate::ffi::{
+    zqlite_after_commit_hook, zqlite_commit_hook, zqlite_rollback_hook, zqlite_update_hook,
+    ZQLITE_DELETE, ZQLITE_INSERT, ZQLITE_UPDATE,
+};
+use crate::Connection;
+use std::ffi::{c_void, CStr};
+use std::os::raw::{c_char, c_int};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+use std::sync::{Mutex, PoisonError};
+
+#[cfg(feature = "async")]
+use std::sync::Arc;
+#[cfg(feature = "async")]
+use tokio::sync::broadcast;
+
+type UpdateHook = Mutex<Box<dyn FnMut(Action, &str, &str, i64) + Send>>;
+type CommitHook = Mutex<Box<dyn FnMut() -> bool + Send>>;
+type AfterCommitHook = Mutex<Box<dyn FnMut() + Send>>;
+type RollbackHook = Mutex<Box<dyn FnMut() + Send>>;
+
+/// Kind of row change reported to the update hook
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    /// Row inserted
+    Insert,
+    /// Row updated
+    Update,
+    /// Row deleted
+    Delete,
+    /// Operation code this version does not know about
+    Unknown(i32),
+}
+
+impl Action {
+    fn from_code(code: c_int) -> Self {
+        match code {
+            x if x == ZQLITE_INSERT as c_int => Action::Insert,
+            x if x == ZQLITE_UPDATE as c_int => Action::Update,
+            x if x == ZQLITE_DELETE as c_int => Action::Delete,
+            other => Action::Unknown(other),
+        }
+    }
+}
+
+impl Connection {
+    /// Call `hook` with `(action, database, table, rowid)` for every row change
+    ///
+    /// Replaces any update hook set before. Hooks run inside the statement that
+    /// caused them and must not use this connection.
+    pub fn set_update_hook<F>(&self, hook: F)
+    where
+        F: FnMut(Action, &str, &str, i64) + Send + 'static,
+    {
+        let hook: UpdateHook = Mutex::new(Box::new(hook));
+        let user_data = Box::into_raw(Box::new(hook)) as *mut c_void;
+        unsafe {
+            let previous = zqlite_update_hook(self.inner, Some(call_update), user_data);
+            free_hook::<UpdateHook>(previous);
+        }
+    }
+
+    /// Call `hook` before each commit; returning `true` rolls the transaction back
+    ///
+    /// A vetoed commit fails with a constraint error. A panicking hook also
+    /// vetoes the commit.
+    pub fn set_commit_hook<F>(&self, hook: F)
+    where
+        F: FnMut() -> bool + Send + 'static,
+    {
+        let hook: CommitHook = Mutex::new(Box::new(hook));
+        let user_data = Box::into_raw(Box::new(hook)) as *mut c_void;
+        unsafe {
+            let previous = zqlite_commit_hook(self.inner, Some(call_commit), user_data);
+            free_hook::<CommitHook>(previous);
+        }
+    }
+
+    /// Call `hook` after each commit, once its changes are durable
+    ///
+    /// Unlike the commit hook it cannot veto the commit, and it does not run
+    /// for a commit that fails.
+    pub fn set_after_commit_hook<F>(&self, hook: F)
+    where
+        F: FnMut() + Send + 'static,
+    {
+        let hook: AfterCommitHook = Mutex::new(Box::new(hook));
+        let user_data = Box::into_raw(Box::new(hook)) as *mut c_void;
+        unsafe {
+            let previous = zqlite_after_commit_hook(self.inner, Some(call_after_commit), user_data);
+            free_hook::<AfterCommitHook>(previous);
+        }
+    }
+
+    /// Call `hook` whenever a transaction is rolled back
+    pub fn set_rollback_hook<F>(&self, hook: F)
+    where
+        F: FnMut() + Send + 'static,
+    {
+        let hook: RollbackHook = Mutex::new(Box::new(hook));
+        let user_data = Box::into_raw(Box::new(hook)) as *mut c_void;
+        unsafe {
+            let previous = zqlite_rollback_hook(self.inner, Some(call_rollback), user_data);
+            free_hook::<RollbackHook>(previous);
+        }
+    }
+
+    /// Remove all update, commit, after-commit and rollback hooks
+    pub fn clear_hooks(&self) {
+        unsafe {
+            free_hook::<UpdateHook>(zqlite_update_hook(self.inner, None, ptr::null_mut()));
+            free_hook::<CommitHook>(zqlite_commit_hook(self.inner, None, ptr::null_mut()));
+            free_hook::<AfterCommitHook>(zqlite_after_commit_hook(self.inner, None, ptr::null_mut()));
+            free_hook::<RollbackHook>(zqlite_rollback_hook(self.inner, None, ptr::null_mut()));
+        }
+    }
+}
+
+/// Release hook state ZQLite handed back when a hook was replaced
+unsafe fn free_hook<T>(previous: *mut c_void) {
+    if !previous.is_null() {
+        drop(Box::from_raw(previous as *mut T));
+    }
+}
+
+unsafe extern "C" fn call_update(
+    user_data: *mut c_void,
+    op: c_int,
+    db_name: *const c_char,
+    table: *const c_char,
+    rowid: i64,
+) {
+    let outcome = catch_unwind(AssertUnwindSafe(|| {
+        let hook = &*(user_data as *const UpdateHook);
+        let db_name = CStr::from_ptr(db_name).to_string_lossy();
+        let table = CStr::from_ptr(table).to_string_lossy();
+        let mut hook = hook.lock().unwrap_or_else(PoisonError::into_inner);
+        hook(Action::from_code(op), &db_name, &table, rowid);
+    }));
+
+    if outcome.is_err() {
+        tracing::error!("update hook panicked");
+    }
+}
+
+unsafe extern "C" fn call_commit(user_data: *mut c_void) -> c_int {
+    let outcome = catch_unwind(AssertUnwindSafe(|| {
+        let hook = &*(user_data as *const CommitHook);
+        let mut hook = hook.lock().unwrap_or_else(PoisonError::into_inner);
+        hook()
+    }));
+
+    match outcome {
+        Ok(rollback) => rollback as c_int,
+        Err(_) => {
+            tracing::error!("commit hook panicked, rolling back");
+            1
+        }
+    }
+}
+
+unsafe extern "C" fn call_after_commit(user_data: *mut c_void) {
+    let outcome = catch_unwind(AssertUnwindSafe(|| {
+        let hook = &*(user_data as *const AfterCommitHook);
+        let mut hook = hook.lock().unwrap_or_else(PoisonError::into_inner);
+        hook();
+    }));
+
+    if outcome.is_err() {
+        tracing::error!("after-commit hook panicked");
+    }
+}
+
+unsafe extern "C" fn call_rollback(user_data: *mut c_void) {
+    let outcome = catch_unwind(AssertUnwindSafe(|| {
+        let hook = &*(user_data as *const RollbackHook);
+        let mut hook = hook.lock().unwrap_or_else(PoisonError::into_inner);
+        hook();
+    }));
+
+    if outcome.is_err() {
+        tracing::error!("rollback hook panicked");
+    }
+}
+
+/// A row change published after its transaction committed
+#[cfg(feature = "async")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    /// Table the row belongs to
+    pub table: String,
+    /// What happened to the row
+    pub op: Action,
+    /// Row id of the changed row
+    pub rowid: i64,
+}
+
+#[cfg(feature = "async")]
+impl Connection {
+    /// Publish committed row changes on a broadcast channel
+    ///
+    /// Changes are buffered until their transaction is durably committed and
+    /// dropped on rollback. This installs the update, after-commit and rollback
+    /// hooks, replacing any set before, and leaves the commit hook free to veto
+    /// commits; for a pool, call it from [`PoolConfig::after_connect`](crate::PoolConfig::after_connect)
+    /// with clones of one sender.
+    pub fn broadcast_changes(&self, sender: broadcast::Sender<Change>) {
+        let pending: Arc<Mutex<Vec<Change>>> = Arc::default();
+
+        let buffer = Arc::clone(&pending);
+        self.set_update_hook(move |op, _db_name, table, rowid| {
+            buffer
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(Change { table: table.to_string(), op, rowid });
+        });
+
+        let committed = Arc::clone(&pending);
+        self.set_after_commit_hook(move || {
+            let changes = std::mem::take(&mut *committed.lock().unwrap_or_else(PoisonError::into_inner));
+            for change in changes {
+                // Nobody listening is not an error
+                let _ = sender.send(change);
+            }
+        });
+
+        self.set_rollback_hook(move || {
+            pending.lock().unwrap_or_else(PoisonError::into_inner).clear();
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_update_hook_reports_changes() {
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE peers (id INTEGER, name TEXT)").unwrap();
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        conn.set_update_hook(move |action, _db_name, table, rowid| {
+            sink.lock().unwrap().push((action, table.to_string(), rowid));
+        });
+
+        conn.execute("INSERT INTO peers VALUES (1, 'a')").unwrap();
+        let first = conn.last_insert_rowid();
+        conn.execute("INSERT INTO peers VALUES (2, 'b')").unwrap();
+        let second = conn.last_insert_rowid();
+
+        // Prepared statements report every changed row as well
+        let mut rename = conn.prepare("UPDATE peers SET name = ? WHERE id > 0").unwrap();
+        rename.bind_text(0, "c").unwrap();
+        conn.query_prepared(&mut rename).unwrap();
+        conn.execute("DELETE FROM peers WHERE id = 1").unwrap();
+
+        let peers = "peers".to_string();
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                (Action::Insert, peers.clone(), first),
+                (Action::Insert, peers.clone(), second),
+                (Action::Update, peers.clone(), first),
+                (Action::Update, peers.clone(), second),
+                (Action::Delete, peers, first),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_commit_hook_can_veto() {
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE peers (id INTEGER)").unwrap();
+
+        let rollbacks = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&rollbacks);
+        conn.set_commit_hook(|| true);
+        conn.set_rollback_hook(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+
+        assert!(conn.execute("INSERT INTO peers VALUES (1)").is_err());
+        assert_eq!(rollbacks.load(Ordering::SeqCst), 1);
+
+        conn.clear_hooks();
+        conn.execute("INSERT INTO peers VALUES (2)").unwrap();
+    }
+
+    #[test]
+    fn test_replaced_hook_is_dropped() {
+        let conn = Connection::open(":memory:").unwrap();
+        let state = Arc::new(());
+
+        let held = Arc::clone(&state);
+        conn.set_rollback_hook(move || {
+            let _ = &held;
+        });
+        assert_eq!(Arc::strong_count(&state), 2);
+
+        conn.set_rollback_hook(|| {});
+        assert_eq!(Arc::strong_count(&state), 1);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_broadcast_changes() {
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE routes (id INTEGER)").unwrap();
+
+        let (sender, mut receiver) = broadcast::channel(16);
+        conn.broadcast_changes(sender);
+
+        conn.execute("INSERT INTO routes VALUES (1)").unwrap();
+
+        let change = receiver.recv().await.unwrap();
+        assert_eq!(change.table, "routes");
+        assert_eq!(change.op, Action::Insert);
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_broadcast_skips_rolled_back_changes() {
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        let conn = Connection::open(&temp_file.path().to_string_lossy()).unwrap();
+        conn.execute("CREATE TABLE acl_rules (id INTEGER)").unwrap();
+        conn.execute("INSERT INTO acl_rules VALUES (1)").unwrap();
+
+        let (sender, mut receiver) = broadcast::channel(16);
+        conn.broadcast_changes(sender);
+
+        let tx = conn.begin_transaction().unwrap();
+        tx.execute("INSERT INTO acl_rules VALUES (2)").unwrap();
+        tx.rollback().unwrap();
+
+        // A vetoed commit publishes nothing either
+        conn.set_commit_hook(|| true);
+        assert!(conn.execute("INSERT INTO acl_rules VALUES (3)").is_err());
+        conn.set_commit_hook(|| false);
+
+        let tx = conn.begin_transaction().unwrap();
+        tx.execute("DELETE FROM acl_rules WHERE id = 1").unwrap();
+        assert!(receiver.try_recv().is_err());
+        tx.commit().unwrap();
+
+        let change = receiver.recv().await.unwrap();
+        assert_eq!(change.op, Action::Delete);
+        assert!(receiver.try_recv().is_err());
+    }
+}
@@ -0,0 +1,123 @@
+//! Cancelling running statements
+
+use crate::ffi::{zqlite_connection_t, zqlite_interrupt};
+use crate::{Connection, Result, Rows};
+use std::fmt;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+/// Thread-safe handle that aborts the statement its connection is running
+///
+/// The interrupted statement fails with [`Error::Interrupted`](crate::Error::Interrupted).
+/// Only the statement running at the time of the call is affected; interrupting
+/// an idle or closed connection does nothing.
+#[derive(Clone)]
+pub struct InterruptHandle {
+    target: Arc<Mutex<RawConnection>>,
+}
+
+struct RawConnection(*mut zqlite_connection_t);
+
+// Safety: the pointer is only passed to zqlite_interrupt, which is thread-safe,
+// and is cleared under the lock before the connection is closed
+unsafe impl Send for RawConnection {}
+
+impl InterruptHandle {
+    pub(crate) fn new(inner: *mut zqlite_connection_t) -> Self {
+        Self {
+            target: Arc::new(Mutex::new(RawConnection(inner))),
+        }
+    }
+
+    /// Abort the statement currently running on the connection
+    pub fn interrupt(&self) {
+        let target = self.target.lock().unwrap_or_else(PoisonError::into_inner);
+        if !target.0.is_null() {
+            unsafe { zqlite_interrupt(target.0) };
+        }
+    }
+
+    /// Detach from the connection; called before it is closed
+    pub(crate) fn invalidate(&self) {
+        self.target.lock().unwrap_or_else(PoisonError::into_inner).0 = std::ptr::null_mut();
+    }
+}
+
+impl fmt::Debug for InterruptHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InterruptHandle").finish_non_exhaustive()
+    }
+}
+
+impl Connection {
+    /// Get a handle that can interrupt this connection from another thread
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interrupt.clone()
+    }
+
+    /// Execute a SQL statement, interrupting it if it runs longer than `timeout`
+    pub fn execute_with_timeout(&self, sql: &str, timeout: Duration) -> Result<()> {
+        self.with_timeout(timeout, || self.execute(sql))
+    }
+
+    /// Execute a SQL query, interrupting it if it runs longer than `timeout`
+    pub fn query_with_timeout(&self, sql: &str, timeout: Duration) -> Result<Rows> {
+        self.with_timeout(timeout, || self.query(sql))
+    }
+
+    fn with_timeout<T>(&self, timeout: Duration, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        let handle = self.interrupt_handle();
+        let (done, finished) = mpsc::channel::<()>();
+        let watchdog = thread::spawn(move || {
+            if let Err(RecvTimeoutError::Timeout) = finished.recv_timeout(timeout) {
+                handle.interrupt();
+            }
+        });
+
+        let result = f();
+
+        // Dropping the sender wakes the watchdog. An interrupt that lands after
+        // the statement finished is a no-op: ZQLite scopes it to the statement
+        // running when it is raised.
+        drop(done);
+        let _ = watchdog.join();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+
+    #[test]
+    fn test_interrupt_after_close_is_noop() {
+        let conn = Connection::open(":memory:").unwrap();
+        let handle = conn.interrupt_handle();
+        drop(conn);
+        handle.interrupt();
+    }
+
+    #[test]
+    fn test_stale_interrupt_does_not_affect_next_statement() {
+        let conn = Connection::open(":memory:").unwrap();
+        conn.interrupt_handle().interrupt();
+        conn.execute("CREATE TABLE peers (id INTEGER)").unwrap();
+    }
+
+    #[test]
+    fn test_fast_statement_beats_timeout() {
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute_with_timeout("CREATE TABLE peers (id INTEGER)", Duration::from_secs(5))
+            .unwrap();
+        conn.execute("INSERT INTO peers VALUES (1)").unwrap();
+    }
+
+    #[test]
+    fn test_interrupted_error_is_typed() {
+        let error = Error::from(crate::ffi::ZQLITE_INTERRUPT as i32);
+        assert!(matches!(error, Error::Interrupted));
+    }
+}
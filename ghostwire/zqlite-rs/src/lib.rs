@@ -10,6 +10,19 @@
 //! - Connection pooling for high-concurrency scenarios
 //! - Observability with tracing and metrics
 //! - Post-quantum cryptographic features
+//! - User-defined scalar and aggregate SQL functions written in Rust
+//! - Update/commit/rollback hooks and a broadcast stream of committed changes
+//! - Statement interrupts and per-statement timeouts
+//! - Incremental blob I/O with `Read`/`Write`/`Seek` and tokio async traits
+//! - Library version and capability checks
+//! - Dynamically typed row access with `Value`, `Row::get_ref` and `Row::to_map`
+//! - Conversions for `IpAddr`, `SocketAddr` and `[u8; N]`, plus `uuid`, `chrono`,
+//!   `time`, `ipnetwork` and `rust_decimal` types behind features of the same name
+//! - A query builder that generates parameterized SQL with validated identifiers
+//! - `query!` and `query_as!` macros checked against a schema snapshot at compile
+//!   time, behind the `macros` feature
+//! - Schema introspection of tables, columns, indexes and foreign keys
+//! - Multi-statement SQL scripts with `execute_batch`
 //!
 //! ## Example
 //!
@@ -24,7 +37,7 @@
 //!     conn.execute("INSERT INTO users (name) VALUES ('Alice')")?;
 //!
 //!     let rows = conn.query("SELECT * FROM users")?;
-//!     for row in rows {
+//!     for row in &rows {
 //!         println!("ID: {}, Name: {}", row.get::<i64>(0)?, row.get::<String>(1)?);
 //!     }
 //!
@@ -36,33 +49,61 @@
 #![warn(rust_2018_idioms)]
 
 use std::ffi::{CStr, CString};
-use std::os::raw::{c_char, c_int};
-use std::ptr;
-use std::sync::Arc;
+use std::os::raw::c_int;
+use std::sync::atomic::{AtomicBool, Ordering};
 
+pub use batch::split_statements;
+pub use blob::Blob;
 pub use error::{Error, Result};
-pub use pool::{ConnectionPool, PoolConfig, PoolStats};
-pub use row::{Row, Rows, FromSql};
+pub use functions::{Aggregate, Context, FunctionFlags};
+pub use hooks::Action;
+pub use interrupt::InterruptHandle;
+pub use query::{col, delete_from, insert_into, select, update, validate_identifier, Column, Condition, Delete, Insert, Order, Query, Select, Update};
+pub use pool::{ConnectionHook, ConnectionPool, PoolConfig, PoolStats, PooledConnectionGuard};
+pub use row::{FromSql, Row, RowIter, Rows};
+pub use schema::{ColumnInfo, ForeignKeyInfo, IndexInfo, TableInfo};
+pub use value::{ToSql, ToSqlOutput, Type, Value, ValueRef};
+pub use version::{check_library_version, Capabilities, Version, SUPPORTED_VERSIONS};
 pub use metrics::{ZQLiteMetrics, TransactionOutcome, Timer, PrometheusConfig, init_prometheus_exporter};
 
 #[cfg(feature = "async")]
 pub use async_connection::{AsyncConnection, AsyncConnectionPool, AsyncPreparedStatement, AsyncTransaction, SqlValue};
-
+#[cfg(feature = "async")]
+pub use hooks::Change;
+#[cfg(feature = "macros")]
+pub use macros::{Compatible, TypedQuery};
+#[cfg(feature = "macros")]
+pub use zqlite_macros::{query, query_as};
+
+mod batch;
+mod blob;
 mod error;
+mod functions;
+mod hooks;
+mod interrupt;
 mod pool;
+mod query;
 mod row;
+mod schema;
+mod types;
 mod metrics;
+mod value;
+mod version;
 
 #[cfg(feature = "async")]
 mod async_connection;
+#[cfg(feature = "macros")]
+pub mod macros;
 
-// Include the generated FFI bindings
-include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+// Raw declarations live in zqlite-sys; only safe wrappers are exported
+use zqlite_sys as ffi;
 
 /// A ZQLite database connection
 pub struct Connection {
-    inner: *mut zqlite_connection_t,
-    _marker: std::marker::PhantomData<zqlite_connection_t>,
+    inner: *mut ffi::zqlite_connection_t,
+    interrupt: InterruptHandle,
+    lenient_conversions: AtomicBool,
+    _marker: std::marker::PhantomData<ffi::zqlite_connection_t>,
 }
 
 impl Connection {
@@ -80,10 +121,14 @@ impl Connection {
     /// let conn = Connection::open("example.db")?;
     /// # Ok::<(), zqlite_rs::Error>(())
     /// ```
+    ///
+    /// Fails with [`Error::IncompatibleLibrary`] if the linked library is not
+    /// one these bindings support.
     pub fn open(path: &str) -> Result<Self> {
+        check_library_version()?;
         let path_cstr = CString::new(path).map_err(|_| Error::InvalidPath)?;
 
-        let conn_ptr = unsafe { zqlite_open(path_cstr.as_ptr()) };
+        let conn_ptr = unsafe { ffi::zqlite_open(path_cstr.as_ptr()) };
 
         if conn_ptr.is_null() {
             return Err(Error::ConnectionFailed);
@@ -91,6 +136,8 @@ impl Connection {
 
         Ok(Connection {
             inner: conn_ptr,
+            interrupt: InterruptHandle::new(conn_ptr),
+            lenient_conversions: AtomicBool::new(false),
             _marker: std::marker::PhantomData,
         })
     }
@@ -113,9 +160,9 @@ impl Connection {
     pub fn execute(&self, sql: &str) -> Result<()> {
         let sql_cstr = CString::new(sql).map_err(|_| Error::InvalidSql)?;
 
-        let result = unsafe { zqlite_execute(self.inner, sql_cstr.as_ptr()) };
+        let result = unsafe { ffi::zqlite_execute(self.inner, sql_cstr.as_ptr()) };
 
-        if result != ZQLITE_OK as c_int {
+        if result != ffi::ZQLITE_OK as c_int {
             return Err(self.get_last_error());
         }
 
@@ -136,7 +183,7 @@ impl Connection {
     /// # conn.execute("CREATE TABLE users (id INTEGER, name TEXT)")?;
     /// # conn.execute("INSERT INTO users VALUES (1, 'Alice')")?;
     /// let rows = conn.query("SELECT id, name FROM users")?;
-    /// for row in rows {
+    /// for row in &rows {
     ///     let id: i64 = row.get(0)?;
     ///     let name: String = row.get(1)?;
     ///     println!("ID: {}, Name: {}", id, name);
@@ -146,13 +193,16 @@ impl Connection {
     pub fn query(&self, sql: &str) -> Result<Rows> {
         let sql_cstr = CString::new(sql).map_err(|_| Error::InvalidSql)?;
 
-        let result_ptr = unsafe { zqlite_query(self.inner, sql_cstr.as_ptr()) };
+        let result_ptr = unsafe { ffi::zqlite_query(self.inner, sql_cstr.as_ptr()) };
 
         if result_ptr.is_null() {
             return Err(self.get_last_error());
         }
 
-        Ok(Rows::new(result_ptr))
+        Ok(Rows::new(
+            result_ptr,
+            self.lenient_conversions.load(Ordering::Relaxed),
+        ))
     }
 
     /// Prepare a SQL statement for repeated execution
@@ -175,62 +225,97 @@ impl Connection {
     pub fn prepare(&self, sql: &str) -> Result<PreparedStatement> {
         let sql_cstr = CString::new(sql).map_err(|_| Error::InvalidSql)?;
 
-        let stmt_ptr = unsafe { zqlite_prepare(self.inner, sql_cstr.as_ptr()) };
+        let stmt_ptr = unsafe { ffi::zqlite_prepare(self.inner, sql_cstr.as_ptr()) };
 
         if stmt_ptr.is_null() {
             return Err(self.get_last_error());
         }
 
-        Ok(PreparedStatement::new(stmt_ptr))
+        Ok(PreparedStatement::new(stmt_ptr, self.inner))
+    }
+
+    /// Run a prepared query with its bound parameters and return results
+    pub fn query_prepared(&self, stmt: &mut PreparedStatement) -> Result<Rows> {
+        let result_ptr = unsafe { ffi::zqlite_query_prepared(self.inner, stmt.inner) };
+
+        if result_ptr.is_null() {
+            return Err(self.get_last_error());
+        }
+
+        Ok(Rows::new(
+            result_ptr,
+            self.lenient_conversions.load(Ordering::Relaxed),
+        ))
     }
 
     /// Begin a transaction
     pub fn begin_transaction(&self) -> Result<Transaction<'_>> {
-        let result = unsafe { zqlite_begin_transaction(self.inner) };
+        let result = unsafe { ffi::zqlite_begin_transaction(self.inner) };
 
-        if result != ZQLITE_OK as c_int {
+        if result != ffi::ZQLITE_OK as c_int {
             return Err(self.get_last_error());
         }
 
         Ok(Transaction::new(self))
     }
 
+    /// Check whether the connection is in autocommit mode
+    ///
+    /// Returns `false` while a transaction is open, whether it was started
+    /// with [`Connection::begin_transaction`] or a raw `BEGIN` statement.
+    pub fn is_autocommit(&self) -> bool {
+        unsafe { ffi::zqlite_get_autocommit(self.inner) != 0 }
+    }
+
+    /// Let [`Row::get`] coerce values the way SQLite column affinity does
+    ///
+    /// Off by default: conversions then require the stored type to match and
+    /// integers to fit. Applies to queries run after the call.
+    pub fn set_lenient_conversions(&self, lenient: bool) {
+        self.lenient_conversions.store(lenient, Ordering::Relaxed);
+    }
+
+    /// Roll back whatever transaction is currently open on this connection
+    pub(crate) fn rollback_open_transaction(&self) -> Result<()> {
+        let result = unsafe { ffi::zqlite_rollback_transaction(self.inner) };
+
+        if result != ffi::ZQLITE_OK as c_int {
+            return Err(Error::TransactionError);
+        }
+
+        Ok(())
+    }
+
     /// Get the last error message
     fn get_last_error(&self) -> Error {
-        let error_msg = unsafe {
-            let msg_ptr = zqlite_errmsg(self.inner);
-            if msg_ptr.is_null() {
-                return Error::Unknown;
-            }
-            CStr::from_ptr(msg_ptr).to_string_lossy().into_owned()
-        };
-
-        Error::Database(error_msg)
+        last_error(self.inner)
     }
 
-    /// Get ZQLite version
+    /// Get ZQLite version; see [`Version::library`] for a structured version
     pub fn version() -> &'static str {
         unsafe {
-            let version_ptr = zqlite_version();
+            let version_ptr = ffi::zqlite_version();
             CStr::from_ptr(version_ptr).to_str().unwrap_or("unknown")
         }
     }
 
     /// Get the number of rows affected by the last operation
     pub fn changes(&self) -> i64 {
-        unsafe { zqlite_changes(self.inner) as i64 }
+        unsafe { ffi::zqlite_changes(self.inner) as i64 }
     }
 
     /// Get the last inserted row ID
     pub fn last_insert_rowid(&self) -> i64 {
-        unsafe { zqlite_last_insert_rowid(self.inner) }
+        unsafe { ffi::zqlite_last_insert_rowid(self.inner) }
     }
 }
 
 impl Drop for Connection {
     fn drop(&mut self) {
+        self.clear_hooks();
+        self.interrupt.invalidate();
         unsafe {
-            zqlite_close(self.inner);
+            ffi::zqlite_close(self.inner);
         }
     }
 }
@@ -239,25 +324,49 @@ impl Drop for Connection {
 unsafe impl Send for Connection {}
 unsafe impl Sync for Connection {}
 
+/// The error recorded for the last statement run on `conn`
+fn last_error(conn: *mut ffi::zqlite_connection_t) -> Error {
+    let code = unsafe { ffi::zqlite_errcode(conn) };
+    if code == ffi::ZQLITE_INTERRUPT as c_int {
+        return Error::Interrupted;
+    }
+
+    let error_msg = unsafe {
+        let msg_ptr = ffi::zqlite_errmsg(conn);
+        if msg_ptr.is_null() {
+            return Error::Unknown;
+        }
+        CStr::from_ptr(msg_ptr).to_string_lossy().into_owned()
+    };
+
+    if code == ffi::ZQLITE_CONSTRAINT as c_int {
+        return Error::ConstraintViolation(error_msg);
+    }
+    Error::Database(error_msg)
+}
+
 /// A prepared SQL statement
 pub struct PreparedStatement {
-    inner: *mut zqlite_stmt_t,
-    _marker: std::marker::PhantomData<zqlite_stmt_t>,
+    inner: *mut ffi::zqlite_stmt_t,
+    /// The connection it was prepared on, for error details
+    conn: *mut ffi::zqlite_connection_t,
+    _marker: std::marker::PhantomData<ffi::zqlite_stmt_t>,
 }
 
 impl PreparedStatement {
-    fn new(stmt: *mut zqlite_stmt_t) -> Self {
+    fn new(stmt: *mut ffi::zqlite_stmt_t, conn: *mut ffi::zqlite_connection_t) -> Self {
         Self {
             inner: stmt,
+            conn,
             _marker: std::marker::PhantomData,
         }
     }
 
     /// Bind an integer parameter
     pub fn bind_int(&mut self, index: usize, value: i64) -> Result<()> {
-        let result = unsafe { zqlite_bind_int(self.inner, index as c_int, value) };
+        let result = unsafe { ffi::zqlite_bind_int(self.inner, index as c_int, value) };
 
-        if result != ZQLITE_OK as c_int {
+        if result != ffi::ZQLITE_OK as c_int {
             return Err(Error::BindError);
         }
 
@@ -267,9 +376,9 @@ impl PreparedStatement {
     /// Bind a text parameter
     pub fn bind_text(&mut self, index: usize, value: &str) -> Result<()> {
         let value_cstr = CString::new(value).map_err(|_| Error::InvalidSql)?;
-        let result = unsafe { zqlite_bind_text(self.inner, index as c_int, value_cstr.as_ptr()) };
+        let result = unsafe { ffi::zqlite_bind_text(self.inner, index as c_int, value_cstr.as_ptr()) };
 
-        if result != ZQLITE_OK as c_int {
+        if result != ffi::ZQLITE_OK as c_int {
             return Err(Error::BindError);
         }
 
@@ -278,9 +387,35 @@ impl PreparedStatement {
 
     /// Bind a real (float) parameter
     pub fn bind_real(&mut self, index: usize, value: f64) -> Result<()> {
-        let result = unsafe { zqlite_bind_real(self.inner, index as c_int, value) };
+        let result = unsafe { ffi::zqlite_bind_real(self.inner, index as c_int, value) };
+
+        if result != ffi::ZQLITE_OK as c_int {
+            return Err(Error::BindError);
+        }
+
+        Ok(())
+    }
+
+    /// Bind a blob parameter
+    pub fn bind_blob(&mut self, index: usize, value: &[u8]) -> Result<()> {
+        let size = c_int::try_from(value.len()).map_err(|_| Error::BindError)?;
+        let result = unsafe {
+            ffi::zqlite_bind_blob(self.inner, index as c_int, value.as_ptr() as *const std::ffi::c_void, size)
+        };
+
+        if result != ffi::ZQLITE_OK as c_int {
+            return Err(Error::BindError);
+        }
+
+        Ok(())
+    }
+
+    /// Bind a zero-filled blob of `len` bytes, to be filled later with [`Blob`]
+    pub fn bind_zeroblob(&mut self, index: usize, len: usize) -> Result<()> {
+        let size = c_int::try_from(len).map_err(|_| Error::BindError)?;
+        let result = unsafe { ffi::zqlite_bind_zeroblob(self.inner, index as c_int, size) };
 
-        if result != ZQLITE_OK as c_int {
+        if result != ffi::ZQLITE_OK as c_int {
             return Err(Error::BindError);
         }
 
@@ -289,31 +424,45 @@ impl PreparedStatement {
 
     /// Bind a null parameter
     pub fn bind_null(&mut self, index: usize) -> Result<()> {
-        let result = unsafe { zqlite_bind_null(self.inner, index as c_int) };
+        let result = unsafe { ffi::zqlite_bind_null(self.inner, index as c_int) };
 
-        if result != ZQLITE_OK as c_int {
+        if result != ffi::ZQLITE_OK as c_int {
             return Err(Error::BindError);
         }
 
         Ok(())
     }
 
-    /// Execute the prepared statement
-    pub fn execute(&mut self) -> Result<()> {
-        let result = unsafe { zqlite_step(self.inner) };
+    /// Bind any [`ToSql`] value, e.g. a UUID or timestamp
+    pub fn bind<T: ToSql + ?Sized>(&mut self, index: usize, value: &T) -> Result<()> {
+        match value.to_sql()?.value_ref() {
+            ValueRef::Null => self.bind_null(index),
+            ValueRef::Integer(i) => self.bind_int(index, i),
+            ValueRef::Real(f) => self.bind_real(index, f),
+            ValueRef::Text(text) => {
+                let text = std::str::from_utf8(text).map_err(|_| Error::BindError)?;
+                self.bind_text(index, text)
+            }
+            ValueRef::Blob(blob) => self.bind_blob(index, blob),
+        }
+    }
 
-        match result {
-            x if x == ZQLITE_DONE as c_int => Ok(()),
-            x if x == ZQLITE_ROW as c_int => Ok(()), // Has results but we're not returning them
-            _ => Err(Error::ExecutionError),
+    /// Run the prepared statement to completion, discarding any rows
+    pub fn execute(&mut self) -> Result<()> {
+        loop {
+            match unsafe { ffi::zqlite_step(self.inner) } {
+                x if x == ffi::ZQLITE_ROW as c_int => continue,
+                x if x == ffi::ZQLITE_DONE as c_int => return Ok(()),
+                _ => return Err(last_error(self.conn)),
+            }
         }
     }
 
     /// Reset the prepared statement for re-execution
     pub fn reset(&mut self) -> Result<()> {
-        let result = unsafe { zqlite_reset(self.inner) };
+        let result = unsafe { ffi::zqlite_reset(self.inner) };
 
-        if result != ZQLITE_OK as c_int {
+        if result != ffi::ZQLITE_OK as c_int {
             return Err(Error::ResetError);
         }
 
@@ -324,7 +473,7 @@ impl PreparedStatement {
 impl Drop for PreparedStatement {
     fn drop(&mut self) {
         unsafe {
-            zqlite_finalize(self.inner);
+            ffi::zqlite_finalize(self.inner);
         }
     }
 }
@@ -345,9 +494,9 @@ impl<'conn> Transaction<'conn> {
 
     /// Commit the transaction
     pub fn commit(mut self) -> Result<()> {
-        let result = unsafe { zqlite_commit_transaction(self.connection.inner) };
+        let result = unsafe { ffi::zqlite_commit_transaction(self.connection.inner) };
 
-        if result != ZQLITE_OK as c_int {
+        if result != ffi::ZQLITE_OK as c_int {
             return Err(Error::TransactionError);
         }
 
@@ -357,9 +506,9 @@ impl<'conn> Transaction<'conn> {
 
     /// Rollback the transaction
     pub fn rollback(self) -> Result<()> {
-        let result = unsafe { zqlite_rollback_transaction(self.connection.inner) };
+        let result = unsafe { ffi::zqlite_rollback_transaction(self.connection.inner) };
 
-        if result != ZQLITE_OK as c_int {
+        if result != ffi::ZQLITE_OK as c_int {
             return Err(Error::TransactionError);
         }
 
@@ -381,7 +530,7 @@ impl<'conn> Drop for Transaction<'conn> {
     fn drop(&mut self) {
         if !self.committed {
             // Auto-rollback on drop if not committed
-            let _ = unsafe { zqlite_rollback_transaction(self.connection.inner) };
+            let _ = unsafe { ffi::zqlite_rollback_transaction(self.connection.inner) };
         }
     }
 }
@@ -417,8 +566,43 @@ mod tests {
         stmt.bind_int(0, 42).unwrap();
         stmt.bind_text(1, "test").unwrap();
         stmt.execute().unwrap();
-
         assert_eq!(conn.changes(), 1);
+
+        // The statement can run again with new bindings
+        stmt.reset().unwrap();
+        stmt.bind_int(0, 43).unwrap();
+        stmt.bind_text(1, "again").unwrap();
+        stmt.execute().unwrap();
+
+        let mut select = conn.prepare("SELECT name FROM test WHERE id = ?").unwrap();
+        select.bind_int(0, 43).unwrap();
+        let rows: Vec<String> = conn
+            .query_prepared(&mut select)
+            .unwrap()
+            .iter()
+            .map(|row| row.get(0).unwrap())
+            .collect();
+        assert_eq!(rows, ["again"]);
+
+        // Execution errors carry the engine's reason
+        let mut missing = conn.prepare("INSERT INTO missing VALUES (?)").unwrap();
+        missing.bind_int(0, 1).unwrap();
+        assert!(matches!(missing.execute(), Err(Error::Database(reason)) if reason == "TableNotFound"));
+    }
+
+    #[test]
+    fn test_unique_violation_is_a_constraint_error() {
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE keys (key BLOB UNIQUE)").unwrap();
+
+        let mut insert = conn.prepare("INSERT INTO keys (key) VALUES (?)").unwrap();
+        insert.bind_blob(0, &[1, 2, 3]).unwrap();
+        insert.execute().unwrap();
+
+        insert.reset().unwrap();
+        insert.bind_blob(0, &[1, 2, 3]).unwrap();
+        let err = insert.execute().unwrap_err();
+        assert!(err.is_constraint_violation(), "{err}");
     }
 
     #[test]
@@ -434,7 +618,7 @@ mod tests {
 
         // Verify data was committed
         let rows = conn.query("SELECT COUNT(*) FROM test").unwrap();
-        // Note: We'd need to implement row iteration to fully test this
+        assert_eq!(rows.get(0).unwrap().get::<i64>(0).unwrap(), 2);
     }
 
     #[test]
@@ -0,0 +1,234 @@
+//! Runtime support for the [`query!`](crate::query!) and [`query_as!`](crate::query_as!) macros
+//!
+//! The macros check SQL against a schema snapshot at compile time: tables and
+//! columns must exist, and every parameter and output field must be a Rust
+//! type [`Compatible`] with its column's affinity. Snapshots are written by
+//! `cargo zqlite prepare` to `zqlite-schema.json` next to the crate's
+//! `Cargo.toml`; set `ZQLITE_SCHEMA` to use another path.
+
+use crate::{Connection, Error, FromSql, Query, Result, Row, ToSql, Value};
+
+/// Marker types for column affinities, named after the SQL type
+pub mod sql_types {
+    /// INTEGER affinity
+    pub struct Integer;
+    /// REAL affinity
+    pub struct Real;
+    /// NUMERIC affinity
+    pub struct Numeric;
+    /// TEXT affinity
+    pub struct Text;
+    /// BLOB affinity, also used for columns without a declared type
+    pub struct Blob;
+}
+
+use sql_types::{Blob, Integer, Numeric, Real, Text};
+
+/// Rust types that can be stored in, or read from, a column of affinity `S`
+///
+/// Implement this for your own [`ToSql`]/[`FromSql`] types to use them with
+/// the query macros.
+pub trait Compatible<S> {}
+
+impl<S, T: Compatible<S> + ?Sized> Compatible<S> for &T {}
+impl<S, T: Compatible<S>> Compatible<S> for Option<T> {}
+impl<S> Compatible<S> for Value {}
+
+macro_rules! compatible {
+    ($affinity:ty => $($t:ty),*) => {
+        $(impl Compatible<$affinity> for $t {})*
+    };
+}
+
+compatible!(Integer => bool, i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+compatible!(Real => f32, f64);
+compatible!(Numeric => i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, f32, f64);
+compatible!(Text => str, String, std::net::IpAddr, std::net::SocketAddr);
+compatible!(Blob => [u8], Vec<u8>);
+
+impl<const N: usize> Compatible<Blob> for [u8; N] {}
+
+#[cfg(feature = "uuid")]
+compatible!(Text => uuid::Uuid);
+#[cfg(feature = "uuid")]
+compatible!(Blob => uuid::Uuid);
+
+#[cfg(feature = "chrono")]
+compatible!(Integer => chrono::DateTime<chrono::Utc>);
+#[cfg(feature = "chrono")]
+compatible!(Real => chrono::DateTime<chrono::Utc>);
+#[cfg(feature = "chrono")]
+compatible!(Text => chrono::DateTime<chrono::Utc>);
+
+#[cfg(feature = "time")]
+compatible!(Integer => time::OffsetDateTime);
+#[cfg(feature = "time")]
+compatible!(Real => time::OffsetDateTime);
+#[cfg(feature = "time")]
+compatible!(Text => time::OffsetDateTime);
+
+#[cfg(feature = "ipnetwork")]
+compatible!(Text => ipnetwork::IpNetwork, ipnetwork::Ipv4Network, ipnetwork::Ipv6Network);
+
+#[cfg(feature = "rust_decimal")]
+compatible!(Text => rust_decimal::Decimal);
+#[cfg(feature = "rust_decimal")]
+compatible!(Numeric => rust_decimal::Decimal);
+
+/// Rust types that can hold SQL NULL
+///
+/// Output fields for nullable columns must be one of these, so reading a
+/// nullable column into a plain `String` is a compile error.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot hold NULL read from a nullable column",
+    label = "use `Option<{Self}>` for this field"
+)]
+pub trait Nullable {}
+
+impl<T> Nullable for Option<T> {}
+impl Nullable for Value {}
+
+/// Convert a macro argument bound to a column of affinity `S`
+#[doc(hidden)]
+pub fn encode<T, S>(value: &T) -> Result<Value>
+where
+    T: ToSql + Compatible<S> + ?Sized,
+{
+    crate::query::to_value(value)
+}
+
+/// Read an output column of affinity `S`
+#[doc(hidden)]
+pub fn decode<T, S>(row: &Row<'_>, column: usize) -> Result<T>
+where
+    T: FromSql + Compatible<S>,
+{
+    row.get(column)
+}
+
+/// Read a nullable output column of affinity `S`
+#[doc(hidden)]
+pub fn decode_nullable<T, S>(row: &Row<'_>, column: usize) -> Result<T>
+where
+    T: FromSql + Compatible<S> + Nullable,
+{
+    row.get(column)
+}
+
+/// A checked query whose rows convert to `R`
+///
+/// Built by the query macros; `R` is `()` for statements without output
+/// columns. Running the query consumes it, so an error from converting a
+/// parameter is returned as is.
+pub struct TypedQuery<R> {
+    query: Result<Query>,
+    map: fn(&Row<'_>) -> Result<R>,
+}
+
+impl<R> TypedQuery<R> {
+    #[doc(hidden)]
+    pub fn new(sql: &str, params: Vec<Result<Value>>, map: fn(&Row<'_>) -> Result<R>) -> Self {
+        let query = params
+            .into_iter()
+            .collect::<Result<Vec<_>>>()
+            .map(|params| Query::new(sql.to_string(), params));
+        Self { query, map }
+    }
+
+    /// The SQL and parameters, or the error from converting a parameter
+    pub fn query(&self) -> std::result::Result<&Query, &Error> {
+        self.query.as_ref()
+    }
+
+    /// Execute without reading rows
+    pub fn execute(self, conn: &Connection) -> Result<()> {
+        conn.execute_query(&self.query?)
+    }
+
+    /// Read every row
+    pub fn fetch_all(self, conn: &Connection) -> Result<Vec<R>> {
+        let rows = conn.fetch_query(&self.query?)?;
+        rows.iter().map(|row| (self.map)(&row)).collect()
+    }
+
+    /// Read the first row, if any
+    pub fn fetch_optional(self, conn: &Connection) -> Result<Option<R>> {
+        let rows = conn.fetch_query(&self.query?)?;
+        rows.get(0).map(|row| (self.map)(&row)).transpose()
+    }
+
+    /// Read the first row, failing if there is none
+    pub fn fetch_one(self, conn: &Connection) -> Result<R> {
+        self.fetch_optional(conn)?
+            .ok_or_else(|| Error::row_error("query returned no rows"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_compatible<T: Compatible<S> + ?Sized, S>() {}
+
+    #[test]
+    fn test_compatibility() {
+        assert_compatible::<i64, Integer>();
+        assert_compatible::<Option<&str>, Text>();
+        assert_compatible::<[u8; 32], Blob>();
+        assert_compatible::<Value, Real>();
+    }
+
+    #[test]
+    fn test_nullable_columns_decode_into_option() {
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE peers (id TEXT, endpoints TEXT)").unwrap();
+        conn.execute("INSERT INTO peers (id) VALUES ('a')").unwrap();
+
+        let select: TypedQuery<Option<String>> = TypedQuery::new(
+            "SELECT endpoints FROM peers",
+            vec![],
+            |row| decode_nullable::<Option<String>, Text>(row, 0),
+        );
+        assert_eq!(select.fetch_one(&conn).unwrap(), None);
+    }
+
+    #[test]
+    fn test_parameter_errors_surface_on_use() {
+        let query: TypedQuery<()> = TypedQuery::new(
+            "UPDATE peers SET rx_bytes = ?",
+            vec![encode::<u64, Integer>(&u64::MAX)],
+            |_| Ok(()),
+        );
+        assert!(matches!(query.query(), Err(Error::TypeMismatch { .. })));
+    }
+
+    #[test]
+    fn test_execute_and_fetch() {
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE peers (id TEXT, rx_bytes INTEGER)").unwrap();
+
+        let insert: TypedQuery<()> = TypedQuery::new(
+            "INSERT INTO peers (id, rx_bytes) VALUES (?, ?)",
+            vec![encode::<str, Text>("a"), encode::<i64, Integer>(&7)],
+            |_| Ok(()),
+        );
+        insert.execute(&conn).unwrap();
+
+        let select = || -> TypedQuery<i64> {
+            TypedQuery::new(
+                "SELECT rx_bytes FROM peers WHERE id = ?",
+                vec![encode::<str, Text>("a")],
+                |row| decode::<i64, Integer>(row, 0),
+            )
+        };
+        assert_eq!(select().fetch_all(&conn).unwrap(), vec![7]);
+        assert_eq!(select().fetch_one(&conn).unwrap(), 7);
+
+        let missing: TypedQuery<i64> = TypedQuery::new(
+            "SELECT rx_bytes FROM peers WHERE id = ?",
+            vec![encode::<str, Text>("b")],
+            |row| decode::<i64, Integer>(row, 0),
+        );
+        assert_eq!(missing.fetch_optional(&conn).unwrap(), None);
+    }
+}
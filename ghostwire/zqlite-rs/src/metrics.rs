@@ -1,6 +1,6 @@
 //! Metrics collection and observability for ZQLite
 
-use metrics::{counter, gauge, histogram, Unit};
+use metrics::{counter, gauge, histogram};
 use std::time::{Duration, Instant};
 use tracing::{debug, error, info, warn};
 
@@ -65,12 +65,10 @@ impl ZQLiteMetrics {
 
         histogram!(
             format!("{}_query_duration_seconds", self.prefix),
-            Unit::Seconds
         ).record(duration.as_secs_f64());
 
         histogram!(
             format!("{}_query_duration_seconds", self.prefix),
-            Unit::Seconds,
             "operation" => operation_type
         ).record(duration.as_secs_f64());
 
@@ -97,7 +95,6 @@ impl ZQLiteMetrics {
 
         histogram!(
             format!("{}_prepared_statement_duration_seconds", self.prefix),
-            Unit::Seconds
         ).record(duration.as_secs_f64());
 
         if success {
@@ -126,12 +123,10 @@ impl ZQLiteMetrics {
 
         histogram!(
             format!("{}_transaction_duration_seconds", self.prefix),
-            Unit::Seconds
         ).record(duration.as_secs_f64());
 
         histogram!(
             format!("{}_transaction_duration_seconds", self.prefix),
-            Unit::Seconds,
             "outcome" => outcome.as_str()
         ).record(duration.as_secs_f64());
 
@@ -161,7 +156,6 @@ impl ZQLiteMetrics {
         counter!(format!("{}_pool_connections_acquired_total", self.prefix)).increment(1);
         histogram!(
             format!("{}_pool_connection_wait_duration_seconds", self.prefix),
-            Unit::Seconds
         ).record(wait_duration.as_secs_f64());
 
         debug!("Connection acquired from pool in {:?}", wait_duration);
@@ -271,7 +265,6 @@ impl Timer {
                 // This would need the SQL string, so we use a generic approach
                 histogram!(
                     format!("{}_operation_duration_seconds", self.metrics.prefix),
-                    Unit::Seconds,
                     "operation" => "query",
                     "success" => success.to_string()
                 ).record(duration.as_secs_f64());
@@ -282,7 +275,6 @@ impl Timer {
             _ => {
                 histogram!(
                     format!("{}_operation_duration_seconds", self.metrics.prefix),
-                    Unit::Seconds,
                     "operation" => self.operation,
                     "success" => success.to_string()
                 ).record(duration.as_secs_f64());
@@ -310,20 +302,24 @@ impl Default for PrometheusConfig {
 }
 
 /// Initialize Prometheus metrics exporter
-pub async fn init_prometheus_exporter(config: PrometheusConfig) -> Result<(), Box<dyn std::error::Error>> {
+pub async fn init_prometheus_exporter(
+    config: PrometheusConfig,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     use metrics_exporter_prometheus::PrometheusBuilder;
     use std::net::SocketAddr;
 
     let addr: SocketAddr = config.bind_address.parse()?;
 
     let builder = PrometheusBuilder::new();
-    let handle = builder.install()?;
+    let handle = builder.install_recorder()?;
 
     // Start the metrics server
+    let metrics_path = config.metrics_path.clone();
     tokio::spawn(async move {
         let app = axum::Router::new()
-            .route(&config.metrics_path, axum::routing::get(move || async move {
-                handle.render()
+            .route(&metrics_path, axum::routing::get(move || {
+                let handle = handle.clone();
+                async move { handle.render() }
             }));
 
         let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
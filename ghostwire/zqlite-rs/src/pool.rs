@@ -2,12 +2,21 @@
 
 use crate::{Connection, Error, Result};
 use std::collections::VecDeque;
-use std::sync::{Arc, Condvar, Mutex};
+use std::fmt;
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
 use std::time::{Duration, Instant};
 use tracing::{debug, error, info, warn};
 
+/// Callback invoked by the pool at a point in a connection's lifecycle
+///
+/// Returning an error from a hook causes the pool to discard the connection
+/// (or, for `after_connect`, to fail the acquisition that opened it).
+/// `after_connect` and `before_acquire` run while the pool lock is held, so
+/// they must not acquire connections from the same pool.
+pub type ConnectionHook = Arc<dyn Fn(&Connection) -> Result<()> + Send + Sync>;
+
 /// Configuration for a connection pool
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct PoolConfig {
     /// Minimum number of connections to maintain
     pub min_connections: u32,
@@ -21,6 +30,42 @@ pub struct PoolConfig {
     pub max_idle_time: Duration,
     /// Test query to validate connections
     pub test_query: Option<String>,
+    /// Run once on every newly opened connection (set pragmas, register
+    /// functions, attach databases)
+    pub after_connect: Option<ConnectionHook>,
+    /// Run on an idle connection before it is handed out (custom validation)
+    pub before_acquire: Option<ConnectionHook>,
+    /// Run when a connection is returned to the pool (reset session state)
+    pub after_release: Option<ConnectionHook>,
+}
+
+impl PoolConfig {
+    /// Set the hook run on every newly opened connection
+    pub fn after_connect<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&Connection) -> Result<()> + Send + Sync + 'static,
+    {
+        self.after_connect = Some(Arc::new(hook));
+        self
+    }
+
+    /// Set the hook run on an idle connection before it is handed out
+    pub fn before_acquire<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&Connection) -> Result<()> + Send + Sync + 'static,
+    {
+        self.before_acquire = Some(Arc::new(hook));
+        self
+    }
+
+    /// Set the hook run when a connection is returned to the pool
+    pub fn after_release<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&Connection) -> Result<()> + Send + Sync + 'static,
+    {
+        self.after_release = Some(Arc::new(hook));
+        self
+    }
 }
 
 impl Default for PoolConfig {
@@ -32,10 +77,29 @@ impl Default for PoolConfig {
             max_connection_lifetime: Duration::from_secs(3600), // 1 hour
             max_idle_time: Duration::from_secs(600),           // 10 minutes
             test_query: Some("SELECT 1".to_string()),
+            after_connect: None,
+            before_acquire: None,
+            after_release: None,
         }
     }
 }
 
+impl fmt::Debug for PoolConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PoolConfig")
+            .field("min_connections", &self.min_connections)
+            .field("max_connections", &self.max_connections)
+            .field("connection_timeout", &self.connection_timeout)
+            .field("max_connection_lifetime", &self.max_connection_lifetime)
+            .field("max_idle_time", &self.max_idle_time)
+            .field("test_query", &self.test_query)
+            .field("after_connect", &self.after_connect.is_some())
+            .field("before_acquire", &self.before_acquire.is_some())
+            .field("after_release", &self.after_release.is_some())
+            .finish()
+    }
+}
+
 /// Statistics about the connection pool
 #[derive(Debug, Clone)]
 pub struct PoolStats {
@@ -79,16 +143,20 @@ impl PooledConnection {
 
     fn is_valid(&self, config: &PoolConfig) -> bool {
         if let Some(ref test_query) = config.test_query {
-            match self.connection.execute(test_query) {
-                Ok(()) => true,
-                Err(e) => {
-                    debug!("Connection validation failed: {}", e);
-                    false
-                }
+            if let Err(e) = self.connection.execute(test_query) {
+                debug!("Connection validation failed: {}", e);
+                return false;
             }
-        } else {
-            true
         }
+
+        if let Some(ref before_acquire) = config.before_acquire {
+            if let Err(e) = before_acquire(&self.connection) {
+                debug!("before_acquire hook rejected connection: {}", e);
+                return false;
+            }
+        }
+
+        true
     }
 }
 
@@ -153,7 +221,7 @@ impl ConnectionPool {
         let min_connections = inner.config.min_connections;
 
         for _ in 0..min_connections {
-            let conn = self.create_connection(&inner.database_path)?;
+            let conn = Self::create_connection(&inner.config, &inner.database_path)?;
             let pooled_conn = PooledConnection::new(conn);
             inner.available.push_back(pooled_conn);
             inner.stats.connections_created += 1;
@@ -163,12 +231,18 @@ impl ConnectionPool {
         Ok(())
     }
 
-    /// Create a new database connection
-    fn create_connection(&self, database_path: &Option<String>) -> Result<Connection> {
-        match database_path {
-            Some(path) => Connection::open(path),
-            None => Connection::open(":memory:"),
+    /// Create a new database connection and run the `after_connect` hook
+    fn create_connection(config: &PoolConfig, database_path: &Option<String>) -> Result<Connection> {
+        let conn = match database_path {
+            Some(path) => Connection::open(path)?,
+            None => Connection::open(":memory:")?,
+        };
+
+        if let Some(ref after_connect) = config.after_connect {
+            after_connect(&conn)?;
         }
+
+        Ok(conn)
     }
 
     /// Get a connection from the pool
@@ -206,8 +280,8 @@ impl ConnectionPool {
             }
 
             // No available connections, try to create a new one
-            if inner.active_count + inner.available.len() as u32 < inner.config.max_connections {
-                match self.create_connection(&inner.database_path) {
+            if inner.active_count + (inner.available.len() as u32) < inner.config.max_connections {
+                match Self::create_connection(&inner.config, &inner.database_path) {
                     Ok(conn) => {
                         inner.active_count += 1;
                         inner.stats.connections_created += 1;
@@ -236,7 +310,10 @@ impl ConnectionPool {
             inner.stats.waiting_requests += 1;
             warn!("Pool exhausted, waiting for available connection");
 
-            let result = self.condvar.wait_timeout(inner, timeout - start.elapsed());
+            let result = self
+                .condvar
+                .wait_timeout(inner, timeout - start.elapsed())
+                .unwrap_or_else(PoisonError::into_inner);
             inner = result.0;
             inner.waiting -= 1;
 
@@ -285,7 +362,7 @@ impl ConnectionPool {
         if current_total < inner.config.min_connections {
             let to_create = inner.config.min_connections - current_total;
             for _ in 0..to_create {
-                if let Ok(conn) = self.create_connection(&inner.database_path) {
+                if let Ok(conn) = Self::create_connection(&inner.config, &inner.database_path) {
                     let pooled_conn = PooledConnection::new(conn);
                     inner.available.push_back(pooled_conn);
                     inner.stats.connections_created += 1;
@@ -320,13 +397,41 @@ impl std::ops::Deref for PooledConnectionGuard {
     }
 }
 
+/// Reset a released connection, returning whether it is safe to reuse
+///
+/// A connection that still has a transaction open after the `after_release`
+/// hook is rolled back; if that fails it is never handed to another caller.
+fn reset_released_connection(connection: &Connection, after_release: Option<ConnectionHook>) -> bool {
+    if let Some(after_release) = after_release {
+        if let Err(e) = after_release(connection) {
+            warn!("after_release hook failed, discarding connection: {}", e);
+            return false;
+        }
+    }
+
+    if !connection.is_autocommit() {
+        warn!("Connection returned with an open transaction, rolling back");
+        if let Err(e) = connection.rollback_open_transaction() {
+            warn!("Failed to roll back dangling transaction: {}", e);
+            return false;
+        }
+    }
+
+    connection.is_autocommit()
+}
+
 impl Drop for PooledConnectionGuard {
     fn drop(&mut self) {
         if let Some(connection) = self.connection.take() {
+            // Run the release hook without holding the pool lock
+            let after_release = self.pool.lock().unwrap().config.after_release.clone();
+            let reusable = reset_released_connection(&connection, after_release);
+
             let mut inner = self.pool.lock().unwrap();
 
             // Check if we should keep this connection
-            let should_keep = inner.available.len() < inner.config.max_connections as usize
+            let should_keep = reusable
+                && inner.available.len() < inner.config.max_connections as usize
                 && inner.active_count + inner.available.len() as u32 >= inner.config.min_connections;
 
             if should_keep {
@@ -353,6 +458,7 @@ impl Drop for PooledConnectionGuard {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use std::thread;
 
     #[test]
@@ -419,6 +525,83 @@ mod tests {
         assert!(stats.connections_created <= 4);
     }
 
+    #[test]
+    fn test_after_connect_hook() {
+        let opened = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&opened);
+
+        let config = PoolConfig {
+            min_connections: 2,
+            max_connections: 3,
+            ..Default::default()
+        }
+        .after_connect(move |conn| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            conn.execute("CREATE TABLE IF NOT EXISTS session (id INTEGER)")
+        });
+
+        let pool = ConnectionPool::new(None, config).unwrap();
+        assert_eq!(opened.load(Ordering::SeqCst), 2);
+
+        let _conn1 = pool.get_connection().unwrap();
+        let _conn2 = pool.get_connection().unwrap();
+        let _conn3 = pool.get_connection().unwrap();
+        assert_eq!(opened.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_after_connect_failure_fails_pool_creation() {
+        let config = PoolConfig::default()
+            .after_connect(|_| Err(Error::database("attach failed")));
+
+        assert!(ConnectionPool::new(None, config).is_err());
+    }
+
+    #[test]
+    fn test_before_acquire_rejects_connection() {
+        let config = PoolConfig::default()
+            .before_acquire(|_| Err(Error::pool_error("stale session")));
+
+        let pool = ConnectionPool::new(None, config).unwrap();
+        let _conn = pool.get_connection().unwrap();
+
+        // The idle connection was rejected and a fresh one opened instead
+        let stats = pool.stats();
+        assert_eq!(stats.connections_destroyed, 1);
+        assert_eq!(stats.connections_created, 2);
+    }
+
+    #[test]
+    fn test_after_release_failure_discards_connection() {
+        let config = PoolConfig::default()
+            .after_release(|_| Err(Error::database("reset failed")));
+
+        let pool = ConnectionPool::new(None, config).unwrap();
+        drop(pool.get_connection().unwrap());
+
+        let stats = pool.stats();
+        assert_eq!(stats.idle_connections, 0);
+        assert_eq!(stats.connections_destroyed, 1);
+    }
+
+    #[test]
+    fn test_released_connection_has_no_open_transaction() {
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        let path = temp_file.path().to_string_lossy().to_string();
+        let pool = ConnectionPool::new_default(Some(&path)).unwrap();
+
+        {
+            let conn = pool.get_connection().unwrap();
+            conn.execute("CREATE TABLE test (id INTEGER)").unwrap();
+            let tx = conn.begin_transaction().unwrap();
+            std::mem::forget(tx); // Leak the guard so nothing rolls back
+            assert!(!conn.is_autocommit());
+        }
+
+        let conn = pool.get_connection().unwrap();
+        assert!(conn.is_autocommit());
+    }
+
     #[test]
     fn test_pool_maintenance() {
         let config = PoolConfig {
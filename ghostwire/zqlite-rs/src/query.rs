@@ -0,0 +1,674 @@
+//! Builders for parameterized statements in ZQLite's SQL dialect
+//!
+//! Values are always bound as `?` parameters. Table and column names cannot
+//! be bound, and ZQLite has no quoted identifiers, so every name is checked
+//! against `[A-Za-z_][A-Za-z0-9_]*` and rejected if it is a keyword.
+
+use crate::{Connection, Error, PreparedStatement, Result, Rows, ToSql, ToSqlOutput, Value};
+use std::fmt::Write as _;
+use std::sync::Arc;
+
+/// Words the ZQLite tokenizer treats as keywords rather than identifiers
+const KEYWORDS: &[&str] = &[
+    "AND", "AS", "ASC", "AUTOINCREMENT", "AVG", "BEGIN", "BY", "CASCADE", "CHECK", "COMMIT",
+    "COUNT", "CREATE", "CURRENT_DATE", "CURRENT_TIME", "CURRENT_TIMESTAMP", "DEFAULT", "DELETE",
+    "DESC", "DISTINCT", "DROP", "EXISTS", "FOREIGN", "FROM", "FULL", "GROUP", "HAVING", "IF",
+    "IGNORE", "IN", "INDEX", "INNER", "INSERT", "INTO", "JOIN", "KEY", "LEFT", "LIKE", "LIMIT",
+    "MAX", "MIN", "NOT", "NULL", "OFFSET", "ON", "OR", "ORDER", "OUTER", "PRIMARY", "REFERENCES",
+    "REPLACE", "RESTRICT", "RIGHT", "ROLLBACK", "SELECT", "SET", "SUM", "TABLE", "TRANSACTION",
+    "UNIQUE", "UPDATE", "VALUES", "WHERE",
+];
+
+/// Check that `name` can be used as a table or column name
+pub fn validate_identifier(name: &str) -> Result<&str> {
+    let mut chars = name.chars();
+    let valid_start = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+    let valid_rest = chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    let keyword = KEYWORDS.iter().any(|k| k.eq_ignore_ascii_case(name));
+
+    if valid_start && valid_rest && !keyword {
+        Ok(name)
+    } else {
+        Err(Error::InvalidIdentifier(name.to_string()))
+    }
+}
+
+pub(crate) fn to_value<T: ToSql + ?Sized>(value: &T) -> Result<Value> {
+    Ok(match value.to_sql()? {
+        ToSqlOutput::Borrowed(value) => Value::from(value),
+        ToSqlOutput::Owned(value) => value,
+    })
+}
+
+/// A value converted when it was handed to a builder, or why that failed
+///
+/// The error is reported by `build()`. It is shared rather than cloned so the
+/// builders stay `Clone`.
+type Pending = std::result::Result<Value, Arc<Error>>;
+
+fn pending<T: ToSql + ?Sized>(value: &T) -> Pending {
+    to_value(value).map_err(Arc::new)
+}
+
+fn resolve(value: Pending) -> Result<Value> {
+    value.map_err(|error| {
+        // Still shared only while a clone of the builder is alive
+        Arc::try_unwrap(error).unwrap_or_else(|shared| Error::database(shared.to_string()))
+    })
+}
+
+/// SQL text with the values for its `?` parameters, in order
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    sql: String,
+    params: Vec<Value>,
+}
+
+impl Query {
+    #[cfg(feature = "macros")]
+    pub(crate) fn new(sql: String, params: Vec<Value>) -> Self {
+        Self { sql, params }
+    }
+
+    /// The generated SQL
+    pub fn sql(&self) -> &str {
+        &self.sql
+    }
+
+    /// Values for the `?` parameters
+    pub fn params(&self) -> &[Value] {
+        &self.params
+    }
+
+    /// Split into SQL and parameters
+    pub fn into_parts(self) -> (String, Vec<Value>) {
+        (self.sql, self.params)
+    }
+}
+
+/// Start a condition on a column
+pub fn col(name: &str) -> Column {
+    Column(name.to_string())
+}
+
+/// A column used on the left of a condition
+#[derive(Debug, Clone)]
+pub struct Column(String);
+
+impl Column {
+    fn compare<T: ToSql + ?Sized>(self, op: &'static str, value: &T) -> Condition {
+        Condition {
+            function: None,
+            column: self.0,
+            op,
+            value: pending(value),
+        }
+    }
+
+    /// `column = value`
+    pub fn eq<T: ToSql + ?Sized>(self, value: &T) -> Condition {
+        self.compare("=", value)
+    }
+
+    /// `column != value`
+    pub fn ne<T: ToSql + ?Sized>(self, value: &T) -> Condition {
+        self.compare("!=", value)
+    }
+
+    /// `column < value`
+    pub fn lt<T: ToSql + ?Sized>(self, value: &T) -> Condition {
+        self.compare("<", value)
+    }
+
+    /// `column <= value`
+    pub fn le<T: ToSql + ?Sized>(self, value: &T) -> Condition {
+        self.compare("<=", value)
+    }
+
+    /// `column > value`
+    pub fn gt<T: ToSql + ?Sized>(self, value: &T) -> Condition {
+        self.compare(">", value)
+    }
+
+    /// `column >= value`
+    pub fn ge<T: ToSql + ?Sized>(self, value: &T) -> Condition {
+        self.compare(">=", value)
+    }
+
+    /// `column LIKE pattern`
+    pub fn like(self, pattern: &str) -> Condition {
+        self.compare("LIKE", pattern)
+    }
+
+    /// `function(column, value)`, for a function that returns a boolean
+    ///
+    /// Typically a function registered with
+    /// [`Connection::create_scalar_function`](crate::Connection::create_scalar_function).
+    pub fn call<T: ToSql + ?Sized>(self, function: &str, value: &T) -> Condition {
+        Condition {
+            function: Some(function.to_string()),
+            ..self.compare("", value)
+        }
+    }
+}
+
+/// One comparison in a WHERE clause
+#[derive(Debug, Clone)]
+pub struct Condition {
+    function: Option<String>,
+    column: String,
+    op: &'static str,
+    value: Pending,
+}
+
+/// Sort direction for ORDER BY
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    /// Smallest first
+    Asc,
+    /// Largest first
+    Desc,
+}
+
+/// Conditions joined with AND
+///
+/// ZQLite evaluates WHERE left to right without precedence or parentheses, so
+/// only AND chains are built.
+#[derive(Debug, Clone, Default)]
+struct Filter(Vec<Condition>);
+
+impl Filter {
+    fn render(self, sql: &mut String, params: &mut Vec<Value>) -> Result<()> {
+        for (i, condition) in self.0.into_iter().enumerate() {
+            sql.push_str(if i == 0 { " WHERE " } else { " AND " });
+            let column = validate_identifier(&condition.column)?;
+            match &condition.function {
+                Some(function) => {
+                    let _ = write!(sql, "{}({}, ?)", validate_identifier(function)?, column);
+                }
+                None => {
+                    let _ = write!(sql, "{} {} ?", column, condition.op);
+                }
+            }
+            params.push(resolve(condition.value)?);
+        }
+        Ok(())
+    }
+}
+
+macro_rules! filter_methods {
+    () => {
+        /// Add a condition, joined to earlier ones with AND
+        pub fn filter(mut self, condition: Condition) -> Self {
+            self.filter.0.push(condition);
+            self
+        }
+
+        /// Add a condition only when `enabled` is true
+        pub fn filter_if(self, enabled: bool, condition: Condition) -> Self {
+            if enabled {
+                self.filter(condition)
+            } else {
+                self
+            }
+        }
+
+        /// Add a condition built from `value` only when it is `Some`
+        pub fn filter_opt<T>(self, value: Option<T>, condition: impl FnOnce(T) -> Condition) -> Self {
+            match value {
+                Some(value) => self.filter(condition(value)),
+                None => self,
+            }
+        }
+    };
+}
+
+/// Start a SELECT from `table`
+pub fn select(table: &str) -> Select {
+    Select {
+        table: table.to_string(),
+        columns: Vec::new(),
+        filter: Filter::default(),
+        order_by: Vec::new(),
+        limit: None,
+        offset: None,
+    }
+}
+
+/// Builder for a SELECT statement
+#[derive(Debug, Clone)]
+pub struct Select {
+    table: String,
+    columns: Vec<String>,
+    filter: Filter,
+    order_by: Vec<(String, Order)>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+}
+
+impl Select {
+    /// Columns to return; all columns when none are given
+    pub fn columns<I, S>(mut self, columns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.columns
+            .extend(columns.into_iter().map(|c| c.as_ref().to_string()));
+        self
+    }
+
+    filter_methods!();
+
+    /// Sort by `column`; later calls break ties
+    pub fn order_by(mut self, column: &str, order: Order) -> Self {
+        self.order_by.push((column.to_string(), order));
+        self
+    }
+
+    /// Return at most `limit` rows
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Skip the first `offset` rows
+    pub fn offset(mut self, offset: u32) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Generate the statement, failing on the first invalid name or value
+    pub fn build(self) -> Result<Query> {
+        let mut sql = String::from("SELECT ");
+        let mut params = Vec::new();
+
+        if self.columns.is_empty() {
+            sql.push('*');
+        }
+        for (i, column) in self.columns.iter().enumerate() {
+            if i > 0 {
+                sql.push_str(", ");
+            }
+            sql.push_str(validate_identifier(column)?);
+        }
+        let _ = write!(sql, " FROM {}", validate_identifier(&self.table)?);
+
+        self.filter.render(&mut sql, &mut params)?;
+
+        for (i, (column, order)) in self.order_by.iter().enumerate() {
+            sql.push_str(if i == 0 { " ORDER BY " } else { ", " });
+            sql.push_str(validate_identifier(column)?);
+            if *order == Order::Desc {
+                sql.push_str(" DESC");
+            }
+        }
+
+        // ZQLite only accepts integer literals here
+        if let Some(limit) = self.limit {
+            let _ = write!(sql, " LIMIT {}", limit);
+        }
+        if let Some(offset) = self.offset {
+            let _ = write!(sql, " OFFSET {}", offset);
+        }
+
+        Ok(Query { sql, params })
+    }
+}
+
+/// Start an INSERT into `table`
+pub fn insert_into(table: &str) -> Insert {
+    Insert {
+        table: table.to_string(),
+        values: Vec::new(),
+        or_conflict: None,
+    }
+}
+
+/// Builder for an INSERT statement
+#[derive(Debug, Clone)]
+pub struct Insert {
+    table: String,
+    values: Vec<(String, Pending)>,
+    /// Conflict resolution, `REPLACE` or `IGNORE`
+    or_conflict: Option<&'static str>,
+}
+
+impl Insert {
+    /// Set `column` to `value`
+    pub fn value<T: ToSql + ?Sized>(mut self, column: &str, value: &T) -> Self {
+        self.values.push((column.to_string(), pending(value)));
+        self
+    }
+
+    /// Replace an existing row with the same key instead of failing
+    pub fn or_replace(mut self) -> Self {
+        self.or_conflict = Some("REPLACE");
+        self
+    }
+
+    /// Skip the row when its key is taken instead of failing
+    pub fn or_ignore(mut self) -> Self {
+        self.or_conflict = Some("IGNORE");
+        self
+    }
+
+    /// Generate the statement, failing on the first invalid name or value
+    pub fn build(self) -> Result<Query> {
+        if self.values.is_empty() {
+            return Err(Error::InvalidSql);
+        }
+
+        let mut sql = match self.or_conflict {
+            Some(resolution) => format!("INSERT OR {} INTO ", resolution),
+            None => String::from("INSERT INTO "),
+        };
+        let mut params = Vec::with_capacity(self.values.len());
+
+        sql.push_str(validate_identifier(&self.table)?);
+        sql.push_str(" (");
+        for (i, (column, value)) in self.values.into_iter().enumerate() {
+            if i > 0 {
+                sql.push_str(", ");
+            }
+            sql.push_str(validate_identifier(&column)?);
+            params.push(resolve(value)?);
+        }
+        sql.push_str(") VALUES (");
+        sql.push_str(&vec!["?"; params.len()].join(", "));
+        sql.push(')');
+
+        Ok(Query { sql, params })
+    }
+}
+
+/// Start an UPDATE of `table`
+pub fn update(table: &str) -> Update {
+    Update {
+        table: table.to_string(),
+        assignments: Vec::new(),
+        filter: Filter::default(),
+    }
+}
+
+/// Builder for an UPDATE statement
+#[derive(Debug, Clone)]
+pub struct Update {
+    table: String,
+    assignments: Vec<(String, Pending)>,
+    filter: Filter,
+}
+
+impl Update {
+    /// Set `column` to `value`
+    pub fn set<T: ToSql + ?Sized>(mut self, column: &str, value: &T) -> Self {
+        self.assignments.push((column.to_string(), pending(value)));
+        self
+    }
+
+    filter_methods!();
+
+    /// Generate the statement, failing on the first invalid name or value
+    pub fn build(self) -> Result<Query> {
+        if self.assignments.is_empty() {
+            return Err(Error::InvalidSql);
+        }
+
+        let mut sql = format!("UPDATE {} SET ", validate_identifier(&self.table)?);
+        let mut params = Vec::new();
+
+        for (i, (column, value)) in self.assignments.into_iter().enumerate() {
+            if i > 0 {
+                sql.push_str(", ");
+            }
+            let _ = write!(sql, "{} = ?", validate_identifier(&column)?);
+            params.push(resolve(value)?);
+        }
+
+        self.filter.render(&mut sql, &mut params)?;
+        Ok(Query { sql, params })
+    }
+}
+
+/// Start a DELETE from `table`
+pub fn delete_from(table: &str) -> Delete {
+    Delete {
+        table: table.to_string(),
+        filter: Filter::default(),
+    }
+}
+
+/// Builder for a DELETE statement
+#[derive(Debug, Clone)]
+pub struct Delete {
+    table: String,
+    filter: Filter,
+}
+
+impl Delete {
+    filter_methods!();
+
+    /// Generate the statement, failing on the first invalid name or value
+    pub fn build(self) -> Result<Query> {
+        let mut sql = format!("DELETE FROM {}", validate_identifier(&self.table)?);
+        let mut params = Vec::new();
+
+        self.filter.render(&mut sql, &mut params)?;
+        Ok(Query { sql, params })
+    }
+}
+
+impl Connection {
+    /// Prepare a built query and bind its parameters
+    pub fn prepare_query(&self, query: &Query) -> Result<PreparedStatement> {
+        let mut stmt = self.prepare(&query.sql)?;
+        for (index, value) in query.params.iter().enumerate() {
+            stmt.bind(index, value)?;
+        }
+        Ok(stmt)
+    }
+
+    /// Execute a built query without returning results
+    pub fn execute_query(&self, query: &Query) -> Result<()> {
+        self.prepare_query(query)?.execute()
+    }
+
+    /// Run a built query and return results
+    pub fn fetch_query(&self, query: &Query) -> Result<Rows> {
+        let mut stmt = self.prepare_query(query)?;
+        self.query_prepared(&mut stmt)
+    }
+
+    /// Execute built queries in order in a transaction, rolling back if any fails
+    ///
+    /// A failure is reported as [`Error::Batch`] with the failing query's index.
+    pub fn execute_queries_in_transaction(&self, queries: &[Query]) -> Result<()> {
+        let tx = self.begin_transaction()?;
+        for (index, query) in queries.iter().enumerate() {
+            self.execute_query(query).map_err(|source| Error::Batch {
+                index,
+                sql: query.sql.clone(),
+                source: Box::new(source),
+            })?;
+        }
+        tx.commit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identifier_validation() {
+        assert!(validate_identifier("peer_id").is_ok());
+        assert!(validate_identifier("_rowid2").is_ok());
+
+        for bad in ["", "2fast", "id; DROP TABLE peers", "name'", "peers.id", "select", "Order"] {
+            assert!(
+                matches!(validate_identifier(bad), Err(Error::InvalidIdentifier(_))),
+                "{:?} should be rejected",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn test_select_with_conditional_filters() {
+        let name: Option<&str> = None;
+        let query = select("peers")
+            .columns(["id", "assigned_ip"])
+            .filter(col("last_seen").ge(&1_700_000_000i64))
+            .filter_if(false, col("id").eq("ignored"))
+            .filter_opt(name, |name| col("name").like(name))
+            .order_by("created_at", Order::Desc)
+            .order_by("id", Order::Asc)
+            .limit(50)
+            .offset(100)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            query.sql(),
+            "SELECT id, assigned_ip FROM peers WHERE last_seen >= ? \
+             ORDER BY created_at DESC, id LIMIT 50 OFFSET 100"
+        );
+        assert_eq!(query.params(), &[Value::Integer(1_700_000_000)]);
+    }
+
+    #[test]
+    fn test_function_condition() {
+        let query = select("acl_rules")
+            .columns(["id"])
+            .filter(col("source_cidr").call("cidr_contains", "10.0.0.7"))
+            .filter(col("priority").gt(&0i64))
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            query.sql(),
+            "SELECT id FROM acl_rules WHERE cidr_contains(source_cidr, ?) AND priority > ?"
+        );
+        assert_eq!(query.params(), &[Value::from("10.0.0.7"), Value::Integer(0)]);
+
+        let bad = select("acl_rules").filter(col("source_cidr").call("f; DROP", "x")).build();
+        assert!(bad.is_err());
+    }
+
+    #[test]
+    fn test_values_are_never_inlined() {
+        let query = delete_from("peers")
+            .filter(col("id").eq("x' OR '1' = '1"))
+            .build()
+            .unwrap();
+
+        assert_eq!(query.sql(), "DELETE FROM peers WHERE id = ?");
+        assert_eq!(query.params(), &[Value::from("x' OR '1' = '1")]);
+    }
+
+    #[test]
+    fn test_invalid_names_fail_build() {
+        assert!(select("peers").columns(["id, secret"]).build().is_err());
+        assert!(update("peers")
+            .set("last_seen", &1i64)
+            .filter(col("1=1 OR id").eq(&0i64))
+            .build()
+            .is_err());
+        assert!(insert_into("peers; DROP TABLE peers").value("id", &1i64).build().is_err());
+        assert!(update("peers").build().is_err());
+    }
+
+    #[test]
+    fn test_conversion_error_reported_by_build() {
+        let insert = insert_into("health").value("rx", &u64::MAX);
+        let copy = insert.clone();
+
+        assert!(matches!(copy.build(), Err(Error::Database(_))));
+        assert!(matches!(insert.build(), Err(Error::TypeMismatch { .. })));
+    }
+
+    #[test]
+    fn test_insert_and_update_execute() {
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE health (peer_id TEXT, latency REAL, rx INTEGER)")
+            .unwrap();
+
+        let insert = insert_into("health")
+            .or_replace()
+            .value("peer_id", "a")
+            .value("latency", &Option::<f64>::None)
+            .value("rx", &10i64)
+            .build()
+            .unwrap();
+        assert_eq!(
+            insert.sql(),
+            "INSERT OR REPLACE INTO health (peer_id, latency, rx) VALUES (?, ?, ?)"
+        );
+        conn.execute_query(&insert).unwrap();
+
+        let update = update("health")
+            .set("rx", &20i64)
+            .filter(col("peer_id").eq("a"))
+            .build()
+            .unwrap();
+        assert_eq!(update.sql(), "UPDATE health SET rx = ? WHERE peer_id = ?");
+        assert_eq!(update.params(), &[Value::Integer(20), Value::from("a")]);
+        conn.execute_query(&update).unwrap();
+
+        let select = select("health")
+            .columns(["rx"])
+            .filter(col("peer_id").eq("a"))
+            .build()
+            .unwrap();
+        let rx: Vec<i64> = conn
+            .fetch_query(&select)
+            .unwrap()
+            .iter()
+            .map(|row| row.get(0).unwrap())
+            .collect();
+        assert_eq!(rx, [20]);
+    }
+
+    #[test]
+    fn test_insert_or_ignore_keeps_existing_row() {
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE identities (subject TEXT PRIMARY KEY, user_id TEXT)")
+            .unwrap();
+
+        for user_id in ["first", "second"] {
+            let insert = insert_into("identities")
+                .or_ignore()
+                .value("subject", "alice")
+                .value("user_id", user_id)
+                .build()
+                .unwrap();
+            assert!(insert.sql().starts_with("INSERT OR IGNORE INTO identities"));
+            conn.execute_query(&insert).unwrap();
+        }
+
+        let select = select("identities").columns(["user_id"]).build().unwrap();
+        let users: Vec<String> = conn
+            .fetch_query(&select)
+            .unwrap()
+            .iter()
+            .map(|row| row.get(0).unwrap())
+            .collect();
+        assert_eq!(users, ["first"]);
+    }
+
+    #[test]
+    fn test_execute_queries_reports_failing_query() {
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE health (peer_id TEXT)").unwrap();
+
+        let queries = [
+            insert_into("health").value("peer_id", "a").build().unwrap(),
+            insert_into("missing").value("peer_id", "b").build().unwrap(),
+        ];
+        let err = conn.execute_queries_in_transaction(&queries).unwrap_err();
+        assert!(matches!(err, Error::Batch { index: 1, .. }));
+        assert!(conn.is_autocommit());
+    }
+}
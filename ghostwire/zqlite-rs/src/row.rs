@@ -1,28 +1,49 @@
 //! Row and result set handling for ZQLite
 
-use crate::{zqlite_result_t, Error, Result};
+use crate::ffi::{self, zqlite_result_t};
+use crate::value::{Type, Value, ValueRef};
+use crate::{Error, Result};
+use indexmap::IndexMap;
 use std::ffi::CStr;
-use std::os::raw::{c_char, c_int};
+use std::os::raw::c_int;
 
 /// A set of rows returned from a query
+///
+/// Iterate with [`Rows::iter`] or `for row in &rows`; each [`Row`] borrows
+/// from the result set and reads its values in place.
 pub struct Rows {
     inner: *mut zqlite_result_t,
     row_count: usize,
     column_count: usize,
-    current_row: usize,
+    lenient: bool,
+    // None where ZQLite has no name
+    column_names: Vec<Option<String>>,
 }
 
+// Safety: the result is a copy ZQLite made for this `Rows` alone. It is not
+// tied to the connection or statement that produced it, is only read through
+// `&self`, and is freed exactly once, in `Drop`, from whichever thread owns it.
+unsafe impl Send for Rows {}
+
 impl Rows {
     /// Create a new Rows from a ZQLite result pointer
-    pub(crate) fn new(result_ptr: *mut zqlite_result_t) -> Self {
-        let row_count = unsafe { crate::zqlite_result_row_count(result_ptr) as usize };
-        let column_count = unsafe { crate::zqlite_result_column_count(result_ptr) as usize };
+    pub(crate) fn new(result_ptr: *mut zqlite_result_t, lenient: bool) -> Self {
+        let row_count = unsafe { ffi::zqlite_result_row_count(result_ptr) as usize };
+        let column_count = unsafe { ffi::zqlite_result_column_count(result_ptr) as usize };
+        let column_names = (0..column_count)
+            .map(|column| {
+                let name_ptr = unsafe { ffi::zqlite_result_column_name(result_ptr, column as c_int) };
+                (!name_ptr.is_null())
+                    .then(|| unsafe { CStr::from_ptr(name_ptr).to_string_lossy().into_owned() })
+            })
+            .collect();
 
         Self {
             inner: result_ptr,
             row_count,
             column_count,
-            current_row: 0,
+            lenient,
+            column_names,
         }
     }
 
@@ -38,18 +59,11 @@ impl Rows {
 
     /// Get the column name at the specified index
     pub fn column_name(&self, column: usize) -> Result<String> {
-        if column >= self.column_count {
-            return Err(Error::index_out_of_bounds(column));
-        }
-
-        let name_ptr = unsafe { crate::zqlite_result_column_name(self.inner, column as c_int) };
-
-        if name_ptr.is_null() {
-            return Err(Error::NullPointer);
+        match self.column_names.get(column) {
+            Some(Some(name)) => Ok(name.clone()),
+            Some(None) => Err(Error::NullPointer),
+            None => Err(Error::index_out_of_bounds(column)),
         }
-
-        let name = unsafe { CStr::from_ptr(name_ptr).to_string_lossy().into_owned() };
-        Ok(name)
     }
 
     /// Get all column names
@@ -60,74 +74,97 @@ impl Rows {
         }
         Ok(names)
     }
+
+    /// Get the row at `index`, if there is one
+    pub fn get(&self, index: usize) -> Option<Row<'_>> {
+        (index < self.row_count).then_some(Row { rows: self, index })
+    }
+
+    /// Iterate over the rows in order
+    pub fn iter(&self) -> RowIter<'_> {
+        RowIter {
+            rows: self,
+            next: 0,
+        }
+    }
 }
 
-impl Iterator for Rows {
-    type Item = Row;
+impl<'a> IntoIterator for &'a Rows {
+    type Item = Row<'a>;
+    type IntoIter = RowIter<'a>;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.current_row >= self.row_count {
-            return None;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl Drop for Rows {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::zqlite_result_free(self.inner);
         }
+    }
+}
 
-        let row = Row {
-            result: self.inner,
-            row_index: self.current_row,
-            column_count: self.column_count,
-        };
+/// Iterator over the rows of a [`Rows`]
+#[derive(Clone)]
+pub struct RowIter<'a> {
+    rows: &'a Rows,
+    next: usize,
+}
+
+impl<'a> Iterator for RowIter<'a> {
+    type Item = Row<'a>;
 
-        self.current_row += 1;
+    fn next(&mut self) -> Option<Self::Item> {
+        let row = self.rows.get(self.next)?;
+        self.next += 1;
         Some(row)
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let remaining = self.row_count.saturating_sub(self.current_row);
+        let remaining = self.rows.row_count.saturating_sub(self.next);
         (remaining, Some(remaining))
     }
 }
 
-impl ExactSizeIterator for Rows {
+impl ExactSizeIterator for RowIter<'_> {
     fn len(&self) -> usize {
-        self.row_count.saturating_sub(self.current_row)
-    }
-}
-
-impl Drop for Rows {
-    fn drop(&mut self) {
-        unsafe {
-            crate::zqlite_result_free(self.inner);
-        }
+        self.rows.row_count.saturating_sub(self.next)
     }
 }
 
 /// A single row in a result set
-pub struct Row {
-    result: *mut zqlite_result_t,
-    row_index: usize,
-    column_count: usize,
+///
+/// The row borrows from the [`Rows`] it came from and reads each value when
+/// asked, so it cannot outlive the result set.
+#[derive(Clone, Copy)]
+pub struct Row<'a> {
+    rows: &'a Rows,
+    index: usize,
 }
 
-impl Row {
+impl<'a> Row<'a> {
     /// Get a value from the row by column index
+    ///
+    /// Fails with [`Error::TypeMismatch`] naming the column if the value does
+    /// not convert to `T`.
     pub fn get<T: FromSql>(&self, column: usize) -> Result<T> {
-        if column >= self.column_count {
-            return Err(Error::index_out_of_bounds(column));
-        }
+        let value = self.get_ref(column)?;
+        let converted = if self.rows.lenient {
+            T::from_sql_lenient(value)
+        } else {
+            T::from_sql(value)
+        };
 
-        T::from_sql(self.result, self.row_index, column)
+        converted.map_err(|e| e.in_column(column, self.column_name(column).ok()))
     }
 
     /// Get a value from the row by column name
     pub fn get_by_name<T: FromSql>(&self, column_name: &str) -> Result<T> {
         // Find column index by name
-        for i in 0..self.column_count {
-            let name_ptr = unsafe { crate::zqlite_result_column_name(self.result, i as c_int) };
-            if !name_ptr.is_null() {
-                let name = unsafe { CStr::from_ptr(name_ptr).to_string_lossy() };
-                if name == column_name {
-                    return self.get(i);
-                }
-            }
+        if let Some(i) = self.rows.column_names.iter().position(|name| name.as_deref() == Some(column_name)) {
+            return self.get(i);
         }
 
         Err(Error::row_error(format!(
@@ -136,169 +173,276 @@ impl Row {
         )))
     }
 
+    /// Borrow a column's value from the result set
+    pub fn get_ref(&self, column: usize) -> Result<ValueRef<'a>> {
+        if column >= self.rows.column_count {
+            return Err(Error::index_out_of_bounds(column));
+        }
+
+        // Safety: the row and column are in bounds, and the borrow of `Rows`
+        // keeps the result alive for 'a
+        unsafe { value_ref(self.rows.inner, self.index, column) }
+    }
+
+    /// Get the storage class of a column's value in this row
+    pub fn column_type(&self, column: usize) -> Result<Type> {
+        Ok(self.get_ref(column)?.data_type())
+    }
+
+    /// Get the column name at the specified index
+    pub fn column_name(&self, column: usize) -> Result<String> {
+        self.rows.column_name(column)
+    }
+
+    /// Copy the row into a map from column name to value, in column order
+    pub fn to_map(&self) -> Result<IndexMap<String, Value>> {
+        let mut map = IndexMap::with_capacity(self.rows.column_count);
+        for i in 0..self.rows.column_count {
+            map.insert(self.column_name(i)?, Value::from(self.get_ref(i)?));
+        }
+        Ok(map)
+    }
+
     /// Get the number of columns in this row
     pub fn column_count(&self) -> usize {
-        self.column_count
+        self.rows.column_count
     }
 
     /// Check if a column value is null
     pub fn is_null(&self, column: usize) -> Result<bool> {
-        if column >= self.column_count {
-            return Err(Error::index_out_of_bounds(column));
-        }
+        Ok(self.get_ref(column)?.is_null())
+    }
+}
 
-        let column_type = unsafe {
-            crate::zqlite_result_column_type(
-                self.result,
-                self.row_index as c_int,
-                column as c_int,
-            )
-        };
+/// Borrow one cell of a result set
+///
+/// # Safety
+///
+/// `result` must be a live result and `row`/`column` within its bounds; the
+/// returned slices are valid until the result is freed.
+unsafe fn value_ref<'a>(result: *mut zqlite_result_t, row: usize, column: usize) -> Result<ValueRef<'a>> {
+    let (row, column) = (row as c_int, column as c_int);
+    let column_type = ffi::zqlite_result_column_type(result, row, column);
+
+    let value = match column_type {
+        x if x == ffi::ZQLITE_NULL as c_int => ValueRef::Null,
+        x if x == ffi::ZQLITE_INTEGER as c_int => {
+            ValueRef::Integer(ffi::zqlite_result_get_int(result, row, column))
+        }
+        x if x == ffi::ZQLITE_FLOAT as c_int => {
+            ValueRef::Real(ffi::zqlite_result_get_real(result, row, column))
+        }
+        x if x == ffi::ZQLITE_TEXT as c_int => {
+            let text_ptr = ffi::zqlite_result_get_text(result, row, column);
+            if text_ptr.is_null() {
+                return Err(Error::NullPointer);
+            }
+            ValueRef::Text(CStr::from_ptr(text_ptr).to_bytes())
+        }
+        x if x == ffi::ZQLITE_BLOB as c_int => {
+            let mut size: c_int = 0;
+            let blob_ptr = ffi::zqlite_result_get_blob(result, row, column, &mut size);
+            if blob_ptr.is_null() || size <= 0 {
+                ValueRef::Blob(&[])
+            } else {
+                ValueRef::Blob(std::slice::from_raw_parts(blob_ptr as *const u8, size as usize))
+            }
+        }
+        other => {
+            return Err(Error::row_error(format!(
+                "Unknown column type {} in column {}",
+                other, column
+            )))
+        }
+    };
 
-        Ok(column_type == crate::ZQLITE_NULL as c_int)
-    }
+    Ok(value)
 }
 
 /// Trait for types that can be extracted from SQL result columns
+///
+/// Conversions are strict: the value's storage class must match and integers
+/// must fit the target type. Rows from a connection with
+/// [`lenient conversions`](crate::Connection::set_lenient_conversions) use
+/// [`FromSql::from_sql_lenient`] instead.
 pub trait FromSql: Sized {
-    /// Extract a value from a SQL result
-    fn from_sql(result: *mut zqlite_result_t, row: usize, column: usize) -> Result<Self>;
-}
-
-impl FromSql for String {
-    fn from_sql(result: *mut zqlite_result_t, row: usize, column: usize) -> Result<Self> {
-        let text_ptr = unsafe {
-            crate::zqlite_result_get_text(result, row as c_int, column as c_int)
-        };
-
-        if text_ptr.is_null() {
-            return Err(Error::NullPointer);
-        }
+    /// Convert a value whose storage class matches the target type
+    fn from_sql(value: ValueRef<'_>) -> Result<Self>;
 
-        let text = unsafe { CStr::from_ptr(text_ptr).to_string_lossy().into_owned() };
-        Ok(text)
+    /// Convert a value after SQLite-style affinity coercion, e.g. numeric
+    /// text to a number or a number to text
+    fn from_sql_lenient(value: ValueRef<'_>) -> Result<Self> {
+        Self::from_sql(value)
     }
 }
 
-impl FromSql for Option<String> {
-    fn from_sql(result: *mut zqlite_result_t, row: usize, column: usize) -> Result<Self> {
-        let column_type = unsafe {
-            crate::zqlite_result_column_type(result, row as c_int, column as c_int)
-        };
+fn mismatch(expected: &str, actual: ValueRef<'_>) -> Error {
+    Error::type_mismatch(expected, actual.type_name())
+}
 
-        if column_type == crate::ZQLITE_NULL as c_int {
-            return Ok(None);
+/// INTEGER affinity: reals and numeric text holding an exact integer become
+/// integers, anything else is left as it is
+fn integer_affinity(value: ValueRef<'_>) -> ValueRef<'_> {
+    let exact = |f: f64| {
+        // i64::MAX as f64 rounds up to 2^63, which is already out of range
+        (f.fract() == 0.0 && f >= i64::MIN as f64 && f < i64::MAX as f64).then_some(f as i64)
+    };
+
+    match value {
+        ValueRef::Real(f) => exact(f).map_or(value, ValueRef::Integer),
+        ValueRef::Text(bytes) => {
+            let text = std::str::from_utf8(bytes).unwrap_or("").trim();
+            text.parse::<i64>()
+                .ok()
+                .or_else(|| text.parse::<f64>().ok().and_then(exact))
+                .map_or(value, ValueRef::Integer)
         }
-
-        let text = String::from_sql(result, row, column)?;
-        Ok(Some(text))
+        other => other,
     }
 }
 
-impl FromSql for i64 {
-    fn from_sql(result: *mut zqlite_result_t, row: usize, column: usize) -> Result<Self> {
-        let value = unsafe {
-            crate::zqlite_result_get_int(result, row as c_int, column as c_int)
-        };
-        Ok(value)
+/// REAL affinity: numeric text becomes a real
+fn real_affinity(value: ValueRef<'_>) -> ValueRef<'_> {
+    match value {
+        ValueRef::Text(bytes) => std::str::from_utf8(bytes)
+            .ok()
+            .and_then(|text| text.trim().parse::<f64>().ok())
+            .map_or(value, ValueRef::Real),
+        other => other,
     }
 }
 
-impl FromSql for Option<i64> {
-    fn from_sql(result: *mut zqlite_result_t, row: usize, column: usize) -> Result<Self> {
-        let column_type = unsafe {
-            crate::zqlite_result_column_type(result, row as c_int, column as c_int)
-        };
-
-        if column_type == crate::ZQLITE_NULL as c_int {
-            return Ok(None);
+impl FromSql for i64 {
+    fn from_sql(value: ValueRef<'_>) -> Result<Self> {
+        match value {
+            ValueRef::Integer(i) => Ok(i),
+            other => Err(mismatch("INTEGER", other)),
         }
-
-        let value = i64::from_sql(result, row, column)?;
-        Ok(Some(value))
     }
-}
 
-impl FromSql for i32 {
-    fn from_sql(result: *mut zqlite_result_t, row: usize, column: usize) -> Result<Self> {
-        let value = i64::from_sql(result, row, column)?;
-        Ok(value as i32)
+    fn from_sql_lenient(value: ValueRef<'_>) -> Result<Self> {
+        Self::from_sql(integer_affinity(value))
     }
 }
 
-impl FromSql for Option<i32> {
-    fn from_sql(result: *mut zqlite_result_t, row: usize, column: usize) -> Result<Self> {
-        let value = Option::<i64>::from_sql(result, row, column)?;
-        Ok(value.map(|v| v as i32))
-    }
+/// Narrow an integer, failing instead of truncating
+fn narrow<T: TryFrom<i64>>(value: i64) -> Result<T> {
+    T::try_from(value).map_err(|_| {
+        Error::type_mismatch(
+            std::any::type_name::<T>(),
+            format!("INTEGER {} (out of range)", value),
+        )
+    })
 }
 
-impl FromSql for f64 {
-    fn from_sql(result: *mut zqlite_result_t, row: usize, column: usize) -> Result<Self> {
-        let value = unsafe {
-            crate::zqlite_result_get_real(result, row as c_int, column as c_int)
-        };
-        Ok(value)
-    }
+macro_rules! from_sql_integer {
+    ($($t:ty),*) => {
+        $(
+            impl FromSql for $t {
+                fn from_sql(value: ValueRef<'_>) -> Result<Self> {
+                    narrow(i64::from_sql(value)?)
+                }
+
+                fn from_sql_lenient(value: ValueRef<'_>) -> Result<Self> {
+                    narrow(i64::from_sql_lenient(value)?)
+                }
+            }
+        )*
+    };
 }
 
-impl FromSql for Option<f64> {
-    fn from_sql(result: *mut zqlite_result_t, row: usize, column: usize) -> Result<Self> {
-        let column_type = unsafe {
-            crate::zqlite_result_column_type(result, row as c_int, column as c_int)
-        };
+from_sql_integer!(i8, i16, i32, isize, u8, u16, u32, u64, usize);
 
-        if column_type == crate::ZQLITE_NULL as c_int {
-            return Ok(None);
+impl FromSql for bool {
+    /// Only 0 and 1 are accepted
+    fn from_sql(value: ValueRef<'_>) -> Result<Self> {
+        match value {
+            ValueRef::Integer(0) => Ok(false),
+            ValueRef::Integer(1) => Ok(true),
+            ValueRef::Integer(i) => Err(Error::type_mismatch(
+                "BOOLEAN (0 or 1)",
+                format!("INTEGER {}", i),
+            )),
+            other => Err(mismatch("BOOLEAN (0 or 1)", other)),
         }
+    }
 
-        let value = f64::from_sql(result, row, column)?;
-        Ok(Some(value))
+    /// Any non-zero number is true; also accepts `true`/`false` text
+    fn from_sql_lenient(value: ValueRef<'_>) -> Result<Self> {
+        if let ValueRef::Text(bytes) = value {
+            let text = std::str::from_utf8(bytes).unwrap_or("").trim();
+            if text.eq_ignore_ascii_case("true") {
+                return Ok(true);
+            }
+            if text.eq_ignore_ascii_case("false") {
+                return Ok(false);
+            }
+        }
+
+        match integer_affinity(value) {
+            ValueRef::Integer(i) => Ok(i != 0),
+            ValueRef::Real(f) => Ok(f != 0.0),
+            other => Err(mismatch("BOOLEAN", other)),
+        }
     }
 }
 
-impl FromSql for bool {
-    fn from_sql(result: *mut zqlite_result_t, row: usize, column: usize) -> Result<Self> {
-        let value = i64::from_sql(result, row, column)?;
-        Ok(value != 0)
+impl FromSql for f64 {
+    /// Integers are widened
+    fn from_sql(value: ValueRef<'_>) -> Result<Self> {
+        value.as_f64()
     }
-}
 
-impl FromSql for Option<bool> {
-    fn from_sql(result: *mut zqlite_result_t, row: usize, column: usize) -> Result<Self> {
-        let value = Option::<i64>::from_sql(result, row, column)?;
-        Ok(value.map(|v| v != 0))
+    fn from_sql_lenient(value: ValueRef<'_>) -> Result<Self> {
+        real_affinity(value).as_f64()
     }
 }
 
-impl FromSql for Vec<u8> {
-    fn from_sql(result: *mut zqlite_result_t, row: usize, column: usize) -> Result<Self> {
-        let mut size: c_int = 0;
-        let blob_ptr = unsafe {
-            crate::zqlite_result_get_blob(result, row as c_int, column as c_int, &mut size)
-        };
+impl FromSql for String {
+    fn from_sql(value: ValueRef<'_>) -> Result<Self> {
+        value.as_str().map(str::to_string)
+    }
 
-        if blob_ptr.is_null() {
-            return Err(Error::NullPointer);
+    /// Numbers are formatted and UTF-8 blobs are accepted
+    fn from_sql_lenient(value: ValueRef<'_>) -> Result<Self> {
+        match value {
+            ValueRef::Integer(i) => Ok(i.to_string()),
+            ValueRef::Real(f) => Ok(f.to_string()),
+            ValueRef::Blob(bytes) => ValueRef::Text(bytes).as_str().map(str::to_string),
+            other => Self::from_sql(other),
         }
+    }
+}
 
-        let slice = unsafe { std::slice::from_raw_parts(blob_ptr as *const u8, size as usize) };
-        Ok(slice.to_vec())
+impl FromSql for Vec<u8> {
+    /// Text is accepted as its bytes
+    fn from_sql(value: ValueRef<'_>) -> Result<Self> {
+        match value {
+            ValueRef::Blob(bytes) | ValueRef::Text(bytes) => Ok(bytes.to_vec()),
+            other => Err(mismatch("BLOB", other)),
+        }
     }
 }
 
-impl FromSql for Option<Vec<u8>> {
-    fn from_sql(result: *mut zqlite_result_t, row: usize, column: usize) -> Result<Self> {
-        let column_type = unsafe {
-            crate::zqlite_result_column_type(result, row as c_int, column as c_int)
-        };
+impl FromSql for Value {
+    fn from_sql(value: ValueRef<'_>) -> Result<Self> {
+        Ok(Value::from(value))
+    }
+}
 
-        if column_type == crate::ZQLITE_NULL as c_int {
-            return Ok(None);
+impl<T: FromSql> FromSql for Option<T> {
+    fn from_sql(value: ValueRef<'_>) -> Result<Self> {
+        match value {
+            ValueRef::Null => Ok(None),
+            other => T::from_sql(other).map(Some),
         }
+    }
 
-        let blob = Vec::<u8>::from_sql(result, row, column)?;
-        Ok(Some(blob))
+    fn from_sql_lenient(value: ValueRef<'_>) -> Result<Self> {
+        match value {
+            ValueRef::Null => Ok(None),
+            other => T::from_sql_lenient(other).map(Some),
+        }
     }
 }
 
@@ -336,7 +480,7 @@ mod tests {
         let rows = conn.query("SELECT * FROM test").unwrap();
         assert_eq!(rows.column_count(), 5);
 
-        for row in rows {
+        for row in &rows {
             let id: i64 = row.get(0).unwrap();
             let name: String = row.get(1).unwrap();
             let score: f64 = row.get(2).unwrap();
@@ -355,6 +499,24 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_rows_are_read_in_place() {
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE notes (id INTEGER, body TEXT)").unwrap();
+        conn.execute("INSERT INTO notes VALUES (1, 'first')").unwrap();
+        conn.execute("INSERT INTO notes VALUES (2, 'second')").unwrap();
+
+        let rows = conn.query("SELECT id, body FROM notes").unwrap();
+        let bodies: Vec<&str> = rows
+            .iter()
+            .map(|row| row.get_ref(1).unwrap().as_str().unwrap())
+            .collect();
+        assert_eq!(bodies, ["first", "second"]);
+        assert_eq!(rows.iter().len(), 2);
+        assert_eq!(rows.get(1).unwrap().get_by_name::<i64>("id").unwrap(), 2);
+        assert!(rows.get(2).is_none());
+    }
+
     #[test]
     fn test_null_values() {
         let conn = Connection::open(":memory:").unwrap();
@@ -364,7 +526,7 @@ mod tests {
         conn.execute("INSERT INTO test VALUES (1, NULL)").unwrap();
 
         let rows = conn.query("SELECT * FROM test").unwrap();
-        for row in rows {
+        for row in &rows {
             let id: i64 = row.get(0).unwrap();
             let name: Option<String> = row.get(1).unwrap();
 
@@ -373,4 +535,82 @@ mod tests {
             assert!(row.is_null(1).unwrap());
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_dynamic_row_access() {
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE peers (id INTEGER, name TEXT, latency REAL, key BLOB)")
+            .unwrap();
+        conn.execute("INSERT INTO peers VALUES (7, 'node-a', 1.5, NULL)").unwrap();
+
+        let rows = conn.query("SELECT * FROM peers").unwrap();
+        let row = rows.get(0).unwrap();
+        assert_eq!(row.get_ref(0).unwrap(), ValueRef::Integer(7));
+        assert_eq!(row.get_ref(1).unwrap().as_str().unwrap(), "node-a");
+        assert_eq!(row.column_type(2).unwrap(), Type::Real);
+        assert_eq!(row.column_type(3).unwrap(), Type::Null);
+        assert!(row.get_ref(4).is_err());
+
+        let value: Value = row.get(1).unwrap();
+        assert_eq!(value, Value::Text("node-a".to_string()));
+
+        let map = row.to_map().unwrap();
+        let columns: Vec<&str> = map.keys().map(String::as_str).collect();
+        assert_eq!(columns, ["id", "name", "latency", "key"]);
+        assert_eq!(
+            serde_json::to_value(&map).unwrap(),
+            serde_json::json!({"id": 7, "name": "node-a", "latency": 1.5, "key": null})
+        );
+    }
+
+    #[test]
+    fn test_strict_conversions() {
+        assert_eq!(i32::from_sql(ValueRef::Integer(7)).unwrap(), 7);
+        assert!(i32::from_sql(ValueRef::Integer(1 << 32)).is_err());
+        assert!(u64::from_sql(ValueRef::Integer(-1)).is_err());
+        assert!(u8::from_sql(ValueRef::Integer(256)).is_err());
+        assert!(i64::from_sql(ValueRef::Null).is_err());
+        assert!(i64::from_sql(ValueRef::Text(b"42")).is_err());
+        assert!(bool::from_sql(ValueRef::Integer(2)).is_err());
+        assert!(String::from_sql(ValueRef::Null).is_err());
+        assert_eq!(f64::from_sql(ValueRef::Integer(2)).unwrap(), 2.0);
+        assert_eq!(Option::<i32>::from_sql(ValueRef::Null).unwrap(), None);
+    }
+
+    #[test]
+    fn test_lenient_conversions() {
+        assert_eq!(i64::from_sql_lenient(ValueRef::Text(b" 42 ")).unwrap(), 42);
+        assert_eq!(i64::from_sql_lenient(ValueRef::Real(3.0)).unwrap(), 3);
+        assert!(i64::from_sql_lenient(ValueRef::Real(3.5)).is_err());
+        assert!(i32::from_sql_lenient(ValueRef::Text(b"4294967296")).is_err());
+        assert_eq!(f64::from_sql_lenient(ValueRef::Text(b"1.5")).unwrap(), 1.5);
+        assert!(bool::from_sql_lenient(ValueRef::Integer(5)).unwrap());
+        assert!(!bool::from_sql_lenient(ValueRef::Text(b"false")).unwrap());
+        assert_eq!(String::from_sql_lenient(ValueRef::Integer(9)).unwrap(), "9");
+    }
+
+    #[test]
+    fn test_type_mismatch_names_column() {
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute("CREATE TABLE counters (name TEXT, hits INTEGER)").unwrap();
+        conn.execute("INSERT INTO counters VALUES ('42', 4294967296)").unwrap();
+
+        let rows = conn.query("SELECT * FROM counters").unwrap();
+        let row = rows.get(0).unwrap();
+        assert_eq!(row.get::<i64>(1).unwrap(), 4_294_967_296);
+        match row.get::<i32>(1) {
+            Err(Error::TypeMismatch { column, column_name, .. }) => {
+                assert_eq!(column, Some(1));
+                assert_eq!(column_name.as_deref(), Some("hits"));
+            }
+            other => panic!("expected a type mismatch, got {:?}", other.map(|_| ())),
+        }
+        assert!(row.get::<i64>(0).is_err());
+
+        conn.set_lenient_conversions(true);
+        let rows = conn.query("SELECT * FROM counters").unwrap();
+        let row = rows.get(0).unwrap();
+        assert_eq!(row.get::<i64>(0).unwrap(), 42);
+        assert!(row.get::<i32>(1).is_err());
+    }
+}
@@ -0,0 +1,266 @@
+//! Schema introspection
+
+use crate::ffi::{
+    zqlite_schema, zqlite_schema_column, zqlite_schema_column_count,
+    zqlite_schema_foreign_key, zqlite_schema_foreign_key_count, zqlite_schema_free,
+    zqlite_schema_index, zqlite_schema_index_column, zqlite_schema_index_count, zqlite_schema_t,
+    zqlite_schema_table_count, zqlite_schema_table_name, ZQLITE_OK,
+};
+use crate::{Connection, Error, Result};
+use serde::{Deserialize, Serialize};
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+
+/// A table with its columns, indexes and foreign keys
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableInfo {
+    /// Table name
+    pub name: String,
+    /// Columns in declaration order
+    pub columns: Vec<ColumnInfo>,
+    /// Indexes on the table, ordered by name
+    #[serde(default)]
+    pub indexes: Vec<IndexInfo>,
+    /// Foreign keys declared by the table
+    #[serde(default)]
+    pub foreign_keys: Vec<ForeignKeyInfo>,
+}
+
+impl TableInfo {
+    /// Find a column by name
+    pub fn column(&self, name: &str) -> Option<&ColumnInfo> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+}
+
+/// A column as declared in `CREATE TABLE`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColumnInfo {
+    /// Column name
+    pub name: String,
+    /// Declared type as written, e.g. `INTEGER` or `VARCHAR(64)`
+    pub decl_type: String,
+    /// Whether the column is `NOT NULL`
+    pub not_null: bool,
+    /// Default value as SQL, e.g. `0` or `'active'`
+    pub default: Option<String>,
+    /// Whether the column is part of the primary key
+    pub pk: bool,
+}
+
+/// An index created with `CREATE INDEX`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexInfo {
+    /// Index name
+    pub name: String,
+    /// Whether the index is `UNIQUE`
+    pub unique: bool,
+    /// Indexed columns, in order
+    pub columns: Vec<String>,
+}
+
+/// A foreign key from one column to a column of another table
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForeignKeyInfo {
+    /// Referencing column
+    pub column: String,
+    /// Referenced table
+    pub table: String,
+    /// Referenced column
+    pub to: String,
+    /// Action on delete, e.g. `CASCADE` or `NO ACTION`
+    pub on_delete: String,
+    /// Action on update
+    pub on_update: String,
+}
+
+/// Frees the snapshot once it has been copied out
+struct Snapshot(*mut zqlite_schema_t);
+
+impl Drop for Snapshot {
+    fn drop(&mut self) {
+        unsafe { zqlite_schema_free(self.0) };
+    }
+}
+
+/// Copy a string owned by the snapshot
+///
+/// # Safety
+/// `ptr` must be null or a valid C string.
+unsafe fn string(ptr: *const c_char) -> Option<String> {
+    if ptr.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(ptr).to_string_lossy().into_owned())
+    }
+}
+
+fn check(result: c_int, what: &str) -> Result<()> {
+    if result != ZQLITE_OK as c_int {
+        return Err(Error::database(format!("cannot read schema {}", what)));
+    }
+    Ok(())
+}
+
+impl Connection {
+    /// List every table with its columns, indexes and foreign keys
+    ///
+    /// Tables are ordered by name.
+    ///
+    /// ```rust,no_run
+    /// # use zqlite_rs::Connection;
+    /// # let conn = Connection::open(":memory:")?;
+    /// for table in conn.schema()? {
+    ///     println!("{}: {} columns", table.name, table.columns.len());
+    /// }
+    /// # Ok::<(), zqlite_rs::Error>(())
+    /// ```
+    pub fn schema(&self) -> Result<Vec<TableInfo>> {
+        let raw = unsafe { zqlite_schema(self.inner) };
+        if raw.is_null() {
+            return Err(self.get_last_error());
+        }
+        let snapshot = Snapshot(raw);
+
+        let table_count = unsafe { zqlite_schema_table_count(snapshot.0) }.max(0);
+        (0..table_count)
+            .map(|table| unsafe { read_table(snapshot.0, table) })
+            .collect()
+    }
+
+    /// Describe one table, or `None` if it does not exist
+    pub fn table_info(&self, name: &str) -> Result<Option<TableInfo>> {
+        Ok(self.schema()?.into_iter().find(|table| table.name == name))
+    }
+}
+
+/// # Safety
+/// `schema` must be a live snapshot and `table` in range.
+unsafe fn read_table(schema: *mut zqlite_schema_t, table: c_int) -> Result<TableInfo> {
+    let name = string(zqlite_schema_table_name(schema, table))
+        .ok_or_else(|| Error::database("cannot read schema table name"))?;
+
+    let mut columns = Vec::new();
+    for column in 0..zqlite_schema_column_count(schema, table).max(0) {
+        let (mut column_name, mut decl_type, mut default) = (ptr::null(), ptr::null(), ptr::null());
+        let (mut not_null, mut pk) = (0, 0);
+        check(
+            zqlite_schema_column(
+                schema,
+                table,
+                column,
+                &mut column_name,
+                &mut decl_type,
+                &mut not_null,
+                &mut default,
+                &mut pk,
+            ),
+            "column",
+        )?;
+        columns.push(ColumnInfo {
+            name: string(column_name).unwrap_or_default(),
+            decl_type: string(decl_type).unwrap_or_default(),
+            not_null: not_null != 0,
+            default: string(default),
+            pk: pk != 0,
+        });
+    }
+
+    let mut indexes = Vec::new();
+    for index in 0..zqlite_schema_index_count(schema, table).max(0) {
+        let mut index_name = ptr::null();
+        let (mut unique, mut column_count) = (0, 0);
+        check(
+            zqlite_schema_index(schema, table, index, &mut index_name, &mut unique, &mut column_count),
+            "index",
+        )?;
+        let columns = (0..column_count.max(0))
+            .filter_map(|column| string(zqlite_schema_index_column(schema, table, index, column)))
+            .collect();
+        indexes.push(IndexInfo {
+            name: string(index_name).unwrap_or_default(),
+            unique: unique != 0,
+            columns,
+        });
+    }
+
+    let mut foreign_keys = Vec::new();
+    for foreign_key in 0..zqlite_schema_foreign_key_count(schema, table).max(0) {
+        let (mut column, mut ref_table, mut ref_column) = (ptr::null(), ptr::null(), ptr::null());
+        let (mut on_delete, mut on_update) = (ptr::null(), ptr::null());
+        check(
+            zqlite_schema_foreign_key(
+                schema,
+                table,
+                foreign_key,
+                &mut column,
+                &mut ref_table,
+                &mut ref_column,
+                &mut on_delete,
+                &mut on_update,
+            ),
+            "foreign key",
+        )?;
+        foreign_keys.push(ForeignKeyInfo {
+            column: string(column).unwrap_or_default(),
+            table: string(ref_table).unwrap_or_default(),
+            to: string(ref_column).unwrap_or_default(),
+            on_delete: string(on_delete).unwrap_or_default(),
+            on_update: string(on_update).unwrap_or_default(),
+        });
+    }
+
+    Ok(TableInfo {
+        name,
+        columns,
+        indexes,
+        foreign_keys,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema() {
+        let conn = Connection::open(":memory:").unwrap();
+        conn.execute(
+            "CREATE TABLE peers (id TEXT PRIMARY KEY, name VARCHAR(64) NOT NULL, rx INTEGER DEFAULT 0, owner uuid)",
+        )
+        .unwrap();
+        conn.execute(
+            "CREATE TABLE routes (cidr TEXT, peer_id TEXT, FOREIGN KEY (peer_id) REFERENCES peers(id) ON DELETE CASCADE)",
+        )
+        .unwrap();
+        conn.execute("CREATE UNIQUE INDEX idx_routes_cidr ON routes (cidr)").unwrap();
+
+        let schema = conn.schema().unwrap();
+        let names: Vec<_> = schema.iter().map(|table| table.name.as_str()).collect();
+        assert_eq!(names, ["peers", "routes"]);
+
+        let peers = &schema[0];
+        assert!(peers.column("id").unwrap().pk);
+        let name = peers.column("name").unwrap();
+        // Declared types are kept as written, size and case included
+        assert_eq!(name.decl_type, "VARCHAR(64)");
+        assert_eq!(peers.column("owner").unwrap().decl_type, "uuid");
+        assert!(name.not_null);
+        assert_eq!(peers.column("rx").unwrap().default.as_deref(), Some("0"));
+
+        let routes = &schema[1];
+        assert_eq!(
+            routes.indexes,
+            [IndexInfo {
+                name: "idx_routes_cidr".into(),
+                unique: true,
+                columns: vec!["cidr".into()],
+            }]
+        );
+        assert_eq!(routes.foreign_keys[0].table, "peers");
+        assert_eq!(routes.foreign_keys[0].on_delete, "CASCADE");
+
+        assert_eq!(conn.table_info("missing").unwrap(), None);
+    }
+}
@@ -0,0 +1,68 @@
+//! `chrono::DateTime<Utc>`, stored as REAL seconds since the Unix epoch
+
+use super::unix_seconds;
+use crate::value::{ToSqlOutput, ValueRef};
+use crate::{Error, FromSql, Result, ToSql};
+use ::chrono::{DateTime, NaiveDateTime, Utc};
+
+const EXPECTED: &str = "timestamp";
+
+impl ToSql for DateTime<Utc> {
+    /// Written as REAL seconds, which keeps microsecond precision
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        let seconds = self.timestamp() as f64 + f64::from(self.timestamp_subsec_nanos()) / 1e9;
+        Ok(ValueRef::Real(seconds).into())
+    }
+}
+
+impl FromSql for DateTime<Utc> {
+    /// Reads INTEGER or REAL Unix seconds, or RFC 3339 / `YYYY-MM-DD HH:MM:SS` text
+    fn from_sql(value: ValueRef<'_>) -> Result<Self> {
+        if let Some((seconds, nanos)) = unix_seconds(value, EXPECTED)? {
+            return DateTime::from_timestamp(seconds, nanos).ok_or_else(|| {
+                Error::type_mismatch(EXPECTED, format!("{} seconds (out of range)", seconds))
+            });
+        }
+
+        let text = match value {
+            ValueRef::Text(_) => value.as_str()?,
+            other => return Err(Error::type_mismatch(EXPECTED, other.type_name())),
+        };
+
+        DateTime::parse_from_rfc3339(text)
+            .map(|time| time.with_timezone(&Utc))
+            .or_else(|_| {
+                NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S%.f").map(|time| time.and_utc())
+            })
+            .map_err(|e| Error::type_mismatch(EXPECTED, format!("malformed TEXT {:?}: {}", text, e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_datetime_round_trip() {
+        let time = DateTime::from_timestamp(1_700_000_000, 250_000_000).unwrap();
+        let stored = time.to_sql().unwrap();
+        assert_eq!(stored, ValueRef::Real(1_700_000_000.25));
+        assert_eq!(DateTime::<Utc>::from_sql(stored.value_ref()).unwrap(), time);
+    }
+
+    #[test]
+    fn test_datetime_from_integer_and_text() {
+        let expected = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        assert_eq!(DateTime::<Utc>::from_sql(ValueRef::Integer(1_700_000_000)).unwrap(), expected);
+        assert_eq!(
+            DateTime::<Utc>::from_sql(ValueRef::Text(b"2023-11-14T22:13:20Z")).unwrap(),
+            expected
+        );
+        assert_eq!(
+            DateTime::<Utc>::from_sql(ValueRef::Text(b"2023-11-14 22:13:20")).unwrap(),
+            expected
+        );
+        assert!(DateTime::<Utc>::from_sql(ValueRef::Text(b"yesterday")).is_err());
+        assert!(DateTime::<Utc>::from_sql(ValueRef::Integer(i64::MAX)).is_err());
+    }
+}
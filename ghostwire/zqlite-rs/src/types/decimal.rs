@@ -0,0 +1,40 @@
+//! `rust_decimal::Decimal`, stored as TEXT so no precision is lost
+
+use super::{display_text, parse_text};
+use crate::value::{ToSqlOutput, ValueRef};
+use crate::{Error, FromSql, Result, ToSql};
+use ::rust_decimal::Decimal;
+
+impl ToSql for Decimal {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        Ok(display_text(self))
+    }
+}
+
+impl FromSql for Decimal {
+    /// Reads TEXT, INTEGER, or REAL (which may already have lost precision)
+    fn from_sql(value: ValueRef<'_>) -> Result<Self> {
+        match value {
+            ValueRef::Integer(i) => Ok(Decimal::from(i)),
+            ValueRef::Real(f) => Decimal::try_from(f)
+                .map_err(|e| Error::type_mismatch("decimal", format!("REAL {}: {}", f, e))),
+            other => parse_text(other, "decimal"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_decimal_round_trip() {
+        let amount = Decimal::from_str("12.3400").unwrap();
+        assert_eq!(amount.to_sql().unwrap(), ValueRef::Text(b"12.3400"));
+        assert_eq!(Decimal::from_sql(ValueRef::Text(b"12.3400")).unwrap(), amount);
+        assert_eq!(Decimal::from_sql(ValueRef::Integer(3)).unwrap(), Decimal::from(3));
+        assert!(Decimal::from_sql(ValueRef::Text(b"12,34")).is_err());
+        assert!(Decimal::from_sql(ValueRef::Real(f64::NAN)).is_err());
+    }
+}
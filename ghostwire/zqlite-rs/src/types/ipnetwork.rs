@@ -0,0 +1,44 @@
+//! `ipnetwork` CIDR blocks, stored as TEXT such as `10.0.0.0/8`
+
+use super::{display_text, parse_text};
+use crate::value::{ToSqlOutput, ValueRef};
+use crate::{FromSql, Result, ToSql};
+use ::ipnetwork::{IpNetwork, Ipv4Network, Ipv6Network};
+
+macro_rules! network_conversions {
+    ($($t:ty),*) => {
+        $(
+            impl ToSql for $t {
+                fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+                    Ok(display_text(self))
+                }
+            }
+
+            impl FromSql for $t {
+                fn from_sql(value: ValueRef<'_>) -> Result<Self> {
+                    parse_text(value, "CIDR")
+                }
+            }
+        )*
+    };
+}
+
+network_conversions!(IpNetwork, Ipv4Network, Ipv6Network);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_network_round_trip() {
+        let network: IpNetwork = "100.64.0.0/10".parse().unwrap();
+        assert_eq!(network.to_sql().unwrap(), ValueRef::Text(b"100.64.0.0/10"));
+        assert_eq!(IpNetwork::from_sql(ValueRef::Text(b"100.64.0.0/10")).unwrap(), network);
+    }
+
+    #[test]
+    fn test_malformed_network() {
+        assert!(IpNetwork::from_sql(ValueRef::Text(b"100.64.0.0/33")).is_err());
+        assert!(Ipv4Network::from_sql(ValueRef::Text(b"fd00::/8")).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+//! `FromSql`/`ToSql` for standard library and ecosystem types
+//!
+//! Types from other crates are behind features named after the crate:
+//! `uuid`, `chrono`, `time`, `ipnetwork` and `rust_decimal`.
+
+use crate::value::{ToSqlOutput, Value, ValueRef};
+use crate::{Error, FromSql, Result, ToSql};
+use std::fmt::Display;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+
+#[cfg(feature = "chrono")]
+mod chrono;
+#[cfg(feature = "rust_decimal")]
+mod decimal;
+#[cfg(feature = "ipnetwork")]
+mod ipnetwork;
+#[cfg(feature = "time")]
+mod time;
+#[cfg(feature = "uuid")]
+mod uuid;
+
+/// Parse a value stored as text, naming the text and the parse error on failure
+pub(crate) fn parse_text<T>(value: ValueRef<'_>, expected: &str) -> Result<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let text = match value {
+        ValueRef::Text(_) => value.as_str()?,
+        other => return Err(Error::type_mismatch(expected, other.type_name())),
+    };
+
+    text.parse().map_err(|e| {
+        Error::type_mismatch(expected, format!("malformed TEXT {:?}: {}", text, e))
+    })
+}
+
+/// Store a value as its `Display` text
+pub(crate) fn display_text<T: Display>(value: &T) -> ToSqlOutput<'static> {
+    Value::Text(value.to_string()).into()
+}
+
+/// Split seconds since the Unix epoch, as stored in INTEGER or REAL columns,
+/// into whole seconds and nanoseconds
+#[cfg(any(feature = "chrono", feature = "time"))]
+pub(crate) fn unix_seconds(value: ValueRef<'_>, expected: &str) -> Result<Option<(i64, u32)>> {
+    match value {
+        ValueRef::Integer(seconds) => Ok(Some((seconds, 0))),
+        ValueRef::Real(seconds) if seconds.is_finite() => {
+            let whole = seconds.floor();
+            if whole < i64::MIN as f64 || whole >= i64::MAX as f64 {
+                return Err(Error::type_mismatch(
+                    expected,
+                    format!("REAL {} (out of range)", seconds),
+                ));
+            }
+            let nanos = ((seconds - whole) * 1e9).round().min(999_999_999.0) as u32;
+            Ok(Some((whole as i64, nanos)))
+        }
+        ValueRef::Real(seconds) => Err(Error::type_mismatch(expected, format!("REAL {}", seconds))),
+        _ => Ok(None),
+    }
+}
+
+macro_rules! text_conversions {
+    ($($t:ty => $name:expr),*) => {
+        $(
+            impl ToSql for $t {
+                fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+                    Ok(display_text(self))
+                }
+            }
+
+            impl FromSql for $t {
+                fn from_sql(value: ValueRef<'_>) -> Result<Self> {
+                    parse_text(value, $name)
+                }
+            }
+        )*
+    };
+}
+
+text_conversions!(IpAddr => "IP address", SocketAddr => "socket address");
+
+impl<const N: usize> ToSql for [u8; N] {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        Ok(ValueRef::Blob(self).into())
+    }
+}
+
+impl<const N: usize> FromSql for [u8; N] {
+    /// Only a BLOB of exactly `N` bytes converts
+    fn from_sql(value: ValueRef<'_>) -> Result<Self> {
+        let expected = || format!("BLOB of {} bytes", N);
+        match value {
+            ValueRef::Blob(bytes) => bytes.try_into().map_err(|_| {
+                Error::type_mismatch(expected(), format!("BLOB of {} bytes", bytes.len()))
+            }),
+            other => Err(Error::type_mismatch(expected(), other.type_name())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn test_ip_addresses() {
+        let ip = IpAddr::V4(Ipv4Addr::new(100, 64, 0, 1));
+        assert_eq!(ip.to_sql().unwrap(), ValueRef::Text(b"100.64.0.1"));
+        assert_eq!(IpAddr::from_sql(ValueRef::Text(b"100.64.0.1")).unwrap(), ip);
+
+        let addr: SocketAddr = "[::1]:41641".parse().unwrap();
+        assert_eq!(SocketAddr::from_sql(addr.to_sql().unwrap().value_ref()).unwrap(), addr);
+
+        let error = IpAddr::from_sql(ValueRef::Text(b"100.64.0")).unwrap_err();
+        assert!(error.to_string().contains("malformed TEXT \"100.64.0\""));
+        assert!(IpAddr::from_sql(ValueRef::Integer(1)).is_err());
+    }
+
+    #[test]
+    fn test_fixed_size_arrays() {
+        let key = [7u8; 32];
+        assert_eq!(key.to_sql().unwrap(), ValueRef::Blob(&key));
+        assert_eq!(<[u8; 32]>::from_sql(ValueRef::Blob(&key)).unwrap(), key);
+
+        let error = <[u8; 32]>::from_sql(ValueRef::Blob(&key[..31])).unwrap_err();
+        assert!(error.to_string().contains("BLOB of 31 bytes"));
+    }
+
+    #[test]
+    #[cfg(any(feature = "chrono", feature = "time"))]
+    fn test_unix_seconds() {
+        assert_eq!(unix_seconds(ValueRef::Integer(5), "t").unwrap(), Some((5, 0)));
+        assert_eq!(unix_seconds(ValueRef::Real(1.5), "t").unwrap(), Some((1, 500_000_000)));
+        assert_eq!(unix_seconds(ValueRef::Real(-0.25), "t").unwrap(), Some((-1, 750_000_000)));
+        assert!(unix_seconds(ValueRef::Real(f64::NAN), "t").is_err());
+        assert_eq!(unix_seconds(ValueRef::Text(b"x"), "t").unwrap(), None);
+    }
+}
@@ -0,0 +1,60 @@
+//! `time::OffsetDateTime`, stored as REAL seconds since the Unix epoch
+
+use super::unix_seconds;
+use crate::value::{ToSqlOutput, ValueRef};
+use crate::{Error, FromSql, Result, ToSql};
+use ::time::format_description::well_known::Rfc3339;
+use ::time::OffsetDateTime;
+
+const EXPECTED: &str = "timestamp";
+
+impl ToSql for OffsetDateTime {
+    /// Written as REAL seconds, which keeps microsecond precision
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        let seconds = self.unix_timestamp() as f64 + f64::from(self.nanosecond()) / 1e9;
+        Ok(ValueRef::Real(seconds).into())
+    }
+}
+
+impl FromSql for OffsetDateTime {
+    /// Reads INTEGER or REAL Unix seconds, or RFC 3339 text
+    fn from_sql(value: ValueRef<'_>) -> Result<Self> {
+        if let Some((seconds, nanos)) = unix_seconds(value, EXPECTED)? {
+            let nanos = i128::from(seconds) * 1_000_000_000 + i128::from(nanos);
+            return OffsetDateTime::from_unix_timestamp_nanos(nanos).map_err(|e| {
+                Error::type_mismatch(EXPECTED, format!("{} seconds: {}", seconds, e))
+            });
+        }
+
+        let text = match value {
+            ValueRef::Text(_) => value.as_str()?,
+            other => return Err(Error::type_mismatch(EXPECTED, other.type_name())),
+        };
+
+        OffsetDateTime::parse(text, &Rfc3339)
+            .map_err(|e| Error::type_mismatch(EXPECTED, format!("malformed TEXT {:?}: {}", text, e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offset_datetime_round_trip() {
+        let time = OffsetDateTime::from_unix_timestamp_nanos(1_700_000_000_250_000_000).unwrap();
+        let stored = time.to_sql().unwrap();
+        assert_eq!(OffsetDateTime::from_sql(stored.value_ref()).unwrap(), time);
+    }
+
+    #[test]
+    fn test_offset_datetime_from_text() {
+        let expected = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+        assert_eq!(
+            OffsetDateTime::from_sql(ValueRef::Text(b"2023-11-14T22:13:20Z")).unwrap(),
+            expected
+        );
+        assert!(OffsetDateTime::from_sql(ValueRef::Text(b"2023-11-14")).is_err());
+        assert!(OffsetDateTime::from_sql(ValueRef::Blob(b"x")).is_err());
+    }
+}
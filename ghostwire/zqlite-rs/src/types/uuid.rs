@@ -0,0 +1,45 @@
+//! `uuid::Uuid`, stored as hyphenated TEXT or a 16-byte BLOB
+
+use super::parse_text;
+use crate::value::{ToSqlOutput, Value, ValueRef};
+use crate::{Error, FromSql, Result, ToSql};
+use ::uuid::Uuid;
+
+impl ToSql for Uuid {
+    /// Written as hyphenated lowercase text
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        Ok(Value::Text(self.hyphenated().to_string()).into())
+    }
+}
+
+impl FromSql for Uuid {
+    fn from_sql(value: ValueRef<'_>) -> Result<Self> {
+        match value {
+            ValueRef::Blob(bytes) => Uuid::from_slice(bytes).map_err(|_| {
+                Error::type_mismatch("UUID", format!("BLOB of {} bytes", bytes.len()))
+            }),
+            other => parse_text(other, "UUID"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uuid_round_trip() {
+        let id = Uuid::new_v4();
+        let stored = id.to_sql().unwrap();
+        assert_eq!(Uuid::from_sql(stored.value_ref()).unwrap(), id);
+        assert_eq!(Uuid::from_sql(ValueRef::Blob(id.as_bytes())).unwrap(), id);
+    }
+
+    #[test]
+    fn test_malformed_uuid() {
+        let error = Uuid::from_sql(ValueRef::Text(b"not-a-uuid")).unwrap_err();
+        assert!(error.to_string().contains("malformed TEXT \"not-a-uuid\""));
+        assert!(Uuid::from_sql(ValueRef::Blob(&[0; 15])).is_err());
+        assert!(Uuid::from_sql(ValueRef::Integer(1)).is_err());
+    }
+}
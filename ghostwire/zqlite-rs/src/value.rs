@@ -0,0 +1,396 @@
+//! SQL value types shared by function arguments, parameter binding and rows
+
+use crate::{Error, Result};
+use std::fmt;
+
+/// Storage class of a SQL value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Type {
+    /// NULL value
+    Null,
+    /// 64-bit signed integer
+    Integer,
+    /// 64-bit floating point number
+    Real,
+    /// Text
+    Text,
+    /// Binary data
+    Blob,
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Type::Null => "NULL",
+            Type::Integer => "INTEGER",
+            Type::Real => "REAL",
+            Type::Text => "TEXT",
+            Type::Blob => "BLOB",
+        })
+    }
+}
+
+/// A borrowed SQL value
+///
+/// Text is kept as raw bytes because ZQLite does not guarantee UTF-8; use
+/// [`ValueRef::as_str`] to validate it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValueRef<'a> {
+    /// NULL value
+    Null,
+    /// 64-bit signed integer
+    Integer(i64),
+    /// 64-bit floating point number
+    Real(f64),
+    /// Text, as stored
+    Text(&'a [u8]),
+    /// Binary data
+    Blob(&'a [u8]),
+}
+
+impl<'a> ValueRef<'a> {
+    /// Storage class of the value
+    pub fn data_type(&self) -> Type {
+        match self {
+            ValueRef::Null => Type::Null,
+            ValueRef::Integer(_) => Type::Integer,
+            ValueRef::Real(_) => Type::Real,
+            ValueRef::Text(_) => Type::Text,
+            ValueRef::Blob(_) => Type::Blob,
+        }
+    }
+
+    /// Name of the value's storage class, for error messages
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ValueRef::Null => "NULL",
+            ValueRef::Integer(_) => "INTEGER",
+            ValueRef::Real(_) => "REAL",
+            ValueRef::Text(_) => "TEXT",
+            ValueRef::Blob(_) => "BLOB",
+        }
+    }
+
+    /// Check if the value is NULL
+    pub fn is_null(&self) -> bool {
+        matches!(self, ValueRef::Null)
+    }
+
+    /// Get the value as an integer
+    pub fn as_i64(&self) -> Result<i64> {
+        match *self {
+            ValueRef::Integer(i) => Ok(i),
+            other => Err(Error::type_mismatch("INTEGER", other.type_name())),
+        }
+    }
+
+    /// Get the value as a float, widening integers
+    pub fn as_f64(&self) -> Result<f64> {
+        match *self {
+            ValueRef::Real(f) => Ok(f),
+            ValueRef::Integer(i) => Ok(i as f64),
+            other => Err(Error::type_mismatch("REAL", other.type_name())),
+        }
+    }
+
+    /// Get the value as UTF-8 text
+    pub fn as_str(&self) -> Result<&'a str> {
+        match *self {
+            ValueRef::Text(bytes) => std::str::from_utf8(bytes)
+                .map_err(|e| Error::type_mismatch("UTF-8 TEXT", format!("invalid UTF-8: {}", e))),
+            other => Err(Error::type_mismatch("TEXT", other.type_name())),
+        }
+    }
+
+    /// Get the value as a byte slice
+    pub fn as_blob(&self) -> Result<&'a [u8]> {
+        match *self {
+            ValueRef::Blob(bytes) => Ok(bytes),
+            other => Err(Error::type_mismatch("BLOB", other.type_name())),
+        }
+    }
+}
+
+/// An owned SQL value, for rows whose types are not known in advance
+///
+/// Serializes as the matching JSON value; blobs become arrays of bytes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// NULL value
+    Null,
+    /// 64-bit signed integer
+    Integer(i64),
+    /// 64-bit floating point number
+    Real(f64),
+    /// Text; invalid UTF-8 is replaced when converting from a [`ValueRef`]
+    Text(String),
+    /// Binary data
+    Blob(Vec<u8>),
+}
+
+impl Value {
+    /// Storage class of the value
+    pub fn data_type(&self) -> Type {
+        self.as_value_ref().data_type()
+    }
+
+    /// Borrow the value
+    pub fn as_value_ref(&self) -> ValueRef<'_> {
+        match self {
+            Value::Null => ValueRef::Null,
+            Value::Integer(i) => ValueRef::Integer(*i),
+            Value::Real(f) => ValueRef::Real(*f),
+            Value::Text(text) => ValueRef::Text(text.as_bytes()),
+            Value::Blob(blob) => ValueRef::Blob(blob),
+        }
+    }
+}
+
+impl From<ValueRef<'_>> for Value {
+    fn from(value: ValueRef<'_>) -> Self {
+        match value {
+            ValueRef::Null => Value::Null,
+            ValueRef::Integer(i) => Value::Integer(i),
+            ValueRef::Real(f) => Value::Real(f),
+            ValueRef::Text(bytes) => Value::Text(String::from_utf8_lossy(bytes).into_owned()),
+            ValueRef::Blob(bytes) => Value::Blob(bytes.to_vec()),
+        }
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Integer(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Real(value)
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::Text(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Text(value.to_string())
+    }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(value: Vec<u8>) -> Self {
+        Value::Blob(value)
+    }
+}
+
+impl serde::Serialize for Value {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            Value::Null => serializer.serialize_none(),
+            Value::Integer(i) => serializer.serialize_i64(*i),
+            Value::Real(f) => serializer.serialize_f64(*f),
+            Value::Text(text) => serializer.serialize_str(text),
+            Value::Blob(blob) => serializer.collect_seq(blob),
+        }
+    }
+}
+
+/// A SQL value produced by [`ToSql`], borrowed from the Rust value when it can be
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToSqlOutput<'a> {
+    /// Borrowed from the Rust value
+    Borrowed(ValueRef<'a>),
+    /// Built by the conversion, e.g. a UUID rendered as text
+    Owned(Value),
+}
+
+impl ToSqlOutput<'_> {
+    /// Borrow the SQL value
+    pub fn value_ref(&self) -> ValueRef<'_> {
+        match self {
+            ToSqlOutput::Borrowed(value) => *value,
+            ToSqlOutput::Owned(value) => value.as_value_ref(),
+        }
+    }
+}
+
+impl<'a> From<ValueRef<'a>> for ToSqlOutput<'a> {
+    fn from(value: ValueRef<'a>) -> Self {
+        ToSqlOutput::Borrowed(value)
+    }
+}
+
+impl From<Value> for ToSqlOutput<'_> {
+    fn from(value: Value) -> Self {
+        ToSqlOutput::Owned(value)
+    }
+}
+
+impl PartialEq<ValueRef<'_>> for ToSqlOutput<'_> {
+    fn eq(&self, other: &ValueRef<'_>) -> bool {
+        self.value_ref() == *other
+    }
+}
+
+/// Trait for Rust values that can be passed to ZQLite
+pub trait ToSql {
+    /// Convert the value to a SQL value
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>>;
+}
+
+impl<T: ToSql + ?Sized> ToSql for &T {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        (**self).to_sql()
+    }
+}
+
+impl<T: ToSql> ToSql for Option<T> {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        match self {
+            Some(value) => value.to_sql(),
+            None => Ok(ValueRef::Null.into()),
+        }
+    }
+}
+
+impl ToSql for ValueRef<'_> {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        Ok((*self).into())
+    }
+}
+
+impl ToSql for Value {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        Ok(self.as_value_ref().into())
+    }
+}
+
+impl ToSql for () {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        Ok(ValueRef::Null.into())
+    }
+}
+
+impl ToSql for bool {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        Ok(ValueRef::Integer(*self as i64).into())
+    }
+}
+
+macro_rules! to_sql_integer {
+    ($($t:ty),*) => {
+        $(
+            impl ToSql for $t {
+                fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+                    Ok(ValueRef::Integer(i64::from(*self)).into())
+                }
+            }
+        )*
+    };
+}
+
+to_sql_integer!(i8, i16, i32, i64, u8, u16, u32);
+
+impl ToSql for u64 {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        i64::try_from(*self)
+            .map(|i| ValueRef::Integer(i).into())
+            .map_err(|_| Error::type_mismatch("INTEGER", format!("u64 {} out of range", self)))
+    }
+}
+
+impl ToSql for f32 {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        Ok(ValueRef::Real(f64::from(*self)).into())
+    }
+}
+
+impl ToSql for f64 {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        Ok(ValueRef::Real(*self).into())
+    }
+}
+
+impl ToSql for str {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        Ok(ValueRef::Text(self.as_bytes()).into())
+    }
+}
+
+impl ToSql for String {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        Ok(ValueRef::Text(self.as_bytes()).into())
+    }
+}
+
+impl ToSql for [u8] {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        Ok(ValueRef::Blob(self).into())
+    }
+}
+
+impl ToSql for Vec<u8> {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>> {
+        Ok(ValueRef::Blob(self).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_value_ref_accessors() {
+        assert_eq!(ValueRef::Integer(7).as_i64().unwrap(), 7);
+        assert_eq!(ValueRef::Integer(2).as_f64().unwrap(), 2.0);
+        assert_eq!(ValueRef::Text(b"abc").as_str().unwrap(), "abc");
+        assert!(ValueRef::Text(&[0xff, 0xfe]).as_str().is_err());
+        assert!(ValueRef::Null.as_i64().is_err());
+        assert!(ValueRef::Null.is_null());
+    }
+
+    #[test]
+    fn test_to_sql() {
+        assert_eq!(42i32.to_sql().unwrap(), ValueRef::Integer(42));
+        assert_eq!(true.to_sql().unwrap(), ValueRef::Integer(1));
+        assert_eq!("hi".to_sql().unwrap(), ValueRef::Text(b"hi"));
+        assert_eq!(None::<i64>.to_sql().unwrap(), ValueRef::Null);
+        assert!(u64::MAX.to_sql().is_err());
+    }
+
+    #[test]
+    fn test_value_conversions() {
+        let value = Value::from(ValueRef::Text(b"peer"));
+        assert_eq!(value, Value::Text("peer".to_string()));
+        assert_eq!(value.data_type(), Type::Text);
+        assert_eq!(value.to_sql().unwrap(), ValueRef::Text(b"peer"));
+        assert_eq!(Value::from(None::<i64>), Value::Null);
+        assert_eq!(Value::from(ValueRef::Blob(&[1, 2])), Value::Blob(vec![1, 2]));
+    }
+
+    #[test]
+    fn test_value_serializes_to_json() {
+        let values = vec![
+            Value::Null,
+            Value::Integer(1),
+            Value::Real(1.5),
+            Value::Text("a".to_string()),
+            Value::Blob(vec![7]),
+        ];
+        assert_eq!(
+            serde_json::to_string(&values).unwrap(),
+            r#"[null,1,1.5,"a",[7]]"#
+        );
+    }
+}
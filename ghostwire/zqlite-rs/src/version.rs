@@ -0,0 +1,196 @@
+//! Library version and capability checks
+
+use crate::ffi;
+use crate::{Error, Result};
+use std::fmt;
+use std::ops::Range;
+use std::os::raw::c_uint;
+use std::sync::OnceLock;
+
+/// A ZQLite release number
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    /// Major release; changes break the C ABI
+    pub major: u32,
+    /// Minor release; only adds to the C API
+    pub minor: u32,
+    /// Patch release
+    pub patch: u32,
+}
+
+impl Version {
+    /// Version of the `zqlite.h` these bindings were generated from
+    pub const HEADER: Version = Version::from_number(ffi::ZQLITE_VERSION_NUMBER);
+
+    /// Create a version from its parts
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self { major, minor, patch }
+    }
+
+    /// Decode `major * 1000000 + minor * 1000 + patch`
+    pub const fn from_number(number: u32) -> Self {
+        Self::new(number / 1_000_000, number / 1_000 % 1_000, number % 1_000)
+    }
+
+    /// Encode as `major * 1000000 + minor * 1000 + patch`
+    pub const fn number(self) -> u32 {
+        self.major * 1_000_000 + self.minor * 1_000 + self.patch
+    }
+
+    /// Version of the library linked at runtime
+    ///
+    /// Parsed from `zqlite_version()`, which every release exports, so this
+    /// links against libraries older than the header. A version string that
+    /// doesn't parse reads as 0.0.0, which no bindings support.
+    pub fn library() -> Self {
+        Self::parse(crate::Connection::version()).unwrap_or(Self::new(0, 0, 0))
+    }
+
+    /// Parse `1.3.3`, also with a `v` prefix or a suffix such as `-beta`
+    fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let mut parts = text.strip_prefix('v').unwrap_or(text).splitn(3, '.');
+        let mut part = || {
+            let part = parts.next()?;
+            let end = part.find(|c: char| !c.is_ascii_digit()).unwrap_or(part.len());
+            part[..end].parse().ok()
+        };
+        Some(Self::new(part()?, part()?, part()?))
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Library releases these bindings work with: the header's release up to,
+/// but not including, the next major release
+pub const SUPPORTED_VERSIONS: Range<Version> =
+    Version::HEADER..Version::new(Version::HEADER.major + 1, 0, 0);
+
+/// Check that the linked library is within [`SUPPORTED_VERSIONS`]
+///
+/// [`Connection::open`](crate::Connection::open) runs this once per process;
+/// call it at startup to fail before any database work. Returns the library
+/// version.
+pub fn check_library_version() -> Result<Version> {
+    static CHECKED: OnceLock<std::result::Result<Version, Version>> = OnceLock::new();
+
+    let checked = CHECKED.get_or_init(|| {
+        let library = Version::library();
+        if SUPPORTED_VERSIONS.contains(&library) {
+            Ok(library)
+        } else {
+            Err(library)
+        }
+    });
+
+    checked.map_err(|found| Error::IncompatibleLibrary {
+        found,
+        expected: Version::HEADER,
+    })
+}
+
+/// Optional features compiled into the ZQLite library
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities(c_uint);
+
+impl Capabilities {
+    /// Encrypted databases and crypto functions
+    pub const ENCRYPTION: Self = Self(ffi::ZQLITE_CAPABILITY_ENCRYPTION as c_uint);
+    /// JSON and JSONB column types
+    pub const JSON: Self = Self(ffi::ZQLITE_CAPABILITY_JSON as c_uint);
+    /// Write-ahead log for file databases
+    pub const WAL: Self = Self(ffi::ZQLITE_CAPABILITY_WAL as c_uint);
+
+    /// Capabilities of the library linked at runtime
+    pub fn library() -> Self {
+        Self(unsafe { ffi::zqlite_capabilities() })
+    }
+
+    /// Check if every capability in `other` is present
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Raw capability bits reported by ZQLite
+    pub fn bits(self) -> c_uint {
+        self.0
+    }
+}
+
+impl std::ops::BitOr for Capabilities {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = [
+            (Self::ENCRYPTION, "encryption"),
+            (Self::JSON, "json"),
+            (Self::WAL, "wal"),
+        ];
+        let present: Vec<&str> = names
+            .iter()
+            .filter(|(capability, _)| self.contains(*capability))
+            .map(|(_, name)| *name)
+            .collect();
+
+        if present.is_empty() {
+            write!(f, "none")
+        } else {
+            write!(f, "{}", present.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_number_round_trip() {
+        let version = Version::from_number(1_003_003);
+        assert_eq!(version, Version::new(1, 3, 3));
+        assert_eq!(version.number(), 1_003_003);
+        assert_eq!(version.to_string(), "1.3.3");
+    }
+
+    #[test]
+    fn test_version_parsing() {
+        assert_eq!(Version::parse("1.3.3"), Some(Version::new(1, 3, 3)));
+        assert_eq!(Version::parse("v2.0.11-beta"), Some(Version::new(2, 0, 11)));
+        assert_eq!(Version::parse("1.3"), None);
+        assert_eq!(Version::parse("unknown"), None);
+    }
+
+    #[test]
+    fn test_supported_versions() {
+        let header = Version::HEADER;
+        assert!(SUPPORTED_VERSIONS.contains(&header));
+        assert!(SUPPORTED_VERSIONS.contains(&Version::new(header.major, header.minor + 1, 0)));
+        assert!(!SUPPORTED_VERSIONS.contains(&Version::new(header.major + 1, 0, 0)));
+        if header.patch > 0 {
+            assert!(!SUPPORTED_VERSIONS.contains(&Version::new(header.major, header.minor, 0)));
+        }
+    }
+
+    #[test]
+    fn test_linked_library_is_compatible() {
+        assert_eq!(check_library_version().unwrap(), Version::library());
+    }
+
+    #[test]
+    fn test_capabilities() {
+        let capabilities = Capabilities::library();
+        assert!(capabilities.contains(Capabilities::WAL));
+        assert!(!Capabilities::JSON.contains(Capabilities::JSON | Capabilities::WAL));
+        assert_eq!((Capabilities::JSON | Capabilities::WAL).to_string(), "json, wal");
+    }
+}
@@ -0,0 +1,260 @@
+//! Locates or builds the ZQLite C library and its bindings
+//!
+//! Library sources, in order of preference:
+//! - `bundled` feature: build the sources vendored in `vendor/zqlite` with Zig
+//! - `ZQLITE_LIB_DIR`: link a prebuilt `libzqlite_c` from that directory
+//! - pkg-config: link the system `zqlite_c`
+//! - otherwise build the checkout at `ZQLITE_DIR` (default `../../`)
+//!
+//! `ZQLITE_STATIC=1` or `0` forces static or dynamic linking. The default is
+//! static, except for libraries found through pkg-config.
+//!
+//! The version in `zqlite.h` must match the library's (build.zig.zon for
+//! source builds, the .pc file for pkg-config). Prebuilt libraries from
+//! `ZQLITE_LIB_DIR` are trusted as-is.
+
+use std::env;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Zig release the ZQLite sources are written for
+const ZIG_VERSION: &str = "0.15";
+const LIB_NAME: &str = "zqlite_c";
+
+type BuildResult<T> = Result<T, Box<dyn Error>>;
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> BuildResult<()> {
+    for var in [
+        "ZQLITE_DIR",
+        "ZQLITE_LIB_DIR",
+        "ZQLITE_INCLUDE_DIR",
+        "ZQLITE_STATIC",
+        "ZIG",
+    ] {
+        println!("cargo:rerun-if-env-changed={}", var);
+    }
+
+    let (include_dir, library_version) = if cfg!(feature = "bundled") {
+        let vendor_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR")?).join("vendor/zqlite");
+        build_from_source(&vendor_dir, static_linking(true))?
+    } else if let Ok(lib_dir) = env::var("ZQLITE_LIB_DIR") {
+        let lib_dir = PathBuf::from(lib_dir);
+        link_library(&lib_dir, static_linking(true));
+        let include_dir = env::var("ZQLITE_INCLUDE_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| lib_dir.join("../include"));
+        (include_dir, None)
+    } else if let Some(found) = probe_pkg_config() {
+        found
+    } else {
+        let source_dir = env::var("ZQLITE_DIR").unwrap_or_else(|_| "../../".to_string());
+        build_from_source(Path::new(&source_dir), static_linking(true))?
+    };
+
+    check_header_version(&include_dir, library_version.as_deref())?;
+    println!("cargo:include={}", include_dir.display());
+    generate_bindings(&include_dir)
+}
+
+/// Whether to link statically, honouring `ZQLITE_STATIC`
+fn static_linking(default: bool) -> bool {
+    match env::var("ZQLITE_STATIC").as_deref() {
+        Ok("1") | Ok("true") => true,
+        Ok("0") | Ok("false") => false,
+        _ => default,
+    }
+}
+
+fn link_library(lib_dir: &Path, statik: bool) {
+    println!("cargo:rustc-link-search=native={}", lib_dir.display());
+    let kind = if statik { "static" } else { "dylib" };
+    println!("cargo:rustc-link-lib={}={}", kind, LIB_NAME);
+}
+
+/// Find a system library; pkg-config prints the link flags itself
+fn probe_pkg_config() -> Option<(PathBuf, Option<String>)> {
+    let library = pkg_config::Config::new()
+        .statik(static_linking(false))
+        .probe(LIB_NAME)
+        .ok()?;
+
+    let include_dir = env::var("ZQLITE_INCLUDE_DIR")
+        .map(PathBuf::from)
+        .ok()
+        .or_else(|| library.include_paths.first().cloned())
+        .unwrap_or_else(|| PathBuf::from("/usr/include"));
+    Some((include_dir, Some(library.version)))
+}
+
+/// `major.minor.patch` as `major * 1000000 + minor * 1000 + patch`
+fn version_number(version: &str) -> Option<u32> {
+    let mut parts = version.trim().splitn(3, '.').map(|part| {
+        let digits: String = part.chars().take_while(char::is_ascii_digit).collect();
+        digits.parse::<u32>().ok()
+    });
+    let major = parts.next()??;
+    let minor = parts.next().flatten().unwrap_or(0);
+    let patch = parts.next().flatten().unwrap_or(0);
+    Some(major * 1_000_000 + minor * 1_000 + patch)
+}
+
+/// Fail early when the header and the library come from different releases
+fn check_header_version(include_dir: &Path, library_version: Option<&str>) -> BuildResult<()> {
+    let header = include_dir.join("zqlite.h");
+    let contents = std::fs::read_to_string(&header)
+        .map_err(|e| format!("cannot read {}: {}", header.display(), e))?;
+    let header_number = contents
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("#define ZQLITE_VERSION_NUMBER"))
+        .find_map(|value| value.trim().parse::<u32>().ok())
+        .ok_or_else(|| format!("{} does not define ZQLITE_VERSION_NUMBER", header.display()))?;
+
+    println!("cargo:version_number={}", header_number);
+
+    let Some(library_version) = library_version else {
+        return Ok(());
+    };
+    match version_number(library_version) {
+        Some(library_number) if library_number != header_number => Err(format!(
+            "{} is for ZQLite {} but the library is {}",
+            header.display(),
+            header_number,
+            library_version
+        )
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+/// Release declared in a source tree's build.zig.zon
+fn source_version(source_dir: &Path) -> Option<String> {
+    let manifest = std::fs::read_to_string(source_dir.join("build.zig.zon")).ok()?;
+    manifest.lines().find_map(|line| {
+        let value = line.trim().strip_prefix(".version")?;
+        let value = value.trim_start().strip_prefix('=')?;
+        Some(
+            value
+                .trim()
+                .trim_end_matches(',')
+                .trim_matches('"')
+                .to_string(),
+        )
+    })
+}
+
+fn zig() -> String {
+    env::var("ZIG").unwrap_or_else(|_| "zig".to_string())
+}
+
+/// Refuse to build with a Zig release the sources were not written for
+fn check_zig_version() -> BuildResult<()> {
+    let output = Command::new(zig()).arg("version").output().map_err(|e| {
+        format!(
+            "could not run `{} version` ({}); install Zig {} or set ZIG",
+            zig(),
+            e,
+            ZIG_VERSION
+        )
+    })?;
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+    let matches = version == ZIG_VERSION || version.starts_with(&format!("{}.", ZIG_VERSION));
+    if !matches {
+        return Err(format!("ZQLite needs Zig {}.x, found {}", ZIG_VERSION, version).into());
+    }
+    Ok(())
+}
+
+/// Build `libzqlite_c` from a source tree into OUT_DIR and link it
+fn build_from_source(source_dir: &Path, statik: bool) -> BuildResult<(PathBuf, Option<String>)> {
+    if !source_dir.join("build.zig").exists() {
+        return Err(format!(
+            "no ZQLite sources in {}; restore vendor/zqlite with scripts/vendor-zqlite.sh",
+            source_dir.display()
+        )
+        .into());
+    }
+    check_zig_version()?;
+
+    let out_dir = PathBuf::from(env::var("OUT_DIR")?);
+    let prefix = out_dir.join("zqlite");
+    let optimize = if env::var("PROFILE").as_deref() == Ok("release") {
+        "ReleaseFast"
+    } else {
+        "Debug"
+    };
+    let linkage = if statik { "static" } else { "dynamic" };
+
+    println!("cargo:warning=Building ZQLite C library...");
+    let output = Command::new(zig())
+        .arg("build")
+        .arg("c-lib")
+        .arg(format!("-Doptimize={}", optimize))
+        .arg(format!("-Dc-linkage={}", linkage))
+        .arg("--prefix")
+        .arg(&prefix)
+        .arg("--cache-dir")
+        .arg(out_dir.join("zig-cache"))
+        .current_dir(source_dir)
+        .output()
+        .map_err(|e| format!("failed to run zig build: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "zig build failed:\n{}",
+            String::from_utf8_lossy(&output.stderr)
+        )
+        .into());
+    }
+
+    link_library(&prefix.join("lib"), statik);
+    println!(
+        "cargo:rerun-if-changed={}",
+        source_dir.join("src").display()
+    );
+    println!(
+        "cargo:rerun-if-changed={}",
+        source_dir.join("build.zig").display()
+    );
+
+    Ok((source_dir.join("include"), source_version(source_dir)))
+}
+
+/// Regenerate the bindings from the header (needs libclang)
+#[cfg(feature = "buildtime_bindgen")]
+fn generate_bindings(include_dir: &Path) -> BuildResult<()> {
+    let header = include_dir.join("zqlite.h");
+    println!("cargo:rerun-if-changed={}", header.display());
+
+    let bindings = bindgen::Builder::default()
+        .header(header.to_string_lossy())
+        .parse_callbacks(Box::new(bindgen::CargoCallbacks::new()))
+        .allowlist_function("zqlite_.*")
+        .allowlist_type("zqlite_.*")
+        .allowlist_var("ZQLITE_.*")
+        .derive_debug(true)
+        .derive_default(true)
+        .derive_copy(true)
+        .derive_eq(true)
+        .derive_partialeq(true)
+        .generate()
+        .map_err(|e| format!("bindgen failed on {}: {}", header.display(), e))?;
+
+    let out_path = PathBuf::from(env::var("OUT_DIR")?).join("bindings.rs");
+    bindings.write_to_file(out_path)?;
+    Ok(())
+}
+
+/// The pre-generated bindings in src/bindings are used instead
+#[cfg(not(feature = "buildtime_bindgen"))]
+fn generate_bindings(_include_dir: &Path) -> BuildResult<()> {
+    Ok(())
+}
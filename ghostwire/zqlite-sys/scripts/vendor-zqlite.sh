@@ -0,0 +1,15 @@
+#!/bin/sh
+# Copy the ZQLite sources into vendor/zqlite for the `bundled` feature.
+# The copy is checked in; rerun this and commit the result after engine changes.
+set -eu
+
+crate_dir="$(cd "$(dirname "$0")/.." && pwd)"
+source_dir="${ZQLITE_DIR:-$crate_dir/../..}"
+vendor_dir="$crate_dir/vendor/zqlite"
+
+rm -rf "$vendor_dir"
+mkdir -p "$vendor_dir"
+cp "$source_dir/build.zig" "$source_dir/build.zig.zon" "$source_dir/LICENSE" "$vendor_dir/"
+cp -R "$source_dir/src" "$source_dir/include" "$vendor_dir/"
+
+echo "Vendored ZQLite from $source_dir into $vendor_dir"
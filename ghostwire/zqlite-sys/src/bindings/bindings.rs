@@ -0,0 +1,582 @@
+/* automatically generated by rust-bindgen 0.70.1 */
+
+pub const ZQLITE_VERSION_NUMBER: u32 = 1003003;
+pub const ZQLITE_OK: u32 = 0;
+pub const ZQLITE_ERROR: u32 = 1;
+pub const ZQLITE_INTERNAL: u32 = 2;
+pub const ZQLITE_PERM: u32 = 3;
+pub const ZQLITE_ABORT: u32 = 4;
+pub const ZQLITE_BUSY: u32 = 5;
+pub const ZQLITE_LOCKED: u32 = 6;
+pub const ZQLITE_NOMEM: u32 = 7;
+pub const ZQLITE_READONLY: u32 = 8;
+pub const ZQLITE_INTERRUPT: u32 = 9;
+pub const ZQLITE_IOERR: u32 = 10;
+pub const ZQLITE_CORRUPT: u32 = 11;
+pub const ZQLITE_NOTFOUND: u32 = 12;
+pub const ZQLITE_FULL: u32 = 13;
+pub const ZQLITE_CANTOPEN: u32 = 14;
+pub const ZQLITE_PROTOCOL: u32 = 15;
+pub const ZQLITE_EMPTY: u32 = 16;
+pub const ZQLITE_SCHEMA: u32 = 17;
+pub const ZQLITE_TOOBIG: u32 = 18;
+pub const ZQLITE_CONSTRAINT: u32 = 19;
+pub const ZQLITE_MISMATCH: u32 = 20;
+pub const ZQLITE_MISUSE: u32 = 21;
+pub const ZQLITE_NOLFS: u32 = 22;
+pub const ZQLITE_AUTH: u32 = 23;
+pub const ZQLITE_FORMAT: u32 = 24;
+pub const ZQLITE_RANGE: u32 = 25;
+pub const ZQLITE_NOTADB: u32 = 26;
+pub const ZQLITE_ROW: u32 = 100;
+pub const ZQLITE_DONE: u32 = 101;
+pub const ZQLITE_INTEGER: u32 = 1;
+pub const ZQLITE_FLOAT: u32 = 2;
+pub const ZQLITE_TEXT: u32 = 3;
+pub const ZQLITE_BLOB: u32 = 4;
+pub const ZQLITE_NULL: u32 = 5;
+pub const ZQLITE_DELETE: u32 = 9;
+pub const ZQLITE_INSERT: u32 = 18;
+pub const ZQLITE_UPDATE: u32 = 23;
+pub const ZQLITE_UTF8: u32 = 1;
+pub const ZQLITE_DETERMINISTIC: u32 = 2048;
+pub const ZQLITE_DIRECTONLY: u32 = 524288;
+pub const ZQLITE_INNOCUOUS: u32 = 2097152;
+pub const ZQLITE_CAPABILITY_ENCRYPTION: u32 = 1;
+pub const ZQLITE_CAPABILITY_JSON: u32 = 2;
+pub const ZQLITE_CAPABILITY_WAL: u32 = 4;
+pub const ZQLITE_VERSION: &[u8; 6] = b"1.3.3\0";
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct zqlite_connection {
+    _unused: [u8; 0],
+}
+pub type zqlite_connection_t = zqlite_connection;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct zqlite_result {
+    _unused: [u8; 0],
+}
+pub type zqlite_result_t = zqlite_result;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct zqlite_stmt {
+    _unused: [u8; 0],
+}
+pub type zqlite_stmt_t = zqlite_stmt;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct zqlite_context {
+    _unused: [u8; 0],
+}
+pub type zqlite_context_t = zqlite_context;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct zqlite_value {
+    _unused: [u8; 0],
+}
+pub type zqlite_value_t = zqlite_value;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct zqlite_blob {
+    _unused: [u8; 0],
+}
+pub type zqlite_blob_t = zqlite_blob;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct zqlite_schema {
+    _unused: [u8; 0],
+}
+pub type zqlite_schema_t = zqlite_schema;
+pub type zqlite_func_callback = ::std::option::Option<
+    unsafe extern "C" fn(
+        ctx: *mut zqlite_context_t,
+        argc: ::std::os::raw::c_int,
+        argv: *mut *mut zqlite_value_t,
+    ),
+>;
+pub type zqlite_final_callback =
+    ::std::option::Option<unsafe extern "C" fn(ctx: *mut zqlite_context_t)>;
+pub type zqlite_destroy_callback =
+    ::std::option::Option<unsafe extern "C" fn(user_data: *mut ::std::os::raw::c_void)>;
+pub type zqlite_update_callback = ::std::option::Option<
+    unsafe extern "C" fn(
+        user_data: *mut ::std::os::raw::c_void,
+        op: ::std::os::raw::c_int,
+        db_name: *const ::std::os::raw::c_char,
+        table: *const ::std::os::raw::c_char,
+        rowid: i64,
+    ),
+>;
+pub type zqlite_commit_callback = ::std::option::Option<
+    unsafe extern "C" fn(user_data: *mut ::std::os::raw::c_void) -> ::std::os::raw::c_int,
+>;
+pub type zqlite_after_commit_callback =
+    ::std::option::Option<unsafe extern "C" fn(user_data: *mut ::std::os::raw::c_void)>;
+pub type zqlite_rollback_callback =
+    ::std::option::Option<unsafe extern "C" fn(user_data: *mut ::std::os::raw::c_void)>;
+extern "C" {
+    pub fn zqlite_open(path: *const ::std::os::raw::c_char) -> *mut zqlite_connection_t;
+}
+extern "C" {
+    pub fn zqlite_open_encrypted(
+        path: *const ::std::os::raw::c_char,
+        password: *const ::std::os::raw::c_char,
+    ) -> *mut zqlite_connection_t;
+}
+extern "C" {
+    pub fn zqlite_close(conn: *mut zqlite_connection_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_execute(
+        conn: *mut zqlite_connection_t,
+        sql: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_query(
+        conn: *mut zqlite_connection_t,
+        sql: *const ::std::os::raw::c_char,
+    ) -> *mut zqlite_result_t;
+}
+extern "C" {
+    pub fn zqlite_result_row_count(result: *mut zqlite_result_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_result_column_count(result: *mut zqlite_result_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_result_column_name(
+        result: *mut zqlite_result_t,
+        column: ::std::os::raw::c_int,
+    ) -> *const ::std::os::raw::c_char;
+}
+extern "C" {
+    pub fn zqlite_result_column_type(
+        result: *mut zqlite_result_t,
+        row: ::std::os::raw::c_int,
+        column: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_result_get_text(
+        result: *mut zqlite_result_t,
+        row: ::std::os::raw::c_int,
+        column: ::std::os::raw::c_int,
+    ) -> *const ::std::os::raw::c_char;
+}
+extern "C" {
+    pub fn zqlite_result_get_int(
+        result: *mut zqlite_result_t,
+        row: ::std::os::raw::c_int,
+        column: ::std::os::raw::c_int,
+    ) -> i64;
+}
+extern "C" {
+    pub fn zqlite_result_get_real(
+        result: *mut zqlite_result_t,
+        row: ::std::os::raw::c_int,
+        column: ::std::os::raw::c_int,
+    ) -> f64;
+}
+extern "C" {
+    pub fn zqlite_result_get_blob(
+        result: *mut zqlite_result_t,
+        row: ::std::os::raw::c_int,
+        column: ::std::os::raw::c_int,
+        size: *mut ::std::os::raw::c_int,
+    ) -> *const ::std::os::raw::c_void;
+}
+extern "C" {
+    pub fn zqlite_result_free(result: *mut zqlite_result_t);
+}
+extern "C" {
+    pub fn zqlite_prepare(
+        conn: *mut zqlite_connection_t,
+        sql: *const ::std::os::raw::c_char,
+    ) -> *mut zqlite_stmt_t;
+}
+extern "C" {
+    pub fn zqlite_bind_int(
+        stmt: *mut zqlite_stmt_t,
+        index: ::std::os::raw::c_int,
+        value: i64,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_bind_real(
+        stmt: *mut zqlite_stmt_t,
+        index: ::std::os::raw::c_int,
+        value: f64,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_bind_text(
+        stmt: *mut zqlite_stmt_t,
+        index: ::std::os::raw::c_int,
+        value: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_bind_blob(
+        stmt: *mut zqlite_stmt_t,
+        index: ::std::os::raw::c_int,
+        data: *const ::std::os::raw::c_void,
+        size: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_bind_null(
+        stmt: *mut zqlite_stmt_t,
+        index: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_bind_zeroblob(
+        stmt: *mut zqlite_stmt_t,
+        index: ::std::os::raw::c_int,
+        size: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_step(stmt: *mut zqlite_stmt_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_query_prepared(
+        conn: *mut zqlite_connection_t,
+        stmt: *mut zqlite_stmt_t,
+    ) -> *mut zqlite_result_t;
+}
+extern "C" {
+    pub fn zqlite_reset(stmt: *mut zqlite_stmt_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_finalize(stmt: *mut zqlite_stmt_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_column_count(stmt: *mut zqlite_stmt_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_column_name(
+        stmt: *mut zqlite_stmt_t,
+        column: ::std::os::raw::c_int,
+    ) -> *const ::std::os::raw::c_char;
+}
+extern "C" {
+    pub fn zqlite_column_type(
+        stmt: *mut zqlite_stmt_t,
+        column: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_column_text(
+        stmt: *mut zqlite_stmt_t,
+        column: ::std::os::raw::c_int,
+    ) -> *const ::std::os::raw::c_char;
+}
+extern "C" {
+    pub fn zqlite_column_int(stmt: *mut zqlite_stmt_t, column: ::std::os::raw::c_int) -> i64;
+}
+extern "C" {
+    pub fn zqlite_column_real(stmt: *mut zqlite_stmt_t, column: ::std::os::raw::c_int) -> f64;
+}
+extern "C" {
+    pub fn zqlite_column_blob(
+        stmt: *mut zqlite_stmt_t,
+        column: ::std::os::raw::c_int,
+        size: *mut ::std::os::raw::c_int,
+    ) -> *const ::std::os::raw::c_void;
+}
+extern "C" {
+    pub fn zqlite_begin_transaction(conn: *mut zqlite_connection_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_commit_transaction(conn: *mut zqlite_connection_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_rollback_transaction(conn: *mut zqlite_connection_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_get_autocommit(conn: *mut zqlite_connection_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_json_extract(
+        conn: *mut zqlite_connection_t,
+        json: *const ::std::os::raw::c_char,
+        path: *const ::std::os::raw::c_char,
+        result: *mut *mut ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_json_set(
+        conn: *mut zqlite_connection_t,
+        json: *const ::std::os::raw::c_char,
+        path: *const ::std::os::raw::c_char,
+        value: *const ::std::os::raw::c_char,
+        result: *mut *mut ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_json_type(
+        conn: *mut zqlite_connection_t,
+        json: *const ::std::os::raw::c_char,
+        path: *const ::std::os::raw::c_char,
+        result: *mut *mut ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_create_function(
+        conn: *mut zqlite_connection_t,
+        name: *const ::std::os::raw::c_char,
+        n_args: ::std::os::raw::c_int,
+        flags: ::std::os::raw::c_int,
+        user_data: *mut ::std::os::raw::c_void,
+        x_func: zqlite_func_callback,
+        x_step: zqlite_func_callback,
+        x_final: zqlite_final_callback,
+        x_destroy: zqlite_destroy_callback,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_value_type(value: *mut zqlite_value_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_value_int(value: *mut zqlite_value_t) -> i64;
+}
+extern "C" {
+    pub fn zqlite_value_real(value: *mut zqlite_value_t) -> f64;
+}
+extern "C" {
+    pub fn zqlite_value_text(
+        value: *mut zqlite_value_t,
+        size: *mut ::std::os::raw::c_int,
+    ) -> *const ::std::os::raw::c_char;
+}
+extern "C" {
+    pub fn zqlite_value_blob(
+        value: *mut zqlite_value_t,
+        size: *mut ::std::os::raw::c_int,
+    ) -> *const ::std::os::raw::c_void;
+}
+extern "C" {
+    pub fn zqlite_user_data(ctx: *mut zqlite_context_t) -> *mut ::std::os::raw::c_void;
+}
+extern "C" {
+    pub fn zqlite_aggregate_context(
+        ctx: *mut zqlite_context_t,
+        n_bytes: ::std::os::raw::c_int,
+    ) -> *mut ::std::os::raw::c_void;
+}
+extern "C" {
+    pub fn zqlite_context_result_int(ctx: *mut zqlite_context_t, value: i64);
+}
+extern "C" {
+    pub fn zqlite_context_result_real(ctx: *mut zqlite_context_t, value: f64);
+}
+extern "C" {
+    pub fn zqlite_context_result_text(
+        ctx: *mut zqlite_context_t,
+        value: *const ::std::os::raw::c_char,
+        size: ::std::os::raw::c_int,
+    );
+}
+extern "C" {
+    pub fn zqlite_context_result_blob(
+        ctx: *mut zqlite_context_t,
+        value: *const ::std::os::raw::c_void,
+        size: ::std::os::raw::c_int,
+    );
+}
+extern "C" {
+    pub fn zqlite_context_result_null(ctx: *mut zqlite_context_t);
+}
+extern "C" {
+    pub fn zqlite_context_result_error(
+        ctx: *mut zqlite_context_t,
+        message: *const ::std::os::raw::c_char,
+        size: ::std::os::raw::c_int,
+    );
+}
+extern "C" {
+    pub fn zqlite_blob_open(
+        conn: *mut zqlite_connection_t,
+        db_name: *const ::std::os::raw::c_char,
+        table: *const ::std::os::raw::c_char,
+        column: *const ::std::os::raw::c_char,
+        rowid: i64,
+        flags: ::std::os::raw::c_int,
+        blob: *mut *mut zqlite_blob_t,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_blob_bytes(blob: *mut zqlite_blob_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_blob_read(
+        blob: *mut zqlite_blob_t,
+        buffer: *mut ::std::os::raw::c_void,
+        n: ::std::os::raw::c_int,
+        offset: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_blob_write(
+        blob: *mut zqlite_blob_t,
+        buffer: *const ::std::os::raw::c_void,
+        n: ::std::os::raw::c_int,
+        offset: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_blob_close(blob: *mut zqlite_blob_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_update_hook(
+        conn: *mut zqlite_connection_t,
+        callback: zqlite_update_callback,
+        user_data: *mut ::std::os::raw::c_void,
+    ) -> *mut ::std::os::raw::c_void;
+}
+extern "C" {
+    pub fn zqlite_commit_hook(
+        conn: *mut zqlite_connection_t,
+        callback: zqlite_commit_callback,
+        user_data: *mut ::std::os::raw::c_void,
+    ) -> *mut ::std::os::raw::c_void;
+}
+extern "C" {
+    pub fn zqlite_after_commit_hook(
+        conn: *mut zqlite_connection_t,
+        callback: zqlite_after_commit_callback,
+        user_data: *mut ::std::os::raw::c_void,
+    ) -> *mut ::std::os::raw::c_void;
+}
+extern "C" {
+    pub fn zqlite_rollback_hook(
+        conn: *mut zqlite_connection_t,
+        callback: zqlite_rollback_callback,
+        user_data: *mut ::std::os::raw::c_void,
+    ) -> *mut ::std::os::raw::c_void;
+}
+extern "C" {
+    pub fn zqlite_schema(conn: *mut zqlite_connection_t) -> *mut zqlite_schema_t;
+}
+extern "C" {
+    pub fn zqlite_schema_free(schema: *mut zqlite_schema_t);
+}
+extern "C" {
+    pub fn zqlite_schema_table_count(schema: *mut zqlite_schema_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_schema_table_name(
+        schema: *mut zqlite_schema_t,
+        table: ::std::os::raw::c_int,
+    ) -> *const ::std::os::raw::c_char;
+}
+extern "C" {
+    pub fn zqlite_schema_column_count(
+        schema: *mut zqlite_schema_t,
+        table: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_schema_column(
+        schema: *mut zqlite_schema_t,
+        table: ::std::os::raw::c_int,
+        column: ::std::os::raw::c_int,
+        name: *mut *const ::std::os::raw::c_char,
+        decl_type: *mut *const ::std::os::raw::c_char,
+        not_null: *mut ::std::os::raw::c_int,
+        default_value: *mut *const ::std::os::raw::c_char,
+        pk: *mut ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_schema_index_count(
+        schema: *mut zqlite_schema_t,
+        table: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_schema_index(
+        schema: *mut zqlite_schema_t,
+        table: ::std::os::raw::c_int,
+        index: ::std::os::raw::c_int,
+        name: *mut *const ::std::os::raw::c_char,
+        unique: *mut ::std::os::raw::c_int,
+        column_count: *mut ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_schema_index_column(
+        schema: *mut zqlite_schema_t,
+        table: ::std::os::raw::c_int,
+        index: ::std::os::raw::c_int,
+        column: ::std::os::raw::c_int,
+    ) -> *const ::std::os::raw::c_char;
+}
+extern "C" {
+    pub fn zqlite_schema_foreign_key_count(
+        schema: *mut zqlite_schema_t,
+        table: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_schema_foreign_key(
+        schema: *mut zqlite_schema_t,
+        table: ::std::os::raw::c_int,
+        foreign_key: ::std::os::raw::c_int,
+        column: *mut *const ::std::os::raw::c_char,
+        reference_table: *mut *const ::std::os::raw::c_char,
+        reference_column: *mut *const ::std::os::raw::c_char,
+        on_delete: *mut *const ::std::os::raw::c_char,
+        on_update: *mut *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_errmsg(conn: *mut zqlite_connection_t) -> *const ::std::os::raw::c_char;
+}
+extern "C" {
+    pub fn zqlite_errcode(conn: *mut zqlite_connection_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_interrupt(conn: *mut zqlite_connection_t);
+}
+extern "C" {
+    pub fn zqlite_version() -> *const ::std::os::raw::c_char;
+}
+extern "C" {
+    pub fn zqlite_libversion_number() -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_capabilities() -> ::std::os::raw::c_uint;
+}
+extern "C" {
+    pub fn zqlite_last_insert_rowid(conn: *mut zqlite_connection_t) -> i64;
+}
+extern "C" {
+    pub fn zqlite_changes(conn: *mut zqlite_connection_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_shutdown();
+}
+extern "C" {
+    pub fn zqlite_enable_wal_mode(conn: *mut zqlite_connection_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_vacuum(conn: *mut zqlite_connection_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_backup(
+        conn: *mut zqlite_connection_t,
+        dest_path: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zqlite_create_index(
+        conn: *mut zqlite_connection_t,
+        table: *const ::std::os::raw::c_char,
+        column: *const ::std::os::raw::c_char,
+        index_type: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
@@ -0,0 +1,16 @@
+//! Raw FFI bindings to the ZQLite C library
+//!
+//! These are the declarations from `include/zqlite.h` and nothing more; use
+//! `zqlite-rs` for a safe API. The build script links the library (see
+//! `build.rs` for how it is found) and exports its header directory and
+//! version number to dependent build scripts as `DEP_ZQLITE_INCLUDE` and
+//! `DEP_ZQLITE_VERSION_NUMBER`.
+
+#![allow(non_camel_case_types, non_snake_case, non_upper_case_globals)]
+
+// The checked-in copy is refreshed by building with `buildtime_bindgen` and
+// copying OUT_DIR/bindings.rs over it.
+#[cfg(feature = "buildtime_bindgen")]
+include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+#[cfg(not(feature = "buildtime_bindgen"))]
+include!("bindings/bindings.rs");
@@ -11,10 +11,19 @@ extern "C" {
 #include <stdint.h>
 #include <stddef.h>
 
+// Version of the library this header belongs to; the number is
+// major * 1000000 + minor * 1000 + patch
+#define ZQLITE_VERSION        "1.3.3"
+#define ZQLITE_VERSION_NUMBER 1003003
+
 // Opaque types
 typedef struct zqlite_connection zqlite_connection_t;
 typedef struct zqlite_result zqlite_result_t;
 typedef struct zqlite_stmt zqlite_stmt_t;
+typedef struct zqlite_context zqlite_context_t;
+typedef struct zqlite_value zqlite_value_t;
+typedef struct zqlite_blob zqlite_blob_t;
+typedef struct zqlite_schema zqlite_schema_t;
 
 // Error codes (compatible with SQLite)
 #define ZQLITE_OK           0   // Successful result
@@ -54,6 +63,17 @@ typedef struct zqlite_stmt zqlite_stmt_t;
 #define ZQLITE_BLOB     4
 #define ZQLITE_NULL     5
 
+// Change operations reported to the update hook
+#define ZQLITE_DELETE   9
+#define ZQLITE_INSERT  18
+#define ZQLITE_UPDATE  23
+
+// Function flags for zqlite_create_function
+#define ZQLITE_UTF8           1
+#define ZQLITE_DETERMINISTIC  0x000000800
+#define ZQLITE_DIRECTONLY     0x000080000
+#define ZQLITE_INNOCUOUS      0x000200000
+
 // Core database operations
 zqlite_connection_t* zqlite_open(const char* path);
 zqlite_connection_t* zqlite_open_encrypted(const char* path, const char* password);
@@ -79,7 +99,9 @@ int zqlite_bind_real(zqlite_stmt_t* stmt, int index, double value);
 int zqlite_bind_text(zqlite_stmt_t* stmt, int index, const char* value);
 int zqlite_bind_blob(zqlite_stmt_t* stmt, int index, const void* data, int size);
 int zqlite_bind_null(zqlite_stmt_t* stmt, int index);
+int zqlite_bind_zeroblob(zqlite_stmt_t* stmt, int index, int size);
 int zqlite_step(zqlite_stmt_t* stmt);
+zqlite_result_t* zqlite_query_prepared(zqlite_connection_t* conn, zqlite_stmt_t* stmt);  // run with bound parameters
 int zqlite_reset(zqlite_stmt_t* stmt);
 int zqlite_finalize(zqlite_stmt_t* stmt);
 
@@ -96,18 +118,89 @@ const void* zqlite_column_blob(zqlite_stmt_t* stmt, int column, int* size);
 int zqlite_begin_transaction(zqlite_connection_t* conn);
 int zqlite_commit_transaction(zqlite_connection_t* conn);
 int zqlite_rollback_transaction(zqlite_connection_t* conn);
+int zqlite_get_autocommit(zqlite_connection_t* conn);  // 1 when no transaction is open
 
 // JSON support (zqlite extension)
 int zqlite_json_extract(zqlite_connection_t* conn, const char* json, const char* path, char** result);
 int zqlite_json_set(zqlite_connection_t* conn, const char* json, const char* path, const char* value, char** result);
 int zqlite_json_type(zqlite_connection_t* conn, const char* json, const char* path, char** result);
 
+// User-defined functions
+typedef void (*zqlite_func_callback)(zqlite_context_t* ctx, int argc, zqlite_value_t** argv);
+typedef void (*zqlite_final_callback)(zqlite_context_t* ctx);
+typedef void (*zqlite_destroy_callback)(void* user_data);
+
+// Scalar: pass x_func. Aggregate: pass x_step and x_final. All NULL removes the function.
+int zqlite_create_function(zqlite_connection_t* conn, const char* name, int n_args, int flags,
+                           void* user_data, zqlite_func_callback x_func, zqlite_func_callback x_step,
+                           zqlite_final_callback x_final, zqlite_destroy_callback x_destroy);
+int zqlite_value_type(zqlite_value_t* value);
+int64_t zqlite_value_int(zqlite_value_t* value);
+double zqlite_value_real(zqlite_value_t* value);
+const char* zqlite_value_text(zqlite_value_t* value, int* size);  // not null-terminated
+const void* zqlite_value_blob(zqlite_value_t* value, int* size);
+void* zqlite_user_data(zqlite_context_t* ctx);
+void* zqlite_aggregate_context(zqlite_context_t* ctx, int n_bytes);
+void zqlite_context_result_int(zqlite_context_t* ctx, int64_t value);
+void zqlite_context_result_real(zqlite_context_t* ctx, double value);
+void zqlite_context_result_text(zqlite_context_t* ctx, const char* value, int size);
+void zqlite_context_result_blob(zqlite_context_t* ctx, const void* value, int size);
+void zqlite_context_result_null(zqlite_context_t* ctx);
+void zqlite_context_result_error(zqlite_context_t* ctx, const char* message, int size);
+
+// Incremental blob I/O (flags: 0 read-only, 1 read-write). Blobs keep their size;
+// reserve space with zqlite_bind_zeroblob. Reads and writes go straight to the row.
+int zqlite_blob_open(zqlite_connection_t* conn, const char* db_name, const char* table, const char* column,
+                     int64_t rowid, int flags, zqlite_blob_t** blob);
+int zqlite_blob_bytes(zqlite_blob_t* blob);
+int zqlite_blob_read(zqlite_blob_t* blob, void* buffer, int n, int offset);
+int zqlite_blob_write(zqlite_blob_t* blob, const void* buffer, int n, int offset);
+int zqlite_blob_close(zqlite_blob_t* blob);
+
+// Change notification hooks (NULL callback removes; each returns the previous user data)
+typedef void (*zqlite_update_callback)(void* user_data, int op, const char* db_name, const char* table, int64_t rowid);
+typedef int (*zqlite_commit_callback)(void* user_data);  // non-zero turns the commit into a rollback
+typedef void (*zqlite_after_commit_callback)(void* user_data);  // the commit is durable
+typedef void (*zqlite_rollback_callback)(void* user_data);
+
+void* zqlite_update_hook(zqlite_connection_t* conn, zqlite_update_callback callback, void* user_data);
+void* zqlite_commit_hook(zqlite_connection_t* conn, zqlite_commit_callback callback, void* user_data);
+void* zqlite_after_commit_hook(zqlite_connection_t* conn, zqlite_after_commit_callback callback, void* user_data);
+void* zqlite_rollback_hook(zqlite_connection_t* conn, zqlite_rollback_callback callback, void* user_data);
+
+// Schema introspection. zqlite_schema takes a snapshot; its strings stay valid
+// until zqlite_schema_free. Tables and indexes are ordered by name.
+zqlite_schema_t* zqlite_schema(zqlite_connection_t* conn);
+void zqlite_schema_free(zqlite_schema_t* schema);
+int zqlite_schema_table_count(zqlite_schema_t* schema);
+const char* zqlite_schema_table_name(zqlite_schema_t* schema, int table);
+int zqlite_schema_column_count(zqlite_schema_t* schema, int table);
+int zqlite_schema_column(zqlite_schema_t* schema, int table, int column, const char** name, const char** decl_type,
+                         int* not_null, const char** default_value, int* pk);  // default_value NULL when none
+int zqlite_schema_index_count(zqlite_schema_t* schema, int table);
+int zqlite_schema_index(zqlite_schema_t* schema, int table, int index, const char** name, int* unique,
+                        int* column_count);
+const char* zqlite_schema_index_column(zqlite_schema_t* schema, int table, int index, int column);
+int zqlite_schema_foreign_key_count(zqlite_schema_t* schema, int table);
+int zqlite_schema_foreign_key(zqlite_schema_t* schema, int table, int foreign_key, const char** column,
+                              const char** reference_table, const char** reference_column,
+                              const char** on_delete, const char** on_update);
+
 // Error handling
 const char* zqlite_errmsg(zqlite_connection_t* conn);
 int zqlite_errcode(zqlite_connection_t* conn);
+void zqlite_interrupt(zqlite_connection_t* conn);  // thread-safe; running statement fails with ZQLITE_INTERRUPT
 
 // Utility functions
 const char* zqlite_version();
+int zqlite_libversion_number();  // runtime counterpart of ZQLITE_VERSION_NUMBER
+
+// Capability bits returned by zqlite_capabilities()
+#define ZQLITE_CAPABILITY_ENCRYPTION 0x01  // Encrypted databases and crypto functions
+#define ZQLITE_CAPABILITY_JSON       0x02  // JSON/JSONB column types
+#define ZQLITE_CAPABILITY_WAL        0x04  // Write-ahead log for file databases
+
+unsigned int zqlite_capabilities();
 int64_t zqlite_last_insert_rowid(zqlite_connection_t* conn);
 int zqlite_changes(zqlite_connection_t* conn);
 void zqlite_shutdown();
@@ -288,7 +288,7 @@ pub const MVCCTransactionManager = struct {
         const table_obj = self.storage_engine.getTable(table) orelse return error.TableNotFound;
         
         // For now, simple overwrite - in full MVCC we'd create new versions
-        try table_obj.insert(row);
+        _ = try table_obj.insert(row);
     }
 
     /// Delete from underlying storage
@@ -600,7 +600,7 @@ pub const SecureTable = struct {
             secure_row.values[i] = try secure_value.decrypt(self.crypto_engine);
         }
 
-        try self.base_table.insert(secure_row);
+        _ = try self.base_table.insert(secure_row);
     }
 
     pub fn deinit(self: *Self) void {
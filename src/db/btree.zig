@@ -30,6 +30,18 @@ pub const BTree = struct {
         return tree;
     }
 
+    /// Open a B-tree whose nodes are already stored, starting at `root_page`
+    pub fn open(allocator: std.mem.Allocator, page_manager: *pager.Pager, root_page: u32) !*Self {
+        const tree = try allocator.create(Self);
+        tree.* = .{
+            .allocator = allocator,
+            .pager = page_manager,
+            .root_page = root_page,
+            .order = DEFAULT_ORDER,
+        };
+        return tree;
+    }
+
     /// Insert a key-value pair
     pub fn insert(self: *Self, key: u64, value: storage.Row) !void {
         var root = try self.readNode(self.root_page);
@@ -39,6 +51,7 @@ pub const BTree = struct {
             // Split root
             const new_root_page = try self.pager.allocatePage();
             var new_root = try Node.initInternal(self.allocator, self.order);
+            defer new_root.deinit(self.allocator);
             new_root.children[0] = self.root_page;
 
             try self.splitChild(&new_root, 0);
@@ -54,10 +67,58 @@ pub const BTree = struct {
         return self.searchNode(self.root_page, key);
     }
 
+    /// Get a copy of the value stored under a key
+    pub fn get(self: *Self, allocator: std.mem.Allocator, key: u64) !?storage.Row {
+        return self.getFromNode(self.root_page, allocator, key);
+    }
+
+    /// Replace the value stored under an existing key
+    ///
+    /// Takes ownership of `value` only when the key exists and true is returned.
+    pub fn update(self: *Self, key: u64, value: storage.Row) !bool {
+        return self.updateNode(self.root_page, key, value);
+    }
+
+    /// Remove the value stored under a key; returns false if there is none
+    ///
+    /// Leaves are not merged afterwards, so separator keys in internal nodes
+    /// stay valid bounds for later lookups and inserts.
+    pub fn delete(self: *Self, key: u64) !bool {
+        return self.deleteFromNode(self.root_page, key);
+    }
+
+    /// Length of a stored text or blob value
+    pub fn valueLength(self: *Self, key: u64, column_index: usize) !usize {
+        const stored = try self.locateValue(key, column_index);
+        return stored.bytes.len;
+    }
+
+    /// Copy part of a stored text or blob value without reading the rest of the row
+    pub fn readValueRange(self: *Self, key: u64, column_index: usize, offset: usize, buffer: []u8) !void {
+        const stored = try self.locateValue(key, column_index);
+        if (offset + buffer.len > stored.bytes.len) return error.RangeOutOfBounds;
+        @memcpy(buffer, stored.bytes[offset..][0..buffer.len]);
+    }
+
+    /// Overwrite part of a stored text or blob value on its page; values can't grow
+    pub fn writeValueRange(self: *Self, key: u64, column_index: usize, offset: usize, data: []const u8) !void {
+        const stored = try self.locateValue(key, column_index);
+        if (offset + data.len > stored.bytes.len) return error.RangeOutOfBounds;
+        @memcpy(stored.bytes[offset..][0..data.len], data);
+        try self.pager.markDirty(stored.page_id);
+    }
+
     /// Select all rows (for table scans)
     pub fn selectAll(self: *Self, allocator: std.mem.Allocator) ![]storage.Row {
         var results = std.array_list.Managed(storage.Row).init(allocator);
-        try self.collectAllLeafValues(self.root_page, &results);
+        try self.collectAllLeafValues(self.root_page, &results, null);
+        return results.toOwnedSlice();
+    }
+
+    /// Select all rows along with their keys, in key order
+    pub fn selectAllWithKeys(self: *Self, allocator: std.mem.Allocator, keys: *std.array_list.Managed(u64)) ![]storage.Row {
+        var results = std.array_list.Managed(storage.Row).init(allocator);
+        try self.collectAllLeafValues(self.root_page, &results, keys);
         return results.toOwnedSlice();
     }
 
@@ -117,19 +178,23 @@ pub const BTree = struct {
             try Node.initLeaf(self.allocator, self.order)
         else
             try Node.initInternal(self.allocator, self.order);
+        defer new_child.deinit(self.allocator);
 
         const mid_index = self.order / 2;
 
-        // Move upper half of keys to new node
-        const keys_to_move = full_child.key_count - mid_index - 1;
+        // Move upper half of keys to new node. Leaves keep the middle key as
+        // the first key of the right half, since only leaves hold rows;
+        // internal nodes hand it up to the parent.
+        const first_moved = if (full_child.is_leaf) mid_index else mid_index + 1;
+        const keys_to_move = full_child.key_count - first_moved;
         if (keys_to_move > 0) {
-            @memcpy(new_child.keys[0..keys_to_move], full_child.keys[mid_index + 1 .. full_child.key_count]);
+            @memcpy(new_child.keys[0..keys_to_move], full_child.keys[first_moved..full_child.key_count]);
             new_child.key_count = @intCast(keys_to_move);
 
             if (full_child.is_leaf) {
-                @memcpy(new_child.values[0..keys_to_move], full_child.values[mid_index + 1 .. full_child.key_count]);
+                @memcpy(new_child.values[0..keys_to_move], full_child.values[first_moved..full_child.key_count]);
             } else {
-                @memcpy(new_child.children[0 .. keys_to_move + 1], full_child.children[mid_index + 1 .. full_child.key_count + 1]);
+                @memcpy(new_child.children[0 .. keys_to_move + 1], full_child.children[first_moved .. full_child.key_count + 1]);
             }
         }
 
@@ -181,8 +246,113 @@ pub const BTree = struct {
         return self.searchNode(node.children[search_result.index], key);
     }
 
-    /// Collect all values from leaf nodes (for table scans)
-    fn collectAllLeafValues(self: *Self, page_id: u32, results: *std.array_list.Managed(storage.Row)) !void {
+    /// Recursive helper for get; clones the row because the node is freed
+    fn getFromNode(self: *Self, page_id: u32, allocator: std.mem.Allocator, key: u64) !?storage.Row {
+        var node = try self.readNode(page_id);
+        defer node.deinit(self.allocator);
+
+        const search_result = node.binarySearchKey(key);
+        if (!node.is_leaf) {
+            // Separator keys live in the right subtree, matching insertNonFull
+            const child_index = if (search_result.found) search_result.index + 1 else search_result.index;
+            return self.getFromNode(node.children[child_index], allocator, key);
+        }
+        if (!search_result.found) return null;
+
+        const original = node.values[search_result.index];
+        var values = try allocator.alloc(storage.Value, original.values.len);
+        var values_cloned: usize = 0;
+        errdefer {
+            for (values[0..values_cloned]) |value| value.deinit(allocator);
+            allocator.free(values);
+        }
+        for (original.values, 0..) |value, i| {
+            values[i] = try value.clone(allocator);
+            values_cloned = i + 1;
+        }
+        return storage.Row{ .values = values };
+    }
+
+    /// Recursive helper for update
+    fn updateNode(self: *Self, page_id: u32, key: u64, value: storage.Row) !bool {
+        var node = try self.readNode(page_id);
+        defer node.deinit(self.allocator);
+
+        const search_result = node.binarySearchKey(key);
+        if (!node.is_leaf) {
+            const child_index = if (search_result.found) search_result.index + 1 else search_result.index;
+            return self.updateNode(node.children[child_index], key, value);
+        }
+        if (!search_result.found) return false;
+
+        // The node owns whichever row it holds when it is freed
+        const previous = node.values[search_result.index];
+        node.values[search_result.index] = value;
+        self.writeNode(page_id, &node) catch |err| {
+            node.values[search_result.index] = previous;
+            return err;
+        };
+
+        for (previous.values) |old_value| old_value.deinit(self.allocator);
+        self.allocator.free(previous.values);
+        return true;
+    }
+
+    /// Recursive helper for delete
+    fn deleteFromNode(self: *Self, page_id: u32, key: u64) !bool {
+        var node = try self.readNode(page_id);
+        defer node.deinit(self.allocator);
+
+        const search_result = node.binarySearchKey(key);
+        if (!node.is_leaf) {
+            const child_index = if (search_result.found) search_result.index + 1 else search_result.index;
+            return self.deleteFromNode(node.children[child_index], key);
+        }
+        if (!search_result.found) return false;
+
+        // The node no longer owns the removed row once it is shifted out
+        const removed = node.values[search_result.index];
+        defer {
+            for (removed.values) |value| value.deinit(self.allocator);
+            self.allocator.free(removed.values);
+        }
+        var i = search_result.index;
+        while (i + 1 < node.key_count) : (i += 1) {
+            node.keys[i] = node.keys[i + 1];
+            node.values[i] = node.values[i + 1];
+        }
+        node.key_count -= 1;
+
+        try self.writeNode(page_id, &node);
+        return true;
+    }
+
+    /// Bytes of a stored text or blob value and the page holding them
+    const StoredValue = struct {
+        page_id: u32,
+        bytes: []u8,
+    };
+
+    /// Find a value on its leaf page; only the internal nodes on the way are decoded
+    fn locateValue(self: *Self, key: u64, column_index: usize) !StoredValue {
+        var page_id = self.root_page;
+        while (true) {
+            const page = try self.pager.getPage(page_id);
+            if (page.data[0] == 1) {
+                const bytes = try Node.locateValueBytes(page.data, key, column_index);
+                return StoredValue{ .page_id = page_id, .bytes = bytes };
+            }
+
+            var node = try Node.deserialize(self.allocator, page.data, self.order);
+            defer node.deinit(self.allocator);
+            const search_result = node.binarySearchKey(key);
+            // Separator keys live in the right subtree, matching insertNonFull
+            page_id = node.children[if (search_result.found) search_result.index + 1 else search_result.index];
+        }
+    }
+
+    /// Collect all values from leaf nodes (for table scans), and their keys if asked
+    fn collectAllLeafValues(self: *Self, page_id: u32, results: *std.array_list.Managed(storage.Row), keys: ?*std.array_list.Managed(u64)) !void {
         var node = try self.readNode(page_id);
         defer node.deinit(self.allocator);
 
@@ -246,11 +416,12 @@ pub const BTree = struct {
                 }
 
                 try results.append(storage.Row{ .values = cloned_values });
+                if (keys) |key_list| try key_list.append(node.keys[i]);
             }
         } else {
             // Recursively collect from all children
             for (0..node.key_count + 1) |i| {
-                try self.collectAllLeafValues(node.children[i], results);
+                try self.collectAllLeafValues(node.children[i], results, keys);
             }
         }
     }
@@ -887,6 +1058,56 @@ pub const Node = struct {
         return node;
     }
 
+    /// Slice of a serialized leaf holding the text or blob stored under a key
+    fn locateValueBytes(buffer: []u8, key: u64, column_index: usize) ![]u8 {
+        if (buffer.len < 9) return error.BufferTooSmall;
+        const key_count = std.mem.readInt(u32, buffer[1..5], .little);
+        if (buffer.len < 9 + @as(usize, key_count) * 8) return error.BufferTooSmall;
+
+        const key_index = for (0..key_count) |i| {
+            if (std.mem.readInt(u64, buffer[9 + i * 8 ..][0..8], .little) == key) break i;
+        } else return error.RowNotFound;
+
+        // Skip the rows stored before this one
+        var pos: usize = 9 + @as(usize, key_count) * 8;
+        for (0..key_index) |_| {
+            if (buffer.len < pos + 4) return error.BufferTooSmall;
+            const value_count = std.mem.readInt(u32, buffer[pos..][0..4], .little);
+            pos += 4;
+            for (0..value_count) |_| pos = try skipStoredValue(buffer, pos);
+        }
+
+        if (buffer.len < pos + 4) return error.BufferTooSmall;
+        const value_count = std.mem.readInt(u32, buffer[pos..][0..4], .little);
+        pos += 4;
+        if (column_index >= value_count) return error.ColumnNotFound;
+        for (0..column_index) |_| pos = try skipStoredValue(buffer, pos);
+
+        if (buffer.len < pos + 5) return error.BufferTooSmall;
+        // Only text (3) and blob (4) values have bytes to address
+        if (buffer[pos] != 3 and buffer[pos] != 4) return error.NotABlob;
+        const len = std.mem.readInt(u32, buffer[pos + 1 ..][0..4], .little);
+        if (buffer.len < pos + 5 + len) return error.BufferTooSmall;
+        return buffer[pos + 5 ..][0..len];
+    }
+
+    /// Position just past the serialized value starting at `pos`
+    fn skipStoredValue(buffer: []const u8, pos: usize) !usize {
+        if (buffer.len < pos + 1) return error.BufferTooSmall;
+        const end = switch (buffer[pos]) {
+            0 => pos + 1,
+            1, 2 => pos + 9,
+            3, 4 => blk: {
+                if (buffer.len < pos + 5) return error.BufferTooSmall;
+                break :blk pos + 5 + std.mem.readInt(u32, buffer[pos + 1 ..][0..4], .little);
+            },
+            5 => pos + 5,
+            else => return error.InvalidValueType,
+        };
+        if (buffer.len < end) return error.BufferTooSmall;
+        return end;
+    }
+
     /// Result structure for deserializeValue
     const DeserializeValueResult = struct {
         value: storage.Row,
@@ -1053,3 +1274,31 @@ test "btree insert and search comprehensive" {
     const not_found = try btree.search(999);
     try std.testing.expect(not_found == null);
 }
+
+test "btree value ranges are read and written in place" {
+    const allocator = std.testing.allocator;
+    const pager_inst = try pager.Pager.initMemory(allocator);
+    defer pager_inst.deinit();
+
+    const btree = try BTree.init(allocator, pager_inst);
+    defer btree.deinit();
+
+    for ([_]u64{ 1, 2 }) |key| {
+        var values = try allocator.alloc(storage.Value, 2);
+        values[0] = storage.Value{ .Integer = @intCast(key) };
+        values[1] = storage.Value{ .Blob = try allocator.dupe(u8, "hello world") };
+        try btree.insert(key, storage.Row{ .values = values });
+    }
+
+    try std.testing.expectEqual(@as(usize, 11), try btree.valueLength(2, 1));
+    try btree.writeValueRange(2, 1, 6, "WORLD");
+    try std.testing.expectError(error.RangeOutOfBounds, btree.writeValueRange(2, 1, 8, "long"));
+    try std.testing.expectError(error.NotABlob, btree.valueLength(2, 0));
+    try std.testing.expectError(error.RowNotFound, btree.valueLength(3, 1));
+
+    var buffer: [11]u8 = undefined;
+    try btree.readValueRange(2, 1, 0, &buffer);
+    try std.testing.expectEqualStrings("hello WORLD", &buffer);
+    try btree.readValueRange(1, 1, 0, &buffer);
+    try std.testing.expectEqualStrings("hello world", &buffer);
+}
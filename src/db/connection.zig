@@ -5,6 +5,7 @@ const ast = @import("../parser/ast.zig");
 const parser = @import("../parser/parser.zig");
 const planner = @import("../executor/planner.zig");
 const vm = @import("../executor/vm.zig");
+const functions = @import("../executor/functions.zig");
 
 /// Database connection handle
 pub const Connection = struct {
@@ -14,6 +15,16 @@ pub const Connection = struct {
     is_memory: bool,
     path: ?[]const u8,
     owns_storage: bool, // Whether this connection owns and should clean up the storage engine
+    user_functions: ?functions.UserFunctionResolver, // Functions registered through the C API
+    change_hooks: ?ChangeHooks, // Update/commit/rollback hooks installed through the C API
+    last_statement_id: u64, // Id handed to the most recently started statement
+    running_statement: std.atomic.Value(u64), // Id of the statement now running, 0 when idle
+    interrupted_statement: std.atomic.Value(u64), // Id of the statement interrupt() aborted
+    in_transaction: bool, // Inside BEGIN ... COMMIT/ROLLBACK; false means autocommit
+    undo_log: std.array_list.Managed(UndoEntry), // Row changes of the open transaction, newest last
+    last_error: ?anyerror, // Error from the most recent execute(), for the C API
+    last_insert_rowid: i64, // Row id of the most recent successful INSERT
+    changes: u32, // Rows changed by the most recent INSERT, UPDATE or DELETE
 
     const Self = @This();
 
@@ -27,10 +38,31 @@ pub const Connection = struct {
         conn.is_memory = false;
         conn.path = try allocator.dupe(u8, path);
         conn.owns_storage = true;
+        conn.user_functions = null;
+        conn.change_hooks = null;
+        conn.last_statement_id = 0;
+        conn.running_statement = std.atomic.Value(u64).init(0);
+        conn.interrupted_statement = std.atomic.Value(u64).init(0);
+        conn.in_transaction = false;
+        conn.undo_log = std.array_list.Managed(UndoEntry).init(allocator);
+        conn.last_error = null;
+        conn.last_insert_rowid = 0;
+        conn.changes = 0;
+        errdefer conn.close();
+
+        try conn.restoreSchema();
 
         return conn;
     }
 
+    /// Recreate the tables and indexes listed in the storage engine's catalog
+    fn restoreSchema(self: *Self) !void {
+        for (self.storage_engine.catalog.items) |entry| {
+            try self.execute(entry.sql);
+        }
+        try self.storage_engine.finishRestore();
+    }
+
     /// Open an in-memory database
     pub fn openMemory(allocator: std.mem.Allocator) !*Self {
 
@@ -41,6 +73,16 @@ pub const Connection = struct {
         conn.is_memory = true;
         conn.path = null;
         conn.owns_storage = true;
+        conn.user_functions = null;
+        conn.change_hooks = null;
+        conn.last_statement_id = 0;
+        conn.running_statement = std.atomic.Value(u64).init(0);
+        conn.interrupted_statement = std.atomic.Value(u64).init(0);
+        conn.in_transaction = false;
+        conn.undo_log = std.array_list.Managed(UndoEntry).init(allocator);
+        conn.last_error = null;
+        conn.last_insert_rowid = 0;
+        conn.changes = 0;
 
         return conn;
     }
@@ -54,25 +96,83 @@ pub const Connection = struct {
         conn.is_memory = true; // Assume shared storage is memory-based for simplicity
         conn.path = null;
         conn.owns_storage = false; // This connection doesn't own the storage
+        conn.user_functions = null;
+        conn.change_hooks = null;
+        conn.last_statement_id = 0;
+        conn.running_statement = std.atomic.Value(u64).init(0);
+        conn.interrupted_statement = std.atomic.Value(u64).init(0);
+        conn.in_transaction = false;
+        conn.undo_log = std.array_list.Managed(UndoEntry).init(allocator);
+        conn.last_error = null;
+        conn.last_insert_rowid = 0;
+        conn.changes = 0;
         
         return conn;
     }
 
     /// Execute a SQL statement
     pub fn execute(self: *Self, sql: []const u8) !void {
+        var result = try self.run(sql);
+        result.deinit();
+    }
+
+    /// Execute a SQL statement and return its rows and column names
+    pub fn run(self: *Self, sql: []const u8) !vm.ExecutionResult {
+        const previous_statement = self.beginStatement();
+        defer self.endStatement(previous_statement);
+        self.last_error = null;
+        errdefer |err| self.last_error = err;
+
         // Parse the SQL
         var parsed = try parser.parse(self.allocator, sql);
         defer parsed.deinit();
 
+        // Create execution plan
+        var query_planner = planner.Planner.init(self.allocator);
+        var plan = try query_planner.plan(&parsed.statement);
+        defer plan.deinit();
+
         // Execute via virtual machine
-        try vm.execute(self, &parsed.statement);
+        var virtual_machine = vm.VirtualMachine.init(self.allocator, self);
+        return virtual_machine.execute(&plan);
+    }
+
+    /// Abort the statement currently running on this connection
+    ///
+    /// Safe to call from any thread; the statement fails with error.Interrupted.
+    /// Only the statement running at the time of the call is affected: with no
+    /// statement running this is a no-op, like sqlite3_interrupt().
+    pub fn interrupt(self: *Self) void {
+        const statement = self.running_statement.load(.acquire);
+        if (statement != 0) self.interrupted_statement.store(statement, .release);
+    }
+
+    /// Check whether the running statement has been interrupted
+    pub fn isInterrupted(self: *const Self) bool {
+        const statement = self.running_statement.load(.acquire);
+        return statement != 0 and self.interrupted_statement.load(.acquire) == statement;
+    }
+
+    /// Give the statement about to run a fresh id; returns the id to restore when it ends
+    fn beginStatement(self: *Self) u64 {
+        const previous = self.running_statement.load(.acquire);
+        self.last_statement_id += 1;
+        self.running_statement.store(self.last_statement_id, .release);
+        return previous;
+    }
+
+    /// Mark the statement started by the matching beginStatement() as finished
+    fn endStatement(self: *Self, previous: u64) void {
+        self.running_statement.store(previous, .release);
     }
 
     /// Begin a transaction
     pub fn beginTransaction(self: *Self) !void {
+        if (self.in_transaction) return error.TransactionAlreadyActive;
         if (self.wal) |w| {
             try w.beginTransaction();
         }
+        self.in_transaction = true;
     }
     
     /// Begin a transaction (alias)
@@ -81,10 +181,24 @@ pub const Connection = struct {
     }
 
     /// Commit a transaction
+    ///
+    /// A commit hook that vetoes the commit rolls the transaction back and
+    /// fails with error.CommitVetoed. The committed hook runs only once the
+    /// WAL commit has succeeded.
     pub fn commitTransaction(self: *Self) !void {
+        if (!self.in_transaction) return error.NoActiveTransaction;
+        if (self.change_hooks) |hooks| {
+            if (!hooks.committing(hooks.context)) {
+                try self.rollbackTransaction();
+                return error.CommitVetoed;
+            }
+        }
         if (self.wal) |w| {
             try w.commit();
         }
+        self.in_transaction = false;
+        self.clearUndoLog();
+        if (self.change_hooks) |hooks| hooks.committed(hooks.context);
     }
     
     /// Commit a transaction (alias)
@@ -94,6 +208,11 @@ pub const Connection = struct {
 
     /// Rollback a transaction
     pub fn rollbackTransaction(self: *Self) !void {
+        if (!self.in_transaction) return error.NoActiveTransaction;
+        // The transaction ends even if the rollback record can't be written
+        defer self.in_transaction = false;
+        self.undoChanges();
+        if (self.change_hooks) |hooks| hooks.rolled_back(hooks.context);
         if (self.wal) |w| {
             try w.rollback();
         }
@@ -104,6 +223,93 @@ pub const Connection = struct {
         try self.rollbackTransaction();
     }
 
+    /// Remember a row change so rollback can undo it; a no-op in autocommit mode
+    ///
+    /// `old_values` is the row before the change, copied here, or null when
+    /// the change inserted the row.
+    pub fn recordUndo(self: *Self, table: *storage.Table, row_id: storage.RowId, old_values: ?[]const storage.Value) !void {
+        if (!self.in_transaction) return;
+
+        var entry = UndoEntry{
+            .table_name = try self.allocator.dupe(u8, table.name),
+            .row_id = row_id,
+            .old_row = null,
+            .old_bytes = null,
+        };
+        errdefer entry.deinit(self.allocator);
+
+        if (old_values) |values| {
+            const copy = try self.allocator.alloc(storage.Value, values.len);
+            @memset(copy, storage.Value.Null);
+            entry.old_row = storage.Row{ .values = copy };
+            for (values, copy) |value, *slot| slot.* = try value.clone(self.allocator);
+        }
+        try self.undo_log.append(entry);
+    }
+
+    /// Remember the bytes an in-place blob write is about to overwrite; a
+    /// no-op in autocommit mode
+    pub fn recordBlobUndo(self: *Self, table: *storage.Table, row_id: storage.RowId, column_index: usize, offset: usize, len: usize) !void {
+        if (!self.in_transaction) return;
+
+        var entry = UndoEntry{
+            .table_name = try self.allocator.dupe(u8, table.name),
+            .row_id = row_id,
+            .old_row = null,
+            .old_bytes = null,
+        };
+        errdefer entry.deinit(self.allocator);
+
+        const data = try self.allocator.alloc(u8, len);
+        entry.old_bytes = .{ .column_index = column_index, .offset = offset, .data = data };
+        try table.readBlob(row_id, column_index, offset, data);
+        try self.undo_log.append(entry);
+    }
+
+    /// Report a changed row to the update hook
+    pub fn notifyRowChange(self: *Self, change: RowChange, table: *storage.Table, row_id: storage.RowId) void {
+        const hooks = self.change_hooks orelse return;
+        hooks.row_changed(hooks.context, change, table.name, row_id);
+    }
+
+    /// Put every row changed by the transaction back as it was, newest change first
+    fn undoChanges(self: *Self) void {
+        while (self.undo_log.pop()) |entry| {
+            var undo = entry;
+            defer undo.deinit(self.allocator);
+
+            // Tables dropped since the change have nothing left to restore
+            const table = self.storage_engine.getTable(undo.table_name) orelse continue;
+            if (undo.old_bytes) |bytes| {
+                table.writeBlob(undo.row_id, bytes.column_index, bytes.offset, bytes.data) catch |err| {
+                    std.log.err("Failed to restore blob in row {d} of {s}: {}", .{ undo.row_id, undo.table_name, err });
+                };
+            } else if (undo.old_row) |row| {
+                table.restoreRow(undo.row_id, row) catch |err| {
+                    std.log.err("Failed to restore row {d} of {s}: {}", .{ undo.row_id, undo.table_name, err });
+                    continue;
+                };
+                // The table owns the restored row now
+                undo.old_row = null;
+            } else {
+                _ = table.deleteRow(undo.row_id) catch |err| {
+                    std.log.err("Failed to remove row {d} of {s}: {}", .{ undo.row_id, undo.table_name, err });
+                };
+            }
+        }
+    }
+
+    /// Forget the row changes of a finished transaction
+    fn clearUndoLog(self: *Self) void {
+        for (self.undo_log.items) |*entry| entry.deinit(self.allocator);
+        self.undo_log.clearRetainingCapacity();
+    }
+
+    /// Check whether statements run in autocommit mode (no open transaction)
+    pub fn isAutocommit(self: *const Self) bool {
+        return !self.in_transaction;
+    }
+
     /// Execute a function within a transaction with automatic rollback on error
     pub fn transaction(self: *Self, comptime context_type: type, function: *const fn (self: *Self, context: context_type) anyerror!void, context: context_type) !void {
         try self.begin();
@@ -149,32 +355,18 @@ pub const Connection = struct {
     
     /// Execute SQL and return structured results (SQLite-style)
     pub fn query(self: *Self, sql: []const u8) !ResultSet {
-        // Parse the SQL
-        var parsed = try parser.parse(self.allocator, sql);
-        defer parsed.deinit();
-        
-        // Create execution plan
-        var query_planner = planner.Planner.init(self.allocator);
-        var plan = try query_planner.plan(&parsed.statement);
-        defer plan.deinit();
-        
-        // Execute and get results
-        var virtual_machine = vm.VirtualMachine.init(self.allocator, self);
-        var result = try virtual_machine.execute(&plan);
+        var result = try self.run(sql);
         defer result.deinit();
 
-        // Convert to ResultSet (copy the rows so result can be cleaned up)
-        var result_set = ResultSet{
+        // Transfer ownership of the rows and column names to the ResultSet
+        const column_names = try result.columns.toOwnedSlice();
+        const result_set = ResultSet{
             .allocator = self.allocator,
             .connection = self,
-            .rows = std.array_list.Managed(storage.Row).init(self.allocator),
+            .rows = result.rows,
             .current_index = 0,
-            .column_names = try self.extractColumnNames(&parsed.statement),
+            .column_names = column_names,
         };
-
-        // Transfer ownership of rows to ResultSet
-        result_set.rows = result.rows;
-        // Prevent result.deinit from freeing the rows (we've transferred ownership)
         result.rows = std.array_list.Managed(storage.Row).init(self.allocator);
 
         return result_set;
@@ -190,20 +382,9 @@ pub const Connection = struct {
     
     /// Execute SQL statement and return affected row count
     pub fn exec(self: *Self, sql: []const u8) !u32 {
-        // Parse the SQL
-        var parsed = try parser.parse(self.allocator, sql);
-        defer parsed.deinit();
-        
-        // Create execution plan
-        var query_planner = planner.Planner.init(self.allocator);
-        var plan = try query_planner.plan(&parsed.statement);
-        defer plan.deinit();
-        
-        // Execute and get results
-        var virtual_machine = vm.VirtualMachine.init(self.allocator, self);
-        var result = try virtual_machine.execute(&plan);
+        var result = try self.run(sql);
         defer result.deinit();
-        
+
         return result.affected_rows;
     }
     
@@ -235,41 +416,14 @@ pub const Connection = struct {
         return self.storage_engine.getTableNames(self.allocator);
     }
     
-    /// Extract column names from parsed statement (helper)
-    fn extractColumnNames(self: *Self, statement: *const ast.Statement) ![][]const u8 {
-        switch (statement.*) {
-            .Select => |select| {
-                if (select.columns.len == 1 and std.mem.eql(u8, select.columns[0].name, "*")) {
-                    // SELECT * - get columns from table schema
-                    const table_name = select.table orelse return &.{};
-                    const table = self.storage_engine.getTable(table_name);
-                    if (table) |t| {
-                        var column_names = try self.allocator.alloc([]const u8, t.schema.columns.len);
-                        for (t.schema.columns, 0..) |column, i| {
-                            column_names[i] = try self.allocator.dupe(u8, column.name);
-                        }
-                        return column_names;
-                    }
-                }
-                
-                // Explicit column list
-                var column_names = try self.allocator.alloc([]const u8, select.columns.len);
-                for (select.columns, 0..) |column, i| {
-                    column_names[i] = try self.allocator.dupe(u8, column.name);
-                }
-                return column_names;
-            },
-            else => {
-                // Non-SELECT statements have no columns
-                return try self.allocator.alloc([]const u8, 0);
-            }
-        }
-    }
-    
     // ========== END BROAD API SURFACES ==========
 
     /// Close the database connection
     pub fn close(self: *Self) void {
+        // An open transaction is abandoned, as on rollback
+        if (self.in_transaction) self.undoChanges();
+        self.undo_log.deinit();
+
         if (self.wal) |w| {
             w.deinit();
         }
@@ -295,6 +449,43 @@ pub const Connection = struct {
     }
 };
 
+/// Kind of row change reported to the update hook
+pub const RowChange = enum { insert, update, delete };
+
+/// Callbacks run as rows change and transactions end
+pub const ChangeHooks = struct {
+    context: *anyopaque,
+    /// Called after each inserted, updated or deleted row
+    row_changed: *const fn (context: *anyopaque, change: RowChange, table: []const u8, row_id: storage.RowId) void,
+    /// Called before a commit; returning false rolls the transaction back instead
+    committing: *const fn (context: *anyopaque) bool,
+    /// Called after a commit is durable
+    committed: *const fn (context: *anyopaque) void,
+    /// Called after a rollback
+    rolled_back: *const fn (context: *anyopaque) void,
+};
+
+/// A row change of the open transaction, kept so rollback can undo it
+pub const UndoEntry = struct {
+    table_name: []const u8,
+    row_id: storage.RowId,
+    old_row: ?storage.Row, // Row before the change; null when the change inserted it
+    old_bytes: ?BlobBytes, // Set instead of old_row for an in-place blob write
+
+    /// A range of a text or blob value as it was before being overwritten
+    pub const BlobBytes = struct {
+        column_index: usize,
+        offset: usize,
+        data: []u8,
+    };
+
+    fn deinit(self: *UndoEntry, allocator: std.mem.Allocator) void {
+        allocator.free(self.table_name);
+        if (self.old_row) |*row| row.deinit(allocator);
+        if (self.old_bytes) |bytes| allocator.free(bytes.data);
+    }
+};
+
 pub const ConnectionInfo = struct {
     is_memory: bool,
     path: ?[]const u8,
@@ -670,6 +861,10 @@ pub const PreparedStatement = struct {
 
     /// Execute the prepared statement
     pub fn execute(self: *Self, connection: *Connection) !vm.ExecutionResult {
+        const previous_statement = connection.beginStatement();
+        defer connection.endStatement(previous_statement);
+        connection.last_error = null;
+        errdefer |err| connection.last_error = err;
         var virtual_machine = vm.VirtualMachine.init(connection.allocator, connection);
         return virtual_machine.executeWithParameters(&self.execution_plan, self.parameters);
     }
@@ -789,3 +984,119 @@ test "memory connection" {
     // Test will be implemented when storage engine is ready
     try std.testing.expect(true);
 }
+
+test "rollback undoes row changes" {
+    const allocator = std.testing.allocator;
+    const conn = try Connection.openMemory(allocator);
+    defer conn.close();
+
+    try conn.execute("CREATE TABLE items (id INTEGER, name TEXT)");
+    try conn.execute("INSERT INTO items VALUES (1, 'kept')");
+    try conn.execute("INSERT INTO items VALUES (2, 'deleted')");
+
+    try conn.begin();
+    try conn.execute("INSERT INTO items VALUES (3, 'added')");
+    try conn.execute("UPDATE items SET name = 'renamed' WHERE id = 1");
+    try conn.execute("DELETE FROM items WHERE id = 2");
+    try conn.rollback();
+
+    var result = try conn.run("SELECT id, name FROM items");
+    defer result.deinit();
+    try std.testing.expectEqual(@as(usize, 2), result.rows.items.len);
+    for (result.rows.items, [_][]const u8{ "kept", "deleted" }) |row, name| {
+        try std.testing.expectEqualStrings(name, row.values[1].Text);
+    }
+}
+
+test "reopening a database file keeps its schema and rows" {
+    const allocator = std.testing.allocator;
+    var tmp = std.testing.tmpDir(.{});
+    defer tmp.cleanup();
+    const path = try std.fmt.allocPrint(allocator, ".zig-cache/tmp/{s}/test.db", .{tmp.sub_path});
+    defer allocator.free(path);
+
+    {
+        const conn = try Connection.open(allocator, path);
+        defer conn.close();
+        try conn.execute("CREATE TABLE items (id INTEGER PRIMARY KEY, name VARCHAR(32) NOT NULL DEFAULT 'it''s')");
+        try conn.execute("CREATE INDEX idx_items_name ON items (name)");
+        try conn.execute("INSERT INTO items VALUES (1, 'first')");
+        try conn.execute("INSERT INTO items VALUES (2, 'second')");
+    }
+
+    const conn = try Connection.open(allocator, path);
+    defer conn.close();
+    const table = conn.storage_engine.getTable("items").?;
+    try std.testing.expectEqualStrings("VARCHAR(32)", table.schema.columns[1].decl_type);
+    try std.testing.expectEqualStrings("it's", table.schema.columns[1].default_value.?.Literal.Text);
+    try std.testing.expect(conn.storage_engine.getIndex("idx_items_name") != null);
+
+    // Row ids carry on from the stored rows
+    try conn.execute("INSERT INTO items VALUES (3, 'third')");
+    var result = try conn.run("SELECT name FROM items");
+    defer result.deinit();
+    try std.testing.expectEqual(@as(usize, 3), result.rows.items.len);
+}
+
+/// Resolver with a `twice` scalar, a `below` predicate and a `total` aggregate
+const TestFunctions = struct {
+    fn call(context: *anyopaque, allocator: std.mem.Allocator, name: []const u8, args: []const storage.Value) anyerror!?storage.Value {
+        _ = context;
+        _ = allocator;
+        if (std.mem.eql(u8, name, "twice")) return storage.Value{ .Integer = args[0].Integer * 2 };
+        if (std.mem.eql(u8, name, "below")) return storage.Value{ .Integer = @intFromBool(args[0].Integer < args[1].Integer) };
+        return null;
+    }
+
+    fn isAggregate(context: *anyopaque, name: []const u8) bool {
+        _ = context;
+        return std.mem.eql(u8, name, "total");
+    }
+
+    fn aggregate(context: *anyopaque, allocator: std.mem.Allocator, name: []const u8, rows: []const []const storage.Value) anyerror!?storage.Value {
+        _ = context;
+        _ = allocator;
+        if (!std.mem.eql(u8, name, "total")) return null;
+        var sum: i64 = 0;
+        for (rows) |args| sum += args[0].Integer;
+        return storage.Value{ .Integer = sum };
+    }
+};
+
+test "user functions read columns and parameters" {
+    const allocator = std.testing.allocator;
+    const conn = try Connection.openMemory(allocator);
+    defer conn.close();
+    var context: u8 = 0;
+    conn.user_functions = .{
+        .context = &context,
+        .call = TestFunctions.call,
+        .is_aggregate = TestFunctions.isAggregate,
+        .aggregate = TestFunctions.aggregate,
+    };
+
+    try conn.execute("CREATE TABLE numbers (n INTEGER)");
+    try conn.execute("INSERT INTO numbers VALUES (1)");
+    try conn.execute("INSERT INTO numbers VALUES (2)");
+    try conn.execute("INSERT INTO numbers VALUES (3)");
+
+    var doubled = try conn.run("SELECT twice(n) FROM numbers");
+    defer doubled.deinit();
+    try std.testing.expectEqual(@as(usize, 3), doubled.rows.items.len);
+    for (doubled.rows.items, [_]i64{ 2, 4, 6 }) |row, expected| {
+        try std.testing.expectEqual(expected, row.values[0].Integer);
+    }
+
+    const stmt = try conn.prepare("SELECT n FROM numbers WHERE below(n, ?) AND n > 1");
+    defer stmt.deinit();
+    try stmt.bind(0, 3);
+    var filtered = try stmt.execute(conn);
+    defer filtered.deinit();
+    try std.testing.expectEqual(@as(usize, 1), filtered.rows.items.len);
+    try std.testing.expectEqual(@as(i64, 2), filtered.rows.items[0].values[0].Integer);
+
+    var summed = try conn.run("SELECT total(n) FROM numbers");
+    defer summed.deinit();
+    try std.testing.expectEqual(@as(usize, 1), summed.rows.items.len);
+    try std.testing.expectEqual(@as(i64, 6), summed.rows.items[0].values[0].Integer);
+}
@@ -15,6 +15,8 @@ pub const StorageEngine = struct {
     tables: std.StringHashMap(*Table),
     indexes: std.StringHashMap(*Index),
     is_memory: bool,
+    catalog_path: ?[]const u8, // `<path>.schema`, listing the tables and indexes of a file database
+    catalog: std.array_list.Managed(CatalogEntry), // Read from the catalog file, waiting to be recreated
 
     const Self = @This();
 
@@ -28,6 +30,8 @@ pub const StorageEngine = struct {
         engine.tables = std.StringHashMap(*Table).init(allocator);
         engine.indexes = std.StringHashMap(*Index).init(allocator);
         engine.is_memory = false;
+        engine.catalog_path = try std.fmt.allocPrint(allocator, "{s}.schema", .{path});
+        engine.catalog = std.array_list.Managed(CatalogEntry).init(allocator);
 
         // Load existing tables from file
         try engine.loadTables();
@@ -45,6 +49,8 @@ pub const StorageEngine = struct {
         engine.tables = std.StringHashMap(*Table).init(allocator);
         engine.indexes = std.StringHashMap(*Index).init(allocator);
         engine.is_memory = true;
+        engine.catalog_path = null;
+        engine.catalog = std.array_list.Managed(CatalogEntry).init(allocator);
 
         return engine;
     }
@@ -65,13 +71,18 @@ pub const StorageEngine = struct {
     }
 
     /// Create a new table
+    ///
+    /// A table being recreated from the catalog gets its stored rows back.
     pub fn createTable(self: *Self, name: []const u8, schema: TableSchema) !void {
-        const table = try Table.create(self.allocator, self.pager, name, schema);
+        const table = if (self.takeRestoredTable(name)) |entry|
+            try Table.open(self.allocator, self.pager, name, schema, entry.root_page, entry.last_row_id)
+        else
+            try Table.create(self.allocator, self.pager, name, schema);
         try self.tables.put(try self.allocator.dupe(u8, name), table);
 
         // Persist table metadata if not in-memory
         if (!self.is_memory) {
-            try self.saveTableMetadata(table);
+            try self.saveCatalog();
         }
     }
 
@@ -100,12 +111,14 @@ pub const StorageEngine = struct {
             entry.value.deinit();
             self.allocator.free(entry.key);
         }
+        try self.saveCatalog();
     }
 
     /// Create an index
     pub fn createIndex(self: *Self, name: []const u8, table_name: []const u8, column_names: [][]const u8, is_unique: bool) !void {
         const index = try Index.create(self.allocator, self.pager, name, table_name, column_names, is_unique);
         try self.indexes.put(try self.allocator.dupe(u8, name), index);
+        try self.saveCatalog();
     }
 
     /// Get an index by name
@@ -119,24 +132,116 @@ pub const StorageEngine = struct {
             entry.value.deinit(self.allocator);
             self.allocator.free(entry.key);
         }
+        try self.saveCatalog();
     }
 
     /// Load existing tables from storage
+    ///
+    /// Reads the catalog written by `saveCatalog`. The storage engine can't
+    /// run SQL, so the connection that opened the file recreates the tables
+    /// and indexes from `catalog` and then calls `finishRestore`.
     fn loadTables(self: *Self) !void {
-        // This would read table metadata from page 0 or a dedicated metadata area
-        // For now, this is a placeholder
-        _ = self;
+        const path = self.catalog_path orelse return;
+        const file = std.fs.cwd().openFile(path, .{}) catch |err| switch (err) {
+            error.FileNotFound => return,
+            else => return err,
+        };
+        defer file.close();
+
+        const text = try file.readToEndAlloc(self.allocator, std.math.maxInt(u32));
+        defer self.allocator.free(text);
+
+        // Each entry is a `<kind> <name> <root page> <last row id> <length>`
+        // line followed by the CREATE statement and a newline
+        var pos: usize = 0;
+        while (pos < text.len) {
+            const line_end = std.mem.indexOfScalarPos(u8, text, pos, '\n') orelse return error.CorruptCatalog;
+            var fields = std.mem.tokenizeScalar(u8, text[pos..line_end], ' ');
+            _ = fields.next() orelse return error.CorruptCatalog;
+            const name = fields.next() orelse return error.CorruptCatalog;
+            const root_page = try std.fmt.parseInt(u32, fields.next() orelse return error.CorruptCatalog, 10);
+            const last_row_id = try std.fmt.parseInt(RowId, fields.next() orelse return error.CorruptCatalog, 10);
+            const sql_len = try std.fmt.parseInt(usize, fields.next() orelse return error.CorruptCatalog, 10);
+
+            const sql_start = line_end + 1;
+            if (sql_start + sql_len > text.len) return error.CorruptCatalog;
+            pos = sql_start + sql_len + 1;
+
+            const entry_name = try self.allocator.dupe(u8, name);
+            errdefer self.allocator.free(entry_name);
+            const sql = try self.allocator.dupe(u8, text[sql_start..][0..sql_len]);
+            errdefer self.allocator.free(sql);
+            try self.catalog.append(.{
+                .name = entry_name,
+                .sql = sql,
+                .root_page = root_page,
+                .last_row_id = last_row_id,
+            });
+        }
+    }
+
+    /// Take the stored rows of a table being recreated from the catalog
+    fn takeRestoredTable(self: *Self, name: []const u8) ?CatalogEntry {
+        for (self.catalog.items) |*entry| {
+            if (entry.root_page != 0 and std.mem.eql(u8, entry.name, name)) {
+                const restored = entry.*;
+                entry.root_page = 0;
+                return restored;
+            }
+        }
+        return null;
     }
 
-    /// Save table metadata to storage
-    fn saveTableMetadata(self: *Self, table: *Table) !void {
-        // This would write table schema to a metadata page
-        _ = self;
-        _ = table;
+    /// Drop the catalog entries once their tables and indexes are recreated
+    pub fn finishRestore(self: *Self) !void {
+        for (self.catalog.items) |entry| entry.deinit(self.allocator);
+        self.catalog.clearRetainingCapacity();
+        try self.saveCatalog();
+    }
+
+    /// Write every table and index to the catalog file
+    ///
+    /// Tables are stored as CREATE statements rebuilt from their schema,
+    /// with the B-tree root holding their rows. Nothing is written while
+    /// a restore is unfinished, so a failed one can't lose the catalog.
+    fn saveCatalog(self: *Self) !void {
+        const path = self.catalog_path orelse return;
+        if (self.catalog.items.len > 0) return;
+
+        var text = std.array_list.Managed(u8).init(self.allocator);
+        defer text.deinit();
+        var sql = std.array_list.Managed(u8).init(self.allocator);
+        defer sql.deinit();
+
+        // Tables first: indexes are recreated on existing tables
+        var table_iterator = self.tables.valueIterator();
+        while (table_iterator.next()) |table| {
+            sql.clearRetainingCapacity();
+            try writeCreateTable(sql.writer(), table.*.name, table.*.schema);
+            try text.writer().print("table {s} {d} {d} {d}\n{s}\n", .{
+                table.*.name, table.*.btree.root_page, table.*.last_row_id, sql.items.len, sql.items,
+            });
+        }
+        var index_iterator = self.indexes.valueIterator();
+        while (index_iterator.next()) |index| {
+            sql.clearRetainingCapacity();
+            try writeCreateIndex(sql.writer(), index.*.*);
+            try text.writer().print("index {s} 0 0 {d}\n{s}\n", .{ index.*.name, sql.items.len, sql.items });
+        }
+
+        try std.fs.cwd().writeFile(.{ .sub_path = path, .data = text.items });
     }
 
     /// Clean up storage engine
     pub fn deinit(self: *Self) void {
+        // Row counters and B-tree roots change without touching the schema
+        self.saveCatalog() catch |err| {
+            std.log.err("Failed to save the schema catalog: {}", .{err});
+        };
+        for (self.catalog.items) |entry| entry.deinit(self.allocator);
+        self.catalog.deinit();
+        if (self.catalog_path) |path| self.allocator.free(path);
+
         var table_iterator = self.tables.iterator();
         while (table_iterator.next()) |entry| {
             entry.value_ptr.*.deinit();
@@ -176,7 +281,7 @@ pub const Table = struct {
     name: []const u8,
     schema: TableSchema,
     btree: *btree.BTree,
-    row_count: u64,
+    last_row_id: RowId, // Largest row id handed out; row ids start at 1
 
     const Self = @This();
 
@@ -188,15 +293,29 @@ pub const Table = struct {
         // Deep clone schema to ensure ownership with storage engine's allocator
         table.schema = try schema.clone(allocator);
         table.btree = try btree.BTree.init(allocator, page_manager);
-        table.row_count = 0;
+        table.last_row_id = 0;
 
         return table;
     }
 
-    /// Insert a row into the table
-    pub fn insert(self: *Self, row: Row) !void {
-        try self.btree.insert(self.row_count, row);
-        self.row_count += 1;
+    /// Reattach a table to rows already stored under a B-tree root
+    pub fn open(allocator: std.mem.Allocator, page_manager: *pager.Pager, name: []const u8, schema: TableSchema, root_page: u32, last_row_id: RowId) !*Self {
+        var table = try allocator.create(Self);
+        table.allocator = allocator;
+        table.name = try allocator.dupe(u8, name);
+        table.schema = try schema.clone(allocator);
+        table.btree = try btree.BTree.open(allocator, page_manager, root_page);
+        table.last_row_id = last_row_id;
+
+        return table;
+    }
+
+    /// Insert a row into the table, taking ownership of it, and return its row id
+    pub fn insert(self: *Self, row: Row) !RowId {
+        const row_id = self.last_row_id + 1;
+        try self.btree.insert(row_id, row);
+        self.last_row_id = row_id;
+        return row_id;
     }
 
     /// Select all rows
@@ -204,6 +323,48 @@ pub const Table = struct {
         return try self.btree.selectAll(allocator);
     }
 
+    /// Select all rows, appending the row id of each to `row_ids`
+    pub fn selectWithIds(self: *Self, allocator: std.mem.Allocator, row_ids: *std.array_list.Managed(RowId)) ![]Row {
+        return try self.btree.selectAllWithKeys(allocator, row_ids);
+    }
+
+    /// Get a copy of the row stored under a row id
+    pub fn getRow(self: *Self, allocator: std.mem.Allocator, row_id: u64) !?Row {
+        return try self.btree.get(allocator, row_id);
+    }
+
+    /// Length of a text or blob value in a row
+    pub fn blobLength(self: *Self, row_id: RowId, column_index: usize) !usize {
+        return try self.btree.valueLength(row_id, column_index);
+    }
+
+    /// Read a range of a text or blob value in place
+    pub fn readBlob(self: *Self, row_id: RowId, column_index: usize, offset: usize, buffer: []u8) !void {
+        try self.btree.readValueRange(row_id, column_index, offset, buffer);
+    }
+
+    /// Overwrite a range of a text or blob value in place
+    pub fn writeBlob(self: *Self, row_id: RowId, column_index: usize, offset: usize, data: []const u8) !void {
+        try self.btree.writeValueRange(row_id, column_index, offset, data);
+    }
+
+    /// Replace an existing row, taking ownership of it when true is returned
+    pub fn updateRow(self: *Self, row_id: u64, row: Row) !bool {
+        return try self.btree.update(row_id, row);
+    }
+
+    /// Store a row under a row id, replacing any row already there
+    ///
+    /// Used to undo updates and deletes; takes ownership of `row` on success.
+    pub fn restoreRow(self: *Self, row_id: RowId, row: Row) !void {
+        if (!try self.btree.update(row_id, row)) try self.btree.insert(row_id, row);
+    }
+
+    /// Delete the row stored under a row id, returning false if there was none
+    pub fn deleteRow(self: *Self, row_id: u64) !bool {
+        return try self.btree.delete(row_id);
+    }
+
     /// Clean up table
     pub fn deinit(self: *Self) void {
         self.btree.deinit();
@@ -213,19 +374,174 @@ pub const Table = struct {
     }
 };
 
+/// A table or index listed in the catalog file, waiting to be recreated
+pub const CatalogEntry = struct {
+    name: []const u8,
+    sql: []const u8, // CREATE statement that recreates it
+    root_page: u32, // B-tree root of a table's rows; 0 for indexes and tables already recreated
+    last_row_id: RowId,
+
+    fn deinit(self: CatalogEntry, allocator: std.mem.Allocator) void {
+        allocator.free(self.name);
+        allocator.free(self.sql);
+    }
+};
+
+/// Write the CREATE TABLE statement for a table schema
+fn writeCreateTable(writer: anytype, name: []const u8, schema: TableSchema) !void {
+    var pk_columns: usize = 0;
+    for (schema.columns) |column| {
+        if (column.is_primary_key) pk_columns += 1;
+    }
+
+    try writer.print("CREATE TABLE {s} (", .{name});
+    for (schema.columns, 0..) |column, i| {
+        if (i > 0) try writer.writeAll(", ");
+        try writer.print("{s} {s}", .{ column.name, column.decl_type });
+        if (column.is_primary_key and pk_columns == 1) try writer.writeAll(" PRIMARY KEY");
+        if (!column.is_nullable) try writer.writeAll(" NOT NULL");
+        if (column.default_value) |default_value| {
+            // Only defaults with an SQL spelling are written
+            if (defaultHasSql(default_value)) {
+                try writer.writeAll(" DEFAULT ");
+                try writeDefaultSql(writer, default_value);
+            }
+        }
+    }
+
+    if (pk_columns > 1) {
+        try writer.writeAll(", PRIMARY KEY (");
+        var written: usize = 0;
+        for (schema.columns) |column| {
+            if (!column.is_primary_key) continue;
+            if (written > 0) try writer.writeAll(", ");
+            try writer.writeAll(column.name);
+            written += 1;
+        }
+        try writer.writeAll(")");
+    }
+    for (schema.unique_keys) |unique_key| {
+        // The primary key's own unique key comes back with PRIMARY KEY
+        const is_primary_key = unique_key.columns.len == pk_columns and for (unique_key.columns) |position| {
+            if (!schema.columns[position].is_primary_key) break false;
+        } else true;
+        if (is_primary_key) continue;
+
+        try writer.writeAll(", UNIQUE (");
+        for (unique_key.columns, 0..) |position, i| {
+            if (i > 0) try writer.writeAll(", ");
+            try writer.writeAll(schema.columns[position].name);
+        }
+        try writer.writeAll(")");
+    }
+    for (schema.foreign_keys) |foreign_key| {
+        try writer.print(", FOREIGN KEY ({s}) REFERENCES {s}({s})", .{
+            foreign_key.column,
+            foreign_key.reference_table,
+            foreign_key.reference_column,
+        });
+        if (foreign_key.on_delete != .NoAction) try writer.print(" ON DELETE {s}", .{foreign_key.on_delete.sqlName()});
+        if (foreign_key.on_update != .NoAction) try writer.print(" ON UPDATE {s}", .{foreign_key.on_update.sqlName()});
+    }
+    try writer.writeAll(")");
+}
+
+/// Write the CREATE INDEX statement for an index
+fn writeCreateIndex(writer: anytype, index: Index) !void {
+    try writer.print("CREATE {s}INDEX {s} ON {s} (", .{
+        if (index.is_unique) "UNIQUE " else "",
+        index.name,
+        index.table_name,
+    });
+    for (index.column_names, 0..) |column, i| {
+        if (i > 0) try writer.writeAll(", ");
+        try writer.writeAll(column);
+    }
+    try writer.writeAll(")");
+}
+
+/// Whether a default value can be written as SQL
+pub fn defaultHasSql(default_value: Column.DefaultValue) bool {
+    return switch (default_value) {
+        .Literal => |value| literalHasSql(value),
+        .FunctionCall => |call| for (call.arguments) |argument| {
+            if (argument == .Literal and !literalHasSql(argument.Literal)) break false;
+        } else true,
+    };
+}
+
+fn literalHasSql(value: Value) bool {
+    return switch (value) {
+        .Integer, .BigInt, .Timestamp, .SmallInt, .Real, .Text, .JSON, .Boolean, .Null => true,
+        else => false,
+    };
+}
+
+/// Write a default value as SQL, e.g. `0`, `'active'` or `datetime('now')`
+///
+/// Values without an SQL spelling are skipped; check `defaultHasSql` first.
+pub fn writeDefaultSql(writer: anytype, default_value: Column.DefaultValue) !void {
+    switch (default_value) {
+        .Literal => |value| try writeLiteralSql(writer, value),
+        .FunctionCall => |call| {
+            try writer.print("{s}(", .{call.name});
+            for (call.arguments, 0..) |argument, i| {
+                if (i > 0) try writer.writeAll(", ");
+                switch (argument) {
+                    .Literal => |value| try writeLiteralSql(writer, value),
+                    .Column => |column| try writer.writeAll(column),
+                    .Parameter => try writer.writeAll("?"),
+                }
+            }
+            try writer.writeAll(")");
+        },
+    }
+}
+
+/// Write a literal as SQL, doubling quotes in text
+fn writeLiteralSql(writer: anytype, value: Value) !void {
+    switch (value) {
+        .Integer, .BigInt, .Timestamp => |int| try writer.print("{d}", .{int}),
+        .SmallInt => |int| try writer.print("{d}", .{int}),
+        .Real => |real| try writer.print("{d}", .{real}),
+        .Text, .JSON => |text| {
+            try writer.writeByte('\'');
+            for (text) |c| {
+                if (c == '\'') try writer.writeByte('\'');
+                try writer.writeByte(c);
+            }
+            try writer.writeByte('\'');
+        },
+        .Boolean => |b| try writer.writeAll(if (b) "TRUE" else "FALSE"),
+        .Null => try writer.writeAll("NULL"),
+        else => {},
+    }
+}
+
 /// Table schema definition
 pub const TableSchema = struct {
     columns: []Column,
+    foreign_keys: []ForeignKey = &.{},
+    unique_keys: []UniqueKey = &.{},
 
     pub fn deinit(self: *TableSchema, allocator: std.mem.Allocator) void {
         // Clean up column names and default values
         for (self.columns) |column| {
             allocator.free(column.name);
+            allocator.free(column.decl_type);
             if (column.default_value) |default_value| {
                 default_value.deinit(allocator);
             }
         }
         allocator.free(self.columns);
+        for (self.foreign_keys) |foreign_key| {
+            foreign_key.deinit(allocator);
+        }
+        allocator.free(self.foreign_keys);
+        for (self.unique_keys) |unique_key| {
+            unique_key.deinit(allocator);
+        }
+        allocator.free(self.unique_keys);
     }
 
     /// Deep clone schema with a new allocator (for ownership transfer)
@@ -236,6 +552,7 @@ pub const TableSchema = struct {
             cloned_columns[i] = Column{
                 .name = try allocator.dupe(u8, column.name),
                 .data_type = column.data_type,
+                .decl_type = try allocator.dupeZ(u8, column.decl_type),
                 .is_primary_key = column.is_primary_key,
                 .is_nullable = column.is_nullable,
                 .default_value = if (column.default_value) |default_val|
@@ -245,8 +562,77 @@ pub const TableSchema = struct {
             };
         }
 
+        var cloned_foreign_keys = try allocator.alloc(ForeignKey, self.foreign_keys.len);
+        for (self.foreign_keys, 0..) |foreign_key, i| {
+            cloned_foreign_keys[i] = try foreign_key.clone(allocator);
+        }
+
+        var cloned_unique_keys = try allocator.alloc(UniqueKey, self.unique_keys.len);
+        for (self.unique_keys, 0..) |unique_key, i| {
+            cloned_unique_keys[i] = try unique_key.clone(allocator);
+        }
+
         return TableSchema{
             .columns = cloned_columns,
+            .foreign_keys = cloned_foreign_keys,
+            .unique_keys = cloned_unique_keys,
+        };
+    }
+};
+
+/// Columns whose values, taken together, may not repeat across rows
+/// (a PRIMARY KEY or UNIQUE constraint); rows with a NULL in the key never conflict
+pub const UniqueKey = struct {
+    columns: []usize,
+
+    pub fn deinit(self: UniqueKey, allocator: std.mem.Allocator) void {
+        allocator.free(self.columns);
+    }
+
+    pub fn clone(self: UniqueKey, allocator: std.mem.Allocator) CloneValueError!UniqueKey {
+        return UniqueKey{ .columns = try allocator.dupe(usize, self.columns) };
+    }
+};
+
+/// Foreign key from one column of a table to a column of another
+pub const ForeignKey = struct {
+    column: []const u8,
+    reference_table: []const u8,
+    reference_column: []const u8,
+    on_delete: ForeignKeyAction,
+    on_update: ForeignKeyAction,
+
+    pub fn deinit(self: ForeignKey, allocator: std.mem.Allocator) void {
+        allocator.free(self.column);
+        allocator.free(self.reference_table);
+        allocator.free(self.reference_column);
+    }
+
+    pub fn clone(self: ForeignKey, allocator: std.mem.Allocator) CloneValueError!ForeignKey {
+        return ForeignKey{
+            .column = try allocator.dupe(u8, self.column),
+            .reference_table = try allocator.dupe(u8, self.reference_table),
+            .reference_column = try allocator.dupe(u8, self.reference_column),
+            .on_delete = self.on_delete,
+            .on_update = self.on_update,
+        };
+    }
+};
+
+/// Action taken on referencing rows when the referenced row changes
+pub const ForeignKeyAction = enum {
+    NoAction,
+    Restrict,
+    SetNull,
+    Cascade,
+
+    /// SQL spelling, as shown by schema introspection
+    pub fn sqlName(self: ForeignKeyAction) [:0]const u8 {
+        return switch (self) {
+            .NoAction => "NO ACTION",
+            .Restrict => "RESTRICT",
+            .SetNull => "SET NULL",
+            .Cascade => "CASCADE",
         };
     }
 };
@@ -255,6 +641,8 @@ pub const TableSchema = struct {
 pub const Column = struct {
     name: []const u8,
     data_type: DataType,
+    /// Type name as written in CREATE TABLE, e.g. `VARCHAR(255)`
+    decl_type: [:0]const u8,
     is_primary_key: bool,
     is_nullable: bool,
     default_value: ?DefaultValue,
@@ -3,10 +3,30 @@ const storage = @import("../db/storage.zig");
 const ast = @import("../parser/ast.zig");
 const Allocator = std.mem.Allocator;
 
+/// Resolver for functions registered from outside the engine (e.g. the C API)
+pub const UserFunctionResolver = struct {
+    context: *anyopaque,
+    /// Returns null when no user function with this name/arity is registered
+    call: *const fn (context: *anyopaque, allocator: Allocator, name: []const u8, args: []const storage.Value) anyerror!?storage.Value,
+    /// Whether the function registered under this name is an aggregate
+    is_aggregate: *const fn (context: *anyopaque, name: []const u8) bool,
+    /// Folds the arguments of every row into one value; null when no aggregate is registered
+    aggregate: *const fn (context: *anyopaque, allocator: Allocator, name: []const u8, rows: []const []const storage.Value) anyerror!?storage.Value,
+};
+
+/// Row that column arguments of user functions are read from
+pub const RowContext = struct {
+    columns: []const []const u8,
+    values: []const storage.Value,
+};
+
 pub const FunctionEvaluator = struct {
     const Self = @This();
     
     allocator: Allocator,
+    user_functions: ?UserFunctionResolver = null,
+    row: ?RowContext = null, // Row being evaluated, set by the VM
+    parameters: ?[]const storage.Value = null, // Parameters bound to the running statement
     
     pub fn init(allocator: Allocator) Self {
         return Self{
@@ -42,8 +62,88 @@ pub const FunctionEvaluator = struct {
         } else if (std.mem.eql(u8, lower_name, "time")) {
             return self.evalTime(function_call.arguments);
         } else {
-            return error.UnknownFunction;
+            return self.evalUserFunction(lower_name, function_call.arguments);
+        }
+    }
+
+    fn evalUserFunction(self: *Self, name: []const u8, arguments: []ast.FunctionArgument) !storage.Value {
+        const resolver = self.user_functions orelse return error.UnknownFunction;
+        // Aggregates only make sense over a set of rows, as a selected column
+        if (resolver.is_aggregate(resolver.context, name)) return error.MisuseOfAggregateFunction;
+
+        const args = try self.allocator.alloc(storage.Value, arguments.len);
+        defer self.allocator.free(args);
+        try self.resolveArguments(arguments, args);
+
+        return (try resolver.call(resolver.context, self.allocator, name, args)) orelse error.UnknownFunction;
+    }
+
+    /// Whether a function call names a user-defined aggregate
+    pub fn isUserAggregate(self: *Self, function_call: ast.FunctionCall) !bool {
+        const resolver = self.user_functions orelse return false;
+        const lower_name = try std.ascii.allocLowerString(self.allocator, function_call.name);
+        defer self.allocator.free(lower_name);
+        return resolver.is_aggregate(resolver.context, lower_name);
+    }
+
+    /// Evaluate a user-defined aggregate over rows with the given column names
+    pub fn evaluateAggregate(self: *Self, function_call: ast.FunctionCall, columns: []const []const u8, rows: []const storage.Row) !storage.Value {
+        const resolver = self.user_functions orelse return error.UnknownFunction;
+        const lower_name = try std.ascii.allocLowerString(self.allocator, function_call.name);
+        defer self.allocator.free(lower_name);
+
+        const arg_count = function_call.arguments.len;
+        const values = try self.allocator.alloc(storage.Value, rows.len * arg_count);
+        defer self.allocator.free(values);
+        const row_args = try self.allocator.alloc([]const storage.Value, rows.len);
+        defer self.allocator.free(row_args);
+
+        const previous_row = self.row;
+        defer self.row = previous_row;
+        for (rows, row_args, 0..) |row, *args, i| {
+            self.row = .{ .columns = columns, .values = row.values };
+            const row_values = values[i * arg_count ..][0..arg_count];
+            try self.resolveArguments(function_call.arguments, row_values);
+            args.* = row_values;
         }
+
+        return (try resolver.aggregate(resolver.context, self.allocator, lower_name, row_args)) orelse error.UnknownFunction;
+    }
+
+    /// Look up the value of each argument; values stay owned by the call, row or parameters
+    fn resolveArguments(self: *Self, arguments: []const ast.FunctionArgument, args: []storage.Value) !void {
+        for (arguments, args) |arg, *value| {
+            value.* = switch (arg) {
+                .Literal => |literal| switch (literal) {
+                    .Integer => |i| storage.Value{ .Integer = i },
+                    .Real => |r| storage.Value{ .Real = r },
+                    .Text => |text| storage.Value{ .Text = text },
+                    .Blob => |blob| storage.Value{ .Blob = blob },
+                    .Null => storage.Value.Null,
+                    .Parameter => |index| try self.parameterValue(index),
+                    .FunctionCall => return error.UnsupportedFunctionArgument,
+                },
+                .String => |text| storage.Value{ .Text = text },
+                .Column => |name| try self.columnValue(name),
+                .Parameter => |index| try self.parameterValue(index),
+            };
+        }
+    }
+
+    fn columnValue(self: *Self, name: []const u8) !storage.Value {
+        const row = self.row orelse return error.ColumnNotFound;
+        for (row.columns, 0..) |column, i| {
+            if (std.ascii.eqlIgnoreCase(column, name)) {
+                return if (i < row.values.len) row.values[i] else storage.Value.Null;
+            }
+        }
+        return error.ColumnNotFound;
+    }
+
+    fn parameterValue(self: *Self, index: u32) !storage.Value {
+        const params = self.parameters orelse return error.NoParametersProvided;
+        if (index >= params.len) return error.ParameterIndexOutOfBounds;
+        return params[index];
     }
     
     fn evalNow(self: *Self, arguments: []ast.FunctionArgument) !storage.Value {
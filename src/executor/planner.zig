@@ -102,22 +102,38 @@ pub const Planner = struct {
                 });
             }
         } else {
+            // Sort before projecting, so ORDER BY can name columns that are not selected
+            if (select.order_by) |order_by| {
+                var keys = try self.allocator.alloc(SortKey, order_by.len);
+                for (order_by, 0..) |clause, i| {
+                    keys[i] = SortKey{
+                        .column = try self.allocator.dupe(u8, clause.column),
+                        .descending = clause.direction == .Desc,
+                    };
+                }
+                try steps.append(ExecutionStep{ .Sort = SortStep{ .keys = keys } });
+            }
+
             // Regular projection step (SELECT columns)
-            var columns = std.array_list.Managed([]const u8).init(self.allocator);
+            var columns = std.array_list.Managed(ProjectColumn).init(self.allocator);
             for (select.columns) |column| {
                 switch (column.expression) {
-                    .Simple => |name| try columns.append(try self.allocator.dupe(u8, name)),
+                    .Simple => |name| try columns.append(ProjectColumn{
+                        .name = try self.allocator.dupe(u8, name),
+                        .alias = if (column.alias) |alias| try self.allocator.dupe(u8, alias) else null,
+                    }),
                     .Aggregate => {
                         // This shouldn't happen if has_aggregates was false
                         return error.UnexpectedAggregate;
                     },
+                    .FunctionCall => |function_call| try columns.append(ProjectColumn{
+                        .name = try self.allocator.dupe(u8, column.name),
+                        .alias = if (column.alias) |alias| try self.allocator.dupe(u8, alias) else null,
+                        .function = try self.cloneFunctionCall(function_call),
+                    }),
                     .Window => {
                         // Window functions will be handled in a later version
-                        try columns.append(try self.allocator.dupe(u8, "window_result"));
-                    },
-                    .FunctionCall => {
-                        // Function calls will be handled in a later version
-                        try columns.append(try self.allocator.dupe(u8, "function_result"));
+                        return error.NotImplemented;
                     },
                 }
             }
@@ -194,6 +210,7 @@ pub const Planner = struct {
                 // For now, don't optimize complex logical conditions
                 // Could be enhanced to handle AND of equi-joins
             },
+            .FunctionCall => {},
         }
         return null;
     }
@@ -243,6 +260,7 @@ pub const Planner = struct {
                 .table_name = try self.allocator.dupe(u8, insert.table),
                 .columns = columns,
                 .values = try values.toOwnedSlice(),
+                .or_conflict = insert.or_conflict,
             },
         });
 
@@ -288,10 +306,17 @@ pub const Planner = struct {
                     .Interval => storage.DataType.Interval,
                     .Numeric => storage.DataType.Numeric,
                 },
+                .decl_type = try self.allocator.dupeZ(u8, col_def.type_name),
                 .is_primary_key = blk: {
                     for (col_def.constraints) |constraint| {
                         if (constraint == .PrimaryKey) break :blk true;
                     }
+                    for (create.table_constraints) |constraint| {
+                        if (constraint != .PrimaryKey) continue;
+                        for (constraint.PrimaryKey.columns) |pk_column| {
+                            if (std.mem.eql(u8, pk_column, col_def.name)) break :blk true;
+                        }
+                    }
                     break :blk false;
                 },
                 .is_nullable = blk: {
@@ -312,10 +337,47 @@ pub const Planner = struct {
             });
         }
 
+        // Column-level REFERENCES and table-level FOREIGN KEY constraints
+        var foreign_keys = std.array_list.Managed(storage.ForeignKey).init(self.allocator);
+        for (create.columns) |col_def| {
+            for (col_def.constraints) |constraint| {
+                if (constraint == .ForeignKey) {
+                    try foreign_keys.append(try self.convertForeignKey(constraint.ForeignKey, col_def.name));
+                }
+            }
+        }
+        for (create.table_constraints) |constraint| {
+            if (constraint == .ForeignKey) {
+                const column = constraint.ForeignKey.column orelse continue;
+                try foreign_keys.append(try self.convertForeignKey(constraint.ForeignKey, column));
+            }
+        }
+
+        // Column-level PRIMARY KEY / UNIQUE and their table-level forms
+        var unique_keys = std.array_list.Managed(storage.UniqueKey).init(self.allocator);
+        for (create.columns, 0..) |col_def, i| {
+            for (col_def.constraints) |constraint| {
+                if (constraint == .PrimaryKey or constraint == .Unique) {
+                    try unique_keys.append(storage.UniqueKey{ .columns = try self.allocator.dupe(usize, &[_]usize{i}) });
+                    break;
+                }
+            }
+        }
+        for (create.table_constraints) |constraint| {
+            const key_columns = switch (constraint) {
+                .PrimaryKey => |pk| pk.columns,
+                .Unique => |unique| unique.columns,
+                else => continue,
+            };
+            try unique_keys.append(try self.resolveUniqueKey(create.columns, key_columns));
+        }
+
         try steps.append(ExecutionStep{
             .CreateTable = CreateTableStep{
                 .table_name = try self.allocator.dupe(u8, create.table_name),
                 .columns = try columns.toOwnedSlice(),
+                .foreign_keys = try foreign_keys.toOwnedSlice(),
+                .unique_keys = try unique_keys.toOwnedSlice(),
                 .if_not_exists = create.if_not_exists,
             },
         });
@@ -326,6 +388,38 @@ pub const Planner = struct {
         };
     }
 
+    /// Map the column names of a table-level key to column positions
+    fn resolveUniqueKey(self: *Self, column_defs: []const ast.ColumnDefinition, names: []const []const u8) !storage.UniqueKey {
+        var positions = try self.allocator.alloc(usize, names.len);
+        errdefer self.allocator.free(positions);
+        for (names, 0..) |name, i| {
+            positions[i] = for (column_defs, 0..) |col_def, position| {
+                if (std.ascii.eqlIgnoreCase(col_def.name, name)) break position;
+            } else return error.ColumnNotFound;
+        }
+        return storage.UniqueKey{ .columns = positions };
+    }
+
+    /// Convert a parsed foreign key on `column` to its stored form
+    fn convertForeignKey(self: *Self, fk: ast.ForeignKeyConstraint, column: []const u8) !storage.ForeignKey {
+        return storage.ForeignKey{
+            .column = try self.allocator.dupe(u8, column),
+            .reference_table = try self.allocator.dupe(u8, fk.reference_table),
+            .reference_column = try self.allocator.dupe(u8, fk.reference_column),
+            .on_delete = convertForeignKeyAction(fk.on_delete),
+            .on_update = convertForeignKeyAction(fk.on_update),
+        };
+    }
+
+    fn convertForeignKeyAction(action: ?ast.ForeignKeyAction) storage.ForeignKeyAction {
+        return switch (action orelse .NoAction) {
+            .Cascade => .Cascade,
+            .SetNull => .SetNull,
+            .Restrict => .Restrict,
+            .NoAction => .NoAction,
+        };
+    }
+
     /// Plan UPDATE statement execution
     fn planUpdate(self: *Self, update: *const ast.UpdateStatement) !ExecutionPlan {
         var steps = std.array_list.Managed(ExecutionStep).init(self.allocator);
@@ -493,6 +587,7 @@ pub const Planner = struct {
                     },
                 };
             },
+            .FunctionCall => |function_call| ast.Condition{ .FunctionCall = try self.cloneFunctionCall(function_call) },
         };
     }
 
@@ -656,6 +751,7 @@ pub const ExecutionStep = union(enum) {
     TableScan: TableScanStep,
     Filter: FilterStep,
     Project: ProjectStep,
+    Sort: SortStep,
     Limit: LimitStep,
     Insert: InsertStep,
     CreateTable: CreateTableStep,
@@ -677,6 +773,7 @@ pub const ExecutionStep = union(enum) {
             .TableScan => |*step| step.deinit(allocator),
             .Filter => |*step| step.deinit(allocator),
             .Project => |*step| step.deinit(allocator),
+            .Sort => |*step| step.deinit(allocator),
             .Limit => {},
             .Insert => |*step| step.deinit(allocator),
             .CreateTable => |*step| step.deinit(allocator),
@@ -716,16 +813,47 @@ pub const FilterStep = struct {
 
 /// Projection step (SELECT columns)
 pub const ProjectStep = struct {
-    columns: [][]const u8,
+    columns: []ProjectColumn,
 
     pub fn deinit(self: *ProjectStep, allocator: std.mem.Allocator) void {
         for (self.columns) |column| {
-            allocator.free(column);
+            allocator.free(column.name);
+            if (column.alias) |alias| {
+                allocator.free(alias);
+            }
+            if (column.function) |function_call| {
+                function_call.deinit(allocator);
+            }
         }
         allocator.free(self.columns);
     }
 };
 
+/// Selected column, or "*" for all of them
+pub const ProjectColumn = struct {
+    name: []const u8,
+    alias: ?[]const u8,
+    function: ?ast.FunctionCall = null, // Evaluated for each row instead of reading a column
+};
+
+/// Sort step (ORDER BY clause)
+pub const SortStep = struct {
+    keys: []SortKey,
+
+    pub fn deinit(self: *SortStep, allocator: std.mem.Allocator) void {
+        for (self.keys) |key| {
+            allocator.free(key.column);
+        }
+        allocator.free(self.keys);
+    }
+};
+
+/// Sort key
+pub const SortKey = struct {
+    column: []const u8,
+    descending: bool,
+};
+
 /// Limit step
 pub const LimitStep = struct {
     count: u32,
@@ -737,6 +865,8 @@ pub const InsertStep = struct {
     table_name: []const u8,
     columns: ?[][]const u8,
     values: [][]storage.Value,
+    /// What to do with a row whose unique key is taken; null aborts
+    or_conflict: ?ast.ConflictResolution = null,
 
     pub fn deinit(self: *InsertStep, allocator: std.mem.Allocator) void {
         // Free table name
@@ -768,17 +898,28 @@ pub const InsertStep = struct {
 pub const CreateTableStep = struct {
     table_name: []const u8,
     columns: []storage.Column,
+    foreign_keys: []storage.ForeignKey,
+    unique_keys: []storage.UniqueKey,
     if_not_exists: bool,
 
     pub fn deinit(self: *CreateTableStep, allocator: std.mem.Allocator) void {
         allocator.free(self.table_name);
         for (self.columns) |column| {
             allocator.free(column.name);
+            allocator.free(column.decl_type);
             if (column.default_value) |default_value| {
                 default_value.deinit(allocator);
             }
         }
         allocator.free(self.columns);
+        for (self.foreign_keys) |foreign_key| {
+            foreign_key.deinit(allocator);
+        }
+        allocator.free(self.foreign_keys);
+        for (self.unique_keys) |unique_key| {
+            unique_key.deinit(allocator);
+        }
+        allocator.free(self.unique_keys);
     }
 };
 
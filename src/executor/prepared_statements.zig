@@ -170,6 +170,13 @@ pub const PreparedStatement = struct {
                 return self.countParametersInCondition(logical.left.*) + 
                        self.countParametersInCondition(logical.right.*);
             },
+            .FunctionCall => |function_call| {
+                var count: u32 = 0;
+                for (function_call.arguments) |argument| {
+                    if (argument == .Parameter) count += 1;
+                }
+                return count;
+            },
         };
     }
     
@@ -10,6 +10,7 @@ pub const VirtualMachine = struct {
     connection: *db.Connection,
     parameters: ?[]storage.Value, // Optional parameters for prepared statements
     function_evaluator: functions.FunctionEvaluator,
+    row_columns: []const []const u8, // Names of the values in the rows being evaluated
 
     const Self = @This();
 
@@ -18,10 +19,14 @@ pub const VirtualMachine = struct {
         // Always use the connection's allocator to ensure consistency
         _ = allocator; // Ignore passed allocator, use connection's allocator
         // VM initialization complete
+        var function_evaluator = functions.FunctionEvaluator.init(connection.allocator);
+        function_evaluator.user_functions = connection.user_functions;
+
         return Self{
             .connection = connection,
             .parameters = null,
-            .function_evaluator = functions.FunctionEvaluator.init(connection.allocator),
+            .function_evaluator = function_evaluator,
+            .row_columns = &.{},
         };
     }
 
@@ -29,13 +34,27 @@ pub const VirtualMachine = struct {
     pub fn execute(self: *Self, plan: *planner.ExecutionPlan) !ExecutionResult {
         var result = ExecutionResult{
             .rows = std.array_list.Managed(storage.Row).init(self.connection.allocator),
+            .columns = std.array_list.Managed([]const u8).init(self.connection.allocator),
             .affected_rows = 0,
             .connection = self.connection,
         };
+        errdefer result.deinit();
+
+        // Autocommit writes run in an implicit transaction, so a failing step
+        // leaves no partial changes behind and commit hooks see every write
+        const implicit = self.connection.isAutocommit() and writesRows(plan);
+        if (implicit) try self.connection.beginTransaction();
+        errdefer if (implicit and self.connection.in_transaction) {
+            self.connection.rollbackTransaction() catch |err| {
+                std.log.err("Failed to rollback implicit transaction: {}", .{err});
+            };
+        };
 
         for (plan.steps) |*step| {
+            try self.checkInterrupt();
             try self.executeStep(step, &result);
         }
+        if (implicit) try self.connection.commitTransaction();
 
         // Execution completed
         return result;
@@ -45,17 +64,28 @@ pub const VirtualMachine = struct {
     pub fn executeWithParameters(self: *Self, plan: *planner.ExecutionPlan, parameters: []storage.Value) !ExecutionResult {
         // Set parameters for this execution
         self.parameters = parameters;
-        defer self.parameters = null; // Clear parameters after execution
+        self.function_evaluator.parameters = parameters;
+        defer {
+            // Clear parameters after execution
+            self.parameters = null;
+            self.function_evaluator.parameters = null;
+        }
         
         return self.execute(plan);
     }
 
+    /// Stop if another thread interrupted the connection
+    fn checkInterrupt(self: *Self) !void {
+        if (self.connection.isInterrupted()) return error.Interrupted;
+    }
+
     /// Execute a single step
     fn executeStep(self: *Self, step: *planner.ExecutionStep, result: *ExecutionResult) !void {
         switch (step.*) {
             .TableScan => |*scan| try self.executeTableScan(scan, result),
             .Filter => |*filter| try self.executeFilter(filter, result),
             .Project => |*project| try self.executeProject(project, result),
+            .Sort => |*sort| try self.executeSort(sort, result),
             .Limit => |*limit| try self.executeLimit(limit, result),
             .Insert => |*insert| try self.executeInsert(insert, result),
             .CreateTable => |*create| try self.executeCreateTable(create, result),
@@ -75,90 +105,187 @@ pub const VirtualMachine = struct {
 
     /// Execute table scan
     fn executeTableScan(self: *Self, scan: *planner.TableScanStep, result: *ExecutionResult) !void {
-        // Executing table scan
         const table = self.connection.storage_engine.getTable(scan.table_name) orelse {
             return error.TableNotFound;
         };
 
+        // Rows come back cloned by btree.selectAll, so the result takes them as-is
         const rows = try table.select(self.connection.allocator);
-        defer {
-            // Only free the rows array itself - the individual rows are now owned by result
-            self.connection.allocator.free(rows);
-        }
+        defer self.connection.allocator.free(rows);
+        errdefer for (rows) |*row| row.deinit(self.connection.allocator);
 
-        for (rows) |row| {
-            // The row is already properly cloned by btree.selectAll - use it directly
-            // Using pre-cloned row from btree
-            for (row.values) |value| {
-                switch (value) {
-                    .Text => {
-                        // Using pre-cloned text value
-                    },
-                    else => {},
-                }
-            }
-            try result.rows.append(row);
-            // Row appended to result
-        }
+        try result.appendTableColumns(table);
+        try result.rows.appendSlice(rows);
     }
 
     /// Execute filter (WHERE clause)
     fn executeFilter(self: *Self, filter: *planner.FilterStep, result: *ExecutionResult) !void {
-        var filtered_rows = std.array_list.Managed(storage.Row).init(self.connection.allocator);
+        const allocator = self.connection.allocator;
+        self.row_columns = result.columns.items;
+        defer self.row_columns = &.{};
+
+        // Decide first, so an error leaves every row in place for result.deinit
+        const keep = try allocator.alloc(bool, result.rows.items.len);
+        defer allocator.free(keep);
+        for (result.rows.items, keep) |*row, *keep_row| {
+            try self.checkInterrupt();
+            keep_row.* = try self.evaluateCondition(&filter.condition, row);
+        }
 
-        for (result.rows.items) |row| {
-            if (try self.evaluateCondition(&filter.condition, &row)) {
-                try filtered_rows.append(row);
+        var kept: usize = 0;
+        for (result.rows.items, keep) |*row, keep_row| {
+            if (keep_row) {
+                result.rows.items[kept] = row.*;
+                kept += 1;
+            } else {
+                row.deinit(allocator);
             }
         }
-
-        result.rows.deinit();
-        result.rows = filtered_rows;
+        result.rows.shrinkRetainingCapacity(kept);
     }
 
     /// Execute projection (SELECT columns)
     fn executeProject(self: *Self, project: *planner.ProjectStep, result: *ExecutionResult) !void {
-        if (project.columns.len == 1 and std.mem.eql(u8, project.columns[0], "*")) {
-            // SELECT * - return all columns, no projection needed
-            return;
+        const allocator = self.connection.allocator;
+        self.row_columns = result.columns.items;
+        defer self.row_columns = &.{};
+
+        // Resolve the selected columns to positions, expanding "*", or to
+        // the function calls evaluated for them
+        var sources = std.array_list.Managed(ColumnSource).init(allocator);
+        defer sources.deinit();
+        var names = std.array_list.Managed([]const u8).init(allocator);
+        defer {
+            for (names.items) |name| allocator.free(name);
+            names.deinit();
+        }
+        var has_aggregate = false;
+
+        for (project.columns) |*column| {
+            if (column.function) |*function_call| {
+                const is_aggregate = try self.function_evaluator.isUserAggregate(function_call.*);
+                has_aggregate = has_aggregate or is_aggregate;
+                try sources.append(.{ .function = .{ .call = function_call, .is_aggregate = is_aggregate } });
+                try names.ensureUnusedCapacity(1);
+                names.appendAssumeCapacity(try allocator.dupe(u8, column.alias orelse column.name));
+                continue;
+            }
+            if (std.mem.eql(u8, column.name, "*")) {
+                for (result.columns.items, 0..) |name, position| {
+                    try sources.append(.{ .position = position });
+                    try names.ensureUnusedCapacity(1);
+                    names.appendAssumeCapacity(try allocator.dupe(u8, name));
+                }
+                continue;
+            }
+            const position = findColumn(result.columns.items, column.name) orelse return error.ColumnNotFound;
+            try sources.append(.{ .position = position });
+            try names.ensureUnusedCapacity(1);
+            names.appendAssumeCapacity(try allocator.dupe(u8, column.alias orelse column.name));
         }
 
-        // Create projected rows with only selected columns
-        var projected_rows = std.array_list.Managed(storage.Row).init(self.connection.allocator);
-
-        for (result.rows.items) |original_row| {
-            var projected_values = std.array_list.Managed(storage.Value).init(self.connection.allocator);
-
-            // For now, we'll assume column order matches project.columns order
-            // In a real implementation, we'd need column metadata from the table schema
-            for (project.columns, 0..) |col_name, i| {
-                if (i < original_row.values.len) {
-                    // Transfer ownership of the value instead of cloning
-                    // Transferring value ownership
-                    try projected_values.append(original_row.values[i]);
-                } else {
-                    // Column doesn't exist, add NULL
-                    try projected_values.append(storage.Value.Null);
+        if (has_aggregate) {
+            try self.projectAggregate(sources.items, result);
+        } else {
+            for (result.rows.items) |*row| {
+                try self.checkInterrupt();
+                const values = try allocator.alloc(storage.Value, sources.items.len);
+                var values_done: usize = 0;
+                errdefer {
+                    for (values[0..values_done]) |value| value.deinit(allocator);
+                    allocator.free(values);
+                }
+                for (sources.items, values) |source, *value| {
+                    value.* = try self.projectValue(source, row);
+                    values_done += 1;
                 }
-                _ = col_name; // Suppress unused warning for now
+                row.deinit(allocator);
+                row.* = storage.Row{ .values = values };
             }
+        }
+
+        try result.setColumns(names.items);
+        names.clearRetainingCapacity();
+    }
 
-            try projected_rows.append(storage.Row{
-                .values = try projected_values.toOwnedSlice(),
-            });
+    /// Where a selected column's values come from
+    const ColumnSource = union(enum) {
+        position: usize,
+        function: struct { call: *const ast.FunctionCall, is_aggregate: bool },
+    };
+
+    /// Value of a selected column for one row
+    fn projectValue(self: *Self, source: ColumnSource, row: *const storage.Row) !storage.Value {
+        return switch (source) {
+            .position => |position| if (position < row.values.len) try self.cloneValue(row.values[position]) else storage.Value.Null,
+            .function => |function| try self.evaluateFunctionForRow(function.call.*, row),
+        };
+    }
+
+    /// Fold all rows into one for a user-defined aggregate
+    ///
+    /// Other selected columns take their values from the last row, as SQLite
+    /// does for bare columns next to an aggregate.
+    fn projectAggregate(self: *Self, sources: []const ColumnSource, result: *ExecutionResult) !void {
+        const allocator = self.connection.allocator;
+        const last_row: ?*const storage.Row = if (result.rows.items.len > 0) &result.rows.items[result.rows.items.len - 1] else null;
+
+        const values = try allocator.alloc(storage.Value, sources.len);
+        var values_done: usize = 0;
+        errdefer {
+            for (values[0..values_done]) |value| value.deinit(allocator);
+            allocator.free(values);
+        }
+        for (sources, values) |source, *value| {
+            value.* = if (source == .function and source.function.is_aggregate)
+                try self.function_evaluator.evaluateAggregate(source.function.call.*, result.columns.items, result.rows.items)
+            else if (last_row) |row|
+                try self.projectValue(source, row)
+            else
+                storage.Value.Null;
+            values_done += 1;
         }
 
-        // Clean up original rows carefully - we transferred value ownership, so only free the arrays
-        for (result.rows.items) |row| {
-            // Don't call value.deinit() since we transferred ownership to projected rows
-            // Cleaning up original row array
-            self.connection.allocator.free(row.values);
+        for (result.rows.items) |*row| row.deinit(allocator);
+        result.rows.clearRetainingCapacity();
+        try result.rows.append(storage.Row{ .values = values });
+    }
+
+    /// Evaluate a function call whose column arguments are read from `row`
+    fn evaluateFunctionForRow(self: *Self, function_call: ast.FunctionCall, row: *const storage.Row) !storage.Value {
+        const previous_row = self.function_evaluator.row;
+        self.function_evaluator.row = .{ .columns = self.row_columns, .values = row.values };
+        defer self.function_evaluator.row = previous_row;
+        return self.function_evaluator.evaluateFunction(function_call);
+    }
+
+    /// Execute sort (ORDER BY clause)
+    fn executeSort(self: *Self, sort: *planner.SortStep, result: *ExecutionResult) !void {
+        const positions = try self.connection.allocator.alloc(usize, sort.keys.len);
+        defer self.connection.allocator.free(positions);
+        for (sort.keys, positions) |key, *position| {
+            position.* = findColumn(result.columns.items, key.column) orelse return error.ColumnNotFound;
         }
-        result.rows.deinit();
-        result.rows = projected_rows;
+
+        const context = SortContext{ .vm = self, .keys = sort.keys, .positions = positions };
+        std.mem.sort(storage.Row, result.rows.items, context, SortContext.lessThan);
     }
 
-    /// Clone a storage value
+    const SortContext = struct {
+        vm: *Self,
+        keys: []const planner.SortKey,
+        positions: []const usize,
+
+        fn lessThan(context: SortContext, a: storage.Row, b: storage.Row) bool {
+            for (context.keys, context.positions) |key, position| {
+                const order = context.vm.compareValues(a.values[position], b.values[position]);
+                if (order == .eq) continue;
+                return if (key.descending) order == .gt else order == .lt;
+            }
+            return false;
+        }
+    };
+
     /// Resolve a value, substituting parameters if needed
     fn resolveValue(self: *Self, value: storage.Value) !storage.Value {
         return switch (value) {
@@ -183,6 +310,15 @@ pub const VirtualMachine = struct {
             else => value, // Return the value as-is for non-parameters
         };
     }
+
+    /// Resolve a value into a copy the caller owns
+    fn resolveOwned(self: *Self, value: storage.Value) !storage.Value {
+        return switch (value) {
+            // Evaluating a function call already produces a fresh value
+            .FunctionCall => try self.resolveValue(value),
+            else => try self.cloneValue(try self.resolveValue(value)),
+        };
+    }
     
     /// Evaluate a default value, including function calls
     fn evaluateDefaultValue(self: *Self, default_value: ast.DefaultValue) !storage.Value {
@@ -416,18 +552,14 @@ pub const VirtualMachine = struct {
 
     /// Execute limit
     fn executeLimit(self: *Self, limit: *planner.LimitStep, result: *ExecutionResult) !void {
+        const allocator = self.connection.allocator;
         const start = @min(limit.offset, result.rows.items.len);
         const end = @min(start + limit.count, result.rows.items.len);
 
-        if (start > 0 or end < result.rows.items.len) {
-            // Create new slice with limited rows
-            var limited_rows = std.array_list.Managed(storage.Row).init(self.connection.allocator);
-            for (result.rows.items[start..end]) |row| {
-                try limited_rows.append(row);
-            }
-            result.rows.deinit();
-            result.rows = limited_rows;
-        }
+        for (result.rows.items[0..start]) |*row| row.deinit(allocator);
+        for (result.rows.items[end..]) |*row| row.deinit(allocator);
+        std.mem.copyForwards(storage.Row, result.rows.items[0 .. end - start], result.rows.items[start..end]);
+        result.rows.shrinkRetainingCapacity(end - start);
     }
 
     /// Execute insert
@@ -436,79 +568,134 @@ pub const VirtualMachine = struct {
             return error.TableNotFound;
         };
 
+        var conflicts = std.array_list.Managed(storage.RowId).init(self.connection.allocator);
+        defer conflicts.deinit();
+
         for (insert.values) |row_values| {
-            // Build final values array for all table columns
-            var final_values = try self.connection.allocator.alloc(storage.Value, table.schema.columns.len);
-            var values_initialized: usize = 0;
-            errdefer {
-                for (final_values[0..values_initialized]) |value| {
-                    value.deinit(self.connection.allocator);
+            const values = try self.buildInsertValues(table, insert.columns, row_values);
+            var stored = false;
+            defer if (!stored) {
+                for (values) |value| value.deinit(self.connection.allocator);
+                self.connection.allocator.free(values);
+            };
+
+            conflicts.clearRetainingCapacity();
+            try self.findConflicts(table, values, null, &conflicts);
+            if (conflicts.items.len > 0) {
+                switch (insert.or_conflict orelse .Abort) {
+                    .Ignore => continue,
+                    .Replace => for (conflicts.items) |row_id| {
+                        try self.deleteRowWithUndo(table, row_id);
+                    },
+                    .Abort, .Fail, .Rollback => return error.UniqueConstraintViolation,
                 }
-                self.connection.allocator.free(final_values);
             }
 
-            // Initialize all values to null first
-            for (final_values) |*value| {
-                value.* = storage.Value.Null;
-            }
+            const row_id = try table.insert(storage.Row{ .values = values });
+            stored = true;
+            try self.connection.recordUndo(table, row_id, null);
+            self.connection.notifyRowChange(.insert, table, row_id);
+            self.connection.last_insert_rowid = @intCast(row_id);
+            result.affected_rows += 1;
+        }
+        self.connection.changes = result.affected_rows;
+    }
 
-            if (insert.columns) |specified_columns| {
-                // INSERT with specific columns: INSERT INTO table (col1, col2) VALUES (...)
-                if (specified_columns.len != row_values.len) {
-                    return error.ColumnValueMismatch;
-                }
+    /// Delete a row replaced by INSERT OR REPLACE, keeping it for rollback
+    fn deleteRowWithUndo(self: *Self, table: *storage.Table, row_id: storage.RowId) !void {
+        if (self.connection.in_transaction) {
+            if (try table.getRow(self.connection.allocator, row_id)) |old_row| {
+                var row = old_row;
+                defer row.deinit(self.connection.allocator);
+                try self.connection.recordUndo(table, row_id, row.values);
+            }
+        }
+        _ = try table.deleteRow(row_id);
+    }
 
-                // Map provided values to specified columns
-                for (specified_columns, 0..) |col_name, value_idx| {
-                    // Find the column index in the table schema
-                    var table_col_idx: ?usize = null;
-                    for (table.schema.columns, 0..) |table_col, table_idx| {
-                        if (std.mem.eql(u8, table_col.name, col_name)) {
-                            table_col_idx = table_idx;
-                            break;
-                        }
-                    }
+    /// Build a full-width row for INSERT, filling in defaults and checking NOT NULL
+    fn buildInsertValues(self: *Self, table: *storage.Table, columns: ?[][]const u8, row_values: []storage.Value) ![]storage.Value {
+        const allocator = self.connection.allocator;
+        const final_values = try allocator.alloc(storage.Value, table.schema.columns.len);
+        @memset(final_values, storage.Value.Null);
+        errdefer {
+            for (final_values) |value| value.deinit(allocator);
+            allocator.free(final_values);
+        }
 
-                    if (table_col_idx == null) {
-                        return error.ColumnNotFound;
-                    }
+        if (columns) |specified_columns| {
+            // INSERT INTO table (col1, col2) VALUES (...)
+            if (specified_columns.len != row_values.len) {
+                return error.ColumnValueMismatch;
+            }
+            for (specified_columns, row_values) |col_name, value| {
+                const index = findTableColumn(table, col_name) orelse return error.ColumnNotFound;
+                const resolved_value = try self.resolveOwned(value);
+                final_values[index].deinit(allocator);
+                final_values[index] = resolved_value;
+            }
+        } else {
+            // INSERT INTO table VALUES (...), in table column order
+            if (row_values.len > final_values.len) {
+                return error.TooManyValues;
+            }
+            for (row_values, 0..) |value, i| {
+                final_values[i] = try self.resolveOwned(value);
+            }
+        }
 
-                    const resolved_value = try self.cloneValue(try self.resolveValue(row_values[value_idx]));
-                    final_values[table_col_idx.?] = resolved_value;
-                    values_initialized = @max(values_initialized, table_col_idx.? + 1);
-                }
-            } else {
-                // INSERT without column specification: INSERT INTO table VALUES (...)
-                // Values are provided in table column order
-                for (row_values, 0..) |value, i| {
-                    if (i >= table.schema.columns.len) {
-                        return error.TooManyValues;
-                    }
-                    const resolved_value = try self.cloneValue(try self.resolveValue(value));
-                    final_values[i] = resolved_value;
-                    values_initialized = i + 1;
-                }
+        // Apply default values for columns that weren't specified
+        for (table.schema.columns, final_values) |column, *value| {
+            if (value.* != .Null) continue;
+            if (column.default_value) |default_value| {
+                value.* = try self.evaluateStorageDefaultValue(default_value);
+            } else if (!column.is_nullable) {
+                return error.MissingRequiredValue;
             }
+        }
 
-            // Apply default values for columns that weren't specified
-            for (table.schema.columns, 0..) |column, i| {
-                if (final_values[i] == .Null) {
-                    if (column.default_value) |default_value| {
-                        // Replace NULL with evaluated default value
-                        const default_val = try self.evaluateStorageDefaultValue(default_value);
-                        final_values[i] = default_val;
-                        values_initialized = @max(values_initialized, i + 1);
-                    } else if (!column.is_nullable) {
-                        // Non-nullable column without default value
-                        return error.MissingRequiredValue;
-                    }
-                    // For nullable columns without defaults, keep as NULL
+        return final_values;
+    }
+
+    /// Fail if `values` repeats a unique key of any row other than `own_row_id`
+    fn checkUnique(self: *Self, table: *storage.Table, values: []const storage.Value, own_row_id: ?storage.RowId) !void {
+        var conflicts = std.array_list.Managed(storage.RowId).init(self.connection.allocator);
+        defer conflicts.deinit();
+
+        try self.findConflicts(table, values, own_row_id, &conflicts);
+        if (conflicts.items.len > 0) return error.UniqueConstraintViolation;
+    }
+
+    /// Collect the ids of rows sharing a unique key with `values`, other than `own_row_id`
+    fn findConflicts(
+        self: *Self,
+        table: *storage.Table,
+        values: []const storage.Value,
+        own_row_id: ?storage.RowId,
+        conflicts: *std.array_list.Managed(storage.RowId),
+    ) !void {
+        if (table.schema.unique_keys.len == 0) return;
+
+        var row_ids = std.array_list.Managed(storage.RowId).init(self.connection.allocator);
+        defer row_ids.deinit();
+        const rows = try table.selectWithIds(self.connection.allocator, &row_ids);
+        defer self.freeRows(rows);
+
+        for (rows, row_ids.items) |row, row_id| {
+            if (own_row_id) |own| {
+                if (own == row_id) continue;
+            }
+            for (table.schema.unique_keys) |unique_key| {
+                // NULLs never equal each other, so a key holding one can't conflict
+                const same_key = for (unique_key.columns) |column| {
+                    if (values[column] == .Null) break false;
+                    if (self.compareValues(values[column], row.values[column]) != .eq) break false;
+                } else true;
+                if (same_key) {
+                    try conflicts.append(row_id);
+                    break;
                 }
             }
-
-            const row = storage.Row{ .values = final_values };
-            try table.insert(row);
-            result.affected_rows += 1;
         }
     }
 
@@ -526,6 +713,7 @@ pub const VirtualMachine = struct {
             // Properly clean up cloned columns on error
             for (cloned_columns[0..columns_cloned]) |column| {
                 self.connection.allocator.free(column.name);
+                self.connection.allocator.free(column.decl_type);
                 if (column.default_value) |default_value| {
                     default_value.deinit(self.connection.allocator);
                 }
@@ -537,6 +725,7 @@ pub const VirtualMachine = struct {
             cloned_columns[i] = storage.Column{
                 .name = try self.connection.allocator.dupe(u8, column.name),
                 .data_type = column.data_type,
+                .decl_type = try self.connection.allocator.dupeZ(u8, column.decl_type),
                 .is_primary_key = column.is_primary_key,
                 .is_nullable = column.is_nullable,
                 .default_value = if (column.default_value) |default_value|
@@ -547,8 +736,38 @@ pub const VirtualMachine = struct {
             columns_cloned = i + 1;
         }
 
+        var cloned_foreign_keys = try self.connection.allocator.alloc(storage.ForeignKey, create.foreign_keys.len);
+        var foreign_keys_cloned: usize = 0;
+        errdefer {
+            for (cloned_foreign_keys[0..foreign_keys_cloned]) |foreign_key| {
+                foreign_key.deinit(self.connection.allocator);
+            }
+            self.connection.allocator.free(cloned_foreign_keys);
+        }
+
+        for (create.foreign_keys, 0..) |foreign_key, i| {
+            cloned_foreign_keys[i] = try foreign_key.clone(self.connection.allocator);
+            foreign_keys_cloned = i + 1;
+        }
+
+        var cloned_unique_keys = try self.connection.allocator.alloc(storage.UniqueKey, create.unique_keys.len);
+        var unique_keys_cloned: usize = 0;
+        errdefer {
+            for (cloned_unique_keys[0..unique_keys_cloned]) |unique_key| {
+                unique_key.deinit(self.connection.allocator);
+            }
+            self.connection.allocator.free(cloned_unique_keys);
+        }
+
+        for (create.unique_keys, 0..) |unique_key, i| {
+            cloned_unique_keys[i] = try unique_key.clone(self.connection.allocator);
+            unique_keys_cloned = i + 1;
+        }
+
         var schema = storage.TableSchema{
             .columns = cloned_columns,
+            .foreign_keys = cloned_foreign_keys,
+            .unique_keys = cloned_unique_keys,
         };
 
         self.connection.storage_engine.createTable(create.table_name, schema) catch |err| {
@@ -563,280 +782,111 @@ pub const VirtualMachine = struct {
 
     /// Execute update
     fn executeUpdate(self: *Self, update: *planner.UpdateStep, result: *ExecutionResult) !void {
+        const allocator = self.connection.allocator;
         const table = self.connection.storage_engine.getTable(update.table_name) orelse {
             return error.TableNotFound;
         };
 
-        // Get all current rows
-        const all_rows = try table.select(self.connection.allocator);
-        defer {
-            for (all_rows) |row| {
-                for (row.values) |value| {
-                    value.deinit(self.connection.allocator);
-                }
-                self.connection.allocator.free(row.values);
-            }
-            self.connection.allocator.free(all_rows);
+        const targets = try allocator.alloc(usize, update.assignments.len);
+        defer allocator.free(targets);
+        for (update.assignments, targets) |assignment, *target| {
+            target.* = findTableColumn(table, assignment.column) orelse return error.ColumnNotFound;
         }
 
-        var updated_count: u32 = 0;
-        var updated_rows = std.array_list.Managed(storage.Row).init(self.connection.allocator);
-        defer {
-            for (updated_rows.items) |row| {
-                for (row.values) |value| {
-                    value.deinit(self.connection.allocator);
-                }
-                self.connection.allocator.free(row.values);
-            }
-            updated_rows.deinit();
-        }
+        var row_ids = std.array_list.Managed(storage.RowId).init(allocator);
+        defer row_ids.deinit();
+        const rows = try table.selectWithIds(allocator, &row_ids);
+        defer self.freeRows(rows);
 
-        for (all_rows) |row| {
-            // Check if row matches condition
-            var matches = true;
-            if (update.condition) |condition| {
-                matches = try self.evaluateCondition(&condition, &row);
-            }
+        const column_names = try tableColumnNames(allocator, table);
+        defer allocator.free(column_names);
+        self.row_columns = column_names;
+        defer self.row_columns = &.{};
 
-            if (matches) {
-                // Create updated row by cloning the original and applying changes
-                var updated_values = try self.connection.allocator.alloc(storage.Value, row.values.len);
-                var values_cloned: usize = 0;
-                errdefer {
-                    for (updated_values[0..values_cloned]) |value| {
-                        value.deinit(self.connection.allocator);
-                    }
-                    self.connection.allocator.free(updated_values);
-                }
-
-                for (row.values, 0..) |value, i| {
-                    updated_values[i] = try self.cloneValue(value);
-                    values_cloned = i + 1;
-                }
-
-                // Apply assignments
-                for (update.assignments) |assignment| {
-                    // For simplicity, update the first column (in a real implementation,
-                    // we'd need proper column name to index mapping from the table schema)
-                    if (updated_values.len > 0) {
-                        updated_values[0].deinit(self.connection.allocator);
-                        updated_values[0] = try self.cloneValue(assignment.value);
-                    }
-                    _ = assignment.column; // Suppress unused warning for now
-                }
-
-                try updated_rows.append(storage.Row{ .values = updated_values });
-                updated_count += 1;
-            } else {
-                // Keep the original row unchanged
-                var cloned_values = try self.connection.allocator.alloc(storage.Value, row.values.len);
-                var values_cloned: usize = 0;
-                errdefer {
-                    for (cloned_values[0..values_cloned]) |value| {
-                        value.deinit(self.connection.allocator);
-                    }
-                    self.connection.allocator.free(cloned_values);
-                }
-
-                for (row.values, 0..) |value, i| {
-                    cloned_values[i] = try self.cloneValue(value);
-                    values_cloned = i + 1;
-                }
-                try updated_rows.append(storage.Row{ .values = cloned_values });
+        // Rows are rewritten in place under their row ids
+        var updated_count: u32 = 0;
+        for (rows, row_ids.items) |row, row_id| {
+            try self.checkInterrupt();
+            if (update.condition) |condition| {
+                if (!try self.evaluateCondition(&condition, &row)) continue;
             }
-        }
 
-        // Replace the table data with updated rows
-        // For now, we'll recreate the table (in a real implementation, we'd have proper update methods)
-        const table_name = try self.connection.allocator.dupe(u8, update.table_name);
-        defer self.connection.allocator.free(table_name);
+            var updated_row = storage.Row{ .values = try self.cloneValues(row.values) };
+            errdefer updated_row.deinit(allocator);
 
-        // Clone table schema before dropping the table to avoid use-after-free
-        var cloned_columns = try self.connection.allocator.alloc(storage.Column, table.schema.columns.len);
-        var columns_cloned: usize = 0;
-        errdefer {
-            // Clean up cloned columns on error
-            for (cloned_columns[0..columns_cloned]) |column| {
-                self.connection.allocator.free(column.name);
-                if (column.default_value) |default_value| {
-                    default_value.deinit(self.connection.allocator);
-                }
+            for (update.assignments, targets) |assignment, target| {
+                const value = try self.resolveOwned(assignment.value);
+                updated_row.values[target].deinit(allocator);
+                updated_row.values[target] = value;
             }
-            self.connection.allocator.free(cloned_columns);
-        }
-
-        for (table.schema.columns, 0..) |column, i| {
-            cloned_columns[i] = storage.Column{
-                .name = try self.connection.allocator.dupe(u8, column.name),
-                .data_type = column.data_type,
-                .is_primary_key = column.is_primary_key,
-                .is_nullable = column.is_nullable,
-                .default_value = if (column.default_value) |default_value|
-                    try self.cloneStorageDefaultValue(default_value)
-                else
-                    null,
-            };
-            columns_cloned = i + 1;
-        }
-
-        var cloned_schema = storage.TableSchema{
-            .columns = cloned_columns,
-        };
-
-        // Drop and recreate table with updated data
-        try self.connection.storage_engine.dropTable(update.table_name);
-        try self.connection.storage_engine.createTable(table_name, cloned_schema);
-
-        // Clean up temporary schema (storage engine has its own clone now)
-        cloned_schema.deinit(self.connection.allocator);
-
-        // Reinsert all rows
-        const new_table = self.connection.storage_engine.getTable(update.table_name).?;
-        for (updated_rows.items) |row| {
-            // Clone the row for insertion
-            var insert_values = try self.connection.allocator.alloc(storage.Value, row.values.len);
-            var values_cloned: usize = 0;
-            errdefer {
-                for (insert_values[0..values_cloned]) |value| {
-                    value.deinit(self.connection.allocator);
-                }
-                self.connection.allocator.free(insert_values);
+            for (table.schema.columns, updated_row.values) |column, value| {
+                if (!column.is_nullable and value == .Null) return error.MissingRequiredValue;
             }
+            try self.checkUnique(table, updated_row.values, row_id);
 
-            for (row.values, 0..) |value, i| {
-                insert_values[i] = try self.cloneValue(value);
-                values_cloned = i + 1;
-            }
-            try new_table.insert(storage.Row{ .values = insert_values });
+            if (!try table.updateRow(row_id, updated_row)) return error.RowNotFound;
+            try self.connection.recordUndo(table, row_id, row.values);
+            self.connection.notifyRowChange(.update, table, row_id);
+            updated_count += 1;
         }
 
         result.affected_rows = updated_count;
+        self.connection.changes = updated_count;
     }
 
     /// Execute delete
     fn executeDelete(self: *Self, delete: *planner.DeleteStep, result: *ExecutionResult) !void {
+        const allocator = self.connection.allocator;
         const table = self.connection.storage_engine.getTable(delete.table_name) orelse {
             return error.TableNotFound;
         };
 
-        // Get all current rows
-        const all_rows = try table.select(self.connection.allocator);
-        defer {
-            for (all_rows) |row| {
-                for (row.values) |value| {
-                    value.deinit(self.connection.allocator);
-                }
-                self.connection.allocator.free(row.values);
-            }
-            self.connection.allocator.free(all_rows);
-        }
+        var row_ids = std.array_list.Managed(storage.RowId).init(allocator);
+        defer row_ids.deinit();
+        const rows = try table.selectWithIds(allocator, &row_ids);
+        defer self.freeRows(rows);
 
-        var deleted_count: u32 = 0;
-        var surviving_rows = std.array_list.Managed(storage.Row).init(self.connection.allocator);
-        defer {
-            for (surviving_rows.items) |row| {
-                for (row.values) |value| {
-                    value.deinit(self.connection.allocator);
-                }
-                self.connection.allocator.free(row.values);
-            }
-            surviving_rows.deinit();
-        }
+        const column_names = try tableColumnNames(allocator, table);
+        defer allocator.free(column_names);
+        self.row_columns = column_names;
+        defer self.row_columns = &.{};
 
-        for (all_rows) |row| {
-            // Check if row matches delete condition
-            var should_delete = true;
+        var deleted_count: u32 = 0;
+        for (rows, row_ids.items) |row, row_id| {
+            try self.checkInterrupt();
             if (delete.condition) |condition| {
-                should_delete = try self.evaluateCondition(&condition, &row);
+                if (!try self.evaluateCondition(&condition, &row)) continue;
             }
-
-            if (should_delete) {
+            if (try table.deleteRow(row_id)) {
+                try self.connection.recordUndo(table, row_id, row.values);
+                self.connection.notifyRowChange(.delete, table, row_id);
                 deleted_count += 1;
-            } else {
-                // Keep this row - clone it for the surviving rows
-                var cloned_values = try self.connection.allocator.alloc(storage.Value, row.values.len);
-                var values_cloned: usize = 0;
-                errdefer {
-                    for (cloned_values[0..values_cloned]) |value| {
-                        value.deinit(self.connection.allocator);
-                    }
-                    self.connection.allocator.free(cloned_values);
-                }
-
-                for (row.values, 0..) |value, i| {
-                    cloned_values[i] = try self.cloneValue(value);
-                    values_cloned = i + 1;
-                }
-                try surviving_rows.append(storage.Row{ .values = cloned_values });
             }
         }
 
-        // Replace the table data with surviving rows
-        if (deleted_count > 0) {
-            const table_name = try self.connection.allocator.dupe(u8, delete.table_name);
-            defer self.connection.allocator.free(table_name);
-
-            // Clone table schema before dropping the table to avoid use-after-free
-            var cloned_columns = try self.connection.allocator.alloc(storage.Column, table.schema.columns.len);
-            var columns_cloned: usize = 0;
-            errdefer {
-                // Clean up cloned columns on error
-                for (cloned_columns[0..columns_cloned]) |column| {
-                    self.connection.allocator.free(column.name);
-                    if (column.default_value) |default_value| {
-                        default_value.deinit(self.connection.allocator);
-                    }
-                }
-                self.connection.allocator.free(cloned_columns);
-            }
-
-            for (table.schema.columns, 0..) |column, i| {
-                cloned_columns[i] = storage.Column{
-                    .name = try self.connection.allocator.dupe(u8, column.name),
-                    .data_type = column.data_type,
-                    .is_primary_key = column.is_primary_key,
-                    .is_nullable = column.is_nullable,
-                    .default_value = if (column.default_value) |default_value|
-                        try self.cloneStorageDefaultValue(default_value)
-                    else
-                        null,
-                };
-                columns_cloned = i + 1;
-            }
-
-            var cloned_schema = storage.TableSchema{
-                .columns = cloned_columns,
-            };
-
-            // Drop and recreate table with remaining data
-            try self.connection.storage_engine.dropTable(delete.table_name);
-            try self.connection.storage_engine.createTable(table_name, cloned_schema);
-
-            // Clean up temporary schema (storage engine has its own clone now)
-            cloned_schema.deinit(self.connection.allocator);
-
-            // Reinsert surviving rows
-            const new_table = self.connection.storage_engine.getTable(delete.table_name).?;
-            for (surviving_rows.items) |row| {
-                // Clone the row for insertion
-                var insert_values = try self.connection.allocator.alloc(storage.Value, row.values.len);
-                var values_cloned: usize = 0;
-                errdefer {
-                    for (insert_values[0..values_cloned]) |value| {
-                        value.deinit(self.connection.allocator);
-                    }
-                    self.connection.allocator.free(insert_values);
-                }
+        result.affected_rows = deleted_count;
+        self.connection.changes = deleted_count;
+    }
 
-                for (row.values, 0..) |value, i| {
-                    insert_values[i] = try self.cloneValue(value);
-                    values_cloned = i + 1;
-                }
-                try new_table.insert(storage.Row{ .values = insert_values });
-            }
+    /// Clone a row's values
+    fn cloneValues(self: *Self, values: []const storage.Value) ![]storage.Value {
+        const cloned = try self.connection.allocator.alloc(storage.Value, values.len);
+        var values_cloned: usize = 0;
+        errdefer {
+            for (cloned[0..values_cloned]) |value| value.deinit(self.connection.allocator);
+            self.connection.allocator.free(cloned);
+        }
+        for (values, cloned) |value, *copy| {
+            copy.* = try self.cloneValue(value);
+            values_cloned += 1;
         }
+        return cloned;
+    }
 
-        result.affected_rows = deleted_count;
+    /// Free rows returned by a table select
+    fn freeRows(self: *Self, rows: []storage.Row) void {
+        for (rows) |*row| row.deinit(self.connection.allocator);
+        self.connection.allocator.free(rows);
     }
 
     /// Evaluate a condition against a row
@@ -852,6 +902,11 @@ pub const VirtualMachine = struct {
                     .Or => left_result or right_result,
                 };
             },
+            .FunctionCall => |function_call| {
+                const value = try self.evaluateFunctionForRow(function_call, row);
+                defer value.deinit(self.connection.allocator);
+                return isTruthy(value);
+            },
         };
     }
 
@@ -890,14 +945,10 @@ pub const VirtualMachine = struct {
     fn evaluateExpression(self: *Self, expression: *const ast.Expression, row: *const storage.Row) !storage.Value {
         return switch (expression.*) {
             .Column => |col_name| {
-                // For now, just return the first value (would need column mapping)
-                _ = col_name;
-                if (row.values.len > 0) {
-                    // Clone the value so it can be safely freed by caller
-                    return try self.cloneValue(row.values[0]);
-                } else {
-                    return storage.Value.Null;
-                }
+                const index = findColumn(self.row_columns, col_name) orelse return error.ColumnNotFound;
+                if (index >= row.values.len) return storage.Value.Null;
+                // Clone the value so it can be safely freed by caller
+                return try self.cloneValue(row.values[index]);
             },
             .Literal => |value| {
                 return switch (value) {
@@ -906,34 +957,14 @@ pub const VirtualMachine = struct {
                     .Real => |r| storage.Value{ .Real = r },
                     .Blob => |b| storage.Value{ .Blob = try self.connection.allocator.dupe(u8, b) },
                     .Null => storage.Value.Null,
-                    .Parameter => |param_index| {
-                        if (self.parameters) |params| {
-                            if (param_index < params.len) {
-                                return try self.resolveValue(params[param_index]);
-                            } else {
-                                return error.ParameterIndexOutOfBounds;
-                            }
-                        } else {
-                            return error.NoParametersProvided;
-                        }
-                    },
+                    .Parameter => |param_index| try self.resolveOwned(storage.Value{ .Parameter = param_index }),
                     .FunctionCall => |function_call| {
-                        // Evaluate function call immediately
-                        return try self.function_evaluator.evaluateFunction(function_call);
+                        // Evaluate function call immediately, reading columns from the row
+                        return try self.evaluateFunctionForRow(function_call, row);
                     },
                 };
             },
-            .Parameter => |param_index| {
-                if (self.parameters) |params| {
-                    if (param_index < params.len) {
-                        return try self.resolveValue(params[param_index]);
-                    } else {
-                        return error.ParameterIndexOutOfBounds;
-                    }
-                } else {
-                    return error.NoParametersProvided;
-                }
-            },
+            .Parameter => |param_index| try self.resolveOwned(storage.Value{ .Parameter = param_index }),
         };
     }
 
@@ -1016,165 +1047,71 @@ pub const VirtualMachine = struct {
     }
 
     /// Execute nested loop join (simple but works for all join types)
+    ///
+    /// The rows produced so far are the left side, so several joins chain.
     fn executeNestedLoopJoin(self: *Self, join: *planner.NestedLoopJoinStep, result: *ExecutionResult) !void {
-        // Get tables
-        const left_table = self.connection.storage_engine.getTable(join.left_table) orelse {
-            return error.TableNotFound;
-        };
+        const allocator = self.connection.allocator;
         const right_table = self.connection.storage_engine.getTable(join.right_table) orelse {
             return error.TableNotFound;
         };
 
-        // Get all rows from both tables
-        const left_rows = try left_table.select(self.connection.allocator);
-        defer {
-            for (left_rows) |row| {
-                for (row.values) |value| {
-                    value.deinit(self.connection.allocator);
-                }
-                self.connection.allocator.free(row.values);
-            }
-            self.connection.allocator.free(left_rows);
-        }
+        const right_rows = try right_table.select(allocator);
+        defer self.freeRows(right_rows);
 
-        const right_rows = try right_table.select(self.connection.allocator);
-        defer {
-            for (right_rows) |row| {
-                for (row.values) |value| {
-                    value.deinit(self.connection.allocator);
-                }
-                self.connection.allocator.free(row.values);
-            }
-            self.connection.allocator.free(right_rows);
-        }
+        const left_rows = try result.rows.toOwnedSlice();
+        defer self.freeRows(left_rows);
+        const left_width = result.columns.items.len;
+        try result.appendTableColumns(right_table);
+
+        self.row_columns = result.columns.items;
+        defer self.row_columns = &.{};
+
+        const null_left_row = try self.createNullRow(left_width);
+        defer allocator.free(null_left_row.values);
+        const null_right_row = try self.createNullRow(right_table.schema.columns.len);
+        defer allocator.free(null_right_row.values);
 
-        // Perform join logic based on join type
-        for (left_rows) |left_row| {
+        const right_matched = try allocator.alloc(bool, right_rows.len);
+        defer allocator.free(right_matched);
+        @memset(right_matched, false);
+
+        for (left_rows) |*left_row| {
+            try self.checkInterrupt();
             var matched = false;
-            
-            for (right_rows) |right_row| {
-                // Create combined row for condition evaluation
-                const combined_row = try self.combineRows(&left_row, &right_row);
-                defer {
-                    for (combined_row.values) |value| {
-                        value.deinit(self.connection.allocator);
-                    }
-                    self.connection.allocator.free(combined_row.values);
-                }
 
-                // Check join condition
-                if (try self.evaluateCondition(&join.condition, &combined_row)) {
+            for (right_rows, right_matched) |*right_row, *right_row_matched| {
+                var combined_row = try self.combineRows(left_row, right_row);
+                const keep = self.evaluateCondition(&join.condition, &combined_row) catch |err| {
+                    combined_row.deinit(allocator);
+                    return err;
+                };
+                if (keep) {
                     matched = true;
-                    // Add the combined row to results
-                    const final_row = try self.combineRows(&left_row, &right_row);
-                    try result.rows.append(final_row);
+                    right_row_matched.* = true;
+                    try self.appendRow(result, combined_row);
+                } else {
+                    combined_row.deinit(allocator);
                 }
             }
 
-            // Handle LEFT JOIN case where no match found
-            if (!matched and join.join_type == .Left) {
-                const null_right_row = try self.createNullRow(right_rows[0].values.len);
-                defer {
-                    for (null_right_row.values) |value| {
-                        value.deinit(self.connection.allocator);
-                    }
-                    self.connection.allocator.free(null_right_row.values);
-                }
-                
-                const final_row = try self.combineRows(&left_row, &null_right_row);
-                try result.rows.append(final_row);
+            // LEFT and FULL joins keep unmatched left rows, padded with NULLs
+            if (!matched and (join.join_type == .Left or join.join_type == .Full)) {
+                try self.appendRow(result, try self.combineRows(left_row, &null_right_row));
             }
         }
 
-        // Handle RIGHT JOIN - iterate from right side
+        // RIGHT and FULL joins keep unmatched right rows, padded with NULLs
         if (join.join_type == .Right or join.join_type == .Full) {
-            for (right_rows) |right_row| {
-                var matched = false;
-                
-                for (left_rows) |left_row| {
-                    const combined_row = try self.combineRows(&left_row, &right_row);
-                    defer {
-                        for (combined_row.values) |value| {
-                            value.deinit(self.connection.allocator);
-                        }
-                        self.connection.allocator.free(combined_row.values);
-                    }
-
-                    if (try self.evaluateCondition(&join.condition, &combined_row)) {
-                        matched = true;
-                        break; // We already added this in the LEFT side iteration for FULL
-                    }
-                }
-
-                // Add unmatched RIGHT rows for RIGHT and FULL joins
-                if (!matched and (join.join_type == .Right or join.join_type == .Full)) {
-                    const null_left_row = try self.createNullRow(left_rows[0].values.len);
-                    defer {
-                        for (null_left_row.values) |value| {
-                            value.deinit(self.connection.allocator);
-                        }
-                        self.connection.allocator.free(null_left_row.values);
-                    }
-                    
-                    const final_row = try self.combineRows(&null_left_row, &right_row);
-                    try result.rows.append(final_row);
-                }
+            for (right_rows, right_matched) |*right_row, right_row_matched| {
+                if (right_row_matched) continue;
+                try self.appendRow(result, try self.combineRows(&null_left_row, right_row));
             }
         }
     }
 
     /// Execute hash join (optimized for equi-joins)
     fn executeHashJoin(self: *Self, join: *planner.HashJoinStep, result: *ExecutionResult) !void {
-        // Get tables
-        const left_table = self.connection.storage_engine.getTable(join.left_table) orelse {
-            return error.TableNotFound;
-        };
-        const right_table = self.connection.storage_engine.getTable(join.right_table) orelse {
-            return error.TableNotFound;
-        };
-
-        // Get all rows from both tables
-        const left_rows = try left_table.select(self.connection.allocator);
-        defer {
-            for (left_rows) |row| {
-                for (row.values) |value| {
-                    value.deinit(self.connection.allocator);
-                }
-                self.connection.allocator.free(row.values);
-            }
-            self.connection.allocator.free(left_rows);
-        }
-
-        const right_rows = try right_table.select(self.connection.allocator);
-        defer {
-            for (right_rows) |row| {
-                for (row.values) |value| {
-                    value.deinit(self.connection.allocator);
-                }
-                self.connection.allocator.free(row.values);
-            }
-            self.connection.allocator.free(right_rows);
-        }
-
-        // Build hash table from smaller table (right table for now)
-        var hash_map = std.AutoHashMap(u64, std.array_list.Managed(storage.Row)).init(self.connection.allocator);
-        defer {
-            var iterator = hash_map.iterator();
-            while (iterator.next()) |entry| {
-                for (entry.value_ptr.items) |row| {
-                    for (row.values) |value| {
-                        value.deinit(self.connection.allocator);
-                    }
-                    self.connection.allocator.free(row.values);
-                }
-                entry.value_ptr.deinit();
-            }
-            hash_map.deinit();
-        }
-
         // TODO: For now, fall back to nested loop join
-        // Hash join implementation requires column index resolution
-        // which needs schema information
         var nested_join = planner.NestedLoopJoinStep{
             .join_type = join.join_type,
             .left_table = join.left_table,
@@ -1184,6 +1121,15 @@ pub const VirtualMachine = struct {
         return self.executeNestedLoopJoin(&nested_join, result);
     }
 
+    /// Append a row to the result, freeing it if that fails
+    fn appendRow(self: *Self, result: *ExecutionResult, row: storage.Row) !void {
+        result.rows.append(row) catch |err| {
+            var owned_row = row;
+            owned_row.deinit(self.connection.allocator);
+            return err;
+        };
+    }
+
     /// Combine two rows into a single row
     fn combineRows(self: *Self, left_row: *const storage.Row, right_row: *const storage.Row) !storage.Row {
         const total_columns = left_row.values.len + right_row.values.len;
@@ -1222,115 +1168,101 @@ pub const VirtualMachine = struct {
 
     /// Execute aggregate operation
     fn executeAggregate(self: *Self, agg: *planner.AggregateStep, result: *ExecutionResult) !void {
-        // For now, implement COUNT(*) aggregate function
-        for (agg.aggregates) |aggregate_op| {
-            switch (aggregate_op.function_type) {
-                .Count => {
-                    // COUNT(*) - count all rows in the result
-                    const count = result.rows.items.len;
-                    const result_value = storage.Value{ .Integer = @intCast(count) };
-                    try self.finishAggregateResult(result, result_value);
-                },
-                .Sum => {
-                    // SUM(column) - sum all numeric values in the specified column
-                    var sum: f64 = 0.0;
-                    for (result.rows.items) |row| {
-                        if (row.values.len > 0) {
-                            switch (row.values[0]) {
-                                .Integer => |i| sum += @floatFromInt(i),
-                                .Real => |r| sum += r,
-                                else => {}, // Skip non-numeric values
-                            }
-                        }
-                    }
-                    const result_value = storage.Value{ .Real = sum };
-                    try self.finishAggregateResult(result, result_value);
-                },
-                .Avg => {
-                    // AVG(column) - average of all numeric values
-                    var sum: f64 = 0.0;
-                    var count: u32 = 0;
-                    for (result.rows.items) |row| {
-                        if (row.values.len > 0) {
-                            switch (row.values[0]) {
-                                .Integer => |i| {
-                                    sum += @floatFromInt(i);
-                                    count += 1;
-                                },
-                                .Real => |r| {
-                                    sum += r;
-                                    count += 1;
-                                },
-                                else => {}, // Skip non-numeric values
-                            }
-                        }
-                    }
-                    const avg = if (count > 0) sum / @as(f64, @floatFromInt(count)) else 0.0;
-                    const result_value = storage.Value{ .Real = avg };
-                    try self.finishAggregateResult(result, result_value);
-                },
-                .Min => {
-                    // MIN(column) - minimum value
-                    var min_value: ?storage.Value = null;
-                    for (result.rows.items) |row| {
-                        if (row.values.len > 0) {
-                            const current_value = row.values[0];
-                            if (min_value == null) {
-                                min_value = try self.cloneValue(current_value);
-                            } else {
-                                if (self.compareValues(current_value, min_value.?) == .lt) {
-                                    min_value.?.deinit(self.connection.allocator);
-                                    min_value = try self.cloneValue(current_value);
-                                }
-                            }
-                        }
-                    }
-                    const result_value = min_value orelse storage.Value.Null;
-                    try self.finishAggregateResult(result, result_value);
-                },
-                .Max => {
-                    // MAX(column) - maximum value
-                    var max_value: ?storage.Value = null;
-                    for (result.rows.items) |row| {
-                        if (row.values.len > 0) {
-                            const current_value = row.values[0];
-                            if (max_value == null) {
-                                max_value = try self.cloneValue(current_value);
-                            } else {
-                                if (self.compareValues(current_value, max_value.?) == .gt) {
-                                    max_value.?.deinit(self.connection.allocator);
-                                    max_value = try self.cloneValue(current_value);
-                                }
-                            }
-                        }
-                    }
-                    const result_value = max_value orelse storage.Value.Null;
-                    try self.finishAggregateResult(result, result_value);
-                },
-                else => {
-                    // TODO: Implement GroupConcat, CountDistinct
-                    return error.NotImplemented;
-                }
-            }
+        const allocator = self.connection.allocator;
+
+        const values = try allocator.alloc(storage.Value, agg.aggregates.len);
+        var values_computed: usize = 0;
+        errdefer {
+            for (values[0..values_computed]) |value| value.deinit(allocator);
+            allocator.free(values);
+        }
+        for (agg.aggregates, values) |*aggregate_op, *value| {
+            value.* = try self.computeAggregate(aggregate_op, result);
+            values_computed += 1;
         }
-    }
 
-    /// Helper function to finish aggregate result
-    fn finishAggregateResult(self: *Self, result: *ExecutionResult, aggregate_value: storage.Value) !void {
-        // Clear existing rows and add the aggregate result
-        for (result.rows.items) |row| {
-            for (row.values) |value| {
-                value.deinit(self.connection.allocator);
-            }
-            self.connection.allocator.free(row.values);
+        const names = try allocator.alloc([]const u8, agg.aggregates.len);
+        defer allocator.free(names);
+        var names_created: usize = 0;
+        errdefer for (names[0..names_created]) |name| allocator.free(name);
+        for (agg.aggregates, names) |aggregate_op, *name| {
+            name.* = if (aggregate_op.alias) |alias|
+                try allocator.dupe(u8, alias)
+            else
+                try aggregateLabel(allocator, aggregate_op);
+            names_created += 1;
         }
-        result.rows.clearAndFree();
 
-        // Create a single row with the aggregate result
-        var aggregate_row_values = try self.connection.allocator.alloc(storage.Value, 1);
-        aggregate_row_values[0] = aggregate_value;
+        // All aggregates collapse the input into a single row
+        for (result.rows.items) |*row| row.deinit(allocator);
+        result.rows.clearRetainingCapacity();
+        try result.rows.ensureUnusedCapacity(1);
+        try result.setColumns(names);
+        result.rows.appendAssumeCapacity(storage.Row{ .values = values });
+    }
 
-        try result.rows.append(storage.Row{ .values = aggregate_row_values });
+    /// Compute one aggregate over the rows of the result
+    fn computeAggregate(self: *Self, aggregate_op: *const planner.AggregateOperation, result: *const ExecutionResult) !storage.Value {
+        const position: ?usize = if (aggregate_op.column) |column|
+            findColumn(result.columns.items, column) orelse return error.ColumnNotFound
+        else
+            null;
+
+        switch (aggregate_op.function_type) {
+            .Count => {
+                // COUNT(*) counts rows; COUNT(column) counts non-NULL values
+                const index = position orelse return storage.Value{ .Integer = @intCast(result.rows.items.len) };
+                var count: i64 = 0;
+                for (result.rows.items) |row| {
+                    if (row.values[index] != .Null) count += 1;
+                }
+                return storage.Value{ .Integer = count };
+            },
+            .Sum, .Avg => {
+                const index = position orelse return error.ColumnNotFound;
+                var integer_sum: i64 = 0;
+                var real_sum: f64 = 0.0;
+                var all_integers = true;
+                var count: u32 = 0;
+                for (result.rows.items) |row| {
+                    switch (row.values[index]) {
+                        .Integer => |i| {
+                            integer_sum +%= i;
+                            real_sum += @floatFromInt(i);
+                        },
+                        .Real => |r| {
+                            real_sum += r;
+                            all_integers = false;
+                        },
+                        else => continue, // Skip NULL and non-numeric values
+                    }
+                    count += 1;
+                }
+                // Like SQLite, the aggregate of no values is NULL
+                if (count == 0) return storage.Value.Null;
+                if (aggregate_op.function_type == .Avg) {
+                    return storage.Value{ .Real = real_sum / @as(f64, @floatFromInt(count)) };
+                }
+                return if (all_integers) storage.Value{ .Integer = integer_sum } else storage.Value{ .Real = real_sum };
+            },
+            .Min, .Max => {
+                const index = position orelse return error.ColumnNotFound;
+                const wanted: std.math.Order = if (aggregate_op.function_type == .Min) .lt else .gt;
+                var best: ?storage.Value = null;
+                for (result.rows.items) |row| {
+                    const current_value = row.values[index];
+                    if (current_value == .Null) continue;
+                    if (best == null or self.compareValues(current_value, best.?) == wanted) {
+                        best = current_value;
+                    }
+                }
+                return if (best) |value| try self.cloneValue(value) else storage.Value.Null;
+            },
+            else => {
+                // TODO: Implement GroupConcat, CountDistinct
+                return error.NotImplemented;
+            },
+        }
     }
 
     /// Execute group by operation (stub implementation)
@@ -1344,26 +1276,20 @@ pub const VirtualMachine = struct {
     
     /// Execute BEGIN TRANSACTION
     fn executeBeginTransaction(self: *Self, result: *ExecutionResult) !void {
-        _ = self;
         _ = result;
-        // TODO: Implement transaction support in storage engine
-        // For now, transactions are a no-op
+        try self.connection.beginTransaction();
     }
     
     /// Execute COMMIT
     fn executeCommit(self: *Self, result: *ExecutionResult) !void {
-        _ = self;
         _ = result;
-        // TODO: Implement transaction support in storage engine
-        // For now, transactions are a no-op
+        try self.connection.commitTransaction();
     }
     
     /// Execute ROLLBACK
     fn executeRollback(self: *Self, result: *ExecutionResult) !void {
-        _ = self;
         _ = result;
-        // TODO: Implement transaction support in storage engine
-        // For now, transactions are a no-op
+        try self.connection.rollbackTransaction();
     }
     
     /// Execute CREATE INDEX
@@ -1389,8 +1315,18 @@ pub const VirtualMachine = struct {
             }
         }
         
-        // TODO: Actually create and store the index
-        // For now, index creation is a no-op
+        if (self.connection.storage_engine.getIndex(create_idx.index_name) != null) {
+            if (create_idx.if_not_exists) return;
+            return error.IndexAlreadyExists;
+        }
+
+        // Register the index so it shows up in the schema; lookups still scan the table
+        try self.connection.storage_engine.createIndex(
+            create_idx.index_name,
+            create_idx.table_name,
+            create_idx.columns,
+            create_idx.unique,
+        );
     }
     
     /// Execute DROP INDEX
@@ -1406,36 +1342,101 @@ pub const VirtualMachine = struct {
 /// Result of query execution
 pub const ExecutionResult = struct {
     rows: std.array_list.Managed(storage.Row),
+    columns: std.array_list.Managed([]const u8), // Owned names of the values in each row
     affected_rows: u32,
     connection: *db.Connection, // Store connection to access consistent allocator
 
     pub fn deinit(self: *ExecutionResult) void {
         const allocator = self.connection.allocator;
-        // Cleaning up execution result
-        for (self.rows.items) |row| {
-            // Cleaning row values
-            for (row.values) |value| {
-                switch (value) {
-                    .Text => {
-                        // Freeing text value
-                    },
-                    .Integer => {
-                        // Freeing integer value
-                    },
-                    else => {
-                        // Freeing other value
-                    },
-                }
-                value.deinit(allocator);
-            }
-            // Freeing values array
-            allocator.free(row.values);
+        for (self.rows.items) |*row| {
+            row.deinit(allocator);
         }
         self.rows.deinit();
-        // Cleanup completed
+        for (self.columns.items) |name| {
+            allocator.free(name);
+        }
+        self.columns.deinit();
+    }
+
+    /// Append the column names of a table
+    fn appendTableColumns(self: *ExecutionResult, table: *const storage.Table) !void {
+        try self.columns.ensureUnusedCapacity(table.schema.columns.len);
+        for (table.schema.columns) |column| {
+            self.columns.appendAssumeCapacity(try self.connection.allocator.dupe(u8, column.name));
+        }
+    }
+
+    /// Replace the column names, taking ownership of `names` on success
+    fn setColumns(self: *ExecutionResult, names: []const []const u8) !void {
+        for (self.columns.items) |name| {
+            self.connection.allocator.free(name);
+        }
+        self.columns.clearRetainingCapacity();
+        try self.columns.appendSlice(names);
     }
 };
 
+/// Whether a condition's value holds: non-zero numbers, like SQLite
+fn isTruthy(value: storage.Value) bool {
+    return switch (value) {
+        .Integer, .BigInt, .Timestamp => |i| i != 0,
+        .SmallInt => |i| i != 0,
+        .Real => |r| r != 0,
+        .Boolean => |b| b,
+        .Text => |text| (std.fmt.parseFloat(f64, std.mem.trim(u8, text, " ")) catch 0) != 0,
+        else => false,
+    };
+}
+
+/// Whether a plan inserts, updates or deletes rows
+fn writesRows(plan: *const planner.ExecutionPlan) bool {
+    for (plan.steps) |step| {
+        switch (step) {
+            .Insert, .Update, .Delete => return true,
+            else => {},
+        }
+    }
+    return false;
+}
+
+/// Position of a column among the names of a row's values
+fn findColumn(columns: []const []const u8, name: []const u8) ?usize {
+    for (columns, 0..) |column, i| {
+        if (std.ascii.eqlIgnoreCase(column, name)) return i;
+    }
+    return null;
+}
+
+/// Position of a column in a table's schema
+fn findTableColumn(table: *const storage.Table, name: []const u8) ?usize {
+    for (table.schema.columns, 0..) |column, i| {
+        if (std.ascii.eqlIgnoreCase(column.name, name)) return i;
+    }
+    return null;
+}
+
+/// Column names of a table, borrowed from its schema; free only the slice
+fn tableColumnNames(allocator: std.mem.Allocator, table: *const storage.Table) ![]const []const u8 {
+    const names = try allocator.alloc([]const u8, table.schema.columns.len);
+    for (table.schema.columns, names) |column, *name| {
+        name.* = column.name;
+    }
+    return names;
+}
+
+/// Column name SQLite would give an unaliased aggregate, e.g. "COUNT(*)"
+fn aggregateLabel(allocator: std.mem.Allocator, aggregate_op: planner.AggregateOperation) ![]const u8 {
+    const function_name = switch (aggregate_op.function_type) {
+        .Count, .CountDistinct => "COUNT",
+        .Sum => "SUM",
+        .Avg => "AVG",
+        .Min => "MIN",
+        .Max => "MAX",
+        .GroupConcat => "GROUP_CONCAT",
+    };
+    return std.fmt.allocPrint(allocator, "{s}({s})", .{ function_name, aggregate_op.column orelse "*" });
+}
+
 /// VM errors
 const VmError = error{
     ColumnValueMismatch,
@@ -1447,6 +1448,8 @@ const VmError = error{
     UnresolvedParameter,
     TableNotFound,
     UnexpectedAggregate,
+    UniqueConstraintViolation,
+    RowNotFound,
     NotImplemented,
 };
 
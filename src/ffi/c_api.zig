@@ -9,32 +9,180 @@ const zqlite_connection_t = anyopaque;
 const zqlite_result_t = anyopaque;
 const zqlite_stmt_t = anyopaque;
 
-/// Error codes for C API
+/// Error codes for C API, matching include/zqlite.h
 pub const ZQLITE_OK = 0;
 pub const ZQLITE_ERROR = 1;
-pub const ZQLITE_BUSY = 2;
-pub const ZQLITE_LOCKED = 3;
-pub const ZQLITE_NOMEM = 4;
-pub const ZQLITE_READONLY = 5;
-pub const ZQLITE_MISUSE = 6;
-pub const ZQLITE_NOLFS = 7;
-pub const ZQLITE_AUTH = 8;
-pub const ZQLITE_FORMAT = 9;
-pub const ZQLITE_RANGE = 10;
-pub const ZQLITE_NOTADB = 11;
-
-/// Result structure for queries
+pub const ZQLITE_INTERNAL = 2;
+pub const ZQLITE_PERM = 3;
+pub const ZQLITE_ABORT = 4;
+pub const ZQLITE_BUSY = 5;
+pub const ZQLITE_LOCKED = 6;
+pub const ZQLITE_NOMEM = 7;
+pub const ZQLITE_READONLY = 8;
+pub const ZQLITE_INTERRUPT = 9;
+pub const ZQLITE_IOERR = 10;
+pub const ZQLITE_CONSTRAINT = 19;
+pub const ZQLITE_MISMATCH = 20;
+pub const ZQLITE_MISUSE = 21;
+pub const ZQLITE_NOLFS = 22;
+pub const ZQLITE_AUTH = 23;
+pub const ZQLITE_FORMAT = 24;
+pub const ZQLITE_RANGE = 25;
+pub const ZQLITE_NOTADB = 26;
+pub const ZQLITE_ROW = 100;
+pub const ZQLITE_DONE = 101;
+
+/// Result structure for queries; owns every string and blob it hands out
 const QueryResult = struct {
-    rows: [][]?[]const u8,
-    column_count: u32,
-    row_count: u32,
-    error_message: ?[]const u8,
+    column_names: [][:0]u8,
+    rows: [][]Cell,
+
+    /// Copy the rows and column names of an execution result
+    fn fromExecution(execution: *const zqlite.vm.ExecutionResult) !*QueryResult {
+        const result = try c_allocator.create(QueryResult);
+        result.* = .{ .column_names = &.{}, .rows = &.{} };
+        errdefer result.destroy();
+
+        result.column_names = try c_allocator.alloc([:0]u8, execution.columns.items.len);
+        for (result.column_names) |*name| name.* = &.{};
+        for (execution.columns.items, result.column_names) |column, *name| {
+            name.* = try c_allocator.dupeZ(u8, column);
+        }
+
+        result.rows = try c_allocator.alloc([]Cell, execution.rows.items.len);
+        for (result.rows) |*row| row.* = &.{};
+        for (execution.rows.items, result.rows) |source, *row| {
+            row.* = try c_allocator.alloc(Cell, source.values.len);
+            @memset(row.*, .null);
+            for (source.values, row.*) |value, *cell| {
+                cell.* = try Cell.fromValue(value);
+            }
+        }
+        return result;
+    }
+
+    fn cell(self: *const QueryResult, row: c_int, column: c_int) ?Cell {
+        if (row < 0 or column < 0) return null;
+        if (row >= self.rows.len) return null;
+        const cells = self.rows[@intCast(row)];
+        if (column >= cells.len) return null;
+        return cells[@intCast(column)];
+    }
+
+    fn destroy(self: *QueryResult) void {
+        for (self.column_names) |name| c_allocator.free(name);
+        c_allocator.free(self.column_names);
+        for (self.rows) |row| {
+            for (row) |cell_value| cell_value.deinit();
+            c_allocator.free(row);
+        }
+        c_allocator.free(self.rows);
+        c_allocator.destroy(self);
+    }
+};
+
+/// A result value in the shape C callers read it
+const Cell = union(enum) {
+    null,
+    integer: i64,
+    real: f64,
+    text: [:0]u8,
+    blob: []u8,
+
+    fn fromValue(value: zqlite.storage.Value) !Cell {
+        return switch (value) {
+            .Null, .Parameter, .FunctionCall => .null,
+            .Integer, .BigInt, .Timestamp, .Time, .Interval => |i| .{ .integer = i },
+            .SmallInt => |i| .{ .integer = i },
+            .Date => |d| .{ .integer = d },
+            .Boolean => |b| .{ .integer = @intFromBool(b) },
+            .TimestampTZ => |tstz| .{ .integer = tstz.timestamp },
+            .Real => |r| .{ .real = r },
+            .Text, .JSON => |text| .{ .text = try c_allocator.dupeZ(u8, text) },
+            .Blob => |blob| .{ .blob = try c_allocator.dupe(u8, blob) },
+            .UUID => |uuid| try textCell(try zqlite.ast.UUIDUtils.toString(uuid, c_allocator)),
+            .JSONB => |jsonb| try textCell(try jsonb.toString(c_allocator)),
+            .Array => |array| try textCell(try array.toString(c_allocator)),
+            // Not produced by SQL statements yet
+            .Numeric => .null,
+        };
+    }
+
+    /// Take ownership of rendered text
+    fn textCell(text: []u8) !Cell {
+        defer c_allocator.free(text);
+        return .{ .text = try c_allocator.dupeZ(u8, text) };
+    }
+
+    fn asText(self: Cell) ?[*:0]const u8 {
+        return switch (self) {
+            .text => |text| text.ptr,
+            else => null,
+        };
+    }
+
+    fn asInt(self: Cell) i64 {
+        return switch (self) {
+            .integer => |i| i,
+            .real => |r| std.math.lossyCast(i64, r),
+            else => 0,
+        };
+    }
+
+    fn asReal(self: Cell) f64 {
+        return switch (self) {
+            .real => |r| r,
+            .integer => |i| @floatFromInt(i),
+            else => 0,
+        };
+    }
+
+    /// Bytes of a blob or text cell
+    fn asBytes(self: Cell, size: ?*c_int) ?*const anyopaque {
+        if (size) |s| s.* = 0;
+        const data: []const u8 = switch (self) {
+            .blob => |blob| blob,
+            .text => |text| text,
+            else => return null,
+        };
+        if (size) |s| s.* = @intCast(data.len);
+        return data.ptr;
+    }
+
+    fn typeCode(self: Cell) c_int {
+        return switch (self) {
+            .null => ZQLITE_NULL,
+            .integer => ZQLITE_INTEGER,
+            .real => ZQLITE_FLOAT,
+            .text => ZQLITE_TEXT,
+            .blob => ZQLITE_BLOB,
+        };
+    }
+
+    fn deinit(self: Cell) void {
+        switch (self) {
+            .text => |text| c_allocator.free(text),
+            .blob => |blob| c_allocator.free(blob),
+            else => {},
+        }
+    }
 };
 
 // Global allocator for C API (TODO: make this configurable)
 var gpa = std.heap.GeneralPurposeAllocator(.{}){};
 const c_allocator = gpa.allocator();
 
+/// Map an engine error to a C API result code
+fn errorCode(err: anyerror) c_int {
+    return switch (err) {
+        error.Interrupted => ZQLITE_INTERRUPT,
+        error.UniqueConstraintViolation, error.MissingRequiredValue, error.CommitVetoed => ZQLITE_CONSTRAINT,
+        error.OutOfMemory => ZQLITE_NOMEM,
+        error.ParameterIndexOutOfBounds, error.InvalidParameterIndex => ZQLITE_RANGE,
+        else => ZQLITE_ERROR,
+    };
+}
+
 /// Open a database connection
 export fn zqlite_open(path: [*:0]const u8) ?*zqlite_connection_t {
     const path_slice = std.mem.span(path);
@@ -48,11 +196,19 @@ export fn zqlite_open(path: [*:0]const u8) ?*zqlite_connection_t {
 }
 
 /// Close a database connection
-export fn zqlite_close(conn: ?*zqlite_connection_t) void {
+export fn zqlite_close(conn: ?*zqlite_connection_t) c_int {
     if (conn) |c| {
         const connection: *zqlite.db.Connection = @ptrCast(@alignCast(c));
+        if (connection.user_functions) |resolver| {
+            const registry: *FunctionRegistry = @ptrCast(@alignCast(resolver.context));
+            registry.deinit();
+        }
+        if (existingHooks(connection)) |hooks| {
+            c_allocator.destroy(hooks);
+        }
         connection.close();
     }
+    return ZQLITE_OK;
 }
 
 /// Execute a SQL statement (no result expected)
@@ -62,95 +218,134 @@ export fn zqlite_execute(conn: ?*zqlite_connection_t, sql: [*:0]const u8) c_int
     const connection: *zqlite.db.Connection = @ptrCast(@alignCast(conn.?));
     const sql_slice = std.mem.span(sql);
 
-    connection.execute(sql_slice) catch return ZQLITE_ERROR;
+    connection.execute(sql_slice) catch |err| return errorCode(err);
     return ZQLITE_OK;
 }
 
 /// Execute a SQL query and return results
+///
+/// Returns null on failure; zqlite_errcode and zqlite_errmsg describe why.
 export fn zqlite_query(conn: ?*zqlite_connection_t, sql: [*:0]const u8) ?*zqlite_result_t {
     if (conn == null) return null;
 
     const connection: *zqlite.db.Connection = @ptrCast(@alignCast(conn.?));
     const sql_slice = std.mem.span(sql);
 
-    // For now, we'll create a simple result structure
-    // TODO: Implement proper result handling with row iteration
-    const result = c_allocator.create(QueryResult) catch return null;
-    result.* = QueryResult{
-        .rows = &[_][]?[]const u8{},
-        .column_count = 0,
-        .row_count = 0,
-        .error_message = null,
-    };
+    var execution = connection.run(sql_slice) catch return null;
+    defer execution.deinit();
 
-    // Execute the query (for now just validate syntax)
-    connection.execute(sql_slice) catch {
-        result.error_message = c_allocator.dupe(u8, "Query execution failed") catch null;
-        return @as(*zqlite_result_t, @ptrCast(result));
-    };
+    return materialize(connection, &execution);
+}
 
+/// Copy an execution result into a C result handle
+fn materialize(connection: *zqlite.db.Connection, execution: *const zqlite.vm.ExecutionResult) ?*zqlite_result_t {
+    const result = QueryResult.fromExecution(execution) catch |err| {
+        connection.last_error = err;
+        return null;
+    };
     return @as(*zqlite_result_t, @ptrCast(result));
 }
 
+fn queryResult(result: ?*zqlite_result_t) ?*QueryResult {
+    return @ptrCast(@alignCast(result orelse return null));
+}
+
 /// Get the number of rows in a result
 export fn zqlite_result_row_count(result: ?*zqlite_result_t) c_int {
-    if (result == null) return -1;
-
-    const query_result: *QueryResult = @ptrCast(@alignCast(result.?));
-    return @intCast(query_result.row_count);
+    const query_result = queryResult(result) orelse return -1;
+    return @intCast(query_result.rows.len);
 }
 
 /// Get the number of columns in a result
 export fn zqlite_result_column_count(result: ?*zqlite_result_t) c_int {
-    if (result == null) return -1;
-
-    const query_result: *QueryResult = @ptrCast(@alignCast(result.?));
-    return @intCast(query_result.column_count);
+    const query_result = queryResult(result) orelse return -1;
+    return @intCast(query_result.column_names.len);
 }
 
-/// Get a cell value from the result
-export fn zqlite_result_get_text(result: ?*zqlite_result_t, row: c_int, column: c_int) ?[*:0]const u8 {
-    if (result == null) return null;
+/// Get the name of a result column
+export fn zqlite_result_column_name(result: ?*zqlite_result_t, column: c_int) ?[*:0]const u8 {
+    const query_result = queryResult(result) orelse return null;
+    if (column < 0 or column >= query_result.column_names.len) return null;
+    return query_result.column_names[@intCast(column)].ptr;
+}
 
-    const query_result: *QueryResult = @ptrCast(@alignCast(result.?));
+/// Get the type code of a cell
+export fn zqlite_result_column_type(result: ?*zqlite_result_t, row: c_int, column: c_int) c_int {
+    const query_result = queryResult(result) orelse return ZQLITE_NULL;
+    const value = query_result.cell(row, column) orelse return ZQLITE_NULL;
+    return value.typeCode();
+}
 
-    if (row < 0 or column < 0) return null;
-    if (row >= query_result.row_count or column >= query_result.column_count) return null;
+/// Get a text cell; the string stays valid until the result is freed
+export fn zqlite_result_get_text(result: ?*zqlite_result_t, row: c_int, column: c_int) ?[*:0]const u8 {
+    const query_result = queryResult(result) orelse return null;
+    const value = query_result.cell(row, column) orelse return null;
+    return value.asText();
+}
 
-    const row_data = query_result.rows[@intCast(row)];
-    const cell_data = row_data[@intCast(column)];
+/// Get an integer cell, converting a real one
+export fn zqlite_result_get_int(result: ?*zqlite_result_t, row: c_int, column: c_int) i64 {
+    const query_result = queryResult(result) orelse return 0;
+    const value = query_result.cell(row, column) orelse return 0;
+    return value.asInt();
+}
 
-    if (cell_data) |data| {
-        // Convert to null-terminated string
-        const c_str = c_allocator.dupeZ(u8, data) catch return null;
-        return c_str.ptr;
-    }
+/// Get a real cell, converting an integer one
+export fn zqlite_result_get_real(result: ?*zqlite_result_t, row: c_int, column: c_int) f64 {
+    const query_result = queryResult(result) orelse return 0;
+    const value = query_result.cell(row, column) orelse return 0;
+    return value.asReal();
+}
 
-    return null;
+/// Get the bytes of a blob or text cell; they stay valid until the result is freed
+export fn zqlite_result_get_blob(result: ?*zqlite_result_t, row: c_int, column: c_int, size: ?*c_int) ?*const anyopaque {
+    if (size) |s| s.* = 0;
+    const query_result = queryResult(result) orelse return null;
+    const value = query_result.cell(row, column) orelse return null;
+    return value.asBytes(size);
 }
 
 /// Free a result
 export fn zqlite_result_free(result: ?*zqlite_result_t) void {
-    if (result) |r| {
-        const query_result: *QueryResult = @ptrCast(@alignCast(r));
-
-        // Free rows and columns
-        for (query_result.rows) |row| {
-            for (row) |cell| {
-                if (cell) |data| {
-                    c_allocator.free(data);
-                }
-            }
-            c_allocator.free(row);
-        }
-        c_allocator.free(query_result.rows);
+    if (queryResult(result)) |query_result| query_result.destroy();
+}
 
-        if (query_result.error_message) |msg| {
-            c_allocator.free(msg);
-        }
+/// A prepared statement and the rows zqlite_step walks through
+const Statement = struct {
+    prepared: *zqlite.db.PreparedStatement,
+    connection: *zqlite.db.Connection,
+    /// Rows of the current execution, until zqlite_step reports DONE
+    result: ?*QueryResult = null,
+    /// Rows zqlite_step has returned so far
+    cursor: usize = 0,
+
+    /// Forget the current execution, so the next step runs the statement again
+    fn clearResult(self: *Statement) void {
+        if (self.result) |result| result.destroy();
+        self.result = null;
+        self.cursor = 0;
+    }
 
-        c_allocator.destroy(query_result);
+    /// A cell of the row zqlite_step last returned
+    fn cell(self: *const Statement, column: c_int) ?Cell {
+        const result = self.result orelse return null;
+        if (self.cursor == 0) return null;
+        return result.cell(@intCast(self.cursor - 1), column);
     }
+};
+
+fn statementHandle(stmt: ?*zqlite_stmt_t) ?*Statement {
+    return @ptrCast(@alignCast(stmt orelse return null));
+}
+
+/// Bind a value, which starts a new execution of the statement
+fn bindValue(stmt: ?*zqlite_stmt_t, index: c_int, value: zqlite.storage.Value) c_int {
+    const statement = statementHandle(stmt) orelse return ZQLITE_MISUSE;
+    if (index < 0) return ZQLITE_RANGE;
+
+    statement.clearResult();
+    statement.prepared.bindParameter(@intCast(index), value) catch |err| return errorCode(err);
+    return ZQLITE_OK;
 }
 
 /// Prepare a SQL statement
@@ -160,78 +355,163 @@ export fn zqlite_prepare(conn: ?*zqlite_connection_t, sql: [*:0]const u8) ?*zqli
     const connection: *zqlite.db.Connection = @ptrCast(@alignCast(conn.?));
     const sql_slice = std.mem.span(sql);
 
-    const stmt = connection.prepare(sql_slice) catch return null;
-    return @as(*zqlite_stmt_t, @ptrCast(stmt));
+    const prepared = connection.prepare(sql_slice) catch |err| {
+        connection.last_error = err;
+        return null;
+    };
+    const statement = c_allocator.create(Statement) catch {
+        prepared.deinit();
+        connection.last_error = error.OutOfMemory;
+        return null;
+    };
+    statement.* = .{ .prepared = prepared, .connection = connection };
+    return @as(*zqlite_stmt_t, @ptrCast(statement));
 }
 
 /// Bind an integer parameter
 export fn zqlite_bind_int(stmt: ?*zqlite_stmt_t, index: c_int, value: i64) c_int {
-    if (stmt == null) return ZQLITE_MISUSE;
-
-    const statement: *zqlite.db.PreparedStatement = @ptrCast(@alignCast(stmt.?));
-    const storage_value = zqlite.storage.Value{ .Integer = value };
-
-    statement.bindParameter(@intCast(index), storage_value) catch return ZQLITE_ERROR;
-    return ZQLITE_OK;
+    return bindValue(stmt, index, .{ .Integer = value });
 }
 
-/// Bind a text parameter
+/// Bind a text parameter (the text is copied)
 export fn zqlite_bind_text(stmt: ?*zqlite_stmt_t, index: c_int, value: [*:0]const u8) c_int {
-    if (stmt == null) return ZQLITE_MISUSE;
-
-    const statement: *zqlite.db.PreparedStatement = @ptrCast(@alignCast(stmt.?));
-    const text_value = std.mem.span(value);
-    const owned_text = c_allocator.dupe(u8, text_value) catch return ZQLITE_NOMEM;
-    const storage_value = zqlite.storage.Value{ .Text = owned_text };
-
-    statement.bindParameter(@intCast(index), storage_value) catch return ZQLITE_ERROR;
-    return ZQLITE_OK;
+    return bindValue(stmt, index, .{ .Text = std.mem.span(value) });
 }
 
 /// Bind a real (float) parameter
 export fn zqlite_bind_real(stmt: ?*zqlite_stmt_t, index: c_int, value: f64) c_int {
-    if (stmt == null) return ZQLITE_MISUSE;
-
-    const statement: *zqlite.db.PreparedStatement = @ptrCast(@alignCast(stmt.?));
-    const storage_value = zqlite.storage.Value{ .Real = value };
-
-    statement.bindParameter(@intCast(index), storage_value) catch return ZQLITE_ERROR;
-    return ZQLITE_OK;
+    return bindValue(stmt, index, .{ .Real = value });
 }
 
 /// Bind a null parameter
 export fn zqlite_bind_null(stmt: ?*zqlite_stmt_t, index: c_int) c_int {
-    if (stmt == null) return ZQLITE_MISUSE;
+    return bindValue(stmt, index, .Null);
+}
 
-    const statement: *zqlite.db.PreparedStatement = @ptrCast(@alignCast(stmt.?));
-    const storage_value = zqlite.storage.Value.Null;
+/// Bind a blob parameter (the data is copied)
+export fn zqlite_bind_blob(stmt: ?*zqlite_stmt_t, index: c_int, data: ?[*]const u8, size: c_int) c_int {
+    if (size < 0) return ZQLITE_MISUSE;
+    if (data == null and size > 0) return ZQLITE_MISUSE;
 
-    statement.bindParameter(@intCast(index), storage_value) catch return ZQLITE_ERROR;
-    return ZQLITE_OK;
+    const bytes: []const u8 = if (data) |d| d[0..@intCast(size)] else &[_]u8{};
+    return bindValue(stmt, index, .{ .Blob = bytes });
+}
+
+/// Bind a zero-filled blob of the given size, to be filled later with zqlite_blob_write
+export fn zqlite_bind_zeroblob(stmt: ?*zqlite_stmt_t, index: c_int, size: c_int) c_int {
+    if (size < 0) return ZQLITE_MISUSE;
+
+    const zeros = c_allocator.alloc(u8, @intCast(size)) catch return ZQLITE_NOMEM;
+    defer c_allocator.free(zeros);
+    @memset(zeros, 0);
+    return bindValue(stmt, index, .{ .Blob = zeros });
 }
 
-/// Execute a prepared statement
+/// Advance a prepared statement
+///
+/// The first step runs the statement. Returns ZQLITE_ROW while a row is
+/// available through the zqlite_column_* functions, then ZQLITE_DONE; a
+/// step after ZQLITE_DONE runs the statement again.
 export fn zqlite_step(stmt: ?*zqlite_stmt_t) c_int {
-    if (stmt == null) return ZQLITE_MISUSE;
+    const statement = statementHandle(stmt) orelse return ZQLITE_MISUSE;
 
-    // TODO: Implement proper step execution
-    return ZQLITE_OK;
+    if (statement.result == null) {
+        var execution = statement.prepared.execute(statement.connection) catch |err| return errorCode(err);
+        defer execution.deinit();
+
+        statement.result = QueryResult.fromExecution(&execution) catch |err| {
+            statement.connection.last_error = err;
+            return errorCode(err);
+        };
+    }
+
+    if (statement.cursor < statement.result.?.rows.len) {
+        statement.cursor += 1;
+        return ZQLITE_ROW;
+    }
+    statement.clearResult();
+    return ZQLITE_DONE;
 }
 
-/// Reset a prepared statement
-export fn zqlite_reset(stmt: ?*zqlite_stmt_t) c_int {
-    if (stmt == null) return ZQLITE_MISUSE;
+/// Number of columns in the rows of the current execution
+export fn zqlite_column_count(stmt: ?*zqlite_stmt_t) c_int {
+    const statement = statementHandle(stmt) orelse return 0;
+    const result = statement.result orelse return 0;
+    return @intCast(result.column_names.len);
+}
+
+/// Name of a column in the rows of the current execution
+export fn zqlite_column_name(stmt: ?*zqlite_stmt_t, column: c_int) ?[*:0]const u8 {
+    const statement = statementHandle(stmt) orelse return null;
+    const result = statement.result orelse return null;
+    if (column < 0 or column >= result.column_names.len) return null;
+    return result.column_names[@intCast(column)].ptr;
+}
+
+/// Type code of a column in the current row
+export fn zqlite_column_type(stmt: ?*zqlite_stmt_t, column: c_int) c_int {
+    const statement = statementHandle(stmt) orelse return ZQLITE_NULL;
+    const value = statement.cell(column) orelse return ZQLITE_NULL;
+    return value.typeCode();
+}
+
+/// Text of a column in the current row, valid until the next step
+export fn zqlite_column_text(stmt: ?*zqlite_stmt_t, column: c_int) ?[*:0]const u8 {
+    const statement = statementHandle(stmt) orelse return null;
+    const value = statement.cell(column) orelse return null;
+    return value.asText();
+}
+
+/// Integer value of a column in the current row
+export fn zqlite_column_int(stmt: ?*zqlite_stmt_t, column: c_int) i64 {
+    const statement = statementHandle(stmt) orelse return 0;
+    const value = statement.cell(column) orelse return 0;
+    return value.asInt();
+}
+
+/// Real value of a column in the current row
+export fn zqlite_column_real(stmt: ?*zqlite_stmt_t, column: c_int) f64 {
+    const statement = statementHandle(stmt) orelse return 0;
+    const value = statement.cell(column) orelse return 0;
+    return value.asReal();
+}
+
+/// Bytes of a blob or text column in the current row, valid until the next step
+export fn zqlite_column_blob(stmt: ?*zqlite_stmt_t, column: c_int, size: ?*c_int) ?*const anyopaque {
+    if (size) |s| s.* = 0;
+    const statement = statementHandle(stmt) orelse return null;
+    const value = statement.cell(column) orelse return null;
+    return value.asBytes(size);
+}
+
+/// Execute a prepared statement with its bound parameters and return results
+export fn zqlite_query_prepared(conn: ?*zqlite_connection_t, stmt: ?*zqlite_stmt_t) ?*zqlite_result_t {
+    if (conn == null) return null;
 
-    const statement: *zqlite.db.PreparedStatement = @ptrCast(@alignCast(stmt.?));
-    statement.reset();
+    const connection: *zqlite.db.Connection = @ptrCast(@alignCast(conn.?));
+    const statement = statementHandle(stmt) orelse return null;
+    statement.clearResult();
+
+    var execution = statement.prepared.execute(connection) catch return null;
+    defer execution.deinit();
+
+    return materialize(connection, &execution);
+}
+
+/// Reset a prepared statement, clearing its bindings
+export fn zqlite_reset(stmt: ?*zqlite_stmt_t) c_int {
+    const statement = statementHandle(stmt) orelse return ZQLITE_MISUSE;
+    statement.clearResult();
+    statement.prepared.reset();
     return ZQLITE_OK;
 }
 
 /// Finalize a prepared statement
 export fn zqlite_finalize(stmt: ?*zqlite_stmt_t) c_int {
-    if (stmt) |s| {
-        const statement: *zqlite.db.PreparedStatement = @ptrCast(@alignCast(s));
-        statement.deinit();
+    if (statementHandle(stmt)) |statement| {
+        statement.clearResult();
+        statement.prepared.deinit();
+        c_allocator.destroy(statement);
     }
     return ZQLITE_OK;
 }
@@ -250,7 +530,7 @@ export fn zqlite_commit_transaction(conn: ?*zqlite_connection_t) c_int {
     if (conn == null) return ZQLITE_MISUSE;
 
     const connection: *zqlite.db.Connection = @ptrCast(@alignCast(conn.?));
-    connection.commit() catch return ZQLITE_ERROR;
+    connection.commit() catch |err| return errorCode(err);
     return ZQLITE_OK;
 }
 
@@ -263,10 +543,917 @@ export fn zqlite_rollback_transaction(conn: ?*zqlite_connection_t) c_int {
     return ZQLITE_OK;
 }
 
+// ========== User-defined functions ==========
+
+const zqlite_context_t = anyopaque;
+const zqlite_value_t = anyopaque;
+
+const FuncCallback = *const fn (ctx: ?*zqlite_context_t, argc: c_int, argv: [*]?*zqlite_value_t) callconv(.c) void;
+const FinalCallback = *const fn (ctx: ?*zqlite_context_t) callconv(.c) void;
+const DestroyCallback = *const fn (user_data: ?*anyopaque) callconv(.c) void;
+
+/// Column/value type codes shared with zqlite.h
+const ZQLITE_INTEGER = 1;
+const ZQLITE_FLOAT = 2;
+const ZQLITE_TEXT = 3;
+const ZQLITE_BLOB = 4;
+const ZQLITE_NULL = 5;
+
+const UserFunction = struct {
+    n_args: c_int,
+    flags: c_int,
+    user_data: ?*anyopaque,
+    x_func: ?FuncCallback,
+    x_step: ?FuncCallback,
+    x_final: ?FinalCallback,
+    x_destroy: ?DestroyCallback,
+
+    fn destroy(self: UserFunction) void {
+        if (self.x_destroy) |destroy_fn| destroy_fn(self.user_data);
+    }
+};
+
+/// State handed to a user function while it runs
+const FunctionContext = struct {
+    allocator: std.mem.Allocator,
+    user_data: ?*anyopaque,
+    result: zqlite.storage.Value = .Null,
+    error_message: ?[]u8 = null,
+    aggregate_state: ?[]u8 = null,
+
+    fn deinit(self: *FunctionContext) void {
+        if (self.error_message) |msg| c_allocator.free(msg);
+        if (self.aggregate_state) |state| c_allocator.free(state);
+    }
+};
+
+/// Per-connection registry of functions created through the C API
+const FunctionRegistry = struct {
+    functions: std.StringHashMap(UserFunction),
+
+    fn create() !*FunctionRegistry {
+        const registry = try c_allocator.create(FunctionRegistry);
+        registry.functions = std.StringHashMap(UserFunction).init(c_allocator);
+        return registry;
+    }
+
+    fn resolve(context: *anyopaque, allocator: std.mem.Allocator, name: []const u8, args: []const zqlite.storage.Value) anyerror!?zqlite.storage.Value {
+        const registry: *FunctionRegistry = @ptrCast(@alignCast(context));
+        const function = registry.functions.get(name) orelse return null;
+
+        if (function.n_args >= 0 and function.n_args != args.len) return error.InvalidArgumentCount;
+        const x_func = function.x_func orelse return error.MisuseOfAggregateFunction;
+
+        var ctx = FunctionContext{ .allocator = allocator, .user_data = function.user_data };
+        defer ctx.deinit();
+
+        const argv = try allocator.alloc(?*zqlite_value_t, args.len);
+        defer allocator.free(argv);
+        for (args, 0..) |*arg, i| {
+            argv[i] = @ptrCast(@constCast(arg));
+        }
+
+        x_func(@ptrCast(&ctx), @intCast(args.len), argv.ptr);
+
+        if (ctx.error_message) |msg| {
+            std.log.warn("user function '{s}' failed: {s}", .{ name, msg });
+            ctx.result.deinit(allocator);
+            return error.UserFunctionFailed;
+        }
+        return ctx.result;
+    }
+
+    fn isAggregate(context: *anyopaque, name: []const u8) bool {
+        const registry: *FunctionRegistry = @ptrCast(@alignCast(context));
+        const function = registry.functions.get(name) orelse return false;
+        return function.x_step != null;
+    }
+
+    /// Run x_step for each row's arguments, then x_final, sharing one context
+    fn aggregate(context: *anyopaque, allocator: std.mem.Allocator, name: []const u8, rows: []const []const zqlite.storage.Value) anyerror!?zqlite.storage.Value {
+        const registry: *FunctionRegistry = @ptrCast(@alignCast(context));
+        const function = registry.functions.get(name) orelse return null;
+        const x_step = function.x_step orelse return error.NotAnAggregateFunction;
+        const x_final = function.x_final.?; // Registered together with x_step
+
+        // Every row passes the same arguments
+        const arg_count = if (rows.len > 0) rows[0].len else 0;
+        if (rows.len > 0 and function.n_args >= 0 and function.n_args != arg_count) return error.InvalidArgumentCount;
+
+        var ctx = FunctionContext{ .allocator = allocator, .user_data = function.user_data };
+        defer ctx.deinit();
+
+        const argv = try allocator.alloc(?*zqlite_value_t, arg_count);
+        defer allocator.free(argv);
+        for (rows) |args| {
+            for (args, argv) |*arg, *arg_ptr| {
+                arg_ptr.* = @ptrCast(@constCast(arg));
+            }
+            x_step(@ptrCast(&ctx), @intCast(arg_count), argv.ptr);
+            if (ctx.error_message != null) break;
+        }
+
+        // x_final runs even after a failed step so the function can free its state
+        const step_error = ctx.error_message;
+        ctx.error_message = null;
+        x_final(@ptrCast(&ctx));
+        if (step_error) |msg| {
+            if (ctx.error_message) |final_error| c_allocator.free(final_error);
+            ctx.error_message = msg;
+        }
+
+        if (ctx.error_message) |msg| {
+            std.log.warn("user function '{s}' failed: {s}", .{ name, msg });
+            ctx.result.deinit(allocator);
+            return error.UserFunctionFailed;
+        }
+        return ctx.result;
+    }
+
+    fn deinit(self: *FunctionRegistry) void {
+        var it = self.functions.iterator();
+        while (it.next()) |entry| {
+            entry.value_ptr.destroy();
+            c_allocator.free(entry.key_ptr.*);
+        }
+        self.functions.deinit();
+        c_allocator.destroy(self);
+    }
+};
+
+/// Register (or, with all callbacks null, remove) a user-defined function
+export fn zqlite_create_function(
+    conn: ?*zqlite_connection_t,
+    name: [*:0]const u8,
+    n_args: c_int,
+    flags: c_int,
+    user_data: ?*anyopaque,
+    x_func: ?FuncCallback,
+    x_step: ?FuncCallback,
+    x_final: ?FinalCallback,
+    x_destroy: ?DestroyCallback,
+) c_int {
+    // Like sqlite3_create_function_v2, user data is released on every failure path
+    const pending = UserFunction{
+        .n_args = n_args,
+        .flags = flags,
+        .user_data = user_data,
+        .x_func = x_func,
+        .x_step = x_step,
+        .x_final = x_final,
+        .x_destroy = x_destroy,
+    };
+
+    if (conn == null) {
+        pending.destroy();
+        return ZQLITE_MISUSE;
+    }
+    // Scalar functions need x_func; aggregates need both x_step and x_final
+    if ((x_func != null and (x_step != null or x_final != null)) or (x_step == null) != (x_final == null)) {
+        pending.destroy();
+        return ZQLITE_MISUSE;
+    }
+
+    const connection: *zqlite.db.Connection = @ptrCast(@alignCast(conn.?));
+
+    const registry: *FunctionRegistry = if (connection.user_functions) |resolver|
+        @ptrCast(@alignCast(resolver.context))
+    else blk: {
+        const created = FunctionRegistry.create() catch {
+            pending.destroy();
+            return ZQLITE_NOMEM;
+        };
+        connection.user_functions = .{
+            .context = created,
+            .call = FunctionRegistry.resolve,
+            .is_aggregate = FunctionRegistry.isAggregate,
+            .aggregate = FunctionRegistry.aggregate,
+        };
+        break :blk created;
+    };
+
+    const key = std.ascii.allocLowerString(c_allocator, std.mem.span(name)) catch {
+        pending.destroy();
+        return ZQLITE_NOMEM;
+    };
+
+    if (registry.functions.fetchRemove(key)) |previous| {
+        previous.value.destroy();
+        c_allocator.free(previous.key);
+    }
+
+    if (x_func == null and x_step == null) {
+        c_allocator.free(key);
+        return ZQLITE_OK;
+    }
+
+    registry.functions.put(key, pending) catch {
+        pending.destroy();
+        c_allocator.free(key);
+        return ZQLITE_NOMEM;
+    };
+    return ZQLITE_OK;
+}
+
+/// Get the type code of a function argument
+export fn zqlite_value_type(value: ?*zqlite_value_t) c_int {
+    if (value == null) return ZQLITE_NULL;
+
+    const v: *const zqlite.storage.Value = @ptrCast(@alignCast(value.?));
+    return switch (v.*) {
+        .Integer, .SmallInt, .Boolean => ZQLITE_INTEGER,
+        .Real => ZQLITE_FLOAT,
+        .Blob => ZQLITE_BLOB,
+        .Null => ZQLITE_NULL,
+        else => ZQLITE_TEXT,
+    };
+}
+
+/// Get a function argument as an integer
+export fn zqlite_value_int(value: ?*zqlite_value_t) i64 {
+    if (value == null) return 0;
+
+    const v: *const zqlite.storage.Value = @ptrCast(@alignCast(value.?));
+    return switch (v.*) {
+        .Integer => |i| i,
+        .SmallInt => |i| i,
+        .Boolean => |b| @intFromBool(b),
+        .Real => |r| @intFromFloat(r),
+        else => 0,
+    };
+}
+
+/// Get a function argument as a float
+export fn zqlite_value_real(value: ?*zqlite_value_t) f64 {
+    if (value == null) return 0;
+
+    const v: *const zqlite.storage.Value = @ptrCast(@alignCast(value.?));
+    return switch (v.*) {
+        .Real => |r| r,
+        .Integer => |i| @floatFromInt(i),
+        else => 0,
+    };
+}
+
+/// Get a function argument as text (not null-terminated; length in *size)
+export fn zqlite_value_text(value: ?*zqlite_value_t, size: ?*c_int) ?[*]const u8 {
+    if (value == null) return null;
+
+    const v: *const zqlite.storage.Value = @ptrCast(@alignCast(value.?));
+    const text = switch (v.*) {
+        .Text => |t| t,
+        .JSON => |j| j,
+        else => return null,
+    };
+    if (size) |s| s.* = @intCast(text.len);
+    return text.ptr;
+}
+
+/// Get a function argument as a blob
+export fn zqlite_value_blob(value: ?*zqlite_value_t, size: ?*c_int) ?*const anyopaque {
+    if (value == null) return null;
+
+    const v: *const zqlite.storage.Value = @ptrCast(@alignCast(value.?));
+    const blob = switch (v.*) {
+        .Blob => |b| b,
+        .Text => |t| t,
+        else => return null,
+    };
+    if (size) |s| s.* = @intCast(blob.len);
+    return blob.ptr;
+}
+
+/// Get the user data pointer passed to zqlite_create_function
+export fn zqlite_user_data(ctx: ?*zqlite_context_t) ?*anyopaque {
+    if (ctx == null) return null;
+
+    const context: *FunctionContext = @ptrCast(@alignCast(ctx.?));
+    return context.user_data;
+}
+
+/// Get (allocating zeroed on first use) per-group aggregate state
+export fn zqlite_aggregate_context(ctx: ?*zqlite_context_t, n_bytes: c_int) ?*anyopaque {
+    if (ctx == null) return null;
+
+    const context: *FunctionContext = @ptrCast(@alignCast(ctx.?));
+    if (context.aggregate_state == null) {
+        // A non-positive size only looks up existing state
+        if (n_bytes <= 0) return null;
+        const state = c_allocator.alloc(u8, @intCast(n_bytes)) catch return null;
+        @memset(state, 0);
+        context.aggregate_state = state;
+    }
+    return context.aggregate_state.?.ptr;
+}
+
+fn setFunctionResult(ctx: ?*zqlite_context_t, value: zqlite.storage.Value) void {
+    if (ctx == null) return;
+
+    const context: *FunctionContext = @ptrCast(@alignCast(ctx.?));
+    context.result.deinit(context.allocator);
+    context.result = value;
+}
+
+/// Return an integer from a user function
+export fn zqlite_context_result_int(ctx: ?*zqlite_context_t, value: i64) void {
+    setFunctionResult(ctx, .{ .Integer = value });
+}
+
+/// Return a float from a user function
+export fn zqlite_context_result_real(ctx: ?*zqlite_context_t, value: f64) void {
+    setFunctionResult(ctx, .{ .Real = value });
+}
+
+/// Return text from a user function (the bytes are copied)
+export fn zqlite_context_result_text(ctx: ?*zqlite_context_t, value: [*]const u8, size: c_int) void {
+    if (ctx == null or size < 0) return;
+
+    const context: *FunctionContext = @ptrCast(@alignCast(ctx.?));
+    const owned = context.allocator.dupe(u8, value[0..@intCast(size)]) catch {
+        zqlite_context_result_error(ctx, "out of memory", -1);
+        return;
+    };
+    setFunctionResult(ctx, .{ .Text = owned });
+}
+
+/// Return a blob from a user function (the bytes are copied)
+export fn zqlite_context_result_blob(ctx: ?*zqlite_context_t, value: ?*const anyopaque, size: c_int) void {
+    if (ctx == null or size < 0) return;
+
+    const context: *FunctionContext = @ptrCast(@alignCast(ctx.?));
+    const bytes: [*]const u8 = if (value) |v| @ptrCast(v) else &[_]u8{};
+    const owned = context.allocator.dupe(u8, bytes[0..@intCast(size)]) catch {
+        zqlite_context_result_error(ctx, "out of memory", -1);
+        return;
+    };
+    setFunctionResult(ctx, .{ .Blob = owned });
+}
+
+/// Return NULL from a user function
+export fn zqlite_context_result_null(ctx: ?*zqlite_context_t) void {
+    setFunctionResult(ctx, .Null);
+}
+
+/// Make the user function raise an SQL error (size < 0 means null-terminated)
+export fn zqlite_context_result_error(ctx: ?*zqlite_context_t, message: [*]const u8, size: c_int) void {
+    if (ctx == null) return;
+
+    const context: *FunctionContext = @ptrCast(@alignCast(ctx.?));
+    const len: usize = if (size < 0) std.mem.len(@as([*:0]const u8, @ptrCast(message))) else @intCast(size);
+    if (context.error_message) |previous| c_allocator.free(previous);
+    context.error_message = c_allocator.dupe(u8, message[0..len]) catch null;
+}
+
+// ========== Change notification hooks ==========
+
+pub const ZQLITE_DELETE = 9;
+pub const ZQLITE_INSERT = 18;
+pub const ZQLITE_UPDATE = 23;
+
+const UpdateCallback = *const fn (user_data: ?*anyopaque, op: c_int, db_name: [*:0]const u8, table: [*:0]const u8, rowid: i64) callconv(.c) void;
+const CommitCallback = *const fn (user_data: ?*anyopaque) callconv(.c) c_int;
+const AfterCommitCallback = *const fn (user_data: ?*anyopaque) callconv(.c) void;
+const RollbackCallback = *const fn (user_data: ?*anyopaque) callconv(.c) void;
+
+/// Hooks installed on a connection, stored in `Connection.change_hooks`
+const HookState = struct {
+    update: ?UpdateCallback = null,
+    update_data: ?*anyopaque = null,
+    commit: ?CommitCallback = null,
+    commit_data: ?*anyopaque = null,
+    after_commit: ?AfterCommitCallback = null,
+    after_commit_data: ?*anyopaque = null,
+    rollback: ?RollbackCallback = null,
+    rollback_data: ?*anyopaque = null,
+};
+
+fn existingHooks(connection: *zqlite.db.Connection) ?*HookState {
+    const hooks = connection.change_hooks orelse return null;
+    return @ptrCast(@alignCast(hooks.context));
+}
+
+fn hookState(connection: *zqlite.db.Connection) ?*HookState {
+    if (existingHooks(connection)) |hooks| return hooks;
+
+    const hooks = c_allocator.create(HookState) catch return null;
+    hooks.* = .{};
+    connection.change_hooks = .{
+        .context = hooks,
+        .row_changed = rowChanged,
+        .committing = committing,
+        .committed = committed,
+        .rolled_back = rolledBack,
+    };
+    return hooks;
+}
+
+fn rowChanged(context: *anyopaque, change: zqlite.db.RowChange, table: []const u8, row_id: zqlite.storage.RowId) void {
+    const hooks: *HookState = @ptrCast(@alignCast(context));
+    const callback = hooks.update orelse return;
+
+    const op: c_int = switch (change) {
+        .insert => ZQLITE_INSERT,
+        .update => ZQLITE_UPDATE,
+        .delete => ZQLITE_DELETE,
+    };
+    const table_name = c_allocator.dupeZ(u8, table) catch return;
+    defer c_allocator.free(table_name);
+    callback(hooks.update_data, op, "main", table_name.ptr, @intCast(row_id));
+}
+
+fn committing(context: *anyopaque) bool {
+    const hooks: *HookState = @ptrCast(@alignCast(context));
+    const callback = hooks.commit orelse return true;
+    return callback(hooks.commit_data) == 0;
+}
+
+fn committed(context: *anyopaque) void {
+    const hooks: *HookState = @ptrCast(@alignCast(context));
+    if (hooks.after_commit) |callback| callback(hooks.after_commit_data);
+}
+
+fn rolledBack(context: *anyopaque) void {
+    const hooks: *HookState = @ptrCast(@alignCast(context));
+    if (hooks.rollback) |callback| callback(hooks.rollback_data);
+}
+
+/// Install the update hook (null removes it); returns the previous user data
+export fn zqlite_update_hook(conn: ?*zqlite_connection_t, callback: ?UpdateCallback, user_data: ?*anyopaque) ?*anyopaque {
+    if (conn == null) return null;
+
+    const connection: *zqlite.db.Connection = @ptrCast(@alignCast(conn.?));
+    const hooks = hookState(connection) orelse return null;
+    const previous = hooks.update_data;
+    hooks.update = callback;
+    hooks.update_data = if (callback != null) user_data else null;
+    return previous;
+}
+
+/// Install the commit hook (null removes it); returns the previous user data
+export fn zqlite_commit_hook(conn: ?*zqlite_connection_t, callback: ?CommitCallback, user_data: ?*anyopaque) ?*anyopaque {
+    if (conn == null) return null;
+
+    const connection: *zqlite.db.Connection = @ptrCast(@alignCast(conn.?));
+    const hooks = hookState(connection) orelse return null;
+    const previous = hooks.commit_data;
+    hooks.commit = callback;
+    hooks.commit_data = if (callback != null) user_data else null;
+    return previous;
+}
+
+/// Install the hook run after each commit is durable (null removes it); returns
+/// the previous user data
+export fn zqlite_after_commit_hook(conn: ?*zqlite_connection_t, callback: ?AfterCommitCallback, user_data: ?*anyopaque) ?*anyopaque {
+    if (conn == null) return null;
+
+    const connection: *zqlite.db.Connection = @ptrCast(@alignCast(conn.?));
+    const hooks = hookState(connection) orelse return null;
+    const previous = hooks.after_commit_data;
+    hooks.after_commit = callback;
+    hooks.after_commit_data = if (callback != null) user_data else null;
+    return previous;
+}
+
+/// Install the rollback hook (null removes it); returns the previous user data
+export fn zqlite_rollback_hook(conn: ?*zqlite_connection_t, callback: ?RollbackCallback, user_data: ?*anyopaque) ?*anyopaque {
+    if (conn == null) return null;
+
+    const connection: *zqlite.db.Connection = @ptrCast(@alignCast(conn.?));
+    const hooks = hookState(connection) orelse return null;
+    const previous = hooks.rollback_data;
+    hooks.rollback = callback;
+    hooks.rollback_data = if (callback != null) user_data else null;
+    return previous;
+}
+
+/// Check whether the connection is in autocommit mode (no open transaction)
+export fn zqlite_get_autocommit(conn: ?*zqlite_connection_t) c_int {
+    if (conn == null) return 1;
+
+    const connection: *zqlite.db.Connection = @ptrCast(@alignCast(conn.?));
+    return if (connection.isAutocommit()) 1 else 0;
+}
+
+// ========== Incremental blob I/O ==========
+
+const zqlite_blob_t = anyopaque;
+
+/// Open handle on one blob value; reads and writes go straight to the stored row
+const BlobHandle = struct {
+    connection: *zqlite.db.Connection,
+    table_name: []u8,
+    row_id: u64,
+    column_index: usize,
+    size: usize,
+    writable: bool,
+
+    fn deinit(self: *BlobHandle) void {
+        c_allocator.free(self.table_name);
+        c_allocator.destroy(self);
+    }
+
+    fn table(self: *BlobHandle) !*zqlite.storage.Table {
+        return self.connection.storage_engine.getTable(self.table_name) orelse error.TableNotFound;
+    }
+
+    /// Save the bytes a write is about to overwrite so a rollback can restore them
+    fn recordUndo(self: *BlobHandle, storage_table: *zqlite.storage.Table, offset: usize, len: usize) !void {
+        try self.connection.recordBlobUndo(storage_table, self.row_id, self.column_index, offset, len);
+    }
+};
+
+/// Open a blob for incremental I/O (flags: 0 read-only, 1 read-write)
+export fn zqlite_blob_open(
+    conn: ?*zqlite_connection_t,
+    db_name: ?[*:0]const u8,
+    table: [*:0]const u8,
+    column: [*:0]const u8,
+    rowid: i64,
+    flags: c_int,
+    blob_out: ?*?*zqlite_blob_t,
+) c_int {
+    const out = blob_out orelse return ZQLITE_MISUSE;
+    out.* = null;
+    if (conn == null or rowid < 0) return ZQLITE_MISUSE;
+    if (db_name) |name| {
+        if (!std.mem.eql(u8, std.mem.span(name), "main")) return ZQLITE_ERROR;
+    }
+
+    const connection: *zqlite.db.Connection = @ptrCast(@alignCast(conn.?));
+    const table_name = std.mem.span(table);
+    const column_name = std.mem.span(column);
+
+    const storage_table = connection.storage_engine.getTable(table_name) orelse return ZQLITE_ERROR;
+    const column_index = for (storage_table.schema.columns, 0..) |col, i| {
+        if (std.ascii.eqlIgnoreCase(col.name, column_name)) break i;
+    } else return ZQLITE_ERROR;
+
+    // Also checks that the row exists and holds text or a blob
+    const size = storage_table.blobLength(@intCast(rowid), column_index) catch return ZQLITE_ERROR;
+
+    const handle = c_allocator.create(BlobHandle) catch return ZQLITE_NOMEM;
+    const owned_table = c_allocator.dupe(u8, table_name) catch {
+        c_allocator.destroy(handle);
+        return ZQLITE_NOMEM;
+    };
+    handle.* = .{
+        .connection = connection,
+        .table_name = owned_table,
+        .row_id = @intCast(rowid),
+        .column_index = column_index,
+        .size = size,
+        .writable = flags != 0,
+    };
+
+    out.* = @ptrCast(handle);
+    return ZQLITE_OK;
+}
+
+/// Size of the blob in bytes
+export fn zqlite_blob_bytes(blob: ?*zqlite_blob_t) c_int {
+    if (blob == null) return 0;
+
+    const handle: *BlobHandle = @ptrCast(@alignCast(blob.?));
+    return @intCast(handle.size);
+}
+
+/// Read n bytes starting at offset; reading past the end is an error
+export fn zqlite_blob_read(blob: ?*zqlite_blob_t, buffer: [*]u8, n: c_int, offset: c_int) c_int {
+    if (blob == null or n < 0 or offset < 0) return ZQLITE_MISUSE;
+
+    const handle: *BlobHandle = @ptrCast(@alignCast(blob.?));
+    const start: usize = @intCast(offset);
+    const len: usize = @intCast(n);
+    if (start + len > handle.size) return ZQLITE_ERROR;
+
+    const storage_table = handle.table() catch |err| return errorCode(err);
+    storage_table.readBlob(handle.row_id, handle.column_index, start, buffer[0..len]) catch |err| return errorCode(err);
+    return ZQLITE_OK;
+}
+
+/// Write n bytes starting at offset; blobs cannot grow, so use zeroblob to reserve space
+export fn zqlite_blob_write(blob: ?*zqlite_blob_t, buffer: [*]const u8, n: c_int, offset: c_int) c_int {
+    if (blob == null or n < 0 or offset < 0) return ZQLITE_MISUSE;
+
+    const handle: *BlobHandle = @ptrCast(@alignCast(blob.?));
+    if (!handle.writable) return ZQLITE_READONLY;
+    const start: usize = @intCast(offset);
+    const len: usize = @intCast(n);
+    if (start + len > handle.size) return ZQLITE_ERROR;
+
+    const storage_table = handle.table() catch |err| return errorCode(err);
+    handle.recordUndo(storage_table, start, len) catch |err| return errorCode(err);
+    storage_table.writeBlob(handle.row_id, handle.column_index, start, buffer[0..len]) catch |err| return errorCode(err);
+    return ZQLITE_OK;
+}
+
+/// Close the blob handle
+export fn zqlite_blob_close(blob: ?*zqlite_blob_t) c_int {
+    const handle: *BlobHandle = @ptrCast(@alignCast(blob orelse return ZQLITE_OK));
+    handle.deinit();
+    return ZQLITE_OK;
+}
+
+// ========== Schema introspection ==========
+
+const zqlite_schema_t = anyopaque;
+
+const SchemaColumn = struct {
+    name: [:0]const u8,
+    decl_type: [:0]const u8,
+    not_null: bool,
+    default_value: ?[:0]const u8,
+    pk: bool,
+};
+
+const SchemaIndex = struct {
+    name: [:0]const u8,
+    unique: bool,
+    columns: []const [:0]const u8,
+};
+
+const SchemaForeignKey = struct {
+    column: [:0]const u8,
+    reference_table: [:0]const u8,
+    reference_column: [:0]const u8,
+    on_delete: [:0]const u8,
+    on_update: [:0]const u8,
+};
+
+const SchemaTable = struct {
+    name: [:0]const u8,
+    columns: []SchemaColumn,
+    indexes: []SchemaIndex,
+    foreign_keys: []SchemaForeignKey,
+};
+
+/// Copy of the catalog taken by zqlite_schema; everything lives in the arena
+const Schema = struct {
+    arena: std.heap.ArenaAllocator,
+    tables: []SchemaTable,
+};
+
+fn lessThanName(_: void, a: []const u8, b: []const u8) bool {
+    return std.mem.lessThan(u8, a, b);
+}
+
+/// Render a column default as SQL, or null when it has no SQL spelling
+fn renderDefault(allocator: std.mem.Allocator, default_value: zqlite.storage.Column.DefaultValue) !?[:0]const u8 {
+    if (!zqlite.storage.defaultHasSql(default_value)) return null;
+    var text = std.array_list.Managed(u8).init(allocator);
+    try zqlite.storage.writeDefaultSql(text.writer(), default_value);
+    return try text.toOwnedSliceSentinel(0);
+}
+
+fn buildSchema(schema: *Schema, connection: *zqlite.db.Connection) !void {
+    const allocator = schema.arena.allocator();
+    const engine = connection.storage_engine;
+
+    // Hash map order is arbitrary; report tables and indexes by name
+    const names = try allocator.alloc([]const u8, engine.tables.count());
+    var table_iterator = engine.tables.keyIterator();
+    var n: usize = 0;
+    while (table_iterator.next()) |name| : (n += 1) names[n] = name.*;
+    std.mem.sort([]const u8, names, {}, lessThanName);
+
+    schema.tables = try allocator.alloc(SchemaTable, names.len);
+    for (names, schema.tables) |name, *info| {
+        const table = engine.getTable(name).?;
+
+        const columns = try allocator.alloc(SchemaColumn, table.schema.columns.len);
+        for (table.schema.columns, columns) |column, *column_info| {
+            column_info.* = .{
+                .name = try allocator.dupeZ(u8, column.name),
+                .decl_type = try allocator.dupeZ(u8, column.decl_type),
+                .not_null = !column.is_nullable,
+                .default_value = if (column.default_value) |default_value|
+                    try renderDefault(allocator, default_value)
+                else
+                    null,
+                .pk = column.is_primary_key,
+            };
+        }
+
+        var indexes = std.array_list.Managed(SchemaIndex).init(allocator);
+        var index_iterator = engine.indexes.valueIterator();
+        while (index_iterator.next()) |index| {
+            if (!std.mem.eql(u8, index.*.table_name, name)) continue;
+            const index_columns = try allocator.alloc([:0]const u8, index.*.column_names.len);
+            for (index.*.column_names, index_columns) |column, *copy| {
+                copy.* = try allocator.dupeZ(u8, column);
+            }
+            try indexes.append(.{
+                .name = try allocator.dupeZ(u8, index.*.name),
+                .unique = index.*.is_unique,
+                .columns = index_columns,
+            });
+        }
+        std.mem.sort(SchemaIndex, indexes.items, {}, struct {
+            fn lessThan(_: void, a: SchemaIndex, b: SchemaIndex) bool {
+                return std.mem.lessThan(u8, a.name, b.name);
+            }
+        }.lessThan);
+
+        const foreign_keys = try allocator.alloc(SchemaForeignKey, table.schema.foreign_keys.len);
+        for (table.schema.foreign_keys, foreign_keys) |foreign_key, *fk_info| {
+            fk_info.* = .{
+                .column = try allocator.dupeZ(u8, foreign_key.column),
+                .reference_table = try allocator.dupeZ(u8, foreign_key.reference_table),
+                .reference_column = try allocator.dupeZ(u8, foreign_key.reference_column),
+                .on_delete = foreign_key.on_delete.sqlName(),
+                .on_update = foreign_key.on_update.sqlName(),
+            };
+        }
+
+        info.* = .{
+            .name = try allocator.dupeZ(u8, name),
+            .columns = columns,
+            .indexes = try indexes.toOwnedSlice(),
+            .foreign_keys = foreign_keys,
+        };
+    }
+}
+
+/// Take a snapshot of the tables, columns, indexes and foreign keys
+export fn zqlite_schema(conn: ?*zqlite_connection_t) ?*zqlite_schema_t {
+    if (conn == null) return null;
+
+    const connection: *zqlite.db.Connection = @ptrCast(@alignCast(conn.?));
+    const schema = c_allocator.create(Schema) catch return null;
+    schema.* = .{
+        .arena = std.heap.ArenaAllocator.init(c_allocator),
+        .tables = &.{},
+    };
+
+    buildSchema(schema, connection) catch {
+        schema.arena.deinit();
+        c_allocator.destroy(schema);
+        return null;
+    };
+
+    return @ptrCast(schema);
+}
+
+/// Free a schema snapshot and every string it handed out
+export fn zqlite_schema_free(schema: ?*zqlite_schema_t) void {
+    if (schema) |s| {
+        const snapshot: *Schema = @ptrCast(@alignCast(s));
+        snapshot.arena.deinit();
+        c_allocator.destroy(snapshot);
+    }
+}
+
+fn schemaTable(schema: ?*zqlite_schema_t, table: c_int) ?*SchemaTable {
+    const snapshot: *Schema = @ptrCast(@alignCast(schema orelse return null));
+    if (table < 0 or table >= snapshot.tables.len) return null;
+    return &snapshot.tables[@intCast(table)];
+}
+
+/// Number of tables in the snapshot
+export fn zqlite_schema_table_count(schema: ?*zqlite_schema_t) c_int {
+    const snapshot: *Schema = @ptrCast(@alignCast(schema orelse return -1));
+    return @intCast(snapshot.tables.len);
+}
+
+/// Name of a table
+export fn zqlite_schema_table_name(schema: ?*zqlite_schema_t, table: c_int) ?[*:0]const u8 {
+    const info = schemaTable(schema, table) orelse return null;
+    return info.name.ptr;
+}
+
+/// Number of columns in a table
+export fn zqlite_schema_column_count(schema: ?*zqlite_schema_t, table: c_int) c_int {
+    const info = schemaTable(schema, table) orelse return -1;
+    return @intCast(info.columns.len);
+}
+
+/// Describe a column; default_value is set to null when the column has none
+export fn zqlite_schema_column(
+    schema: ?*zqlite_schema_t,
+    table: c_int,
+    column: c_int,
+    name: *?[*:0]const u8,
+    decl_type: *?[*:0]const u8,
+    not_null: *c_int,
+    default_value: *?[*:0]const u8,
+    pk: *c_int,
+) c_int {
+    const info = schemaTable(schema, table) orelse return ZQLITE_RANGE;
+    if (column < 0 or column >= info.columns.len) return ZQLITE_RANGE;
+
+    const column_info = info.columns[@intCast(column)];
+    name.* = column_info.name.ptr;
+    decl_type.* = column_info.decl_type.ptr;
+    not_null.* = @intFromBool(column_info.not_null);
+    default_value.* = if (column_info.default_value) |value| value.ptr else null;
+    pk.* = @intFromBool(column_info.pk);
+    return ZQLITE_OK;
+}
+
+/// Number of indexes on a table
+export fn zqlite_schema_index_count(schema: ?*zqlite_schema_t, table: c_int) c_int {
+    const info = schemaTable(schema, table) orelse return -1;
+    return @intCast(info.indexes.len);
+}
+
+/// Describe an index; read its columns with zqlite_schema_index_column
+export fn zqlite_schema_index(
+    schema: ?*zqlite_schema_t,
+    table: c_int,
+    index: c_int,
+    name: *?[*:0]const u8,
+    unique: *c_int,
+    column_count: *c_int,
+) c_int {
+    const info = schemaTable(schema, table) orelse return ZQLITE_RANGE;
+    if (index < 0 or index >= info.indexes.len) return ZQLITE_RANGE;
+
+    const index_info = info.indexes[@intCast(index)];
+    name.* = index_info.name.ptr;
+    unique.* = @intFromBool(index_info.unique);
+    column_count.* = @intCast(index_info.columns.len);
+    return ZQLITE_OK;
+}
+
+/// Name of an indexed column
+export fn zqlite_schema_index_column(schema: ?*zqlite_schema_t, table: c_int, index: c_int, column: c_int) ?[*:0]const u8 {
+    const info = schemaTable(schema, table) orelse return null;
+    if (index < 0 or index >= info.indexes.len) return null;
+
+    const index_info = info.indexes[@intCast(index)];
+    if (column < 0 or column >= index_info.columns.len) return null;
+    return index_info.columns[@intCast(column)].ptr;
+}
+
+/// Number of foreign keys declared by a table
+export fn zqlite_schema_foreign_key_count(schema: ?*zqlite_schema_t, table: c_int) c_int {
+    const info = schemaTable(schema, table) orelse return -1;
+    return @intCast(info.foreign_keys.len);
+}
+
+/// Describe a foreign key; actions are spelled as in SQL, e.g. "SET NULL"
+export fn zqlite_schema_foreign_key(
+    schema: ?*zqlite_schema_t,
+    table: c_int,
+    foreign_key: c_int,
+    column: *?[*:0]const u8,
+    reference_table: *?[*:0]const u8,
+    reference_column: *?[*:0]const u8,
+    on_delete: *?[*:0]const u8,
+    on_update: *?[*:0]const u8,
+) c_int {
+    const info = schemaTable(schema, table) orelse return ZQLITE_RANGE;
+    if (foreign_key < 0 or foreign_key >= info.foreign_keys.len) return ZQLITE_RANGE;
+
+    const fk_info = info.foreign_keys[@intCast(foreign_key)];
+    column.* = fk_info.column.ptr;
+    reference_table.* = fk_info.reference_table.ptr;
+    reference_column.* = fk_info.reference_column.ptr;
+    on_delete.* = fk_info.on_delete.ptr;
+    on_update.* = fk_info.on_update.ptr;
+    return ZQLITE_OK;
+}
+
+/// Abort the statement running on the connection; safe to call from any thread
+export fn zqlite_interrupt(conn: ?*zqlite_connection_t) void {
+    if (conn) |c| {
+        const connection: *zqlite.db.Connection = @ptrCast(@alignCast(c));
+        connection.interrupt();
+    }
+}
+
+/// Get the result code of the last statement executed on the connection
+export fn zqlite_errcode(conn: ?*zqlite_connection_t) c_int {
+    if (conn == null) return ZQLITE_MISUSE;
+
+    const connection: *zqlite.db.Connection = @ptrCast(@alignCast(conn.?));
+    const err = connection.last_error orelse return ZQLITE_OK;
+    return errorCode(err);
+}
+
 /// Get the last error message
 export fn zqlite_errmsg(conn: ?*zqlite_connection_t) [*:0]const u8 {
-    _ = conn; // TODO: Implement error message tracking
-    return "Unknown error";
+    if (conn == null) return "bad parameter or other API misuse";
+
+    const connection: *zqlite.db.Connection = @ptrCast(@alignCast(conn.?));
+    const err = connection.last_error orelse return "not an error";
+    return @errorName(err);
+}
+
+/// Get the row id of the most recent successful INSERT on the connection
+export fn zqlite_last_insert_rowid(conn: ?*zqlite_connection_t) i64 {
+    if (conn == null) return 0;
+
+    const connection: *zqlite.db.Connection = @ptrCast(@alignCast(conn.?));
+    return connection.last_insert_rowid;
+}
+
+/// Get the number of rows changed by the most recent INSERT, UPDATE or DELETE
+export fn zqlite_changes(conn: ?*zqlite_connection_t) c_int {
+    if (conn == null) return 0;
+
+    const connection: *zqlite.db.Connection = @ptrCast(@alignCast(conn.?));
+    return @intCast(connection.changes);
 }
 
 /// Get the version string
@@ -274,6 +1461,26 @@ export fn zqlite_version() [*:0]const u8 {
     return zqlite.version.VERSION_STRING.ptr;
 }
 
+/// Get the version as major * 1000000 + minor * 1000 + patch
+export fn zqlite_libversion_number() c_int {
+    return @intCast(zqlite.version.getVersionNumber());
+}
+
+// Capability bits returned by zqlite_capabilities
+pub const ZQLITE_CAPABILITY_ENCRYPTION = 0x01;
+pub const ZQLITE_CAPABILITY_JSON = 0x02;
+pub const ZQLITE_CAPABILITY_WAL = 0x04;
+
+/// Report which optional features this build of the library supports
+export fn zqlite_capabilities() c_uint {
+    // JSON/JSONB columns and the write-ahead log are always compiled in
+    var capabilities: c_uint = ZQLITE_CAPABILITY_JSON | ZQLITE_CAPABILITY_WAL;
+    if (zqlite.crypto_enabled) {
+        capabilities |= ZQLITE_CAPABILITY_ENCRYPTION;
+    }
+    return capabilities;
+}
+
 /// Cleanup global resources
 export fn zqlite_shutdown() void {
     _ = gpa.deinit();
@@ -286,7 +1493,7 @@ test "c api basic functionality" {
     // Test opening database
     const conn = zqlite_open(":memory:");
     try testing.expect(conn != null);
-    defer zqlite_close(conn);
+    defer _ = zqlite_close(conn);
 
     // Test executing statement
     const result = zqlite_execute(conn, "CREATE TABLE test (id INTEGER, name TEXT)");
@@ -295,6 +1502,9 @@ test "c api basic functionality" {
     // Test version
     const version = zqlite_version();
     try testing.expect(std.mem.len(version) > 0);
+    try testing.expectEqual(@as(c_int, @intCast(zqlite.version.getVersionNumber())), zqlite_libversion_number());
+    try testing.expect(zqlite_capabilities() & ZQLITE_CAPABILITY_WAL != 0);
+    try testing.expectEqual(zqlite.crypto_enabled, zqlite_capabilities() & ZQLITE_CAPABILITY_ENCRYPTION != 0);
 }
 
 test "c api prepared statements" {
@@ -303,7 +1513,7 @@ test "c api prepared statements" {
     // Test opening database
     const conn = zqlite_open(":memory:");
     try testing.expect(conn != null);
-    defer zqlite_close(conn);
+    defer _ = zqlite_close(conn);
 
     // Create table
     _ = zqlite_execute(conn, "CREATE TABLE test (id INTEGER, name TEXT)");
@@ -316,4 +1526,221 @@ test "c api prepared statements" {
     // Test binding parameters
     try testing.expectEqual(ZQLITE_OK, zqlite_bind_int(stmt, 0, 123));
     try testing.expectEqual(ZQLITE_OK, zqlite_bind_text(stmt, 1, "test"));
+    try testing.expectEqual(ZQLITE_DONE, zqlite_step(stmt));
+    try testing.expectEqual(@as(c_int, 1), zqlite_changes(conn));
+
+    // Rows come back one step at a time
+    const select = zqlite_prepare(conn, "SELECT id, name FROM test WHERE id = ?");
+    try testing.expect(select != null);
+    defer _ = zqlite_finalize(select);
+    try testing.expectEqual(ZQLITE_OK, zqlite_bind_int(select, 0, 123));
+
+    try testing.expectEqual(ZQLITE_ROW, zqlite_step(select));
+    try testing.expectEqual(@as(c_int, 2), zqlite_column_count(select));
+    try testing.expectEqual(@as(i64, 123), zqlite_column_int(select, 0));
+    try testing.expectEqualStrings("test", std.mem.span(zqlite_column_text(select, 1).?));
+    try testing.expectEqual(ZQLITE_DONE, zqlite_step(select));
+
+    try testing.expectEqual(ZQLITE_RANGE, zqlite_bind_int(select, 1, 0));
+}
+
+test "c api insert conflict resolution" {
+    const testing = std.testing;
+
+    const conn = zqlite_open(":memory:");
+    try testing.expect(conn != null);
+    defer _ = zqlite_close(conn);
+
+    try testing.expectEqual(ZQLITE_OK, zqlite_execute(conn, "CREATE TABLE state (id INTEGER PRIMARY KEY, generation INTEGER)"));
+    try testing.expectEqual(ZQLITE_OK, zqlite_execute(conn, "INSERT INTO state VALUES (1, 1)"));
+    try testing.expectEqual(ZQLITE_OK, zqlite_execute(conn, "INSERT OR IGNORE INTO state VALUES (1, 5)"));
+    try testing.expectEqual(@as(c_int, 0), zqlite_changes(conn));
+    try testing.expectEqual(ZQLITE_OK, zqlite_execute(conn, "INSERT OR REPLACE INTO state VALUES (1, 7)"));
+
+    const result = zqlite_query(conn, "SELECT generation FROM state");
+    try testing.expect(result != null);
+    defer zqlite_result_free(result);
+    try testing.expectEqual(@as(c_int, 1), zqlite_result_row_count(result));
+    try testing.expectEqual(@as(i64, 7), zqlite_result_get_int(result, 0, 0));
+}
+
+test "c api query results" {
+    const testing = std.testing;
+
+    const conn = zqlite_open(":memory:");
+    try testing.expect(conn != null);
+    defer _ = zqlite_close(conn);
+
+    try testing.expectEqual(ZQLITE_OK, zqlite_execute(conn, "CREATE TABLE test (id INTEGER PRIMARY KEY, name TEXT, score REAL)"));
+    try testing.expectEqual(ZQLITE_OK, zqlite_execute(conn, "INSERT INTO test VALUES (2, 'bob', 1.5)"));
+    try testing.expectEqual(ZQLITE_OK, zqlite_execute(conn, "INSERT INTO test VALUES (1, 'alice', 2.5)"));
+    try testing.expectEqual(@as(i64, 2), zqlite_last_insert_rowid(conn));
+    try testing.expectEqual(@as(c_int, 1), zqlite_changes(conn));
+
+    const result = zqlite_query(conn, "SELECT name, id FROM test WHERE score > 1.0 ORDER BY id");
+    try testing.expect(result != null);
+    defer zqlite_result_free(result);
+
+    try testing.expectEqual(@as(c_int, 2), zqlite_result_row_count(result));
+    try testing.expectEqual(@as(c_int, 2), zqlite_result_column_count(result));
+    try testing.expectEqualStrings("name", std.mem.span(zqlite_result_column_name(result, 0).?));
+    try testing.expectEqual(ZQLITE_TEXT, zqlite_result_column_type(result, 0, 0));
+    try testing.expectEqualStrings("alice", std.mem.span(zqlite_result_get_text(result, 0, 0).?));
+    try testing.expectEqual(@as(i64, 2), zqlite_result_get_int(result, 1, 1));
+
+    // Bound parameters reach the WHERE clause
+    const stmt = zqlite_prepare(conn, "SELECT score FROM test WHERE name = ?");
+    try testing.expect(stmt != null);
+    defer _ = zqlite_finalize(stmt);
+    try testing.expectEqual(ZQLITE_OK, zqlite_bind_text(stmt, 0, "bob"));
+
+    const prepared = zqlite_query_prepared(conn, stmt);
+    try testing.expect(prepared != null);
+    defer zqlite_result_free(prepared);
+    try testing.expectEqual(@as(c_int, 1), zqlite_result_row_count(prepared));
+    try testing.expectEqual(@as(f64, 1.5), zqlite_result_get_real(prepared, 0, 0));
+
+    // A duplicate primary key is a constraint violation
+    try testing.expectEqual(ZQLITE_CONSTRAINT, zqlite_execute(conn, "INSERT INTO test VALUES (1, 'carol', 0.5)"));
+    try testing.expectEqual(ZQLITE_CONSTRAINT, zqlite_errcode(conn));
+}
+
+/// Changes seen by the update hook, as (op, rowid) pairs
+const SeenChanges = struct {
+    ops: [8]c_int = undefined,
+    rowids: [8]i64 = undefined,
+    len: usize = 0,
+};
+
+fn recordUpdate(user_data: ?*anyopaque, op: c_int, db_name: [*:0]const u8, table: [*:0]const u8, rowid: i64) callconv(.c) void {
+    _ = db_name;
+    const seen: *SeenChanges = @ptrCast(@alignCast(user_data.?));
+    if (!std.mem.eql(u8, std.mem.span(table), "test")) return;
+    seen.ops[seen.len] = op;
+    seen.rowids[seen.len] = rowid;
+    seen.len += 1;
+}
+
+fn vetoCommit(user_data: ?*anyopaque) callconv(.c) c_int {
+    _ = user_data;
+    return 1;
+}
+
+fn countRollback(user_data: ?*anyopaque) callconv(.c) void {
+    const count: *u32 = @ptrCast(@alignCast(user_data.?));
+    count.* += 1;
+}
+
+fn countCommit(user_data: ?*anyopaque) callconv(.c) void {
+    const count: *u32 = @ptrCast(@alignCast(user_data.?));
+    count.* += 1;
+}
+
+test "c api change hooks" {
+    const testing = std.testing;
+
+    const conn = zqlite_open(":memory:");
+    try testing.expect(conn != null);
+    defer _ = zqlite_close(conn);
+
+    _ = zqlite_execute(conn, "CREATE TABLE test (id INTEGER, name TEXT)");
+
+    var seen = SeenChanges{};
+    try testing.expect(zqlite_update_hook(conn, recordUpdate, &seen) == null);
+    try testing.expectEqual(ZQLITE_OK, zqlite_execute(conn, "INSERT INTO test VALUES (1, 'a')"));
+    try testing.expectEqual(ZQLITE_OK, zqlite_execute(conn, "INSERT INTO test VALUES (2, 'b')"));
+
+    // Prepared statements fire the hook too, once per changed row
+    const update = zqlite_prepare(conn, "UPDATE test SET name = 'c' WHERE id > ?");
+    try testing.expect(update != null);
+    defer _ = zqlite_finalize(update);
+    try testing.expectEqual(ZQLITE_OK, zqlite_bind_int(update, 0, 0));
+    try testing.expectEqual(ZQLITE_DONE, zqlite_step(update));
+
+    const delete = zqlite_prepare(conn, "DELETE FROM test WHERE id = ?");
+    try testing.expect(delete != null);
+    defer _ = zqlite_finalize(delete);
+    try testing.expectEqual(ZQLITE_OK, zqlite_bind_int(delete, 0, 2));
+    const deleted = zqlite_query_prepared(conn, delete);
+    try testing.expect(deleted != null);
+    zqlite_result_free(deleted);
+
+    const first = zqlite_last_insert_rowid(conn) - 1;
+    try testing.expectEqual(@as(usize, 5), seen.len);
+    try testing.expectEqualSlices(c_int, &.{ ZQLITE_INSERT, ZQLITE_INSERT, ZQLITE_UPDATE, ZQLITE_UPDATE, ZQLITE_DELETE }, seen.ops[0..seen.len]);
+    try testing.expectEqualSlices(i64, &.{ first, first + 1, first, first + 1, first + 1 }, seen.rowids[0..seen.len]);
+
+    // Every autocommit write commits on its own; the after-commit hook
+    // fires once the commit went through
+    var commits: u32 = 0;
+    _ = zqlite_after_commit_hook(conn, countCommit, &commits);
+    try testing.expectEqual(ZQLITE_OK, zqlite_execute(conn, "INSERT INTO test VALUES (3, 'd')"));
+    try testing.expectEqual(@as(u32, 1), commits);
+
+    // A vetoed commit undoes the write and fires the rollback hook, but not
+    // the after-commit hook
+    var rollbacks: u32 = 0;
+    _ = zqlite_rollback_hook(conn, countRollback, &rollbacks);
+    _ = zqlite_commit_hook(conn, vetoCommit, null);
+    try testing.expectEqual(ZQLITE_CONSTRAINT, zqlite_execute(conn, "INSERT INTO test VALUES (4, 'e')"));
+    try testing.expectEqual(@as(u32, 1), rollbacks);
+    try testing.expectEqual(@as(u32, 1), commits);
+    try testing.expectEqual(@as(c_int, 1), zqlite_get_autocommit(conn));
+
+    try testing.expect(zqlite_update_hook(conn, null, null) == @as(?*anyopaque, &seen));
+}
+
+test "c api transaction state" {
+    const testing = std.testing;
+
+    const conn = zqlite_open(":memory:");
+    try testing.expect(conn != null);
+    defer _ = zqlite_close(conn);
+
+    try testing.expectEqual(@as(c_int, 1), zqlite_get_autocommit(conn));
+    try testing.expectEqual(ZQLITE_OK, zqlite_execute(conn, "BEGIN"));
+    try testing.expectEqual(@as(c_int, 0), zqlite_get_autocommit(conn));
+    try testing.expectEqual(ZQLITE_OK, zqlite_execute(conn, "COMMIT"));
+    try testing.expectEqual(@as(c_int, 1), zqlite_get_autocommit(conn));
+
+    try testing.expectEqual(ZQLITE_OK, zqlite_begin_transaction(conn));
+    try testing.expect(zqlite_begin_transaction(conn) != ZQLITE_OK);
+    try testing.expectEqual(ZQLITE_OK, zqlite_rollback_transaction(conn));
+    try testing.expectEqual(@as(c_int, 1), zqlite_get_autocommit(conn));
+    try testing.expect(zqlite_commit_transaction(conn) != ZQLITE_OK);
+}
+
+fn interruptAfterDelay(conn: ?*zqlite_connection_t) void {
+    std.Thread.sleep(20 * std.time.ns_per_ms);
+    zqlite_interrupt(conn);
+}
+
+test "c api interrupt" {
+    const testing = std.testing;
+
+    const conn = zqlite_open(":memory:");
+    try testing.expect(conn != null);
+    defer _ = zqlite_close(conn);
+
+    _ = zqlite_execute(conn, "CREATE TABLE a (x INTEGER)");
+    _ = zqlite_execute(conn, "CREATE TABLE b (y INTEGER)");
+
+    // An interrupt raised while nothing is running is a no-op
+    zqlite_interrupt(conn);
+    try testing.expectEqual(ZQLITE_OK, zqlite_execute(conn, "INSERT INTO a VALUES (0)"));
+    try testing.expectEqual(ZQLITE_OK, zqlite_execute(conn, "INSERT INTO b VALUES (0)"));
+
+    var sql_buf: [64]u8 = undefined;
+    for (0..1000) |i| {
+        try testing.expectEqual(ZQLITE_OK, zqlite_execute(conn, try std.fmt.bufPrintZ(&sql_buf, "INSERT INTO a VALUES ({d})", .{i})));
+        try testing.expectEqual(ZQLITE_OK, zqlite_execute(conn, try std.fmt.bufPrintZ(&sql_buf, "INSERT INTO b VALUES ({d})", .{i + 1})));
+    }
+
+    // A million-row nested loop join is still running when the interrupt lands
+    const interrupter = try std.Thread.spawn(.{}, interruptAfterDelay, .{conn});
+    const status = zqlite_execute(conn, "SELECT * FROM a INNER JOIN b ON x = y");
+    interrupter.join();
+    try testing.expectEqual(ZQLITE_INTERRUPT, status);
+    try testing.expectEqual(ZQLITE_INTERRUPT, zqlite_errcode(conn));
+    try testing.expectEqual(ZQLITE_OK, zqlite_execute(conn, "SELECT * FROM a"));
 }
@@ -141,6 +141,7 @@ pub const CreateTableStatement = struct {
         allocator.free(self.table_name);
         for (self.columns) |column| {
             allocator.free(column.name);
+            allocator.free(column.type_name);
             for (column.constraints) |constraint| {
                 constraint.deinit(allocator);
             }
@@ -238,6 +239,7 @@ pub const AggregateFunctionType = enum {
 pub const ColumnDefinition = struct {
     name: []const u8,
     data_type: DataType,
+    type_name: []const u8, // Type as written, e.g. VARCHAR(255)
     constraints: []ColumnConstraint,
 };
 
@@ -346,11 +348,13 @@ pub const WhereClause = struct {
 pub const Condition = union(enum) {
     Comparison: ComparisonCondition,
     Logical: LogicalCondition,
+    FunctionCall: FunctionCall, // Bare call, true when its result is non-zero
 
     pub fn deinit(self: *Condition, allocator: std.mem.Allocator) void {
         switch (self.*) {
             .Comparison => |*comp| comp.deinit(allocator),
             .Logical => |*logical| logical.deinit(allocator),
+            .FunctionCall => |func| func.deinit(allocator),
         }
     }
 };
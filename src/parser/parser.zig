@@ -656,6 +656,28 @@ pub const Parser = struct {
             };
         }
 
+        // Function calls like cidr_contains(source_cidr, ?), named after their text
+        if (try self.isFunctionCall()) {
+            const function_call = try self.parseFunctionCall();
+            errdefer function_call.deinit(self.allocator);
+            const name = try self.functionCallLabel(function_call);
+            errdefer self.allocator.free(name);
+
+            var alias: ?[]const u8 = null;
+            if (std.meta.activeTag(self.current_token) == .As) {
+                try self.advance(); // consume AS
+                alias = try self.expectIdentifierOrKeyword();
+            } else if (std.meta.activeTag(self.current_token) == .Identifier) {
+                alias = try self.expectIdentifier();
+            }
+
+            return ast.Column{
+                .name = name,
+                .expression = ast.ColumnExpression{ .FunctionCall = function_call },
+                .alias = alias,
+            };
+        }
+
         // Regular column parsing
         const name = try self.expectIdentifier();
         var alias: ?[]const u8 = null;
@@ -678,7 +700,9 @@ pub const Parser = struct {
     /// Parse a column definition in CREATE TABLE
     fn parseColumnDefinition(self: *Self) !ast.ColumnDefinition {
         const name = try self.expectIdentifier();
-        const data_type = try self.parseDataType();
+        errdefer self.allocator.free(name);
+        const type_name = try self.parseTypeName();
+        errdefer self.allocator.free(type_name);
 
         var constraints = std.array_list.Managed(ast.ColumnConstraint).init(self.allocator);
         defer constraints.deinit();
@@ -691,20 +715,52 @@ pub const Parser = struct {
 
         return ast.ColumnDefinition{
             .name = name,
-            .data_type = data_type,
+            .data_type = dataTypeFromName(type_name),
+            .type_name = type_name,
             .constraints = try constraints.toOwnedSlice(),
         };
     }
 
-    /// Parse data type
-    fn parseDataType(self: *Self) !ast.DataType {
-        const type_name = try self.expectIdentifier();
-        defer self.allocator.free(type_name);
+    /// Parse a column type name as written, with any size, e.g. `VARCHAR(255)`
+    fn parseTypeName(self: *Self) ![]const u8 {
+        const name = try self.expectIdentifier();
+        if (std.meta.activeTag(self.current_token) != .LeftParen) return name;
+        defer self.allocator.free(name);
+
+        var text = std.array_list.Managed(u8).init(self.allocator);
+        errdefer text.deinit();
+        try text.appendSlice(name);
+        try text.append('(');
+        try self.advance();
+        while (true) {
+            const size = switch (self.current_token) {
+                .Integer => |size| size,
+                else => return error.UnexpectedToken,
+            };
+            const digits = try std.fmt.allocPrint(self.allocator, "{d}", .{size});
+            defer self.allocator.free(digits);
+            try text.appendSlice(digits);
+            try self.advance();
+
+            if (std.meta.activeTag(self.current_token) != .Comma) break;
+            try text.appendSlice(", ");
+            try self.advance();
+        }
+        try self.expect(.RightParen);
+        try text.append(')');
+
+        return text.toOwnedSlice();
+    }
+
+    /// Map a declared type name to a data type; unknown names are TEXT
+    fn dataTypeFromName(type_name: []const u8) ast.DataType {
+        // The size, if any, doesn't change the type
+        const base = type_name[0 .. std.mem.indexOfScalar(u8, type_name, '(') orelse type_name.len];
 
         // Convert to uppercase for case-insensitive comparison
         var upper_type: [64]u8 = undefined;
-        const len = @min(type_name.len, upper_type.len);
-        for (type_name[0..len], 0..) |c, i| {
+        const len = @min(base.len, upper_type.len);
+        for (base[0..len], 0..) |c, i| {
             upper_type[i] = std.ascii.toUpper(c);
         }
         const type_str = upper_type[0..len];
@@ -730,24 +786,8 @@ pub const Parser = struct {
         } else if (std.mem.eql(u8, type_str, "DECIMAL") or std.mem.eql(u8, type_str, "NUMERIC")) {
             return .Decimal;
         } else if (std.mem.eql(u8, type_str, "VARCHAR")) {
-            // Skip optional length specification
-            if (std.meta.activeTag(self.current_token) == .LeftParen) {
-                try self.advance();
-                if (self.current_token == .Integer) {
-                    try self.advance();
-                }
-                try self.expect(.RightParen);
-            }
             return .Varchar;
         } else if (std.mem.eql(u8, type_str, "CHAR")) {
-            // Skip optional length specification
-            if (std.meta.activeTag(self.current_token) == .LeftParen) {
-                try self.advance();
-                if (self.current_token == .Integer) {
-                    try self.advance();
-                }
-                try self.expect(.RightParen);
-            }
             return .Char;
         } else if (std.mem.eql(u8, type_str, "FLOAT")) {
             return .Float;
@@ -1017,6 +1057,41 @@ pub const Parser = struct {
         };
     }
     
+    /// Whether the current token starts a function call, i.e. is a name followed by '('
+    fn isFunctionCall(self: *Self) !bool {
+        if (self.current_token != .Identifier) return false;
+        const next_token = (try self.peekNextToken()) orelse return false;
+        defer next_token.deinit(self.allocator);
+        return std.meta.activeTag(next_token) == .LeftParen;
+    }
+
+    /// Column name for a selected function call, e.g. "add_one(n)"
+    fn functionCallLabel(self: *Self, function_call: ast.FunctionCall) ![]const u8 {
+        var label = std.array_list.Managed(u8).init(self.allocator);
+        errdefer label.deinit();
+        const writer = label.writer();
+
+        try writer.print("{s}(", .{function_call.name});
+        for (function_call.arguments, 0..) |argument, i| {
+            if (i > 0) try writer.writeAll(", ");
+            switch (argument) {
+                .String => |text| try writer.print("'{s}'", .{text}),
+                .Column => |column| try writer.writeAll(column),
+                .Parameter => try writer.writeAll("?"),
+                .Literal => |value| switch (value) {
+                    .Integer => |int| try writer.print("{d}", .{int}),
+                    .Real => |real| try writer.print("{d}", .{real}),
+                    .Text => |text| try writer.print("'{s}'", .{text}),
+                    .Null => try writer.writeAll("NULL"),
+                    .Parameter => try writer.writeAll("?"),
+                    .Blob, .FunctionCall => try writer.writeAll("..."),
+                },
+            }
+        }
+        try writer.writeAll(")");
+        return label.toOwnedSlice();
+    }
+
     /// Parse function argument
     fn parseFunctionArgument(self: *Self) !ast.FunctionArgument {
         return switch (self.current_token) {
@@ -1025,6 +1100,21 @@ pub const Parser = struct {
                 try self.advance();
                 return ast.FunctionArgument{ .String = owned_string };
             },
+            .Identifier => |id| {
+                // Nested calls such as datetime('now') are still read as values
+                if (try self.isFunctionCall()) {
+                    return ast.FunctionArgument{ .Literal = try self.parseValue() };
+                }
+                const column = try self.allocator.dupe(u8, id);
+                try self.advance();
+                return ast.FunctionArgument{ .Column = column };
+            },
+            .QuestionMark => {
+                const param_index = self.parameter_index;
+                self.parameter_index += 1;
+                try self.advance();
+                return ast.FunctionArgument{ .Parameter = param_index };
+            },
             else => {
                 const value = try self.parseValue();
                 return ast.FunctionArgument{ .Literal = value };
@@ -1040,18 +1130,18 @@ pub const Parser = struct {
 
     /// Parse condition in WHERE clause
     fn parseCondition(self: *Self) !ast.Condition {
-        var left = ast.Condition{ .Comparison = try self.parseComparison() };
+        var left = try self.parsePredicate();
 
         while (std.meta.activeTag(self.current_token) == .And or std.meta.activeTag(self.current_token) == .Or) {
             const op: ast.LogicalOperator = if (std.meta.activeTag(self.current_token) == .And) .And else .Or;
             try self.advance();
 
-            const right = try self.parseComparison();
+            const right = try self.parsePredicate();
             const left_ptr = try self.allocator.create(ast.Condition);
             left_ptr.* = left;
 
             const right_ptr = try self.allocator.create(ast.Condition);
-            right_ptr.* = ast.Condition{ .Comparison = right };
+            right_ptr.* = right;
 
             left = ast.Condition{
                 .Logical = ast.LogicalCondition{
@@ -1065,6 +1155,32 @@ pub const Parser = struct {
         return left;
     }
 
+    /// Parse a comparison, or a function call used as a condition
+    ///
+    /// A bare call like `cidr_contains(source_cidr, ?)` holds when its result
+    /// is non-zero; a call followed by an operator is compared like a column.
+    fn parsePredicate(self: *Self) !ast.Condition {
+        if (!try self.isFunctionCall()) {
+            return ast.Condition{ .Comparison = try self.parseComparison() };
+        }
+
+        const function_call = try self.parseFunctionCall();
+        errdefer function_call.deinit(self.allocator);
+        const op = self.parseComparisonOperator() catch |err| switch (err) {
+            error.ExpectedOperator => return ast.Condition{ .FunctionCall = function_call },
+            else => return err,
+        };
+        const right = try self.parseExpression();
+
+        return ast.Condition{
+            .Comparison = ast.ComparisonCondition{
+                .left = ast.Expression{ .Literal = ast.Value{ .FunctionCall = function_call } },
+                .operator = op,
+                .right = right,
+            },
+        };
+    }
+
     /// Parse comparison condition
     fn parseComparison(self: *Self) !ast.ComparisonCondition {
         const left = try self.parseExpression();
@@ -1265,23 +1381,13 @@ pub const Parser = struct {
                 try self.expect(.Key); // expect KEY
                 try self.expect(.LeftParen);
                 const column = try self.expectIdentifier();
+                errdefer self.allocator.free(column);
                 try self.expect(.RightParen);
                 
-                try self.expect(.References);
-                const ref_table_name = try self.expectIdentifier();
-                try self.expect(.LeftParen);
-                const ref_column_name = try self.expectIdentifier();
-                try self.expect(.RightParen);
-                
-                return ast.TableConstraint{ 
-                    .ForeignKey = ast.ForeignKeyConstraint{
-                        .column = column,
-                        .reference_table = ref_table_name,
-                        .reference_column = ref_column_name,
-                        .on_delete = null,
-                        .on_update = null,
-                    }
-                };
+                // Same REFERENCES clause, including ON DELETE/UPDATE, as the column form
+                var fk = try self.parseForeignKeyConstraint();
+                fk.column = column;
+                return ast.TableConstraint{ .ForeignKey = fk };
             },
             .Unique => {
                 try self.advance(); // consume UNIQUE
@@ -1455,3 +1561,19 @@ test "parse strftime function in default" {
 
     try std.testing.expectEqual(std.meta.Tag(ast.Statement).CreateTable, std.meta.activeTag(result.statement));
 }
+
+test "parse function call predicate in where" {
+    const allocator = std.testing.allocator;
+    const sql = "SELECT id FROM acl_rules WHERE cidr_contains(source_cidr, ?) AND action = 'allow'";
+
+    var result = try parse(allocator, sql);
+    defer result.deinit();
+
+    const where = result.statement.Select.where_clause.?;
+    try std.testing.expectEqual(std.meta.Tag(ast.Condition).Logical, std.meta.activeTag(where.condition));
+    const predicate = where.condition.Logical.left.*;
+    try std.testing.expectEqual(std.meta.Tag(ast.Condition).FunctionCall, std.meta.activeTag(predicate));
+    try std.testing.expectEqualStrings("cidr_contains", predicate.FunctionCall.name);
+    try std.testing.expectEqualStrings("source_cidr", predicate.FunctionCall.arguments[0].Column);
+    try std.testing.expectEqual(@as(u32, 0), predicate.FunctionCall.arguments[1].Parameter);
+}
@@ -246,26 +246,25 @@ pub const Tokenizer = struct {
         return Token{ .Identifier = owned_identifier };
     }
 
-    /// Read a string literal
+    /// Read a string literal, where a doubled quote stands for one quote
     fn readString(self: *Self, allocator: std.mem.Allocator) !Token {
         const quote_char = self.current_char.?;
         self.advance(); // Skip opening quote
 
-        const start = self.position;
+        var content = std.array_list.Managed(u8).init(allocator);
+        errdefer content.deinit();
 
-        while (self.current_char != null and self.current_char.? != quote_char) {
+        while (true) {
+            const char = self.current_char orelse return error.UnterminatedString;
             self.advance();
+            if (char == quote_char) {
+                if (self.current_char != quote_char) break;
+                self.advance(); // Skip the second quote of the pair
+            }
+            try content.append(char);
         }
 
-        if (self.current_char == null) {
-            return error.UnterminatedString;
-        }
-
-        const string_content = self.input[start..self.position];
-        self.advance(); // Skip closing quote
-
-        const owned_string = try allocator.dupe(u8, string_content);
-        return Token{ .String = owned_string };
+        return Token{ .String = try content.toOwnedSlice() };
     }
 };
 
@@ -535,3 +534,16 @@ test "tokenizer numbers" {
     defer token2.deinit(allocator);
     try std.testing.expectEqual(@as(f64, 3.14), token2.Real);
 }
+
+test "tokenizer string escapes" {
+    const allocator = std.testing.allocator;
+    var tokenizer = Tokenizer.init("'O''Brien' ''");
+
+    const token1 = try tokenizer.nextToken(allocator);
+    defer token1.deinit(allocator);
+    try std.testing.expectEqualStrings("O'Brien", token1.String);
+
+    const token2 = try tokenizer.nextToken(allocator);
+    defer token2.deinit(allocator);
+    try std.testing.expectEqualStrings("", token2.String);
+}
@@ -47,7 +47,10 @@ pub const cli = @import("shell/cli.zig");
 pub const logging = @import("logging/logger.zig");
 
 // Advanced cryptographic features (optional - v1.2.2)
-pub const crypto = if (@import("builtin").is_test or @hasDecl(@import("root"), "zqlite_enable_crypto"))
+/// Whether the crypto module is compiled in; the root enables it with `zqlite_enable_crypto`
+pub const crypto_enabled = @import("builtin").is_test or @hasDecl(@import("root"), "zqlite_enable_crypto");
+
+pub const crypto = if (crypto_enabled)
     struct {
         pub const CryptoEngine = @import("crypto/secure_storage.zig").CryptoEngine;
         pub const CryptoTransactionLog = @import("crypto/secure_storage.zig").CryptoTransactionLog;